@@ -2,7 +2,13 @@ use chrono::{DateTime, Utc};
 use ipnetwork::{Ipv4Network, Ipv6Network};
 use serde::{Deserialize, Serialize};
 use serde_json::Value as JsonValue;
-use std::net::{Ipv4Addr, Ipv6Addr};
+use std::net::{Ipv4Addr, Ipv6Addr, SocketAddr};
+
+/// Node annotation holding the backend type a node has set itself up for.
+pub const BACKEND_TYPE_ANNOTATION: &str = "rk8s.io/backend-type";
+/// Node annotation holding the backend data of a node as JSON, e.g. its VTEP
+/// MAC or WireGuard public key. It ends up in the `BackendData` of its lease.
+pub const BACKEND_DATA_ANNOTATION: &str = "rk8s.io/backend-data";
 
 #[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq)]
 #[repr(u8)]
@@ -108,3 +114,32 @@ impl std::fmt::Display for LeaseAttrs {
         )
     }
 }
+
+/// Tunnel endpoint of a remote node in an overlay network, sent to the other
+/// nodes together with the routes to its subnet.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub enum OverlayPeer {
+    /// VXLAN peer: the tunnel address of `subnet` resolves to `vtep_mac`, and
+    /// frames for `vtep_mac` are sent to `public_ip`.
+    Vxlan {
+        subnet: Ipv4Network,
+        vtep_mac: String,
+        public_ip: Ipv4Addr,
+    },
+    /// WireGuard peer: packets for `subnet` are encrypted for `public_key`
+    /// and sent to `endpoint`.
+    Wireguard {
+        subnet: Ipv4Network,
+        public_key: String,
+        endpoint: SocketAddr,
+    },
+}
+
+impl OverlayPeer {
+    /// Subnet of the node behind this peer.
+    pub fn subnet(&self) -> Ipv4Network {
+        match self {
+            OverlayPeer::Vxlan { subnet, .. } | OverlayPeer::Wireguard { subnet, .. } => *subnet,
+        }
+    }
+}
@@ -6,7 +6,7 @@ use std::fmt::{Display, Formatter};
 use std::{
     collections::HashMap,
     fmt,
-    net::{Ipv4Addr, Ipv6Addr},
+    net::{IpAddr, Ipv4Addr, Ipv6Addr},
     time::Duration,
 };
 use uuid::Uuid;
@@ -102,6 +102,7 @@ pub enum ResourceKind {
     Deployment,
     ReplicaSet,
     Endpoint,
+    NetworkPolicy,
     #[default]
     Unknown,
 }
@@ -114,6 +115,7 @@ impl fmt::Display for ResourceKind {
             ResourceKind::Deployment => "Deployment",
             ResourceKind::ReplicaSet => "ReplicaSet",
             ResourceKind::Endpoint => "Endpoint",
+            ResourceKind::NetworkPolicy => "NetworkPolicy",
             ResourceKind::Unknown => "Unknown",
         };
         write!(f, "{}", kind)
@@ -127,6 +129,7 @@ impl From<&str> for ResourceKind {
             "Deployment" => ResourceKind::Deployment,
             "ReplicaSet" => ResourceKind::ReplicaSet,
             "Endpoint" => ResourceKind::Endpoint,
+            "NetworkPolicy" => ResourceKind::NetworkPolicy,
             _ => ResourceKind::Unknown, // Default to Unknown for unknown kinds
         }
     }
@@ -551,6 +554,9 @@ pub struct PodTask {
 pub struct PodStatus {
     #[serde(rename = "podIP")]
     pub pod_ip: Option<String>,
+    /// IPs of a dual-stack pod, one per IP family. The first one is `podIP`
+    #[serde(rename = "podIPs", default, skip_serializing_if = "Vec::is_empty")]
+    pub pod_ips: Vec<String>,
 
     #[serde(rename = "containerStatuses", default)]
     pub container_statuses: Vec<ContainerStatus>,
@@ -570,6 +576,21 @@ pub struct PodStatus {
     pub start_time: Option<DateTime<Utc>>,
 }
 
+impl PodStatus {
+    /// IPs of the pod without prefix length, `podIPs` or else `podIP`
+    pub fn ips(&self) -> Vec<&str> {
+        let ips: Vec<&str> = if self.pod_ips.is_empty() {
+            self.pod_ip.as_deref().into_iter().collect()
+        } else {
+            self.pod_ips.iter().map(String::as_str).collect()
+        };
+        ips.into_iter()
+            .filter_map(|ip| ip.split('/').next())
+            .filter(|ip| !ip.is_empty())
+            .collect()
+    }
+}
+
 #[derive(Debug, Serialize, Deserialize, Clone, Copy, Default, PartialEq, Eq)]
 pub enum PodPhase {
     #[default]
@@ -772,6 +793,12 @@ pub enum RksMessage {
     GetService(String),
     ListService,
 
+    // NetworkPolicy operations
+    CreateNetworkPolicy(Box<NetworkPolicy>),
+    DeleteNetworkPolicy(String),
+    GetNetworkPolicy(String),
+    ListNetworkPolicy,
+
     GetNodeCount,
     RegisterNode(Box<Node>),
     UserRequest(String),
@@ -780,7 +807,8 @@ pub enum RksMessage {
         status: NodeStatus,
     },
     SetNetwork(Box<NodeNetworkConfig>),
-    UpdateRoutes(String, Vec<Route>),
+    /// Routes of a node, plus the overlay peers they go through
+    UpdateRoutes(String, Vec<Route>, Vec<lease::OverlayPeer>),
     SetDns(String, u16),
 
     CertificateSign {
@@ -791,6 +819,8 @@ pub enum RksMessage {
     SetNftablesRules(String),
     /// Update nftables rules payload (serialized nft commands) - Incremental
     UpdateNftablesRules(String),
+    /// LoadBalancer VIPs the node announces, replacing the previous ones
+    SetLoadBalancerVips(Vec<String>),
 
     UpdatePodStatus {
         pod_name: String,
@@ -826,8 +856,13 @@ pub enum RksMessage {
     // Service responses
     GetServiceRes(Box<ServiceTask>),
     ListServiceRes(Vec<ServiceTask>),
+    // NetworkPolicy responses
+    GetNetworkPolicyRes(Box<NetworkPolicy>),
+    ListNetworkPolicyRes(Vec<NetworkPolicy>),
     // (Podname, Podip)
     SetPodip((String, String)),
+    // (Podname, Podips), one IP per family of a dual-stack pod
+    SetPodIps((String, Vec<String>)),
     Certificate(IssueCertificateResponse),
 
     // Log responses
@@ -890,6 +925,14 @@ impl std::fmt::Debug for RksMessage {
             }
             Self::GetService(name) => write!(f, "RksMessage::GetService {{ name: {} }}", name),
             Self::ListService => f.write_str("RksMessage::ListService"),
+            Self::CreateNetworkPolicy(_) => f.write_str("RksMessage::CreateNetworkPolicy { .. }"),
+            Self::DeleteNetworkPolicy(name) => {
+                write!(f, "RksMessage::DeleteNetworkPolicy {{ name: {} }}", name)
+            }
+            Self::GetNetworkPolicy(name) => {
+                write!(f, "RksMessage::GetNetworkPolicy {{ name: {} }}", name)
+            }
+            Self::ListNetworkPolicy => f.write_str("RksMessage::ListNetworkPolicy"),
             Self::GetNodeCount => f.write_str("RksMessage::GetNodeCount"),
             Self::RegisterNode(_) => f.write_str("RksMessage::RegisterNode { .. }"),
             Self::UserRequest(_) => f.write_str("RksMessage::UserRequest { .. }"),
@@ -901,12 +944,13 @@ impl std::fmt::Debug for RksMessage {
                 )
             }
             Self::SetNetwork(_) => f.write_str("RksMessage::SetNetwork { .. }"),
-            Self::UpdateRoutes(node_name, routes) => {
+            Self::UpdateRoutes(node_name, routes, peers) => {
                 write!(
                     f,
-                    "RksMessage::UpdateRoutes {{ node_name: {}, routes_count: {} }}",
+                    "RksMessage::UpdateRoutes {{ node_name: {}, routes_count: {}, peers_count: {} }}",
                     node_name,
-                    routes.len()
+                    routes.len(),
+                    peers.len()
                 )
             }
             Self::CertificateSign { .. } => f.write_str("RksMessage::CertificateSign { .. }"),
@@ -925,6 +969,9 @@ impl std::fmt::Debug for RksMessage {
             Self::UpdateNftablesRules(rules) => {
                 write!(f, "RksMessage::UpdateNftablesRules (len={})", rules.len())
             }
+            Self::SetLoadBalancerVips(vips) => {
+                write!(f, "RksMessage::SetLoadBalancerVips({vips:?})")
+            }
             // response
             Self::Ack => f.write_str("RksMessage::Ack"),
             Self::Error(err_msg) => write!(f, "RksMessage::Error({})", err_msg),
@@ -965,6 +1012,14 @@ impl std::fmt::Debug for RksMessage {
                     services.len()
                 )
             }
+            Self::GetNetworkPolicyRes(_) => f.write_str("RksMessage::GetNetworkPolicyRes { .. }"),
+            Self::ListNetworkPolicyRes(policies) => {
+                write!(
+                    f,
+                    "RksMessage::ListNetworkPolicyRes {{ count: {} }}",
+                    policies.len()
+                )
+            }
             Self::SetPodip((pod_name, pod_ip)) => {
                 write!(
                     f,
@@ -972,6 +1027,13 @@ impl std::fmt::Debug for RksMessage {
                     pod_name, pod_ip
                 )
             }
+            Self::SetPodIps((pod_name, pod_ips)) => {
+                write!(
+                    f,
+                    "RksMessage::SetPodIps {{ pod_name: {}, pod_ips: {:?} }}",
+                    pod_name, pod_ips
+                )
+            }
             Self::SetDns(ip, dns_port) => write!(
                 f,
                 "RksMessage::SetDns {{ ip: {}, dns_port: {} }}",
@@ -1056,6 +1118,12 @@ impl Display for RksMessage {
             Self::DeleteService(name) => write!(f, "Delete service '{}'", name),
             Self::GetService(name) => write!(f, "Get service '{}'", name),
             Self::ListService => f.write_str("List services"),
+            Self::CreateNetworkPolicy(policy) => {
+                write!(f, "Create network policy '{}'", policy.metadata.name)
+            }
+            Self::DeleteNetworkPolicy(name) => write!(f, "Delete network policy '{}'", name),
+            Self::GetNetworkPolicy(name) => write!(f, "Get network policy '{}'", name),
+            Self::ListNetworkPolicy => f.write_str("List network policies"),
             Self::GetNodeCount => f.write_str("Get node count"),
             Self::RegisterNode(node) => write!(f, "Register node '{}'", node.metadata.name),
             Self::UserRequest(payload) => write!(f, "User request: {}", payload),
@@ -1063,6 +1131,9 @@ impl Display for RksMessage {
             Self::UpdateNftablesRules(rules) => {
                 write!(f, "UpdateNftablesRules (len={})", rules.len())
             }
+            Self::SetLoadBalancerVips(vips) => {
+                write!(f, "SetLoadBalancerVips ({})", vips.join(", "))
+            }
             Self::Heartbeat { node_name, status } => {
                 let ready_state = status
                     .conditions
@@ -1084,10 +1155,11 @@ impl Display for RksMessage {
                 "Apply network settings for node '{}' (subnet: {})",
                 config.node_id, config.subnet_env
             ),
-            Self::UpdateRoutes(node_name, routes) => write!(
+            Self::UpdateRoutes(node_name, routes, peers) => write!(
                 f,
-                "Update {} route(s) on node '{}'",
+                "Update {} route(s) and {} overlay peer(s) on node '{}'",
                 routes.len(),
+                peers.len(),
                 node_name
             ),
             Self::SetDns(ip, dns_port) => {
@@ -1212,9 +1284,39 @@ impl Display for RksMessage {
                 }
                 write!(f, "List services response: {}", preview.join(", "))
             }
+            Self::GetNetworkPolicyRes(policy) => {
+                write!(f, "Get network policy '{}' response", policy.metadata.name)
+            }
+            Self::ListNetworkPolicyRes(policies) => {
+                if policies.is_empty() {
+                    return f.write_str("List network policies response: no policies found");
+                }
+                let preview = policies
+                    .iter()
+                    .take(3)
+                    .map(|policy| policy.metadata.name.as_str())
+                    .collect::<Vec<_>>();
+                if policies.len() > preview.len() {
+                    return write!(
+                        f,
+                        "List network policies response: {} (+{} more)",
+                        preview.join(", "),
+                        policies.len() - preview.len()
+                    );
+                }
+                write!(f, "List network policies response: {}", preview.join(", "))
+            }
             Self::SetPodip((pod_name, pod_ip)) => {
                 write!(f, "Set pod '{}' IP address to {}", pod_name, pod_ip)
             }
+            Self::SetPodIps((pod_name, pod_ips)) => {
+                write!(
+                    f,
+                    "Set pod '{}' IP addresses to {}",
+                    pod_name,
+                    pod_ips.join(", ")
+                )
+            }
             Self::Certificate(_) => f.write_str("Certificate response received"),
             Self::GetPodLogs {
                 pod_name,
@@ -1286,6 +1388,9 @@ pub struct DeploymentRevisionInfo {
 pub struct NodeSpec {
     #[serde(rename = "podCIDR")]
     pub pod_cidr: String, // Pod network CIDR assigned to this node
+    /// Pod network CIDRs of each IP family, the first one is `podCIDR`
+    #[serde(rename = "podCIDRs", default, skip_serializing_if = "Vec::is_empty")]
+    pub pod_cidrs: Vec<String>,
     #[serde(default)]
     pub taints: Vec<Taint>,
 }
@@ -1521,6 +1626,167 @@ pub struct ServiceSpec {
     pub ports: Vec<ServicePort>,
     #[serde(rename = "clusterIP", default)]
     pub cluster_ip: Option<String>,
+    /// ClusterIPs of a dual-stack Service, at most one per IP family. The
+    /// first one is `clusterIP`
+    #[serde(rename = "clusterIPs", default, skip_serializing_if = "Vec::is_empty")]
+    pub cluster_ips: Vec<String>,
+    /// IP families served by the Service, those of its ClusterIPs if unset
+    #[serde(rename = "ipFamilies", default, skip_serializing_if = "Vec::is_empty")]
+    pub ip_families: Vec<IpFamily>,
+    /// `None` (default) or `ClientIP`, to send all connections of a client
+    /// to the same endpoint
+    #[serde(rename = "sessionAffinity", default)]
+    pub session_affinity: Option<String>,
+    #[serde(rename = "sessionAffinityConfig", default)]
+    pub session_affinity_config: Option<SessionAffinityConfig>,
+    /// `Cluster` (default) or `Local`, to only send NodePort and LoadBalancer
+    /// traffic to the endpoints of the node it arrives on, keeping its source IP
+    #[serde(rename = "externalTrafficPolicy", default)]
+    pub external_traffic_policy: Option<String>,
+    /// VIP requested for a LoadBalancer Service, allocated from the pool if unset
+    #[serde(rename = "loadBalancerIP", default)]
+    pub load_balancer_ip: Option<String>,
+}
+
+impl Default for ServiceSpec {
+    fn default() -> Self {
+        Self {
+            service_type: default_service_type(),
+            selector: None,
+            ports: Vec::new(),
+            cluster_ip: None,
+            cluster_ips: Vec::new(),
+            ip_families: Vec::new(),
+            session_affinity: None,
+            session_affinity_config: None,
+            external_traffic_policy: None,
+            load_balancer_ip: None,
+        }
+    }
+}
+
+/// Session affinity lasts 3 hours without traffic, as in Kubernetes
+pub const DEFAULT_SESSION_AFFINITY_TIMEOUT: u32 = 10800;
+
+impl ServiceSpec {
+    /// Timeout of the ClientIP session affinity, `None` without affinity
+    pub fn client_ip_affinity_timeout(&self) -> Option<u32> {
+        if self.session_affinity.as_deref() != Some("ClientIP") {
+            return None;
+        }
+        let timeout = self
+            .session_affinity_config
+            .as_ref()
+            .and_then(|cfg| cfg.client_ip.as_ref())
+            .and_then(|cfg| cfg.timeout_seconds)
+            .filter(|t| *t > 0)
+            .map(|t| t as u32);
+        Some(timeout.unwrap_or(DEFAULT_SESSION_AFFINITY_TIMEOUT))
+    }
+
+    pub fn is_external_traffic_local(&self) -> bool {
+        self.external_traffic_policy.as_deref() == Some("Local")
+    }
+
+    pub fn is_load_balancer(&self) -> bool {
+        self.service_type == "LoadBalancer"
+    }
+
+    /// Whether the Service has no ClusterIP (`clusterIP: None`)
+    pub fn is_headless(&self) -> bool {
+        self.cluster_ip.as_deref() == Some("None")
+            || self.cluster_ips.first().map(String::as_str) == Some("None")
+    }
+
+    /// ClusterIPs of the Service, `clusterIPs` or else `clusterIP`
+    pub fn cluster_ip_addrs(&self) -> Vec<IpAddr> {
+        let ips: Vec<&str> = if self.cluster_ips.is_empty() {
+            self.cluster_ip.as_deref().into_iter().collect()
+        } else {
+            self.cluster_ips.iter().map(String::as_str).collect()
+        };
+        ips.into_iter().filter_map(|ip| ip.parse().ok()).collect()
+    }
+
+    /// ClusterIP of the Service in `family`, if it serves it
+    pub fn cluster_ip_of(&self, family: IpFamily) -> Option<IpAddr> {
+        self.cluster_ip_addrs()
+            .into_iter()
+            .find(|ip| IpFamily::of(ip) == family)
+    }
+
+    /// IP families served by the Service: `ipFamilies`, else those of its
+    /// ClusterIPs, else IPv4 only
+    pub fn effective_ip_families(&self) -> Vec<IpFamily> {
+        if !self.ip_families.is_empty() {
+            return self.ip_families.clone();
+        }
+        let families: Vec<IpFamily> = self.cluster_ip_addrs().iter().map(IpFamily::of).collect();
+        if families.is_empty() {
+            vec![IpFamily::IPv4]
+        } else {
+            families
+        }
+    }
+}
+
+/// IP family of an address, as listed in `ipFamilies`
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum IpFamily {
+    IPv4,
+    IPv6,
+}
+
+impl IpFamily {
+    pub fn of(ip: &IpAddr) -> Self {
+        match ip {
+            IpAddr::V4(_) => IpFamily::IPv4,
+            IpAddr::V6(_) => IpFamily::IPv6,
+        }
+    }
+
+    /// Family of the textual address `ip`, `None` if it is not an IP address
+    pub fn of_str(ip: &str) -> Option<Self> {
+        ip.parse::<IpAddr>().ok().map(|ip| Self::of(&ip))
+    }
+}
+
+impl Display for IpFamily {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        match self {
+            IpFamily::IPv4 => f.write_str("IPv4"),
+            IpFamily::IPv6 => f.write_str("IPv6"),
+        }
+    }
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq, Eq, Default)]
+pub struct SessionAffinityConfig {
+    #[serde(rename = "clientIP", default)]
+    pub client_ip: Option<ClientIPConfig>,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq, Eq, Default)]
+pub struct ClientIPConfig {
+    #[serde(rename = "timeoutSeconds", default)]
+    pub timeout_seconds: Option<i32>,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq, Eq, Default)]
+pub struct ServiceStatus {
+    #[serde(rename = "loadBalancer", default)]
+    pub load_balancer: LoadBalancerStatus,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq, Eq, Default)]
+pub struct LoadBalancerStatus {
+    #[serde(default)]
+    pub ingress: Vec<LoadBalancerIngress>,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq, Eq)]
+pub struct LoadBalancerIngress {
+    pub ip: String,
 }
 
 fn default_service_type() -> String {
@@ -1549,6 +1815,20 @@ pub struct ServiceTask {
     pub kind: String,
     pub metadata: ObjectMeta,
     pub spec: ServiceSpec,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub status: Option<ServiceStatus>,
+}
+
+impl ServiceTask {
+    /// VIP allocated to a LoadBalancer Service, if any
+    pub fn load_balancer_ip(&self) -> Option<&str> {
+        self.status
+            .as_ref()?
+            .load_balancer
+            .ingress
+            .first()
+            .map(|ingress| ingress.ip.as_str())
+    }
 }
 
 #[derive(Debug, Serialize, Deserialize, Clone, Default, PartialEq, Eq)]
@@ -1639,8 +1919,15 @@ pub struct EndpointAddress {
     /// Optional reference to the target object (keeps shape simple - use ObjectReference)
     #[serde(rename = "targetRef", default)]
     pub target_ref: Option<ObjectReference>,
+    /// Share of the traffic relative to the other addresses, 1 if unset and
+    /// none if 0
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub weight: Option<u32>,
 }
 
+/// Pod annotation setting the weight of its endpoint addresses
+pub const ENDPOINT_WEIGHT_ANNOTATION: &str = "rk8s.io/endpoint-weight";
+
 #[derive(Debug, Serialize, Deserialize, Clone, PartialEq, Eq)]
 pub struct EndpointSubset {
     #[serde(default)]
@@ -1662,6 +1949,108 @@ pub struct Endpoint {
     pub subsets: Vec<EndpointSubset>,
 }
 
+/// NetworkPolicy related types (similar to Kubernetes NetworkPolicy)
+///
+/// A pod selected by a policy with the `Ingress` (`Egress`) policy type is
+/// isolated for ingress (egress): only the traffic allowed by one of the
+/// policies selecting it gets through. Pods selected by no policy are not
+/// isolated.
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct NetworkPolicy {
+    #[serde(rename = "apiVersion")]
+    pub api_version: String,
+    #[serde(rename = "kind")]
+    pub kind: String,
+    pub metadata: ObjectMeta,
+    pub spec: NetworkPolicySpec,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq, Eq, Default)]
+pub struct NetworkPolicySpec {
+    /// Pods of the policy namespace the policy applies to, an empty selector
+    /// selects all of them
+    #[serde(rename = "podSelector", default)]
+    pub pod_selector: LabelSelector,
+    #[serde(default)]
+    pub ingress: Vec<NetworkPolicyIngressRule>,
+    #[serde(default)]
+    pub egress: Vec<NetworkPolicyEgressRule>,
+    /// Defaults to `Ingress`, plus `Egress` if the policy has egress rules
+    #[serde(rename = "policyTypes", default)]
+    pub policy_types: Option<Vec<PolicyType>>,
+}
+
+impl NetworkPolicySpec {
+    /// Policy types in effect, with the defaults applied
+    pub fn effective_policy_types(&self) -> Vec<PolicyType> {
+        match &self.policy_types {
+            Some(types) => types.clone(),
+            None if self.egress.is_empty() => vec![PolicyType::Ingress],
+            None => vec![PolicyType::Ingress, PolicyType::Egress],
+        }
+    }
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum PolicyType {
+    Ingress,
+    Egress,
+}
+
+/// Traffic allowed to the selected pods: from any of `from` (anywhere if
+/// empty) to any of `ports` (any port if empty)
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq, Eq, Default)]
+pub struct NetworkPolicyIngressRule {
+    #[serde(default)]
+    pub from: Vec<NetworkPolicyPeer>,
+    #[serde(default)]
+    pub ports: Vec<NetworkPolicyPort>,
+}
+
+/// Traffic allowed from the selected pods: to any of `to` (anywhere if empty)
+/// on any of `ports` (any port if empty)
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq, Eq, Default)]
+pub struct NetworkPolicyEgressRule {
+    #[serde(default)]
+    pub to: Vec<NetworkPolicyPeer>,
+    #[serde(default)]
+    pub ports: Vec<NetworkPolicyPort>,
+}
+
+/// Either an `ipBlock`, or pods selected by `podSelector` and/or
+/// `namespaceSelector`.
+///
+/// There is no Namespace resource, so a namespace only has the
+/// `kubernetes.io/metadata.name` label, holding its name.
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq, Eq, Default)]
+pub struct NetworkPolicyPeer {
+    #[serde(rename = "podSelector", default)]
+    pub pod_selector: Option<LabelSelector>,
+    #[serde(rename = "namespaceSelector", default)]
+    pub namespace_selector: Option<LabelSelector>,
+    #[serde(rename = "ipBlock", default)]
+    pub ip_block: Option<IPBlock>,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq, Eq, Default)]
+pub struct IPBlock {
+    pub cidr: String,
+    #[serde(default)]
+    pub except: Vec<String>,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq, Eq)]
+pub struct NetworkPolicyPort {
+    #[serde(default = "default_protocol")]
+    pub protocol: String,
+    /// Any port of `protocol` if unset
+    #[serde(default)]
+    pub port: Option<i32>,
+    /// Last port of the range starting at `port`
+    #[serde(rename = "endPort", default)]
+    pub end_port: Option<i32>,
+}
+
 /// Support absolute values or percentages
 /// rkl needs to check positive value and percentage format
 #[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq)]
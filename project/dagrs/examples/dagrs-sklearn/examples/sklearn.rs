@@ -1,7 +1,10 @@
-use std::{collections::HashMap, sync::Arc};
+use std::sync::Arc;
 
-use dagrs::{async_trait::async_trait, Action, Content, EnvVar, InChannels, OutChannels, Output};
-use dagrs_sklearn::{yaml_parser::YamlParser, CommandAction, Parser};
+use dagrs::{
+    async_trait::async_trait,
+    parser::{self, Registry},
+    Action, CommandAction, Content, EnvVar, InChannels, OutChannels, Output,
+};
 
 const ENV_DATA_SRC: &str = "data_src";
 
@@ -88,53 +91,15 @@ impl Action for RootAction {
 fn main() {
     env_logger::init();
 
-    let specific_actions: HashMap<String, Box<dyn Action>> = HashMap::from([
-        (
-            "node0".to_string(),
-            Box::new(NodeAction::new(0)) as Box<dyn Action>,
-        ),
-        (
-            "node1".to_string(),
-            Box::new(NodeAction::new(1)) as Box<dyn Action>,
-        ),
-        (
-            "node2".to_string(),
-            Box::new(NodeAction::new(2)) as Box<dyn Action>,
-        ),
-        (
-            "node3".to_string(),
-            Box::new(NodeAction::new(3)) as Box<dyn Action>,
-        ),
-        (
-            "node4".to_string(),
-            Box::new(NodeAction::new(4)) as Box<dyn Action>,
-        ),
-        (
-            "node5".to_string(),
-            Box::new(NodeAction::new(5)) as Box<dyn Action>,
-        ),
-        (
-            "node6".to_string(),
-            Box::new(NodeAction::new(6)) as Box<dyn Action>,
-        ),
-        (
-            "node7".to_string(),
-            Box::new(NodeAction::new(7)) as Box<dyn Action>,
-        ),
-        (
-            "node8".to_string(),
-            Box::new(NodeAction::new(8)) as Box<dyn Action>,
-        ),
-        (
-            "node9".to_string(),
-            Box::new(NodeAction::new(9)) as Box<dyn Action>,
-        ),
-        ("root".to_string(), Box::new(RootAction) as Box<dyn Action>),
-    ]);
-
-    let (mut dag, mut env_var) = YamlParser
-        .parse_tasks("examples/config.yml", specific_actions)
-        .unwrap();
+    let mut registry = Registry::new();
+    registry
+        .register_action("lr_node", |params| {
+            let class = params["class"].as_u64().ok_or("`class` must be a number")?;
+            Ok(NodeAction::new(class as usize))
+        })
+        .register_action("lr_root", |_| Ok(RootAction));
+
+    let (mut dag, mut env_var) = parser::from_file("examples/config.yml", &registry).unwrap();
     env_var.set(ENV_DATA_SRC, "examples/ex3data1.mat");
 
     let root_id = *env_var.get_node_id("root").unwrap();
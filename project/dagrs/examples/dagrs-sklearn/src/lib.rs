@@ -1,8 +1 @@
-mod command_action;
-mod parser;
-pub mod utils;
-
-pub use command_action::*;
 pub use dagrs::*;
-pub use parser::*;
-pub use utils::parser::*;
@@ -1,2 +0,0 @@
-pub mod file;
-pub mod parser;
@@ -1 +0,0 @@
-console.log("hello js")
\ No newline at end of file
@@ -1 +0,0 @@
-print("hello python")
\ No newline at end of file
@@ -1 +0,0 @@
-echo "hello sh"
\ No newline at end of file
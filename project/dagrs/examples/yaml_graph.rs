@@ -0,0 +1,101 @@
+//! # Example: yaml_graph
+//! Builds a graph from a YAML definition and runs it. The graph is as follows:
+//!
+//! numbers ──→ enough ──→ greet
+//!
+//! `numbers` prints some numbers with the built-in command action, the
+//! conditional node `enough` checks that there are at least `min` of them, and
+//! `greet` greets the name given in its `params` with the greeting of the
+//! environment.
+//!
+//! Run with `cargo run --example yaml_graph --features yaml`.
+
+use std::sync::Arc;
+
+use async_trait::async_trait;
+use dagrs::{
+    Action, Content, EnvVar, InChannels, OutChannels, Output,
+    node::conditional_node::Condition,
+    parser::{self, Registry},
+};
+
+const DEFINITION: &str = r#"
+env:
+  greeting: Hello
+dagrs:
+  numbers:
+    cmd: echo 1 2 3 4 5
+  enough:
+    kind: condition
+    after: [ numbers ]
+    condition: at_least
+    params: { min: 3 }
+  greet:
+    after: [ enough ]
+    action: greet
+    params: { who: Dagrs }
+"#;
+
+/// Holds when the predecessors printed at least `min` words.
+struct AtLeast {
+    min: usize,
+}
+
+#[async_trait]
+impl Condition for AtLeast {
+    async fn run(&self, in_channels: &mut InChannels, _: &OutChannels, _: Arc<EnvVar>) -> bool {
+        let words: usize = in_channels
+            .map(|content| {
+                let content = content.unwrap();
+                let (stdout, _) = content.get::<(Vec<String>, Vec<String>)>().unwrap();
+                stdout
+                    .iter()
+                    .map(|line| line.split_whitespace().count())
+                    .sum::<usize>()
+            })
+            .await
+            .into_iter()
+            .sum();
+        words >= self.min
+    }
+}
+
+/// Greets `who` with the `greeting` of the environment.
+struct Greet {
+    who: String,
+}
+
+#[async_trait]
+impl Action for Greet {
+    async fn run(&self, _: &mut InChannels, _: &mut OutChannels, env: Arc<EnvVar>) -> Output {
+        let greeting: String = env.get("greeting").unwrap();
+        Output::Out(Some(Content::new(format!("{} {}", greeting, self.who))))
+    }
+}
+
+fn main() {
+    env_logger::init();
+
+    let mut registry = Registry::new();
+    registry
+        .register_condition("at_least", |params| {
+            let min = params["min"].as_u64().ok_or("`min` must be a number")?;
+            Ok(AtLeast { min: min as usize })
+        })
+        .register_action("greet", |params| {
+            let who = params["who"].as_str().ok_or("`who` must be a string")?;
+            Ok(Greet {
+                who: who.to_string(),
+            })
+        });
+
+    let (mut graph, env) = parser::from_yaml_str(DEFINITION, &registry).unwrap();
+    let greet = *env.get_node_id("greet").unwrap();
+    graph.start().unwrap();
+
+    let outputs = graph.get_outputs();
+    let greeting = outputs[&greet].get_out().unwrap();
+    let greeting = greeting.get::<String>().unwrap();
+    assert_eq!(greeting, "Hello Dagrs");
+    println!("{}", greeting);
+}
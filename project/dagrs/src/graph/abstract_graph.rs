@@ -127,17 +127,25 @@ impl AbstractGraph {
 
     /// Get topological sort of the graph.
     /// Returns None if cycle detected.
+    ///
+    /// Ties are broken by node ID, so that a graph built the same way is always
+    /// sorted (and partitioned into blocks) the same way, which checkpoints of
+    /// a previous process rely on.
     pub fn get_topological_sort(&self) -> Option<Vec<NodeId>> {
         let mut in_degree = self.in_degree.clone();
-        let mut queue: VecDeque<NodeId> = in_degree
+        let mut roots: Vec<NodeId> = in_degree
             .iter()
             .filter_map(|(&node, &degree)| if degree == 0 { Some(node) } else { None })
             .collect();
+        roots.sort();
+        let mut queue: VecDeque<NodeId> = roots.into();
         let mut sorted = Vec::new();
 
         while let Some(node) = queue.pop_front() {
             sorted.push(node);
             if let Some(nexts) = self.edges.get(&node) {
+                let mut nexts: Vec<&NodeId> = nexts.iter().collect();
+                nexts.sort();
                 for next in nexts {
                     let degree = in_degree.get_mut(next).unwrap();
                     *degree -= 1;
@@ -19,6 +19,8 @@ pub enum GraphError {
     LoopLimitExceeded(usize),
     /// Checkpoint operation failed
     CheckpointError(String),
+    /// Execution was cancelled through the graph's cancellation token
+    Cancelled,
 }
 
 impl std::fmt::Display for GraphError {
@@ -92,4 +92,11 @@ pub enum GraphEvent {
     /// This is the final event in the stream.
     /// Consumers can use this to signal the end of monitoring.
     GraphFinished,
+
+    /// Emitted when the graph execution is cancelled through its
+    /// cancellation token.
+    ///
+    /// Nodes still running are aborted. This is the final event of a
+    /// cancelled execution; `GraphFinished` is not sent.
+    GraphCancelled,
 }
@@ -2,6 +2,7 @@ mod abstract_graph;
 pub mod error;
 pub mod event;
 pub mod loop_subgraph;
+pub mod visualize;
 
 use std::hash::Hash;
 use std::sync::atomic::Ordering;
@@ -26,12 +27,18 @@ use crate::{
 };
 
 use log::{debug, error, info, warn};
+use serde::{Serialize, de::DeserializeOwned};
 use tokio::sync::Mutex;
-use tokio::sync::{RwLock, broadcast, mpsc};
+use tokio::sync::{RwLock, Semaphore, broadcast, mpsc};
 use tokio::task;
+use tokio_util::sync::CancellationToken;
 
 use abstract_graph::AbstractGraph;
 use error::GraphError;
+use visualize::{Topology, TopologyNode};
+
+/// Output recorded for nodes aborted by a cancellation.
+const CANCELLED: &str = "Cancelled";
 
 /// [`Graph`] is dagrs's main body.
 ///
@@ -57,6 +64,13 @@ use error::GraphError;
 /// - Enable automatic checkpointing with `set_checkpoint_config()`
 /// - Manually save checkpoints with `save_checkpoint()`
 /// - Resume from checkpoints with `resume_from_checkpoint()`
+/// - Continue the latest unfinished run on start with
+///   [`CheckpointConfig::with_auto_resume`]
+///
+/// # Cancellation and Concurrency
+///
+/// - Cancel a running graph through the token returned by `cancellation_token()`
+/// - Bound the number of nodes running at once with `set_max_concurrency()`
 pub struct Graph {
     /// Define the Net struct that holds all nodes
     pub(crate) nodes: HashMap<NodeId, Arc<Mutex<dyn Node>>>,
@@ -91,6 +105,10 @@ pub struct Graph {
     pub(crate) checkpoint_store: Option<Arc<dyn CheckpointStore>>,
     /// Checkpoint configuration
     pub(crate) checkpoint_config: CheckpointConfig,
+    /// Token cancelling the execution of the graph
+    pub(crate) cancel_token: CancellationToken,
+    /// Maximum number of nodes running at the same time, 0 for no limit
+    pub(crate) max_concurrency: usize,
 }
 
 impl Default for Graph {
@@ -118,6 +136,8 @@ impl Graph {
             max_loop_count: 1000,
             checkpoint_store: None,
             checkpoint_config: CheckpointConfig::default(),
+            cancel_token: CancellationToken::new(),
+            max_concurrency: 0,
         }
     }
 
@@ -126,6 +146,46 @@ impl Graph {
         self.max_loop_count = count;
     }
 
+    /// Set the maximum number of nodes running at the same time.
+    ///
+    /// Nodes of a block wait for a free slot before they start, and keep it
+    /// through their retries. 0, the default, removes the limit.
+    ///
+    /// Nodes of a block that exchange data through channels must be able to
+    /// run together: with a limit below their number, a sender can wait on a
+    /// receiver that never gets to start.
+    pub fn set_max_concurrency(&mut self, limit: usize) {
+        self.max_concurrency = limit;
+    }
+
+    /// Get the token cancelling the execution of this graph.
+    ///
+    /// Cancelling it aborts the running nodes, emits
+    /// [`GraphEvent::GraphCancelled`] and makes the execution return
+    /// [`GraphError::Cancelled`].
+    ///
+    /// # Example
+    /// ```ignore
+    /// let token = graph.cancellation_token();
+    /// tokio::spawn(async move {
+    ///     tokio::time::sleep(Duration::from_secs(10)).await;
+    ///     token.cancel();
+    /// });
+    /// graph.async_start().await?;
+    /// ```
+    pub fn cancellation_token(&self) -> CancellationToken {
+        self.cancel_token.clone()
+    }
+
+    /// Replace the token cancelling the execution of this graph, e.g. with a
+    /// child token of a larger job.
+    ///
+    /// A cancelled token stays cancelled: set a new one before executing the
+    /// graph again.
+    pub fn set_cancellation_token(&mut self, token: CancellationToken) {
+        self.cancel_token = token;
+    }
+
     /// Set the checkpoint store for state persistence.
     ///
     /// # Example
@@ -175,6 +235,18 @@ impl Graph {
         pc: usize,
         loop_count: usize,
         active_nodes: &HashSet<NodeId>,
+    ) -> Result<String, CheckpointError> {
+        self.save_run_checkpoint(pc, loop_count, active_nodes, false)
+            .await
+    }
+
+    /// Save a checkpoint, marking whether the run has finished.
+    async fn save_run_checkpoint(
+        &self,
+        pc: usize,
+        loop_count: usize,
+        active_nodes: &HashSet<NodeId>,
+        completed_run: bool,
     ) -> Result<String, CheckpointError> {
         let store = self
             .checkpoint_store
@@ -183,6 +255,7 @@ impl Graph {
 
         let mut checkpoint = Checkpoint::new(pc, loop_count);
         checkpoint.set_active_nodes(active_nodes);
+        checkpoint.completed = completed_run;
 
         // Capture node execution states with output data
         for (node_id, exec_state) in &self.execute_states {
@@ -196,6 +269,13 @@ impl Graph {
                 NodeState::pending(node_id.0)
             };
 
+            // Record the name to match the node after a restart
+            if let Some(node) = self.nodes.get(node_id)
+                && let Ok(node) = node.try_lock()
+            {
+                node_state = node_state.with_name(node.name());
+            }
+
             // Try to capture output summary for debugging
             if let Some(content) = output.get_out() {
                 // Try to get a debug representation of common types
@@ -205,8 +285,10 @@ impl Graph {
                 }
 
                 // Try to serialize if the content is a serializable primitive
-                if let Some(data) = Self::try_serialize_output(&content) {
-                    node_state = node_state.with_output_data(data);
+                if let Some((output_type, data)) = Self::try_serialize_output(&content) {
+                    node_state = node_state
+                        .with_output_data(data)
+                        .with_output_type(output_type);
                 }
             } else if let Some(err) = output.get_err() {
                 node_state = node_state.with_summary(format!("Error: {}", err));
@@ -221,9 +303,13 @@ impl Graph {
 
         store.save(&checkpoint).await?;
 
-        // Enforce max checkpoints limit
-        if self.checkpoint_config.max_checkpoints > 0 {
-            self.cleanup_old_checkpoints(store.as_ref()).await?;
+        // Enforce the retention policy
+        let retention = self.checkpoint_config.retention();
+        if retention.is_limited() {
+            let deleted = store.prune(&retention).await?;
+            if deleted > 0 {
+                debug!("Deleted {} old checkpoints", deleted);
+            }
         }
 
         // Emit event
@@ -279,40 +365,48 @@ impl Graph {
         None
     }
 
-    /// Try to serialize output content to JSON bytes
-    fn try_serialize_output(content: &Content) -> Option<Vec<u8>> {
-        // Try common serializable types
-        if let Some(v) = content.get::<String>() {
-            return serde_json::to_vec(v).ok();
-        }
-        if let Some(v) = content.get::<i32>() {
-            return serde_json::to_vec(v).ok();
-        }
-        if let Some(v) = content.get::<i64>() {
-            return serde_json::to_vec(v).ok();
-        }
-        if let Some(v) = content.get::<u32>() {
-            return serde_json::to_vec(v).ok();
-        }
-        if let Some(v) = content.get::<u64>() {
-            return serde_json::to_vec(v).ok();
-        }
-        if let Some(v) = content.get::<f64>() {
-            return serde_json::to_vec(v).ok();
-        }
-        if let Some(v) = content.get::<bool>() {
-            return serde_json::to_vec(v).ok();
-        }
-        if let Some(v) = content.get::<Vec<String>>() {
-            return serde_json::to_vec(v).ok();
+    /// Try to serialize output content to JSON bytes, with the name of its type
+    fn try_serialize_output(content: &Content) -> Option<(&'static str, Vec<u8>)> {
+        fn as_json<T: Serialize + 'static>(
+            content: &Content,
+            output_type: &'static str,
+        ) -> Option<(&'static str, Vec<u8>)> {
+            let data = serde_json::to_vec(content.get::<T>()?).ok()?;
+            Some((output_type, data))
         }
-        if let Some(v) = content.get::<Vec<i32>>() {
-            return serde_json::to_vec(v).ok();
+
+        // Try common serializable types
+        as_json::<String>(content, "String")
+            .or_else(|| as_json::<i32>(content, "i32"))
+            .or_else(|| as_json::<i64>(content, "i64"))
+            .or_else(|| as_json::<u32>(content, "u32"))
+            .or_else(|| as_json::<u64>(content, "u64"))
+            .or_else(|| as_json::<f64>(content, "f64"))
+            .or_else(|| as_json::<bool>(content, "bool"))
+            .or_else(|| as_json::<Vec<String>>(content, "Vec<String>"))
+            .or_else(|| as_json::<Vec<i32>>(content, "Vec<i32>"))
+            .or_else(|| as_json::<Vec<i64>>(content, "Vec<i64>"))
+    }
+
+    /// Restore output content serialized by `try_serialize_output`
+    fn restore_output(output_type: &str, data: &[u8]) -> Option<Content> {
+        fn from_json<T: DeserializeOwned + Send + Sync + 'static>(data: &[u8]) -> Option<Content> {
+            serde_json::from_slice::<T>(data).ok().map(Content::new)
         }
-        if let Some(v) = content.get::<Vec<i64>>() {
-            return serde_json::to_vec(v).ok();
+
+        match output_type {
+            "String" => from_json::<String>(data),
+            "i32" => from_json::<i32>(data),
+            "i64" => from_json::<i64>(data),
+            "u32" => from_json::<u32>(data),
+            "u64" => from_json::<u64>(data),
+            "f64" => from_json::<f64>(data),
+            "bool" => from_json::<bool>(data),
+            "Vec<String>" => from_json::<Vec<String>>(data),
+            "Vec<i32>" => from_json::<Vec<i32>>(data),
+            "Vec<i64>" => from_json::<Vec<i64>>(data),
+            _ => None,
         }
-        None
     }
 
     /// Load a checkpoint by ID.
@@ -366,44 +460,17 @@ impl Graph {
         store.delete(&checkpoint_id.to_string()).await
     }
 
-    /// Clean up old checkpoints to maintain the max_checkpoints limit.
-    async fn cleanup_old_checkpoints(
-        &self,
-        store: &dyn CheckpointStore,
-    ) -> Result<(), CheckpointError> {
-        let ids = store.list().await?;
-        if ids.len() <= self.checkpoint_config.max_checkpoints {
-            return Ok(());
-        }
-
-        // Load all checkpoints and sort by timestamp
-        let mut checkpoints = Vec::new();
-        for id in ids {
-            if let Ok(cp) = store.load(&id).await {
-                checkpoints.push(cp);
-            }
-        }
-        checkpoints.sort_by_key(|c| c.timestamp);
-
-        // Delete oldest checkpoints
-        let to_delete = checkpoints
-            .len()
-            .saturating_sub(self.checkpoint_config.max_checkpoints);
-        for checkpoint in checkpoints.into_iter().take(to_delete) {
-            store.delete(&checkpoint.id).await?;
-            debug!("Deleted old checkpoint: {}", checkpoint.id);
-        }
-
-        Ok(())
-    }
-
     /// Resume graph execution from a checkpoint.
     ///
     /// This method:
     /// 1. Loads the checkpoint
-    /// 2. Restores execution state
+    /// 2. Restores execution state, sending the restored outputs of the nodes
+    ///    completed before the checkpoint again to their successors
     /// 3. Continues execution from the saved point
     ///
+    /// The checkpoint may come from another process running the same graph:
+    /// its nodes are matched by name, or by ID when node names are not unique.
+    ///
     /// # Arguments
     /// * `checkpoint_id` - The ID of the checkpoint to resume from
     ///
@@ -427,39 +494,86 @@ impl Graph {
             .await
             .map_err(|e| GraphError::CheckpointError(e.to_string()))?;
 
+        // Initialize if not already done
+        if self.blocks.is_empty() {
+            self.init();
+            let is_loop = self.check_loop_and_partition().await;
+            if is_loop {
+                return Err(GraphError::GraphLoopDetected);
+            }
+        }
+
+        self.resume(checkpoint).await
+    }
+
+    /// Restore the state saved in `checkpoint` and continue execution from it.
+    async fn resume(&mut self, checkpoint: Checkpoint) -> Result<(), GraphError> {
         info!(
             "Resuming from checkpoint: {} (pc={}, loop_count={})",
             checkpoint.id, checkpoint.pc, checkpoint.loop_count
         );
 
+        let node_ids = self
+            .match_checkpoint_nodes(&checkpoint)
+            .await
+            .map_err(|e| GraphError::CheckpointError(e.to_string()))?;
+
         // Emit event
         let _ = self.event_sender.send(GraphEvent::CheckpointRestored {
             checkpoint_id: checkpoint.id.clone(),
             pc: checkpoint.pc,
         });
 
-        // Initialize if not already done
-        if self.blocks.is_empty() {
-            self.init();
-            let is_loop = self.check_loop_and_partition().await;
-            if is_loop {
-                return Err(GraphError::GraphLoopDetected);
-            }
-        }
-
         // Restore active nodes
-        let active_nodes = checkpoint.get_active_nodes();
+        let active_nodes: HashSet<NodeId> = checkpoint
+            .active_nodes
+            .iter()
+            .filter_map(|id| node_ids.get(id).copied())
+            .collect();
 
         // Restore execution states for completed nodes
         for (node_id_val, node_state) in &checkpoint.node_states {
-            let node_id = NodeId(*node_id_val);
-            if let Some(exec_state) = self.execute_states.get(&node_id)
-                && node_state.completed
-            {
-                if node_state.success {
-                    exec_state.exe_success();
-                } else {
-                    exec_state.exe_fail();
+            let node_id = node_ids[node_id_val];
+            let Some(exec_state) = self.execute_states.get(&node_id) else {
+                continue;
+            };
+            let mut content = None;
+            if node_state.completed && node_state.success {
+                if let (Some(output_type), Some(data)) =
+                    (&node_state.output_type, &node_state.output_data)
+                {
+                    content = Self::restore_output(output_type, data);
+                }
+                if let Some(content) = &content {
+                    exec_state.set_output(Output::Out(Some(content.clone())));
+                }
+                exec_state.exe_success();
+            } else if node_state.completed {
+                exec_state.exe_fail();
+            }
+
+            // The nodes before the checkpoint do not run again: send their output
+            // again to the successors still to run, standing in for what they sent
+            // when they ran, or let the successors see closed channels instead of
+            // waiting forever
+            let before_checkpoint = self
+                .node_block_map
+                .get(&node_id)
+                .is_some_and(|&block| block < checkpoint.pc);
+            if before_checkpoint && let Some(node) = self.nodes.get(&node_id) {
+                let mut node = node.lock().await;
+                match content {
+                    Some(content) => {
+                        node.output_channels().broadcast(content).await;
+                    }
+                    None => {
+                        debug!(
+                            "No output to restore for node [name: {}, id: {}]",
+                            node.name(),
+                            node_id.0
+                        );
+                        node.output_channels().close_all();
+                    }
                 }
             }
         }
@@ -469,6 +583,54 @@ impl Graph {
             .await
     }
 
+    /// Map the node IDs of `checkpoint` to the nodes of this graph.
+    ///
+    /// Node IDs are allocated by the process building the graph, so nodes are
+    /// matched by name when the names of the graph are unique.
+    async fn match_checkpoint_nodes(
+        &self,
+        checkpoint: &Checkpoint,
+    ) -> Result<HashMap<usize, NodeId>, CheckpointError> {
+        if let Some(blocks) = checkpoint.metadata.get("blocks_count")
+            && *blocks != self.blocks.len().to_string()
+        {
+            return Err(CheckpointError::InvalidCheckpoint(format!(
+                "checkpoint has {} blocks, the graph has {}",
+                blocks,
+                self.blocks.len()
+            )));
+        }
+
+        let mut names = HashMap::new();
+        for (id, node) in &self.nodes {
+            names.insert(node.lock().await.name(), *id);
+        }
+        let by_name = names.len() == self.nodes.len()
+            && checkpoint
+                .node_states
+                .values()
+                .all(|state| state.node_name.is_some());
+
+        let mut node_ids = HashMap::new();
+        for (id, state) in &checkpoint.node_states {
+            let node_id = if by_name {
+                let name = state.node_name.as_ref().unwrap();
+                names.get(name).copied().ok_or_else(|| {
+                    CheckpointError::InvalidCheckpoint(format!("unknown node `{}`", name))
+                })?
+            } else if self.nodes.contains_key(&NodeId(*id)) {
+                NodeId(*id)
+            } else {
+                return Err(CheckpointError::InvalidCheckpoint(format!(
+                    "unknown node id {}",
+                    id
+                )));
+            };
+            node_ids.insert(*id, node_id);
+        }
+        Ok(node_ids)
+    }
+
     /// Internal method to run from a specific checkpoint state.
     async fn run_from_checkpoint(
         &mut self,
@@ -478,6 +640,8 @@ impl Graph {
     ) -> Result<(), GraphError> {
         let condition_flag = Arc::new(Mutex::new(true));
         let errors = Arc::new(Mutex::new(Vec::new()));
+        let limiter =
+            (self.max_concurrency > 0).then(|| Arc::new(Semaphore::new(self.max_concurrency)));
 
         let mut pc = start_pc;
         let mut loop_count = start_loop_count;
@@ -497,6 +661,10 @@ impl Graph {
 
         // Start the nodes by blocks
         while pc < self.blocks.len() {
+            if self.cancel_token.is_cancelled() {
+                return Err(self.cancelled());
+            }
+
             // Check if we should create a checkpoint
             if self.checkpoint_config.enabled && self.checkpoint_store.is_some() {
                 let should_checkpoint = self.should_create_checkpoint(
@@ -564,16 +732,29 @@ impl Graph {
                 let errors = errors.clone();
                 let hooks = self.hooks.clone();
                 let event_sender = self.event_sender.clone();
+                let cancel = self.cancel_token.clone();
+                let limiter = limiter.clone();
 
                 let task = task::spawn({
                     let errors = Arc::clone(&errors);
                     async move {
+                        // Wait for a free slot, kept until the node is done
+                        let _permit = match limiter {
+                            Some(limiter) => tokio::select! {
+                                permit = limiter.acquire_owned() => permit.ok(),
+                                _ = cancel.cancelled() => {
+                                    return (node_id, Output::Err(CANCELLED.to_string()));
+                                }
+                            },
+                            None => None,
+                        };
                         let node_ref: Arc<Mutex<dyn Node>> = node.clone();
                         let node_guard = node.lock().await;
                         let node_name = node_guard.name().to_string();
                         let node_id = node_guard.id();
                         let id_val = node_id.0;
                         let max_retries = node_guard.max_retries();
+                        let timeout_ms = node_guard.timeout_ms();
 
                         // Hook: before_node_run
                         {
@@ -611,7 +792,10 @@ impl Graph {
 
                             match result {
                                 Ok(future) => {
-                                    let out = future.await;
+                                    let Some(out) = run_attempt(future, timeout_ms, &cancel).await
+                                    else {
+                                        break Ok(Output::Err(CANCELLED.to_string()));
+                                    };
 
                                     if out.is_err() {
                                         let error_msg = out.get_err().unwrap_or_default();
@@ -666,11 +850,14 @@ impl Graph {
                                                     retry_delay,
                                                     error_msg
                                                 );
-                                                tokio::time::sleep(Duration::from_millis(
-                                                    retry_delay,
-                                                ))
-                                                .await;
-                                                continue;
+                                                tokio::select! {
+                                                    _ = tokio::time::sleep(Duration::from_millis(
+                                                        retry_delay,
+                                                    )) => continue,
+                                                    _ = cancel.cancelled() => {
+                                                        break Ok(Output::Err(CANCELLED.to_string()));
+                                                    }
+                                                }
                                             }
                                         }
 
@@ -688,6 +875,14 @@ impl Graph {
                             }
                         };
 
+                        if cancel.is_cancelled() {
+                            let mut node_guard = node_ref.lock().await;
+                            node_guard.input_channels().close_all();
+                            node_guard.output_channels().close_all();
+                            debug!("Execution cancelled [name: {}, id: {}]", node_name, id_val);
+                            return (node_id, Output::Err(CANCELLED.to_string()));
+                        }
+
                         // Process the final output
                         match final_output {
                             Ok(out) => {
@@ -798,6 +993,14 @@ impl Graph {
             // Update nodes completed count
             nodes_since_checkpoint += active_block_nodes.len();
 
+            if self.cancel_token.is_cancelled() {
+                // Save checkpoint to resume the cancelled execution from
+                if self.checkpoint_config.enabled && self.checkpoint_store.is_some() {
+                    let _ = self.save_checkpoint(pc, loop_count, &active_nodes).await;
+                }
+                return Err(self.cancelled());
+            }
+
             // Check for errors immediately
             let errors_guard = errors.lock().await;
             if !errors_guard.is_empty() {
@@ -868,6 +1071,15 @@ impl Graph {
             pc = next_pc;
         }
 
+        // Mark the run as finished so that it is not resumed again
+        if self.auto_resume_enabled()
+            && let Err(e) = self
+                .save_run_checkpoint(self.blocks.len(), loop_count, &active_nodes, true)
+                .await
+        {
+            error!("Failed to save the final checkpoint: {}", e);
+        }
+
         let _ = self.event_sender.send(GraphEvent::GraphFinished);
 
         self.is_active
@@ -876,6 +1088,13 @@ impl Graph {
         Ok(())
     }
 
+    /// Whether the graph continues its latest unfinished run when started.
+    fn auto_resume_enabled(&self) -> bool {
+        self.checkpoint_config.enabled
+            && self.checkpoint_config.auto_resume
+            && self.checkpoint_store.is_some()
+    }
+
     /// Check if a checkpoint should be created based on configuration.
     fn should_create_checkpoint(&self, nodes_completed: usize, seconds_elapsed: u64) -> bool {
         if let Some(interval) = self.checkpoint_config.interval_nodes
@@ -1106,6 +1325,23 @@ impl Graph {
         if !self.is_active.load(Ordering::Relaxed) {
             return Err(GraphError::GraphNotActive);
         }
+
+        if self.auto_resume_enabled() {
+            let latest = self
+                .get_latest_checkpoint()
+                .await
+                .map_err(|e| GraphError::CheckpointError(e.to_string()))?;
+            if let Some(checkpoint) = latest.filter(|checkpoint| !checkpoint.completed) {
+                return self.resume(checkpoint).await;
+            }
+
+            // Start a new run, saving checkpoints to resume it from
+            for node in self.nodes.values() {
+                node.lock().await.reset();
+            }
+            let all_nodes = self.nodes.keys().cloned().collect();
+            return self.run_from_checkpoint(0, 0, all_nodes).await;
+        }
         self.run().await
     }
 
@@ -1125,6 +1361,8 @@ impl Graph {
     async fn run(&mut self) -> Result<(), GraphError> {
         let condition_flag = Arc::new(Mutex::new(true));
         let errors = Arc::new(Mutex::new(Vec::new()));
+        let limiter =
+            (self.max_concurrency > 0).then(|| Arc::new(Semaphore::new(self.max_concurrency)));
 
         // Reset all nodes
         for node in self.nodes.values() {
@@ -1146,6 +1384,10 @@ impl Graph {
 
         // Start the nodes by blocks
         while pc < self.blocks.len() {
+            if self.cancel_token.is_cancelled() {
+                return Err(self.cancelled());
+            }
+
             let block = &self.blocks[pc];
 
             let mut active_block_nodes = Vec::new();
@@ -1200,16 +1442,29 @@ impl Graph {
                 let errors = errors.clone();
                 let hooks = self.hooks.clone();
                 let event_sender = self.event_sender.clone();
+                let cancel = self.cancel_token.clone();
+                let limiter = limiter.clone();
 
                 let task = task::spawn({
                     let errors = Arc::clone(&errors);
                     async move {
+                        // Wait for a free slot, kept until the node is done
+                        let _permit = match limiter {
+                            Some(limiter) => tokio::select! {
+                                permit = limiter.acquire_owned() => permit.ok(),
+                                _ = cancel.cancelled() => {
+                                    return (node_id, Output::Err(CANCELLED.to_string()));
+                                }
+                            },
+                            None => None,
+                        };
                         let node_ref: Arc<Mutex<dyn Node>> = node.clone();
                         let node_guard = node.lock().await;
                         let node_name = node_guard.name().to_string();
                         let node_id = node_guard.id();
                         let id_val = node_id.0;
                         let max_retries = node_guard.max_retries();
+                        let timeout_ms = node_guard.timeout_ms();
 
                         // Hook: before_node_run
                         {
@@ -1247,7 +1502,10 @@ impl Graph {
 
                             match result {
                                 Ok(future) => {
-                                    let out = future.await;
+                                    let Some(out) = run_attempt(future, timeout_ms, &cancel).await
+                                    else {
+                                        break Ok(Output::Err(CANCELLED.to_string()));
+                                    };
 
                                     if out.is_err() {
                                         let error_msg = out.get_err().unwrap_or_default();
@@ -1302,11 +1560,14 @@ impl Graph {
                                                     retry_delay,
                                                     error_msg
                                                 );
-                                                tokio::time::sleep(Duration::from_millis(
-                                                    retry_delay,
-                                                ))
-                                                .await;
-                                                continue;
+                                                tokio::select! {
+                                                    _ = tokio::time::sleep(Duration::from_millis(
+                                                        retry_delay,
+                                                    )) => continue,
+                                                    _ = cancel.cancelled() => {
+                                                        break Ok(Output::Err(CANCELLED.to_string()));
+                                                    }
+                                                }
                                             }
                                         }
 
@@ -1324,6 +1585,14 @@ impl Graph {
                             }
                         };
 
+                        if cancel.is_cancelled() {
+                            let mut node_guard = node_ref.lock().await;
+                            node_guard.input_channels().close_all();
+                            node_guard.output_channels().close_all();
+                            debug!("Execution cancelled [name: {}, id: {}]", node_name, id_val);
+                            return (node_id, Output::Err(CANCELLED.to_string()));
+                        }
+
                         match final_output {
                             Ok(out) => {
                                 let node = node_ref.lock().await;
@@ -1432,6 +1701,10 @@ impl Graph {
             let results: Vec<Result<(NodeId, Output), tokio::task::JoinError>> =
                 futures::future::join_all(tasks).await;
 
+            if self.cancel_token.is_cancelled() {
+                return Err(self.cancelled());
+            }
+
             // Check for errors immediately
             let errors_guard = errors.lock().await;
             if !errors_guard.is_empty() {
@@ -1504,6 +1777,16 @@ impl Graph {
         Ok(())
     }
 
+    /// Ends a cancelled execution: emits `GraphCancelled` and marks the graph
+    /// inactive.
+    fn cancelled(&self) -> GraphError {
+        info!("Graph execution cancelled");
+        let _ = self.event_sender.send(GraphEvent::GraphCancelled);
+        self.is_active
+            .store(false, std::sync::atomic::Ordering::Relaxed);
+        GraphError::Cancelled
+    }
+
     #[allow(clippy::too_many_arguments)]
     fn handle_flow_control(
         &self,
@@ -1721,6 +2004,43 @@ impl Graph {
             .collect()
     }
 
+    /// Takes a snapshot of the nodes and edges of the graph, to be rendered with
+    /// [`Topology::to_dot`] or [`Topology::to_mermaid`].
+    ///
+    /// Edges to and from a loop subgraph are drawn to every node of the
+    /// subgraph. This method is async because it needs to acquire locks on
+    /// nodes, so take the snapshot before starting the graph.
+    pub async fn topology(&self) -> Topology {
+        let mut nodes = Vec::with_capacity(self.nodes.len());
+        for (id, node) in &self.nodes {
+            let node = node.lock().await;
+            nodes.push(TopologyNode {
+                id: *id,
+                name: node.name(),
+                is_condition: node.is_condition(),
+            });
+        }
+        nodes.sort_by_key(|node| node.id);
+
+        let unfold = |id: NodeId| {
+            self.abstract_graph
+                .unfold_node(id)
+                .cloned()
+                .unwrap_or_else(|| vec![id])
+        };
+        let mut edges = Vec::new();
+        for (from_id, to_ids) in &self.abstract_graph.edges {
+            for from in unfold(*from_id) {
+                for to_id in to_ids {
+                    edges.extend(unfold(*to_id).into_iter().map(|to| (from, to)));
+                }
+            }
+        }
+        edges.sort();
+
+        Topology { nodes, edges }
+    }
+
     /// Before the dag starts executing, set the dag's global environment variable.
     pub fn set_env(&mut self, env: EnvVar) {
         self.env = Arc::new(env);
@@ -1736,6 +2056,27 @@ impl Graph {
     }
 }
 
+/// Runs one attempt of a node, failing it once it has taken longer than
+/// `timeout_ms`. Returns `None`, dropping the attempt, if `cancel` fires first.
+async fn run_attempt(
+    attempt: impl Future<Output = Output>,
+    timeout_ms: Option<u64>,
+    cancel: &CancellationToken,
+) -> Option<Output> {
+    let attempt = async {
+        match timeout_ms {
+            Some(ms) => tokio::time::timeout(Duration::from_millis(ms), attempt)
+                .await
+                .unwrap_or_else(|_| Output::Err(format!("Timed out after {}ms", ms))),
+            None => attempt.await,
+        }
+    };
+    tokio::select! {
+        out = attempt => Some(out),
+        _ = cancel.cancelled() => None,
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -1759,7 +2100,7 @@ mod tests {
 
     impl HelloAction {
         pub fn new() -> Self {
-            Self::default()
+            Self
         }
     }
 
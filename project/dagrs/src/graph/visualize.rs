@@ -0,0 +1,237 @@
+//! Graph visualization
+//!
+//! A [`Topology`] is a snapshot of the nodes and edges of a
+//! [`Graph`](super::Graph), taken with `Graph::topology()`. It renders to
+//! [Graphviz DOT](https://graphviz.org/doc/info/lang.html) and
+//! [Mermaid](https://mermaid.js.org/syntax/flowchart.html) flowcharts, with the
+//! nodes optionally coloured by their [`NodeStatus`].
+//!
+//! The statuses of a running graph are tracked by feeding the events of
+//! `Graph::subscribe()` into [`NodeStatuses`]:
+//!
+//! ```rust
+//! use dagrs::graph::visualize::NodeStatuses;
+//! use dagrs::{DefaultNode, EmptyAction, Graph, NodeTable};
+//!
+//! let mut node_table = NodeTable::new();
+//! let mut graph = Graph::new();
+//! graph.add_node(DefaultNode::with_action("a".to_string(), EmptyAction, &mut node_table));
+//!
+//! let runtime = tokio::runtime::Runtime::new().unwrap();
+//! runtime.block_on(async {
+//!     let topology = graph.topology().await;
+//!     let mut events = graph.subscribe();
+//!     graph.async_start().await.unwrap();
+//!
+//!     let mut statuses = NodeStatuses::default();
+//!     while let Ok(event) = events.try_recv() {
+//!         statuses.apply(&event);
+//!     }
+//!     println!("{}", topology.to_dot_with_status(&statuses));
+//! });
+//! ```
+
+use std::collections::HashMap;
+use std::fmt::Write;
+
+use crate::graph::event::GraphEvent;
+use crate::node::{NodeId, NodeName};
+
+/// Execution status of a node, as reported by [`GraphEvent`]s.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum NodeStatus {
+    /// Not started yet.
+    #[default]
+    Pending,
+    /// Running.
+    Running,
+    /// Waiting for a retry after a failed attempt.
+    Retrying,
+    /// Finished successfully.
+    Succeeded,
+    /// Failed after its last attempt.
+    Failed,
+    /// Skipped by a condition or router.
+    Skipped,
+    /// Aborted by the cancellation of the graph.
+    Cancelled,
+}
+
+impl NodeStatus {
+    /// Fill colour of the status in the rendered graphs.
+    pub fn color(&self) -> &'static str {
+        match self {
+            NodeStatus::Pending => "#ffffff",
+            NodeStatus::Running => "#87cefa",
+            NodeStatus::Retrying => "#ffd700",
+            NodeStatus::Succeeded => "#90ee90",
+            NodeStatus::Failed => "#f08080",
+            NodeStatus::Skipped => "#d3d3d3",
+            NodeStatus::Cancelled => "#ffa500",
+        }
+    }
+
+    fn as_str(&self) -> &'static str {
+        match self {
+            NodeStatus::Pending => "pending",
+            NodeStatus::Running => "running",
+            NodeStatus::Retrying => "retrying",
+            NodeStatus::Succeeded => "succeeded",
+            NodeStatus::Failed => "failed",
+            NodeStatus::Skipped => "skipped",
+            NodeStatus::Cancelled => "cancelled",
+        }
+    }
+
+    const ALL: [NodeStatus; 7] = [
+        NodeStatus::Pending,
+        NodeStatus::Running,
+        NodeStatus::Retrying,
+        NodeStatus::Succeeded,
+        NodeStatus::Failed,
+        NodeStatus::Skipped,
+        NodeStatus::Cancelled,
+    ];
+}
+
+/// The statuses of the nodes of a graph, updated from its [`GraphEvent`]s.
+///
+/// Nodes without events are [`NodeStatus::Pending`].
+#[derive(Debug, Clone, Default)]
+pub struct NodeStatuses(HashMap<NodeId, NodeStatus>);
+
+impl NodeStatuses {
+    /// Updates the statuses with `event`.
+    pub fn apply(&mut self, event: &GraphEvent) {
+        match event {
+            GraphEvent::NodeStart { id, .. } => {
+                self.0.insert(*id, NodeStatus::Running);
+            }
+            GraphEvent::NodeSuccess { id } => {
+                self.0.insert(*id, NodeStatus::Succeeded);
+            }
+            GraphEvent::NodeFailed { id, .. } => {
+                self.0.insert(*id, NodeStatus::Failed);
+            }
+            GraphEvent::NodeSkipped { id } => {
+                self.0.insert(*id, NodeStatus::Skipped);
+            }
+            GraphEvent::NodeRetry { id, .. } => {
+                self.0.insert(*id, NodeStatus::Retrying);
+            }
+            GraphEvent::GraphCancelled => {
+                for status in self.0.values_mut() {
+                    if matches!(status, NodeStatus::Running | NodeStatus::Retrying) {
+                        *status = NodeStatus::Cancelled;
+                    }
+                }
+            }
+            _ => {}
+        }
+    }
+
+    /// The status of the node `id`.
+    pub fn get(&self, id: NodeId) -> NodeStatus {
+        self.0.get(&id).copied().unwrap_or_default()
+    }
+}
+
+/// A node of a [`Topology`].
+#[derive(Debug, Clone)]
+pub struct TopologyNode {
+    pub id: NodeId,
+    pub name: NodeName,
+    /// Whether the node is a conditional or router node, drawn as a diamond.
+    pub is_condition: bool,
+}
+
+/// The structure of a graph: its nodes and edges, sorted by [`NodeId`].
+#[derive(Debug, Clone, Default)]
+pub struct Topology {
+    pub nodes: Vec<TopologyNode>,
+    pub edges: Vec<(NodeId, NodeId)>,
+}
+
+impl Topology {
+    /// Renders the graph in Graphviz DOT.
+    pub fn to_dot(&self) -> String {
+        self.to_dot_with_status(&NodeStatuses::default())
+    }
+
+    /// Renders the graph in Graphviz DOT, filling the nodes with the colour of
+    /// their status.
+    pub fn to_dot_with_status(&self, statuses: &NodeStatuses) -> String {
+        let mut dot = String::from("digraph dagrs {\n");
+        dot.push_str("    node [shape=box, style=\"rounded,filled\"];\n");
+        for node in &self.nodes {
+            let status = statuses.get(node.id);
+            let shape = if node.is_condition {
+                ", shape=diamond"
+            } else {
+                ""
+            };
+            let _ = writeln!(
+                dot,
+                "    n{} [label=\"{}\", fillcolor=\"{}\", tooltip=\"{}\"{}];",
+                node.id.as_usize(),
+                escape_dot(&node.name),
+                status.color(),
+                status.as_str(),
+                shape
+            );
+        }
+        for (from, to) in &self.edges {
+            let _ = writeln!(dot, "    n{} -> n{};", from.as_usize(), to.as_usize());
+        }
+        dot.push_str("}\n");
+        dot
+    }
+
+    /// Renders the graph as a Mermaid flowchart.
+    pub fn to_mermaid(&self) -> String {
+        self.to_mermaid_with_status(&NodeStatuses::default())
+    }
+
+    /// Renders the graph as a Mermaid flowchart, filling the nodes with the
+    /// colour of their status.
+    pub fn to_mermaid_with_status(&self, statuses: &NodeStatuses) -> String {
+        let mut mermaid = String::from("flowchart TD\n");
+        for node in &self.nodes {
+            let name = escape_mermaid(&node.name);
+            let _ = if node.is_condition {
+                writeln!(mermaid, "    n{}{{\"{}\"}}", node.id.as_usize(), name)
+            } else {
+                writeln!(mermaid, "    n{}[\"{}\"]", node.id.as_usize(), name)
+            };
+        }
+        for (from, to) in &self.edges {
+            let _ = writeln!(mermaid, "    n{} --> n{}", from.as_usize(), to.as_usize());
+        }
+        for status in NodeStatus::ALL {
+            let ids: Vec<String> = self
+                .nodes
+                .iter()
+                .filter(|node| statuses.get(node.id) == status)
+                .map(|node| format!("n{}", node.id.as_usize()))
+                .collect();
+            if !ids.is_empty() {
+                let _ = writeln!(
+                    mermaid,
+                    "    classDef {} fill:{};",
+                    status.as_str(),
+                    status.color()
+                );
+                let _ = writeln!(mermaid, "    class {} {};", ids.join(","), status.as_str());
+            }
+        }
+        mermaid
+    }
+}
+
+fn escape_dot(name: &str) -> String {
+    name.replace('\\', "\\\\").replace('"', "\\\"")
+}
+
+fn escape_mermaid(name: &str) -> String {
+    name.replace('"', "#quot;")
+}
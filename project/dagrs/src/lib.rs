@@ -1,6 +1,8 @@
 pub mod connection;
 pub mod graph;
 pub mod node;
+#[cfg(feature = "yaml")]
+pub mod parser;
 pub mod utils;
 
 pub use connection::{
@@ -15,8 +17,10 @@ pub use graph::*;
 pub use tokio;
 pub use utils::checkpoint::{
     Checkpoint, CheckpointConfig, CheckpointError, CheckpointId, CheckpointStore,
-    FileCheckpointStore, MemoryCheckpointStore, NodeState,
+    FileCheckpointStore, MemoryCheckpointStore, NodeState, RetentionPolicy,
 };
+#[cfg(feature = "sqlite")]
+pub use utils::sqlite_checkpoint::SqliteCheckpointStore;
 pub use utils::{env::EnvVar, output::Output};
 
 #[cfg(feature = "derive")]
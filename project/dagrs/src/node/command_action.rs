@@ -0,0 +1,112 @@
+use std::sync::Arc;
+
+use async_trait::async_trait;
+use tokio::process::Command;
+
+use crate::{
+    connection::{in_channel::InChannels, information_packet::Content, out_channel::OutChannels},
+    utils::{env::EnvVar, output::Output},
+};
+
+use super::action::Action;
+
+/// # Command action
+///
+/// [`CommandAction`] runs an operating system command. The strings received on
+/// the input channels are appended to its arguments.
+///
+/// The output of a run is a `(Vec<String>, Vec<String>)` holding the lines of
+/// stdout and stderr, which is also broadcast to the output channels. A command
+/// exiting with a non-zero status fails the node with its exit code.
+///
+/// On Windows the command line is run through `powershell -Command`.
+///
+/// ```rust
+/// use dagrs::{CommandAction, DefaultNode, NodeTable};
+///
+/// let mut node_table = NodeTable::new();
+/// let node = DefaultNode::with_action(
+///     "list".to_string(),
+///     CommandAction::new("ls", vec!["-l".to_string()]),
+///     &mut node_table,
+/// );
+/// ```
+pub struct CommandAction {
+    command: String,
+    args: Vec<String>,
+}
+
+impl CommandAction {
+    /// Creates a [`CommandAction`] running `cmd` with `args`.
+    pub fn new(cmd: &str, args: Vec<String>) -> Self {
+        Self {
+            command: cmd.to_owned(),
+            args,
+        }
+    }
+}
+
+#[async_trait]
+impl Action for CommandAction {
+    async fn run(
+        &self,
+        in_channels: &mut InChannels,
+        out_channels: &mut OutChannels,
+        _: Arc<EnvVar>,
+    ) -> Output {
+        let mut args = Vec::new();
+        let mut cmd = if cfg!(target_os = "windows") {
+            args.push("-Command".to_string());
+            args.push(self.command.clone());
+            Command::new("powershell")
+        } else {
+            Command::new(&self.command)
+        };
+
+        let inputs: Vec<String> = in_channels
+            .map(|input| input.ok().and_then(|input| input.get::<String>().cloned()))
+            .await
+            .into_iter()
+            .flatten()
+            .collect();
+        args.extend(self.args.iter().cloned());
+        args.extend(inputs);
+
+        log::info!("cmd: {:?}, args: {:?}", self.command, args);
+
+        let out = match cmd.args(args).output().await {
+            Ok(o) => o,
+            Err(e) => {
+                out_channels.broadcast(Content::new(e.raw_os_error())).await;
+                return Output::error_with_exit_code(
+                    e.raw_os_error(),
+                    Some(Content::new(e.to_string())),
+                );
+            }
+        };
+        let stdout = lines(out.stdout);
+        let stderr = lines(out.stderr);
+        out_channels
+            .broadcast(Content::new((stdout.clone(), stderr.clone())))
+            .await;
+        if out.status.success() {
+            Output::new((stdout, stderr))
+        } else {
+            let code = out.status.code().unwrap_or(0);
+            Output::error_with_exit_code(Some(code), Some(Content::new((stdout, stderr))))
+        }
+    }
+}
+
+/// Splits the output of a command into lines.
+fn lines(output: Vec<u8>) -> Vec<String> {
+    let output = String::from_utf8(output).unwrap_or_default();
+    if cfg!(target_os = "windows") {
+        output
+            .rsplit_terminator("\r\n")
+            .map(str::to_string)
+            .collect()
+    } else {
+        output.split_terminator('\n').map(str::to_string).collect()
+    }
+}
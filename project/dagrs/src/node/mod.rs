@@ -1,4 +1,5 @@
 pub mod action;
+pub mod command_action;
 pub mod conditional_node;
 pub mod default_node;
 pub mod id_allocate;
@@ -8,6 +9,7 @@ pub mod router_node;
 pub mod typed_action;
 
 pub use action::{Action, EmptyAction};
+pub use command_action::CommandAction;
 pub use conditional_node::ConditionalNode;
 pub use default_node::DefaultNode;
 pub use loop_node::{LoopCondition, LoopNode};
@@ -105,6 +107,17 @@ pub trait Node: Send + Sync {
         100
     }
 
+    /// Returns the time limit of a single run of this node in milliseconds.
+    ///
+    /// A run that takes longer is aborted and treated as a failure, so it is
+    /// retried like any other failed attempt if [`Node::max_retries`] allows.
+    ///
+    /// # Default
+    /// Returns `None` by default, meaning runs are not time-limited.
+    fn timeout_ms(&self) -> Option<u64> {
+        None
+    }
+
     /// Reset the node state to its initial state.
     ///
     /// # Behavior
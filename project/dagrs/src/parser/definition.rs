@@ -0,0 +1,90 @@
+//! The document structure of a graph definition.
+
+use std::collections::BTreeMap;
+
+use serde::Deserialize;
+
+use super::Params;
+
+/// A whole graph definition.
+#[derive(Debug, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub(crate) struct GraphDef {
+    /// Environment variables by name.
+    #[serde(default)]
+    pub(crate) env: BTreeMap<String, Params>,
+    /// The nodes by their identifier in the document.
+    pub(crate) dagrs: BTreeMap<String, NodeDef>,
+}
+
+/// The kind of a node, selecting the node type it is built as.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub(crate) enum NodeKind {
+    /// A `DefaultNode` running an action.
+    #[default]
+    Action,
+    /// A `ConditionalNode`.
+    Condition,
+    /// A `RouterNode`.
+    Router,
+    /// A `LoopNode`.
+    Loop,
+}
+
+impl NodeKind {
+    pub(crate) fn as_str(&self) -> &'static str {
+        match self {
+            NodeKind::Action => "action",
+            NodeKind::Condition => "condition",
+            NodeKind::Router => "router",
+            NodeKind::Loop => "loop",
+        }
+    }
+}
+
+/// A command line, either split at whitespace or given as a list.
+#[derive(Debug, Deserialize)]
+#[serde(untagged)]
+pub(crate) enum CommandDef {
+    Line(String),
+    Args(Vec<String>),
+}
+
+impl CommandDef {
+    /// The program and its arguments.
+    pub(crate) fn split(&self) -> Vec<String> {
+        match self {
+            CommandDef::Line(line) => line.split_whitespace().map(str::to_string).collect(),
+            CommandDef::Args(args) => args.clone(),
+        }
+    }
+}
+
+/// One node of a graph definition.
+#[derive(Debug, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub(crate) struct NodeDef {
+    /// Node name, defaults to the identifier of the node.
+    pub(crate) name: Option<String>,
+    #[serde(default)]
+    pub(crate) kind: NodeKind,
+    /// Identifiers of the nodes this one runs after.
+    #[serde(default)]
+    pub(crate) after: Vec<String>,
+    /// Command run by the built-in command action.
+    pub(crate) cmd: Option<CommandDef>,
+    /// Registered action of an action node.
+    pub(crate) action: Option<String>,
+    /// Registered condition of a condition or loop node.
+    pub(crate) condition: Option<String>,
+    /// Registered router of a router node.
+    pub(crate) router: Option<String>,
+    /// Identifier of the node a loop node jumps back to.
+    pub(crate) target: Option<String>,
+    /// Number of times a loop node jumps back.
+    pub(crate) max_iterations: Option<usize>,
+    /// Passed to the factory of the action, condition or router.
+    #[serde(default)]
+    pub(crate) params: Params,
+}
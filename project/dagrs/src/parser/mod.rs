@@ -0,0 +1,471 @@
+//! Declarative graph definitions
+//!
+//! # Graph definition files
+//!
+//! A [`Graph`] can be described in a YAML or JSON document instead of code.
+//! Nodes are listed under `dagrs` by an identifier, and environment variables
+//! under `env`:
+//!
+//! ```yaml
+//! env:
+//!   threshold: 10
+//! dagrs:
+//!   fetch:
+//!     name: "Fetch data"          # defaults to the identifier
+//!     cmd: curl -sO https://example.com/data.csv
+//!   check:
+//!     kind: condition
+//!     after: [ fetch ]
+//!     condition: file_exists      # registered condition
+//!     params: { path: data.csv }
+//!   train:
+//!     after: [ check ]
+//!     action: train_model         # registered action
+//!     params: { epochs: 3 }
+//!   again:
+//!     kind: loop
+//!     after: [ train ]
+//!     target: train
+//!     max_iterations: 2
+//! ```
+//!
+//! A node is one of the following kinds:
+//! - `action` (the default): a [`DefaultNode`](crate::DefaultNode) running either
+//!   the command line `cmd` (a string split at whitespace, or a list) through the
+//!   built-in [`CommandAction`](crate::CommandAction), or the registered
+//!   `action` built from `params`.
+//! - `condition`: a [`ConditionalNode`](crate::ConditionalNode) with the
+//!   registered `condition`.
+//! - `router`: a [`RouterNode`](crate::RouterNode) with the registered `router`.
+//! - `loop`: a [`LoopNode`](crate::LoopNode) jumping back to the node `target`,
+//!   either `max_iterations` times or while the registered loop `condition`
+//!   holds.
+//!
+//! `after` lists the nodes a node depends on. The logic of the nodes is looked
+//! up by name in a [`Registry`]. String, integer, float and boolean variables
+//! in `env` are stored as `String`, `i64`, `f64` and `bool`, other values as a
+//! `serde_json::Value`.
+//!
+//! A definition that does not describe a valid graph is rejected with a
+//! [`ParseError`] pointing at the line of the offending node or field. Cycles
+//! are detected when the graph is started, as for graphs built in code.
+//!
+//! # Example
+//!
+//! ```rust
+//! use dagrs::parser::{self, Registry};
+//! use dagrs::EmptyAction;
+//!
+//! let mut registry = Registry::new();
+//! registry.register_action("noop", |_| Ok(EmptyAction));
+//!
+//! let (mut graph, _env) = parser::from_yaml_str(
+//!     "dagrs:\n  a:\n    action: noop\n  b:\n    after: [ a ]\n    action: noop\n",
+//!     &registry,
+//! )
+//! .unwrap();
+//! graph.start().unwrap();
+//! ```
+//!
+//! This module requires the `yaml` feature.
+
+mod definition;
+mod registry;
+
+pub use registry::{COMMAND_ACTION, Registry};
+
+use std::collections::{BTreeMap, HashMap};
+use std::fmt;
+use std::path::Path;
+
+use crate::{
+    EnvVar, Graph,
+    node::{LoopNode, NODE_TABLE_STR, Node, NodeId, NodeTable, loop_node::CountLoopCondition},
+};
+
+use definition::{GraphDef, NodeDef, NodeKind};
+
+/// Parameters of a node, handed to the factories of a [`Registry`].
+pub type Params = serde_json::Value;
+
+/// An invalid graph definition.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ParseError {
+    /// The line (1-based) of the definition the error is about, if known.
+    pub line: Option<usize>,
+    /// What is wrong.
+    pub message: String,
+}
+
+impl ParseError {
+    fn new(line: Option<usize>, message: impl Into<String>) -> Self {
+        Self {
+            line,
+            message: message.into(),
+        }
+    }
+}
+
+impl fmt::Display for ParseError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self.line {
+            Some(line) => write!(f, "line {}: {}", line, self.message),
+            None => write!(f, "{}", self.message),
+        }
+    }
+}
+
+impl std::error::Error for ParseError {}
+
+/// Builds a graph from a YAML definition.
+///
+/// Returns the graph along with its environment, which is already set on the
+/// graph; variables added later have to be set again with
+/// [`Graph::set_env`]. Like [`Graph::add_edge`], this must not be called from
+/// within an async runtime.
+pub fn from_yaml_str(content: &str, registry: &Registry) -> Result<(Graph, EnvVar), ParseError> {
+    let def: GraphDef = serde_yaml::from_str(content).map_err(|e| {
+        ParseError::new(
+            e.location().map(|location| location.line()),
+            strip_location(&e.to_string()),
+        )
+    })?;
+    build(def, content, registry)
+}
+
+/// Builds a graph from a JSON definition, see [`from_yaml_str`].
+pub fn from_json_str(content: &str, registry: &Registry) -> Result<(Graph, EnvVar), ParseError> {
+    let def: GraphDef = serde_json::from_str(content).map_err(|e| {
+        ParseError::new(
+            Some(e.line()).filter(|line| *line > 0),
+            strip_location(&e.to_string()),
+        )
+    })?;
+    build(def, content, registry)
+}
+
+/// Builds a graph from a definition file, read as JSON if its extension is
+/// `.json` and as YAML otherwise, see [`from_yaml_str`].
+pub fn from_file(
+    path: impl AsRef<Path>,
+    registry: &Registry,
+) -> Result<(Graph, EnvVar), ParseError> {
+    let path = path.as_ref();
+    let content = std::fs::read_to_string(path)
+        .map_err(|e| ParseError::new(None, format!("cannot read {}: {}", path.display(), e)))?;
+    if path.extension().is_some_and(|ext| ext == "json") {
+        from_json_str(&content, registry)
+    } else {
+        from_yaml_str(&content, registry)
+    }
+}
+
+fn build(def: GraphDef, source: &str, registry: &Registry) -> Result<(Graph, EnvVar), ParseError> {
+    let locator = Locator::new(source);
+    let node_err = |id: &str, message: String| {
+        ParseError::new(locator.node(id), format!("node `{}`: {}", id, message))
+    };
+    let field_err = |id: &str, field: &str, message: String| {
+        ParseError::new(
+            locator.field(id, field),
+            format!("node `{}`: {}", id, message),
+        )
+    };
+
+    let mut names: HashMap<String, &str> = HashMap::new();
+    for (id, node) in &def.dagrs {
+        let name = node.name.clone().unwrap_or_else(|| id.clone());
+        if let Some(other) = names.insert(name.clone(), id) {
+            return Err(node_err(
+                id,
+                format!("name `{}` is already used by node `{}`", name, other),
+            ));
+        }
+        check_fields(node).map_err(|(field, message)| field_err(id, field, message))?;
+    }
+
+    let mut node_table = NodeTable::new();
+    let mut graph = Graph::new();
+    let mut ids: HashMap<&str, NodeId> = HashMap::new();
+    // Loop nodes come last, they need the id of their target.
+    let (loops, others): (Vec<_>, Vec<_>) = def
+        .dagrs
+        .iter()
+        .partition(|(_, node)| node.kind == NodeKind::Loop);
+    for (id, node) in others.into_iter().chain(loops) {
+        let name = node.name.clone().unwrap_or_else(|| id.clone());
+        let node_id = match node.kind {
+            NodeKind::Action => {
+                let (factory, params, field) = match (&node.cmd, &node.action) {
+                    (Some(cmd), None) => {
+                        let mut args = cmd.split();
+                        if args.is_empty() {
+                            return Err(field_err(id, "cmd", "`cmd` is empty".to_string()));
+                        }
+                        let cmd = args.remove(0);
+                        let params = serde_json::json!({ "cmd": cmd, "args": args });
+                        (COMMAND_ACTION, params, "cmd")
+                    }
+                    (None, Some(action)) => (action.as_str(), node.params.clone(), "action"),
+                    (Some(_), Some(_)) => {
+                        return Err(field_err(
+                            id,
+                            "action",
+                            "`cmd` and `action` cannot be used together".to_string(),
+                        ));
+                    }
+                    (None, None) => {
+                        return Err(node_err(id, "needs `cmd` or `action`".to_string()));
+                    }
+                };
+                let make = registry
+                    .action(factory)
+                    .ok_or_else(|| field_err(id, field, format!("unknown action `{}`", factory)))?;
+                let node =
+                    make(name, &params, &mut node_table).map_err(|e| field_err(id, field, e))?;
+                let node_id = node.id();
+                graph.add_node(node);
+                node_id
+            }
+            NodeKind::Condition => {
+                let condition =
+                    required(&node.condition, "condition").map_err(|e| node_err(id, e))?;
+                let make = registry.condition(condition).ok_or_else(|| {
+                    field_err(
+                        id,
+                        "condition",
+                        format!("unknown condition `{}`", condition),
+                    )
+                })?;
+                let node = make(name, &node.params, &mut node_table)
+                    .map_err(|e| field_err(id, "condition", e))?;
+                let node_id = node.id();
+                graph.add_node(node);
+                node_id
+            }
+            NodeKind::Router => {
+                let router = required(&node.router, "router").map_err(|e| node_err(id, e))?;
+                let make = registry.router(router).ok_or_else(|| {
+                    field_err(id, "router", format!("unknown router `{}`", router))
+                })?;
+                let node = make(name, &node.params, &mut node_table)
+                    .map_err(|e| field_err(id, "router", e))?;
+                let node_id = node.id();
+                graph.add_node(node);
+                node_id
+            }
+            NodeKind::Loop => {
+                let target = required(&node.target, "target").map_err(|e| node_err(id, e))?;
+                let target_id = match def.dagrs.get(target) {
+                    Some(target) if target.kind == NodeKind::Loop => {
+                        return Err(field_err(
+                            id,
+                            "target",
+                            "the target cannot be a loop node".to_string(),
+                        ));
+                    }
+                    Some(_) => ids[target],
+                    None => {
+                        return Err(field_err(
+                            id,
+                            "target",
+                            format!("unknown target `{}`", target),
+                        ));
+                    }
+                };
+                let node = match (node.max_iterations, &node.condition) {
+                    (Some(max), None) => LoopNode::new(
+                        name,
+                        target_id,
+                        CountLoopCondition::new(max),
+                        &mut node_table,
+                    ),
+                    (None, Some(condition)) => {
+                        let make = registry.loop_condition(condition).ok_or_else(|| {
+                            field_err(
+                                id,
+                                "condition",
+                                format!("unknown loop condition `{}`", condition),
+                            )
+                        })?;
+                        make(name, target_id, &node.params, &mut node_table)
+                            .map_err(|e| field_err(id, "condition", e))?
+                    }
+                    _ => {
+                        return Err(node_err(
+                            id,
+                            "needs either `max_iterations` or `condition`".to_string(),
+                        ));
+                    }
+                };
+                let node_id = node.id();
+                graph.add_node(node);
+                node_id
+            }
+        };
+        ids.insert(id.as_str(), node_id);
+    }
+
+    let mut edges: BTreeMap<NodeId, Vec<NodeId>> = BTreeMap::new();
+    for (id, node) in &def.dagrs {
+        for pre in &node.after {
+            let from = ids.get(pre.as_str()).ok_or_else(|| {
+                field_err(id, "after", format!("unknown node `{}` in `after`", pre))
+            })?;
+            edges.entry(*from).or_default().push(ids[id.as_str()]);
+        }
+    }
+    for (from, to) in edges {
+        graph.add_edge(from, to);
+    }
+
+    let mut env = EnvVar::new(node_table);
+    for (name, value) in &def.env {
+        if name == NODE_TABLE_STR {
+            return Err(ParseError::new(
+                locator.env(name),
+                format!("`{}` is a reserved environment variable", name),
+            ));
+        }
+        set_env_var(&mut env, name, value);
+    }
+    graph.set_env(env.clone());
+    Ok((graph, env))
+}
+
+/// Checks that `node` only has the fields of its kind, returning the first
+/// unexpected one with the reason.
+fn check_fields(node: &NodeDef) -> Result<(), (&'static str, String)> {
+    let allowed: &[&str] = match node.kind {
+        NodeKind::Action => &["cmd", "action"],
+        NodeKind::Condition => &["condition"],
+        NodeKind::Router => &["router"],
+        NodeKind::Loop => &["target", "max_iterations", "condition"],
+    };
+    let present = [
+        ("cmd", node.cmd.is_some()),
+        ("action", node.action.is_some()),
+        ("condition", node.condition.is_some()),
+        ("router", node.router.is_some()),
+        ("target", node.target.is_some()),
+        ("max_iterations", node.max_iterations.is_some()),
+    ];
+    for (field, is_present) in present {
+        if is_present && !allowed.contains(&field) {
+            return Err((
+                field,
+                format!("`{}` is not allowed on {} nodes", field, node.kind.as_str()),
+            ));
+        }
+    }
+    // `params` go to a registered factory, which a command line or a counted
+    // loop do not have.
+    for (field, is_present) in [
+        ("cmd", node.cmd.is_some()),
+        ("max_iterations", node.max_iterations.is_some()),
+    ] {
+        if is_present && !node.params.is_null() {
+            return Err((
+                "params",
+                format!("`params` cannot be used with `{}`", field),
+            ));
+        }
+    }
+    Ok(())
+}
+
+fn required<'a>(value: &'a Option<String>, field: &str) -> Result<&'a str, String> {
+    value.as_deref().ok_or_else(|| format!("needs `{}`", field))
+}
+
+fn set_env_var(env: &mut EnvVar, name: &str, value: &Params) {
+    match value {
+        Params::String(s) => env.set(name, s.clone()),
+        Params::Bool(b) => env.set(name, *b),
+        Params::Number(n) => match n.as_i64() {
+            Some(i) => env.set(name, i),
+            None => env.set(name, n.as_f64().unwrap_or_default()),
+        },
+        other => env.set(name, other.clone()),
+    }
+}
+
+/// Removes the ` at line X column Y` suffix of serde errors, the line is kept
+/// separately.
+fn strip_location(message: &str) -> String {
+    match message.rfind(" at line ") {
+        Some(at) => message[..at].to_string(),
+        None => message.to_string(),
+    }
+}
+
+/// Finds the lines of nodes and fields in the source of a definition.
+///
+/// The documents are not re-parsed with positions; keys are looked up in the
+/// text instead, which is precise for the usual one key per line layout.
+struct Locator<'a> {
+    lines: Vec<&'a str>,
+}
+
+impl<'a> Locator<'a> {
+    fn new(source: &'a str) -> Self {
+        Self {
+            lines: source.lines().collect(),
+        }
+    }
+
+    /// The first line from `from` (1-based) on holding the mapping key `key`.
+    fn key(&self, key: &str, from: usize) -> Option<usize> {
+        self.lines
+            .iter()
+            .enumerate()
+            .skip(from.saturating_sub(1))
+            .find(|(_, line)| is_key(line, key))
+            .map(|(i, _)| i + 1)
+    }
+
+    fn node(&self, id: &str) -> Option<usize> {
+        let root = self.key("dagrs", 1)?;
+        self.key(id, root)
+    }
+
+    /// The line of `field` of node `id`, or of the node when it lacks the field.
+    fn field(&self, id: &str, field: &str) -> Option<usize> {
+        let node = self.node(id)?;
+        let next = self
+            .lines
+            .iter()
+            .enumerate()
+            .skip(node)
+            .find(|(_, line)| {
+                indent(line) <= indent(self.lines[node - 1]) && !line.trim().is_empty()
+            })
+            .map_or(self.lines.len() + 1, |(i, _)| i + 1);
+        self.key(field, node)
+            .filter(|line| *line < next || *line == node)
+            .or(Some(node))
+    }
+
+    fn env(&self, name: &str) -> Option<usize> {
+        let env = self.key("env", 1)?;
+        self.key(name, env)
+    }
+}
+
+fn indent(line: &str) -> usize {
+    line.len() - line.trim_start().len()
+}
+
+/// Whether `line` holds `key` as a plain or quoted mapping key.
+fn is_key(line: &str, key: &str) -> bool {
+    let followed_by_colon = |rest: &str| rest.trim_start().starts_with(':');
+    let plain = line.trim_start().trim_start_matches("- ");
+    if plain.strip_prefix(key).is_some_and(followed_by_colon) {
+        return true;
+    }
+    ['"', '\''].iter().any(|quote| {
+        let quoted = format!("{}{}{}", quote, key, quote);
+        line.match_indices(&quoted)
+            .any(|(i, _)| followed_by_colon(&line[i + quoted.len()..]))
+    })
+}
@@ -0,0 +1,186 @@
+use std::collections::HashMap;
+
+use crate::node::{
+    Action, CommandAction, ConditionalNode, DefaultNode, LoopNode, NodeId, NodeName, NodeTable,
+    RouterNode, conditional_node::Condition, loop_node::LoopCondition, router_node::Router,
+};
+
+use super::Params;
+
+/// Name of the built-in [`CommandAction`] factory.
+///
+/// Its `params` are `{ cmd: <program>, args: [<argument>, ...] }`; the `cmd`
+/// field of a node is a shorthand for it.
+pub const COMMAND_ACTION: &str = "command";
+
+type ActionFactory =
+    Box<dyn Fn(NodeName, &Params, &mut NodeTable) -> Result<DefaultNode, String> + Send + Sync>;
+type ConditionFactory =
+    Box<dyn Fn(NodeName, &Params, &mut NodeTable) -> Result<ConditionalNode, String> + Send + Sync>;
+type RouterFactory =
+    Box<dyn Fn(NodeName, &Params, &mut NodeTable) -> Result<RouterNode, String> + Send + Sync>;
+type LoopFactory = Box<
+    dyn Fn(NodeName, NodeId, &Params, &mut NodeTable) -> Result<LoopNode, String> + Send + Sync,
+>;
+
+/// # Registry of named node logic
+///
+/// A graph definition refers to the logic of its nodes by name. The [`Registry`]
+/// maps these names to factories building the logic from the `params` of a
+/// node, so one factory can serve many nodes. A factory returns an error
+/// message when the `params` do not suit it.
+///
+/// [`Registry::new`] comes with the built-in [`COMMAND_ACTION`].
+///
+/// ```rust
+/// use dagrs::parser::Registry;
+/// use dagrs::{CommandAction, EmptyAction};
+///
+/// let mut registry = Registry::new();
+/// registry
+///     .register_action("noop", |_| Ok(EmptyAction))
+///     .register_action("greet", |params| {
+///         let who = params["who"].as_str().ok_or("`who` must be a string")?;
+///         Ok(CommandAction::new("echo", vec![format!("hello {}", who)]))
+///     });
+/// ```
+pub struct Registry {
+    actions: HashMap<String, ActionFactory>,
+    conditions: HashMap<String, ConditionFactory>,
+    routers: HashMap<String, RouterFactory>,
+    loop_conditions: HashMap<String, LoopFactory>,
+}
+
+impl Default for Registry {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Registry {
+    /// Creates a registry holding the built-in [`COMMAND_ACTION`].
+    pub fn new() -> Self {
+        let mut registry = Self {
+            actions: HashMap::new(),
+            conditions: HashMap::new(),
+            routers: HashMap::new(),
+            loop_conditions: HashMap::new(),
+        };
+        registry.register_action(COMMAND_ACTION, command_action);
+        registry
+    }
+
+    /// Registers a factory of [`Action`]s for plain nodes (`action: <name>`).
+    /// A factory registered under an existing name replaces it.
+    pub fn register_action<A, F>(&mut self, name: &str, factory: F) -> &mut Self
+    where
+        A: Action + 'static,
+        F: Fn(&Params) -> Result<A, String> + Send + Sync + 'static,
+    {
+        self.actions.insert(
+            name.to_string(),
+            Box::new(move |node_name, params, node_table| {
+                Ok(DefaultNode::with_action(
+                    node_name,
+                    factory(params)?,
+                    node_table,
+                ))
+            }),
+        );
+        self
+    }
+
+    /// Registers a factory of [`Condition`]s for conditional nodes
+    /// (`kind: condition`, `condition: <name>`).
+    pub fn register_condition<C, F>(&mut self, name: &str, factory: F) -> &mut Self
+    where
+        C: Condition + 'static,
+        F: Fn(&Params) -> Result<C, String> + Send + Sync + 'static,
+    {
+        self.conditions.insert(
+            name.to_string(),
+            Box::new(move |node_name, params, node_table| {
+                Ok(ConditionalNode::with_condition(
+                    node_name,
+                    factory(params)?,
+                    node_table,
+                ))
+            }),
+        );
+        self
+    }
+
+    /// Registers a factory of [`Router`]s for router nodes (`kind: router`,
+    /// `router: <name>`).
+    pub fn register_router<R, F>(&mut self, name: &str, factory: F) -> &mut Self
+    where
+        R: Router + 'static,
+        F: Fn(&Params) -> Result<R, String> + Send + Sync + 'static,
+    {
+        self.routers.insert(
+            name.to_string(),
+            Box::new(move |node_name, params, node_table| {
+                Ok(RouterNode::new(node_name, factory(params)?, node_table))
+            }),
+        );
+        self
+    }
+
+    /// Registers a factory of [`LoopCondition`]s for loop nodes (`kind: loop`,
+    /// `condition: <name>`).
+    pub fn register_loop_condition<L, F>(&mut self, name: &str, factory: F) -> &mut Self
+    where
+        L: LoopCondition + 'static,
+        F: Fn(&Params) -> Result<L, String> + Send + Sync + 'static,
+    {
+        self.loop_conditions.insert(
+            name.to_string(),
+            Box::new(move |node_name, target, params, node_table| {
+                Ok(LoopNode::new(
+                    node_name,
+                    target,
+                    factory(params)?,
+                    node_table,
+                ))
+            }),
+        );
+        self
+    }
+
+    pub(crate) fn action(&self, name: &str) -> Option<&ActionFactory> {
+        self.actions.get(name)
+    }
+
+    pub(crate) fn condition(&self, name: &str) -> Option<&ConditionFactory> {
+        self.conditions.get(name)
+    }
+
+    pub(crate) fn router(&self, name: &str) -> Option<&RouterFactory> {
+        self.routers.get(name)
+    }
+
+    pub(crate) fn loop_condition(&self, name: &str) -> Option<&LoopFactory> {
+        self.loop_conditions.get(name)
+    }
+}
+
+/// Factory of the built-in [`COMMAND_ACTION`].
+fn command_action(params: &Params) -> Result<CommandAction, String> {
+    let cmd = params["cmd"]
+        .as_str()
+        .filter(|cmd| !cmd.is_empty())
+        .ok_or("`cmd` must be a non-empty string")?;
+    let args = match &params["args"] {
+        Params::Null => Vec::new(),
+        Params::Array(args) => args
+            .iter()
+            .map(|arg| match arg {
+                Params::String(arg) => Ok(arg.clone()),
+                Params::Number(_) | Params::Bool(_) => Ok(arg.to_string()),
+                _ => Err("`args` must be a list of strings".to_string()),
+            })
+            .collect::<Result<_, _>>()?,
+        _ => return Err("`args` must be a list of strings".to_string()),
+    };
+    Ok(CommandAction::new(cmd, args))
+}
@@ -13,6 +13,18 @@
 //! - [`CheckpointStore`]: Trait for pluggable storage backends
 //! - [`MemoryCheckpointStore`]: In-memory storage (for testing)
 //! - [`FileCheckpointStore`]: File-based persistent storage
+//! - `SqliteCheckpointStore`: SQLite database storage (with the `sqlite` feature)
+//! - [`RetentionPolicy`]: Which checkpoints a store keeps
+//!
+//! # Automatic resume
+//!
+//! With [`CheckpointConfig::with_auto_resume`], `Graph::async_start()` continues
+//! the latest run that did not finish from its last checkpoint, e.g. after the
+//! process was killed. The restarted process must build the graph the same way:
+//! nodes are matched by name (or by ID when names are not unique), and the
+//! outputs of the nodes that completed before the checkpoint are sent again to
+//! their successors. Only outputs of the types listed in [`NodeState`] survive a
+//! restart.
 //!
 //! # Example
 //!
@@ -40,6 +52,7 @@ use std::time::{SystemTime, UNIX_EPOCH};
 
 use async_trait::async_trait;
 use serde::{Deserialize, Serialize};
+use tokio::io::AsyncWriteExt;
 
 use crate::node::NodeId;
 
@@ -60,9 +73,16 @@ pub struct NodeState {
     /// This field stores JSON-serialized output data for nodes that produce
     /// serializable output. The data can be restored when resuming from a checkpoint.
     ///
-    /// Note: Only outputs that implement `serde::Serialize` can be stored here.
-    /// For non-serializable outputs, this field will be `None`.
+    /// Note: Only outputs of type `String`, `i32`, `i64`, `u32`, `u64`, `f64`,
+    /// `bool`, `Vec<String>`, `Vec<i32>` or `Vec<i64>` are stored here.
+    /// For other outputs, this field will be `None`.
     pub output_data: Option<Vec<u8>>,
+    /// Name of the type of `output_data`, needed to restore it
+    #[serde(default)]
+    pub output_type: Option<String>,
+    /// Node name, used to match the node in a restarted process
+    #[serde(default)]
+    pub node_name: Option<String>,
     /// Human-readable output summary (for debugging)
     #[serde(default)]
     pub output_summary: Option<String>,
@@ -76,6 +96,8 @@ impl NodeState {
             completed: true,
             success,
             output_data: None,
+            output_type: None,
+            node_name: None,
             output_summary: None,
         }
     }
@@ -87,6 +109,8 @@ impl NodeState {
             completed: false,
             success: false,
             output_data: None,
+            output_type: None,
+            node_name: None,
             output_summary: None,
         }
     }
@@ -97,6 +121,18 @@ impl NodeState {
         self
     }
 
+    /// Set the name of the type of the output data
+    pub fn with_output_type(mut self, output_type: impl Into<String>) -> Self {
+        self.output_type = Some(output_type.into());
+        self
+    }
+
+    /// Set node name
+    pub fn with_name(mut self, name: impl Into<String>) -> Self {
+        self.node_name = Some(name.into());
+        self
+    }
+
     /// Set output summary
     pub fn with_summary(mut self, summary: impl Into<String>) -> Self {
         self.output_summary = Some(summary.into());
@@ -111,6 +147,10 @@ pub struct Checkpoint {
     pub id: CheckpointId,
     /// Timestamp when checkpoint was created (Unix epoch seconds)
     pub timestamp: u64,
+    /// Timestamp when checkpoint was created (Unix epoch microseconds), to
+    /// order the checkpoints created within the same second
+    #[serde(default)]
+    pub timestamp_micros: u64,
     /// Current program counter (block index)
     pub pc: usize,
     /// Number of loop iterations completed
@@ -123,49 +163,45 @@ pub struct Checkpoint {
     pub env_data: Option<Vec<u8>>,
     /// Custom metadata
     pub metadata: HashMap<String, String>,
+    /// Whether the run finished; the checkpoints of unfinished runs are
+    /// continued by automatic resume
+    #[serde(default)]
+    pub completed: bool,
 }
 
 impl Checkpoint {
     /// Create a new checkpoint with generated ID
     pub fn new(pc: usize, loop_count: usize) -> Self {
-        let timestamp = SystemTime::now()
-            .duration_since(UNIX_EPOCH)
-            .unwrap_or_default()
-            .as_secs();
-
-        let id = format!("ckpt_{}_{}", timestamp, pc);
-
-        Self {
-            id,
-            timestamp,
-            pc,
-            loop_count,
-            active_nodes: HashSet::new(),
-            node_states: HashMap::new(),
-            env_data: None,
-            metadata: HashMap::new(),
-        }
+        let mut checkpoint = Self::with_id("", pc, loop_count);
+        checkpoint.id = format!("ckpt_{}_{}", checkpoint.timestamp_micros, pc);
+        checkpoint
     }
 
     /// Create checkpoint with a specific ID
     pub fn with_id(id: impl Into<String>, pc: usize, loop_count: usize) -> Self {
-        let timestamp = SystemTime::now()
+        let now = SystemTime::now()
             .duration_since(UNIX_EPOCH)
-            .unwrap_or_default()
-            .as_secs();
+            .unwrap_or_default();
 
         Self {
             id: id.into(),
-            timestamp,
+            timestamp: now.as_secs(),
+            timestamp_micros: now.as_micros() as u64,
             pc,
             loop_count,
             active_nodes: HashSet::new(),
             node_states: HashMap::new(),
             env_data: None,
             metadata: HashMap::new(),
+            completed: false,
         }
     }
 
+    /// Key ordering checkpoints from the oldest to the latest
+    pub fn recency(&self) -> (u64, u64) {
+        (self.timestamp, self.timestamp_micros)
+    }
+
     /// Set active nodes from NodeId set
     pub fn set_active_nodes(&mut self, nodes: &HashSet<NodeId>) {
         self.active_nodes = nodes.iter().map(|id| id.0).collect();
@@ -243,6 +279,72 @@ pub trait CheckpointStore: Send + Sync {
 
     /// Clear all checkpoints
     async fn clear(&self) -> Result<(), CheckpointError>;
+
+    /// Delete the checkpoints not retained by `policy`, returning how many
+    /// were deleted
+    ///
+    /// The default implementation loads every checkpoint; stores that can
+    /// order checkpoints themselves should override it.
+    async fn prune(&self, policy: &RetentionPolicy) -> Result<usize, CheckpointError> {
+        let mut checkpoints = Vec::new();
+        for id in self.list().await? {
+            if let Ok(checkpoint) = self.load(&id).await {
+                checkpoints.push(checkpoint);
+            }
+        }
+        // Latest first
+        checkpoints.sort_by_key(|c| std::cmp::Reverse(c.recency()));
+
+        let now = now_secs();
+        let mut deleted = 0;
+        for (rank, checkpoint) in checkpoints.iter().enumerate() {
+            if !policy.retains(rank, checkpoint, now) {
+                self.delete(&checkpoint.id).await?;
+                deleted += 1;
+            }
+        }
+        Ok(deleted)
+    }
+}
+
+/// Which checkpoints a [`CheckpointStore`] keeps when pruned
+///
+/// The latest checkpoint is always kept, so that an unfinished run can be
+/// resumed however old it is.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct RetentionPolicy {
+    /// Maximum number of checkpoints to keep (0 = unlimited)
+    pub max_checkpoints: usize,
+    /// Maximum age of the checkpoints to keep, in seconds
+    pub max_age_seconds: Option<u64>,
+}
+
+impl RetentionPolicy {
+    /// Whether the policy limits the checkpoints kept at all
+    pub fn is_limited(&self) -> bool {
+        self.max_checkpoints > 0 || self.max_age_seconds.is_some()
+    }
+
+    /// Whether `checkpoint`, the `rank`-th latest one (from 0), is kept at
+    /// `now` (Unix epoch seconds)
+    pub fn retains(&self, rank: usize, checkpoint: &Checkpoint, now: u64) -> bool {
+        if rank == 0 {
+            return true;
+        }
+        if self.max_checkpoints > 0 && rank >= self.max_checkpoints {
+            return false;
+        }
+        self.max_age_seconds
+            .is_none_or(|max_age| checkpoint.timestamp >= now.saturating_sub(max_age))
+    }
+}
+
+/// Current time in Unix epoch seconds
+pub(crate) fn now_secs() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .unwrap_or_default()
+        .as_secs()
 }
 
 /// In-memory checkpoint store (for testing and temporary storage)
@@ -298,7 +400,7 @@ impl CheckpointStore for MemoryCheckpointStore {
         let store = self.checkpoints.read().map_err(|e| {
             CheckpointError::StorageError(format!("Failed to acquire read lock: {}", e))
         })?;
-        Ok(store.values().max_by_key(|c| c.timestamp).cloned())
+        Ok(store.values().max_by_key(|c| c.recency()).cloned())
     }
 
     async fn clear(&self) -> Result<(), CheckpointError> {
@@ -314,6 +416,9 @@ impl CheckpointStore for MemoryCheckpointStore {
 ///
 /// This implementation uses `tokio::fs` for non-blocking async I/O operations,
 /// making it safe to use in async contexts without blocking the runtime.
+///
+/// A checkpoint is written to a temporary file which is then renamed, so a
+/// crash while saving never leaves a truncated checkpoint behind.
 pub struct FileCheckpointStore {
     base_path: PathBuf,
 }
@@ -360,9 +465,20 @@ impl CheckpointStore for FileCheckpointStore {
             .map_err(|e| CheckpointError::SerializationError(e.to_string()))?;
 
         let path = self.checkpoint_path(&checkpoint.id)?;
-        tokio::fs::write(&path, json).await.map_err(|e| {
-            CheckpointError::StorageError(format!("Failed to write checkpoint file: {}", e))
-        })?;
+        let tmp_path = path.with_extension("json.tmp");
+        let write = async {
+            let mut file = tokio::fs::File::create(&tmp_path).await?;
+            file.write_all(json.as_bytes()).await?;
+            file.sync_all().await?;
+            tokio::fs::rename(&tmp_path, &path).await
+        };
+        if let Err(e) = write.await {
+            let _ = tokio::fs::remove_file(&tmp_path).await;
+            return Err(CheckpointError::StorageError(format!(
+                "Failed to write checkpoint file: {}",
+                e
+            )));
+        }
 
         Ok(())
     }
@@ -448,7 +564,7 @@ impl CheckpointStore for FileCheckpointStore {
             if let Ok(checkpoint) = self.load(&id).await
                 && latest
                     .as_ref()
-                    .is_none_or(|l| checkpoint.timestamp > l.timestamp)
+                    .is_none_or(|l| checkpoint.recency() > l.recency())
             {
                 latest = Some(checkpoint);
             }
@@ -481,6 +597,10 @@ pub struct CheckpointConfig {
     pub before_conditional: bool,
     /// Maximum number of checkpoints to keep (0 = unlimited)
     pub max_checkpoints: usize,
+    /// Maximum age of the checkpoints to keep, in seconds
+    pub max_age_seconds: Option<u64>,
+    /// Continue the latest unfinished run when the graph starts
+    pub auto_resume: bool,
 }
 
 impl Default for CheckpointConfig {
@@ -492,6 +612,8 @@ impl Default for CheckpointConfig {
             on_loop_iteration: true,
             before_conditional: true,
             max_checkpoints: 5,
+            max_age_seconds: None,
+            auto_resume: false,
         }
     }
 }
@@ -528,6 +650,26 @@ impl CheckpointConfig {
         self.max_checkpoints = max;
         self
     }
+
+    /// Set maximum age of the checkpoints to retain
+    pub fn with_max_age(mut self, seconds: u64) -> Self {
+        self.max_age_seconds = Some(seconds);
+        self
+    }
+
+    /// Continue the latest unfinished run when the graph starts
+    pub fn with_auto_resume(mut self, enabled: bool) -> Self {
+        self.auto_resume = enabled;
+        self
+    }
+
+    /// The retention policy applied after each checkpoint is saved
+    pub fn retention(&self) -> RetentionPolicy {
+        RetentionPolicy {
+            max_checkpoints: self.max_checkpoints,
+            max_age_seconds: self.max_age_seconds,
+        }
+    }
 }
 
 #[cfg(test)]
@@ -584,11 +726,21 @@ mod tests {
         let config = CheckpointConfig::enabled()
             .with_node_interval(5)
             .with_time_interval(60)
-            .with_max_checkpoints(10);
+            .with_max_checkpoints(10)
+            .with_max_age(3600)
+            .with_auto_resume(true);
 
         assert!(config.enabled);
         assert_eq!(config.interval_nodes, Some(5));
         assert_eq!(config.interval_seconds, Some(60));
         assert_eq!(config.max_checkpoints, 10);
+        assert!(config.auto_resume);
+        assert_eq!(
+            config.retention(),
+            RetentionPolicy {
+                max_checkpoints: 10,
+                max_age_seconds: Some(3600),
+            }
+        );
     }
 }
@@ -3,3 +3,6 @@ pub mod env;
 pub mod execstate;
 pub mod hook;
 pub mod output;
+#[cfg(feature = "sqlite")]
+pub mod sqlite_checkpoint;
+pub mod trace;
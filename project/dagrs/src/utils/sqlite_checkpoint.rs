@@ -0,0 +1,204 @@
+//! SQLite checkpoint store
+//!
+//! [`SqliteCheckpointStore`] keeps checkpoints in a table of a SQLite database,
+//! one row per checkpoint with the checkpoint as JSON. Each write is a single
+//! statement or transaction, so a crash never leaves a partial checkpoint, and
+//! the database is opened in WAL mode so that committed checkpoints survive a
+//! crash of the process.
+//!
+//! ```rust
+//! use dagrs::{CheckpointConfig, Graph, SqliteCheckpointStore};
+//!
+//! let runtime = tokio::runtime::Runtime::new().unwrap();
+//! runtime.block_on(async {
+//!     let store = SqliteCheckpointStore::in_memory().await.unwrap();
+//!     let mut graph = Graph::new();
+//!     graph.set_checkpoint_store(Box::new(store));
+//!     graph.set_checkpoint_config(CheckpointConfig::enabled().with_auto_resume(true));
+//! });
+//! ```
+
+use std::path::Path;
+use std::str::FromStr;
+
+use async_trait::async_trait;
+use sqlx::sqlite::{SqliteConnectOptions, SqliteJournalMode, SqlitePool, SqlitePoolOptions};
+
+use super::checkpoint::{
+    Checkpoint, CheckpointError, CheckpointId, CheckpointStore, RetentionPolicy, now_secs,
+};
+
+const CREATE_TABLE: &str = "CREATE TABLE IF NOT EXISTS dagrs_checkpoints (
+    id TEXT PRIMARY KEY NOT NULL,
+    timestamp INTEGER NOT NULL,
+    timestamp_micros INTEGER NOT NULL,
+    data TEXT NOT NULL
+)";
+
+const CREATE_INDEX: &str = "CREATE INDEX IF NOT EXISTS dagrs_checkpoints_recency \
+    ON dagrs_checkpoints (timestamp, timestamp_micros)";
+
+/// Ids of the checkpoints from the latest to the oldest.
+const LATEST_FIRST: &str =
+    "SELECT id FROM dagrs_checkpoints ORDER BY timestamp DESC, timestamp_micros DESC";
+
+/// SQLite-backed checkpoint store for persistent storage
+pub struct SqliteCheckpointStore {
+    pool: SqlitePool,
+}
+
+impl SqliteCheckpointStore {
+    /// Open the database at `path`, creating it if it does not exist
+    pub async fn open(path: impl AsRef<Path>) -> Result<Self, CheckpointError> {
+        let options = SqliteConnectOptions::new()
+            .filename(path)
+            .create_if_missing(true)
+            .journal_mode(SqliteJournalMode::Wal);
+        let pool = SqlitePoolOptions::new()
+            .connect_with(options)
+            .await
+            .map_err(storage_error)?;
+        Self::with_pool(pool).await
+    }
+
+    /// Open a private in-memory database, lost when the store is dropped
+    pub async fn in_memory() -> Result<Self, CheckpointError> {
+        let options = SqliteConnectOptions::from_str("sqlite::memory:").map_err(storage_error)?;
+        // The database lives as long as its only connection
+        let pool = SqlitePoolOptions::new()
+            .max_connections(1)
+            .idle_timeout(None)
+            .max_lifetime(None)
+            .connect_with(options)
+            .await
+            .map_err(storage_error)?;
+        Self::with_pool(pool).await
+    }
+
+    /// Use a database of an existing pool, creating the checkpoint table if
+    /// it does not exist
+    pub async fn with_pool(pool: SqlitePool) -> Result<Self, CheckpointError> {
+        sqlx::query(CREATE_TABLE)
+            .execute(&pool)
+            .await
+            .map_err(storage_error)?;
+        sqlx::query(CREATE_INDEX)
+            .execute(&pool)
+            .await
+            .map_err(storage_error)?;
+        Ok(Self { pool })
+    }
+}
+
+#[async_trait]
+impl CheckpointStore for SqliteCheckpointStore {
+    async fn save(&self, checkpoint: &Checkpoint) -> Result<(), CheckpointError> {
+        let json = serde_json::to_string(checkpoint)
+            .map_err(|e| CheckpointError::SerializationError(e.to_string()))?;
+
+        sqlx::query(
+            "INSERT INTO dagrs_checkpoints (id, timestamp, timestamp_micros, data)
+             VALUES (?1, ?2, ?3, ?4)
+             ON CONFLICT (id) DO UPDATE SET
+                 timestamp = excluded.timestamp,
+                 timestamp_micros = excluded.timestamp_micros,
+                 data = excluded.data",
+        )
+        .bind(&checkpoint.id)
+        .bind(checkpoint.timestamp as i64)
+        .bind(checkpoint.timestamp_micros as i64)
+        .bind(json)
+        .execute(&self.pool)
+        .await
+        .map_err(storage_error)?;
+        Ok(())
+    }
+
+    async fn load(&self, id: &CheckpointId) -> Result<Checkpoint, CheckpointError> {
+        let json: Option<String> =
+            sqlx::query_scalar("SELECT data FROM dagrs_checkpoints WHERE id = ?1")
+                .bind(id)
+                .fetch_optional(&self.pool)
+                .await
+                .map_err(storage_error)?;
+        let json = json.ok_or_else(|| CheckpointError::NotFound(id.clone()))?;
+        serde_json::from_str(&json)
+            .map_err(|e| CheckpointError::DeserializationError(e.to_string()))
+    }
+
+    async fn delete(&self, id: &CheckpointId) -> Result<(), CheckpointError> {
+        sqlx::query("DELETE FROM dagrs_checkpoints WHERE id = ?1")
+            .bind(id)
+            .execute(&self.pool)
+            .await
+            .map_err(storage_error)?;
+        Ok(())
+    }
+
+    async fn list(&self) -> Result<Vec<CheckpointId>, CheckpointError> {
+        sqlx::query_scalar("SELECT id FROM dagrs_checkpoints ORDER BY timestamp, timestamp_micros")
+            .fetch_all(&self.pool)
+            .await
+            .map_err(storage_error)
+    }
+
+    async fn latest(&self) -> Result<Option<Checkpoint>, CheckpointError> {
+        let json: Option<String> = sqlx::query_scalar(
+            "SELECT data FROM dagrs_checkpoints
+             ORDER BY timestamp DESC, timestamp_micros DESC LIMIT 1",
+        )
+        .fetch_optional(&self.pool)
+        .await
+        .map_err(storage_error)?;
+        json.map(|json| {
+            serde_json::from_str(&json)
+                .map_err(|e| CheckpointError::DeserializationError(e.to_string()))
+        })
+        .transpose()
+    }
+
+    async fn clear(&self) -> Result<(), CheckpointError> {
+        sqlx::query("DELETE FROM dagrs_checkpoints")
+            .execute(&self.pool)
+            .await
+            .map_err(storage_error)?;
+        Ok(())
+    }
+
+    async fn prune(&self, policy: &RetentionPolicy) -> Result<usize, CheckpointError> {
+        let mut tx = self.pool.begin().await.map_err(storage_error)?;
+        let mut deleted = 0;
+
+        if policy.max_checkpoints > 0 {
+            deleted += sqlx::query(&format!(
+                "DELETE FROM dagrs_checkpoints WHERE id NOT IN ({} LIMIT ?1)",
+                LATEST_FIRST
+            ))
+            .bind(policy.max_checkpoints as i64)
+            .execute(&mut *tx)
+            .await
+            .map_err(storage_error)?
+            .rows_affected();
+        }
+        if let Some(max_age) = policy.max_age_seconds {
+            // The latest checkpoint is kept however old it is
+            deleted += sqlx::query(&format!(
+                "DELETE FROM dagrs_checkpoints
+                 WHERE timestamp < ?1 AND id NOT IN ({} LIMIT 1)",
+                LATEST_FIRST
+            ))
+            .bind(now_secs().saturating_sub(max_age) as i64)
+            .execute(&mut *tx)
+            .await
+            .map_err(storage_error)?
+            .rows_affected();
+        }
+
+        tx.commit().await.map_err(storage_error)?;
+        Ok(deleted as usize)
+    }
+}
+
+fn storage_error(e: sqlx::Error) -> CheckpointError {
+    CheckpointError::StorageError(e.to_string())
+}
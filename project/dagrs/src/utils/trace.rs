@@ -0,0 +1,378 @@
+//! Execution tracing
+//!
+//! [`TraceHook`] is an [`ExecutionHook`] recording a span for each node run,
+//! with its retries and errors. The recorded trace is exported as a
+//! [Chrome trace](https://docs.google.com/document/d/1CvAClvFfyA5R-PhYUmn5OOQtYMH4h6I0nSsKchNAySU)
+//! (viewable in `chrome://tracing` or [Perfetto](https://ui.perfetto.dev)) or as
+//! [OpenTelemetry](https://opentelemetry.io/docs/specs/otlp/#json-protobuf-encoding)
+//! OTLP/JSON spans.
+//!
+//! ```rust
+//! use dagrs::utils::trace::{TraceFormat, TraceHook};
+//! use dagrs::{DefaultNode, EmptyAction, Graph, NodeTable};
+//!
+//! let mut node_table = NodeTable::new();
+//! let mut graph = Graph::new();
+//! graph.add_node(DefaultNode::with_action("a".to_string(), EmptyAction, &mut node_table));
+//!
+//! let trace = TraceHook::new();
+//! let runtime = tokio::runtime::Runtime::new().unwrap();
+//! runtime.block_on(async {
+//!     graph.add_hook(Box::new(trace.clone())).await;
+//!     graph.async_start().await.unwrap();
+//! });
+//!
+//! let json = trace.to_json(TraceFormat::Chrome);
+//! assert_eq!(json["traceEvents"].as_array().unwrap().len(), 3);
+//! ```
+
+use std::collections::HashMap;
+use std::collections::hash_map::RandomState;
+use std::hash::{BuildHasher, Hasher};
+use std::path::Path;
+use std::sync::{Arc, Mutex};
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use async_trait::async_trait;
+use serde_json::{Value, json};
+
+use super::{
+    env::EnvVar,
+    hook::{ExecutionHook, RetryDecision},
+    output::Output,
+};
+use crate::node::{Node, NodeId, NodeName};
+
+/// Format of an exported trace.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum TraceFormat {
+    /// Chrome trace event format: a complete (`X`) event per node run and
+    /// instant events for retries, skips and errors.
+    Chrome,
+    /// OTLP/JSON: a span per node run under a root span of the graph, with
+    /// retries as span events.
+    OpenTelemetry,
+}
+
+/// # Tracing hook
+///
+/// Records the runs of the nodes of the graphs it is added to. It is cheap to
+/// clone and clones share the recorded trace, so keep a clone to export the
+/// trace once the graph has run.
+///
+/// A node that panics or is cancelled has an unfinished span, which ends at
+/// the time of the export with an error status.
+#[derive(Clone)]
+pub struct TraceHook {
+    trace: Arc<Mutex<Trace>>,
+}
+
+struct Trace {
+    origin: SystemTime,
+    trace_id: String,
+    spans: Vec<Span>,
+    /// Index in `spans` of the running span of a node.
+    running: HashMap<NodeId, usize>,
+    errors: Vec<(SystemTime, String)>,
+}
+
+struct Span {
+    node_id: NodeId,
+    name: NodeName,
+    start: SystemTime,
+    end: Option<SystemTime>,
+    error: Option<String>,
+    skipped: bool,
+    retries: Vec<Retry>,
+}
+
+struct Retry {
+    time: SystemTime,
+    attempt: u32,
+    error: String,
+}
+
+impl Default for TraceHook {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl TraceHook {
+    /// Creates a hook with an empty trace.
+    pub fn new() -> Self {
+        Self {
+            trace: Arc::new(Mutex::new(Trace {
+                origin: SystemTime::now(),
+                trace_id: format!("{:016x}{:016x}", random_u64(), random_u64()),
+                spans: Vec::new(),
+                running: HashMap::new(),
+                errors: Vec::new(),
+            })),
+        }
+    }
+
+    /// Exports the recorded trace.
+    pub fn to_json(&self, format: TraceFormat) -> Value {
+        let trace = self.trace.lock().unwrap();
+        let now = SystemTime::now();
+        match format {
+            TraceFormat::Chrome => trace.chrome(now),
+            TraceFormat::OpenTelemetry => trace.otlp(now),
+        }
+    }
+
+    /// Writes the recorded trace to the file at `path`.
+    pub fn write(&self, path: impl AsRef<Path>, format: TraceFormat) -> std::io::Result<()> {
+        let json = serde_json::to_string_pretty(&self.to_json(format))?;
+        std::fs::write(path, json)
+    }
+
+    fn record(&self, f: impl FnOnce(&mut Trace)) {
+        f(&mut self.trace.lock().unwrap())
+    }
+}
+
+#[async_trait]
+impl ExecutionHook for TraceHook {
+    async fn before_node_run(&self, node: &dyn Node, _env: &Arc<EnvVar>) {
+        let (node_id, name) = (node.id(), node.name());
+        self.record(|trace| {
+            trace.running.insert(node_id, trace.spans.len());
+            trace
+                .spans
+                .push(Span::new(node_id, name, SystemTime::now()));
+        });
+    }
+
+    async fn after_node_run(&self, node: &dyn Node, output: &Output, _env: &Arc<EnvVar>) {
+        let node_id = node.id();
+        self.record(|trace| {
+            if let Some(span) = trace.running.remove(&node_id) {
+                let span = &mut trace.spans[span];
+                span.end = Some(SystemTime::now());
+                span.error = output
+                    .is_err()
+                    .then(|| output.get_err().unwrap_or_default());
+            }
+        });
+    }
+
+    async fn on_error(&self, error: &(dyn std::error::Error + Send + Sync), _env: &Arc<EnvVar>) {
+        let error = error.to_string();
+        self.record(|trace| trace.errors.push((SystemTime::now(), error)));
+    }
+
+    async fn on_retry(
+        &self,
+        node: &dyn Node,
+        error: &(dyn std::error::Error + Send + Sync),
+        attempt: u32,
+        _max_retries: u32,
+        _env: &Arc<EnvVar>,
+    ) -> RetryDecision {
+        let (node_id, error) = (node.id(), error.to_string());
+        self.record(|trace| {
+            if let Some(&span) = trace.running.get(&node_id) {
+                trace.spans[span].retries.push(Retry {
+                    time: SystemTime::now(),
+                    attempt,
+                    error,
+                });
+            }
+        });
+        RetryDecision::Retry
+    }
+
+    async fn on_skip(&self, node: &dyn Node, _env: &Arc<EnvVar>) {
+        let (node_id, name) = (node.id(), node.name());
+        self.record(|trace| {
+            let now = SystemTime::now();
+            let mut span = Span::new(node_id, name, now);
+            span.end = Some(now);
+            span.skipped = true;
+            trace.spans.push(span);
+        });
+    }
+}
+
+impl Span {
+    fn new(node_id: NodeId, name: NodeName, start: SystemTime) -> Self {
+        Self {
+            node_id,
+            name,
+            start,
+            end: None,
+            error: None,
+            skipped: false,
+            retries: Vec::new(),
+        }
+    }
+
+    /// End and error of the span, closing unfinished spans at `now`.
+    fn outcome(&self, now: SystemTime) -> (SystemTime, Option<&str>) {
+        match self.end {
+            Some(end) => (end, self.error.as_deref()),
+            None => (now, Some("unfinished")),
+        }
+    }
+}
+
+impl Trace {
+    fn chrome(&self, now: SystemTime) -> Value {
+        let micros = |time: SystemTime| {
+            time.duration_since(self.origin)
+                .unwrap_or_default()
+                .as_micros() as u64
+        };
+        let mut events = vec![json!({
+            "name": "process_name", "ph": "M", "pid": 1, "args": { "name": "dagrs" }
+        })];
+        let mut named = Vec::new();
+        for span in &self.spans {
+            let tid = span.node_id.as_usize();
+            if !named.contains(&tid) {
+                named.push(tid);
+                events.push(json!({
+                    "name": "thread_name", "ph": "M", "pid": 1, "tid": tid,
+                    "args": { "name": span.name }
+                }));
+            }
+            if span.skipped {
+                events.push(json!({
+                    "name": span.name, "cat": "skipped", "ph": "i", "s": "t",
+                    "ts": micros(span.start), "pid": 1, "tid": tid
+                }));
+                continue;
+            }
+            let (end, error) = span.outcome(now);
+            let mut args = json!({ "node_id": tid, "status": "ok" });
+            if let Some(error) = error {
+                args["status"] = json!("error");
+                args["error"] = json!(error);
+            }
+            events.push(json!({
+                "name": span.name, "cat": "node", "ph": "X",
+                "ts": micros(span.start),
+                "dur": micros(end).saturating_sub(micros(span.start)),
+                "pid": 1, "tid": tid, "args": args
+            }));
+            for retry in &span.retries {
+                events.push(json!({
+                    "name": "retry", "cat": "retry", "ph": "i", "s": "t",
+                    "ts": micros(retry.time), "pid": 1, "tid": tid,
+                    "args": { "attempt": retry.attempt, "error": retry.error }
+                }));
+            }
+        }
+        for (time, error) in &self.errors {
+            events.push(json!({
+                "name": "error", "cat": "error", "ph": "i", "s": "p",
+                "ts": micros(*time), "pid": 1, "tid": 0, "args": { "error": error }
+            }));
+        }
+        json!({ "traceEvents": events, "displayTimeUnit": "ms" })
+    }
+
+    fn otlp(&self, now: SystemTime) -> Value {
+        let nanos = |time: SystemTime| {
+            time.duration_since(UNIX_EPOCH)
+                .unwrap_or_default()
+                .as_nanos()
+                .to_string()
+        };
+        let root_id = format!("{:016x}", random_u64());
+        let mut start: Option<SystemTime> = None;
+        let mut end: Option<SystemTime> = None;
+        let mut spans = Vec::new();
+        for span in &self.spans {
+            let (span_end, error) = span.outcome(now);
+            start = Some(start.map_or(span.start, |start| start.min(span.start)));
+            end = Some(end.map_or(span_end, |end| end.max(span_end)));
+            let mut attributes = vec![
+                attribute(
+                    "dagrs.node.id",
+                    json!({ "intValue": span.node_id.as_usize().to_string() }),
+                ),
+                attribute("dagrs.node.name", json!({ "stringValue": span.name })),
+            ];
+            if span.skipped {
+                attributes.push(attribute(
+                    "dagrs.node.skipped",
+                    json!({ "boolValue": true }),
+                ));
+            }
+            let events: Vec<Value> = span
+                .retries
+                .iter()
+                .map(|retry| {
+                    let attempt = json!({ "intValue": retry.attempt.to_string() });
+                    json!({
+                        "timeUnixNano": nanos(retry.time),
+                        "name": "retry",
+                        "attributes": [
+                            attribute("dagrs.retry.attempt", attempt),
+                            attribute("exception.message", json!({ "stringValue": retry.error })),
+                        ]
+                    })
+                })
+                .collect();
+            spans.push(json!({
+                "traceId": self.trace_id,
+                "spanId": format!("{:016x}", random_u64()),
+                "parentSpanId": root_id,
+                "name": span.name,
+                "kind": 1,
+                "startTimeUnixNano": nanos(span.start),
+                "endTimeUnixNano": nanos(span_end),
+                "attributes": attributes,
+                "events": events,
+                "status": status(error),
+            }));
+        }
+        let error = (!self.errors.is_empty()).then(|| {
+            let errors: Vec<&str> = self.errors.iter().map(|(_, e)| e.as_str()).collect();
+            errors.join("; ")
+        });
+        spans.insert(
+            0,
+            json!({
+                "traceId": self.trace_id,
+                "spanId": root_id,
+                "name": "graph",
+                "kind": 1,
+                "startTimeUnixNano": nanos(start.unwrap_or(self.origin)),
+                "endTimeUnixNano": nanos(end.unwrap_or(self.origin)),
+                "status": status(error.as_deref()),
+            }),
+        );
+        json!({
+            "resourceSpans": [{
+                "resource": {
+                    "attributes": [attribute("service.name", json!({ "stringValue": "dagrs" }))]
+                },
+                "scopeSpans": [{
+                    "scope": { "name": "dagrs", "version": env!("CARGO_PKG_VERSION") },
+                    "spans": spans
+                }]
+            }]
+        })
+    }
+}
+
+fn attribute(key: &str, value: Value) -> Value {
+    json!({ "key": key, "value": value })
+}
+
+/// OTLP status: `STATUS_CODE_OK` or `STATUS_CODE_ERROR` with the message.
+fn status(error: Option<&str>) -> Value {
+    match error {
+        Some(message) => json!({ "code": 2, "message": message }),
+        None => json!({ "code": 1 }),
+    }
+}
+
+/// A random number from the randomly keyed hasher of the standard library.
+fn random_u64() -> u64 {
+    RandomState::new().build_hasher().finish()
+}
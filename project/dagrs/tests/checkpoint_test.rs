@@ -7,15 +7,21 @@
 //! - Resuming execution from checkpoint
 //! - Automatic checkpointing configuration
 //! - Memory and file-based checkpoint stores
+//! - Retention policies
+//! - Automatically resuming an unfinished run in a restarted process
 
 use async_trait::async_trait;
 use dagrs::graph::event::GraphEvent;
 use dagrs::node::action::Action;
+use dagrs::node::conditional_node::{Condition, ConditionalNode};
 use dagrs::node::default_node::DefaultNode;
 use dagrs::{
-    Checkpoint, CheckpointConfig, CheckpointStore, EnvVar, Graph, InChannels,
-    MemoryCheckpointStore, Node, NodeTable, OutChannels, Output,
+    Checkpoint, CheckpointConfig, CheckpointStore, Content, EnvVar, FileCheckpointStore, Graph,
+    InChannels, MemoryCheckpointStore, Node, NodeId, NodeTable, OutChannels, Output,
+    RetentionPolicy,
 };
+use std::path::Path;
+use std::sync::atomic::{AtomicBool, Ordering};
 use std::sync::{Arc, Mutex};
 use std::time::Duration;
 
@@ -236,16 +242,13 @@ fn test_checkpoint_events() {
         // Spawn event collector
         let collector = tokio::spawn(async move {
             let mut collected = Vec::new();
-            loop {
-                match tokio::time::timeout(Duration::from_millis(200), receiver.recv()).await {
-                    Ok(Ok(event)) => {
-                        let is_finished = matches!(event, GraphEvent::GraphFinished);
-                        collected.push(event);
-                        if is_finished {
-                            break;
-                        }
-                    }
-                    _ => break,
+            while let Ok(Ok(event)) =
+                tokio::time::timeout(Duration::from_millis(200), receiver.recv()).await
+            {
+                let is_finished = matches!(event, GraphEvent::GraphFinished);
+                collected.push(event);
+                if is_finished {
+                    break;
                 }
             }
             collected
@@ -404,6 +407,16 @@ fn test_file_checkpoint_store_basic() {
         let latest = store.latest().await.unwrap();
         assert!(latest.is_some());
 
+        // No temporary file is left behind
+        let leftovers = std::fs::read_dir(&test_dir)
+            .unwrap()
+            .filter(|entry| {
+                let name = entry.as_ref().unwrap().file_name();
+                name.to_string_lossy().ends_with(".tmp")
+            })
+            .count();
+        assert_eq!(leftovers, 0);
+
         // Clean up
         store.clear().await.unwrap();
         let _ = std::fs::remove_dir_all(&test_dir);
@@ -523,3 +536,164 @@ fn test_checkpoint_output_data_serialization() {
         serde_json::from_slice(restored_state.output_data.as_ref().unwrap()).unwrap();
     assert_eq!(output, "test_output");
 }
+
+#[test]
+fn test_retention_policy() {
+    let store = MemoryCheckpointStore::new();
+
+    let rt = tokio::runtime::Runtime::new().unwrap();
+    rt.block_on(async {
+        for (id, timestamp) in [("a", 1000), ("b", 2000), ("c", 3000), ("d", 4000)] {
+            let mut checkpoint = Checkpoint::with_id(id, 0, 0);
+            checkpoint.timestamp = timestamp;
+            store.save(&checkpoint).await.unwrap();
+        }
+
+        let policy = RetentionPolicy {
+            max_checkpoints: 3,
+            max_age_seconds: None,
+        };
+        assert_eq!(store.prune(&policy).await.unwrap(), 1);
+        let mut ids = store.list().await.unwrap();
+        ids.sort();
+        assert_eq!(ids, ["b", "c", "d"]);
+
+        // All checkpoints are older than a day, but the latest one is kept
+        let policy = RetentionPolicy {
+            max_checkpoints: 0,
+            max_age_seconds: Some(24 * 60 * 60),
+        };
+        assert_eq!(store.prune(&policy).await.unwrap(), 2);
+        assert_eq!(store.list().await.unwrap(), ["d"]);
+    });
+}
+
+/// Condition that always holds, ending the block of its predecessors.
+struct Always;
+
+#[async_trait]
+impl Condition for Always {
+    async fn run(&self, _: &mut InChannels, _: &OutChannels, _: Arc<EnvVar>) -> bool {
+        true
+    }
+}
+
+/// Action sending a message to its successors, counting its runs.
+struct Produce {
+    runs: Arc<Mutex<usize>>,
+}
+
+#[async_trait]
+impl Action for Produce {
+    async fn run(&self, _: &mut InChannels, out: &mut OutChannels, _: Arc<EnvVar>) -> Output {
+        *self.runs.lock().unwrap() += 1;
+        out.broadcast(Content::new("from A".to_string())).await;
+        Output::new("from A".to_string())
+    }
+}
+
+/// Action recording the message of `from`, failing once if `fail` is set.
+struct Consume {
+    from: NodeId,
+    fail: Arc<AtomicBool>,
+    received: Arc<Mutex<Vec<String>>>,
+}
+
+#[async_trait]
+impl Action for Consume {
+    async fn run(&self, input: &mut InChannels, _: &mut OutChannels, _: Arc<EnvVar>) -> Output {
+        if let Ok(content) = input.recv_from(&self.from).await {
+            let message = content.get::<String>().unwrap().clone();
+            self.received.lock().unwrap().push(message);
+        }
+        if self.fail.swap(false, Ordering::SeqCst) {
+            Output::error("B failed".to_string())
+        } else {
+            Output::empty()
+        }
+    }
+}
+
+/// Builds `A -> Gate -> B` with `A -> B`, checkpointed to `dir`, as a process
+/// starting the graph would.
+fn resumable_graph(
+    dir: &Path,
+    runs: &Arc<Mutex<usize>>,
+    fail: bool,
+    received: &Arc<Mutex<Vec<String>>>,
+) -> (Graph, NodeId) {
+    let mut table = NodeTable::new();
+    let a = DefaultNode::with_action("A".to_string(), Produce { runs: runs.clone() }, &mut table);
+    let gate = ConditionalNode::with_condition("Gate".to_string(), Always, &mut table);
+    let b = DefaultNode::with_action(
+        "B".to_string(),
+        Consume {
+            from: a.id(),
+            fail: Arc::new(AtomicBool::new(fail)),
+            received: received.clone(),
+        },
+        &mut table,
+    );
+    let (id_a, id_gate, id_b) = (a.id(), gate.id(), b.id());
+
+    let mut graph = Graph::new();
+    graph.add_node(a);
+    graph.add_node(gate);
+    graph.add_node(b);
+    graph.add_edge(id_a, vec![id_gate, id_b]);
+    graph.add_edge(id_gate, vec![id_b]);
+
+    graph.set_checkpoint_store(Box::new(FileCheckpointStore::new(dir)));
+    graph.set_checkpoint_config(
+        CheckpointConfig::enabled()
+            .with_node_interval(1)
+            .with_auto_resume(true),
+    );
+    (graph, id_a)
+}
+
+#[test]
+fn test_auto_resume_unfinished_run() {
+    let dir = std::env::temp_dir().join("dagrs_auto_resume_test");
+    let _ = std::fs::remove_dir_all(&dir);
+    let runs = Arc::new(Mutex::new(0));
+    let received = Arc::new(Mutex::new(Vec::new()));
+
+    // Graphs are built outside of the runtime, as adding edges blocks
+    let (mut failing, _) = resumable_graph(&dir, &runs, true, &received);
+    let (mut resumed, id_a) = resumable_graph(&dir, &runs, false, &received);
+    let (mut rerun, _) = resumable_graph(&dir, &runs, false, &received);
+
+    let rt = tokio::runtime::Runtime::new().unwrap();
+    rt.block_on(async {
+        // First process: B fails after A and Gate ran
+        let graph = &mut failing;
+        assert!(graph.async_start().await.is_err());
+        let latest = graph.get_latest_checkpoint().await.unwrap().unwrap();
+        assert!(!latest.completed);
+        assert_eq!(latest.pc, 1);
+
+        // Restarted process: only B runs, receiving the restored output of A
+        let graph = &mut resumed;
+        let mut events = graph.subscribe();
+        graph.async_start().await.unwrap();
+
+        assert_eq!(*runs.lock().unwrap(), 1);
+        assert_eq!(*received.lock().unwrap(), ["from A", "from A"]);
+        let output = graph.get_outputs()[&id_a].get_out().unwrap();
+        assert_eq!(output.get::<String>().unwrap(), "from A");
+        let mut restored = false;
+        while let Ok(event) = events.try_recv() {
+            restored |= matches!(event, GraphEvent::CheckpointRestored { pc: 1, .. });
+        }
+        assert!(restored, "Should have a CheckpointRestored event");
+        let latest = graph.get_latest_checkpoint().await.unwrap().unwrap();
+        assert!(latest.completed);
+
+        // The run finished: the next start runs the whole graph again
+        rerun.async_start().await.unwrap();
+        assert_eq!(*runs.lock().unwrap(), 2);
+    });
+
+    let _ = std::fs::remove_dir_all(&dir);
+}
@@ -0,0 +1,294 @@
+//! Tests of graph definition files
+//!
+//! - Definitions in `tests/config` and inline YAML or JSON are built into graphs
+//!   that run as described.
+//! - Registered actions, conditions, routers and loop conditions are built from
+//!   the `params` of their nodes, and `env` becomes the environment of the graph.
+//! - Invalid definitions are rejected with a `ParseError` on the offending line.
+
+use std::sync::{Arc, Mutex};
+
+use async_trait::async_trait;
+use dagrs::graph::error::GraphError;
+use dagrs::node::router_node::Router;
+use dagrs::parser::{self, ParseError, Registry};
+use dagrs::{Action, Content, EnvVar, InChannels, OutChannels, Output};
+
+/// Action recording its `label` each time it runs, once the nodes it runs
+/// after are done: the nodes of a block run concurrently, ordered only by
+/// their channels.
+struct Record {
+    label: String,
+    log: Arc<Mutex<Vec<String>>>,
+}
+
+#[async_trait]
+impl Action for Record {
+    async fn run(
+        &self,
+        input: &mut InChannels,
+        output: &mut OutChannels,
+        _: Arc<EnvVar>,
+    ) -> Output {
+        input.map(|_| ()).await;
+        self.log.lock().unwrap().push(self.label.clone());
+        output.broadcast(Content::new(self.label.clone())).await;
+        Output::empty()
+    }
+}
+
+/// Router selecting the node named `to`, which it hands a token like `Record`.
+struct RouteTo {
+    to: String,
+}
+
+#[async_trait]
+impl Router for RouteTo {
+    async fn route(
+        &self,
+        _: &mut InChannels,
+        output: &OutChannels,
+        env: Arc<EnvVar>,
+    ) -> Vec<usize> {
+        output.broadcast(Content::new(self.to.clone())).await;
+        vec![env.get_node_id(&self.to).unwrap().as_usize()]
+    }
+}
+
+/// A registry with the `record` action and the `route_to` router.
+fn registry(log: &Arc<Mutex<Vec<String>>>) -> Registry {
+    let log = log.clone();
+    let mut registry = Registry::new();
+    registry
+        .register_action("record", move |params| {
+            let label = params["label"].as_str().ok_or("`label` must be a string")?;
+            Ok(Record {
+                label: label.to_string(),
+                log: log.clone(),
+            })
+        })
+        .register_router("route_to", |params| {
+            let to = params["to"].as_str().ok_or("`to` must be a string")?;
+            Ok(RouteTo { to: to.to_string() })
+        });
+    registry
+}
+
+fn parse_error(content: &str) -> ParseError {
+    match parser::from_yaml_str(content, &Registry::new()) {
+        Ok(_) => panic!("Definition should be rejected"),
+        Err(e) => e,
+    }
+}
+
+#[test]
+fn correct_execute() {
+    let (mut graph, env) =
+        parser::from_file("tests/config/correct.yaml", &Registry::new()).unwrap();
+    let id = *env.get_node_id("Task 1").unwrap();
+    graph.start().unwrap();
+
+    let outputs = graph.get_outputs();
+    let out = outputs[&id].get_out().unwrap();
+    let (stdout, _) = out.get::<(Vec<String>, Vec<String>)>().unwrap();
+    assert_eq!(stdout, &vec!["a".to_string()]);
+}
+
+#[test]
+fn loop_graph() {
+    for file in [
+        "tests/config/loop_error.yaml",
+        "tests/config/self_loop_error.yaml",
+    ] {
+        let (mut graph, _) = parser::from_file(file, &Registry::new()).unwrap();
+        assert!(matches!(graph.start(), Err(GraphError::GraphLoopDetected)));
+    }
+}
+
+#[test]
+fn failed_execute() {
+    let (mut graph, _) =
+        parser::from_file("tests/config/script_run_failed.yaml", &Registry::new()).unwrap();
+    assert!(graph.start().is_err());
+}
+
+#[test]
+fn file_not_found() {
+    let error = parser::from_file("./no_such_file.yaml", &Registry::new())
+        .err()
+        .expect("definition was accepted");
+    assert_eq!(error.line, None);
+}
+
+#[test]
+fn illegal_content() {
+    let error = parser::from_file("tests/config/illegal_content.yaml", &Registry::new());
+    assert!(error.err().expect("definition was accepted").line.is_some());
+}
+
+#[test]
+fn empty_content() {
+    assert!(parser::from_file("tests/config/empty_file.yaml", &Registry::new()).is_err());
+}
+
+#[test]
+fn no_start_with_dagrs() {
+    let error = parser::from_file("tests/config/no_start_with_dagrs.yaml", &Registry::new())
+        .err()
+        .expect("definition was accepted");
+    assert!(error.message.contains("unknown field `a`"), "{}", error);
+}
+
+#[test]
+fn precursor_not_found() {
+    let error = parser::from_file("tests/config/precursor_not_found.yaml", &Registry::new())
+        .err()
+        .expect("definition was accepted");
+    assert_eq!(error.line, Some(4));
+    assert!(error.message.contains("unknown node `b`"), "{}", error);
+}
+
+#[test]
+fn no_script() {
+    let error = parser::from_file("tests/config/no_script.yaml", &Registry::new())
+        .err()
+        .expect("definition was accepted");
+    assert_eq!(error.line, Some(2));
+    assert!(
+        error.message.contains("needs `cmd` or `action`"),
+        "{}",
+        error
+    );
+}
+
+#[test]
+fn invalid_nodes() {
+    let unknown_action = parse_error("dagrs:\n  a:\n    cmd: echo a\n  b:\n    action: nope\n");
+    assert_eq!(unknown_action.line, Some(5));
+    assert_eq!(unknown_action.message, "node `b`: unknown action `nope`");
+
+    let wrong_field = parse_error("dagrs:\n  a:\n    router: nope\n    cmd: echo a\n");
+    assert_eq!(wrong_field.line, Some(3));
+    assert!(wrong_field.message.contains("`router` is not allowed"));
+
+    let same_name =
+        parse_error("dagrs:\n  a:\n    cmd: echo a\n  b:\n    name: a\n    cmd: echo b\n");
+    assert_eq!(same_name.line, Some(4));
+
+    let unknown_target =
+        parse_error("dagrs:\n  a:\n    kind: loop\n    target: b\n    max_iterations: 1\n");
+    assert_eq!(unknown_target.line, Some(4));
+    assert!(unknown_target.message.contains("unknown target `b`"));
+
+    let unknown_kind = parse_error("dagrs:\n  a:\n    kind: lambda\n");
+    assert_eq!(unknown_kind.line, Some(3));
+
+    let reserved_env = parse_error("env:\n  node_table: 1\ndagrs:\n  a:\n    cmd: echo a\n");
+    assert_eq!(reserved_env.line, Some(2));
+}
+
+#[test]
+fn invalid_params() {
+    let log = Arc::new(Mutex::new(Vec::new()));
+    let error = parser::from_yaml_str(
+        "dagrs:\n  a:\n    action: record\n    params: { label: 1 }\n",
+        &registry(&log),
+    )
+    .err()
+    .expect("definition was accepted");
+    assert_eq!(error.line, Some(3));
+    assert_eq!(error.message, "node `a`: `label` must be a string");
+}
+
+#[test]
+fn env_vars() {
+    let content = r#"
+env:
+  name: dagrs
+  retries: 3
+  ratio: 0.5
+  verbose: true
+  tags: [ fast, small ]
+dagrs:
+  a:
+    cmd: [ echo, "two words" ]
+"#;
+    let (_, env) = parser::from_yaml_str(content, &Registry::new()).unwrap();
+    assert_eq!(env.get::<String>("name").unwrap(), "dagrs");
+    assert_eq!(env.get::<i64>("retries").unwrap(), 3);
+    assert_eq!(env.get::<f64>("ratio").unwrap(), 0.5);
+    assert!(env.get::<bool>("verbose").unwrap());
+    assert_eq!(
+        env.get::<serde_json::Value>("tags").unwrap(),
+        serde_json::json!(["fast", "small"])
+    );
+    assert!(env.get_node_id("a").is_some());
+}
+
+#[test]
+fn registered_router() {
+    let content = r#"
+dagrs:
+  start:
+    action: record
+    params: { label: start }
+  route:
+    kind: router
+    after: [ start ]
+    router: route_to
+    params: { to: left }
+  left:
+    after: [ route ]
+    action: record
+    params: { label: left }
+  right:
+    after: [ route ]
+    action: record
+    params: { label: right }
+"#;
+    let log = Arc::new(Mutex::new(Vec::new()));
+    let (mut graph, _) = parser::from_yaml_str(content, &registry(&log)).unwrap();
+    graph.start().unwrap();
+
+    assert_eq!(*log.lock().unwrap(), ["start", "left"]);
+}
+
+#[test]
+fn counted_loop() {
+    let content = r#"
+dagrs:
+  work:
+    action: record
+    params: { label: work }
+  again:
+    kind: loop
+    after: [ work ]
+    target: work
+    max_iterations: 2
+"#;
+    let log = Arc::new(Mutex::new(Vec::new()));
+    let (mut graph, _) = parser::from_yaml_str(content, &registry(&log)).unwrap();
+    graph.start().unwrap();
+
+    // Once, then once per iteration
+    assert_eq!(log.lock().unwrap().len(), 3);
+}
+
+#[test]
+fn json_definition() {
+    let content = r#"{
+  "dagrs": {
+    "a": { "action": "record", "params": { "label": "a" } },
+    "b": { "after": ["a"], "action": "record", "params": { "label": "b" } }
+  }
+}"#;
+    let log = Arc::new(Mutex::new(Vec::new()));
+    let (mut graph, _) = parser::from_json_str(content, &registry(&log)).unwrap();
+    graph.start().unwrap();
+    assert_eq!(*log.lock().unwrap(), ["a", "b"]);
+
+    let error = parser::from_json_str("{\n  \"dagrs\": {\n    \"a\": {}\n  }\n}", &registry(&log))
+        .err()
+        .expect("definition was accepted");
+    assert_eq!(error.line, Some(3));
+}
@@ -0,0 +1,114 @@
+//! Tests of the SQLite checkpoint store
+//!
+//! - Checkpoints are saved, loaded, listed, replaced and deleted.
+//! - Checkpoints created within the same second are ordered by microseconds.
+//! - Checkpoints persist in the database file across reopens.
+//! - Pruning applies the retention policy in the database.
+
+use dagrs::{Checkpoint, CheckpointError, CheckpointStore, RetentionPolicy, SqliteCheckpointStore};
+
+#[test]
+fn save_load_and_delete() {
+    let rt = tokio::runtime::Runtime::new().unwrap();
+    rt.block_on(async {
+        let store = SqliteCheckpointStore::in_memory().await.unwrap();
+
+        let mut checkpoint = Checkpoint::with_id("first", 3, 1);
+        checkpoint.add_metadata("key", "value");
+        checkpoint.active_nodes.insert(7);
+        store.save(&checkpoint).await.unwrap();
+
+        let loaded = store.load(&"first".to_string()).await.unwrap();
+        assert_eq!(loaded.pc, 3);
+        assert_eq!(loaded.loop_count, 1);
+        assert!(loaded.active_nodes.contains(&7));
+        assert_eq!(loaded.metadata["key"], "value");
+
+        // Saving again replaces the checkpoint
+        checkpoint.pc = 4;
+        store.save(&checkpoint).await.unwrap();
+        assert_eq!(store.load(&"first".to_string()).await.unwrap().pc, 4);
+        assert_eq!(store.list().await.unwrap(), ["first"]);
+
+        store.delete(&"first".to_string()).await.unwrap();
+        assert!(matches!(
+            store.load(&"first".to_string()).await,
+            Err(CheckpointError::NotFound(_))
+        ));
+    });
+}
+
+#[test]
+fn latest_checkpoint() {
+    let rt = tokio::runtime::Runtime::new().unwrap();
+    rt.block_on(async {
+        let store = SqliteCheckpointStore::in_memory().await.unwrap();
+        assert!(store.latest().await.unwrap().is_none());
+
+        // Created within the same second, ordered by their microseconds
+        let mut older = Checkpoint::with_id("older", 1, 0);
+        let mut newer = Checkpoint::with_id("newer", 2, 0);
+        older.timestamp = 1000;
+        older.timestamp_micros = 1_000_000_001;
+        newer.timestamp = 1000;
+        newer.timestamp_micros = 1_000_000_002;
+        store.save(&newer).await.unwrap();
+        store.save(&older).await.unwrap();
+
+        assert_eq!(store.latest().await.unwrap().unwrap().id, "newer");
+        assert_eq!(store.list().await.unwrap(), ["older", "newer"]);
+
+        store.clear().await.unwrap();
+        assert!(store.list().await.unwrap().is_empty());
+    });
+}
+
+#[test]
+fn persists_across_reopens() {
+    let path = std::env::temp_dir().join("dagrs_sqlite_checkpoint_test.db");
+    let _ = std::fs::remove_file(&path);
+
+    let rt = tokio::runtime::Runtime::new().unwrap();
+    rt.block_on(async {
+        let store = SqliteCheckpointStore::open(&path).await.unwrap();
+        let mut checkpoint = Checkpoint::new(5, 0);
+        checkpoint.completed = true;
+        store.save(&checkpoint).await.unwrap();
+        drop(store);
+
+        let store = SqliteCheckpointStore::open(&path).await.unwrap();
+        let latest = store.latest().await.unwrap().unwrap();
+        assert_eq!(latest.id, checkpoint.id);
+        assert!(latest.completed);
+    });
+
+    let _ = std::fs::remove_file(&path);
+}
+
+#[test]
+fn prune() {
+    let rt = tokio::runtime::Runtime::new().unwrap();
+    rt.block_on(async {
+        let store = SqliteCheckpointStore::in_memory().await.unwrap();
+        for (id, timestamp) in [("a", 1000), ("b", 2000), ("c", 3000), ("d", 4000)] {
+            let mut checkpoint = Checkpoint::with_id(id, 0, 0);
+            checkpoint.timestamp = timestamp;
+            store.save(&checkpoint).await.unwrap();
+        }
+
+        let policy = RetentionPolicy {
+            max_checkpoints: 3,
+            max_age_seconds: None,
+        };
+        assert_eq!(store.prune(&policy).await.unwrap(), 1);
+        assert_eq!(store.list().await.unwrap(), ["b", "c", "d"]);
+
+        // All checkpoints are older than a day, but the latest one is kept
+        let policy = RetentionPolicy {
+            max_checkpoints: 0,
+            max_age_seconds: Some(24 * 60 * 60),
+        };
+        assert_eq!(store.prune(&policy).await.unwrap(), 2);
+        assert_eq!(store.list().await.unwrap(), ["d"]);
+    });
+}
@@ -0,0 +1,269 @@
+//! Tests for node timeouts, graph cancellation and the concurrency limit
+//!
+//! - A node running longer than `Node::timeout_ms` fails the attempt, which is
+//!   retried like any other failure.
+//! - Cancelling the graph's `CancellationToken` aborts running nodes, emits
+//!   `GraphCancelled` and makes the execution return `GraphError::Cancelled`.
+//! - `Graph::set_max_concurrency` bounds how many nodes run at the same time.
+
+use async_trait::async_trait;
+use dagrs::graph::error::GraphError;
+use dagrs::graph::event::GraphEvent;
+use dagrs::node::action::Action;
+use dagrs::node::default_node::DefaultNode;
+use dagrs::{EnvVar, Graph, InChannels, Node, NodeTable, OutChannels, Output};
+use std::sync::Arc;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::time::{Duration, Instant};
+
+/// Node sleeping for the given durations on successive attempts.
+struct SleepyNode {
+    id: dagrs::node::NodeId,
+    name: dagrs::node::NodeName,
+    in_channels: InChannels,
+    out_channels: OutChannels,
+    sleeps_ms: Vec<u64>,
+    attempts: Arc<AtomicUsize>,
+    timeout_ms: Option<u64>,
+    max_retries: u32,
+}
+
+impl SleepyNode {
+    fn new(
+        name: &str,
+        sleeps_ms: Vec<u64>,
+        timeout_ms: Option<u64>,
+        max_retries: u32,
+        attempts: Arc<AtomicUsize>,
+        table: &mut NodeTable,
+    ) -> Self {
+        Self {
+            id: table.alloc_id_for(name),
+            name: name.to_string(),
+            in_channels: InChannels::default(),
+            out_channels: OutChannels::default(),
+            sleeps_ms,
+            attempts,
+            timeout_ms,
+            max_retries,
+        }
+    }
+}
+
+#[async_trait]
+impl Node for SleepyNode {
+    fn id(&self) -> dagrs::node::NodeId {
+        self.id
+    }
+    fn name(&self) -> dagrs::node::NodeName {
+        self.name.clone()
+    }
+    fn input_channels(&mut self) -> &mut InChannels {
+        &mut self.in_channels
+    }
+    fn output_channels(&mut self) -> &mut OutChannels {
+        &mut self.out_channels
+    }
+    async fn run(&mut self, _env: Arc<EnvVar>) -> Output {
+        let attempt = self.attempts.fetch_add(1, Ordering::SeqCst);
+        let sleep = self.sleeps_ms.get(attempt).copied().unwrap_or(0);
+        tokio::time::sleep(Duration::from_millis(sleep)).await;
+        Output::new(attempt)
+    }
+    fn max_retries(&self) -> u32 {
+        self.max_retries
+    }
+    fn retry_delay_ms(&self, _attempt: u32) -> u64 {
+        10
+    }
+    fn timeout_ms(&self) -> Option<u64> {
+        self.timeout_ms
+    }
+}
+
+/// Action recording how many instances of it run at the same time.
+struct CountingAction {
+    running: Arc<AtomicUsize>,
+    peak: Arc<AtomicUsize>,
+}
+
+#[async_trait]
+impl Action for CountingAction {
+    async fn run(&self, _: &mut InChannels, _: &mut OutChannels, _: Arc<EnvVar>) -> Output {
+        let now = self.running.fetch_add(1, Ordering::SeqCst) + 1;
+        self.peak.fetch_max(now, Ordering::SeqCst);
+        tokio::time::sleep(Duration::from_millis(30)).await;
+        self.running.fetch_sub(1, Ordering::SeqCst);
+        Output::empty()
+    }
+}
+
+#[test]
+fn test_timeout_is_retried() {
+    let mut graph = Graph::new();
+    let mut table = NodeTable::new();
+    let attempts = Arc::new(AtomicUsize::new(0));
+
+    // First attempt exceeds the timeout, the second one does not
+    let node = SleepyNode::new(
+        "Slow",
+        vec![5_000, 0],
+        Some(50),
+        1,
+        attempts.clone(),
+        &mut table,
+    );
+    let mut receiver = graph.subscribe();
+    graph.add_node(node);
+
+    let rt = tokio::runtime::Runtime::new().unwrap();
+    let start = Instant::now();
+    let result = rt.block_on(async { graph.async_start().await });
+
+    assert!(
+        result.is_ok(),
+        "Graph should succeed on retry: {:?}",
+        result
+    );
+    assert!(start.elapsed() < Duration::from_secs(2));
+    assert_eq!(attempts.load(Ordering::SeqCst), 2);
+
+    let mut retry_error = None;
+    while let Ok(event) = receiver.try_recv() {
+        if let GraphEvent::NodeRetry { error, .. } = event {
+            retry_error = Some(error);
+        }
+    }
+    let retry_error = retry_error.expect("Should have a NodeRetry event");
+    assert!(retry_error.contains("Timed out"), "{}", retry_error);
+}
+
+#[test]
+fn test_timeout_fails_without_retries() {
+    let mut graph = Graph::new();
+    let mut table = NodeTable::new();
+    let attempts = Arc::new(AtomicUsize::new(0));
+
+    let node = SleepyNode::new(
+        "Slow",
+        vec![5_000],
+        Some(50),
+        0,
+        attempts.clone(),
+        &mut table,
+    );
+    graph.add_node(node);
+
+    let rt = tokio::runtime::Runtime::new().unwrap();
+    let result = rt.block_on(async { graph.async_start().await });
+
+    match result {
+        Err(GraphError::ExecutionFailed { error, .. }) => {
+            assert!(error.contains("Timed out"), "{}", error)
+        }
+        other => panic!("Expected a timeout failure, got {:?}", other),
+    }
+}
+
+#[test]
+fn test_cancel_running_graph() {
+    let mut graph = Graph::new();
+    let mut table = NodeTable::new();
+    let attempts = Arc::new(AtomicUsize::new(0));
+
+    let node = SleepyNode::new(
+        "Endless",
+        vec![60_000],
+        None,
+        0,
+        attempts.clone(),
+        &mut table,
+    );
+    let node_id = node.id();
+    let mut receiver = graph.subscribe();
+    graph.add_node(node);
+
+    let token = graph.cancellation_token();
+    let rt = tokio::runtime::Runtime::new().unwrap();
+    let start = Instant::now();
+    let result = rt.block_on(async {
+        tokio::spawn(async move {
+            tokio::time::sleep(Duration::from_millis(50)).await;
+            token.cancel();
+        });
+        graph.async_start().await
+    });
+
+    assert!(
+        matches!(result, Err(GraphError::Cancelled)),
+        "Expected cancellation, got {:?}",
+        result
+    );
+    assert!(start.elapsed() < Duration::from_secs(5));
+
+    let mut events = Vec::new();
+    while let Ok(event) = receiver.try_recv() {
+        events.push(event);
+    }
+    assert!(
+        matches!(events.last(), Some(GraphEvent::GraphCancelled)),
+        "GraphCancelled should be the final event: {:?}",
+        events
+    );
+    assert!(
+        !events
+            .iter()
+            .any(|e| matches!(e, GraphEvent::NodeFailed { id, .. } if *id == node_id)),
+        "A cancelled node is not reported as failed"
+    );
+}
+
+#[test]
+fn test_cancel_before_start() {
+    let mut graph = Graph::new();
+    let mut table = NodeTable::new();
+    let attempts = Arc::new(AtomicUsize::new(0));
+
+    let node = SleepyNode::new("Never", vec![0], None, 0, attempts.clone(), &mut table);
+    graph.add_node(node);
+    graph.cancellation_token().cancel();
+
+    let rt = tokio::runtime::Runtime::new().unwrap();
+    let result = rt.block_on(async { graph.async_start().await });
+
+    assert!(matches!(result, Err(GraphError::Cancelled)));
+    assert_eq!(attempts.load(Ordering::SeqCst), 0);
+}
+
+#[test]
+fn test_max_concurrency() {
+    let mut graph = Graph::new();
+    let mut table = NodeTable::new();
+    let running = Arc::new(AtomicUsize::new(0));
+    let peak = Arc::new(AtomicUsize::new(0));
+
+    for i in 0..6 {
+        let node = DefaultNode::with_action(
+            format!("Node{}", i),
+            CountingAction {
+                running: running.clone(),
+                peak: peak.clone(),
+            },
+            &mut table,
+        );
+        graph.add_node(node);
+    }
+    graph.set_max_concurrency(2);
+
+    let rt = tokio::runtime::Runtime::new().unwrap();
+    let result = rt.block_on(async { graph.async_start().await });
+
+    assert!(result.is_ok());
+    assert_eq!(running.load(Ordering::SeqCst), 0);
+    let peak = peak.load(Ordering::SeqCst);
+    assert!(
+        (1..=2).contains(&peak),
+        "At most 2 nodes should run at once, saw {}",
+        peak
+    );
+}
@@ -0,0 +1,196 @@
+//! Tests for execution tracing
+//!
+//! - `TraceHook` records a span per node run, with retries as instant events in
+//!   Chrome traces and span events in OTLP/JSON.
+//! - Failed nodes get an error status, and the trace is written to a file.
+
+use std::sync::Arc;
+use std::sync::atomic::{AtomicUsize, Ordering};
+
+use async_trait::async_trait;
+use dagrs::utils::trace::{TraceFormat, TraceHook};
+use dagrs::{
+    Action, Content, DefaultNode, EnvVar, Graph, InChannels, Node, NodeTable, OutChannels, Output,
+};
+use serde_json::Value;
+
+/// Node failing its first attempt.
+struct FlakyNode {
+    id: dagrs::node::NodeId,
+    name: dagrs::node::NodeName,
+    in_channels: InChannels,
+    out_channels: OutChannels,
+    attempts: AtomicUsize,
+}
+
+#[async_trait]
+impl Node for FlakyNode {
+    fn id(&self) -> dagrs::node::NodeId {
+        self.id
+    }
+    fn name(&self) -> dagrs::node::NodeName {
+        self.name.clone()
+    }
+    fn input_channels(&mut self) -> &mut InChannels {
+        &mut self.in_channels
+    }
+    fn output_channels(&mut self) -> &mut OutChannels {
+        &mut self.out_channels
+    }
+    async fn run(&mut self, _env: Arc<EnvVar>) -> Output {
+        match self.attempts.fetch_add(1, Ordering::SeqCst) {
+            0 => {
+                // Nodes of a block run concurrently, wait for the signal of Start
+                self.in_channels.map(|_| ()).await;
+                Output::Err("first attempt".to_string())
+            }
+            _ => Output::empty(),
+        }
+    }
+    fn max_retries(&self) -> u32 {
+        1
+    }
+    fn retry_delay_ms(&self, _attempt: u32) -> u64 {
+        10
+    }
+}
+
+/// Action signalling the nodes after it that it is done.
+struct Signal;
+
+#[async_trait]
+impl Action for Signal {
+    async fn run(&self, _: &mut InChannels, out: &mut OutChannels, _: Arc<EnvVar>) -> Output {
+        out.broadcast(Content::new(())).await;
+        Output::empty()
+    }
+}
+
+/// Action that always fails.
+struct FailingAction;
+
+#[async_trait]
+impl Action for FailingAction {
+    async fn run(&self, _: &mut InChannels, _: &mut OutChannels, _: Arc<EnvVar>) -> Output {
+        Output::Err("broken".to_string())
+    }
+}
+
+/// Runs `Start -> Flaky` with a trace hook.
+fn traced_run() -> TraceHook {
+    let mut table = NodeTable::new();
+    let start = DefaultNode::with_action("Start".to_string(), Signal, &mut table);
+    let flaky = FlakyNode {
+        id: table.alloc_id_for("Flaky"),
+        name: "Flaky".to_string(),
+        in_channels: InChannels::default(),
+        out_channels: OutChannels::default(),
+        attempts: AtomicUsize::new(0),
+    };
+    let (start_id, flaky_id) = (start.id(), flaky.id());
+
+    let mut graph = Graph::new();
+    graph.add_node(start);
+    graph.add_node(flaky);
+    graph.add_edge(start_id, vec![flaky_id]);
+
+    let trace = TraceHook::new();
+    let rt = tokio::runtime::Runtime::new().unwrap();
+    rt.block_on(async {
+        graph.add_hook(Box::new(trace.clone())).await;
+        graph.async_start().await.unwrap();
+    });
+    trace
+}
+
+fn events_of<'a>(trace: &'a Value, ph: &str) -> Vec<&'a Value> {
+    trace["traceEvents"]
+        .as_array()
+        .unwrap()
+        .iter()
+        .filter(|event| event["ph"] == ph)
+        .collect()
+}
+
+#[test]
+fn test_chrome_trace() {
+    let trace = traced_run().to_json(TraceFormat::Chrome);
+
+    // Nodes of a block start together, so spans are looked up by name
+    let mut spans = events_of(&trace, "X");
+    spans.sort_by_key(|s| s["name"] != "Start");
+    let names: Vec<&str> = spans.iter().map(|s| s["name"].as_str().unwrap()).collect();
+    assert_eq!(names, ["Start", "Flaky"]);
+    assert!(spans.iter().all(|s| s["args"]["status"] == "ok"));
+    // Flaky waits for the signal of Start
+    let end = |span: &Value| span["ts"].as_u64().unwrap() + span["dur"].as_u64().unwrap();
+    assert!(end(spans[1]) >= end(spans[0]));
+
+    let retries = events_of(&trace, "i");
+    assert_eq!(retries.len(), 1);
+    assert_eq!(retries[0]["name"], "retry");
+    assert_eq!(retries[0]["tid"], spans[1]["tid"]);
+    assert_eq!(retries[0]["args"]["attempt"], 1);
+
+    // Process name and one thread name per node
+    assert_eq!(events_of(&trace, "M").len(), 3);
+}
+
+#[test]
+fn test_opentelemetry_trace() {
+    let trace = traced_run().to_json(TraceFormat::OpenTelemetry);
+
+    let spans = trace["resourceSpans"][0]["scopeSpans"][0]["spans"]
+        .as_array()
+        .unwrap();
+    assert_eq!(spans.len(), 3);
+    let (root, nodes) = (&spans[0], &spans[1..]);
+    assert_eq!(root["name"], "graph");
+    assert_eq!(root["traceId"].as_str().unwrap().len(), 32);
+    for span in nodes {
+        assert_eq!(span["traceId"], root["traceId"]);
+        assert_eq!(span["parentSpanId"], root["spanId"]);
+        assert_eq!(span["status"]["code"], 1);
+        let start: u128 = span["startTimeUnixNano"].as_str().unwrap().parse().unwrap();
+        let end: u128 = span["endTimeUnixNano"].as_str().unwrap().parse().unwrap();
+        assert!(start <= end);
+    }
+    let flaky = nodes
+        .iter()
+        .find(|span| span["name"] == "Flaky")
+        .expect("missing Flaky span");
+    assert_eq!(flaky["events"][0]["name"], "retry");
+}
+
+#[test]
+fn test_failed_node_and_file() {
+    let mut table = NodeTable::new();
+    let mut graph = Graph::new();
+    graph.add_node(DefaultNode::with_action(
+        "Broken".to_string(),
+        FailingAction,
+        &mut table,
+    ));
+
+    let trace = TraceHook::new();
+    let rt = tokio::runtime::Runtime::new().unwrap();
+    rt.block_on(async {
+        graph.add_hook(Box::new(trace.clone())).await;
+        assert!(graph.async_start().await.is_err());
+    });
+
+    let chrome = trace.to_json(TraceFormat::Chrome);
+    let spans = events_of(&chrome, "X");
+    assert_eq!(spans[0]["args"]["status"], "error");
+    assert_eq!(spans[0]["args"]["error"], "broken");
+
+    let path = std::env::temp_dir().join(format!("dagrs_trace_{}.json", std::process::id()));
+    trace.write(&path, TraceFormat::OpenTelemetry).unwrap();
+    let written: Value = serde_json::from_str(&std::fs::read_to_string(&path).unwrap()).unwrap();
+    std::fs::remove_file(&path).unwrap();
+
+    let spans = &written["resourceSpans"][0]["scopeSpans"][0]["spans"];
+    assert_eq!(spans[0]["status"]["code"], 2);
+    assert_eq!(spans[1]["status"]["code"], 2);
+    assert_eq!(spans[1]["status"]["message"], "broken");
+}
@@ -0,0 +1,107 @@
+//! Tests for graph visualization
+//!
+//! - `Graph::topology` captures the nodes and edges of a graph.
+//! - The topology renders to Graphviz DOT and Mermaid, with conditional nodes
+//!   drawn as diamonds.
+//! - `NodeStatuses` follows the events of a run and colours the rendered nodes.
+
+use std::sync::Arc;
+
+use async_trait::async_trait;
+use dagrs::graph::visualize::{NodeStatus, NodeStatuses};
+use dagrs::node::conditional_node::{Condition, ConditionalNode};
+use dagrs::{
+    DefaultNode, EmptyAction, EnvVar, Graph, InChannels, Node, NodeId, NodeTable, OutChannels,
+};
+
+/// Condition that never holds.
+struct Never;
+
+#[async_trait]
+impl Condition for Never {
+    async fn run(&self, _: &mut InChannels, _: &OutChannels, _: Arc<EnvVar>) -> bool {
+        false
+    }
+}
+
+/// Builds `Load -> Check -> Store`, where `Check` never holds.
+fn build_graph() -> (Graph, [NodeId; 3]) {
+    let mut table = NodeTable::new();
+    let load = DefaultNode::with_action("Load".to_string(), EmptyAction, &mut table);
+    let check = ConditionalNode::with_condition("Check \"x\"".to_string(), Never, &mut table);
+    let store = DefaultNode::with_action("Store".to_string(), EmptyAction, &mut table);
+    let ids = [load.id(), check.id(), store.id()];
+
+    let mut graph = Graph::new();
+    graph.add_node(load);
+    graph.add_node(check);
+    graph.add_node(store);
+    graph.add_edge(ids[0], vec![ids[1]]);
+    graph.add_edge(ids[1], vec![ids[2]]);
+    (graph, ids)
+}
+
+#[test]
+fn test_topology() {
+    let (graph, [load, check, store]) = build_graph();
+    let rt = tokio::runtime::Runtime::new().unwrap();
+    let topology = rt.block_on(graph.topology());
+
+    let names: Vec<&str> = topology.nodes.iter().map(|n| n.name.as_str()).collect();
+    assert_eq!(names, ["Load", "Check \"x\"", "Store"]);
+    assert!(topology.nodes[1].is_condition);
+    assert_eq!(topology.edges, [(load, check), (check, store)]);
+}
+
+#[test]
+fn test_dot_and_mermaid() {
+    let (graph, [load, check, _]) = build_graph();
+    let rt = tokio::runtime::Runtime::new().unwrap();
+    let topology = rt.block_on(graph.topology());
+    let (load, check) = (load.as_usize(), check.as_usize());
+
+    let dot = topology.to_dot();
+    assert!(dot.starts_with("digraph dagrs {"));
+    assert!(dot.contains(&format!("n{} [label=\"Load\"", load)));
+    assert!(dot.contains("label=\"Check \\\"x\\\"\""));
+    assert!(dot.contains(", shape=diamond]"));
+    assert!(dot.contains(&format!("n{} -> n{};", load, check)));
+
+    let mermaid = topology.to_mermaid();
+    assert!(mermaid.starts_with("flowchart TD\n"));
+    assert!(mermaid.contains(&format!("n{}[\"Load\"]", load)));
+    assert!(mermaid.contains(&format!("n{}{{\"Check #quot;x#quot;\"}}", check)));
+    assert!(mermaid.contains(&format!("n{} --> n{}", load, check)));
+    assert!(mermaid.contains("classDef pending fill:#ffffff;"));
+}
+
+#[test]
+fn test_status_colouring() {
+    let (mut graph, [load, check, store]) = build_graph();
+    let rt = tokio::runtime::Runtime::new().unwrap();
+    let topology = rt.block_on(graph.topology());
+    let mut events = graph.subscribe();
+    rt.block_on(graph.async_start()).unwrap();
+
+    let mut statuses = NodeStatuses::default();
+    while let Ok(event) = events.try_recv() {
+        statuses.apply(&event);
+    }
+    assert_eq!(statuses.get(load), NodeStatus::Succeeded);
+    assert_eq!(statuses.get(check), NodeStatus::Succeeded);
+    assert_ne!(statuses.get(store), NodeStatus::Succeeded);
+
+    let dot = topology.to_dot_with_status(&statuses);
+    assert!(dot.contains(&format!(
+        "n{} [label=\"Load\", fillcolor=\"{}\"",
+        load.as_usize(),
+        NodeStatus::Succeeded.color()
+    )));
+
+    let mermaid = topology.to_mermaid_with_status(&statuses);
+    assert!(mermaid.contains(&format!(
+        "class n{},n{} succeeded;",
+        load.as_usize(),
+        check.as_usize()
+    )));
+}
@@ -8,6 +8,7 @@ use crate::service::manifest::{
     delete_manifest_handler, get_manifest_handler, get_tag_list_handler, head_manifest_handler,
     put_manifest_handler,
 };
+use crate::service::referrer::get_referrers_handler;
 use crate::utils::state::AppState;
 use axum::extract::{Path, Query, Request, State};
 use axum::http::{HeaderMap, Method, StatusCode};
@@ -170,6 +171,22 @@ async fn dispatch_handler(
                 Ok((StatusCode::METHOD_NOT_ALLOWED, "method not allowed").into_response())
             }
         }
+        // tail: /{name}/referrers/{digest}
+        [name @ .., "referrers", digest] if !name.is_empty() => {
+            let name = name.join("/");
+            if *method == Method::GET {
+                // List manifests referring to a subject
+                get_referrers_handler(
+                    State(state),
+                    Path((name, digest.to_string())),
+                    Query(params),
+                )
+                .await
+                .map(|res| res.into_response())
+            } else {
+                Ok((StatusCode::METHOD_NOT_ALLOWED, "method not allowed").into_response())
+            }
+        }
         _ => Ok((StatusCode::NOT_FOUND, "not found").into_response()),
     }
 }
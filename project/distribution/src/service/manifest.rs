@@ -167,10 +167,18 @@ pub async fn put_manifest_handler(
             .await?;
     }
 
+    // The subject does not have to exist yet, so signatures can be pushed first.
+    if let Some(subject) = manifest.subject() {
+        state
+            .storage
+            .put_referrer(&name, subject.digest(), &calculated_digest)
+            .await?;
+    }
+
     let identifier = identifier_from_full_name(&name);
     state.repo_storage.ensure_repo_exists(&identifier).await?;
     let location = format!("/v2/{name}/manifests/{calculated_digest_str}");
-    Ok((
+    let mut response = (
         StatusCode::CREATED,
         [
             (header::LOCATION, location),
@@ -181,7 +189,15 @@ pub async fn put_manifest_handler(
         ],
         Body::empty(),
     )
-        .into_response())
+        .into_response();
+
+    if let Some(subject) = manifest.subject() {
+        response
+            .headers_mut()
+            .insert("OCI-Subject", subject.digest().to_string().parse().unwrap());
+    }
+
+    Ok(response)
 }
 
 pub async fn get_tag_list_handler(
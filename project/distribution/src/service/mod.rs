@@ -1,4 +1,5 @@
 pub mod auth;
 pub mod blob;
 pub mod manifest;
+pub mod referrer;
 pub mod repo;
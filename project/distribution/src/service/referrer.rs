@@ -0,0 +1,176 @@
+use crate::error::{AppError, MapToAppError, OciError};
+use crate::utils::{state::AppState, validation::is_valid_name};
+use axum::response::IntoResponse;
+use axum::{
+    body,
+    extract::{Path, Query, State},
+    http::{Response, StatusCode, header},
+};
+use oci_spec::image::{
+    Descriptor, DescriptorBuilder, Digest as oci_digest, ImageIndexBuilder, ImageManifest,
+    MediaType, SCHEMA_VERSION,
+};
+use std::{collections::HashMap, str::FromStr, sync::Arc};
+
+/// Describe a referrer manifest as it is listed by the referrers API.
+///
+/// The artifact type falls back to the config media type when the manifest does
+/// not set `artifactType`, as required by the distribution spec.
+fn referrer_descriptor(
+    manifest: &ImageManifest,
+    digest: oci_digest,
+    size: u64,
+) -> Result<Descriptor, AppError> {
+    let artifact_type = manifest
+        .artifact_type()
+        .clone()
+        .unwrap_or_else(|| manifest.config().media_type().clone());
+
+    let mut builder = DescriptorBuilder::default()
+        .media_type(
+            manifest
+                .media_type()
+                .clone()
+                .unwrap_or(MediaType::ImageManifest),
+        )
+        .digest(digest)
+        .size(size)
+        .artifact_type(artifact_type);
+    if let Some(annotations) = manifest.annotations() {
+        builder = builder.annotations(annotations.clone());
+    }
+    Ok(builder.build().map_err(|_| OciError::Unsupported)?)
+}
+
+pub async fn get_referrers_handler(
+    State(state): State<Arc<AppState>>,
+    Path((name, digest)): Path<(String, String)>,
+    Query(params): Query<HashMap<String, String>>,
+) -> Result<impl IntoResponse, AppError> {
+    if !is_valid_name(&name) {
+        return Err(OciError::NameInvalid(name).into());
+    }
+    let subject = oci_digest::from_str(&digest).map_err(|_| OciError::DigestInvalid(digest))?;
+    let artifact_type = params.get("artifactType");
+
+    let mut descriptors = Vec::new();
+    for referrer in state.storage.list_referrers(&name, &subject).await? {
+        // Deleting a referrer by digest leaves its link behind.
+        let object = match state.storage.get_blob(&referrer).await {
+            Ok(object) => object,
+            Err(AppError::Oci(OciError::BlobUnknown(_))) => continue,
+            Err(e) => return Err(e),
+        };
+        let buffer = object.into_bytes().await.map_to_internal()?;
+        let manifest: ImageManifest = serde_json::from_slice(&buffer)
+            .map_err(|e| OciError::ManifestInvalid(e.to_string()))?;
+
+        let descriptor = referrer_descriptor(&manifest, referrer, buffer.len() as u64)?;
+        let matches_filter = artifact_type.is_none_or(|artifact_type| {
+            descriptor
+                .artifact_type()
+                .as_ref()
+                .is_some_and(|t| t.to_string() == *artifact_type)
+        });
+        if matches_filter {
+            descriptors.push(descriptor);
+        }
+    }
+    descriptors.sort_by_key(|d| d.digest().to_string());
+
+    let image_index = ImageIndexBuilder::default()
+        .schema_version(SCHEMA_VERSION)
+        .media_type(MediaType::ImageIndex)
+        .manifests(descriptors)
+        .build()
+        .map_err(|_| OciError::Unsupported)?;
+
+    let json_body = serde_json::to_string(&image_index).map_err(|_| OciError::Unsupported)?;
+
+    let mut response = Response::builder()
+        .status(StatusCode::OK)
+        .header(header::CONTENT_TYPE, MediaType::ImageIndex.to_string())
+        .body(body::Body::from(json_body))
+        .unwrap();
+
+    if artifact_type.is_some() {
+        response
+            .headers_mut()
+            .insert("OCI-Filters-Applied", "artifactType".parse().unwrap());
+    }
+
+    Ok(response)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::referrer_descriptor;
+    use oci_spec::image::{Digest, ImageManifest, MediaType};
+    use std::str::FromStr;
+
+    const SUBJECT: &str = "sha256:1111111111111111111111111111111111111111111111111111111111111111";
+    const REFERRER: &str =
+        "sha256:2222222222222222222222222222222222222222222222222222222222222222";
+
+    fn manifest(artifact_type: Option<&str>) -> ImageManifest {
+        let artifact_type = artifact_type
+            .map(|t| format!(r#""artifactType": "{t}","#))
+            .unwrap_or_default();
+        serde_json::from_str(&format!(
+            r#"{{
+  "schemaVersion": 2,
+  "mediaType": "application/vnd.oci.image.manifest.v1+json",
+  {artifact_type}
+  "config": {{
+    "mediaType": "application/vnd.example.sbom.config+json",
+    "size": 2,
+    "digest": "sha256:44136fa355b3678a1146ad16f7e8649e94fb4fc21fe77e8310c060f61caaff8a"
+  }},
+  "layers": [],
+  "subject": {{
+    "mediaType": "application/vnd.oci.image.manifest.v1+json",
+    "size": 100,
+    "digest": "{SUBJECT}"
+  }},
+  "annotations": {{ "org.opencontainers.image.created": "2024-01-01T00:00:00Z" }}
+}}"#
+        ))
+        .unwrap()
+    }
+
+    #[test]
+    fn referrer_descriptor_uses_artifact_type() {
+        let manifest = manifest(Some("application/vnd.example.signature"));
+        assert_eq!(
+            manifest.subject().as_ref().unwrap().digest().to_string(),
+            SUBJECT
+        );
+
+        let descriptor =
+            referrer_descriptor(&manifest, Digest::from_str(REFERRER).unwrap(), 42).unwrap();
+        assert_eq!(descriptor.media_type(), &MediaType::ImageManifest);
+        assert_eq!(descriptor.digest().to_string(), REFERRER);
+        assert_eq!(descriptor.size(), 42);
+        assert_eq!(
+            descriptor.artifact_type().as_ref().unwrap().to_string(),
+            "application/vnd.example.signature"
+        );
+        assert!(
+            descriptor
+                .annotations()
+                .as_ref()
+                .unwrap()
+                .contains_key("org.opencontainers.image.created")
+        );
+    }
+
+    #[test]
+    fn referrer_descriptor_falls_back_to_config_media_type() {
+        let descriptor =
+            referrer_descriptor(&manifest(None), Digest::from_str(REFERRER).unwrap(), 42).unwrap();
+        assert_eq!(
+            descriptor.artifact_type().as_ref().unwrap().to_string(),
+            "application/vnd.example.sbom.config+json"
+        );
+    }
+}
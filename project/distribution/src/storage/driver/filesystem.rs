@@ -218,4 +218,47 @@ impl Storage for FilesystemStorage {
             Err(e) => Err(InternalError::from(e).into()),
         }
     }
+
+    async fn put_referrer(&self, name: &str, subject: &Digest, referrer: &Digest) -> Result<()> {
+        let link_path = self
+            .path_manager
+            .referrer_link_path(name, subject, referrer);
+        let file_path = self.ensure_parent_dir(&link_path).await?;
+
+        tokio::fs::write(file_path, referrer.to_string())
+            .await
+            .map_to_internal()?;
+
+        Ok(())
+    }
+
+    async fn list_referrers(&self, name: &str, subject: &Digest) -> Result<Vec<Digest>> {
+        let mut referrers = vec![];
+        let path = self.path_manager.referrers_path(name, subject);
+
+        let mut algorithms = match read_dir(path).await {
+            Ok(rd) => rd,
+            Err(e) if e.kind() == io::ErrorKind::NotFound => {
+                return Ok(Vec::new());
+            }
+            Err(e) => {
+                return Err(InternalError::from(e).into());
+            }
+        };
+
+        while let Some(algorithm) = algorithms.next_entry().await.map_to_internal()? {
+            let mut digests = read_dir(algorithm.path()).await.map_to_internal()?;
+            while let Some(digest) = digests.next_entry().await.map_to_internal()? {
+                let digest = format!(
+                    "{}:{}",
+                    algorithm.file_name().to_string_lossy(),
+                    digest.file_name().to_string_lossy()
+                );
+                if let Ok(digest) = Digest::from_str(&digest) {
+                    referrers.push(digest);
+                }
+            }
+        }
+        Ok(referrers)
+    }
 }
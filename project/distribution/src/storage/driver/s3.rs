@@ -391,4 +391,46 @@ impl Storage for S3Storage {
 
         Ok(())
     }
+
+    async fn put_referrer(&self, name: &str, subject: &Digest, referrer: &Digest) -> Result<()> {
+        let key = self
+            .path_manager
+            .referrer_link_path(name, subject, referrer);
+        let path = self.to_object_path(&key);
+
+        self.store
+            .put(
+                &path,
+                PutPayload::from_bytes(Bytes::from(referrer.to_string())),
+            )
+            .await
+            .map_err(|e| InternalError::Others(format!("S3 put referrer error: {e}")))?;
+
+        Ok(())
+    }
+
+    async fn list_referrers(&self, name: &str, subject: &Digest) -> Result<Vec<Digest>> {
+        let key = self.path_manager.referrers_path(name, subject);
+        let prefix = self.to_object_path(&format!("{key}/"));
+
+        let objects: Vec<_> = self
+            .store
+            .list(Some(&prefix))
+            .try_collect()
+            .await
+            .map_err(|e| InternalError::Others(format!("S3 list error: {e}")))?;
+
+        // Keys look like `<prefix>/<algorithm>/<digest>/link`.
+        let referrers = objects
+            .iter()
+            .filter_map(|meta| {
+                let relative = meta.location.as_ref().strip_prefix(prefix.as_ref())?;
+                let (algorithm, rest) = relative.trim_start_matches('/').split_once('/')?;
+                let digest = rest.strip_suffix("/link")?;
+                Digest::from_str(&format!("{algorithm}:{digest}")).ok()
+            })
+            .collect();
+
+        Ok(referrers)
+    }
 }
@@ -103,4 +103,10 @@ pub trait Storage: Send + Sync {
     async fn delete_tag(&self, name: &str, tag: &str) -> Result<()>;
 
     async fn delete_blob(&self, digest: &Digest) -> Result<()>;
+
+    /// Record that the manifest `referrer` declares `subject` as its subject.
+    async fn put_referrer(&self, name: &str, subject: &Digest, referrer: &Digest) -> Result<()>;
+
+    /// Digests of all manifests in `name` whose subject is `subject`.
+    async fn list_referrers(&self, name: &str, subject: &Digest) -> Result<Vec<Digest>>;
 }
@@ -13,9 +13,15 @@
 //	└── repositories
 //	    └── <name>
 //	        └── _manifests
-//	            └── tags
-//	                └── <tag>
-//	                    └── link
+//	            ├── tags
+//	            │   └── <tag>
+//	            │       └── link
+//	            └── referrers
+//	                └── <subject algorithm>
+//	                    └── <subject digest>
+//	                        └── <algorithm>
+//	                            └── <digest>
+//	                                └── link
 //
 // The storage backend layout is broken up into a content-addressable blob
 // store and repositories. The content-addressable blob store holds most data
@@ -57,6 +63,24 @@ impl PathManager {
         format!("{}/link", self.manifest_tag_path(name, tag))
     }
 
+    pub fn referrers_path(&self, name: &str, subject: &Digest) -> String {
+        format!(
+            "{}/referrers/{}/{}",
+            self.manifest_path(name),
+            subject.algorithm(),
+            subject.digest()
+        )
+    }
+
+    pub fn referrer_link_path(&self, name: &str, subject: &Digest, referrer: &Digest) -> String {
+        format!(
+            "{}/{}/{}/link",
+            self.referrers_path(name, subject),
+            referrer.algorithm(),
+            referrer.digest()
+        )
+    }
+
     pub fn uploads_path(&self) -> String {
         format!("{}/v2/uploads", self.root_path)
     }
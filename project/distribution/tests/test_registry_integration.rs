@@ -493,6 +493,87 @@ async fn test_visibility_permissions(
 /// Note: Only debug builds are supported because the /debug/users route
 /// (used for user registration in tests) is only available when compiled
 /// with debug_assertions enabled.
+/// Test attaching an artifact to an image and listing it through the referrers API
+async fn test_referrers(vm: &mut Machine, user: &str, pass: &str) -> Result<()> {
+    tracing::info!("--- Running Test Case 4: Referrers API ---");
+
+    let api_url = format!("http://{}:{}", REGISTRY_HOST, REGISTRY_PORT);
+    let repo = "referrers-test";
+    let token = get_auth_token(vm, user, pass).await?;
+    push_minimal_image(vm, &token, user, repo, "v1").await?;
+
+    let subject_digest = exec_check(
+        vm,
+        &format!(
+            r#"curl -sf -I -H "Authorization: Bearer {}" '{}/v2/{}/{}/manifests/v1' | grep -i '^docker-content-digest:' | tr -d '\r' | cut -d' ' -f2"#,
+            token, api_url, user, repo
+        ),
+    )
+    .await?;
+    let subject_digest = subject_digest.trim();
+
+    // The config reuses the empty blob uploaded by push_minimal_image
+    let artifact = format!(
+        r#"{{"schemaVersion":2,"mediaType":"application/vnd.oci.image.manifest.v1+json","artifactType":"application/vnd.example.signature","config":{{"mediaType":"application/vnd.oci.empty.v1+json","size":0,"digest":"sha256:e3b0c44298fc1c149afbf4c8996fb92427ae41e4649b934ca495991b7852b855"}},"layers":[],"subject":{{"mediaType":"application/vnd.docker.distribution.manifest.v2+json","size":0,"digest":"{}"}}}}"#,
+        subject_digest
+    );
+    exec_check(vm, &format!("echo -n '{}' > /tmp/artifact.json", artifact)).await?;
+    let artifact_digest = exec_check(vm, "sha256sum /tmp/artifact.json | cut -d' ' -f1").await?;
+    let artifact_digest = format!("sha256:{}", artifact_digest.trim());
+
+    tracing::info!(
+        "Pushing artifact manifest referring to {}...",
+        subject_digest
+    );
+    let headers = exec_check(
+        vm,
+        &format!(
+            r#"curl -sf -D - -o /dev/null -X PUT -H "Authorization: Bearer {}" -H "Content-Type: application/vnd.oci.image.manifest.v1+json" --data-binary @/tmp/artifact.json '{}/v2/{}/{}/manifests/{}'"#,
+            token, api_url, user, repo, artifact_digest
+        ),
+    )
+    .await?;
+    if !headers
+        .to_ascii_lowercase()
+        .contains(&format!("oci-subject: {}", subject_digest))
+    {
+        anyhow::bail!(
+            "OCI-Subject header missing from manifest push response: {}",
+            headers
+        );
+    }
+
+    let referrers = exec_check(
+        vm,
+        &format!(
+            r#"curl -sf -H "Authorization: Bearer {}" '{}/v2/{}/{}/referrers/{}' | jq -r '.manifests[] | .digest + " " + .artifactType'"#,
+            token, api_url, user, repo, subject_digest
+        ),
+    )
+    .await?;
+    if referrers.trim() != format!("{} application/vnd.example.signature", artifact_digest) {
+        anyhow::bail!("Unexpected referrers for {}: {}", subject_digest, referrers);
+    }
+
+    let filtered = exec_check(
+        vm,
+        &format!(
+            r#"curl -sf -H "Authorization: Bearer {}" '{}/v2/{}/{}/referrers/{}?artifactType=application/vnd.example.sbom' | jq -r '.manifests | length'"#,
+            token, api_url, user, repo, subject_digest
+        ),
+    )
+    .await?;
+    if filtered.trim() != "0" {
+        anyhow::bail!(
+            "artifactType filter returned {} referrers, expected 0",
+            filtered.trim()
+        );
+    }
+
+    tracing::info!("[SUCCESS] Referrers API lists attached artifacts.");
+    Ok(())
+}
+
 fn get_distribution_binary_path() -> Result<PathBuf> {
     // First, try CARGO_BIN_EXE_distribution which Cargo sets for integration tests
     if let Ok(path) = std::env::var("CARGO_BIN_EXE_distribution") {
@@ -588,6 +669,7 @@ async fn test_registry_integration() -> Result<()> {
             test_anonymous_user(vm).await?;
             test_cross_namespace_push(vm, &user_a, pass_a, &user_b, pass_b).await?;
             test_visibility_permissions(vm, &user_a, pass_a, &user_b, pass_b).await?;
+            test_referrers(vm, &user_a, pass_a).await?;
 
             tracing::info!("");
             tracing::info!("=================================================");
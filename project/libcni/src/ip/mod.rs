@@ -1,6 +1,7 @@
 pub mod addr;
 pub mod ipam;
 pub mod link;
+pub mod neigh;
 pub mod route;
 pub mod utils;
 pub mod veth;
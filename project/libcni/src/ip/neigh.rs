@@ -0,0 +1,105 @@
+use std::net::IpAddr;
+
+use crate::ip::link::get_handle;
+
+use anyhow::anyhow;
+use futures::TryStreamExt;
+use netlink_packet_route::{
+    AddressFamily,
+    neighbour::{NeighbourAddress, NeighbourAttribute, NeighbourMessage, NeighbourState},
+};
+
+/// Adds or replaces a permanent neighbour (ARP/NDP) entry on a link.
+///
+/// # Arguments
+/// * `index` - The index of the network interface.
+/// * `address` - The IP address of the neighbour.
+/// * `lladdr` - The link-layer address the IP address resolves to.
+pub async fn neigh_set(index: u32, address: IpAddr, lladdr: &[u8]) -> anyhow::Result<()> {
+    let handle = get_handle()?.ok_or_else(|| anyhow!("Cannot get handle"))?;
+
+    handle
+        .neighbours()
+        .add(index, address)
+        .link_local_address(lladdr)
+        .state(NeighbourState::Permanent)
+        .replace()
+        .execute()
+        .await?;
+
+    Ok(())
+}
+
+/// Removes the neighbour entry of `address` from a link, if there is one.
+pub async fn neigh_del(index: u32, address: IpAddr) -> anyhow::Result<()> {
+    let family = match address {
+        IpAddr::V4(_) => AddressFamily::Inet,
+        IpAddr::V6(_) => AddressFamily::Inet6,
+    };
+    del_matching(family, index, |msg| destination(msg) == Some(address)).await
+}
+
+/// Adds or replaces a forwarding database entry on a link, sending the frames
+/// for `lladdr` to the remote endpoint `dst`. This is how a VXLAN device
+/// learns which host a remote VTEP lives on.
+pub async fn fdb_set(index: u32, lladdr: &[u8], dst: IpAddr) -> anyhow::Result<()> {
+    let handle = get_handle()?.ok_or_else(|| anyhow!("Cannot get handle"))?;
+
+    handle
+        .neighbours()
+        .add_bridge(index, lladdr)
+        .destination(dst)
+        .replace()
+        .execute()
+        .await?;
+
+    Ok(())
+}
+
+/// Removes the forwarding database entry of `lladdr` pointing at `dst` from a
+/// link, if there is one.
+pub async fn fdb_del(index: u32, lladdr: &[u8], dst: IpAddr) -> anyhow::Result<()> {
+    del_matching(AddressFamily::Bridge, index, |msg| {
+        destination(msg) == Some(dst) && link_local_address(msg) == Some(lladdr)
+    })
+    .await
+}
+
+async fn del_matching<F>(family: AddressFamily, index: u32, predicate: F) -> anyhow::Result<()>
+where
+    F: Fn(&NeighbourMessage) -> bool,
+{
+    let handle = get_handle()?.ok_or_else(|| anyhow!("Cannot get handle"))?;
+
+    let mut req = handle.neighbours().get();
+    req.message_mut().header.family = family;
+    let mut stream = req.execute();
+
+    let mut matched = Vec::new();
+    while let Some(msg) = stream.try_next().await? {
+        if msg.header.ifindex == index && predicate(&msg) {
+            matched.push(msg);
+        }
+    }
+
+    for msg in matched {
+        handle.neighbours().del(msg).execute().await?;
+    }
+
+    Ok(())
+}
+
+fn destination(msg: &NeighbourMessage) -> Option<IpAddr> {
+    msg.attributes.iter().find_map(|attr| match attr {
+        NeighbourAttribute::Destination(NeighbourAddress::Inet(ip)) => Some(IpAddr::V4(*ip)),
+        NeighbourAttribute::Destination(NeighbourAddress::Inet6(ip)) => Some(IpAddr::V6(*ip)),
+        _ => None,
+    })
+}
+
+fn link_local_address(msg: &NeighbourMessage) -> Option<&[u8]> {
+    msg.attributes.iter().find_map(|attr| match attr {
+        NeighbourAttribute::LinkLocalAddress(lladdr) => Some(lladdr.as_slice()),
+        _ => None,
+    })
+}
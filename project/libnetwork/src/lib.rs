@@ -1,6 +1,11 @@
 pub mod config;
 pub mod iface;
 pub mod ip;
+pub mod network_policy;
 pub mod nftables;
+pub mod overlay;
 pub mod route;
 pub mod subnet;
+pub mod vip;
+pub mod vxlan;
+pub mod wireguard;
@@ -0,0 +1,535 @@
+//! Compiles NetworkPolicies into the nftables rules of one node.
+//!
+//! The rules live in their own `inet rk8s-policy` table, so that the full
+//! syncs of the Service rules, which flush the `rk8s` tables, leave them alone,
+//! and a single set of chains covers both IPv4 and IPv6 traffic. Each node
+//! only gets the policies selecting some of its pods: ingress is enforced on
+//! the node of the destination pod and egress on the node of the source pod.
+//!
+//! For each such policy, the table has a set of the local pods it selects,
+//! split by address family since a set only holds one type of address, and
+//! a chain per direction, holding one `accept` rule per allowed combination of
+//! peer and port. Two base chains on the forward hook dispatch the packets of
+//! the selected pods to these chains, and drop those of isolated pods that no
+//! policy accepted. Egress is checked first, at a lower priority: `accept`
+//! only ends the evaluation of the current base chain, so the packet is then
+//! checked for ingress too.
+//!
+//! Traffic between pods of the same node goes through the bridge, which only
+//! reaches the forward hook with `br_netfilter` loaded and
+//! `net.bridge.bridge-nf-call-iptables=1`.
+use anyhow::{Context, Result, anyhow, bail};
+use common::{
+    IPBlock, LabelSelector, LabelSelectorOperator, NetworkPolicy, NetworkPolicyPeer,
+    NetworkPolicyPort, PodTask, PolicyType,
+};
+use ipnetwork::IpNetwork;
+use nftables::{expr, schema, stmt, types};
+use std::borrow::Cow;
+use std::collections::{BTreeSet, HashMap};
+use std::net::IpAddr;
+
+/// Table holding the NetworkPolicy rules
+pub const POLICY_TABLE: &str = "rk8s-policy";
+/// Label every namespace has, holding its name
+pub const NAMESPACE_NAME_LABEL: &str = "kubernetes.io/metadata.name";
+
+const EGRESS_CHAIN: &str = "policy-egress";
+const INGRESS_CHAIN: &str = "policy-ingress";
+const EGRESS_ISOLATED_SET: &str = "egress-isolated";
+const INGRESS_ISOLATED_SET: &str = "ingress-isolated";
+
+/// Generates the FULL policy configuration of `node_name`, replacing the
+/// previous one.
+pub fn generate_network_policy_config(
+    node_name: &str,
+    policies: &[NetworkPolicy],
+    pods: &[PodTask],
+) -> Result<String> {
+    let pods: Vec<PolicyPod> = pods.iter().filter_map(PolicyPod::new).collect();
+
+    let mut policies: Vec<&NetworkPolicy> = policies.iter().collect();
+    policies.sort_by(|a, b| {
+        (&a.metadata.namespace, &a.metadata.name).cmp(&(&b.metadata.namespace, &b.metadata.name))
+    });
+
+    // 1. Recreate the table, dropping the chains and sets of removed policies.
+    // Adding it first keeps the delete from failing on a fresh node.
+    let mut objects = vec![
+        schema::NfObject::ListObject(schema::NfListObject::Table(policy_table())),
+        schema::NfObject::CmdObject(schema::NfCmd::Delete(schema::NfListObject::Table(
+            policy_table(),
+        ))),
+        schema::NfObject::ListObject(schema::NfListObject::Table(policy_table())),
+    ];
+
+    // 2. Base chains, egress first
+    for (name, prio) in [(EGRESS_CHAIN, 0), (INGRESS_CHAIN, 1)] {
+        objects.push(schema::NfObject::ListObject(schema::NfListObject::Chain(
+            schema::Chain {
+                family: types::NfFamily::INet,
+                table: Cow::Borrowed(POLICY_TABLE),
+                name: Cow::Borrowed(name),
+                _type: Some(types::NfChainType::Filter),
+                hook: Some(types::NfHook::Forward),
+                prio: Some(prio),
+                policy: Some(types::NfChainPolicy::Accept),
+                ..Default::default()
+            },
+        )));
+        // Replies of allowed connections
+        objects.push(policy_rule(
+            name.to_string(),
+            vec![
+                stmt::Statement::Match(stmt::Match {
+                    left: expr::Expression::Named(expr::NamedExpression::CT(expr::CT {
+                        key: Cow::Borrowed("state"),
+                        family: None,
+                        dir: None,
+                    })),
+                    op: stmt::Operator::IN,
+                    right: expr::Expression::List(vec![
+                        expr::Expression::String(Cow::Borrowed("established")),
+                        expr::Expression::String(Cow::Borrowed("related")),
+                    ]),
+                }),
+                stmt::Statement::Accept(None),
+            ],
+            None,
+        ));
+    }
+
+    // 3. Policy sets and chains
+    let mut egress_isolated = BTreeSet::new();
+    let mut ingress_isolated = BTreeSet::new();
+
+    for (idx, policy) in policies.iter().enumerate() {
+        let namespace = &policy.metadata.namespace;
+        let selected: BTreeSet<IpAddr> = pods
+            .iter()
+            .filter(|pod| {
+                pod.node_name == Some(node_name)
+                    && pod.namespace == namespace
+                    && selector_matches(&policy.spec.pod_selector, pod.labels)
+            })
+            .flat_map(|pod| pod.ips.iter().copied())
+            .collect();
+        if selected.is_empty() {
+            continue;
+        }
+
+        let prefix = format!("np{idx}");
+        let pods_set = format!("{prefix}-pods");
+        let comment = format!("{}/{}", namespace, policy.metadata.name);
+        let pods_sets = ip_sets(&mut objects, &pods_set, &selected);
+
+        for policy_type in policy.spec.effective_policy_types() {
+            let (base_chain, chain, pod_field, peer_field) = match policy_type {
+                PolicyType::Egress => {
+                    egress_isolated.extend(selected.iter().copied());
+                    (EGRESS_CHAIN, format!("{prefix}-egress"), "saddr", "daddr")
+                }
+                PolicyType::Ingress => {
+                    ingress_isolated.extend(selected.iter().copied());
+                    (INGRESS_CHAIN, format!("{prefix}-ingress"), "daddr", "saddr")
+                }
+            };
+            let rules: Vec<(&[NetworkPolicyPeer], &[NetworkPolicyPort])> = match policy_type {
+                PolicyType::Egress => policy
+                    .spec
+                    .egress
+                    .iter()
+                    .map(|rule| (rule.to.as_slice(), rule.ports.as_slice()))
+                    .collect(),
+                PolicyType::Ingress => policy
+                    .spec
+                    .ingress
+                    .iter()
+                    .map(|rule| (rule.from.as_slice(), rule.ports.as_slice()))
+                    .collect(),
+            };
+
+            objects.push(schema::NfObject::ListObject(schema::NfListObject::Chain(
+                schema::Chain {
+                    family: types::NfFamily::INet,
+                    table: Cow::Borrowed(POLICY_TABLE),
+                    name: Cow::Owned(chain.clone()),
+                    ..Default::default()
+                },
+            )));
+            for (protocol, set) in &pods_sets {
+                objects.push(policy_rule(
+                    base_chain.to_string(),
+                    vec![
+                        ip_match(*protocol, pod_field, set_ref(set)),
+                        stmt::Statement::Jump(stmt::JumpTarget {
+                            target: Cow::Owned(chain.clone()),
+                        }),
+                    ],
+                    Some(comment.clone()),
+                ));
+            }
+
+            for (rule_idx, (peers, ports)) in rules.into_iter().enumerate() {
+                let peer_matches = if peers.is_empty() {
+                    // Any peer
+                    vec![vec![]]
+                } else {
+                    let peer_set = format!("{chain}-{rule_idx}");
+                    let (peer_ips, blocks) = resolve_peers(peers, namespace, &pods);
+
+                    let mut matches = Vec::new();
+                    for (protocol, set) in ip_sets(&mut objects, &peer_set, &peer_ips) {
+                        matches.push(vec![ip_match(protocol, peer_field, set_ref(&set))]);
+                    }
+                    for block in blocks {
+                        matches.push(ip_block_matches(peer_field, &block)?);
+                    }
+                    matches
+                };
+
+                let port_matches = if ports.is_empty() {
+                    // Any port
+                    vec![vec![]]
+                } else {
+                    ports.iter().map(port_matches).collect()
+                };
+
+                for peer_match in &peer_matches {
+                    for port_match in &port_matches {
+                        let mut expr = peer_match.clone();
+                        expr.extend(port_match.iter().cloned());
+                        expr.push(stmt::Statement::Accept(None));
+                        objects.push(policy_rule(chain.clone(), expr, None));
+                    }
+                }
+            }
+        }
+    }
+
+    // 4. Drop what no policy accepted for isolated pods
+    for (base_chain, set, field, isolated) in [
+        (EGRESS_CHAIN, EGRESS_ISOLATED_SET, "saddr", &egress_isolated),
+        (
+            INGRESS_CHAIN,
+            INGRESS_ISOLATED_SET,
+            "daddr",
+            &ingress_isolated,
+        ),
+    ] {
+        for (protocol, set) in ip_sets(&mut objects, set, isolated) {
+            objects.push(policy_rule(
+                base_chain.to_string(),
+                vec![
+                    ip_match(protocol, field, set_ref(&set)),
+                    stmt::Statement::Drop(None),
+                ],
+                Some("isolated by NetworkPolicy".to_string()),
+            ));
+        }
+    }
+
+    let nftables = schema::Nftables {
+        objects: Cow::Owned(objects),
+    };
+    serde_json::to_string(&nftables).map_err(|e| anyhow!(e))
+}
+
+/// Removes the policy table, lifting the isolation of all pods of a node.
+pub fn generate_network_policy_delete() -> Result<String> {
+    let objects = vec![
+        schema::NfObject::ListObject(schema::NfListObject::Table(policy_table())),
+        schema::NfObject::CmdObject(schema::NfCmd::Delete(schema::NfListObject::Table(
+            policy_table(),
+        ))),
+    ];
+    let nftables = schema::Nftables {
+        objects: Cow::Owned(objects),
+    };
+    serde_json::to_string(&nftables).map_err(|e| anyhow!(e))
+}
+
+/// Checks what the rules of a policy are compiled from, so that a bad policy
+/// is rejected when it is created rather than breaking the rules of every node.
+pub fn validate_network_policy(policy: &NetworkPolicy) -> Result<()> {
+    let ingress = policy
+        .spec
+        .ingress
+        .iter()
+        .map(|rule| (&rule.from, &rule.ports));
+    let egress = policy
+        .spec
+        .egress
+        .iter()
+        .map(|rule| (&rule.to, &rule.ports));
+
+    for (peers, ports) in ingress.chain(egress) {
+        for block in peers.iter().filter_map(|peer| peer.ip_block.as_ref()) {
+            ip_block_matches("saddr", block)?;
+        }
+        for port in ports {
+            let protocol = port.protocol.to_lowercase();
+            if !matches!(protocol.as_str(), "tcp" | "udp" | "sctp") {
+                bail!("unsupported protocol {}", port.protocol);
+            }
+            for value in port.port.iter().chain(port.end_port.iter()) {
+                if !(1..=65535).contains(value) {
+                    bail!("invalid port {value}");
+                }
+            }
+            if port.end_port.is_some() && port.port.is_none() {
+                bail!("endPort requires port");
+            }
+        }
+    }
+    Ok(())
+}
+
+/// Whether `labels` satisfy `selector`: supports MatchLabels and
+/// MatchExpressions, an empty selector matches everything.
+pub fn selector_matches(selector: &LabelSelector, labels: &HashMap<String, String>) -> bool {
+    let labels_match = selector
+        .match_labels
+        .iter()
+        .all(|(k, v)| labels.get(k) == Some(v));
+
+    labels_match
+        && selector.match_expressions.iter().all(|req| {
+            let value = labels.get(&req.key);
+            match req.operator {
+                LabelSelectorOperator::In => value.is_some_and(|v| req.values.contains(v)),
+                LabelSelectorOperator::NotIn => !value.is_some_and(|v| req.values.contains(v)),
+                LabelSelectorOperator::Exists => value.is_some(),
+                LabelSelectorOperator::DoesNotExist => value.is_none(),
+            }
+        })
+}
+
+/// The fields of a pod the policies look at, for pods that have an IP
+struct PolicyPod<'a> {
+    /// IPv4 and/or IPv6 addresses of the pod
+    ips: Vec<IpAddr>,
+    namespace: &'a str,
+    node_name: Option<&'a str>,
+    labels: &'a HashMap<String, String>,
+}
+
+impl<'a> PolicyPod<'a> {
+    fn new(pod: &'a PodTask) -> Option<Self> {
+        let ips: Vec<IpAddr> = pod
+            .status
+            .ips()
+            .into_iter()
+            .filter_map(|ip| ip.parse().ok())
+            .collect();
+        if ips.is_empty() {
+            return None;
+        }
+        Some(Self {
+            ips,
+            namespace: &pod.metadata.namespace,
+            node_name: pod.spec.node_name.as_deref(),
+            labels: &pod.metadata.labels,
+        })
+    }
+
+    fn namespace_labels(&self) -> HashMap<String, String> {
+        HashMap::from([(NAMESPACE_NAME_LABEL.to_string(), self.namespace.to_string())])
+    }
+}
+
+/// IPs of the pods selected by `peers`, and their IP blocks
+fn resolve_peers(
+    peers: &[NetworkPolicyPeer],
+    policy_namespace: &str,
+    pods: &[PolicyPod],
+) -> (BTreeSet<IpAddr>, Vec<IPBlock>) {
+    let mut ips = BTreeSet::new();
+    let mut blocks = Vec::new();
+
+    for peer in peers {
+        if let Some(block) = &peer.ip_block {
+            blocks.push(block.clone());
+            continue;
+        }
+        if peer.pod_selector.is_none() && peer.namespace_selector.is_none() {
+            continue;
+        }
+
+        ips.extend(
+            pods.iter()
+                .filter(|pod| match &peer.namespace_selector {
+                    Some(selector) => selector_matches(selector, &pod.namespace_labels()),
+                    None => pod.namespace == policy_namespace,
+                })
+                .filter(|pod| {
+                    peer.pod_selector
+                        .as_ref()
+                        .is_none_or(|selector| selector_matches(selector, pod.labels))
+                })
+                .flat_map(|pod| pod.ips.iter().copied()),
+        );
+    }
+
+    (ips, blocks)
+}
+
+fn policy_table() -> schema::Table<'static> {
+    schema::Table {
+        family: types::NfFamily::INet,
+        name: Cow::Borrowed(POLICY_TABLE),
+        ..Default::default()
+    }
+}
+
+fn policy_rule(
+    chain: String,
+    expr: Vec<stmt::Statement<'static>>,
+    comment: Option<String>,
+) -> schema::NfObject<'static> {
+    schema::NfObject::ListObject(schema::NfListObject::Rule(schema::Rule {
+        family: types::NfFamily::INet,
+        table: Cow::Borrowed(POLICY_TABLE),
+        chain: Cow::Owned(chain),
+        expr: Cow::Owned(expr),
+        comment: comment.map(Cow::Owned),
+        ..Default::default()
+    }))
+}
+
+/// Adds a set of the IPv4 addresses of `ips` named `name` and a set of the
+/// IPv6 ones named `{name}6`, skipping empty ones. Returns the payload
+/// protocol and the name of each added set.
+fn ip_sets(
+    objects: &mut Vec<schema::NfObject<'static>>,
+    name: &str,
+    ips: &BTreeSet<IpAddr>,
+) -> Vec<(&'static str, String)> {
+    let (v4, v6): (Vec<IpAddr>, Vec<IpAddr>) = ips.iter().copied().partition(|ip| ip.is_ipv4());
+
+    let mut sets = Vec::new();
+    for (protocol, set_name, set_type, ips) in [
+        ("ip", name.to_string(), schema::SetType::Ipv4Addr, v4),
+        ("ip6", format!("{name}6"), schema::SetType::Ipv6Addr, v6),
+    ] {
+        if ips.is_empty() {
+            continue;
+        }
+        objects.push(schema::NfObject::ListObject(schema::NfListObject::Set(
+            Box::new(schema::Set {
+                family: types::NfFamily::INet,
+                table: Cow::Borrowed(POLICY_TABLE),
+                name: Cow::Owned(set_name.clone()),
+                set_type: schema::SetTypeValue::Single(set_type),
+                elem: Some(Cow::Owned(
+                    ips.iter()
+                        .map(|ip| expr::Expression::String(Cow::Owned(ip.to_string())))
+                        .collect(),
+                )),
+                ..Default::default()
+            }),
+        )));
+        sets.push((protocol, set_name));
+    }
+    sets
+}
+
+fn set_ref(name: &str) -> expr::Expression<'static> {
+    expr::Expression::String(Cow::Owned(format!("@{name}")))
+}
+
+/// The `field` address of the `ip` or `ip6` header, which in an `inet` table
+/// only matches packets of that family
+fn ip_field(protocol: &'static str, field: &'static str) -> expr::Expression<'static> {
+    expr::Expression::Named(expr::NamedExpression::Payload(expr::Payload::PayloadField(
+        expr::PayloadField {
+            protocol: Cow::Borrowed(protocol),
+            field: Cow::Borrowed(field),
+        },
+    )))
+}
+
+fn ip_match(
+    protocol: &'static str,
+    field: &'static str,
+    right: expr::Expression<'static>,
+) -> stmt::Statement<'static> {
+    stmt::Statement::Match(stmt::Match {
+        left: ip_field(protocol, field),
+        op: stmt::Operator::EQ,
+        right,
+    })
+}
+
+fn prefix(network: IpNetwork) -> expr::Expression<'static> {
+    expr::Expression::Named(expr::NamedExpression::Prefix(expr::Prefix {
+        addr: Box::new(expr::Expression::String(Cow::Owned(
+            network.network().to_string(),
+        ))),
+        len: network.prefix() as u32,
+    }))
+}
+
+/// Matches of the addresses of `block`, minus its exceptions
+fn ip_block_matches(field: &'static str, block: &IPBlock) -> Result<Vec<stmt::Statement<'static>>> {
+    let cidr: IpNetwork = block
+        .cidr
+        .parse()
+        .with_context(|| format!("invalid ipBlock cidr {}", block.cidr))?;
+    let protocol = if cidr.is_ipv4() { "ip" } else { "ip6" };
+
+    let mut matches = vec![ip_match(protocol, field, prefix(cidr))];
+    for except in &block.except {
+        let except: IpNetwork = except
+            .parse()
+            .with_context(|| format!("invalid ipBlock except {except}"))?;
+        if except.is_ipv4() != cidr.is_ipv4() {
+            bail!(
+                "ipBlock except {except} is not of the family of cidr {}",
+                block.cidr
+            );
+        }
+        matches.push(stmt::Statement::Match(stmt::Match {
+            left: ip_field(protocol, field),
+            op: stmt::Operator::NEQ,
+            right: prefix(except),
+        }));
+    }
+    Ok(matches)
+}
+
+/// Matches of `port`, the transport protocol before the port as in the
+/// Service rules
+fn port_matches(port: &NetworkPolicyPort) -> Vec<stmt::Statement<'static>> {
+    let protocol = port.protocol.to_lowercase();
+    let mut matches = vec![stmt::Statement::Match(stmt::Match {
+        left: expr::Expression::Named(expr::NamedExpression::Meta(expr::Meta {
+            key: expr::MetaKey::L4proto,
+        })),
+        op: stmt::Operator::EQ,
+        right: expr::Expression::String(Cow::Owned(protocol.clone())),
+    })];
+
+    let dport = |op, value: i32| {
+        stmt::Statement::Match(stmt::Match {
+            left: expr::Expression::Named(expr::NamedExpression::Payload(
+                expr::Payload::PayloadField(expr::PayloadField {
+                    protocol: Cow::Owned(protocol.clone()),
+                    field: Cow::Borrowed("dport"),
+                }),
+            )),
+            op,
+            right: expr::Expression::Number(value as u32),
+        })
+    };
+
+    match (port.port, port.end_port) {
+        (Some(start), Some(end)) if end > start => {
+            matches.push(dport(stmt::Operator::GEQ, start));
+            matches.push(dport(stmt::Operator::LEQ, end));
+        }
+        (Some(port), _) => matches.push(dport(stmt::Operator::EQ, port)),
+        (None, _) => {}
+    }
+    matches
+}
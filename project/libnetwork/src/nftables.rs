@@ -1,24 +1,109 @@
 use anyhow::Result;
-use common;
+use common::{self, IpFamily};
 use nftables::{expr, schema, stmt, types};
 use serde_json::json;
 use std::borrow::Cow;
+use std::collections::HashSet;
 
-// Mark used to tag service traffic for postrouting masquerade
+// Mark used to tag hairpin service traffic (a pod reaching itself through a
+// Service) for postrouting masquerade
 const SERVICE_TRAFFIC_MARK: u32 = 0x4000;
 
+// Mark telling that no backend was selected yet while looking up session affinity.
+// Backend slots stay below it, so endpoint weights are ignored when they add up past it.
+const SELECT_PENDING_MARK: u32 = 0x2000;
+
 /// Generates the FULL configuration (Table, Base Chains, and all Service Chains).
 /// Used for initialization.
+///
+/// `externalTrafficPolicy: Local` needs to know the node the rules are for and is
+/// ignored here, see `generate_node_nftables_config`.
 pub fn generate_nftables_config(
     services: &[common::ServiceTask],
     endpoints: &[common::Endpoint],
+) -> Result<String> {
+    build_nftables_config(services, endpoints, None)
+}
+
+/// Generates the FULL configuration for the node `node_name`, whose NodePort and
+/// LoadBalancer traffic only reaches local endpoints for Services with
+/// `externalTrafficPolicy: Local`.
+pub fn generate_node_nftables_config(
+    services: &[common::ServiceTask],
+    endpoints: &[common::Endpoint],
+    node_name: &str,
+) -> Result<String> {
+    build_nftables_config(services, endpoints, Some(node_name))
+}
+
+fn build_nftables_config(
+    services: &[common::ServiceTask],
+    endpoints: &[common::Endpoint],
+    node_name: Option<&str>,
 ) -> Result<String> {
     let mut objects: Vec<schema::NfObject> = Vec::new();
 
+    push_table_objects(&mut objects, IpFamily::IPv4);
+    // The ip6 table only exists on the nodes while a Service has an IPv6 ClusterIP.
+    // Without one, drop the table a previous sync may have left, adding it first
+    // so that the delete does not fail on nodes that never had it.
+    if services
+        .iter()
+        .any(|svc| svc.spec.cluster_ip_of(IpFamily::IPv6).is_some())
+    {
+        push_table_objects(&mut objects, IpFamily::IPv6);
+    } else {
+        let ip6_table = schema::Table {
+            family: nf_family(IpFamily::IPv6),
+            name: Cow::Borrowed("rk8s"),
+            ..Default::default()
+        };
+        objects.push(schema::NfObject::ListObject(schema::NfListObject::Table(
+            ip6_table.clone(),
+        )));
+        objects.push(schema::NfObject::CmdObject(schema::NfCmd::Delete(
+            schema::NfListObject::Table(ip6_table),
+        )));
+    }
+
+    // Generate Service Chains (Full Sync)
+    let mut parsed_endpoints = std::collections::HashMap::new();
+    for ep in endpoints {
+        parsed_endpoints.insert(
+            (ep.metadata.namespace.clone(), ep.metadata.name.clone()),
+            ep,
+        );
+    }
+
+    for svc in services {
+        let ep = parsed_endpoints
+            .get(&(svc.metadata.namespace.clone(), svc.metadata.name.clone()))
+            .map(|&e| e.clone())
+            .unwrap_or_else(|| common::Endpoint {
+                api_version: "v1".into(),
+                kind: "Endpoints".into(),
+                metadata: svc.metadata.clone(),
+                subsets: vec![],
+            });
+
+        // Full Sync: Do not include delete commands for dispatch rules, as table is flushed.
+        let update_objects = generate_service_update_objects(svc, &ep, true, node_name)?;
+        objects.extend(update_objects);
+    }
+
+    let nftables = schema::Nftables {
+        objects: Cow::Owned(objects),
+    };
+    serde_json::to_string(&nftables).map_err(|e| anyhow::anyhow!(e))
+}
+
+/// Adds the `rk8s` table of `family`, flushed, with its base chains, dispatch
+/// rules and masquerade rules
+fn push_table_objects(objects: &mut Vec<schema::NfObject<'static>>, family: IpFamily) {
     // 1. Base Table
     objects.push(schema::NfObject::ListObject(schema::NfListObject::Table(
         schema::Table {
-            family: types::NfFamily::IP,
+            family: nf_family(family),
             name: Cow::Borrowed("rk8s"),
             ..Default::default()
         },
@@ -27,7 +112,7 @@ pub fn generate_nftables_config(
     // 2. Flush Table (Command)
     objects.push(schema::NfObject::CmdObject(schema::NfCmd::Flush(
         schema::FlushObject::Table(schema::Table {
-            family: types::NfFamily::IP,
+            family: nf_family(family),
             name: Cow::Borrowed("rk8s"),
             ..Default::default()
         }),
@@ -89,7 +174,7 @@ pub fn generate_nftables_config(
     for (name, ctype, hook, prio, policy) in base_chains {
         objects.push(schema::NfObject::ListObject(schema::NfListObject::Chain(
             schema::Chain {
-                family: types::NfFamily::IP,
+                family: nf_family(family),
                 table: Cow::Borrowed("rk8s"),
                 name: Cow::Borrowed(name),
                 _type: Some(ctype),
@@ -106,7 +191,7 @@ pub fn generate_nftables_config(
     for name in custom_chains {
         objects.push(schema::NfObject::ListObject(schema::NfListObject::Chain(
             schema::Chain {
-                family: types::NfFamily::IP,
+                family: nf_family(family),
                 table: Cow::Borrowed("rk8s"),
                 name: Cow::Borrowed(name),
                 ..Default::default()
@@ -123,7 +208,7 @@ pub fn generate_nftables_config(
     for (chain, target) in jumps {
         objects.push(schema::NfObject::ListObject(schema::NfListObject::Rule(
             schema::Rule {
-                family: types::NfFamily::IP,
+                family: nf_family(family),
                 table: Cow::Borrowed("rk8s"),
                 chain: Cow::Borrowed(chain),
                 expr: Cow::Owned(vec![stmt::Statement::Jump(stmt::JumpTarget {
@@ -138,7 +223,7 @@ pub fn generate_nftables_config(
     // TCP
     objects.push(schema::NfObject::ListObject(schema::NfListObject::Rule(
         schema::Rule {
-            family: types::NfFamily::IP,
+            family: nf_family(family),
             table: Cow::Borrowed("rk8s"),
             chain: Cow::Borrowed("services"),
             expr: Cow::Owned(vec![
@@ -159,7 +244,7 @@ pub fn generate_nftables_config(
     // UDP
     objects.push(schema::NfObject::ListObject(schema::NfListObject::Rule(
         schema::Rule {
-            family: types::NfFamily::IP,
+            family: nf_family(family),
             table: Cow::Borrowed("rk8s"),
             chain: Cow::Borrowed("services"),
             expr: Cow::Owned(vec![
@@ -179,11 +264,11 @@ pub fn generate_nftables_config(
     )));
 
     // 7. Masquerade Rules (Granular policies)
-    // Scenario 1: Pod → ClusterIP → Other Pod (identified by the service mark)
-    // When traffic is marked, it indicates Pod-to-Pod traffic via Service, requiring SNAT
+    // Scenario 1: Pod → ClusterIP → Self (Hairpin, identified by the service mark)
+    // The reply would otherwise go from the pod to itself, bypassing the DNAT
     objects.push(schema::NfObject::ListObject(schema::NfListObject::Rule(
         schema::Rule {
-            family: types::NfFamily::IP,
+            family: nf_family(family),
             table: Cow::Borrowed("rk8s"),
             chain: Cow::Borrowed("masquerade"),
             expr: Cow::Owned(vec![
@@ -198,57 +283,54 @@ pub fn generate_nftables_config(
                 // Action: Masquerade
                 stmt::Statement::Masquerade(None),
             ]),
-            comment: Some(Cow::Borrowed("Pod → ClusterIP → Other Pod")),
+            comment: Some(Cow::Borrowed("Pod → ClusterIP → Self")),
             ..Default::default()
         },
     )));
 
     // Scenario 2: Pod → Same Node Pod - No handling (no rules, direct routing)
-    // Scenario 3: Pod → Service → Self (Hairpin) - No handling (no rules, loopback)
+    // Scenario 3: Pod → ClusterIP → Other Pod - No handling, the pod address is kept
+    // as source for the NetworkPolicies of the destination
     // Note: These two scenarios don't require rules; traffic passes naturally without masquerade
-
-    // 8. Generate Service Chains (Full Sync)
-    let mut parsed_endpoints = std::collections::HashMap::new();
-    for ep in endpoints {
-        parsed_endpoints.insert(
-            (ep.metadata.namespace.clone(), ep.metadata.name.clone()),
-            ep,
-        );
-    }
-
-    for svc in services {
-        let ep = parsed_endpoints
-            .get(&(svc.metadata.namespace.clone(), svc.metadata.name.clone()))
-            .map(|&e| e.clone())
-            .unwrap_or_else(|| common::Endpoint {
-                api_version: "v1".into(),
-                kind: "Endpoints".into(),
-                metadata: svc.metadata.clone(),
-                subsets: vec![],
-            });
-
-        // Full Sync: Do not include delete commands for dispatch rules, as table is flushed.
-        let update_objects = generate_service_update_objects(svc, &ep, true)?;
-        objects.extend(update_objects);
-    }
-
-    let nftables = schema::Nftables {
-        objects: Cow::Owned(objects),
-    };
-    serde_json::to_string(&nftables).map_err(|e| anyhow::anyhow!(e))
 }
 
 fn generate_service_update_objects(
     svc: &common::ServiceTask,
     ep: &common::Endpoint,
     full_sync: bool,
+    node_name: Option<&str>,
 ) -> Result<Vec<schema::NfObject<'static>>> {
-    let cluster_ip = match svc.spec.cluster_ip.as_deref() {
-        Some(ip) if ip != "None" && !ip.is_empty() => ip,
-        _ => return Ok(Vec::new()),
-    };
-
     let mut objects = Vec::new();
+    // A dual-stack Service gets the same chains in the table of each family
+    for family in [IpFamily::IPv4, IpFamily::IPv6] {
+        if let Some(cluster_ip) = svc.spec.cluster_ip_of(family) {
+            push_service_objects(
+                &mut objects,
+                svc,
+                ep,
+                family,
+                &cluster_ip.to_string(),
+                full_sync,
+                node_name,
+            );
+        }
+    }
+    Ok(objects)
+}
+
+/// Adds the rules of `svc` in the table of `family`, for its ClusterIP
+/// `cluster_ip` of this family
+fn push_service_objects(
+    objects: &mut Vec<schema::NfObject<'static>>,
+    svc: &common::ServiceTask,
+    ep: &common::Endpoint,
+    family: IpFamily,
+    cluster_ip: &str,
+    full_sync: bool,
+    node_name: Option<&str>,
+) {
+    let lb_ip = load_balancer_ip_of(svc, family);
+    let affinity_timeout = svc.spec.client_ip_affinity_timeout();
 
     for svc_port in &svc.spec.ports {
         let protocol = svc_port.protocol.to_lowercase();
@@ -261,10 +343,15 @@ fn generate_service_update_objects(
         } else {
             "services_tcp"
         };
+        // Only the rules of a given node dispatch to the endpoints of that node
+        let local_chain =
+            node_name.and_then(|_| local_chain_name(svc, family, svc_port, &chain_name));
+        let external_chain = local_chain.as_deref().unwrap_or(&chain_name);
 
         if !full_sync {
             // Delete old dispatch rule for ClusterIP to avoid duplicates (Incremental only)
             let delete_clusterip_rule = create_dispatch_rule(
+                family,
                 dispatch_chain,
                 &protocol,
                 cluster_ip,
@@ -278,22 +365,38 @@ fn generate_service_update_objects(
             // Delete old dispatch rule for NodePort if exists (Incremental only)
             if let Some(node_port) = svc_port.node_port {
                 let delete_nodeport_rule = create_dispatch_rule(
+                    family,
                     dispatch_chain,
                     &protocol,
-                    "0.0.0.0/0",
+                    any_addr(family),
                     node_port,
-                    &chain_name,
+                    external_chain,
                 );
                 objects.push(schema::NfObject::CmdObject(schema::NfCmd::Delete(
                     schema::NfListObject::Rule(delete_nodeport_rule),
                 )));
             }
+
+            // Delete old dispatch rule for the LoadBalancer VIP if exists (Incremental only)
+            if let Some(lb_ip) = lb_ip {
+                let delete_lb_rule = create_dispatch_rule(
+                    family,
+                    dispatch_chain,
+                    &protocol,
+                    lb_ip,
+                    svc_port.port,
+                    external_chain,
+                );
+                objects.push(schema::NfObject::CmdObject(schema::NfCmd::Delete(
+                    schema::NfListObject::Rule(delete_lb_rule),
+                )));
+            }
         }
 
         // 1. Create Chain
         objects.push(schema::NfObject::ListObject(schema::NfListObject::Chain(
             schema::Chain {
-                family: types::NfFamily::IP,
+                family: nf_family(family),
                 table: Cow::Borrowed("rk8s"),
                 name: Cow::Owned(chain_name.clone()),
                 ..Default::default()
@@ -303,7 +406,7 @@ fn generate_service_update_objects(
         // 3. Flush & Delete Chain
         objects.push(schema::NfObject::CmdObject(schema::NfCmd::Flush(
             schema::FlushObject::Chain(schema::Chain {
-                family: types::NfFamily::IP,
+                family: nf_family(family),
                 table: Cow::Borrowed("rk8s"),
                 name: Cow::Owned(chain_name.clone()),
                 ..Default::default()
@@ -314,7 +417,7 @@ fn generate_service_update_objects(
         // Mark ClusterIP traffic for SNAT identification in postrouting
         objects.push(schema::NfObject::ListObject(schema::NfListObject::Rule(
             schema::Rule {
-                family: types::NfFamily::IP,
+                family: nf_family(family),
                 table: Cow::Borrowed("rk8s"),
                 chain: Cow::Borrowed(dispatch_chain),
                 expr: Cow::Owned(vec![
@@ -328,7 +431,7 @@ fn generate_service_update_objects(
                     stmt::Statement::Match(stmt::Match {
                         left: expr::Expression::Named(expr::NamedExpression::Payload(
                             expr::Payload::PayloadField(expr::PayloadField {
-                                protocol: Cow::Borrowed("ip"),
+                                protocol: Cow::Borrowed(addr_protocol(family)),
                                 field: Cow::Borrowed("daddr"),
                             }),
                         )),
@@ -345,13 +448,6 @@ fn generate_service_update_objects(
                         op: stmt::Operator::EQ,
                         right: expr::Expression::Number(svc_port.port as u32),
                     }),
-                    // Mark ClusterIP path for postrouting SNAT (NodePort path won't have this mark)
-                    stmt::Statement::Mangle(stmt::Mangle {
-                        key: expr::Expression::Named(expr::NamedExpression::Meta(expr::Meta {
-                            key: expr::MetaKey::Mark,
-                        })),
-                        value: expr::Expression::Number(SERVICE_TRAFFIC_MARK),
-                    }),
                     stmt::Statement::Jump(stmt::JumpTarget {
                         target: Cow::Owned(chain_name.clone()),
                     }),
@@ -361,31 +457,14 @@ fn generate_service_update_objects(
         )));
 
         // 4. Build Backends
-        let mut backends = Vec::new();
-        for subset in &ep.subsets {
-            let target_port = subset
-                .ports
-                .iter()
-                .find(|p| match (&svc_port.name, &p.name) {
-                    (Some(n1), Some(n2)) => n1 == n2,
-                    (None, None) => true,
-                    (None, Some(_)) => false,
-                    _ => false,
-                });
-
-            if let Some(tp) = target_port {
-                for addr in &subset.addresses {
-                    backends.push((addr.ip.clone(), tp.port));
-                }
-            }
-        }
+        let backends = collect_backends(svc_port, ep, family);
 
         // 5. Generate Rules in svc chain
         if backends.is_empty() {
             // Reject
             objects.push(schema::NfObject::ListObject(schema::NfListObject::Rule(
                 schema::Rule {
-                    family: types::NfFamily::IP,
+                    family: nf_family(family),
                     table: Cow::Borrowed("rk8s"),
                     chain: Cow::Owned(chain_name.clone()),
                     expr: Cow::Owned(vec![
@@ -396,120 +475,72 @@ fn generate_service_update_objects(
                 },
             )));
         } else {
-            // Load Balancing
-            let num_backends = backends.len() as u32;
+            push_backend_rules(
+                objects,
+                family,
+                &chain_name,
+                &protocol,
+                &backends,
+                affinity_timeout,
+                SERVICE_TRAFFIC_MARK,
+            );
+        }
 
-            if num_backends > 1 {
-                // 1. Set backend index for load balancing (temporarily overwrites the service mark, restored after selection)
-                objects.push(schema::NfObject::ListObject(schema::NfListObject::Rule(
-                    schema::Rule {
-                        family: types::NfFamily::IP,
-                        table: Cow::Borrowed("rk8s"),
-                        chain: Cow::Owned(chain_name.clone()),
-                        expr: Cow::Owned(vec![stmt::Statement::Mangle(stmt::Mangle {
-                            key: expr::Expression::Named(expr::NamedExpression::Meta(expr::Meta {
-                                key: expr::MetaKey::Mark,
-                            })),
-                            value: expr::Expression::Named(expr::NamedExpression::Numgen(
-                                expr::Numgen {
-                                    mode: expr::NgMode::Random,
-                                    ng_mod: num_backends,
-                                    offset: Some(0),
-                                },
-                            )),
-                        })]),
-                        comment: Some(Cow::Borrowed("LB: set backend index")),
-                        ..Default::default()
-                    },
-                )));
+        // 6. Node-local chain for externalTrafficPolicy: Local
+        // Only the endpoints of this node are used and the service mark is cleared,
+        // so the client source IP is neither masqueraded nor lost on a second hop.
+        if let Some(local_chain) = &local_chain {
+            objects.push(schema::NfObject::ListObject(schema::NfListObject::Chain(
+                schema::Chain {
+                    family: nf_family(family),
+                    table: Cow::Borrowed("rk8s"),
+                    name: Cow::Owned(local_chain.clone()),
+                    ..Default::default()
+                },
+            )));
+            objects.push(schema::NfObject::CmdObject(schema::NfCmd::Flush(
+                schema::FlushObject::Chain(schema::Chain {
+                    family: nf_family(family),
+                    table: Cow::Borrowed("rk8s"),
+                    name: Cow::Owned(local_chain.clone()),
+                    ..Default::default()
+                }),
+            )));
 
-                // 2. Dispatch to backends based on index (service mark already set by dispatch rule)
-                for (i, (ip, port)) in backends.iter().enumerate() {
-                    objects.push(schema::NfObject::ListObject(schema::NfListObject::Rule(
-                        schema::Rule {
-                            family: types::NfFamily::IP,
-                            table: Cow::Borrowed("rk8s"),
-                            chain: Cow::Owned(chain_name.clone()),
-                            expr: Cow::Owned(vec![
-                                // Ensure l4proto is matched before DNAT with port
-                                stmt::Statement::Match(stmt::Match {
-                                    left: expr::Expression::Named(expr::NamedExpression::Meta(
-                                        expr::Meta {
-                                            key: expr::MetaKey::L4proto,
-                                        },
-                                    )),
-                                    op: stmt::Operator::EQ,
-                                    right: expr::Expression::String(Cow::Owned(protocol.clone())),
-                                }),
-                                // Match backend index
-                                stmt::Statement::Match(stmt::Match {
-                                    left: expr::Expression::Named(expr::NamedExpression::Meta(
-                                        expr::Meta {
-                                            key: expr::MetaKey::Mark,
-                                        },
-                                    )),
-                                    op: stmt::Operator::EQ,
-                                    right: expr::Expression::Number(i as u32),
-                                }),
-                                // Restore service mark for masquerade identification in postrouting
-                                stmt::Statement::Mangle(stmt::Mangle {
-                                    key: expr::Expression::Named(expr::NamedExpression::Meta(
-                                        expr::Meta {
-                                            key: expr::MetaKey::Mark,
-                                        },
-                                    )),
-                                    value: expr::Expression::Number(SERVICE_TRAFFIC_MARK),
-                                }),
-                                // Perform DNAT
-                                stmt::Statement::DNAT(Some(stmt::NAT {
-                                    addr: Some(expr::Expression::String(Cow::Owned(ip.clone()))),
-                                    family: Some(stmt::NATFamily::IP),
-                                    port: Some(expr::Expression::Number(*port as u32)),
-                                    flags: None,
-                                })),
-                            ]),
-                            ..Default::default()
-                        },
-                    )));
-                }
-            } else {
-                // Single backend (mark already set by ClusterIP dispatch rule, preserved for masquerade)
-                let (ip, port) = &backends[0];
+            let local_backends: Vec<Backend> = backends
+                .iter()
+                .filter(|b| b.node_name.as_deref() == node_name)
+                .cloned()
+                .collect();
+            if local_backends.is_empty() {
                 objects.push(schema::NfObject::ListObject(schema::NfListObject::Rule(
                     schema::Rule {
-                        family: types::NfFamily::IP,
+                        family: nf_family(family),
                         table: Cow::Borrowed("rk8s"),
-                        chain: Cow::Owned(chain_name.clone()),
-                        expr: Cow::Owned(vec![
-                            // Ensure l4proto is matched before DNAT with port
-                            stmt::Statement::Match(stmt::Match {
-                                left: expr::Expression::Named(expr::NamedExpression::Meta(
-                                    expr::Meta {
-                                        key: expr::MetaKey::L4proto,
-                                    },
-                                )),
-                                op: stmt::Operator::EQ,
-                                right: expr::Expression::String(Cow::Owned(protocol.clone())),
-                            }),
-                            // Perform DNAT (mark already set by ClusterIP dispatch rule)
-                            stmt::Statement::DNAT(Some(stmt::NAT {
-                                addr: Some(expr::Expression::String(Cow::Owned(ip.clone()))),
-                                family: Some(stmt::NATFamily::IP),
-                                port: Some(expr::Expression::Number(*port as u32)),
-                                flags: None,
-                            })),
-                        ]),
+                        chain: Cow::Owned(local_chain.clone()),
+                        expr: Cow::Owned(vec![stmt::Statement::Drop(None)]),
+                        comment: Some(Cow::Borrowed("Drop (no local endpoints)")),
                         ..Default::default()
                     },
                 )));
+            } else {
+                push_backend_rules(
+                    objects,
+                    family,
+                    local_chain,
+                    &protocol,
+                    &local_backends,
+                    affinity_timeout,
+                    0,
+                );
             }
         }
 
-        // 6. NodePort Logic
+        // 7. NodePort Logic
         if let Some(node_port) = svc_port.node_port {
             objects.push(schema::NfObject::ListObject(schema::NfListObject::Rule(
                 schema::Rule {
-                    family: types::NfFamily::IP,
+                    family: nf_family(family),
                     table: Cow::Borrowed("rk8s"),
                     chain: Cow::Borrowed(dispatch_chain),
                     expr: Cow::Owned(vec![
@@ -534,20 +565,388 @@ fn generate_service_update_objects(
                             right: expr::Expression::Number(node_port as u32),
                         }),
                         stmt::Statement::Jump(stmt::JumpTarget {
-                            target: Cow::Owned(chain_name.clone()),
+                            target: Cow::Owned(external_chain.to_string()),
                         }),
                     ]),
                     ..Default::default()
                 },
             )));
         }
+
+        // 8. LoadBalancer VIP (not marked, like NodePort traffic)
+        if let Some(lb_ip) = lb_ip {
+            objects.push(schema::NfObject::ListObject(schema::NfListObject::Rule(
+                create_dispatch_rule(
+                    family,
+                    dispatch_chain,
+                    &protocol,
+                    lb_ip,
+                    svc_port.port,
+                    external_chain,
+                ),
+            )));
+        }
     }
+}
 
-    Ok(objects)
+/// One endpoint address of a Service port
+#[derive(Clone)]
+struct Backend {
+    ip: String,
+    port: i32,
+    weight: u32,
+    node_name: Option<String>,
+}
+
+/// Ready endpoint addresses of `family` serving `svc_port`, without the drained ones
+fn collect_backends(
+    svc_port: &common::ServicePort,
+    ep: &common::Endpoint,
+    family: IpFamily,
+) -> Vec<Backend> {
+    let mut backends = Vec::new();
+    for subset in &ep.subsets {
+        let target_port = subset
+            .ports
+            .iter()
+            .find(|p| match (&svc_port.name, &p.name) {
+                (Some(n1), Some(n2)) => n1 == n2,
+                (None, None) => true,
+                (None, Some(_)) => false,
+                _ => false,
+            });
+
+        if let Some(tp) = target_port {
+            for addr in &subset.addresses {
+                let weight = addr.weight.unwrap_or(1);
+                if weight == 0 || IpFamily::of_str(&addr.ip) != Some(family) {
+                    continue;
+                }
+                backends.push(Backend {
+                    ip: addr.ip.clone(),
+                    port: tp.port,
+                    weight,
+                    node_name: addr.node_name.clone(),
+                });
+            }
+        }
+    }
+
+    // Weights only fit below the pending mark
+    let total_weight: u64 = backends.iter().map(|b| b.weight as u64).sum();
+    if total_weight >= SELECT_PENDING_MARK as u64 {
+        for backend in &mut backends {
+            backend.weight = 1;
+        }
+    }
+    backends
+}
+
+/// Name of the chain only dispatching to the endpoints of the node, if the
+/// external traffic of this Service port must stay on the node
+fn local_chain_name(
+    svc: &common::ServiceTask,
+    family: IpFamily,
+    svc_port: &common::ServicePort,
+    chain_name: &str,
+) -> Option<String> {
+    let has_external_traffic =
+        svc_port.node_port.is_some() || load_balancer_ip_of(svc, family).is_some();
+    (svc.spec.is_external_traffic_local() && has_external_traffic)
+        .then(|| format!("{chain_name}-local"))
+}
+
+/// VIP of a LoadBalancer Service, if it belongs to `family`
+fn load_balancer_ip_of(svc: &common::ServiceTask, family: IpFamily) -> Option<&str> {
+    svc.load_balancer_ip()
+        .filter(|ip| IpFamily::of_str(ip) == Some(family))
+}
+
+/// nftables family of the `rk8s` table holding the rules of `family`
+fn nf_family(family: IpFamily) -> types::NfFamily {
+    match family {
+        IpFamily::IPv4 => types::NfFamily::IP,
+        IpFamily::IPv6 => types::NfFamily::IP6,
+    }
+}
+
+/// Payload protocol of the addresses of `family`
+fn addr_protocol(family: IpFamily) -> &'static str {
+    match family {
+        IpFamily::IPv4 => "ip",
+        IpFamily::IPv6 => "ip6",
+    }
+}
+
+/// Prefix matching every address of `family`
+fn any_addr(family: IpFamily) -> &'static str {
+    match family {
+        IpFamily::IPv4 => "0.0.0.0/0",
+        IpFamily::IPv6 => "::/0",
+    }
+}
+
+/// Name of the map remembering the backend slot picked for each client
+fn affinity_map_name(chain_name: &str) -> String {
+    format!("{chain_name}-affinity")
+}
+
+/// Affinity map of `chain_name`, whose entries expire after `timeout` seconds
+fn affinity_map_spec(family: IpFamily, chain_name: &str, timeout: u32) -> schema::Map<'static> {
+    schema::Map {
+        family: nf_family(family),
+        table: Cow::Borrowed("rk8s"),
+        name: Cow::Owned(affinity_map_name(chain_name)),
+        set_type: schema::SetTypeValue::Single(match family {
+            IpFamily::IPv4 => schema::SetType::Ipv4Addr,
+            IpFamily::IPv6 => schema::SetType::Ipv6Addr,
+        }),
+        map: schema::SetTypeValue::Single(schema::SetType::Mark),
+        flags: Some(HashSet::from([
+            schema::SetFlag::Timeout,
+            schema::SetFlag::Dynamic,
+        ])),
+        timeout: Some(timeout),
+        ..Default::default()
+    }
+}
+
+/// Adds the commands deleting the affinity map of `chain_name`, created with
+/// `timeout`, once no rule references it anymore. Adding it first keeps the
+/// delete from failing when the chain had a single backend and thus no map.
+fn push_affinity_map_delete(
+    objects: &mut Vec<schema::NfObject<'static>>,
+    family: IpFamily,
+    chain_name: &str,
+    timeout: u32,
+) {
+    let map = affinity_map_spec(family, chain_name, timeout);
+    objects.push(schema::NfObject::ListObject(schema::NfListObject::Map(
+        Box::new(map.clone()),
+    )));
+    objects.push(schema::NfObject::CmdObject(schema::NfCmd::Delete(
+        schema::NfListObject::Map(Box::new(map)),
+    )));
 }
 
-pub fn generate_service_update(svc: &common::ServiceTask, ep: &common::Endpoint) -> Result<String> {
-    let objects = generate_service_update_objects(svc, ep, false)?;
+fn rk8s_chain(family: IpFamily, name: &str) -> schema::Chain<'static> {
+    schema::Chain {
+        family: nf_family(family),
+        table: Cow::Borrowed("rk8s"),
+        name: Cow::Owned(name.to_string()),
+        ..Default::default()
+    }
+}
+
+fn mark_expr() -> expr::Expression<'static> {
+    expr::Expression::Named(expr::NamedExpression::Meta(expr::Meta {
+        key: expr::MetaKey::Mark,
+    }))
+}
+
+fn saddr_expr(family: IpFamily) -> expr::Expression<'static> {
+    expr::Expression::Named(expr::NamedExpression::Payload(expr::Payload::PayloadField(
+        expr::PayloadField {
+            protocol: Cow::Borrowed(addr_protocol(family)),
+            field: Cow::Borrowed("saddr"),
+        },
+    )))
+}
+
+fn mark_match(op: stmt::Operator, value: u32) -> stmt::Statement<'static> {
+    stmt::Statement::Match(stmt::Match {
+        left: mark_expr(),
+        op,
+        right: expr::Expression::Number(value),
+    })
+}
+
+fn set_mark(value: expr::Expression<'static>) -> stmt::Statement<'static> {
+    stmt::Statement::Mangle(stmt::Mangle {
+        key: mark_expr(),
+        value,
+    })
+}
+
+/// Adds the rules of `chain_name` DNATing to `backends` (not empty), setting
+/// `service_mark` on the traffic a backend sends to itself and clearing the mark
+/// of the rest.
+///
+/// Each backend owns `weight` consecutive slots of the random backend index kept
+/// in the mark. With session affinity, the slot picked for a client is stored in
+/// a map whose entries expire after `affinity_timeout` seconds without traffic.
+fn push_backend_rules(
+    objects: &mut Vec<schema::NfObject<'static>>,
+    family: IpFamily,
+    chain_name: &str,
+    protocol: &str,
+    backends: &[Backend],
+    affinity_timeout: Option<u32>,
+    service_mark: u32,
+) {
+    let rule = |expr: Vec<stmt::Statement<'static>>, comment: Option<&'static str>| {
+        schema::NfObject::ListObject(schema::NfListObject::Rule(schema::Rule {
+            family: nf_family(family),
+            table: Cow::Borrowed("rk8s"),
+            chain: Cow::Owned(chain_name.to_string()),
+            expr: Cow::Owned(expr),
+            comment: comment.map(Cow::Borrowed),
+            ..Default::default()
+        }))
+    };
+    // Ensure l4proto is matched before DNAT with port
+    let l4proto_match = || {
+        stmt::Statement::Match(stmt::Match {
+            left: expr::Expression::Named(expr::NamedExpression::Meta(expr::Meta {
+                key: expr::MetaKey::L4proto,
+            })),
+            op: stmt::Operator::EQ,
+            right: expr::Expression::String(Cow::Owned(protocol.to_string())),
+        })
+    };
+    let dnat = |backend: &Backend| {
+        stmt::Statement::DNAT(Some(stmt::NAT {
+            addr: Some(expr::Expression::String(Cow::Owned(backend.ip.clone()))),
+            family: Some(match family {
+                IpFamily::IPv4 => stmt::NATFamily::IP,
+                IpFamily::IPv6 => stmt::NATFamily::IP6,
+            }),
+            port: Some(expr::Expression::Number(backend.port as u32)),
+            flags: None,
+        }))
+    };
+
+    // Only hairpin traffic is masqueraded, so that the reply of the pod goes back
+    // through the node. Other traffic keeps the pod address as source, which the
+    // NetworkPolicies of the destination pod match on.
+    let dnat_rules = |matches: Vec<stmt::Statement<'static>>,
+                      actions: Vec<stmt::Statement<'static>>,
+                      backend: &Backend| {
+        let mut rules = Vec::new();
+        if service_mark != 0 {
+            let mut hairpin = matches.clone();
+            hairpin.push(stmt::Statement::Match(stmt::Match {
+                left: saddr_expr(family),
+                op: stmt::Operator::EQ,
+                right: expr::Expression::String(Cow::Owned(backend.ip.clone())),
+            }));
+            hairpin.extend(actions.clone());
+            hairpin.push(set_mark(expr::Expression::Number(service_mark)));
+            hairpin.push(dnat(backend));
+            rules.push(rule(hairpin, Some("Hairpin")));
+        }
+        let mut other = matches;
+        other.extend(actions);
+        other.push(set_mark(expr::Expression::Number(0)));
+        other.push(dnat(backend));
+        rules.push(rule(other, None));
+        rules
+    };
+
+    if backends.len() == 1 {
+        objects.extend(dnat_rules(vec![l4proto_match()], vec![], &backends[0]));
+        return;
+    }
+
+    let total_weight: u32 = backends.iter().map(|b| b.weight).sum();
+    let affinity_map = affinity_timeout.map(|_| affinity_map_name(chain_name));
+
+    let mut select = Vec::new();
+    if let (Some(map), Some(timeout)) = (&affinity_map, affinity_timeout) {
+        objects.push(schema::NfObject::ListObject(schema::NfListObject::Map(
+            Box::new(affinity_map_spec(family, chain_name, timeout)),
+        )));
+
+        objects.push(rule(
+            vec![set_mark(expr::Expression::Number(SELECT_PENDING_MARK))],
+            Some("Affinity: no backend selected yet"),
+        ));
+        // The lookup ends the rule for unknown clients, leaving the pending mark
+        objects.push(rule(
+            vec![set_mark(expr::Expression::Named(
+                expr::NamedExpression::Map(Box::new(expr::Map {
+                    key: saddr_expr(family),
+                    data: expr::Expression::String(Cow::Owned(format!("@{map}"))),
+                })),
+            ))],
+            Some("Affinity: reuse backend of client"),
+        ));
+        // Also catches the slots left over from a larger set of backends
+        select.push(mark_match(stmt::Operator::GEQ, total_weight));
+    }
+
+    // 1. Set backend index for load balancing (the mark is set again after selection)
+    select.push(set_mark(expr::Expression::Named(
+        expr::NamedExpression::Numgen(expr::Numgen {
+            mode: expr::NgMode::Random,
+            ng_mod: total_weight,
+            offset: Some(0),
+        }),
+    )));
+    objects.push(rule(select, Some("LB: set backend index")));
+
+    // 2. Dispatch to backends based on index
+    let mut slot = 0;
+    for backend in backends {
+        let mut matches = vec![l4proto_match()];
+        // Match backend index
+        if backend.weight == 1 {
+            matches.push(mark_match(stmt::Operator::EQ, slot));
+        } else {
+            matches.push(mark_match(stmt::Operator::GEQ, slot));
+            matches.push(mark_match(stmt::Operator::LEQ, slot + backend.weight - 1));
+        }
+        let mut actions = Vec::new();
+        if let Some(map) = &affinity_map {
+            // Remember the backend of the client, refreshing the timeout
+            actions.push(stmt::Statement::Map(stmt::Map {
+                op: stmt::SetOp::Update,
+                elem: saddr_expr(family),
+                data: expr::Expression::Number(slot),
+                map: Cow::Owned(format!("@{map}")),
+            }));
+        }
+        objects.extend(dnat_rules(matches, actions, backend));
+        slot += backend.weight;
+    }
+}
+
+/// Generates the incremental update of the rules of `svc`, whose previous version
+/// `previous` is needed to replace the affinity maps when the affinity changed.
+pub fn generate_service_update(
+    svc: &common::ServiceTask,
+    ep: &common::Endpoint,
+    previous: Option<&common::ServiceTask>,
+) -> Result<String> {
+    let mut objects = Vec::new();
+
+    // A map keeps the timeout it was created with: drop the old maps, after
+    // flushing the chains referencing them, and let the update add new ones.
+    let stale_timeout = previous
+        .and_then(|previous| previous.spec.client_ip_affinity_timeout())
+        .filter(|&timeout| svc.spec.client_ip_affinity_timeout() != Some(timeout));
+    if let (Some(previous), Some(timeout)) = (previous, stale_timeout) {
+        for family in [IpFamily::IPv4, IpFamily::IPv6] {
+            if previous.spec.cluster_ip_of(family).is_none() {
+                continue;
+            }
+            for svc_port in &previous.spec.ports {
+                let chain_name = format!(
+                    "svc-{}-{}-{}",
+                    previous.metadata.namespace, previous.metadata.name, svc_port.port
+                );
+                objects.push(schema::NfObject::ListObject(schema::NfListObject::Chain(
+                    rk8s_chain(family, &chain_name),
+                )));
+                objects.push(schema::NfObject::CmdObject(schema::NfCmd::Flush(
+                    schema::FlushObject::Chain(rk8s_chain(family, &chain_name)),
+                )));
+                push_affinity_map_delete(&mut objects, family, &chain_name, timeout);
+            }
+        }
+    }
+
+    objects.extend(generate_service_update_objects(svc, ep, false, None)?);
     let nftables = schema::Nftables {
         objects: Cow::Owned(objects),
     };
@@ -555,13 +954,31 @@ pub fn generate_service_update(svc: &common::ServiceTask, ep: &common::Endpoint)
 }
 
 pub fn generate_service_delete(svc: &common::ServiceTask) -> Result<String> {
-    let cluster_ip = match svc.spec.cluster_ip.as_deref() {
-        Some(ip) => ip,
-        None => return Ok(json!({"nftables": []}).to_string()),
-    };
+    let cluster_ips = svc.spec.cluster_ip_addrs();
+    if cluster_ips.is_empty() {
+        return Ok(json!({"nftables": []}).to_string());
+    }
 
     let mut objects = Vec::new();
+    for family in [IpFamily::IPv4, IpFamily::IPv6] {
+        if let Some(cluster_ip) = svc.spec.cluster_ip_of(family) {
+            push_service_delete_objects(&mut objects, svc, family, &cluster_ip.to_string());
+        }
+    }
+
+    let nftables = schema::Nftables {
+        objects: Cow::Owned(objects),
+    };
+    serde_json::to_string(&nftables).map_err(|e| anyhow::anyhow!(e))
+}
 
+/// Adds the commands deleting the rules of `svc` from the table of `family`
+fn push_service_delete_objects(
+    objects: &mut Vec<schema::NfObject<'static>>,
+    svc: &common::ServiceTask,
+    family: IpFamily,
+    cluster_ip: &str,
+) {
     for svc_port in &svc.spec.ports {
         let protocol = svc_port.protocol.to_lowercase();
         let chain_name = format!(
@@ -573,10 +990,14 @@ pub fn generate_service_delete(svc: &common::ServiceTask) -> Result<String> {
         } else {
             "services_tcp"
         };
+        // The NodePort and LoadBalancer rules of the nodes target the local chain
+        // with `externalTrafficPolicy: Local`
+        let local_chain = local_chain_name(svc, family, svc_port, &chain_name);
+        let external_chain = local_chain.as_deref().unwrap_or(&chain_name);
 
-        // 1. Delete ClusterIP Dispatch Rule (must match exactly including mark statement)
+        // 1. Delete ClusterIP Dispatch Rule (must match the creation rule exactly)
         let rule = schema::Rule {
-            family: types::NfFamily::IP,
+            family: nf_family(family),
             table: Cow::Borrowed("rk8s"),
             chain: Cow::Borrowed(dispatch_chain),
             expr: Cow::Owned(vec![
@@ -590,7 +1011,7 @@ pub fn generate_service_delete(svc: &common::ServiceTask) -> Result<String> {
                 stmt::Statement::Match(stmt::Match {
                     left: expr::Expression::Named(expr::NamedExpression::Payload(
                         expr::Payload::PayloadField(expr::PayloadField {
-                            protocol: Cow::Borrowed("ip"),
+                            protocol: Cow::Borrowed(addr_protocol(family)),
                             field: Cow::Borrowed("daddr"),
                         }),
                     )),
@@ -607,13 +1028,6 @@ pub fn generate_service_delete(svc: &common::ServiceTask) -> Result<String> {
                     op: stmt::Operator::EQ,
                     right: expr::Expression::Number(svc_port.port as u32),
                 }),
-                // Include mark statement to match creation rule exactly
-                stmt::Statement::Mangle(stmt::Mangle {
-                    key: expr::Expression::Named(expr::NamedExpression::Meta(expr::Meta {
-                        key: expr::MetaKey::Mark,
-                    })),
-                    value: expr::Expression::Number(SERVICE_TRAFFIC_MARK),
-                }),
                 stmt::Statement::Jump(stmt::JumpTarget {
                     target: Cow::Owned(chain_name.clone()),
                 }),
@@ -627,7 +1041,7 @@ pub fn generate_service_delete(svc: &common::ServiceTask) -> Result<String> {
         // 2. Delete NodePort Rule
         if let Some(node_port) = svc_port.node_port {
             let np_rule = schema::Rule {
-                family: types::NfFamily::IP,
+                family: nf_family(family),
                 table: Cow::Borrowed("rk8s"),
                 chain: Cow::Borrowed(dispatch_chain),
                 expr: Cow::Owned(vec![
@@ -650,7 +1064,7 @@ pub fn generate_service_delete(svc: &common::ServiceTask) -> Result<String> {
                         right: expr::Expression::Number(node_port as u32),
                     }),
                     stmt::Statement::Jump(stmt::JumpTarget {
-                        target: Cow::Owned(chain_name.clone()),
+                        target: Cow::Owned(external_chain.to_string()),
                     }),
                 ]),
                 ..Default::default()
@@ -660,34 +1074,53 @@ pub fn generate_service_delete(svc: &common::ServiceTask) -> Result<String> {
             )));
         }
 
-        // 3. Flush & Delete Chain
-        objects.push(schema::NfObject::CmdObject(schema::NfCmd::Flush(
-            schema::FlushObject::Chain(schema::Chain {
-                family: types::NfFamily::IP,
-                table: Cow::Borrowed("rk8s"),
-                name: Cow::Owned(chain_name.clone()),
-                ..Default::default()
-            }),
-        )));
-        objects.push(schema::NfObject::CmdObject(schema::NfCmd::Delete(
-            schema::NfListObject::Chain(schema::Chain {
-                family: types::NfFamily::IP,
-                table: Cow::Borrowed("rk8s"),
-                name: Cow::Owned(chain_name.clone()),
-                ..Default::default()
-            }),
-        )));
-    }
+        // 3. Delete LoadBalancer VIP Rule
+        if let Some(lb_ip) = load_balancer_ip_of(svc, family) {
+            let lb_rule = create_dispatch_rule(
+                family,
+                dispatch_chain,
+                &protocol,
+                lb_ip,
+                svc_port.port,
+                external_chain,
+            );
+            objects.push(schema::NfObject::CmdObject(schema::NfCmd::Delete(
+                schema::NfListObject::Rule(lb_rule),
+            )));
+        }
 
-    let nftables = schema::Nftables {
-        objects: Cow::Owned(objects),
-    };
-    serde_json::to_string(&nftables).map_err(|e| anyhow::anyhow!(e))
+        // 4. Flush & Delete Chains. The local chain is added first, as only the
+        // nodes given their own rules have it.
+        if let Some(local_chain) = &local_chain {
+            objects.push(schema::NfObject::ListObject(schema::NfListObject::Chain(
+                rk8s_chain(family, local_chain),
+            )));
+        }
+        let chains: Vec<&str> = std::iter::once(chain_name.as_str())
+            .chain(local_chain.as_deref())
+            .collect();
+        for name in &chains {
+            objects.push(schema::NfObject::CmdObject(schema::NfCmd::Flush(
+                schema::FlushObject::Chain(rk8s_chain(family, name)),
+            )));
+            objects.push(schema::NfObject::CmdObject(schema::NfCmd::Delete(
+                schema::NfListObject::Chain(rk8s_chain(family, name)),
+            )));
+        }
+
+        // 5. Delete Affinity Maps, no longer referenced
+        if let Some(timeout) = svc.spec.client_ip_affinity_timeout() {
+            for name in &chains {
+                push_affinity_map_delete(objects, family, name, timeout);
+            }
+        }
+    }
 }
 
 /// Helper function to create a dispatch rule for ClusterIP or NodePort
 /// Used for both adding and deleting rules in services_tcp/udp chains
 fn create_dispatch_rule<'a>(
+    family: IpFamily,
     chain: &'a str,
     protocol: &str,
     dst_ip: &str,
@@ -695,7 +1128,7 @@ fn create_dispatch_rule<'a>(
     target_chain: &str,
 ) -> schema::Rule<'a> {
     schema::Rule {
-        family: types::NfFamily::IP,
+        family: nf_family(family),
         table: Cow::Borrowed("rk8s"),
         chain: Cow::Borrowed(chain),
         expr: Cow::Owned(vec![
@@ -711,7 +1144,7 @@ fn create_dispatch_rule<'a>(
             stmt::Statement::Match(stmt::Match {
                 left: expr::Expression::Named(expr::NamedExpression::Payload(
                     expr::Payload::PayloadField(expr::PayloadField {
-                        protocol: Cow::Borrowed("ip"),
+                        protocol: Cow::Borrowed(addr_protocol(family)),
                         field: Cow::Borrowed("daddr"),
                     }),
                 )),
@@ -0,0 +1,544 @@
+//! Overlay backends (VXLAN and WireGuard) for clusters whose nodes do not
+//! share an L2 segment.
+//!
+//! Each node gets a tunnel device whose address is the first address of its
+//! subnet, with the prefix of the whole cluster network. A route to a remote
+//! subnet goes through the tunnel address of the remote node, like host-gw
+//! routes go through its public IP, and the backends only differ in the peer
+//! entries that carry the packets to that address:
+//!
+//! - VXLAN resolves the tunnel address to the VTEP MAC of the remote node
+//!   with a permanent ARP entry, and an FDB entry sends the frames for that
+//!   MAC to the public IP of the remote node.
+//! - WireGuard sends the packets for the remote subnet to the peer whose
+//!   allowed IPs contain it, encrypted with the public key it registered with.
+//!
+//! Both only carry the IPv4 subnets, so networks with `EnableIPv6` are
+//! rejected rather than leaving the IPv6 subnets of the nodes unreachable.
+use anyhow::{Context, Result, bail};
+use common::{
+    ExternalInterface,
+    lease::{Lease, OverlayPeer},
+};
+use ipnetwork::{IpNetwork, Ipv4Network};
+use libcni::ip::{addr, route::Route};
+use log::{info, warn};
+use netlink_packet_route::AddressFamily;
+use serde::de::DeserializeOwned;
+use serde_json::Value as JsonValue;
+use std::net::{IpAddr, SocketAddr};
+
+use crate::{
+    config::NetworkConfig,
+    vxlan::{self, VxlanConfig, VxlanDevice, VxlanLeaseAttrs},
+    wireguard::{self, WireguardConfig, WireguardDevice, WireguardKeys, WireguardLeaseAttrs},
+};
+
+pub const VXLAN_BACKEND: &str = "vxlan";
+pub const WIREGUARD_BACKEND: &str = "wireguard";
+
+/// Backend settings of an overlay network
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum OverlayConfig {
+    Vxlan(VxlanConfig),
+    Wireguard(WireguardConfig),
+}
+
+impl OverlayConfig {
+    /// Overlay settings of a network config, `None` if its backend is not an
+    /// overlay
+    pub fn from_network_config(config: &NetworkConfig) -> Result<Option<Self>> {
+        let backend = config.backend.as_ref();
+        if config.enable_ipv6 && is_overlay(&config.backend_type) {
+            bail!(
+                "the {} backend only routes IPv4 subnets, use the hostgw backend with EnableIPv6",
+                config.backend_type
+            );
+        }
+        match config.backend_type.as_str() {
+            VXLAN_BACKEND => Ok(Some(Self::Vxlan(parse_backend(backend)?))),
+            WIREGUARD_BACKEND => Ok(Some(Self::Wireguard(parse_backend(backend)?))),
+            _ => Ok(None),
+        }
+    }
+
+    pub fn backend_type(&self) -> &'static str {
+        match self {
+            Self::Vxlan(_) => VXLAN_BACKEND,
+            Self::Wireguard(_) => WIREGUARD_BACKEND,
+        }
+    }
+
+    /// Lines of the subnet env sent to a node, from which it rebuilds these
+    /// settings with [`OverlayConfig::from_env`]
+    pub fn env_lines(&self) -> String {
+        let mut contents = format!("RKL_BACKEND_TYPE={}\n", self.backend_type());
+        match self {
+            Self::Vxlan(cfg) => {
+                contents += &format!("RKL_VXLAN_VNI={}\n", cfg.vni);
+                contents += &format!("RKL_VXLAN_PORT={}\n", cfg.port);
+            }
+            Self::Wireguard(cfg) => {
+                contents += &format!("RKL_WG_LISTEN_PORT={}\n", cfg.listen_port);
+                contents += &format!("RKL_WG_PERSISTENT_KEEPALIVE={}\n", cfg.persistent_keepalive);
+            }
+        }
+        contents
+    }
+
+    /// Overlay settings of a subnet env, `None` if it is not for an overlay
+    pub fn from_env(subnet_env: &str) -> Result<Option<Self>> {
+        let mut backend_type = None;
+        let mut vxlan = VxlanConfig::default();
+        let mut wireguard = WireguardConfig::default();
+
+        for line in subnet_env.lines() {
+            if let Some((key, value)) = line.split_once('=') {
+                match key {
+                    "RKL_BACKEND_TYPE" => backend_type = Some(value.to_string()),
+                    "RKL_VXLAN_VNI" => vxlan.vni = value.parse().context("invalid VXLAN VNI")?,
+                    "RKL_VXLAN_PORT" => vxlan.port = value.parse().context("invalid VXLAN port")?,
+                    "RKL_WG_LISTEN_PORT" => {
+                        wireguard.listen_port =
+                            value.parse().context("invalid WireGuard listen port")?
+                    }
+                    "RKL_WG_PERSISTENT_KEEPALIVE" => {
+                        wireguard.persistent_keepalive = value
+                            .parse()
+                            .context("invalid WireGuard keepalive interval")?
+                    }
+                    _ => {}
+                }
+            }
+        }
+
+        Ok(match backend_type.as_deref() {
+            Some(VXLAN_BACKEND) => Some(Self::Vxlan(vxlan)),
+            Some(WIREGUARD_BACKEND) => Some(Self::Wireguard(wireguard)),
+            _ => None,
+        })
+    }
+}
+
+fn parse_backend<T: DeserializeOwned + Default>(backend: Option<&JsonValue>) -> Result<T> {
+    match backend {
+        Some(value) if !value.is_null() => {
+            serde_json::from_value(value.clone()).context("decoding Backend property of config")
+        }
+        _ => Ok(T::default()),
+    }
+}
+
+/// Whether `backend_type` is the type of an overlay backend
+pub fn is_overlay(backend_type: &str) -> bool {
+    matches!(backend_type, VXLAN_BACKEND | WIREGUARD_BACKEND)
+}
+
+/// Route to the subnet of a lease through the tunnel address of its node
+pub fn route_from_lease(lease: &Lease) -> Option<Route> {
+    if !lease.enable_ipv4 {
+        return None;
+    }
+
+    Some(Route {
+        dst: Some(IpNetwork::V4(lease.subnet)),
+        gateway: Some(IpAddr::V4(lease.subnet.ip())),
+        oif_index: None,
+        metric: None,
+        ..Default::default()
+    })
+}
+
+/// Tunnel endpoint of the node holding a lease, `None` if the lease is not
+/// for this overlay or lacks its backend data
+pub fn peer_from_lease(lease: &Lease, config: &OverlayConfig) -> Option<OverlayPeer> {
+    if lease.attrs.backend_type != config.backend_type() {
+        warn!(
+            "Ignoring non-{} subnet {}: type={}",
+            config.backend_type(),
+            lease.subnet,
+            lease.attrs.backend_type
+        );
+        return None;
+    }
+    let Some(data) = lease.attrs.backend_data.clone() else {
+        warn!("Ignoring subnet {} without backend data", lease.subnet);
+        return None;
+    };
+
+    let peer =
+        match config {
+            OverlayConfig::Vxlan(_) => {
+                serde_json::from_value::<VxlanLeaseAttrs>(data).map(|attrs| OverlayPeer::Vxlan {
+                    subnet: lease.subnet,
+                    vtep_mac: attrs.vtep_mac,
+                    public_ip: lease.attrs.public_ip,
+                })
+            }
+            OverlayConfig::Wireguard(cfg) => serde_json::from_value::<WireguardLeaseAttrs>(data)
+                .map(|attrs| OverlayPeer::Wireguard {
+                    subnet: lease.subnet,
+                    public_key: attrs.public_key,
+                    endpoint: SocketAddr::new(IpAddr::V4(lease.attrs.public_ip), cfg.listen_port),
+                }),
+        };
+
+    match peer {
+        Ok(peer) => Some(peer),
+        Err(e) => {
+            warn!(
+                "Ignoring subnet {} with invalid backend data: {e}",
+                lease.subnet
+            );
+            None
+        }
+    }
+}
+
+/// Check that the backend data a node registered with suits `backend_type`
+pub fn validate_backend_data(backend_type: &str, data: &JsonValue) -> Result<()> {
+    match backend_type {
+        VXLAN_BACKEND => {
+            let attrs: VxlanLeaseAttrs =
+                serde_json::from_value(data.clone()).context("decoding VXLAN backend data")?;
+            vxlan::parse_mac(&attrs.vtep_mac)?;
+        }
+        WIREGUARD_BACKEND => {
+            let attrs: WireguardLeaseAttrs =
+                serde_json::from_value(data.clone()).context("decoding WireGuard backend data")?;
+            if attrs.public_key.is_empty() {
+                bail!("WireGuard backend data has an empty public key");
+            }
+        }
+        _ => {}
+    }
+    Ok(())
+}
+
+/// Backend data this node registers with, so that the other nodes can reach
+/// it. `None` for backends without backend data.
+pub async fn local_backend_data(
+    backend_type: &str,
+    ext_iface: &ExternalInterface,
+) -> Result<Option<JsonValue>> {
+    let data = match backend_type {
+        VXLAN_BACKEND => {
+            let public_ip = ext_iface
+                .ext_addr
+                .or(ext_iface.iface_addr)
+                .context("VXLAN backend requires an IPv4 address on the external interface")?;
+            serde_json::to_value(VxlanLeaseAttrs {
+                vtep_mac: vxlan::format_mac(&vxlan::vtep_mac(public_ip)),
+            })?
+        }
+        WIREGUARD_BACKEND => {
+            let keys = WireguardKeys::load_or_generate(&wireguard::key_file_path()).await?;
+            serde_json::to_value(keys.lease_attrs())?
+        }
+        _ => return Ok(None),
+    };
+    Ok(Some(data))
+}
+
+/// Give a tunnel device the tunnel address of `subnet`, removing the
+/// addresses of previous leases.
+pub async fn ensure_tunnel_address(
+    index: u32,
+    subnet: Ipv4Network,
+    network: Ipv4Network,
+) -> Result<()> {
+    let tunnel_ip = IpAddr::V4(subnet.ip());
+    let mut present = false;
+
+    for existing in addr::addr_list(index, AddressFamily::Inet).await? {
+        if existing.ipnet.ip() == tunnel_ip && existing.ipnet.prefix() == network.prefix() {
+            present = true;
+        } else {
+            info!("Removing stale tunnel address {}", existing.ipnet);
+            addr::addr_del(index, existing.ipnet.ip()).await?;
+        }
+    }
+
+    if !present {
+        addr::addr_add(index, tunnel_ip, network.prefix()).await?;
+        info!(
+            "Assigned tunnel address {}/{}",
+            subnet.ip(),
+            network.prefix()
+        );
+    }
+    Ok(())
+}
+
+enum TunnelLink {
+    Vxlan(VxlanDevice),
+    Wireguard(WireguardDevice),
+}
+
+/// Tunnel device of this node together with the peers it knows about
+pub struct OverlayDevice {
+    link: TunnelLink,
+    peers: Vec<OverlayPeer>,
+}
+
+impl OverlayDevice {
+    /// Create the tunnel device of `config` and give it the tunnel address of
+    /// the subnet of this node.
+    pub async fn ensure(
+        config: &OverlayConfig,
+        ext_iface: &ExternalInterface,
+        subnet: Ipv4Network,
+        network: Ipv4Network,
+    ) -> Result<Self> {
+        let link = match config {
+            OverlayConfig::Vxlan(cfg) => {
+                let device = VxlanDevice::ensure(cfg, ext_iface).await?;
+                device.configure(subnet, network).await?;
+                TunnelLink::Vxlan(device)
+            }
+            OverlayConfig::Wireguard(cfg) => {
+                let keys = WireguardKeys::load_or_generate(&wireguard::key_file_path()).await?;
+                let device = WireguardDevice::ensure(cfg, ext_iface, keys).await?;
+                device.configure(subnet, network).await?;
+                TunnelLink::Wireguard(device)
+            }
+        };
+        Ok(Self {
+            link,
+            peers: vec![],
+        })
+    }
+
+    pub fn mtu(&self) -> u32 {
+        match &self.link {
+            TunnelLink::Vxlan(device) => device.mtu,
+            TunnelLink::Wireguard(device) => device.mtu,
+        }
+    }
+
+    /// Backend data of this node, stored in its lease
+    pub fn backend_data(&self) -> Result<JsonValue> {
+        Ok(match &self.link {
+            TunnelLink::Vxlan(device) => serde_json::to_value(device.lease_attrs())?,
+            TunnelLink::Wireguard(device) => serde_json::to_value(device.keys.lease_attrs())?,
+        })
+    }
+
+    pub fn peers(&self) -> &[OverlayPeer] {
+        &self.peers
+    }
+
+    pub async fn add_peer(&mut self, peer: OverlayPeer) -> Result<()> {
+        match (&self.link, &peer) {
+            (
+                TunnelLink::Vxlan(device),
+                OverlayPeer::Vxlan {
+                    subnet,
+                    vtep_mac,
+                    public_ip,
+                },
+            ) => device.add_peer(*subnet, vtep_mac, *public_ip).await?,
+            (
+                TunnelLink::Wireguard(device),
+                OverlayPeer::Wireguard {
+                    subnet,
+                    public_key,
+                    endpoint,
+                },
+            ) => device.add_peer(*subnet, public_key, *endpoint).await?,
+            _ => bail!("peer {} is for another backend", peer.subnet()),
+        }
+        if !self.peers.contains(&peer) {
+            self.peers.push(peer);
+        }
+        Ok(())
+    }
+
+    pub async fn remove_peer(&mut self, peer: &OverlayPeer) -> Result<()> {
+        match (&self.link, peer) {
+            (
+                TunnelLink::Vxlan(device),
+                OverlayPeer::Vxlan {
+                    subnet,
+                    vtep_mac,
+                    public_ip,
+                },
+            ) => device.remove_peer(*subnet, vtep_mac, *public_ip).await?,
+            (
+                TunnelLink::Wireguard(device),
+                OverlayPeer::Wireguard {
+                    subnet, public_key, ..
+                },
+            ) => device.remove_peer(*subnet, public_key).await?,
+            _ => bail!("peer {} is for another backend", peer.subnet()),
+        }
+        self.peers.retain(|p| p != peer);
+        Ok(())
+    }
+
+    /// Make the peers of the device exactly `peers`: add the new or changed
+    /// ones and remove the ones no longer present.
+    pub async fn sync_peers(&mut self, peers: &[OverlayPeer]) -> Result<()> {
+        let stale: Vec<OverlayPeer> = self
+            .peers
+            .iter()
+            .filter(|p| !peers.contains(p))
+            .cloned()
+            .collect();
+        for peer in &stale {
+            if let Err(e) = self.remove_peer(peer).await {
+                warn!("Failed to remove overlay peer {}: {e}", peer.subnet());
+                self.peers.retain(|p| p != peer);
+            }
+        }
+
+        for peer in peers {
+            if self.peers.contains(peer) {
+                continue;
+            }
+            if let Err(e) = self.add_peer(peer.clone()).await {
+                warn!("Failed to add overlay peer {}: {e}", peer.subnet());
+            }
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::parse_network_config;
+    use common::lease::LeaseAttrs;
+    use std::net::Ipv4Addr;
+
+    #[test]
+    fn test_overlay_config_from_network_config() {
+        let cfg = parse_network_config(
+            r#"{"Network": "10.244.0.0/16", "Backend": {"Type": "vxlan", "VNI": 4}}"#,
+        )
+        .unwrap();
+        assert_eq!(
+            OverlayConfig::from_network_config(&cfg).unwrap(),
+            Some(OverlayConfig::Vxlan(VxlanConfig {
+                vni: 4,
+                port: vxlan::DEFAULT_PORT,
+            }))
+        );
+
+        let cfg = parse_network_config(
+            r#"{"Network": "10.244.0.0/16", "Backend": {"Type": "wireguard"}}"#,
+        )
+        .unwrap();
+        assert_eq!(
+            OverlayConfig::from_network_config(&cfg).unwrap(),
+            Some(OverlayConfig::Wireguard(WireguardConfig::default()))
+        );
+
+        let cfg = parse_network_config(r#"{"Network": "10.244.0.0/16"}"#).unwrap();
+        assert_eq!(OverlayConfig::from_network_config(&cfg).unwrap(), None);
+    }
+
+    #[test]
+    fn test_overlay_rejects_ipv6() {
+        for backend in [VXLAN_BACKEND, WIREGUARD_BACKEND] {
+            let cfg = parse_network_config(&format!(
+                r#"{{"Network": "10.244.0.0/16", "EnableIPv6": true, "IPv6Network": "fd00:1::/56", "Backend": {{"Type": "{backend}"}}}}"#
+            ))
+            .unwrap();
+            assert!(OverlayConfig::from_network_config(&cfg).is_err());
+        }
+
+        let cfg = parse_network_config(
+            r#"{"Network": "10.244.0.0/16", "EnableIPv6": true, "IPv6Network": "fd00:1::/56"}"#,
+        )
+        .unwrap();
+        assert_eq!(OverlayConfig::from_network_config(&cfg).unwrap(), None);
+    }
+
+    #[test]
+    fn test_overlay_config_env_roundtrip() {
+        let configs = [
+            OverlayConfig::Vxlan(VxlanConfig { vni: 7, port: 4789 }),
+            OverlayConfig::Wireguard(WireguardConfig {
+                listen_port: 51821,
+                persistent_keepalive: 25,
+            }),
+        ];
+        for config in configs {
+            let env = format!("RKL_SUBNET=10.244.1.0/24\n{}", config.env_lines());
+            assert_eq!(OverlayConfig::from_env(&env).unwrap(), Some(config));
+        }
+        assert_eq!(
+            OverlayConfig::from_env("RKL_SUBNET=10.244.1.0/24\n").unwrap(),
+            None
+        );
+    }
+
+    fn lease(backend_type: &str, backend_data: JsonValue) -> Lease {
+        Lease {
+            enable_ipv4: true,
+            subnet: "10.244.3.0/24".parse().unwrap(),
+            attrs: LeaseAttrs {
+                public_ip: Ipv4Addr::new(192, 168, 7, 3),
+                backend_type: backend_type.to_string(),
+                backend_data: Some(backend_data),
+                ..Default::default()
+            },
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn test_peer_and_route_from_lease() {
+        let vxlan = lease(
+            VXLAN_BACKEND,
+            serde_json::json!({"VtepMAC": "0e:6b:c0:a8:07:03"}),
+        );
+        let config = OverlayConfig::Vxlan(VxlanConfig::default());
+        assert_eq!(
+            peer_from_lease(&vxlan, &config),
+            Some(OverlayPeer::Vxlan {
+                subnet: vxlan.subnet,
+                vtep_mac: "0e:6b:c0:a8:07:03".to_string(),
+                public_ip: Ipv4Addr::new(192, 168, 7, 3),
+            })
+        );
+
+        let route = route_from_lease(&vxlan).unwrap();
+        assert_eq!(route.dst, Some(IpNetwork::V4(vxlan.subnet)));
+        assert_eq!(route.gateway, Some("10.244.3.0".parse().unwrap()));
+
+        let wg = lease(WIREGUARD_BACKEND, serde_json::json!({"PublicKey": "key"}));
+        let config = OverlayConfig::Wireguard(WireguardConfig::default());
+        assert_eq!(
+            peer_from_lease(&wg, &config),
+            Some(OverlayPeer::Wireguard {
+                subnet: wg.subnet,
+                public_key: "key".to_string(),
+                endpoint: "192.168.7.3:51820".parse().unwrap(),
+            })
+        );
+
+        // Leases of another backend or without usable data are skipped
+        assert_eq!(peer_from_lease(&vxlan, &config), None);
+        let broken = lease(WIREGUARD_BACKEND, serde_json::json!({"VtepMAC": "x"}));
+        assert_eq!(peer_from_lease(&broken, &config), None);
+    }
+
+    #[test]
+    fn test_vtep_mac() {
+        let mac = vxlan::vtep_mac(Ipv4Addr::new(192, 168, 7, 3));
+        assert_eq!(vxlan::format_mac(&mac), "0e:6b:c0:a8:07:03");
+        assert_eq!(vxlan::parse_mac("0e:6b:c0:a8:07:03").unwrap(), mac);
+        assert!(vxlan::parse_mac("0e:6b:c0").is_err());
+
+        assert!(
+            validate_backend_data(VXLAN_BACKEND, &serde_json::json!({"VtepMAC": "zz"})).is_err()
+        );
+        assert!(validate_backend_data(WIREGUARD_BACKEND, &serde_json::json!({})).is_err());
+        assert!(
+            validate_backend_data(WIREGUARD_BACKEND, &serde_json::json!({"PublicKey": "k"}))
+                .is_ok()
+        );
+    }
+}
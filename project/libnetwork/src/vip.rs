@@ -0,0 +1,99 @@
+use anyhow::{Context, Result, bail};
+use common::ExternalInterface;
+use libcni::ip::addr;
+use log::{info, warn};
+use netlink_packet_route::AddressFamily;
+use std::collections::BTreeSet;
+use std::net::{IpAddr, Ipv4Addr};
+use std::process::Stdio;
+use tokio::process::Command;
+
+/// VIPs are assigned as host addresses, so that they are not routed
+pub const VIP_PREFIX_LEN: u8 = 32;
+/// Gratuitous ARP packets sent for each newly announced VIP
+const GARP_COUNT: u32 = 3;
+
+/// Assigns the LoadBalancer VIPs elected on this node to its external
+/// interface, so that it answers ARP requests for them, and announces each new
+/// VIP with gratuitous ARP so that the neighbours forget the previous node.
+///
+/// Only the VIPs set through the announcer are removed, and this requires
+/// `arping` (iputils) on the node.
+pub struct VipAnnouncer {
+    iface_index: u32,
+    iface_name: String,
+    vips: BTreeSet<Ipv4Addr>,
+}
+
+impl VipAnnouncer {
+    pub fn new(ext_iface: &ExternalInterface) -> Self {
+        Self {
+            iface_index: ext_iface.iface.index,
+            iface_name: ext_iface.iface.name.clone(),
+            vips: BTreeSet::new(),
+        }
+    }
+
+    /// Replace the announced VIPs with `vips`
+    pub async fn set_vips(&mut self, vips: &[String]) -> Result<()> {
+        let wanted = vips
+            .iter()
+            .map(|vip| {
+                vip.parse::<Ipv4Addr>()
+                    .with_context(|| format!("invalid VIP {vip}"))
+            })
+            .collect::<Result<BTreeSet<_>>>()?;
+
+        let present: BTreeSet<IpAddr> = addr::addr_list(self.iface_index, AddressFamily::Inet)
+            .await?
+            .into_iter()
+            .filter(|existing| existing.ipnet.prefix() == VIP_PREFIX_LEN)
+            .map(|existing| existing.ipnet.ip())
+            .collect();
+
+        for vip in self.vips.difference(&wanted) {
+            if present.contains(&IpAddr::V4(*vip)) {
+                addr::addr_del(self.iface_index, IpAddr::V4(*vip)).await?;
+            }
+            info!("Stopped announcing VIP {vip} on {}", self.iface_name);
+        }
+
+        for vip in wanted.difference(&self.vips) {
+            if !present.contains(&IpAddr::V4(*vip)) {
+                addr::addr_add(self.iface_index, IpAddr::V4(*vip), VIP_PREFIX_LEN).await?;
+            }
+            info!("Announcing VIP {vip} on {}", self.iface_name);
+
+            // Neighbours may still map the VIP to the previous node
+            let iface_name = self.iface_name.clone();
+            let vip = *vip;
+            tokio::spawn(async move {
+                if let Err(e) = send_gratuitous_arp(&iface_name, vip).await {
+                    warn!("Failed to send gratuitous ARP for {vip}: {e:#}");
+                }
+            });
+        }
+
+        self.vips = wanted;
+        Ok(())
+    }
+}
+
+/// Send unsolicited ARP replies for `ip` out of `iface_name` with `arping`
+async fn send_gratuitous_arp(iface_name: &str, ip: Ipv4Addr) -> Result<()> {
+    let output = Command::new("arping")
+        .args(["-U", "-c", &GARP_COUNT.to_string(), "-I", iface_name])
+        .arg(ip.to_string())
+        .stdin(Stdio::null())
+        .output()
+        .await
+        .context("failed to run arping, is iputils-arping installed?")?;
+
+    if !output.status.success() {
+        bail!(
+            "arping -U {ip} failed: {}",
+            String::from_utf8_lossy(&output.stderr).trim()
+        );
+    }
+    Ok(())
+}
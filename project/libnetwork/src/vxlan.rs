@@ -0,0 +1,197 @@
+use anyhow::{Context, Result, anyhow, bail};
+use common::ExternalInterface;
+use ipnetwork::Ipv4Network;
+use libcni::ip::{link, neigh};
+use log::{info, warn};
+use netlink_packet_route::link::LinkAttribute;
+use rtnetlink::LinkVxlan;
+use serde::{Deserialize, Serialize};
+use std::net::{IpAddr, Ipv4Addr};
+
+use crate::overlay;
+
+/// Default VXLAN network identifier, as in flannel
+pub const DEFAULT_VNI: u32 = 1;
+/// Default UDP port of VXLAN traffic, the Linux default rather than IANA's 4789
+pub const DEFAULT_PORT: u16 = 8472;
+/// Bytes of the outer IPv4, UDP and VXLAN headers plus the inner Ethernet header
+pub const OVERHEAD: u32 = 50;
+
+/// `Backend` section of a network config using the VXLAN backend
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Eq)]
+pub struct VxlanConfig {
+    #[serde(rename = "VNI", default = "default_vni")]
+    pub vni: u32,
+    #[serde(rename = "Port", default = "default_port")]
+    pub port: u16,
+}
+
+impl Default for VxlanConfig {
+    fn default() -> Self {
+        Self {
+            vni: DEFAULT_VNI,
+            port: DEFAULT_PORT,
+        }
+    }
+}
+
+fn default_vni() -> u32 {
+    DEFAULT_VNI
+}
+
+fn default_port() -> u16 {
+    DEFAULT_PORT
+}
+
+/// `BackendData` of a lease held by a VXLAN node
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Eq)]
+pub struct VxlanLeaseAttrs {
+    #[serde(rename = "VtepMAC")]
+    pub vtep_mac: String,
+}
+
+/// Name of the VXLAN device of a VNI
+pub fn device_name(vni: u32) -> String {
+    format!("rk8s.{vni}")
+}
+
+/// MAC address of the VTEP of the node with `public_ip`.
+///
+/// It is derived from the address so that it is stable across restarts
+/// without being stored anywhere: a locally administered unicast prefix
+/// followed by the four bytes of the address.
+pub fn vtep_mac(public_ip: Ipv4Addr) -> [u8; 6] {
+    let [a, b, c, d] = public_ip.octets();
+    [0x0e, 0x6b, a, b, c, d]
+}
+
+pub fn format_mac(mac: &[u8; 6]) -> String {
+    mac.iter()
+        .map(|b| format!("{b:02x}"))
+        .collect::<Vec<_>>()
+        .join(":")
+}
+
+pub fn parse_mac(s: &str) -> Result<[u8; 6]> {
+    let bytes = s
+        .split(':')
+        .map(|part| u8::from_str_radix(part, 16))
+        .collect::<Result<Vec<_>, _>>()
+        .with_context(|| format!("invalid MAC address: {s}"))?;
+    bytes
+        .try_into()
+        .map_err(|_| anyhow!("invalid MAC address: {s}"))
+}
+
+/// VXLAN device of this node
+pub struct VxlanDevice {
+    pub name: String,
+    pub index: u32,
+    pub mac: [u8; 6],
+    pub mtu: u32,
+}
+
+impl VxlanDevice {
+    /// Create the VXLAN device on top of the external interface, or reuse it
+    /// if it already exists with the expected MAC address.
+    pub async fn ensure(config: &VxlanConfig, ext_iface: &ExternalInterface) -> Result<Self> {
+        let local_ip = ext_iface
+            .iface_addr
+            .context("VXLAN backend requires an IPv4 address on the external interface")?;
+        let public_ip = ext_iface.ext_addr.unwrap_or(local_ip);
+        let mac = vtep_mac(public_ip);
+        let mtu = ext_iface.iface.mtu.unwrap_or(1500) - OVERHEAD;
+        let name = device_name(config.vni);
+
+        if let Ok(existing) = link::link_by_name(&name).await {
+            let has_mac = existing
+                .attributes
+                .iter()
+                .any(|attr| matches!(attr, LinkAttribute::Address(addr) if addr[..] == mac[..]));
+            if has_mac {
+                info!("Reusing VXLAN device {name}");
+                link::link_set_up(&existing).await?;
+                return Ok(Self {
+                    name,
+                    index: existing.header.index,
+                    mac,
+                    mtu,
+                });
+            }
+            warn!("VXLAN device {name} has an unexpected MAC address, recreating it");
+            link::del_link(existing).await?;
+        }
+
+        let msg = LinkVxlan::new(&name, config.vni)
+            .dev(ext_iface.iface.index)
+            .local(local_ip)
+            .port(config.port)
+            .learning(false)
+            .mtu(mtu)
+            .address(mac.to_vec())
+            .up()
+            .build();
+        link::add_link(msg)
+            .await
+            .with_context(|| format!("failed to create VXLAN device {name}"))?;
+
+        let created = link::link_by_name(&name).await?;
+        link::link_set_up(&created).await?;
+        info!(
+            "Created VXLAN device {name} (VNI {}, port {}, MAC {})",
+            config.vni,
+            config.port,
+            format_mac(&mac)
+        );
+
+        Ok(Self {
+            name,
+            index: created.header.index,
+            mac,
+            mtu,
+        })
+    }
+
+    /// Backend data the other nodes need to reach this one
+    pub fn lease_attrs(&self) -> VxlanLeaseAttrs {
+        VxlanLeaseAttrs {
+            vtep_mac: format_mac(&self.mac),
+        }
+    }
+
+    /// Give the device the tunnel address of `subnet`
+    pub async fn configure(&self, subnet: Ipv4Network, network: Ipv4Network) -> Result<()> {
+        overlay::ensure_tunnel_address(self.index, subnet, network).await
+    }
+
+    /// Resolve the tunnel address of `subnet` to the remote VTEP and send its
+    /// frames to the remote node.
+    pub async fn add_peer(
+        &self,
+        subnet: Ipv4Network,
+        vtep_mac: &str,
+        public_ip: Ipv4Addr,
+    ) -> Result<()> {
+        let mac = parse_mac(vtep_mac)?;
+        if mac == self.mac {
+            bail!("peer {subnet} has the VTEP MAC of this node ({vtep_mac})");
+        }
+        neigh::neigh_set(self.index, IpAddr::V4(subnet.ip()), &mac).await?;
+        neigh::fdb_set(self.index, &mac, IpAddr::V4(public_ip)).await?;
+        info!("Added VXLAN peer {subnet} -> {vtep_mac} at {public_ip}");
+        Ok(())
+    }
+
+    pub async fn remove_peer(
+        &self,
+        subnet: Ipv4Network,
+        vtep_mac: &str,
+        public_ip: Ipv4Addr,
+    ) -> Result<()> {
+        let mac = parse_mac(vtep_mac)?;
+        neigh::neigh_del(self.index, IpAddr::V4(subnet.ip())).await?;
+        neigh::fdb_del(self.index, &mac, IpAddr::V4(public_ip)).await?;
+        info!("Removed VXLAN peer {subnet} -> {vtep_mac} at {public_ip}");
+        Ok(())
+    }
+}
@@ -0,0 +1,237 @@
+use anyhow::{Context, Result, bail};
+use common::ExternalInterface;
+use ipnetwork::Ipv4Network;
+use libcni::ip::link;
+use log::info;
+use rtnetlink::LinkWireguard;
+use serde::{Deserialize, Serialize};
+use std::{
+    fs,
+    io::Write,
+    net::SocketAddr,
+    os::unix::fs::OpenOptionsExt,
+    path::{Path, PathBuf},
+    process::Stdio,
+};
+use tokio::{io::AsyncWriteExt, process::Command};
+
+use crate::overlay;
+
+/// Name of the WireGuard device
+pub const DEVICE_NAME: &str = "rk8s-wg";
+/// Default UDP port WireGuard listens on
+pub const DEFAULT_LISTEN_PORT: u16 = 51820;
+/// Bytes of the outer IPv4 and UDP headers plus the WireGuard header and tag
+pub const OVERHEAD: u32 = 80;
+/// Default location of the private key of this node
+pub const DEFAULT_KEY_FILE: &str = "/etc/rk8s/wireguard/private.key";
+
+/// `Backend` section of a network config using the WireGuard backend
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Eq)]
+pub struct WireguardConfig {
+    #[serde(rename = "ListenPort", default = "default_listen_port")]
+    pub listen_port: u16,
+    /// Seconds between keepalives sent to each peer, 0 to disable them
+    #[serde(rename = "PersistentKeepaliveInterval", default)]
+    pub persistent_keepalive: u16,
+}
+
+impl Default for WireguardConfig {
+    fn default() -> Self {
+        Self {
+            listen_port: DEFAULT_LISTEN_PORT,
+            persistent_keepalive: 0,
+        }
+    }
+}
+
+fn default_listen_port() -> u16 {
+    DEFAULT_LISTEN_PORT
+}
+
+/// `BackendData` of a lease held by a WireGuard node
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Eq)]
+pub struct WireguardLeaseAttrs {
+    #[serde(rename = "PublicKey")]
+    pub public_key: String,
+}
+
+/// Path of the private key of this node, overridable with `WG_KEY_FILE`
+pub fn key_file_path() -> PathBuf {
+    std::env::var("WG_KEY_FILE")
+        .map(PathBuf::from)
+        .unwrap_or_else(|_| PathBuf::from(DEFAULT_KEY_FILE))
+}
+
+/// Key pair of this node. Only the public key ever leaves the node.
+pub struct WireguardKeys {
+    pub private_key_file: PathBuf,
+    pub public_key: String,
+}
+
+impl WireguardKeys {
+    /// Load the private key from `path`, generating and storing a new one
+    /// if the file does not exist yet.
+    pub async fn load_or_generate(path: &Path) -> Result<Self> {
+        if !path.exists() {
+            let private_key = wg(&["genkey"], None).await?;
+            if let Some(parent) = path.parent() {
+                fs::create_dir_all(parent)?;
+            }
+            let mut file = fs::OpenOptions::new()
+                .create_new(true)
+                .write(true)
+                .mode(0o600)
+                .open(path)
+                .with_context(|| format!("failed to create {}", path.display()))?;
+            writeln!(file, "{private_key}")?;
+            info!("Generated WireGuard private key at {}", path.display());
+        }
+
+        let private_key = fs::read_to_string(path)
+            .with_context(|| format!("failed to read {}", path.display()))?;
+        let public_key = wg(&["pubkey"], Some(private_key.trim())).await?;
+
+        Ok(Self {
+            private_key_file: path.to_path_buf(),
+            public_key,
+        })
+    }
+
+    pub fn lease_attrs(&self) -> WireguardLeaseAttrs {
+        WireguardLeaseAttrs {
+            public_key: self.public_key.clone(),
+        }
+    }
+}
+
+/// WireGuard device of this node
+pub struct WireguardDevice {
+    pub name: String,
+    pub index: u32,
+    pub mtu: u32,
+    pub config: WireguardConfig,
+    pub keys: WireguardKeys,
+}
+
+impl WireguardDevice {
+    /// Create the WireGuard device if it does not exist, and give it the
+    /// private key and listen port of this node.
+    pub async fn ensure(
+        config: &WireguardConfig,
+        ext_iface: &ExternalInterface,
+        keys: WireguardKeys,
+    ) -> Result<Self> {
+        let name = DEVICE_NAME.to_string();
+        let mtu = ext_iface.iface.mtu.unwrap_or(1500) - OVERHEAD;
+
+        if link::link_by_name(&name).await.is_err() {
+            let msg = LinkWireguard::new(&name).mtu(mtu).build();
+            link::add_link(msg)
+                .await
+                .with_context(|| format!("failed to create WireGuard device {name}"))?;
+            info!("Created WireGuard device {name}");
+        }
+
+        let key_file = keys.private_key_file.to_string_lossy().into_owned();
+        let listen_port = config.listen_port.to_string();
+        wg(
+            &[
+                "set",
+                &name,
+                "listen-port",
+                &listen_port,
+                "private-key",
+                &key_file,
+            ],
+            None,
+        )
+        .await?;
+
+        let device = link::link_by_name(&name).await?;
+        link::link_set_up(&device).await?;
+        info!(
+            "WireGuard device {name} listening on port {} (public key {})",
+            config.listen_port, keys.public_key
+        );
+
+        Ok(Self {
+            name,
+            index: device.header.index,
+            mtu,
+            config: config.clone(),
+            keys,
+        })
+    }
+
+    /// Give the device the tunnel address of `subnet`
+    pub async fn configure(&self, subnet: Ipv4Network, network: Ipv4Network) -> Result<()> {
+        overlay::ensure_tunnel_address(self.index, subnet, network).await
+    }
+
+    /// Encrypt packets for `subnet` with `public_key` and send them to
+    /// `endpoint`.
+    pub async fn add_peer(
+        &self,
+        subnet: Ipv4Network,
+        public_key: &str,
+        endpoint: SocketAddr,
+    ) -> Result<()> {
+        if public_key == self.keys.public_key {
+            bail!("peer {subnet} has the public key of this node");
+        }
+        let endpoint_arg = endpoint.to_string();
+        let allowed_ips = subnet.to_string();
+        let keepalive = self.config.persistent_keepalive.to_string();
+        let mut args = vec![
+            "set",
+            self.name.as_str(),
+            "peer",
+            public_key,
+            "endpoint",
+            endpoint_arg.as_str(),
+            "allowed-ips",
+            allowed_ips.as_str(),
+        ];
+        if self.config.persistent_keepalive > 0 {
+            args.extend(["persistent-keepalive", keepalive.as_str()]);
+        }
+        wg(&args, None).await?;
+        info!("Added WireGuard peer {subnet} at {endpoint}");
+        Ok(())
+    }
+
+    pub async fn remove_peer(&self, subnet: Ipv4Network, public_key: &str) -> Result<()> {
+        wg(&["set", &self.name, "peer", public_key, "remove"], None).await?;
+        info!("Removed WireGuard peer {subnet}");
+        Ok(())
+    }
+}
+
+/// Run the `wg` tool, feeding it `input` if any, and return its trimmed output
+async fn wg(args: &[&str], input: Option<&str>) -> Result<String> {
+    let mut child = Command::new("wg")
+        .args(args)
+        .stdin(Stdio::piped())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .spawn()
+        .context("failed to run wg, is wireguard-tools installed?")?;
+
+    let mut stdin = child.stdin.take().context("wg stdin unavailable")?;
+    if let Some(input) = input {
+        stdin.write_all(input.as_bytes()).await?;
+        stdin.write_all(b"\n").await?;
+    }
+    drop(stdin);
+
+    let output = child.wait_with_output().await?;
+    if !output.status.success() {
+        bail!(
+            "wg {} failed: {}",
+            args.first().copied().unwrap_or_default(),
+            String::from_utf8_lossy(&output.stderr).trim()
+        );
+    }
+    Ok(String::from_utf8_lossy(&output.stdout).trim().to_string())
+}
@@ -0,0 +1,335 @@
+use common::{
+    IPBlock, LabelSelector, NetworkPolicy, NetworkPolicyIngressRule, NetworkPolicyPeer,
+    NetworkPolicyPort, NetworkPolicySpec, ObjectMeta, PodSpec, PodStatus, PodTask, PolicyType,
+};
+use libnetwork::network_policy::{
+    NAMESPACE_NAME_LABEL, generate_network_policy_config, selector_matches, validate_network_policy,
+};
+use serde_json::Value;
+use std::collections::HashMap;
+use std::io::Write;
+use std::process::{Command, Stdio};
+
+fn pod(name: &str, namespace: &str, node: &str, ip: &str, app: &str) -> PodTask {
+    PodTask {
+        api_version: "v1".into(),
+        kind: "Pod".into(),
+        metadata: ObjectMeta {
+            name: name.into(),
+            namespace: namespace.into(),
+            labels: HashMap::from([("app".to_string(), app.to_string())]),
+            ..Default::default()
+        },
+        spec: PodSpec {
+            node_name: Some(node.into()),
+            ..Default::default()
+        },
+        status: PodStatus {
+            pod_ip: Some(ip.into()),
+            ..Default::default()
+        },
+    }
+}
+
+fn policy(name: &str, namespace: &str, spec: NetworkPolicySpec) -> NetworkPolicy {
+    NetworkPolicy {
+        api_version: "networking.k8s.io/v1".into(),
+        kind: "NetworkPolicy".into(),
+        metadata: ObjectMeta {
+            name: name.into(),
+            namespace: namespace.into(),
+            ..Default::default()
+        },
+        spec,
+    }
+}
+
+fn default_deny(namespace: &str) -> NetworkPolicy {
+    policy(
+        "default-deny",
+        namespace,
+        NetworkPolicySpec {
+            policy_types: Some(vec![PolicyType::Ingress, PolicyType::Egress]),
+            ..Default::default()
+        },
+    )
+}
+
+fn allow_web_from_frontend() -> NetworkPolicy {
+    policy(
+        "allow-web",
+        "prod",
+        NetworkPolicySpec {
+            pod_selector: LabelSelector {
+                match_labels: HashMap::from([("app".to_string(), "web".to_string())]),
+                ..Default::default()
+            },
+            ingress: vec![NetworkPolicyIngressRule {
+                from: vec![
+                    NetworkPolicyPeer {
+                        namespace_selector: Some(LabelSelector {
+                            match_labels: HashMap::from([(
+                                NAMESPACE_NAME_LABEL.to_string(),
+                                "frontend".to_string(),
+                            )]),
+                            ..Default::default()
+                        }),
+                        ..Default::default()
+                    },
+                    NetworkPolicyPeer {
+                        ip_block: Some(IPBlock {
+                            cidr: "192.168.0.0/16".into(),
+                            except: vec!["192.168.1.0/24".into()],
+                        }),
+                        ..Default::default()
+                    },
+                ],
+                ports: vec![NetworkPolicyPort {
+                    protocol: "TCP".into(),
+                    port: Some(8080),
+                    end_port: None,
+                }],
+            }],
+            ..Default::default()
+        },
+    )
+}
+
+fn pods() -> Vec<PodTask> {
+    vec![
+        pod("web", "prod", "node-a", "10.1.1.2", "web"),
+        pod("db", "prod", "node-b", "10.1.2.2", "db"),
+        pod("ui", "frontend", "node-b", "10.1.2.3", "ui"),
+    ]
+}
+
+fn objects(json: &str) -> Vec<Value> {
+    let value: Value = serde_json::from_str(json).expect("invalid nftables json");
+    value["nftables"]
+        .as_array()
+        .expect("missing nftables array")
+        .clone()
+}
+
+/// Elements of the set `name`, empty if there is no such set
+fn set_elements(objects: &[Value], name: &str) -> Vec<String> {
+    objects
+        .iter()
+        .filter_map(|obj| obj.get("set"))
+        .filter(|set| set["name"] == name)
+        .flat_map(|set| set["elem"].as_array().cloned().unwrap_or_default())
+        .filter_map(|elem| elem.as_str().map(str::to_string))
+        .collect()
+}
+
+fn rules_in(objects: &[Value], chain: &str) -> Vec<Value> {
+    objects
+        .iter()
+        .filter_map(|obj| obj.get("rule"))
+        .filter(|rule| rule["chain"] == chain)
+        .cloned()
+        .collect()
+}
+
+fn has_verdict(rule: &Value, verdict: &str) -> bool {
+    rule["expr"]
+        .as_array()
+        .is_some_and(|expr| expr.iter().any(|stmt| stmt.get(verdict).is_some()))
+}
+
+#[test]
+fn test_default_deny_isolates_local_pods() {
+    let json = generate_network_policy_config("node-a", &[default_deny("prod")], &pods())
+        .expect("generate_network_policy_config failed");
+    let objects = objects(&json);
+
+    // Only the prod pod of node-a is isolated on node-a
+    assert_eq!(set_elements(&objects, "np0-pods"), vec!["10.1.1.2"]);
+    assert_eq!(set_elements(&objects, "ingress-isolated"), vec!["10.1.1.2"]);
+    assert_eq!(set_elements(&objects, "egress-isolated"), vec!["10.1.1.2"]);
+
+    // Nothing is accepted besides established connections
+    assert!(rules_in(&objects, "np0-ingress").is_empty());
+    assert!(rules_in(&objects, "np0-egress").is_empty());
+    assert!(
+        rules_in(&objects, "policy-ingress")
+            .iter()
+            .any(|rule| has_verdict(rule, "drop"))
+    );
+
+    validate_with_nft(&json);
+}
+
+#[test]
+fn test_node_without_selected_pods_isolates_nothing() {
+    let json = generate_network_policy_config("node-c", &[default_deny("prod")], &pods())
+        .expect("generate_network_policy_config failed");
+    let objects = objects(&json);
+
+    assert!(set_elements(&objects, "ingress-isolated").is_empty());
+    assert!(
+        !rules_in(&objects, "policy-ingress")
+            .iter()
+            .any(|rule| has_verdict(rule, "drop"))
+    );
+}
+
+#[test]
+fn test_allow_rule_resolves_peers_and_ports() {
+    let policies = [default_deny("prod"), allow_web_from_frontend()];
+    let json = generate_network_policy_config("node-a", &policies, &pods())
+        .expect("generate_network_policy_config failed");
+    let objects = objects(&json);
+
+    // Policies are ordered by namespace/name: allow-web comes first
+    assert_eq!(set_elements(&objects, "np0-pods"), vec!["10.1.1.2"]);
+    assert_eq!(set_elements(&objects, "np0-ingress-0"), vec!["10.1.2.3"]);
+
+    // One accept rule for the frontend pods and one for the IP block
+    let rules = rules_in(&objects, "np0-ingress");
+    assert_eq!(rules.len(), 2);
+    assert!(rules.iter().all(|rule| has_verdict(rule, "accept")));
+    let block_rule = serde_json::to_string(&rules[1]).unwrap();
+    assert!(block_rule.contains("192.168.0.0") && block_rule.contains("192.168.1.0"));
+    assert!(block_rule.contains("8080"));
+
+    // allow-web only has the Ingress type
+    assert!(rules_in(&objects, "np0-egress").is_empty());
+
+    validate_with_nft(&json);
+}
+
+#[test]
+fn test_dual_stack_pod_is_isolated_in_both_families() {
+    let mut pods = pods();
+    pods[0].status.pod_ips = vec!["10.1.1.2".into(), "fd00:1:1::2".into()];
+    pods[2].status.pod_ips = vec!["10.1.2.3".into(), "fd00:1:2::3".into()];
+
+    let mut allow = allow_web_from_frontend();
+    allow.spec.ingress[0].from.push(NetworkPolicyPeer {
+        ip_block: Some(IPBlock {
+            cidr: "2001:db8::/32".into(),
+            except: vec!["2001:db8:1::/48".into()],
+        }),
+        ..Default::default()
+    });
+    validate_network_policy(&allow).expect("IPv6 ipBlock rejected");
+
+    let policies = [default_deny("prod"), allow];
+    let json = generate_network_policy_config("node-a", &policies, &pods)
+        .expect("generate_network_policy_config failed");
+    let objects = objects(&json);
+
+    assert!(
+        objects
+            .iter()
+            .filter_map(|obj| obj.get("table"))
+            .all(|table| table["family"] == "inet")
+    );
+
+    // Each family gets its own sets
+    assert_eq!(set_elements(&objects, "np0-pods"), vec!["10.1.1.2"]);
+    assert_eq!(set_elements(&objects, "np0-pods6"), vec!["fd00:1:1::2"]);
+    assert_eq!(
+        set_elements(&objects, "np0-ingress-06"),
+        vec!["fd00:1:2::3"]
+    );
+    assert_eq!(
+        set_elements(&objects, "ingress-isolated6"),
+        vec!["fd00:1:1::2"]
+    );
+    assert_eq!(
+        set_elements(&objects, "egress-isolated6"),
+        vec!["fd00:1:1::2"]
+    );
+
+    // IPv6 traffic of the pod is dropped like its IPv4 traffic
+    let drops: Vec<String> = rules_in(&objects, "policy-ingress")
+        .iter()
+        .filter(|rule| has_verdict(rule, "drop"))
+        .map(|rule| serde_json::to_string(rule).unwrap())
+        .collect();
+    assert_eq!(drops.len(), 2);
+    assert!(drops.iter().any(|rule| rule.contains("\"ip6\"")));
+
+    // Frontend pods of both families and both IP blocks are accepted
+    let rules = rules_in(&objects, "np0-ingress");
+    assert_eq!(rules.len(), 4);
+    assert!(rules.iter().any(|rule| {
+        serde_json::to_string(rule)
+            .unwrap()
+            .contains("2001:db8:1::")
+    }));
+
+    validate_with_nft(&json);
+}
+
+#[test]
+fn test_ip_block_families_must_match() {
+    let mut allow = allow_web_from_frontend();
+    allow.spec.ingress[0].from[1].ip_block = Some(IPBlock {
+        cidr: "2001:db8::/32".into(),
+        except: vec!["192.168.1.0/24".into()],
+    });
+    assert!(validate_network_policy(&allow).is_err());
+}
+
+#[test]
+fn test_selector_matches() {
+    let labels = HashMap::from([("app".to_string(), "web".to_string())]);
+    assert!(selector_matches(&LabelSelector::default(), &labels));
+    assert!(selector_matches(
+        &LabelSelector {
+            match_labels: labels.clone(),
+            ..Default::default()
+        },
+        &labels
+    ));
+    assert!(!selector_matches(
+        &LabelSelector {
+            match_labels: HashMap::from([("app".to_string(), "db".to_string())]),
+            ..Default::default()
+        },
+        &labels
+    ));
+}
+
+/// Use `nft --check` to validate the generated rules; tolerate missing perms/binary
+fn validate_with_nft(json: &str) {
+    let mut child = match Command::new("nft")
+        .args(["-j", "--check", "-f", "-"])
+        .stdin(Stdio::piped())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .spawn()
+    {
+        Ok(child) => child,
+        Err(e) => {
+            println!("Skipping nft validation: nft not available or failed to start: {e}");
+            return;
+        }
+    };
+
+    if let Some(mut stdin) = child.stdin.take() {
+        stdin
+            .write_all(json.as_bytes())
+            .expect("failed to write nftables json to stdin");
+    }
+
+    let output = child
+        .wait_with_output()
+        .expect("failed to wait for nft --check");
+    let stderr = String::from_utf8_lossy(&output.stderr);
+    let stdout = String::from_utf8_lossy(&output.stdout);
+
+    if !output.status.success() {
+        if stderr.contains("Permission denied")
+            || stderr.contains("Operation not permitted")
+            || (stderr.is_empty() && stdout.is_empty())
+        {
+            println!("Skipping validation: insufficient permissions to run nft --check");
+            return;
+        }
+        panic!("nft configuration invalid: {stderr}");
+    }
+}
@@ -1,8 +1,16 @@
 use common::{
-    Endpoint, EndpointAddress, EndpointPort, EndpointSubset, ObjectMeta, ServicePort, ServiceSpec,
-    ServiceTask,
+    ClientIPConfig, Endpoint, EndpointAddress, EndpointPort, EndpointSubset, IpFamily,
+    LabelSelector, LoadBalancerIngress, LoadBalancerStatus, NetworkPolicy,
+    NetworkPolicyIngressRule, NetworkPolicyPeer, NetworkPolicySpec, ObjectMeta, PodSpec, PodStatus,
+    PodTask, ServicePort, ServiceSpec, ServiceStatus, ServiceTask, SessionAffinityConfig,
 };
-use libnetwork::nftables::generate_nftables_config;
+use libnetwork::network_policy::generate_network_policy_config;
+use libnetwork::nftables::{
+    generate_nftables_config, generate_node_nftables_config, generate_service_delete,
+    generate_service_update,
+};
+use serde_json::Value;
+use std::collections::HashMap;
 use std::io::Write;
 use std::process::{Command, Stdio};
 
@@ -82,7 +90,9 @@ fn test_generate_and_check_nftables() {
             }],
             selector: None,
             service_type: "NodePort".into(),
+            ..Default::default()
         },
+        status: None,
     };
     let ep = Endpoint {
         api_version: "v1".into(),
@@ -98,11 +108,13 @@ fn test_generate_and_check_nftables() {
                     ip: "10.244.1.2".into(),
                     node_name: None,
                     target_ref: None,
+                    weight: None,
                 },
                 EndpointAddress {
                     ip: "10.244.1.3".into(),
                     node_name: None,
                     target_ref: None,
+                    weight: None,
                 },
             ],
             not_ready_addresses: vec![],
@@ -116,8 +128,11 @@ fn test_generate_and_check_nftables() {
     };
     let json = generate_nftables_config(&[svc], &[ep]).expect("generate_nftables_config failed");
     diagnose_transport_payloads(&json);
+    validate_with_nft(&json);
+}
 
-    // Use `nft --check` to validate the generated rules; tolerate missing perms/binary
+/// Use `nft --check` to validate the generated rules; tolerate missing perms/binary
+fn validate_with_nft(json: &str) {
     let spawn_result = Command::new("nft")
         .args(["-j", "--check", "-f", "-"])
         .stdin(Stdio::piped())
@@ -173,3 +188,433 @@ fn test_generate_and_check_nftables() {
         panic!("nft configuration invalid");
     }
 }
+
+fn service(spec: ServiceSpec) -> ServiceTask {
+    ServiceTask {
+        api_version: "v1".into(),
+        kind: "Service".into(),
+        metadata: ObjectMeta {
+            name: "web".into(),
+            namespace: "default".into(),
+            ..Default::default()
+        },
+        spec: ServiceSpec {
+            cluster_ip: Some("10.96.0.50".into()),
+            ports: vec![ServicePort {
+                name: None,
+                protocol: "TCP".into(),
+                port: 80,
+                target_port: Some(8080),
+                node_port: Some(30081),
+            }],
+            ..spec
+        },
+        status: None,
+    }
+}
+
+/// Endpoints of `web` at `(ip, node, weight)`
+fn endpoints(addresses: &[(&str, &str, Option<u32>)]) -> Endpoint {
+    Endpoint {
+        api_version: "v1".into(),
+        kind: "Endpoints".into(),
+        metadata: ObjectMeta {
+            name: "web".into(),
+            namespace: "default".into(),
+            ..Default::default()
+        },
+        subsets: vec![EndpointSubset {
+            addresses: addresses
+                .iter()
+                .map(|(ip, node, weight)| EndpointAddress {
+                    ip: ip.to_string(),
+                    node_name: Some(node.to_string()),
+                    target_ref: None,
+                    weight: *weight,
+                })
+                .collect(),
+            not_ready_addresses: vec![],
+            ports: vec![EndpointPort {
+                name: None,
+                port: 8080,
+                protocol: "TCP".into(),
+                app_protocol: None,
+            }],
+        }],
+    }
+}
+
+fn objects(json: &str) -> Vec<Value> {
+    let value: Value = serde_json::from_str(json).expect("invalid nftables json");
+    value["nftables"]
+        .as_array()
+        .expect("missing nftables array")
+        .clone()
+}
+
+fn rules_in(objects: &[Value], chain: &str) -> Vec<String> {
+    objects
+        .iter()
+        .filter_map(|obj| obj.get("rule"))
+        .filter(|rule| rule["chain"] == chain)
+        .map(|rule| rule["expr"].to_string())
+        .collect()
+}
+
+#[test]
+fn test_weighted_backends_share_slots() {
+    let svc = service(ServiceSpec::default());
+    let ep = endpoints(&[
+        ("10.244.1.2", "node-a", Some(3)),
+        ("10.244.2.2", "node-b", None),
+        ("10.244.2.3", "node-b", Some(0)),
+    ]);
+    let json = generate_nftables_config(&[svc], &[ep]).expect("generate_nftables_config failed");
+    let rules = rules_in(&objects(&json), "svc-default-web-80");
+
+    // Drained backend excluded, the remaining ones get 3 + 1 slots, each with
+    // a hairpin rule and a rule for the other clients
+    assert_eq!(rules.len(), 5);
+    assert!(rules[0].contains(r#""mod":4"#));
+    for rule in &rules[1..3] {
+        assert!(rule.contains(r#""op":">=""#) && rule.contains(r#""op":"<=""#));
+        assert!(rule.contains("10.244.1.2"));
+    }
+    for rule in &rules[3..5] {
+        assert!(rule.contains(r#""right":3"#) && rule.contains("10.244.2.2"));
+    }
+    assert!(!json.contains("10.244.2.3"));
+
+    validate_with_nft(&json);
+}
+
+fn affinity_service(timeout: u32) -> ServiceTask {
+    service(ServiceSpec {
+        session_affinity: Some("ClientIP".into()),
+        session_affinity_config: Some(SessionAffinityConfig {
+            client_ip: Some(ClientIPConfig {
+                timeout_seconds: Some(timeout),
+            }),
+        }),
+        ..Default::default()
+    })
+}
+
+#[test]
+fn test_client_ip_affinity_uses_timeout_map() {
+    let svc = affinity_service(600);
+    let ep = endpoints(&[
+        ("10.244.1.2", "node-a", None),
+        ("10.244.2.2", "node-b", None),
+    ]);
+    let json = generate_nftables_config(&[svc], &[ep]).expect("generate_nftables_config failed");
+    let objects = objects(&json);
+
+    let map = objects
+        .iter()
+        .filter_map(|obj| obj.get("map"))
+        .find(|map| map["name"] == "svc-default-web-80-affinity")
+        .expect("missing affinity map");
+    assert_eq!(map["timeout"], 600);
+
+    // Every backend refreshes the entry of the client, hairpin or not
+    let rules = rules_in(&objects, "svc-default-web-80");
+    let backend_rules: Vec<_> = rules.iter().filter(|r| r.contains("dnat")).collect();
+    assert_eq!(backend_rules.len(), 4);
+    assert!(
+        backend_rules
+            .iter()
+            .all(|r| r.contains("@svc-default-web-80-affinity"))
+    );
+
+    validate_with_nft(&json);
+}
+
+/// Position of the first `delete` command of the `kind` object named `name`
+fn delete_position(objects: &[Value], kind: &str, name: &str) -> Option<usize> {
+    objects.iter().position(|obj| {
+        obj.get("delete")
+            .and_then(|delete| delete.get(kind))
+            .is_some_and(|object| object["name"] == name)
+    })
+}
+
+#[test]
+fn test_service_delete_removes_affinity_maps_and_local_chain() {
+    let mut svc = affinity_service(600);
+    svc.spec.external_traffic_policy = Some("Local".into());
+    let json = generate_service_delete(&svc).expect("generate_service_delete failed");
+    let objects = objects(&json);
+
+    let local_chain = delete_position(&objects, "chain", "svc-default-web-80-local")
+        .expect("local chain not deleted");
+    for map in [
+        "svc-default-web-80-affinity",
+        "svc-default-web-80-local-affinity",
+    ] {
+        let position = delete_position(&objects, "map", map).expect("affinity map not deleted");
+        // Maps go once the chains referencing them are gone
+        assert!(position > local_chain);
+    }
+
+    // The NodePort rule of the nodes jumps to the local chain
+    let nodeport_delete = objects
+        .iter()
+        .filter_map(|obj| obj.get("delete").and_then(|delete| delete.get("rule")))
+        .find(|rule| rule["expr"].to_string().contains("30081"))
+        .expect("NodePort rule not deleted");
+    assert!(
+        nodeport_delete["expr"]
+            .to_string()
+            .contains("svc-default-web-80-local")
+    );
+}
+
+#[test]
+fn test_service_update_replaces_affinity_map_on_timeout_change() {
+    let ep = endpoints(&[
+        ("10.244.1.2", "node-a", None),
+        ("10.244.2.2", "node-b", None),
+    ]);
+
+    let json = generate_service_update(&affinity_service(300), &ep, Some(&affinity_service(600)))
+        .expect("generate_service_update failed");
+    let objects = objects(&json);
+    let deleted = delete_position(&objects, "map", "svc-default-web-80-affinity")
+        .expect("stale affinity map not deleted");
+    let added = objects
+        .iter()
+        .rposition(|obj| {
+            obj.get("map")
+                .is_some_and(|map| map["name"] == "svc-default-web-80-affinity")
+        })
+        .expect("affinity map not added");
+    assert!(added > deleted);
+    assert_eq!(objects[added]["map"]["timeout"], 300);
+
+    // An unchanged affinity keeps the map and the clients it remembers
+    let json = generate_service_update(&affinity_service(300), &ep, Some(&affinity_service(300)))
+        .expect("generate_service_update failed");
+    assert!(delete_position(&objects(&json), "map", "svc-default-web-80-affinity").is_none());
+}
+
+#[test]
+fn test_external_traffic_local_only_targets_node_endpoints() {
+    let mut svc = service(ServiceSpec {
+        service_type: "LoadBalancer".into(),
+        external_traffic_policy: Some("Local".into()),
+        ..Default::default()
+    });
+    svc.status = Some(ServiceStatus {
+        load_balancer: LoadBalancerStatus {
+            ingress: vec![LoadBalancerIngress {
+                ip: "192.168.10.240".into(),
+            }],
+        },
+    });
+    let ep = endpoints(&[
+        ("10.244.1.2", "node-a", None),
+        ("10.244.2.2", "node-b", None),
+    ]);
+
+    let json = generate_node_nftables_config(&[svc.clone()], &[ep.clone()], "node-a")
+        .expect("generate_node_nftables_config failed");
+    let objects = objects(&json);
+
+    let local = rules_in(&objects, "svc-default-web-80-local");
+    assert_eq!(local.len(), 1);
+    assert!(local[0].contains("10.244.1.2") && !local[0].contains("10.244.2.2"));
+
+    // NodePort and VIP traffic goes to the local chain, ClusterIP traffic does not
+    let dispatch = rules_in(&objects, "services_tcp");
+    let to_local: Vec<_> = dispatch
+        .iter()
+        .filter(|r| r.contains("svc-default-web-80-local"))
+        .collect();
+    assert_eq!(to_local.len(), 2);
+    assert!(to_local.iter().any(|r| r.contains("192.168.10.240")));
+    assert!(to_local.iter().any(|r| r.contains("30081")));
+    assert!(!to_local.iter().any(|r| r.contains("10.96.0.50")));
+    validate_with_nft(&json);
+
+    // Nodes without endpoints drop the external traffic
+    let json = generate_node_nftables_config(&[svc], &[ep], "node-c")
+        .expect("generate_node_nftables_config failed");
+    let local = rules_in(&objects(&json), "svc-default-web-80-local");
+    assert_eq!(local.len(), 1);
+    assert!(local[0].contains("drop"));
+}
+
+#[test]
+fn test_dual_stack_service_gets_ip6_table() {
+    let svc = service(ServiceSpec {
+        cluster_ips: vec!["10.96.0.50".into(), "fd00:96::50".into()],
+        ip_families: vec![IpFamily::IPv4, IpFamily::IPv6],
+        ..Default::default()
+    });
+    let ep = endpoints(&[
+        ("10.244.1.2", "node-a", None),
+        ("fd00:244:1::2", "node-a", None),
+    ]);
+    let json = generate_nftables_config(&[svc], &[ep]).expect("generate_nftables_config failed");
+    let objects = objects(&json);
+
+    let tables: Vec<_> = objects
+        .iter()
+        .filter_map(|obj| obj.get("table"))
+        .map(|table| table["family"].as_str().unwrap_or_default())
+        .collect();
+    assert!(tables.contains(&"ip") && tables.contains(&"ip6"));
+
+    // Each table only dispatches its ClusterIP to the backends of its family
+    let rules_of = |family: &str| -> Vec<String> {
+        objects
+            .iter()
+            .filter_map(|obj| obj.get("rule"))
+            .filter(|rule| rule["family"] == family)
+            .map(|rule| rule["expr"].to_string())
+            .collect()
+    };
+    let (v4, v6) = (rules_of("ip").join("\n"), rules_of("ip6").join("\n"));
+    assert!(v4.contains("10.96.0.50") && v4.contains("10.244.1.2"));
+    assert!(!v4.contains("fd00:"));
+    assert!(v6.contains("fd00:96::50") && v6.contains("fd00:244:1::2"));
+    assert!(!v6.contains("10.96.0.50") && !v6.contains("10.244.1.2"));
+
+    validate_with_nft(&json);
+}
+
+#[test]
+fn test_ip6_table_dropped_with_last_ipv6_service() {
+    let dual_stack = service(ServiceSpec {
+        cluster_ips: vec!["10.96.0.50".into(), "fd00:96::50".into()],
+        ip_families: vec![IpFamily::IPv4, IpFamily::IPv6],
+        ..Default::default()
+    });
+    let ep = endpoints(&[
+        ("10.244.1.2", "node-a", None),
+        ("fd00:244:1::2", "node-a", None),
+    ]);
+    let ip6_deleted = |objects: &[Value]| {
+        objects
+            .iter()
+            .filter_map(|obj| obj.get("delete"))
+            .filter_map(|delete| delete.get("table"))
+            .any(|table| table["family"] == "ip6" && table["name"] == "rk8s")
+    };
+
+    // With an IPv6 ClusterIP, the ip6 table is flushed and filled
+    let json = generate_nftables_config(&[dual_stack], std::slice::from_ref(&ep))
+        .expect("generate_nftables_config failed");
+    let objects = objects(&json);
+    assert!(!ip6_deleted(&objects));
+    assert!(
+        objects
+            .iter()
+            .filter_map(|obj| obj.get("rule"))
+            .any(|rule| rule["family"] == "ip6")
+    );
+
+    // Once the Service is IPv4-only, the next sync drops the ip6 table and its rules
+    let json = generate_nftables_config(&[service(ServiceSpec::default())], &[ep])
+        .expect("generate_nftables_config failed");
+    let objects = objects(&json);
+    assert!(ip6_deleted(&objects));
+    assert!(
+        !objects
+            .iter()
+            .filter_map(|obj| obj.get("rule"))
+            .any(|rule| rule["family"] == "ip6" || rule["expr"].to_string().contains("fd00:"))
+    );
+
+    validate_with_nft(&json);
+}
+
+fn pod(name: &str, node: &str, ip: &str) -> PodTask {
+    PodTask {
+        api_version: "v1".into(),
+        kind: "Pod".into(),
+        metadata: ObjectMeta {
+            name: name.into(),
+            namespace: "default".into(),
+            labels: HashMap::from([("app".to_string(), name.to_string())]),
+            ..Default::default()
+        },
+        spec: PodSpec {
+            node_name: Some(node.into()),
+            ..Default::default()
+        },
+        status: PodStatus {
+            pod_ip: Some(ip.into()),
+            ..Default::default()
+        },
+    }
+}
+
+fn app_selector(app: &str) -> LabelSelector {
+    LabelSelector {
+        match_labels: HashMap::from([("app".to_string(), app.to_string())]),
+        ..Default::default()
+    }
+}
+
+#[test]
+fn test_cluster_ip_traffic_keeps_pod_source() {
+    // `client` on node-b reaches `web` on node-a through the ClusterIP
+    let svc = service(ServiceSpec::default());
+    let ep = endpoints(&[("10.244.1.2", "node-a", None)]);
+    let json = generate_node_nftables_config(&[svc], &[ep], "node-b")
+        .expect("generate_node_nftables_config failed");
+    let objects = objects(&json);
+
+    // Only the traffic of the backend to itself gets the masquerade mark
+    let masquerade = rules_in(&objects, "masquerade");
+    assert_eq!(masquerade.len(), 1);
+    assert!(masquerade[0].contains(r#""right":16384"#));
+    let rules = rules_in(&objects, "svc-default-web-80");
+    assert_eq!(rules.len(), 2);
+    assert!(rules[0].contains(r#""saddr""#) && rules[0].contains(r#""value":16384"#));
+    assert!(!rules[1].contains(r#""saddr""#) && rules[1].contains(r#""value":0"#));
+    assert!(
+        !rules_in(&objects, "services_tcp")
+            .iter()
+            .any(|r| r.contains("mangle"))
+    );
+    validate_with_nft(&json);
+
+    // So it reaches node-a from the address of `client`, which the ingress
+    // policy of `web` accepts
+    let policy = NetworkPolicy {
+        api_version: "networking.k8s.io/v1".into(),
+        kind: "NetworkPolicy".into(),
+        metadata: ObjectMeta {
+            name: "allow-client".into(),
+            namespace: "default".into(),
+            ..Default::default()
+        },
+        spec: NetworkPolicySpec {
+            pod_selector: app_selector("web"),
+            ingress: vec![NetworkPolicyIngressRule {
+                from: vec![NetworkPolicyPeer {
+                    pod_selector: Some(app_selector("client")),
+                    ..Default::default()
+                }],
+                ..Default::default()
+            }],
+            ..Default::default()
+        },
+    };
+    let pods = [
+        pod("web", "node-a", "10.244.1.2"),
+        pod("client", "node-b", "10.244.2.5"),
+    ];
+    let json = generate_network_policy_config("node-a", &[policy], &pods)
+        .expect("generate_network_policy_config failed");
+    let allowed: Vec<_> = objects(&json)
+        .iter()
+        .filter_map(|obj| obj.get("set"))
+        .filter(|set| set["name"] == "np0-ingress-0")
+        .flat_map(|set| set["elem"].as_array().cloned().unwrap_or_default())
+        .collect();
+    assert_eq!(allowed, vec![Value::from("10.244.2.5")]);
+}
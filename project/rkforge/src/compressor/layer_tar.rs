@@ -0,0 +1,399 @@
+use std::{
+    ffi::OsStr,
+    fs::{self, File, Metadata},
+    io::{self, BufReader, BufWriter, Read, Write},
+    os::unix::{
+        ffi::OsStrExt,
+        fs::{FileTypeExt, MetadataExt},
+    },
+    path::{Path, PathBuf},
+};
+
+use anyhow::{Context, Result, anyhow, bail};
+use flate2::write::GzEncoder;
+use rand::{Rng, distr::Alphanumeric};
+use sha2::{Digest, Sha256};
+use tar::{Builder, Header};
+use walkdir::WalkDir;
+
+/// A wrapper around a `Write`able object that calculates the SHA256 hash and total size
+/// of the data being written, all in a single pass.
+///
+/// When creating a compressed tar archive (`.tar.gz`), we often need two pieces of
+/// information for both the uncompressed tar data and the final compressed gzip data:
+/// 1. The total size in bytes.
+/// 2. The SHA256 checksum.
+///
+/// A naive approach would be to first write the data to a file, then read it back
+/// entirely to calculate its size and hash. This is highly inefficient as it requires
+/// double the I/O operations.
+///
+/// `HashingWriter` solves this by implementing the `Write` trait itself. It acts as a
+/// "pass-through" adapter. When data is written to `HashingWriter`, it performs three
+/// actions simultaneously:
+/// - It passes the data to the inner `writer` (e.g., a file or another encoder).
+/// - It updates its internal `Sha256` hasher with the same data.
+/// - It increments its internal `size` counter.
+///
+/// This allows us to calculate the hash and size "on the fly" as the data streams
+/// through, avoiding extra I/O passes and unnecessary memory buffering. It is used in
+/// a chain (`tar -> encoder -> file`) to get metrics for both the raw tar data and the
+/// final compressed data efficiently.
+pub(super) struct HashingWriter<W: Write> {
+    writer: W,
+    hasher: Sha256,
+    size: u64,
+}
+
+impl<W: Write> HashingWriter<W> {
+    pub(super) fn new(writer: W) -> Self {
+        Self {
+            writer,
+            hasher: Sha256::new(),
+            size: 0,
+        }
+    }
+
+    /// Number of bytes written so far.
+    pub(super) fn size(&self) -> u64 {
+        self.size
+    }
+
+    pub(super) fn finalize(self) -> (W, String, u64) {
+        let hash = format!("{:x}", self.hasher.finalize());
+        (self.writer, hash, self.size)
+    }
+}
+
+impl<W: Write> Write for HashingWriter<W> {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        let n = self.writer.write(buf)?;
+        self.hasher.update(&buf[..n]);
+        self.size += n as u64;
+        Ok(n)
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        self.writer.flush()
+    }
+}
+
+/// Encoders that wrap a writer and hand it back once the compressed stream is terminated.
+pub(super) trait StreamEncoder<W: Write>: Write {
+    fn finish_stream(self) -> io::Result<W>;
+}
+
+impl<W: Write> StreamEncoder<W> for GzEncoder<W> {
+    fn finish_stream(self) -> io::Result<W> {
+        self.finish()
+    }
+}
+
+impl<W: Write> StreamEncoder<W> for zstd::Encoder<'static, W> {
+    fn finish_stream(self) -> io::Result<W> {
+        self.finish()
+    }
+}
+
+/// Digests and sizes of a written layer blob.
+pub(super) struct BlobDigests {
+    pub tar_sha256sum: String,
+    pub tar_size: u64,
+    pub blob_sha256sum: String,
+    pub blob_size: u64,
+}
+
+/// Where the uncompressed layer tar comes from.
+pub(super) enum TarSource<'a> {
+    /// A layer directory that is archived on the fly.
+    Dir(&'a Path),
+    /// An existing uncompressed tar stream.
+    Stream(&'a mut dyn Read),
+}
+
+impl TarSource<'_> {
+    /// Write the uncompressed tar stream into `writer` and hand the writer back.
+    pub(super) fn write_to<W: Write>(self, writer: W) -> Result<W> {
+        match self {
+            Self::Dir(source_path) => {
+                let mut builder = Builder::new(writer);
+                append_layer_dir(&mut builder, source_path)?;
+                Ok(builder.into_inner()?)
+            }
+            Self::Stream(reader) => {
+                let mut writer = writer;
+                io::copy(reader, &mut writer).context("Failed to copy layer tar stream")?;
+                Ok(writer)
+            }
+        }
+    }
+}
+
+/// Compress the whole tar stream with a single encoder and store it as a blob in `output_dir`.
+pub(super) fn compress_stream<E, F>(
+    source: TarSource<'_>,
+    output_dir: &Path,
+    extension: &str,
+    encoder: F,
+) -> Result<BlobDigests>
+where
+    E: StreamEncoder<HashingWriter<BufWriter<File>>>,
+    F: FnOnce(HashingWriter<BufWriter<File>>) -> io::Result<E>,
+{
+    let blob_path = temp_blob_path(output_dir, extension);
+    let blob_file = File::create(&blob_path)
+        .with_context(|| format!("Failed to create {}", blob_path.display()))?;
+    let blob_writer = BufWriter::with_capacity(1024 * 1024, blob_file);
+    let blob_hashing_writer = HashingWriter::new(blob_writer);
+
+    let tar_hashing_writer = HashingWriter::new(encoder(blob_hashing_writer)?);
+    let (encoder, tar_sha256sum, tar_size) = source.write_to(tar_hashing_writer)?.finalize();
+    let (mut blob_writer, blob_sha256sum, blob_size) = encoder.finish_stream()?.finalize();
+    blob_writer.flush()?;
+
+    persist_blob(&blob_path, output_dir, &blob_sha256sum)?;
+    Ok(BlobDigests {
+        tar_sha256sum,
+        tar_size,
+        blob_sha256sum,
+        blob_size,
+    })
+}
+
+/// Archive `source_path` on a helper thread and let `consume` read the tar stream.
+///
+/// Used by compressors that need to walk the tar entries rather than its raw bytes.
+pub(super) fn with_layer_tar<T>(
+    source_path: &Path,
+    consume: impl FnOnce(&mut dyn Read) -> Result<T>,
+) -> Result<T> {
+    let (reader, writer) = io::pipe().context("Failed to create pipe for layer tar")?;
+    std::thread::scope(|scope| {
+        let producer = scope.spawn(move || -> Result<()> {
+            let writer = BufWriter::with_capacity(1024 * 1024, writer);
+            TarSource::Dir(source_path)
+                .write_to(writer)?
+                .flush()
+                .context("Failed to flush layer tar stream")
+        });
+
+        let consumed = {
+            let mut reader = BufReader::with_capacity(1024 * 1024, reader);
+            let consumed = consume(&mut reader);
+            // Drain whatever the consumer left behind (e.g. the tar end-of-archive
+            // blocks) so the producer never blocks or fails on a closed pipe.
+            io::copy(&mut reader, &mut io::sink()).context("Failed to drain layer tar")?;
+            consumed
+        };
+        producer
+            .join()
+            .map_err(|_| anyhow!("Layer tar writer panicked"))??;
+        consumed
+    })
+}
+
+/// A random, not yet content-addressed blob path inside `output_dir`.
+pub(super) fn temp_blob_path(output_dir: &Path, extension: &str) -> PathBuf {
+    let rng = rand::rng();
+    let random_string: String = rng
+        .sample_iter(&Alphanumeric)
+        .take(10)
+        .map(char::from)
+        .collect();
+    output_dir.join(format!("{random_string}.{extension}"))
+}
+
+/// Rename a finished blob to its digest.
+pub(super) fn persist_blob(blob_path: &Path, output_dir: &Path, sha256sum: &str) -> Result<()> {
+    let formatted_path = output_dir.join(sha256sum);
+    fs::rename(blob_path, &formatted_path).with_context(|| {
+        format!(
+            "Failed to rename {} to {}",
+            blob_path.display(),
+            formatted_path.display()
+        )
+    })
+}
+
+/// Skip virtual file system
+fn should_skip_path(path: &Path) -> bool {
+    let vfs_roots = [
+        Path::new("/proc"),
+        Path::new("/sys"),
+        Path::new("/dev"),
+        Path::new("/run"),
+    ];
+
+    for vfs_root in &vfs_roots {
+        if path.starts_with(vfs_root) {
+            return true;
+        }
+    }
+    false
+}
+
+/// Add regular file
+fn append_file<W: Write>(
+    builder: &mut Builder<W>,
+    path: &Path,
+    name: &Path,
+    metadata: &Metadata,
+) -> Result<()> {
+    let file = File::open(path).with_context(|| format!("Cannot open file: {}", path.display()))?;
+    let mut file = BufReader::with_capacity(1024 * 1024, file);
+    let mut header = Header::new_gnu();
+    header.set_metadata(metadata);
+    header.set_path(name)?;
+    header.set_size(metadata.len());
+    header.set_cksum();
+
+    builder
+        .append(&header, &mut file)
+        .with_context(|| format!("Failed to append file {} to tar archive", path.display()))
+}
+
+/// Add directory
+fn append_dir<W: Write>(
+    builder: &mut Builder<W>,
+    path: &Path,
+    name: &Path,
+    metadata: &Metadata,
+) -> Result<()> {
+    let mut header = Header::new_gnu();
+    header.set_metadata(metadata);
+    let dir_name_bytes = name.as_os_str().as_bytes();
+    let dir_name_with_slash = if dir_name_bytes.last() == Some(&b'/') {
+        name.as_os_str().to_owned()
+    } else {
+        let mut new_name = dir_name_bytes.to_vec();
+        new_name.push(b'/');
+        OsStr::from_bytes(&new_name).to_owned()
+    };
+    header.set_path(Path::new(&dir_name_with_slash))?;
+    header.set_size(0);
+    header.set_entry_type(tar::EntryType::Directory);
+    header.set_cksum();
+
+    builder.append(&header, &mut io::empty()).with_context(|| {
+        format!(
+            "Failed to append directory {} to tar archive",
+            path.display()
+        )
+    })
+}
+
+/// Add symbolic link
+fn append_symlink<W: Write>(builder: &mut Builder<W>, path: &Path, name: &Path) -> Result<()> {
+    let target = fs::read_link(path)?;
+    let mut header = Header::new_gnu();
+    let metadata = fs::symlink_metadata(path)?;
+    header.set_metadata(&metadata);
+    header.set_path(name)?;
+    header.set_link_name(&target)?;
+    header.set_entry_type(tar::EntryType::Symlink);
+    header.set_size(0);
+    header.set_cksum();
+
+    builder
+        .append(&header, &mut io::empty())
+        .with_context(|| format!("Failed to append symlink {} to tar archive", path.display()))
+}
+
+/// Add special file
+fn append_special_file<W: Write>(
+    builder: &mut Builder<W>,
+    path: &Path,
+    name: &Path,
+    metadata: &Metadata,
+) -> Result<()> {
+    let mut header = Header::new_gnu();
+    header.set_metadata(metadata);
+    header.set_path(name)?;
+    header.set_size(0);
+    let file_type = metadata.file_type();
+    if file_type.is_block_device() {
+        header.set_entry_type(tar::EntryType::Block);
+    } else if file_type.is_char_device() {
+        header.set_entry_type(tar::EntryType::Char);
+    } else if file_type.is_file() {
+        header.set_entry_type(tar::EntryType::Fifo);
+    } else if file_type.is_socket() {
+        header.set_entry_type(tar::EntryType::Regular);
+    }
+    if file_type.is_block_device() || file_type.is_char_device() {
+        let dev_major = (metadata.rdev() >> 8) & 0xFFF;
+        let dev_minor = metadata.rdev() & 0xFF;
+        header.set_device_major(dev_major as _)?;
+        header.set_device_minor(dev_minor as _)?;
+    }
+    header.set_cksum();
+
+    builder
+        .append(&header, &mut io::empty())
+        .with_context(|| format!("Failed to append symlink {} to tar archive", path.display()))
+}
+
+/// Append every entry below `source_path` to `builder`, using paths relative to it.
+fn append_layer_dir<W: Write>(builder: &mut Builder<W>, source_path: &Path) -> Result<()> {
+    if !source_path.exists() {
+        bail!("Source path doesn't exist: {}", source_path.display());
+    }
+    if !source_path.is_dir() {
+        bail!("Source path is not a directory: {}", source_path.display());
+    }
+
+    for entry_result in WalkDir::new(source_path)
+        .follow_links(false)
+        .into_iter()
+        .filter_entry(|e| !should_skip_path(e.path()))
+    {
+        let entry = match entry_result {
+            Ok(entry) => entry,
+            Err(err) => {
+                tracing::error!("Error in walkdir: {err}");
+                continue;
+            }
+        };
+
+        let path = entry.path();
+        let metadata = match entry.metadata() {
+            Ok(meta) => meta,
+            Err(_) => {
+                tracing::error!("Failed to get metadata from {}", path.display());
+                continue;
+            }
+        };
+
+        // relative path used in tar file
+        let relative_path = match path.strip_prefix(source_path) {
+            Ok(rel_path) => rel_path,
+            Err(_) => {
+                continue;
+            }
+        };
+
+        // skip source directory itself
+        if relative_path.as_os_str().is_empty() {
+            continue;
+        }
+
+        let _result = if metadata.is_file() {
+            append_file(builder, path, relative_path, &metadata)
+        } else if metadata.is_dir() {
+            append_dir(builder, path, relative_path, &metadata)
+        } else if metadata.file_type().is_symlink() {
+            append_symlink(builder, path, relative_path)
+        } else if metadata.file_type().is_block_device()
+            || metadata.file_type().is_char_device()
+            || metadata.file_type().is_fifo()
+            || metadata.file_type().is_socket()
+        {
+            // use unix header
+            append_special_file(builder, path, relative_path, &metadata)
+        } else {
+            tracing::warn!("Skip unknown file type: {}", path.display());
+            continue;
+        };
+    }
+    Ok(())
+}
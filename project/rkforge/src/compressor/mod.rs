@@ -1,26 +1,102 @@
+mod layer_tar;
+pub mod seekable_compressor;
 pub mod tar_gz_compressor;
+pub mod zstd_compressor;
 
-use std::path::PathBuf;
+use std::{collections::HashMap, io::Read, path::Path, path::PathBuf, sync::Arc};
 
 use anyhow::Result;
+use clap::ValueEnum;
+use oci_spec::image::MediaType;
+
+use seekable_compressor::{
+    ESTARGZ_TOC_DIGEST_ANNOTATION, SeekableCompressor, SeekableFormat,
+    ZSTD_CHUNKED_MANIFEST_CHECKSUM_ANNOTATION,
+};
+use tar_gz_compressor::TarGzCompressor;
+use zstd_compressor::ZstdCompressor;
+
+/// Layer blob formats that rkforge can produce.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, ValueEnum)]
+pub enum LayerCompression {
+    /// `tar+gzip`, understood by every registry and runtime
+    #[default]
+    Gzip,
+    /// `tar+zstd`, faster to compress and unpack than gzip
+    Zstd,
+    /// Seekable gzip (eStargz) that lazy-pulling snapshotters can mount on demand
+    Estargz,
+    /// Seekable zstd (zstd:chunked) with per-file frames and an embedded TOC
+    ZstdChunked,
+}
+
+impl LayerCompression {
+    pub fn media_type(self) -> MediaType {
+        match self {
+            Self::Gzip | Self::Estargz => MediaType::ImageLayerGzip,
+            Self::Zstd | Self::ZstdChunked => MediaType::ImageLayerZstd,
+        }
+    }
+
+    pub fn compressor(self) -> Arc<dyn LayerCompressor + Send + Sync> {
+        match self {
+            Self::Gzip => Arc::new(TarGzCompressor),
+            Self::Zstd => Arc::new(ZstdCompressor),
+            Self::Estargz => Arc::new(SeekableCompressor::new(SeekableFormat::Estargz)),
+            Self::ZstdChunked => Arc::new(SeekableCompressor::new(SeekableFormat::ZstdChunked)),
+        }
+    }
+
+    /// Whether an existing layer descriptor is already in this format.
+    ///
+    /// Seekable layers share their media type with the plain formats and are told
+    /// apart by their TOC annotations.
+    pub fn matches(self, media_type: &str, annotations: Option<&HashMap<String, String>>) -> bool {
+        let has_annotation = |key: &str| annotations.is_some_and(|a| a.contains_key(key));
+        media_type == self.media_type().to_string()
+            && match self {
+                Self::Gzip => !has_annotation(ESTARGZ_TOC_DIGEST_ANNOTATION),
+                Self::Zstd => !has_annotation(ZSTD_CHUNKED_MANIFEST_CHECKSUM_ANNOTATION),
+                Self::Estargz => has_annotation(ESTARGZ_TOC_DIGEST_ANNOTATION),
+                Self::ZstdChunked => has_annotation(ZSTD_CHUNKED_MANIFEST_CHECKSUM_ANNOTATION),
+            }
+    }
+}
 
 #[derive(Debug, Clone)]
 pub struct LayerCompressionResult {
     pub tar_sha256sum: String,
     pub tar_size: u64,
-    pub gz_sha256sum: String,
-    pub gz_size: u64,
+    pub blob_sha256sum: String,
+    pub blob_size: u64,
+    pub media_type: MediaType,
+    /// Descriptor annotations required to consume the blob, e.g. the TOC digest of
+    /// seekable layers.
+    pub annotations: HashMap<String, String>,
 }
 
 impl LayerCompressionResult {
-    pub fn new(tar_sha256sum: String, tar_size: u64, gz_sha256sum: String, gz_size: u64) -> Self {
+    pub fn new(
+        tar_sha256sum: String,
+        tar_size: u64,
+        blob_sha256sum: String,
+        blob_size: u64,
+        media_type: MediaType,
+    ) -> Self {
         Self {
             tar_sha256sum,
             tar_size,
-            gz_sha256sum,
-            gz_size,
+            blob_sha256sum,
+            blob_size,
+            media_type,
+            annotations: HashMap::new(),
         }
     }
+
+    pub fn annotations(mut self, annotations: HashMap<String, String>) -> Self {
+        self.annotations = annotations;
+        self
+    }
 }
 
 #[derive(Debug, Clone)]
@@ -39,8 +115,12 @@ impl LayerCompressionConfig {
 }
 
 pub trait LayerCompressor {
-    /// Compress layer to tar.gz
+    /// Archive and compress a layer directory into a blob under `output_dir`
     ///
-    /// Returns the size and sha256sum of the result
+    /// Returns the size and sha256sum of both the tar and the blob
     fn compress_layer(&self, config: &LayerCompressionConfig) -> Result<LayerCompressionResult>;
+
+    /// Compress an existing uncompressed tar stream into a blob under `output_dir`
+    fn compress_tar(&self, tar: &mut dyn Read, output_dir: &Path)
+    -> Result<LayerCompressionResult>;
 }
@@ -0,0 +1,767 @@
+//! Seekable layer formats: eStargz and zstd:chunked.
+//!
+//! Both formats store every tar entry (and every `CHUNK_SIZE` piece of large files)
+//! in its own gzip member or zstd frame, followed by a JSON table of contents that
+//! records where each file lives in the compressed blob. The blob still decompresses
+//! to a plain tar, so runtimes without lazy pulling unpack it like any other layer,
+//! while snapshotters that understand the TOC can fetch single files on demand.
+
+use std::{
+    borrow::Cow,
+    collections::HashMap,
+    fs::File,
+    io::{self, BufWriter, Read, Seek, SeekFrom, Write},
+    path::Path,
+};
+
+use anyhow::{Context, Result, bail};
+use chrono::{DateTime, SecondsFormat};
+use flate2::{Compression, write::GzEncoder};
+use oci_spec::image::MediaType;
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use tar::{Builder, EntryType, Header};
+
+use crate::compressor::LayerCompressor;
+
+use super::{
+    LayerCompressionConfig, LayerCompressionResult,
+    layer_tar::{BlobDigests, HashingWriter, persist_blob, temp_blob_path, with_layer_tar},
+};
+
+pub const ESTARGZ_TOC_DIGEST_ANNOTATION: &str = "containerd.io/snapshot/stargz/toc.digest";
+pub const ESTARGZ_UNCOMPRESSED_SIZE_ANNOTATION: &str = "io.containers.estargz.uncompressed-size";
+pub const ZSTD_CHUNKED_MANIFEST_CHECKSUM_ANNOTATION: &str =
+    "io.github.containers.zstd-chunked.manifest-checksum";
+pub const ZSTD_CHUNKED_MANIFEST_POSITION_ANNOTATION: &str =
+    "io.github.containers.zstd-chunked.manifest-position";
+
+/// Name of the tar entry holding the eStargz TOC.
+pub const ESTARGZ_TOC_NAME: &str = "stargz.index.json";
+
+const ESTARGZ_FOOTER_SIZE: usize = 51;
+
+/// Regular files larger than this are split into independently compressed chunks.
+const CHUNK_SIZE: u64 = 4 << 20;
+const TAR_BLOCK_SIZE: usize = 512;
+const ZSTD_SKIPPABLE_FRAME_MAGIC: u32 = 0x184D_2A50;
+const ZSTD_CHUNKED_FOOTER_MAGIC: &[u8; 8] = b"GNUlInUx";
+const ZSTD_CHUNKED_MANIFEST_TYPE_CRFS: u64 = 1;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum SeekableFormat {
+    Estargz,
+    ZstdChunked,
+}
+
+impl SeekableFormat {
+    fn extension(self) -> &'static str {
+        match self {
+            Self::Estargz => "tar.gz",
+            Self::ZstdChunked => "tar.zst",
+        }
+    }
+
+    fn media_type(self) -> MediaType {
+        match self {
+            Self::Estargz => MediaType::ImageLayerGzip,
+            Self::ZstdChunked => MediaType::ImageLayerZstd,
+        }
+    }
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+struct Toc {
+    version: u32,
+    entries: Vec<TocEntry>,
+}
+
+/// A TOC entry, shared by eStargz and zstd:chunked.
+#[derive(Debug, Default, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct TocEntry {
+    name: String,
+    #[serde(rename = "type")]
+    kind: String,
+    #[serde(default, skip_serializing_if = "is_zero")]
+    size: u64,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    modtime: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    link_name: Option<String>,
+    #[serde(default)]
+    mode: u32,
+    #[serde(default)]
+    uid: u64,
+    #[serde(default)]
+    gid: u64,
+    #[serde(default, skip_serializing_if = "is_zero")]
+    dev_major: u64,
+    #[serde(default, skip_serializing_if = "is_zero")]
+    dev_minor: u64,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    digest: Option<String>,
+    /// Compressed offset of the frame holding this entry (or chunk).
+    #[serde(default, skip_serializing_if = "is_zero")]
+    offset: u64,
+    /// Compressed offset right after that frame.
+    #[serde(default, skip_serializing_if = "is_zero")]
+    end_offset: u64,
+    #[serde(default, skip_serializing_if = "is_zero")]
+    chunk_offset: u64,
+    #[serde(default, skip_serializing_if = "is_zero")]
+    chunk_size: u64,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    chunk_digest: Option<String>,
+}
+
+/// A compressed frame holding part of a regular file.
+struct Chunk {
+    offset: u64,
+    end_offset: u64,
+    chunk_offset: u64,
+    chunk_size: u64,
+    digest: String,
+}
+
+fn is_zero(value: &u64) -> bool {
+    *value == 0
+}
+
+impl TocEntry {
+    /// Build the TOC entry for a tar header, or `None` for entries that carry no file
+    /// (e.g. global PAX headers).
+    fn from_header(header: &Header, path: &Path, link_name: Option<&Path>) -> Result<Option<Self>> {
+        let kind = match header.entry_type() {
+            EntryType::Regular | EntryType::Continuous => "reg",
+            EntryType::Directory => "dir",
+            EntryType::Symlink => "symlink",
+            EntryType::Link => "hardlink",
+            EntryType::Char => "char",
+            EntryType::Block => "block",
+            EntryType::Fifo => "fifo",
+            _ => return Ok(None),
+        };
+        let name = path.to_string_lossy();
+        let name = name.trim_start_matches("./").trim_start_matches('/');
+
+        Ok(Some(Self {
+            name: name.to_string(),
+            kind: kind.to_string(),
+            size: if kind == "reg" { header.size()? } else { 0 },
+            modtime: DateTime::from_timestamp(header.mtime()? as i64, 0)
+                .map(|time| time.to_rfc3339_opts(SecondsFormat::Secs, true)),
+            link_name: link_name.map(|link| link.to_string_lossy().into_owned()),
+            mode: header.mode()?,
+            uid: header.uid()?,
+            gid: header.gid()?,
+            dev_major: header.device_major()?.unwrap_or_default() as u64,
+            dev_minor: header.device_minor()?.unwrap_or_default() as u64,
+            ..Default::default()
+        }))
+    }
+}
+
+/// One independently compressed frame: a gzip member or a zstd frame.
+enum FrameEncoder<W: Write> {
+    Gzip(GzEncoder<W>),
+    Zstd(zstd::Encoder<'static, W>),
+}
+
+impl<W: Write> FrameEncoder<W> {
+    fn new(format: SeekableFormat, writer: W) -> io::Result<Self> {
+        Ok(match format {
+            SeekableFormat::Estargz => Self::Gzip(GzEncoder::new(writer, Compression::fast())),
+            SeekableFormat::ZstdChunked => {
+                Self::Zstd(zstd::Encoder::new(writer, zstd::DEFAULT_COMPRESSION_LEVEL)?)
+            }
+        })
+    }
+
+    fn finish(self) -> io::Result<W> {
+        match self {
+            Self::Gzip(encoder) => encoder.finish(),
+            Self::Zstd(encoder) => encoder.finish(),
+        }
+    }
+}
+
+impl<W: Write> Write for FrameEncoder<W> {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        match self {
+            Self::Gzip(encoder) => encoder.write(buf),
+            Self::Zstd(encoder) => encoder.write(buf),
+        }
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        match self {
+            Self::Gzip(encoder) => encoder.flush(),
+            Self::Zstd(encoder) => encoder.flush(),
+        }
+    }
+}
+
+/// Writes the uncompressed tar as a sequence of frames and tracks where each starts.
+///
+/// Exactly one of `blob` and `frame` is set: the blob writer is moved into the frame
+/// encoder while a frame is open and handed back when it is finished.
+struct FrameWriter<W: Write> {
+    format: SeekableFormat,
+    blob: Option<HashingWriter<W>>,
+    frame: Option<FrameEncoder<HashingWriter<W>>>,
+    tar_hasher: Sha256,
+    tar_size: u64,
+}
+
+impl<W: Write> FrameWriter<W> {
+    fn new(format: SeekableFormat, writer: W) -> Self {
+        Self {
+            format,
+            blob: Some(HashingWriter::new(writer)),
+            frame: None,
+            tar_hasher: Sha256::new(),
+            tar_size: 0,
+        }
+    }
+
+    fn blob_mut(&mut self) -> &mut HashingWriter<W> {
+        self.blob
+            .as_mut()
+            .expect("blob writer is available between frames")
+    }
+
+    /// Close the open frame (if any) and start a new one at the returned offset.
+    fn begin_frame(&mut self) -> Result<u64> {
+        let offset = self.end_frame()?;
+        let blob = self
+            .blob
+            .take()
+            .expect("blob writer is available between frames");
+        self.frame = Some(FrameEncoder::new(self.format, blob)?);
+        Ok(offset)
+    }
+
+    /// Close the open frame (if any) and return the offset right after it.
+    fn end_frame(&mut self) -> Result<u64> {
+        if let Some(frame) = self.frame.take() {
+            self.blob = Some(frame.finish()?);
+        }
+        Ok(self.blob_mut().size())
+    }
+
+    /// Write uncompressed tar bytes into the open frame.
+    fn write_tar(&mut self, data: &[u8]) -> Result<()> {
+        self.tar_hasher.update(data);
+        self.tar_size += data.len() as u64;
+        self.frame
+            .as_mut()
+            .context("No compressed frame is open")?
+            .write_all(data)?;
+        Ok(())
+    }
+
+    /// Append a zstd skippable frame and return the offset of its payload.
+    fn write_skippable_frame(&mut self, data: &[u8]) -> Result<u64> {
+        let len = u32::try_from(data.len()).context("Skippable frame is too large")?;
+        self.end_frame()?;
+        let blob = self.blob_mut();
+        blob.write_all(&ZSTD_SKIPPABLE_FRAME_MAGIC.to_le_bytes())?;
+        blob.write_all(&len.to_le_bytes())?;
+        let offset = blob.size();
+        blob.write_all(data)?;
+        Ok(offset)
+    }
+
+    /// Append raw bytes after the last frame.
+    fn write_raw(&mut self, data: &[u8]) -> Result<()> {
+        self.end_frame()?;
+        self.blob_mut().write_all(data)?;
+        Ok(())
+    }
+
+    fn finish(mut self) -> Result<(W, BlobDigests)> {
+        self.end_frame()?;
+        let blob = self
+            .blob
+            .take()
+            .expect("blob writer is available between frames");
+        let (writer, blob_sha256sum, blob_size) = blob.finalize();
+        Ok((
+            writer,
+            BlobDigests {
+                tar_sha256sum: format!("{:x}", self.tar_hasher.finalize()),
+                tar_size: self.tar_size,
+                blob_sha256sum,
+                blob_size,
+            },
+        ))
+    }
+}
+
+/// Compresses layers into eStargz or zstd:chunked blobs.
+#[derive(Debug)]
+pub struct SeekableCompressor {
+    format: SeekableFormat,
+}
+
+impl SeekableCompressor {
+    pub fn new(format: SeekableFormat) -> Self {
+        Self { format }
+    }
+
+    fn compress(&self, tar: &mut dyn Read, output_dir: &Path) -> Result<LayerCompressionResult> {
+        let blob_path = temp_blob_path(output_dir, self.format.extension());
+        let blob_file = File::create(&blob_path)
+            .with_context(|| format!("Failed to create {}", blob_path.display()))?;
+        let mut writer = FrameWriter::new(
+            self.format,
+            BufWriter::with_capacity(1024 * 1024, blob_file),
+        );
+
+        let entries = write_entries(tar, &mut writer)?;
+        let toc = serde_json::to_vec(&Toc {
+            version: 1,
+            entries,
+        })?;
+        let mut annotations = match self.format {
+            SeekableFormat::Estargz => write_estargz_toc(&mut writer, &toc)?,
+            SeekableFormat::ZstdChunked => write_zstd_chunked_manifest(&mut writer, &toc)?,
+        };
+
+        let (mut blob_writer, digests) = writer.finish()?;
+        blob_writer.flush()?;
+        persist_blob(&blob_path, output_dir, &digests.blob_sha256sum)?;
+
+        if self.format == SeekableFormat::Estargz {
+            annotations.insert(
+                ESTARGZ_UNCOMPRESSED_SIZE_ANNOTATION.to_string(),
+                digests.tar_size.to_string(),
+            );
+        }
+        Ok(LayerCompressionResult::new(
+            digests.tar_sha256sum,
+            digests.tar_size,
+            digests.blob_sha256sum,
+            digests.blob_size,
+            self.format.media_type(),
+        )
+        .annotations(annotations))
+    }
+}
+
+impl LayerCompressor for SeekableCompressor {
+    fn compress_layer(
+        &self,
+        compression_config: &LayerCompressionConfig,
+    ) -> Result<LayerCompressionResult> {
+        let source_dir = &compression_config.layer_dir;
+        tracing::info!(
+            "Compressing layer {} as {:?}",
+            source_dir.display(),
+            self.format
+        );
+        with_layer_tar(source_dir, |tar| {
+            self.compress(tar, &compression_config.output_dir)
+        })
+    }
+
+    fn compress_tar(
+        &self,
+        tar: &mut dyn Read,
+        output_dir: &Path,
+    ) -> Result<LayerCompressionResult> {
+        self.compress(tar, output_dir)
+    }
+}
+
+/// Re-encode `header` for `path`, emitting GNU long name records when needed.
+fn encode_header(header: &Header, path: &Path, link_name: Option<&Path>) -> Result<Vec<u8>> {
+    let mut header = header.clone();
+    let mut builder = Builder::new(Vec::new());
+    match link_name {
+        Some(link_name) => builder.append_link(&mut header, path, link_name)?,
+        None => builder.append_data(&mut header, path, io::empty())?,
+    }
+    Ok(std::mem::take(builder.get_mut()))
+}
+
+fn tar_padding(size: u64) -> &'static [u8] {
+    const ZEROS: [u8; TAR_BLOCK_SIZE] = [0; TAR_BLOCK_SIZE];
+    let remainder = (size % TAR_BLOCK_SIZE as u64) as usize;
+    if remainder == 0 {
+        &[]
+    } else {
+        &ZEROS[remainder..]
+    }
+}
+
+/// Copy `len` bytes of entry data into the open frame, returning the chunk digest.
+fn copy_chunk<R: Read, W: Write>(
+    reader: &mut R,
+    len: u64,
+    writer: &mut FrameWriter<W>,
+    file_hasher: &mut Sha256,
+) -> Result<String> {
+    let mut chunk_hasher = Sha256::new();
+    let mut reader = reader.take(len);
+    let mut buf = vec![0; 256 * 1024];
+    let mut copied = 0;
+    loop {
+        let n = reader.read(&mut buf)?;
+        if n == 0 {
+            break;
+        }
+        chunk_hasher.update(&buf[..n]);
+        file_hasher.update(&buf[..n]);
+        writer.write_tar(&buf[..n])?;
+        copied += n as u64;
+    }
+    if copied != len {
+        bail!("Layer tar entry is truncated: expected {len} bytes, got {copied}");
+    }
+    Ok(format!("sha256:{:x}", chunk_hasher.finalize()))
+}
+
+/// Write every tar entry into its own frame(s) and collect the TOC.
+fn write_entries<W: Write>(
+    tar: &mut dyn Read,
+    writer: &mut FrameWriter<W>,
+) -> Result<Vec<TocEntry>> {
+    let mut toc = Vec::new();
+    let mut archive = tar::Archive::new(tar);
+    for entry in archive.entries().context("Failed to read layer tar")? {
+        let mut entry = entry.context("Failed to read layer tar entry")?;
+        let path = entry.path()?.into_owned();
+        let link_name = entry.link_name()?.map(Cow::into_owned);
+        let header = entry.header().clone();
+        let size = header.size()?;
+
+        let offset = writer.begin_frame()?;
+        writer.write_tar(&encode_header(&header, &path, link_name.as_deref())?)?;
+        let toc_entry = TocEntry::from_header(&header, &path, link_name.as_deref())?;
+
+        let mut file_hasher = Sha256::new();
+        let mut chunks = Vec::new();
+        let mut chunk_offset = 0;
+        while chunk_offset < size {
+            let chunk_size = CHUNK_SIZE.min(size - chunk_offset);
+            let frame_offset = if chunk_offset == 0 {
+                offset
+            } else {
+                writer.begin_frame()?
+            };
+            let digest = copy_chunk(&mut entry, chunk_size, writer, &mut file_hasher)?;
+            if chunk_offset + chunk_size == size {
+                writer.write_tar(tar_padding(size))?;
+            }
+            chunks.push(Chunk {
+                offset: frame_offset,
+                end_offset: writer.end_frame()?,
+                chunk_offset,
+                chunk_size,
+                digest,
+            });
+            chunk_offset += chunk_size;
+        }
+        let end_offset = writer.end_frame()?;
+
+        let Some(mut toc_entry) = toc_entry else {
+            continue;
+        };
+        if toc_entry.kind != "reg" {
+            toc.push(toc_entry);
+            continue;
+        }
+
+        // The first chunk shares the frame of the tar header and is described by the
+        // `reg` entry itself; the remaining chunks get `chunk` entries.
+        let file_digest = format!("sha256:{:x}", file_hasher.finalize());
+        let chunked = chunks.len() > 1;
+        let mut chunks = chunks.into_iter();
+        let first = chunks.next();
+        toc_entry.offset = offset;
+        toc_entry.end_offset = first.as_ref().map_or(end_offset, |c| c.end_offset);
+        toc_entry.chunk_size = if chunked { CHUNK_SIZE } else { 0 };
+        toc_entry.chunk_digest = Some(first.map_or_else(|| file_digest.clone(), |c| c.digest));
+        toc_entry.digest = Some(file_digest);
+        let name = toc_entry.name.clone();
+        toc.push(toc_entry);
+
+        toc.extend(chunks.map(|chunk| TocEntry {
+            name: name.clone(),
+            kind: "chunk".to_string(),
+            offset: chunk.offset,
+            end_offset: chunk.end_offset,
+            chunk_offset: chunk.chunk_offset,
+            chunk_size: chunk.chunk_size,
+            chunk_digest: Some(chunk.digest),
+            ..Default::default()
+        }));
+    }
+    Ok(toc)
+}
+
+/// Append the TOC as the final tar entry and the eStargz footer pointing at it.
+fn write_estargz_toc<W: Write>(
+    writer: &mut FrameWriter<W>,
+    toc: &[u8],
+) -> Result<HashMap<String, String>> {
+    let toc_offset = writer.begin_frame()?;
+    let mut header = Header::new_ustar();
+    header.set_path(ESTARGZ_TOC_NAME)?;
+    header.set_entry_type(EntryType::Regular);
+    header.set_mode(0o644);
+    header.set_uid(0);
+    header.set_gid(0);
+    header.set_mtime(0);
+    header.set_size(toc.len() as u64);
+    header.set_cksum();
+    writer.write_tar(header.as_bytes())?;
+    writer.write_tar(toc)?;
+    writer.write_tar(tar_padding(toc.len() as u64))?;
+    // End-of-archive marker
+    writer.write_tar(&[0; TAR_BLOCK_SIZE * 2])?;
+    writer.write_raw(&estargz_footer(toc_offset))?;
+
+    Ok(HashMap::from([(
+        ESTARGZ_TOC_DIGEST_ANNOTATION.to_string(),
+        format!("sha256:{:x}", Sha256::digest(toc)),
+    )]))
+}
+
+/// The 51-byte eStargz footer: an empty gzip member whose extra field records the
+/// TOC offset.
+fn estargz_footer(toc_offset: u64) -> Vec<u8> {
+    let payload = format!("{toc_offset:016x}STARGZ");
+    let mut footer = vec![0x1f, 0x8b, 0x08, 0x04, 0, 0, 0, 0, 0, 0xff];
+    footer.extend_from_slice(&(4 + payload.len() as u16).to_le_bytes());
+    footer.extend_from_slice(b"SG");
+    footer.extend_from_slice(&(payload.len() as u16).to_le_bytes());
+    footer.extend_from_slice(payload.as_bytes());
+    // A final, empty stored block followed by CRC32 and ISIZE of the empty body.
+    footer.extend_from_slice(&[0x01, 0x00, 0x00, 0xff, 0xff]);
+    footer.extend_from_slice(&[0; 8]);
+    footer
+}
+
+/// Read the TOC offset from the footer of an eStargz blob.
+///
+/// Everything before that offset is the layer content without the TOC entry.
+pub fn read_estargz_toc_offset(blob: &mut (impl Read + Seek)) -> Result<u64> {
+    let mut footer = [0; ESTARGZ_FOOTER_SIZE];
+    blob.seek(SeekFrom::End(-(ESTARGZ_FOOTER_SIZE as i64)))?;
+    blob.read_exact(&mut footer)
+        .context("Failed to read eStargz footer")?;
+    if &footer[32..38] != b"STARGZ" {
+        bail!("Blob has no eStargz footer");
+    }
+    let offset = std::str::from_utf8(&footer[16..32])?;
+    u64::from_str_radix(offset, 16).context("Invalid TOC offset in eStargz footer")
+}
+
+/// Append the tar end-of-archive marker, the compressed TOC (manifest) in a skippable
+/// frame and the zstd:chunked footer.
+fn write_zstd_chunked_manifest<W: Write>(
+    writer: &mut FrameWriter<W>,
+    toc: &[u8],
+) -> Result<HashMap<String, String>> {
+    writer.begin_frame()?;
+    writer.write_tar(&[0; TAR_BLOCK_SIZE * 2])?;
+
+    let manifest = zstd::encode_all(toc, zstd::DEFAULT_COMPRESSION_LEVEL)?;
+    let manifest_offset = writer.write_skippable_frame(&manifest)?;
+    let manifest_size = manifest.len() as u64;
+    let toc_size = toc.len() as u64;
+
+    let mut footer = Vec::with_capacity(40);
+    footer.extend_from_slice(&manifest_offset.to_le_bytes());
+    footer.extend_from_slice(&manifest_size.to_le_bytes());
+    footer.extend_from_slice(&toc_size.to_le_bytes());
+    footer.extend_from_slice(&ZSTD_CHUNKED_MANIFEST_TYPE_CRFS.to_le_bytes());
+    footer.extend_from_slice(ZSTD_CHUNKED_FOOTER_MAGIC);
+    writer.write_skippable_frame(&footer)?;
+
+    Ok(HashMap::from([
+        (
+            ZSTD_CHUNKED_MANIFEST_CHECKSUM_ANNOTATION.to_string(),
+            format!("sha256:{:x}", Sha256::digest(&manifest)),
+        ),
+        (
+            ZSTD_CHUNKED_MANIFEST_POSITION_ANNOTATION.to_string(),
+            format!(
+                "{manifest_offset}:{manifest_size}:{toc_size}:{ZSTD_CHUNKED_MANIFEST_TYPE_CRFS}"
+            ),
+        ),
+    ]))
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{fs, io::Read, path::Path};
+
+    use flate2::read::{GzDecoder, MultiGzDecoder};
+    use sha2::{Digest, Sha256};
+    use tempfile::tempdir;
+
+    use super::*;
+
+    fn write_layer(dir: &Path) -> Vec<u8> {
+        fs::create_dir_all(dir.join("nested")).unwrap();
+        fs::write(dir.join("root.txt"), "root-file").unwrap();
+        let big = (0..CHUNK_SIZE + 4096)
+            .map(|i| (i % 251) as u8)
+            .collect::<Vec<_>>();
+        fs::write(dir.join("nested/big.bin"), &big).unwrap();
+        std::os::unix::fs::symlink("root.txt", dir.join("link")).unwrap();
+        big
+    }
+
+    fn compress(format: SeekableFormat) -> (LayerCompressionResult, Vec<u8>, Vec<u8>) {
+        let layer_tmp = tempdir().unwrap();
+        let big = write_layer(layer_tmp.path());
+        let output_tmp = tempdir().unwrap();
+        let config = LayerCompressionConfig::new(
+            layer_tmp.path().to_path_buf(),
+            output_tmp.path().to_path_buf(),
+        );
+        let result = SeekableCompressor::new(format)
+            .compress_layer(&config)
+            .unwrap();
+        let blob = fs::read(output_tmp.path().join(&result.blob_sha256sum)).unwrap();
+        assert_eq!(blob.len() as u64, result.blob_size);
+        assert_eq!(
+            format!("{:x}", Sha256::digest(&blob)),
+            result.blob_sha256sum
+        );
+        (result, blob, big)
+    }
+
+    fn assert_tar(tar: &[u8], result: &LayerCompressionResult, big: &[u8]) {
+        assert_eq!(tar.len() as u64, result.tar_size);
+        assert_eq!(format!("{:x}", Sha256::digest(tar)), result.tar_sha256sum);
+        let mut archive = tar::Archive::new(tar);
+        for entry in archive.entries().unwrap() {
+            let mut entry = entry.unwrap();
+            if entry.path().unwrap() == Path::new("nested/big.bin") {
+                let mut data = Vec::new();
+                entry.read_to_end(&mut data).unwrap();
+                assert_eq!(data, big);
+                return;
+            }
+        }
+        panic!("nested/big.bin missing from layer tar");
+    }
+
+    fn assert_chunks(toc: &Toc, blob: &[u8], format: SeekableFormat, big: &[u8]) {
+        let chunks = toc
+            .entries
+            .iter()
+            .filter(|e| e.name == "nested/big.bin")
+            .collect::<Vec<_>>();
+        assert_eq!(chunks.len(), 2);
+        assert_eq!(chunks[0].kind, "reg");
+        assert_eq!(chunks[0].size, big.len() as u64);
+        assert_eq!(chunks[0].chunk_size, CHUNK_SIZE);
+        assert_eq!(
+            chunks[0].digest.as_deref(),
+            Some(format!("sha256:{:x}", Sha256::digest(big)).as_str())
+        );
+        assert_eq!(chunks[1].kind, "chunk");
+        assert_eq!(chunks[1].chunk_offset, CHUNK_SIZE);
+
+        // The second chunk can be decompressed on its own.
+        let frame = &blob[chunks[1].offset as usize..chunks[1].end_offset as usize];
+        let mut data = Vec::new();
+        match format {
+            SeekableFormat::Estargz => {
+                GzDecoder::new(frame).read_to_end(&mut data).unwrap();
+            }
+            SeekableFormat::ZstdChunked => {
+                zstd::Decoder::new(frame)
+                    .unwrap()
+                    .read_to_end(&mut data)
+                    .unwrap();
+            }
+        }
+        assert_eq!(&data[..4096], &big[CHUNK_SIZE as usize..]);
+        assert_eq!(
+            chunks[1].chunk_digest.as_deref(),
+            Some(format!("sha256:{:x}", Sha256::digest(&big[CHUNK_SIZE as usize..])).as_str())
+        );
+        assert!(
+            toc.entries
+                .iter()
+                .any(|e| e.name == "link" && e.kind == "symlink")
+        );
+    }
+
+    #[test]
+    fn test_estargz_layout() {
+        let (result, blob, big) = compress(SeekableFormat::Estargz);
+        assert_eq!(result.media_type, MediaType::ImageLayerGzip);
+
+        let mut tar = Vec::new();
+        MultiGzDecoder::new(blob.as_slice())
+            .read_to_end(&mut tar)
+            .unwrap();
+        assert_tar(&tar, &result, &big);
+        assert_eq!(
+            result.annotations[ESTARGZ_UNCOMPRESSED_SIZE_ANNOTATION],
+            result.tar_size.to_string()
+        );
+
+        // The footer is an empty gzip member of its own.
+        let mut footer = Vec::new();
+        GzDecoder::new(&blob[blob.len() - ESTARGZ_FOOTER_SIZE..])
+            .read_to_end(&mut footer)
+            .unwrap();
+        assert!(footer.is_empty());
+        let toc_offset = read_estargz_toc_offset(&mut io::Cursor::new(&blob)).unwrap();
+        let mut toc_tar = Vec::new();
+        GzDecoder::new(&blob[toc_offset as usize..])
+            .read_to_end(&mut toc_tar)
+            .unwrap();
+        let mut archive = tar::Archive::new(toc_tar.as_slice());
+        let mut toc_entry = archive.entries().unwrap().next().unwrap().unwrap();
+        assert_eq!(toc_entry.path().unwrap(), Path::new(ESTARGZ_TOC_NAME));
+        let mut toc = Vec::new();
+        toc_entry.read_to_end(&mut toc).unwrap();
+        assert_eq!(
+            result.annotations[ESTARGZ_TOC_DIGEST_ANNOTATION],
+            format!("sha256:{:x}", Sha256::digest(&toc))
+        );
+
+        let toc: Toc = serde_json::from_slice(&toc).unwrap();
+        assert_chunks(&toc, &blob, SeekableFormat::Estargz, &big);
+    }
+
+    #[test]
+    fn test_zstd_chunked_layout() {
+        let (result, blob, big) = compress(SeekableFormat::ZstdChunked);
+        assert_eq!(result.media_type, MediaType::ImageLayerZstd);
+
+        let mut tar = Vec::new();
+        zstd::Decoder::new(blob.as_slice())
+            .unwrap()
+            .read_to_end(&mut tar)
+            .unwrap();
+        assert_tar(&tar, &result, &big);
+
+        let position = result.annotations[ZSTD_CHUNKED_MANIFEST_POSITION_ANNOTATION]
+            .split(':')
+            .map(|part| part.parse::<usize>().unwrap())
+            .collect::<Vec<_>>();
+        let manifest = &blob[position[0]..position[0] + position[1]];
+        assert_eq!(
+            result.annotations[ZSTD_CHUNKED_MANIFEST_CHECKSUM_ANNOTATION],
+            format!("sha256:{:x}", Sha256::digest(manifest))
+        );
+        assert!(blob.ends_with(ZSTD_CHUNKED_FOOTER_MAGIC));
+
+        let toc = zstd::decode_all(manifest).unwrap();
+        assert_eq!(toc.len(), position[2]);
+        let toc: Toc = serde_json::from_slice(&toc).unwrap();
+        assert_chunks(&toc, &blob, SeekableFormat::ZstdChunked, &big);
+    }
+}
@@ -0,0 +1,103 @@
+use std::{io::Read, path::Path};
+
+use anyhow::Result;
+use oci_spec::image::MediaType;
+
+use crate::compressor::LayerCompressor;
+
+use super::{
+    LayerCompressionConfig, LayerCompressionResult,
+    layer_tar::{BlobDigests, TarSource, compress_stream},
+};
+
+/// Compresses layers to `tar+zstd`, which is considerably faster to produce and to
+/// unpack than gzip at a similar or better ratio.
+#[derive(Debug, Default)]
+pub struct ZstdCompressor;
+
+impl ZstdCompressor {
+    fn compress(&self, source: TarSource<'_>, output_dir: &Path) -> Result<LayerCompressionResult> {
+        let BlobDigests {
+            tar_sha256sum,
+            tar_size,
+            blob_sha256sum,
+            blob_size,
+        } = compress_stream(source, output_dir, "tar.zst", |writer| {
+            zstd::Encoder::new(writer, zstd::DEFAULT_COMPRESSION_LEVEL)
+        })?;
+
+        Ok(LayerCompressionResult::new(
+            tar_sha256sum,
+            tar_size,
+            blob_sha256sum,
+            blob_size,
+            MediaType::ImageLayerZstd,
+        ))
+    }
+}
+
+impl LayerCompressor for ZstdCompressor {
+    fn compress_layer(
+        &self,
+        compression_config: &LayerCompressionConfig,
+    ) -> Result<LayerCompressionResult> {
+        let source_dir = &compression_config.layer_dir;
+        tracing::info!("Compressing layer {} with zstd", source_dir.display());
+        self.compress(TarSource::Dir(source_dir), &compression_config.output_dir)
+    }
+
+    fn compress_tar(
+        &self,
+        tar: &mut dyn Read,
+        output_dir: &Path,
+    ) -> Result<LayerCompressionResult> {
+        self.compress(TarSource::Stream(tar), output_dir)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{fs, io::Read};
+
+    use oci_spec::image::MediaType;
+    use sha2::{Digest, Sha256};
+    use tempfile::tempdir;
+
+    use crate::compressor::{LayerCompressionConfig, LayerCompressor};
+
+    #[test]
+    fn test_zstd_compression_roundtrip() {
+        let layer_tmp = tempdir().unwrap();
+        fs::create_dir_all(layer_tmp.path().join("nested")).unwrap();
+        fs::write(layer_tmp.path().join("root.txt"), "root-file").unwrap();
+        fs::write(layer_tmp.path().join("nested/child.txt"), "nested-file").unwrap();
+
+        let output_tmp = tempdir().unwrap();
+        let config = LayerCompressionConfig::new(
+            layer_tmp.path().to_path_buf(),
+            output_tmp.path().to_path_buf(),
+        );
+        let result = super::ZstdCompressor.compress_layer(&config).unwrap();
+        assert_eq!(result.media_type, MediaType::ImageLayerZstd);
+
+        let blob = fs::read(output_tmp.path().join(&result.blob_sha256sum)).unwrap();
+        assert_eq!(blob.len() as u64, result.blob_size);
+
+        let mut tar = Vec::new();
+        zstd::Decoder::new(blob.as_slice())
+            .unwrap()
+            .read_to_end(&mut tar)
+            .unwrap();
+        assert_eq!(tar.len() as u64, result.tar_size);
+        assert_eq!(format!("{:x}", Sha256::digest(&tar)), result.tar_sha256sum);
+
+        let mut archive = tar::Archive::new(tar.as_slice());
+        let mut names = archive
+            .entries()
+            .unwrap()
+            .map(|entry| entry.unwrap().path().unwrap().display().to_string())
+            .collect::<Vec<_>>();
+        names.sort();
+        assert_eq!(names, ["nested/", "nested/child.txt", "root.txt"]);
+    }
+}
@@ -164,6 +164,8 @@ fn ensure_storage_root_writable(root_dir: &Path) -> Result<()> {
 pub struct Config {
     pub layers_store_root: PathBuf,
     pub build_dir: PathBuf,
+    /// Persistent directories backing `RUN --mount=type=cache`.
+    pub cache_dir: PathBuf,
     pub metadata_dir: PathBuf,
     pub default_registry: String,
     pub is_root: bool,
@@ -186,6 +188,7 @@ impl Config {
         }
         let layers_store_root = root_dir.join("layers");
         let build_dir = root_dir.join("build");
+        let cache_dir = root_dir.join("cache");
         let metadata_dir = root_dir.join("metadata");
 
         fs::create_dir_all(&layers_store_root).with_context(|| {
@@ -203,6 +206,7 @@ impl Config {
         Ok(Self {
             layers_store_root,
             build_dir,
+            cache_dir,
             metadata_dir,
             default_registry: String::from(REGISTRY),
             is_root,
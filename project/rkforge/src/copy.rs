@@ -9,6 +9,9 @@ pub struct CopyArgs {
     pub src: Vec<String>,
     #[arg(long)]
     pub dest: String,
+    /// Local archives to unpack into `dest` (ADD auto-extraction).
+    #[arg(long)]
+    pub extract: Vec<String>,
 }
 
 pub fn copy(args: CopyArgs) -> Result<()> {
@@ -31,5 +34,23 @@ pub fn copy(args: CopyArgs) -> Result<()> {
             );
         }
     }
+    for archive in args.extract {
+        let archive_path = Path::new(&archive);
+        std::fs::create_dir_all(dest_path)?;
+        // `tar` detects gzip, bzip2 and xz compression on its own.
+        let status = Command::new("tar")
+            .arg("-xf")
+            .arg(archive_path)
+            .arg("-C")
+            .arg(dest_path)
+            .status()?;
+        if !status.success() {
+            bail!(
+                "Failed to extract {} to {}",
+                archive_path.display(),
+                dest_path.display()
+            );
+        }
+    }
     Ok(())
 }
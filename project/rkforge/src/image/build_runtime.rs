@@ -97,6 +97,62 @@ impl BuildNetworkMode {
     }
 }
 
+/// A `RUN --mount` resolved on the host, ready to be set up inside the build container.
+///
+/// Targets are absolute paths inside the container rootfs. None of these mounts
+/// are committed into the layer produced by the RUN step.
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq)]
+pub enum RunMount {
+    /// Host directories (context path, or the layers of a stage/image) bound at `target`.
+    Bind {
+        target: String,
+        /// Layer directories, bottom-most first. A single entry is bound directly.
+        layers: Vec<PathBuf>,
+        /// Sub-path of the merged layers to expose.
+        source: PathBuf,
+        /// Writes are allowed but discarded when the RUN step finishes.
+        readwrite: bool,
+    },
+    /// A persistent cache directory that survives across builds.
+    Cache {
+        target: String,
+        dir: PathBuf,
+        readonly: bool,
+    },
+    Tmpfs {
+        target: String,
+        size: Option<u64>,
+    },
+    /// A build secret exposed as a file at `target` and/or as the `env` variable.
+    Secret {
+        id: String,
+        src: PathBuf,
+        target: Option<String>,
+        env: Option<String>,
+        mode: u32,
+        uid: u32,
+        gid: u32,
+    },
+    /// An SSH agent socket bound at `target`.
+    Ssh {
+        id: String,
+        socket_path: PathBuf,
+        target: String,
+    },
+}
+
+impl RunMount {
+    pub fn target(&self) -> Option<&str> {
+        match self {
+            Self::Bind { target, .. }
+            | Self::Cache { target, .. }
+            | Self::Tmpfs { target, .. }
+            | Self::Ssh { target, .. } => Some(target),
+            Self::Secret { target, .. } => target.as_deref(),
+        }
+    }
+}
+
 pub fn normalize_cgroup_parent(raw: &str) -> Result<PathBuf> {
     let raw = raw.trim();
     if raw.is_empty() {
@@ -1,5 +1,6 @@
 use anyhow::{Context, Result};
 use oci_spec::image::{Config, ConfigBuilder};
+use serde::{Deserialize, Serialize};
 use std::collections::HashMap;
 use std::path::{Component, Path, PathBuf};
 
@@ -46,6 +47,31 @@ pub(crate) fn normalize_path(path: &str) -> String {
     }
 }
 
+/// Container health check recorded by the `HEALTHCHECK` instruction.
+///
+/// Serialized in the Docker image config layout (`config.Healthcheck`), durations
+/// are in nanoseconds and zero values mean "inherit the runtime default".
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "PascalCase")]
+pub struct HealthConfig {
+    /// `["NONE"]`, `["CMD", args...]` or `["CMD-SHELL", command]`.
+    pub test: Vec<String>,
+    #[serde(default, skip_serializing_if = "is_zero")]
+    pub interval: i64,
+    #[serde(default, skip_serializing_if = "is_zero")]
+    pub timeout: i64,
+    #[serde(default, skip_serializing_if = "is_zero")]
+    pub start_period: i64,
+    #[serde(default, skip_serializing_if = "is_zero")]
+    pub start_interval: i64,
+    #[serde(default, skip_serializing_if = "is_zero")]
+    pub retries: i64,
+}
+
+fn is_zero(value: &i64) -> bool {
+    *value == 0
+}
+
 /// Image config is used in OCI image's `config.json`.
 ///
 /// Currently not exhaustive, only some simple fields.
@@ -63,6 +89,8 @@ pub struct ImageConfig {
     pub stop_signal: Option<String>,
     pub exposed_ports: Option<Vec<String>>,
     pub shell: Option<Vec<String>>,
+    pub healthcheck: Option<HealthConfig>,
+    pub on_build: Option<Vec<String>>,
 }
 
 impl ImageConfig {
@@ -172,6 +200,15 @@ impl ImageConfig {
             .unwrap_or_else(|| vec!["/bin/sh".to_string(), "-c".to_string()])
     }
 
+    pub fn set_healthcheck(&mut self, healthcheck: HealthConfig) {
+        self.healthcheck = Some(healthcheck);
+    }
+
+    /// Record an `ONBUILD` trigger, executed when this image is used as a base.
+    pub fn add_on_build(&mut self, trigger: String) {
+        self.on_build.get_or_insert_with(Vec::new).push(trigger);
+    }
+
     pub fn get_oci_image_config(&self) -> Result<Config> {
         let mut config = ConfigBuilder::default();
 
@@ -215,7 +252,8 @@ impl ImageConfig {
             config = config.exposed_ports(exposed_ports.clone());
         }
 
-        // Note: SHELL is not part of OCI image config spec, it only affects build-time behavior
+        // Note: SHELL is not part of OCI image config spec, it only affects build-time behavior.
+        // HEALTHCHECK and ONBUILD are Docker extensions, see `OciImageConfig::healthcheck`.
 
         config.build().context("Failed to build OCI image config")
     }
@@ -237,6 +275,8 @@ impl Default for ImageConfig {
             stop_signal: None,
             exposed_ports: None,
             shell: None,
+            healthcheck: None,
+            on_build: None,
         }
     }
 }
@@ -1,4 +1,7 @@
-use std::{collections::HashMap, path::Path};
+use std::{
+    collections::HashMap,
+    path::{Path, PathBuf},
+};
 
 use crate::{
     image::{
@@ -7,6 +10,8 @@ use crate::{
             BuildHostEntry, BuildNetworkMode, BuildSecret, BuildSshAgent, BuildUlimit,
         },
         config::ImageConfig,
+        heredoc::HeredocMap,
+        platform::Platform,
     },
     overlayfs::MountConfig,
 };
@@ -22,6 +27,8 @@ pub struct StageContext<'ctx, P: AsRef<Path>> {
     pub mount_config: &'ctx mut MountConfig,
     pub image_config: &'ctx mut ImageConfig,
     pub image_aliases: &'ctx mut HashMap<String, String>,
+    /// Layer directories of finished stages, keyed by stage name and index.
+    pub stage_rootfs: &'ctx HashMap<String, Vec<PathBuf>>,
     pub args: HashMap<String, Option<String>>,
     pub cli_build_args: &'ctx HashMap<String, String>,
     pub global_args: &'ctx HashMap<String, Option<String>>,
@@ -36,4 +43,8 @@ pub struct StageContext<'ctx, P: AsRef<Path>> {
     pub cgroup_parent: Option<String>,
     pub secrets: &'ctx [BuildSecret],
     pub ssh: &'ctx [BuildSshAgent],
+    pub heredocs: &'ctx HeredocMap,
+    /// Platform of the stage: the build's target platform unless `FROM --platform`
+    /// overrides it.
+    pub platform: Platform,
 }
@@ -1,20 +1,41 @@
-use anyhow::{Result, bail};
+use anyhow::{Context as _, Result, bail};
 use dockerfile_parser::{
     ArgInstruction, BreakableString, BreakableStringComponent, CmdInstruction, CopyInstruction,
-    EntrypointInstruction, EnvInstruction, FromInstruction, Instruction, LabelInstruction,
-    RunInstruction, ShellOrExecExpr,
+    Dockerfile, EntrypointInstruction, EnvInstruction, FromInstruction, Instruction,
+    LabelInstruction, MiscInstruction, RunInstruction, ShellOrExecExpr,
 };
+use flate2::read::GzDecoder;
+use oci_client::manifest::OciManifest;
+use once_cell::sync::Lazy;
 use serde_json;
+use sha2::{Digest, Sha256};
 use std::collections::HashMap;
+use std::fs;
+use std::io::Read;
 use std::path::{Path, PathBuf};
+use tempfile::TempDir;
 
 use crate::{
-    image::{config::normalize_path, context::StageContext as Context},
-    pull::sync_pull_or_get_image_with_policy_and_output,
-    storage::full_image_ref,
+    image::{
+        build_runtime::RunMount,
+        config::{HealthConfig, normalize_path},
+        context::StageContext as Context,
+        heredoc::{Heredoc, HeredocMap, heredoc_run_script, parse_heredoc_marker},
+        run_mount::{resolve_run_mounts, split_run_flags},
+    },
+    pull::sync_pull_or_get_image_for_platform,
+    rt::block_on,
+    storage::{full_image_ref, ultimate_blob_path},
     task::{CopyTask, RunTask, TaskExec},
 };
 
+/// Heredoc table used while replaying `ONBUILD` triggers, whose spans do not
+/// refer to the Dockerfile being built.
+static NO_HEREDOCS: Lazy<HeredocMap> = Lazy::new(HeredocMap::new);
+
+/// Instructions that must not be used as `ONBUILD` triggers.
+const FORBIDDEN_ONBUILD_TRIGGERS: [&str; 3] = ["ONBUILD", "FROM", "MAINTAINER"];
+
 /// Extract the argument string from a BreakableString (used for Misc instructions like WORKDIR, USER).
 fn extract_misc_argument(args: &BreakableString) -> String {
     let mut result = String::new();
@@ -115,6 +136,364 @@ fn expand_env_value(value: &str, envp: &HashMap<String, String>) -> String {
     out
 }
 
+/// Variables visible to build-time expansion: build args, overridden by ENV.
+fn expansion_scope<P: AsRef<Path>>(ctx: &Context<P>) -> HashMap<String, String> {
+    let mut scope = HashMap::new();
+    for (key, value) in &ctx.args {
+        if let Some(value) = value {
+            scope.insert(key.clone(), value.clone());
+        }
+    }
+    scope.extend(ctx.image_config.envp.clone());
+    scope
+}
+
+/// Variables visible to `FROM`: global build args, overridden by `--build-arg`.
+fn global_scope<P: AsRef<Path>>(ctx: &Context<P>) -> HashMap<String, String> {
+    ctx.global_args
+        .iter()
+        .filter_map(|(key, value)| {
+            let value = ctx.cli_build_args.get(key).or(value.as_ref())?;
+            Some((key.clone(), value.clone()))
+        })
+        .collect()
+}
+
+/// Resolve a destination path inside the container rootfs being built.
+///
+/// Relative paths are resolved against WORKDIR; `..` segments are normalized so
+/// the result never escapes the mountpoint.
+fn resolve_destination<P: AsRef<Path>>(ctx: &Context<P>, dest: &str) -> PathBuf {
+    let abs_dest = if dest.starts_with('/') {
+        dest.to_string()
+    } else {
+        let working_dir = ctx.image_config.get_working_dir();
+        if working_dir == "/" {
+            format!("/{}", dest)
+        } else {
+            format!("{}/{}", working_dir, dest)
+        }
+    };
+    let normalized = normalize_path(&abs_dest);
+    ctx.mount_config
+        .mountpoint
+        .join(normalized.trim_start_matches('/'))
+}
+
+/// Write heredoc sources (`COPY <<EOF /dest`) to files under `dir`.
+///
+/// Returns `None` if `source` is not a heredoc marker.
+fn materialize_heredoc_source(
+    source: &str,
+    heredocs: &[Heredoc],
+    scope: &HashMap<String, String>,
+    dir: &Path,
+) -> Result<Option<PathBuf>> {
+    let Some(marker) = parse_heredoc_marker(source) else {
+        return Ok(None);
+    };
+    let heredoc = heredocs
+        .iter()
+        .find(|heredoc| heredoc.name == marker.name)
+        .with_context(|| format!("heredoc `{}` has no body", marker.name))?;
+    let content = if heredoc.expand {
+        expand_env_value(&heredoc.content(), scope)
+    } else {
+        heredoc.content()
+    };
+    let path = dir.join(&heredoc.name);
+    fs::write(&path, content)
+        .with_context(|| format!("Failed to write heredoc `{}`", heredoc.name))?;
+    Ok(Some(path))
+}
+
+/// Parse a Go-style duration (`30s`, `1m30s`, `500ms`) into nanoseconds.
+fn parse_duration_nanos(raw: &str) -> Result<i64> {
+    let raw = raw.trim();
+    if raw.is_empty() {
+        bail!("invalid duration: empty input");
+    }
+    if raw == "0" {
+        return Ok(0);
+    }
+
+    let mut total = 0f64;
+    let mut rest = raw;
+    while !rest.is_empty() {
+        let number_len = rest
+            .find(|c: char| !(c.is_ascii_digit() || c == '.'))
+            .unwrap_or(rest.len());
+        if number_len == 0 {
+            bail!("invalid duration `{raw}`: expected a number");
+        }
+        let number = rest[..number_len]
+            .parse::<f64>()
+            .with_context(|| format!("invalid duration `{raw}`"))?;
+        rest = &rest[number_len..];
+
+        let unit_len = rest
+            .find(|c: char| c.is_ascii_digit() || c == '.')
+            .unwrap_or(rest.len());
+        let multiplier = match &rest[..unit_len] {
+            "ns" => 1f64,
+            "us" | "µs" => 1e3,
+            "ms" => 1e6,
+            "s" => 1e9,
+            "m" => 60e9,
+            "h" => 3600e9,
+            "" => bail!("invalid duration `{raw}`: missing unit"),
+            unit => bail!("invalid duration `{raw}`: unknown unit `{unit}`"),
+        };
+        rest = &rest[unit_len..];
+        total += number * multiplier;
+    }
+    Ok(total.round() as i64)
+}
+
+/// Parse the arguments of a `HEALTHCHECK` instruction.
+///
+/// Supports `HEALTHCHECK NONE` and `HEALTHCHECK [OPTIONS] CMD command` in shell
+/// or exec (JSON array) form.
+fn parse_healthcheck(args: &str) -> Result<HealthConfig> {
+    let mut health = HealthConfig::default();
+    let mut rest = args.trim();
+
+    while let Some(flag) = rest.strip_prefix("--") {
+        let (flag, remaining) = flag.split_once(char::is_whitespace).unwrap_or((flag, ""));
+        let (name, value) = flag
+            .split_once('=')
+            .with_context(|| format!("HEALTHCHECK flag `--{flag}` requires a value"))?;
+        match name {
+            "interval" => health.interval = parse_duration_nanos(value)?,
+            "timeout" => health.timeout = parse_duration_nanos(value)?,
+            "start-period" => health.start_period = parse_duration_nanos(value)?,
+            "start-interval" => health.start_interval = parse_duration_nanos(value)?,
+            "retries" => {
+                health.retries = value
+                    .parse::<u32>()
+                    .with_context(|| format!("invalid HEALTHCHECK retries `{value}`"))?
+                    .into();
+            }
+            other => bail!("Unknown HEALTHCHECK flag `--{other}`"),
+        }
+        rest = remaining.trim_start();
+    }
+
+    if rest.eq_ignore_ascii_case("NONE") {
+        if health != HealthConfig::default() {
+            bail!("HEALTHCHECK NONE does not accept options");
+        }
+        health.test = vec!["NONE".to_string()];
+        return Ok(health);
+    }
+
+    let (keyword, command) = rest.split_once(char::is_whitespace).unwrap_or((rest, ""));
+    if !keyword.eq_ignore_ascii_case("CMD") {
+        bail!("HEALTHCHECK requires `CMD <command>` or `NONE`");
+    }
+    let command = command.trim();
+    if command.is_empty() {
+        bail!("HEALTHCHECK CMD requires a command");
+    }
+
+    health.test = if command.starts_with('[') {
+        let args: Vec<String> = serde_json::from_str(command)
+            .with_context(|| "HEALTHCHECK CMD requires a valid JSON array")?;
+        if args.is_empty() {
+            bail!("HEALTHCHECK CMD array cannot be empty");
+        }
+        std::iter::once("CMD".to_string()).chain(args).collect()
+    } else {
+        vec!["CMD-SHELL".to_string(), command.to_string()]
+    };
+    Ok(health)
+}
+
+/// Validate an `ONBUILD` trigger and return it in normalized form.
+fn parse_onbuild_trigger(args: &str) -> Result<String> {
+    let trigger = args.trim();
+    let keyword = trigger
+        .split_whitespace()
+        .next()
+        .map(|word| word.to_ascii_uppercase())
+        .with_context(|| "ONBUILD requires an instruction argument")?;
+    if FORBIDDEN_ONBUILD_TRIGGERS.contains(&keyword.as_str()) {
+        bail!("{keyword} is not allowed as an ONBUILD trigger");
+    }
+    Ok(trigger.to_string())
+}
+
+/// Read the `ONBUILD` triggers recorded in a pulled base image's config.
+fn base_image_triggers(manifest_path: &Path) -> Result<Vec<String>> {
+    let manifest_content = fs::read_to_string(manifest_path)
+        .with_context(|| format!("Failed to read manifest {}", manifest_path.display()))?;
+    let manifest: OciManifest = serde_json::from_str(&manifest_content)
+        .with_context(|| format!("Failed to parse manifest {}", manifest_path.display()))?;
+    let OciManifest::Image(manifest) = manifest else {
+        return Ok(Vec::new());
+    };
+
+    let config_path = ultimate_blob_path(&manifest.config.digest)?;
+    let config_content = fs::read_to_string(&config_path)
+        .with_context(|| format!("Failed to read image config {}", config_path.display()))?;
+    let config: serde_json::Value = serde_json::from_str(&config_content)
+        .with_context(|| format!("Failed to parse image config {}", config_path.display()))?;
+
+    Ok(config
+        .pointer("/config/OnBuild")
+        .and_then(|triggers| triggers.as_array())
+        .map(|triggers| {
+            triggers
+                .iter()
+                .filter_map(|trigger| trigger.as_str().map(str::to_string))
+                .collect()
+        })
+        .unwrap_or_default())
+}
+
+/// Whether `path` is a local tar archive that ADD should unpack.
+///
+/// Plain tar and gzip-compressed tar are verified; bzip2 and xz are trusted by
+/// their magic bytes and left to `tar` to decode.
+fn is_local_archive(path: &Path) -> Result<bool> {
+    const TAR_MAGIC_OFFSET: usize = 257;
+
+    fn has_tar_magic(header: &[u8]) -> bool {
+        header.len() > TAR_MAGIC_OFFSET + 5
+            && &header[TAR_MAGIC_OFFSET..TAR_MAGIC_OFFSET + 5] == b"ustar"
+    }
+
+    if !path.is_file() {
+        return Ok(false);
+    }
+    let mut header = Vec::with_capacity(512);
+    fs::File::open(path)
+        .with_context(|| format!("Failed to open {}", path.display()))?
+        .take(512)
+        .read_to_end(&mut header)?;
+
+    if header.starts_with(&[0x1f, 0x8b]) {
+        let mut decoded = Vec::with_capacity(512);
+        let file = fs::File::open(path)?;
+        // A gzip file that does not decode to a tar header is copied as-is.
+        let _ = GzDecoder::new(file).take(512).read_to_end(&mut decoded);
+        return Ok(has_tar_magic(&decoded));
+    }
+    if header.starts_with(b"BZh") || header.starts_with(&[0xfd, b'7', b'z', b'X', b'Z', 0x00]) {
+        return Ok(true);
+    }
+    Ok(has_tar_magic(&header))
+}
+
+/// File name ADD uses for a remote source: the last path segment of the URL.
+fn url_file_name(url: &str) -> String {
+    let without_query = url.split(['?', '#']).next().unwrap_or(url);
+    let path = without_query
+        .split_once("://")
+        .map(|(_, rest)| rest)
+        .unwrap_or(without_query);
+    path.split_once('/')
+        .map(|(_, path)| path)
+        .and_then(|path| path.rsplit('/').find(|segment| !segment.is_empty()))
+        .unwrap_or("index.html")
+        .to_string()
+}
+
+/// Download a remote ADD source into `dir`, verifying `checksum` if given.
+fn download_url(url: &str, dir: &Path, checksum: Option<&str>) -> Result<PathBuf> {
+    let url_owned = url.to_string();
+    let bytes = block_on(async move {
+        let response = reqwest::get(&url_owned).await?.error_for_status()?;
+        response.bytes().await
+    })?
+    .with_context(|| format!("Failed to download {url}"))?;
+
+    if let Some(checksum) = checksum {
+        let expected = checksum.strip_prefix("sha256:").with_context(|| {
+            format!("unsupported ADD checksum `{checksum}`: expected sha256:<hex>")
+        })?;
+        let actual = hex::encode(Sha256::digest(&bytes));
+        if !actual.eq_ignore_ascii_case(expected) {
+            bail!("checksum mismatch for {url}: expected sha256:{expected}, got sha256:{actual}");
+        }
+    }
+
+    let path = dir.join(url_file_name(url));
+    fs::write(&path, &bytes).with_context(|| format!("Failed to write {}", path.display()))?;
+    Ok(path)
+}
+
+/// Execute `ADD [--checksum=<digest>] [--link] <src>... <dest>`.
+///
+/// Compared to COPY, sources may be remote URLs, and local tar archives are
+/// unpacked into the destination.
+fn execute_add<P: AsRef<Path>>(misc: &MiscInstruction, ctx: &mut Context<P>) -> Result<()> {
+    let raw_args = extract_misc_argument(&misc.arguments);
+    let mut checksum = None;
+    let mut rest = raw_args.as_str();
+    while let Some(flag) = rest.strip_prefix("--") {
+        let (flag, remaining) = flag.split_once(char::is_whitespace).unwrap_or((flag, ""));
+        match flag.split_once('=') {
+            Some(("checksum", value)) => checksum = Some(value.to_string()),
+            // `--link` only affects layer reuse, the resulting filesystem is identical.
+            None if flag == "link" => {}
+            _ => bail!("Flag --{flag} is not supported in ADD instruction"),
+        }
+        rest = remaining.trim_start();
+    }
+
+    let mut paths: Vec<String> = if rest.starts_with('[') {
+        serde_json::from_str(rest)
+            .with_context(|| "Invalid JSON array syntax in ADD instruction")?
+    } else {
+        rest.split_whitespace().map(str::to_string).collect()
+    };
+    if paths.len() < 2 {
+        bail!("ADD requires at least one source and a destination");
+    }
+    let dest = paths.pop().unwrap_or_default();
+    let dest = resolve_destination(ctx, &dest);
+
+    let heredocs = ctx
+        .heredocs
+        .get(&misc.span.start)
+        .map(Vec::as_slice)
+        .unwrap_or_default();
+    let scope = expansion_scope(ctx);
+    let staging = TempDir::new().context("Failed to create ADD staging directory")?;
+    let build_ctx = ctx.build_context.as_ref().canonicalize()?;
+
+    let mut src = Vec::new();
+    let mut extract = Vec::new();
+    for source in &paths {
+        if let Some(path) = materialize_heredoc_source(source, heredocs, &scope, staging.path())? {
+            src.push(path);
+        } else if source.starts_with("http://") || source.starts_with("https://") {
+            src.push(download_url(source, staging.path(), checksum.as_deref())?);
+        } else if source.starts_with("git@") || source.ends_with(".git") {
+            bail!("Git sources are not supported in ADD instruction: {source}");
+        } else {
+            if checksum.is_some() {
+                bail!("ADD --checksum is only supported for remote URL sources");
+            }
+            let path = build_ctx.join(source);
+            if is_local_archive(&path)? {
+                extract.push(path);
+            } else {
+                src.push(path);
+            }
+        }
+    }
+
+    let task = CopyTask {
+        src,
+        dest,
+        extract,
+        quiet: ctx.quiet,
+    };
+    task.execute(ctx.mount_config)
+}
+
 /// An extension trait to execute dockerfile instructions.
 pub trait InstructionExt<P: AsRef<Path>> {
     fn execute(&self, ctx: &mut Context<P>) -> Result<()>;
@@ -240,15 +619,21 @@ impl<P: AsRef<Path>> InstructionExt<P> for Instruction {
                         ctx.image_config.set_shell(shell);
                         Ok(())
                     }
-                    // TODO: These instructions are currently ignored but should be properly
-                    // recorded in the image config for OCI compliance
-                    "HEALTHCHECK" | "ONBUILD" => {
-                        tracing::warn!(
-                            "Instruction {} is ignored (not yet implemented)",
-                            instr_name
-                        );
+                    "HEALTHCHECK" => {
+                        let healthcheck =
+                            parse_healthcheck(&extract_misc_argument(&misc.arguments))?;
+                        tracing::debug!("Setting HEALTHCHECK to: {:?}", healthcheck);
+                        ctx.image_config.set_healthcheck(healthcheck);
+                        Ok(())
+                    }
+                    "ONBUILD" => {
+                        let trigger =
+                            parse_onbuild_trigger(&extract_misc_argument(&misc.arguments))?;
+                        tracing::debug!("Adding ONBUILD trigger: {}", trigger);
+                        ctx.image_config.add_on_build(trigger);
                         Ok(())
                     }
+                    "ADD" => execute_add(misc, ctx),
                     _ => {
                         bail!("Instruction {:?} is not supported", self);
                     }
@@ -260,16 +645,24 @@ impl<P: AsRef<Path>> InstructionExt<P> for Instruction {
 
 impl<P: AsRef<Path>> InstructionExt<P> for FromInstruction {
     fn execute(&self, ctx: &mut Context<P>) -> Result<()> {
-        let (_from_flags, image_parsed) = (&self.flags, &self.image_parsed);
+        let (from_flags, image_parsed) = (&self.flags, &self.image_parsed);
+
+        for flag in from_flags {
+            match flag.name.content.as_str() {
+                "platform" => {
+                    let raw = expand_env_value(&flag.value.content, &global_scope(ctx));
+                    ctx.platform = raw
+                        .parse()
+                        .with_context(|| format!("Invalid FROM --platform value `{raw}`"))?;
+                }
+                name => bail!("Flag --{name} is not supported in FROM instruction"),
+            }
+        }
 
         let img_ref = full_image_ref(&image_parsed.image, image_parsed.tag.as_deref());
 
-        let (_, layers) = sync_pull_or_get_image_with_policy_and_output(
-            &img_ref,
-            None::<String>,
-            ctx.no_cache,
-            ctx.quiet,
-        )?;
+        let (manifest_path, layers) =
+            sync_pull_or_get_image_for_platform(&img_ref, &ctx.platform, ctx.no_cache, ctx.quiet)?;
 
         // add image alias mapping
         if let Some(alias) = &self.alias {
@@ -282,7 +675,25 @@ impl<P: AsRef<Path>> InstructionExt<P> for FromInstruction {
         }
 
         // mount config should be unintialized
-        ctx.mount_config.init()
+        ctx.mount_config.init()?;
+
+        // Replay the base image's ONBUILD triggers before the stage's own instructions.
+        let triggers = base_image_triggers(&manifest_path)?;
+        if triggers.is_empty() {
+            return Ok(());
+        }
+        let heredocs = std::mem::replace(&mut ctx.heredocs, &NO_HEREDOCS);
+        let result = triggers.iter().try_for_each(|trigger| {
+            tracing::debug!("Executing ONBUILD trigger: {}", trigger);
+            let dockerfile = Dockerfile::parse(trigger)
+                .with_context(|| format!("Failed to parse ONBUILD trigger `{trigger}`"))?;
+            dockerfile
+                .instructions
+                .iter()
+                .try_for_each(|inst| inst.execute(ctx))
+        });
+        ctx.heredocs = heredocs;
+        result
     }
 }
 
@@ -313,7 +724,9 @@ impl<P: AsRef<Path>> InstructionExt<P> for LabelInstruction {
 
 impl<P: AsRef<Path>> InstructionExt<P> for RunInstruction {
     fn execute(&self, ctx: &mut Context<P>) -> Result<()> {
+        ctx.platform.ensure_executable()?;
         let mut command_args = vec![];
+        let mut mount_specs = vec![];
         match &self.expr {
             ShellOrExecExpr::Exec(exec_expr) => {
                 command_args = exec_expr
@@ -323,9 +736,6 @@ impl<P: AsRef<Path>> InstructionExt<P> for RunInstruction {
                     .collect();
             }
             ShellOrExecExpr::Shell(shell_expr) => {
-                // Use custom shell if set, otherwise default to ["/bin/sh", "-c"]
-                let shell = ctx.image_config.get_shell();
-                command_args.extend(shell);
                 let mut script = String::new();
                 for component in shell_expr.components.iter() {
                     match component {
@@ -335,11 +745,29 @@ impl<P: AsRef<Path>> InstructionExt<P> for RunInstruction {
                         }
                     }
                 }
-                command_args.push(script);
+                // The parser leaves `RUN --mount=...` flags in the command text.
+                let (specs, command) = split_run_flags(&script)?;
+                mount_specs = specs;
+                if command.starts_with('[') {
+                    command_args = serde_json::from_str(command)
+                        .with_context(|| "Invalid JSON array syntax in RUN instruction")?;
+                } else {
+                    // Use custom shell if set, otherwise default to ["/bin/sh", "-c"]
+                    let shell = ctx.image_config.get_shell();
+                    command_args.extend(shell);
+                    let mut command = command.to_string();
+                    if let Some(heredocs) = ctx.heredocs.get(&self.span.start) {
+                        command = heredoc_run_script(&command, heredocs);
+                    }
+                    command_args.push(command);
+                }
             }
         }
         // println!("Executing RUN command: {:?}", command_args);
 
+        // Keep cache locks held until the command has finished.
+        let (mounts, _mount_guard) = resolve_run_mounts(&mount_specs, ctx)?;
+
         let mut merged_envp = ctx.image_config.envp.clone();
         for (key, value) in &ctx.args {
             if merged_envp.contains_key(key) {
@@ -349,11 +777,14 @@ impl<P: AsRef<Path>> InstructionExt<P> for RunInstruction {
                 merged_envp.insert(key.clone(), value.clone());
             }
         }
-        if !ctx.ssh.is_empty() && !merged_envp.contains_key("SSH_AUTH_SOCK") {
-            merged_envp.insert(
-                "SSH_AUTH_SOCK".to_string(),
-                "/run/rkforge/ssh/ssh_agent.0".to_string(),
-            );
+        let ssh_target = mounts.iter().find_map(|mount| match mount {
+            RunMount::Ssh { target, .. } => Some(target.clone()),
+            _ => None,
+        });
+        if let Some(ssh_target) = ssh_target
+            && !merged_envp.contains_key("SSH_AUTH_SOCK")
+        {
+            merged_envp.insert("SSH_AUTH_SOCK".to_string(), ssh_target);
         }
         let envp: Vec<String> = merged_envp
             .iter()
@@ -371,8 +802,7 @@ impl<P: AsRef<Path>> InstructionExt<P> for RunInstruction {
             ulimits: ctx.ulimits.to_vec(),
             network_mode: ctx.network_mode,
             cgroup_parent: ctx.cgroup_parent.clone(),
-            secrets: ctx.secrets.to_vec(),
-            ssh: ctx.ssh.to_vec(),
+            mounts,
         };
         task.execute(ctx.mount_config)
     }
@@ -448,38 +878,30 @@ impl<P: AsRef<Path>> InstructionExt<P> for CopyInstruction {
             bail!("Flags are not supported in COPY instruction");
         }
 
-        let dest = self.destination.content.clone();
-        let dest = if dest.starts_with('/') {
-            // Absolute path - normalize to resolve `.` and `..` components
-            let normalized = normalize_path(&dest);
-            ctx.mount_config
-                .mountpoint
-                .join(normalized.trim_start_matches('/'))
-        } else {
-            // Relative path - resolve based on working directory, then normalize
-            let working_dir = ctx.image_config.get_working_dir();
-            let abs_dest = if working_dir == "/" {
-                format!("/{}", dest)
-            } else {
-                format!("{}/{}", working_dir, dest)
-            };
-            // Normalize to resolve `..` segments and prevent path traversal
-            let normalized = normalize_path(&abs_dest);
-            ctx.mount_config
-                .mountpoint
-                .join(normalized.trim_start_matches('/'))
-        };
+        let dest = resolve_destination(ctx, &self.destination.content);
 
+        let heredocs = ctx
+            .heredocs
+            .get(&self.span.start)
+            .map(Vec::as_slice)
+            .unwrap_or_default();
+        let scope = expansion_scope(ctx);
+        let staging = TempDir::new().context("Failed to create COPY staging directory")?;
         let build_ctx = ctx.build_context.as_ref().canonicalize()?;
-        let src: Vec<PathBuf> = self
+        let src = self
             .sources
             .iter()
-            .map(|s| build_ctx.join(&s.content))
-            .collect();
+            .map(|s| {
+                let heredoc =
+                    materialize_heredoc_source(&s.content, heredocs, &scope, staging.path())?;
+                Ok(heredoc.unwrap_or_else(|| build_ctx.join(&s.content)))
+            })
+            .collect::<Result<Vec<PathBuf>>>()?;
 
         let task = CopyTask {
             src,
             dest,
+            extract: Vec::new(),
             quiet: ctx.quiet,
         };
         task.execute(ctx.mount_config)
@@ -488,14 +910,8 @@ impl<P: AsRef<Path>> InstructionExt<P> for CopyInstruction {
 
 impl<P: AsRef<Path>> InstructionExt<P> for EnvInstruction {
     fn execute(&self, ctx: &mut Context<P>) -> Result<()> {
-        let mut expand_scope = HashMap::new();
-        for (key, value) in &ctx.args {
-            if let Some(value) = value {
-                expand_scope.insert(key.clone(), value.clone());
-            }
-        }
         // ENV has higher precedence for future expansion.
-        expand_scope.extend(ctx.image_config.envp.clone());
+        let expand_scope = expansion_scope(ctx);
 
         for var in self.vars.iter() {
             let mut val = Vec::new();
@@ -530,11 +946,17 @@ mod tests {
             build_runtime::BuildNetworkMode,
             config::{DEFAULT_ENV, ImageConfig},
             context::StageContext,
+            heredoc::HeredocMap,
+            platform::Platform,
         },
+        oci_spec::config::OciImageConfig,
         overlayfs::MountConfig,
     };
 
-    use super::{InstructionExt, expand_env_value};
+    use super::{
+        InstructionExt, expand_env_value, is_local_archive, parse_duration_nanos,
+        parse_healthcheck, parse_onbuild_trigger, url_file_name,
+    };
 
     #[test]
     fn test_expand_env_value_path_and_braced_vars() {
@@ -576,13 +998,16 @@ ARG BASE=alpine
         let mut mount_config = MountConfig::default();
         let mut image_config = ImageConfig::default();
         let mut image_aliases = HashMap::new();
+        let stage_rootfs = HashMap::new();
         let cli_build_args = HashMap::new();
         let global_args = HashMap::from([("BASE".to_string(), Some("ubuntu".to_string()))]);
+        let heredocs = HeredocMap::new();
 
         let mut ctx = StageContext {
             mount_config: &mut mount_config,
             image_config: &mut image_config,
             image_aliases: &mut image_aliases,
+            stage_rootfs: &stage_rootfs,
             args: HashMap::new(),
             cli_build_args: &cli_build_args,
             global_args: &global_args,
@@ -597,6 +1022,8 @@ ARG BASE=alpine
             cgroup_parent: None,
             secrets: &[],
             ssh: &[],
+            heredocs: &heredocs,
+            platform: Platform::host(),
         };
 
         arg_inst.execute(&mut ctx).unwrap();
@@ -624,13 +1051,16 @@ ARG BASE=alpine
         let mut mount_config = MountConfig::default();
         let mut image_config = ImageConfig::default();
         let mut image_aliases = HashMap::new();
+        let stage_rootfs = HashMap::new();
         let cli_build_args = HashMap::from([("BASE".to_string(), "debian".to_string())]);
         let global_args = HashMap::from([("BASE".to_string(), Some("ubuntu".to_string()))]);
+        let heredocs = HeredocMap::new();
 
         let mut ctx = StageContext {
             mount_config: &mut mount_config,
             image_config: &mut image_config,
             image_aliases: &mut image_aliases,
+            stage_rootfs: &stage_rootfs,
             args: HashMap::new(),
             cli_build_args: &cli_build_args,
             global_args: &global_args,
@@ -645,6 +1075,8 @@ ARG BASE=alpine
             cgroup_parent: None,
             secrets: &[],
             ssh: &[],
+            heredocs: &heredocs,
+            platform: Platform::host(),
         };
 
         arg_inst.execute(&mut ctx).unwrap();
@@ -668,13 +1100,16 @@ ENV A=1 B=$A
         let mut mount_config = MountConfig::default();
         let mut image_config = ImageConfig::default();
         let mut image_aliases = HashMap::new();
+        let stage_rootfs = HashMap::new();
         let cli_build_args = HashMap::new();
         let global_args = HashMap::new();
+        let heredocs = HeredocMap::new();
 
         let mut ctx = StageContext {
             mount_config: &mut mount_config,
             image_config: &mut image_config,
             image_aliases: &mut image_aliases,
+            stage_rootfs: &stage_rootfs,
             args: HashMap::new(),
             cli_build_args: &cli_build_args,
             global_args: &global_args,
@@ -689,6 +1124,8 @@ ENV A=1 B=$A
             cgroup_parent: None,
             secrets: &[],
             ssh: &[],
+            heredocs: &heredocs,
+            platform: Platform::host(),
         };
 
         dockerfile
@@ -702,4 +1139,169 @@ ENV A=1 B=$A
         assert_eq!(ctx.image_config.envp.get("A"), Some(&"1".to_string()));
         assert_eq!(ctx.image_config.envp.get("B"), Some(&"hello".to_string()));
     }
+
+    #[test]
+    fn test_parse_duration_nanos() {
+        assert_eq!(parse_duration_nanos("30s").unwrap(), 30_000_000_000);
+        assert_eq!(parse_duration_nanos("1m30s").unwrap(), 90_000_000_000);
+        assert_eq!(parse_duration_nanos("500ms").unwrap(), 500_000_000);
+        assert_eq!(parse_duration_nanos("1.5h").unwrap(), 5_400_000_000_000);
+        assert!(parse_duration_nanos("10").is_err());
+        assert!(parse_duration_nanos("5d").is_err());
+    }
+
+    #[test]
+    fn test_parse_healthcheck_shell_and_exec_forms() {
+        let health = parse_healthcheck(
+            "--interval=5m --timeout=3s --retries=3 CMD curl -f http://localhost/",
+        )
+        .unwrap();
+        assert_eq!(health.test, vec!["CMD-SHELL", "curl -f http://localhost/"]);
+        assert_eq!(health.interval, 300_000_000_000);
+        assert_eq!(health.timeout, 3_000_000_000);
+        assert_eq!(health.retries, 3);
+
+        let health = parse_healthcheck(r#"CMD ["pg_isready", "-U", "postgres"]"#).unwrap();
+        assert_eq!(health.test, vec!["CMD", "pg_isready", "-U", "postgres"]);
+
+        let health = parse_healthcheck("NONE").unwrap();
+        assert_eq!(health.test, vec!["NONE"]);
+
+        assert!(parse_healthcheck("--interval=5s NONE").is_err());
+        assert!(parse_healthcheck("--bogus=1 CMD true").is_err());
+        assert!(parse_healthcheck("true").is_err());
+    }
+
+    #[test]
+    fn test_healthcheck_and_onbuild_serialized_into_image_config() {
+        let dockerfile = Dockerfile::parse(
+            r#"
+FROM scratch
+HEALTHCHECK --interval=10s CMD wget -q -O- http://localhost/ || exit 1
+ONBUILD COPY . /app
+ONBUILD RUN make
+"#,
+        )
+        .unwrap();
+
+        let mut mount_config = MountConfig::default();
+        let mut image_config = ImageConfig::default();
+        let mut image_aliases = HashMap::new();
+        let stage_rootfs = HashMap::new();
+        let cli_build_args = HashMap::new();
+        let global_args = HashMap::new();
+        let heredocs = HeredocMap::new();
+
+        let mut ctx = StageContext {
+            mount_config: &mut mount_config,
+            image_config: &mut image_config,
+            image_aliases: &mut image_aliases,
+            stage_rootfs: &stage_rootfs,
+            args: HashMap::new(),
+            cli_build_args: &cli_build_args,
+            global_args: &global_args,
+            build_context: PathBuf::from("."),
+            no_cache: false,
+            quiet: true,
+            progress_mode: BuildProgressMode::Plain,
+            add_hosts: &[],
+            shm_size: None,
+            ulimits: &[],
+            network_mode: BuildNetworkMode::Default,
+            cgroup_parent: None,
+            secrets: &[],
+            ssh: &[],
+            heredocs: &heredocs,
+            platform: Platform::host(),
+        };
+
+        dockerfile
+            .instructions
+            .iter()
+            .filter(|inst| matches!(inst, Instruction::Misc(_)))
+            .for_each(|inst| {
+                inst.execute(&mut ctx).unwrap();
+            });
+
+        let temp_dir = tempfile::tempdir().unwrap();
+        let config_path = temp_dir.path().join("config.json");
+        OciImageConfig::default()
+            .config(ctx.image_config.get_oci_image_config().unwrap())
+            .unwrap()
+            .healthcheck(ctx.image_config.healthcheck.clone())
+            .on_build(ctx.image_config.on_build.clone())
+            .write_file_pretty(&config_path)
+            .unwrap();
+
+        let config: serde_json::Value =
+            serde_json::from_str(&std::fs::read_to_string(&config_path).unwrap()).unwrap();
+        assert_eq!(
+            config.pointer("/config/Healthcheck/Test").unwrap(),
+            &serde_json::json!(["CMD-SHELL", "wget -q -O- http://localhost/ || exit 1"])
+        );
+        assert_eq!(
+            config.pointer("/config/Healthcheck/Interval").unwrap(),
+            &serde_json::json!(10_000_000_000i64)
+        );
+        assert!(config.pointer("/config/Healthcheck/Timeout").is_none());
+        assert_eq!(
+            config.pointer("/config/OnBuild").unwrap(),
+            &serde_json::json!(["COPY . /app", "RUN make"])
+        );
+    }
+
+    #[test]
+    fn test_parse_onbuild_trigger_rejects_forbidden() {
+        assert_eq!(parse_onbuild_trigger(" RUN make ").unwrap(), "RUN make");
+        assert!(parse_onbuild_trigger("ONBUILD RUN make").is_err());
+        assert!(parse_onbuild_trigger("from alpine").is_err());
+        assert!(parse_onbuild_trigger("").is_err());
+    }
+
+    #[test]
+    fn test_is_local_archive() {
+        let temp_dir = tempfile::tempdir().unwrap();
+        let payload = temp_dir.path().join("payload.txt");
+        std::fs::write(&payload, b"hello").unwrap();
+
+        let tar_path = temp_dir.path().join("payload.tar");
+        let mut builder = tar::Builder::new(std::fs::File::create(&tar_path).unwrap());
+        builder
+            .append_path_with_name(&payload, "payload.txt")
+            .unwrap();
+        builder.finish().unwrap();
+        drop(builder);
+
+        let tar_gz_path = temp_dir.path().join("payload.tar.gz");
+        let mut encoder = flate2::write::GzEncoder::new(
+            std::fs::File::create(&tar_gz_path).unwrap(),
+            flate2::Compression::default(),
+        );
+        std::io::copy(&mut std::fs::File::open(&tar_path).unwrap(), &mut encoder).unwrap();
+        encoder.finish().unwrap();
+
+        let plain_gz_path = temp_dir.path().join("payload.txt.gz");
+        let mut encoder = flate2::write::GzEncoder::new(
+            std::fs::File::create(&plain_gz_path).unwrap(),
+            flate2::Compression::default(),
+        );
+        std::io::copy(&mut std::fs::File::open(&payload).unwrap(), &mut encoder).unwrap();
+        encoder.finish().unwrap();
+
+        assert!(is_local_archive(&tar_path).unwrap());
+        assert!(is_local_archive(&tar_gz_path).unwrap());
+        assert!(!is_local_archive(&plain_gz_path).unwrap());
+        assert!(!is_local_archive(&payload).unwrap());
+        assert!(!is_local_archive(temp_dir.path()).unwrap());
+    }
+
+    #[test]
+    fn test_url_file_name() {
+        assert_eq!(
+            url_file_name("https://example.com/releases/v1/tool.tar.gz?sig=abc"),
+            "tool.tar.gz"
+        );
+        assert_eq!(url_file_name("https://example.com/"), "index.html");
+        assert_eq!(url_file_name("https://example.com"), "index.html");
+    }
 }
@@ -7,6 +7,8 @@ use crate::{
         },
         config::ImageConfig,
         context::StageContext,
+        heredoc::HeredocMap,
+        platform::Platform,
         stage_executor::StageExecutor,
     },
     oci_spec::{
@@ -21,7 +23,10 @@ use rayon::prelude::*;
 use std::fs;
 use std::io::IsTerminal;
 use std::sync::Arc;
-use std::{collections::HashMap, path::PathBuf};
+use std::{
+    collections::HashMap,
+    path::{Path, PathBuf},
+};
 
 /// Executor coordinates the entire build by using one or more
 /// StageExecutors to handle each stage of the build.
@@ -30,6 +35,9 @@ use std::{collections::HashMap, path::PathBuf};
 pub struct Executor {
     // Use a guard to ensure all build directories are cleaned up
     guard: OverlayGuard,
+    stages_guard: OverlayGuard,
+    /// Finished stages are moved here so later stages can mount them.
+    stages_dir: PathBuf,
 
     pub dockerfile: Dockerfile,
     pub context: PathBuf,
@@ -38,6 +46,7 @@ pub struct Executor {
     pub mount_config: MountConfig,
     pub image_config: ImageConfig,
     pub image_aliases: HashMap<String, String>,
+    pub stage_rootfs: HashMap<String, Vec<PathBuf>>,
     pub image_layers: Vec<LayerCompressionResult>,
     pub image_ref_names: Vec<String>,
     pub cli_build_args: HashMap<String, String>,
@@ -56,6 +65,8 @@ pub struct Executor {
     pub no_cache_filters: Vec<String>,
     pub secrets: Vec<BuildSecret>,
     pub ssh: Vec<BuildSshAgent>,
+    pub heredocs: HeredocMap,
+    pub platform: Platform,
 
     pub compressor: Arc<dyn LayerCompressor + Send + Sync>,
 }
@@ -84,14 +95,18 @@ impl Executor {
         compressor: Arc<dyn LayerCompressor + Send + Sync>,
     ) -> Self {
         let mount_config = MountConfig::default();
+        let stages_dir = mount_config.overlay.with_file_name("stages");
         Self {
             guard: OverlayGuard::new(mount_config.overlay.clone()),
+            stages_guard: OverlayGuard::new(stages_dir.clone()),
+            stages_dir,
             dockerfile,
             context,
             image_output_dir,
             mount_config,
             image_config: ImageConfig::default(),
             image_aliases: HashMap::new(),
+            stage_rootfs: HashMap::new(),
             image_layers: Vec::new(),
             image_ref_names,
             cli_build_args,
@@ -110,6 +125,8 @@ impl Executor {
             no_cache_filters: Vec::new(),
             secrets: Vec::new(),
             ssh: Vec::new(),
+            heredocs: HeredocMap::new(),
+            platform: Platform::host(),
             compressor,
         }
     }
@@ -166,14 +183,50 @@ impl Executor {
         self.ssh = ssh;
     }
 
+    pub fn heredocs(&mut self, heredocs: HeredocMap) {
+        self.heredocs = heredocs;
+    }
+
+    /// Set the target platform and expose it to the Dockerfile through the automatic
+    /// `TARGET*`/`BUILD*` build args.
+    pub fn platform(&mut self, platform: Platform) {
+        for (key, value) in platform.build_args() {
+            let entry = self.global_args.entry(key).or_default();
+            if entry.is_none() {
+                *entry = Some(value);
+            }
+        }
+        self.platform = platform;
+    }
+
     pub fn build_image(&mut self) -> Result<()> {
+        self.run_build()?
+            .build()
+            .context("Failed to build OCI metadata")
+    }
+
+    /// Build the image for one platform of a multi-platform build.
+    ///
+    /// Only the config and manifest blobs are written; returns the size and
+    /// sha256sum of the manifest.
+    pub fn build_manifest(&mut self) -> Result<(u64, String)> {
+        self.run_build()?
+            .build_manifest()
+            .context("Failed to build OCI metadata")
+    }
+
+    fn run_build(&mut self) -> Result<OciBuilder> {
         self.execute_stages()?;
         // Apply CLI labels last so they override Dockerfile LABEL with the same key.
         self.apply_cli_labels();
         self.compress_layers()?;
-        self.generate_oci_metadata()?;
+        self.generate_oci_metadata()
+    }
 
-        Ok(())
+    pub fn image_index(&self) -> OciImageIndex {
+        OciImageIndex::default()
+            .reference_names(self.image_ref_names.clone())
+            .descriptor_annotations(self.cli_annotations.clone())
     }
 
     fn apply_cli_labels(&mut self) {
@@ -296,6 +349,7 @@ impl Executor {
                     mount_config: &mut self.mount_config,
                     image_config: &mut self.image_config,
                     image_aliases: &mut self.image_aliases,
+                    stage_rootfs: &self.stage_rootfs,
                     args: HashMap::new(),
                     cli_build_args: &self.cli_build_args,
                     global_args: &self.global_args,
@@ -310,10 +364,62 @@ impl Executor {
                     cgroup_parent: self.cgroup_parent.clone(),
                     secrets: &self.secrets,
                     ssh: &self.ssh,
+                    heredocs: &self.heredocs,
+                    platform: self.platform.clone(),
                 };
+                let stage_index = stage.index;
+                let stage_alias = stage.name.clone();
                 let mut stage_executor = StageExecutor::new(ctx, stage);
-                stage_executor.execute()
+                stage_executor.execute()?;
+
+                if stage_index < target_index {
+                    let layers = Self::snapshot_stage(
+                        &mut self.mount_config,
+                        &self.stages_dir,
+                        stage_index,
+                    )?;
+                    if let Some(name) = stage_alias {
+                        self.stage_rootfs.insert(name, layers.clone());
+                    }
+                    self.stage_rootfs.insert(stage_index.to_string(), layers);
+                }
+                Ok(())
+            })
+    }
+
+    /// Move a finished stage out of the overlay directory and reset the mount config
+    /// for the next stage.
+    ///
+    /// Returns the stage's layer directories, bottom-most first.
+    fn snapshot_stage(
+        mount_config: &mut MountConfig,
+        stages_dir: &Path,
+        stage_index: usize,
+    ) -> Result<Vec<PathBuf>> {
+        let snapshot_dir = stages_dir.join(stage_index.to_string());
+        if snapshot_dir.exists() {
+            fs::remove_dir_all(&snapshot_dir)?;
+        }
+        fs::create_dir_all(stages_dir)
+            .with_context(|| format!("Failed to create directory {}", stages_dir.display()))?;
+        fs::rename(&mount_config.overlay, &snapshot_dir).with_context(|| {
+            format!(
+                "Failed to move stage {stage_index} to {}",
+                snapshot_dir.display()
+            )
+        })?;
+
+        let overlay = mount_config.overlay.clone();
+        let layers = mount_config
+            .lower_dir
+            .drain(..)
+            .map(|layer| match layer.strip_prefix(&overlay) {
+                Ok(relative) => snapshot_dir.join(relative),
+                Err(_) => layer,
             })
+            .collect();
+        mount_config.upper_cnt = 0;
+        Ok(layers)
     }
 
     fn compress_layers(&mut self) -> Result<()> {
@@ -346,7 +452,7 @@ impl Executor {
         Ok(())
     }
 
-    fn generate_oci_metadata(&self) -> Result<()> {
+    fn generate_oci_metadata(&self) -> Result<OciBuilder> {
         let config = self
             .image_config
             .get_oci_image_config()
@@ -360,25 +466,18 @@ impl Executor {
                     .map(|l| l.tar_sha256sum.clone())
                     .collect();
                 config.rootfs(layer_ids)
-            })?;
+            })?
+            .platform(&self.platform)
+            .healthcheck(self.image_config.healthcheck.clone())
+            .on_build(self.image_config.on_build.clone());
 
-        let image_manifest = OciImageManifest::default().layers(
-            self.image_layers
-                .iter()
-                .map(|l| (l.gz_size, l.gz_sha256sum.clone()))
-                .collect::<Vec<(u64, String)>>(),
-        )?;
+        let image_manifest = OciImageManifest::default().layers(&self.image_layers)?;
 
-        let image_index = OciImageIndex::default()
-            .reference_names(self.image_ref_names.clone())
-            .descriptor_annotations(self.cli_annotations.clone());
-        let oci_builder = OciBuilder::default()
+        Ok(OciBuilder::default()
             .image_dir(self.image_output_dir.clone())
             .oci_image_config(image_config)
             .oci_image_manifest(image_manifest)
-            .oci_image_index(image_index);
-
-        oci_builder.build().context("Failed to build OCI metadata")
+            .oci_image_index(self.image_index()))
     }
 }
 
@@ -0,0 +1,311 @@
+//! Dockerfile heredoc (`<<EOF`) support.
+//!
+//! `dockerfile_parser` does not understand heredocs, so the Dockerfile is
+//! preprocessed before parsing: heredoc bodies are cut out of the source and
+//! stored in a [`HeredocMap`], keyed by the byte offset at which the owning
+//! instruction starts in the preprocessed text. That offset is exactly the
+//! `span.start` reported by the parser, so executors can look bodies up again.
+//!
+//! [Reference](https://docs.docker.com/reference/dockerfile/#here-documents)
+
+use std::collections::HashMap;
+
+use anyhow::{Result, bail};
+
+/// Instructions that accept heredoc arguments.
+const HEREDOC_INSTRUCTIONS: [&str; 3] = ["RUN", "COPY", "ADD"];
+
+/// Heredoc bodies indexed by the `span.start` of their instruction.
+pub type HeredocMap = HashMap<usize, Vec<Heredoc>>;
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Heredoc {
+    /// Delimiter word, without quotes or the `-` prefix.
+    pub name: String,
+    /// Body lines exactly as written in the Dockerfile, each terminated by `\n`.
+    pub raw: String,
+    /// `<<-EOF` form: leading tabs are stripped from every body line.
+    pub strip_tabs: bool,
+    /// Unquoted delimiter: the body is subject to variable expansion.
+    pub expand: bool,
+}
+
+impl Heredoc {
+    /// The body as seen by the consumer, with leading tabs removed for `<<-`.
+    pub fn content(&self) -> String {
+        if !self.strip_tabs {
+            return self.raw.clone();
+        }
+        self.raw
+            .split_inclusive('\n')
+            .map(|line| line.trim_start_matches('\t'))
+            .collect()
+    }
+}
+
+/// A parsed `<<[-]["']NAME["']` marker.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct HeredocMarker {
+    pub name: String,
+    pub strip_tabs: bool,
+    pub expand: bool,
+}
+
+fn is_word_start(ch: char) -> bool {
+    ch == '_' || ch.is_ascii_alphabetic()
+}
+
+fn is_word_char(ch: char) -> bool {
+    ch == '_' || ch.is_ascii_alphanumeric()
+}
+
+/// Parse a marker at the beginning of `input` (which must start with `<<`).
+///
+/// Returns the marker and the number of bytes it occupies.
+fn parse_marker_prefix(input: &str) -> Option<(HeredocMarker, usize)> {
+    let rest = input.strip_prefix("<<")?;
+    let (strip_tabs, rest) = match rest.strip_prefix('-') {
+        Some(rest) => (true, rest),
+        None => (false, rest),
+    };
+    let quote = rest.chars().next().filter(|c| *c == '"' || *c == '\'');
+    let word_start = if quote.is_some() { &rest[1..] } else { rest };
+    if !word_start.chars().next().is_some_and(is_word_start) {
+        return None;
+    }
+    let word_len = word_start
+        .find(|c: char| !is_word_char(c))
+        .unwrap_or(word_start.len());
+    let name = &word_start[..word_len];
+    let mut consumed = input.len() - rest.len() + word_len;
+    if let Some(quote) = quote {
+        if !word_start[word_len..].starts_with(quote) {
+            return None;
+        }
+        consumed += 2;
+    }
+    Some((
+        HeredocMarker {
+            name: name.to_string(),
+            strip_tabs,
+            expand: quote.is_none(),
+        },
+        consumed,
+    ))
+}
+
+/// Parse a standalone marker such as a `COPY` source (`<<EOF`, `<<-"EOF"`).
+pub fn parse_heredoc_marker(word: &str) -> Option<HeredocMarker> {
+    let word = word.trim();
+    match parse_marker_prefix(word) {
+        Some((marker, consumed)) if consumed == word.len() => Some(marker),
+        _ => None,
+    }
+}
+
+/// Find every heredoc marker in a single instruction line.
+///
+/// Here-strings (`<<<`) and arithmetic shifts (`1<<2`) are not markers.
+fn find_markers(line: &str) -> Vec<HeredocMarker> {
+    let mut markers = Vec::new();
+    let mut search_from = 0;
+    while let Some(pos) = line[search_from..].find("<<") {
+        let start = search_from + pos;
+        if start > 0 && line.as_bytes()[start - 1] == b'<' {
+            search_from = start + 2;
+            continue;
+        }
+        match parse_marker_prefix(&line[start..]) {
+            Some((marker, consumed)) => {
+                markers.push(marker);
+                search_from = start + consumed;
+            }
+            None => search_from = start + 2,
+        }
+    }
+    markers
+}
+
+/// Split heredoc bodies out of a Dockerfile.
+///
+/// Returns the Dockerfile text with every heredoc body (and its terminator line)
+/// removed, plus the extracted bodies keyed by instruction offset.
+pub fn extract_heredocs(content: &str) -> Result<(String, HeredocMap)> {
+    let mut output = String::with_capacity(content.len());
+    let mut heredocs = HeredocMap::new();
+    let mut lines = content.split_inclusive('\n');
+    let mut instruction: Option<(usize, String)> = None;
+    let mut continued = false;
+
+    while let Some(line) = lines.next() {
+        let trimmed = line.trim_start();
+        let line_offset = output.len() + (line.len() - trimmed.len());
+        output.push_str(line);
+
+        let body = trimmed.trim_end_matches(['\n', '\r']);
+        if body.is_empty() || body.starts_with('#') {
+            continue;
+        }
+        if !continued {
+            let keyword = body
+                .split_whitespace()
+                .next()
+                .unwrap_or_default()
+                .to_ascii_uppercase();
+            instruction = Some((line_offset, keyword));
+        }
+        continued = body.ends_with('\\');
+
+        let Some((instruction_start, keyword)) = instruction.as_ref() else {
+            continue;
+        };
+        if !HEREDOC_INSTRUCTIONS.contains(&keyword.as_str()) {
+            continue;
+        }
+
+        for marker in find_markers(body) {
+            let mut raw = String::new();
+            let mut terminated = false;
+            for body_line in lines.by_ref() {
+                let candidate = body_line.trim_end_matches(['\n', '\r']);
+                let candidate = if marker.strip_tabs {
+                    candidate.trim_start_matches('\t')
+                } else {
+                    candidate
+                };
+                if candidate == marker.name {
+                    terminated = true;
+                    break;
+                }
+                raw.push_str(body_line.trim_end_matches(['\n', '\r']));
+                raw.push('\n');
+            }
+            if !terminated {
+                bail!(
+                    "unterminated heredoc `{}` in {keyword} instruction",
+                    marker.name
+                );
+            }
+            heredocs
+                .entry(*instruction_start)
+                .or_default()
+                .push(Heredoc {
+                    name: marker.name,
+                    raw,
+                    strip_tabs: marker.strip_tabs,
+                    expand: marker.expand,
+                });
+        }
+    }
+
+    Ok((output, heredocs))
+}
+
+/// Build the shell script for a shell-form `RUN` that carries heredocs.
+///
+/// A bare `RUN <<EOF` runs the body itself (through its shebang interpreter if
+/// it has one); otherwise the bodies are re-attached so the shell can feed them
+/// to the command as usual.
+pub fn heredoc_run_script(script: &str, heredocs: &[Heredoc]) -> String {
+    if let [heredoc] = heredocs
+        && parse_heredoc_marker(script).is_some()
+    {
+        let content = heredoc.content();
+        return match content.strip_prefix("#!") {
+            Some(rest) => {
+                let interpreter = rest.lines().next().unwrap_or_default().trim();
+                format!(
+                    "{interpreter} <<'{}'\n{content}{}\n",
+                    heredoc.name, heredoc.name
+                )
+            }
+            None => content,
+        };
+    }
+
+    let mut full = script.trim_end().to_string();
+    full.push('\n');
+    for heredoc in heredocs {
+        full.push_str(&heredoc.raw);
+        full.push_str(&heredoc.name);
+        full.push('\n');
+    }
+    full
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{extract_heredocs, find_markers, heredoc_run_script, parse_heredoc_marker};
+    use dockerfile_parser::{Dockerfile, Instruction};
+
+    #[test]
+    fn test_parse_heredoc_marker_variants() {
+        let marker = parse_heredoc_marker("<<EOF").unwrap();
+        assert_eq!(marker.name, "EOF");
+        assert!(marker.expand);
+        assert!(!marker.strip_tabs);
+
+        let marker = parse_heredoc_marker("<<-'END'").unwrap();
+        assert_eq!(marker.name, "END");
+        assert!(!marker.expand);
+        assert!(marker.strip_tabs);
+
+        assert!(parse_heredoc_marker("<<\"EOF").is_none());
+        assert!(parse_heredoc_marker("file.txt").is_none());
+    }
+
+    #[test]
+    fn test_find_markers_skips_herestrings_and_shifts() {
+        assert!(find_markers("RUN echo $((1<<2)) && cat <<<\"x\"").is_empty());
+        let markers = find_markers("RUN cat <<A > /a && cat <<-\"B\" > /b");
+        let names: Vec<_> = markers.iter().map(|m| m.name.as_str()).collect();
+        assert_eq!(names, vec!["A", "B"]);
+    }
+
+    #[test]
+    fn test_extract_heredocs_keys_by_instruction_span() {
+        let (content, heredocs) = extract_heredocs(
+            "FROM alpine\nRUN <<EOF\napk add curl\necho done\nEOF\nCOPY <<-CONF /etc/app.conf\n\tkey=value\n\tCONF\nCMD [\"sh\"]\n",
+        )
+        .unwrap();
+
+        let dockerfile = Dockerfile::parse(&content).unwrap();
+        assert_eq!(dockerfile.instructions.len(), 4);
+
+        let Instruction::Run(run) = &dockerfile.instructions[1] else {
+            panic!("expected RUN");
+        };
+        let run_docs = heredocs.get(&run.span.start).unwrap();
+        assert_eq!(run_docs[0].content(), "apk add curl\necho done\n");
+
+        let Instruction::Copy(copy) = &dockerfile.instructions[2] else {
+            panic!("expected COPY");
+        };
+        let copy_docs = heredocs.get(&copy.span.start).unwrap();
+        assert_eq!(copy_docs[0].name, "CONF");
+        assert_eq!(copy_docs[0].content(), "key=value\n");
+    }
+
+    #[test]
+    fn test_extract_heredocs_unterminated() {
+        assert!(extract_heredocs("FROM alpine\nRUN <<EOF\necho hi\n").is_err());
+    }
+
+    #[test]
+    fn test_heredoc_run_script() {
+        let (_, heredocs) =
+            extract_heredocs("RUN <<EOF\n#!/usr/bin/env python3\nprint(1)\nEOF\n").unwrap();
+        let docs = heredocs.values().next().unwrap();
+        assert_eq!(
+            heredoc_run_script("<<EOF", docs),
+            "/usr/bin/env python3 <<'EOF'\n#!/usr/bin/env python3\nprint(1)\nEOF\n"
+        );
+
+        let (_, heredocs) = extract_heredocs("RUN cat <<EOF > /etc/motd\nhello\nEOF\n").unwrap();
+        let docs = heredocs.values().next().unwrap();
+        assert_eq!(
+            heredoc_run_script("cat <<EOF > /etc/motd", docs),
+            "cat <<EOF > /etc/motd\nhello\nEOF\n"
+        );
+    }
+}
@@ -3,7 +3,10 @@ pub mod config;
 pub mod context;
 pub mod execute;
 pub mod executor;
+pub mod heredoc;
 mod metadata;
+pub mod platform;
+pub mod run_mount;
 pub mod stage_executor;
 
 use std::collections::{HashMap, HashSet};
@@ -11,16 +14,18 @@ use std::fs;
 use std::io::Write;
 use std::net::IpAddr;
 use std::path::{Path, PathBuf};
-use std::sync::Arc;
 use std::time::Instant;
 
-use crate::compressor::tar_gz_compressor::TarGzCompressor;
+use crate::compressor::LayerCompression;
 use crate::image::build_runtime::{
     BuildHostEntry, BuildNetworkMode, BuildSecret, BuildSshAgent, BuildUlimit, BuildUlimitResource,
     BuildUlimitValue, normalize_cgroup_parent,
 };
 use crate::image::executor::Executor;
+use crate::image::heredoc::{HeredocMap, extract_heredocs};
 use crate::image::metadata::{BuildMetadata, write_metadata_file};
+use crate::image::platform::{Platform, parse_platform_arg};
+use crate::oci_spec::builder::OciBuilder;
 use crate::push::push_from_layout;
 use anyhow::{Context, Result, bail};
 use clap::{Parser, ValueEnum};
@@ -132,6 +137,19 @@ pub struct BuildArgs {
     #[arg(long = "ssh", value_name = "default|<id>[=<socket>]", value_parser = parse_ssh_option)]
     pub ssh: Vec<BuildSshAgent>,
 
+    /// Layer compression (gzip, zstd, estargz, zstd-chunked)
+    #[arg(long, value_enum, default_value = "gzip")]
+    pub compression: LayerCompression,
+
+    /// Target platforms (format: "os/arch[/variant]"), comma separated or set multiple times. More than one produces a multi-platform image index
+    #[arg(
+        long = "platform",
+        value_name = "OS/ARCH[/VARIANT]",
+        value_delimiter = ',',
+        value_parser = parse_platform_arg
+    )]
+    pub platforms: Vec<Platform>,
+
     /// Build context. Defaults to the directory of the Dockerfile.
     #[arg(default_value = ".")]
     pub context: PathBuf,
@@ -171,9 +189,14 @@ fn parse_add_host_option(raw: &str) -> std::result::Result<BuildHostEntry, Strin
 }
 
 fn parse_shm_size(raw: &str) -> std::result::Result<u64, String> {
+    parse_size_bytes(raw, "--shm-size")
+}
+
+/// Parse a byte size with an optional binary unit suffix (`64m`, `1g`, `1024`).
+pub(crate) fn parse_size_bytes(raw: &str, what: &str) -> std::result::Result<u64, String> {
     let value = raw.trim().to_ascii_lowercase();
     if value.is_empty() {
-        return Err("invalid --shm-size value: empty input".to_string());
+        return Err(format!("invalid {what} value: empty input"));
     }
 
     let unit_start = value
@@ -183,13 +206,13 @@ fn parse_shm_size(raw: &str) -> std::result::Result<u64, String> {
 
     if num_part.is_empty() {
         return Err(format!(
-            "invalid --shm-size value `{raw}`: expected positive number with optional unit"
+            "invalid {what} value `{raw}`: expected positive number with optional unit"
         ));
     }
 
-    let number = num_part.parse::<u64>().map_err(|e| {
-        format!("invalid --shm-size value `{raw}`: invalid number `{num_part}`: {e}")
-    })?;
+    let number = num_part
+        .parse::<u64>()
+        .map_err(|e| format!("invalid {what} value `{raw}`: invalid number `{num_part}`: {e}"))?;
     let multiplier = match unit_part {
         "" | "b" => 1_u64,
         "k" | "kb" | "ki" | "kib" => 1024_u64,
@@ -199,17 +222,17 @@ fn parse_shm_size(raw: &str) -> std::result::Result<u64, String> {
         "p" | "pb" | "pi" | "pib" => 1024_u64.pow(5),
         _ => {
             return Err(format!(
-                "invalid --shm-size value `{raw}`: unsupported unit `{unit_part}`"
+                "invalid {what} value `{raw}`: unsupported unit `{unit_part}`"
             ));
         }
     };
 
     let size_bytes = number
         .checked_mul(multiplier)
-        .ok_or_else(|| format!("invalid --shm-size value `{raw}`: value exceeds u64 range"))?;
+        .ok_or_else(|| format!("invalid {what} value `{raw}`: value exceeds u64 range"))?;
     if size_bytes == 0 {
         return Err(format!(
-            "invalid --shm-size value `{raw}`: size must be greater than 0"
+            "invalid {what} value `{raw}`: size must be greater than 0"
         ));
     }
     Ok(size_bytes)
@@ -386,13 +409,15 @@ fn parse_ssh_option(raw: &str) -> std::result::Result<BuildSshAgent, String> {
     Ok(BuildSshAgent { id, socket_path })
 }
 
-fn parse_dockerfile<P: AsRef<Path>>(dockerfile_path: P) -> Result<Dockerfile> {
+fn parse_dockerfile<P: AsRef<Path>>(dockerfile_path: P) -> Result<(Dockerfile, HeredocMap)> {
     let dockerfile_path = dockerfile_path.as_ref().to_path_buf();
     let dockerfile_content = fs::read_to_string(&dockerfile_path)
         .with_context(|| format!("Failed to read Dockerfile: {}", dockerfile_path.display()))?;
+    let (dockerfile_content, heredocs) = extract_heredocs(&dockerfile_content)
+        .with_context(|| format!("Failed to parse Dockerfile: {}", dockerfile_path.display()))?;
     let dockerfile = Dockerfile::parse(&dockerfile_content)
         .with_context(|| format!("Failed to parse Dockerfile: {}", dockerfile_path.display()))?;
-    Ok(dockerfile)
+    Ok((dockerfile, heredocs))
 }
 
 fn resolve_dockerfile_path(build_args: &BuildArgs) -> Result<PathBuf> {
@@ -448,6 +473,20 @@ fn parse_global_args(dockerfile: &Dockerfile) -> HashMap<String, Option<String>>
         .collect()
 }
 
+/// Deduplicate the requested platforms, defaulting to the host platform.
+fn resolve_platforms(platforms: &[Platform]) -> Vec<Platform> {
+    let mut resolved = Vec::with_capacity(platforms.len());
+    for platform in platforms {
+        if !resolved.contains(platform) {
+            resolved.push(platform.clone());
+        }
+    }
+    if resolved.is_empty() {
+        resolved.push(Platform::host());
+    }
+    resolved
+}
+
 fn read_primary_image_digest<P: AsRef<Path>>(
     image_output_dir: P,
     preferred_ref_name: Option<&str>,
@@ -628,7 +667,7 @@ pub fn build_image(build_args: &BuildArgs) -> Result<()> {
 
     let build_started_at = Instant::now();
     let dockerfile_path = resolve_dockerfile_path(build_args)?;
-    let dockerfile = parse_dockerfile(&dockerfile_path)?;
+    let (dockerfile, heredocs) = parse_dockerfile(&dockerfile_path)?;
     let cli_build_args = parse_key_value_options(&build_args.build_args, "--build-arg")?;
     let cli_labels = parse_key_value_options(&build_args.labels, "--label")?;
     let cli_annotations = parse_key_value_options(&build_args.annotations, "--annotation")?;
@@ -667,33 +706,63 @@ pub fn build_image(build_args: &BuildArgs) -> Result<()> {
     let global_args = parse_global_args(&dockerfile);
     let metadata_build_args = cli_build_args.clone();
 
-    let mut executor = Executor::new(
-        dockerfile,
-        context,
-        image_output_dir.clone(),
-        ref_names,
-        cli_build_args,
-        global_args,
-        Arc::new(TarGzCompressor),
-    );
-    executor.libfuse(build_args.libfuse);
-    executor.no_cache(build_args.no_cache);
-    executor.target(build_args.target.clone());
-    executor.output_options(build_args.quiet, build_args.progress);
-    executor.cli_labels(cli_labels);
-    executor.cli_annotations(cli_annotations);
-    executor.runtime_options(
-        build_args.add_hosts.clone(),
-        build_args.shm_size,
-        build_args.ulimits.clone(),
-        build_args.network,
-        cgroup_parent,
-    );
-    executor.no_cache_filter(no_cache_filters);
-    executor.secrets(build_args.secrets.clone());
-    executor.ssh(build_args.ssh.clone());
+    let new_executor = |platform: &Platform| {
+        let mut executor = Executor::new(
+            dockerfile.clone(),
+            context.clone(),
+            image_output_dir.clone(),
+            ref_names.clone(),
+            cli_build_args.clone(),
+            global_args.clone(),
+            build_args.compression.compressor(),
+        );
+        executor.libfuse(build_args.libfuse);
+        executor.no_cache(build_args.no_cache);
+        executor.target(build_args.target.clone());
+        executor.output_options(build_args.quiet, build_args.progress);
+        executor.cli_labels(cli_labels.clone());
+        executor.cli_annotations(cli_annotations.clone());
+        executor.runtime_options(
+            build_args.add_hosts.clone(),
+            build_args.shm_size,
+            build_args.ulimits.clone(),
+            build_args.network,
+            cgroup_parent.clone(),
+        );
+        executor.no_cache_filter(no_cache_filters.clone());
+        executor.secrets(build_args.secrets.clone());
+        executor.ssh(build_args.ssh.clone());
+        executor.heredocs(heredocs.clone());
+        executor.platform(platform.clone());
+        executor
+    };
 
-    executor.build_image()?;
+    let platforms = resolve_platforms(&build_args.platforms);
+    if let [platform] = platforms.as_slice() {
+        new_executor(platform).build_image()?;
+    } else {
+        // Platforms are built one after another since they share the overlay
+        // directory; content-addressed blobs land in the same layout.
+        let mut manifests = Vec::with_capacity(platforms.len());
+        let mut image_index = None;
+        for platform in &platforms {
+            if !build_args.quiet {
+                println!("# platform {platform}");
+            }
+            let mut executor = new_executor(platform);
+            let (size, digest) = executor
+                .build_manifest()
+                .with_context(|| format!("Failed to build for platform {platform}"))?;
+            manifests.push((size, digest, platform.to_oci()?));
+            image_index = Some(executor.image_index());
+        }
+        OciBuilder::build_platform_index(
+            &image_output_dir,
+            manifests,
+            image_index.unwrap_or_default(),
+        )
+        .context("Failed to build OCI image index")?;
+    }
 
     let image_digest = read_primary_image_digest(&image_output_dir, preferred_ref_name.as_deref())?;
     if let Some(iidfile) = build_args.iidfile.as_ref() {
@@ -727,14 +796,16 @@ mod tests {
     use std::fs;
     use std::path::PathBuf;
 
+    use crate::compressor::LayerCompression;
     use crate::image::build_runtime::BuildNetworkMode;
+    use crate::image::platform::Platform;
 
     use super::{
         BuildArgs, BuildProgressMode, derive_output_name, has_explicit_tag,
         normalize_cgroup_parent_option, normalize_push_reference, parse_add_host_option,
         parse_dockerfile, parse_global_args, parse_key_value_options, parse_secret_option,
         parse_shm_size, parse_ssh_option, parse_tags, parse_ulimit_option,
-        read_primary_image_digest, resolve_dockerfile_path, unique_ref_names,
+        read_primary_image_digest, resolve_dockerfile_path, resolve_platforms, unique_ref_names,
     };
     use clap::Parser;
     use dockerfile_parser::{BreakableStringComponent, Dockerfile, Instruction, ShellOrExecExpr};
@@ -746,10 +817,46 @@ mod tests {
 
         assert_eq!(build_args.file, Some(PathBuf::from("example-Dockerfile")));
         assert_eq!(build_args.tags, vec!["image1".to_string()]);
-        let dockerfile = parse_dockerfile(PathBuf::from("example-Dockerfile")).unwrap();
+        let (dockerfile, _) = parse_dockerfile(PathBuf::from("example-Dockerfile")).unwrap();
         assert_eq!(dockerfile.instructions.len(), 4);
     }
 
+    #[test]
+    fn test_compression_option() {
+        let build_args = BuildArgs::parse_from(vec!["rkforge", "-f", "example-Dockerfile"]);
+        assert_eq!(build_args.compression, LayerCompression::Gzip);
+
+        let build_args = BuildArgs::parse_from(vec![
+            "rkforge",
+            "-f",
+            "example-Dockerfile",
+            "--compression",
+            "zstd-chunked",
+        ]);
+        assert_eq!(build_args.compression, LayerCompression::ZstdChunked);
+    }
+
+    #[test]
+    fn test_platform_option() {
+        let build_args = BuildArgs::parse_from(vec!["rkforge", "-f", "example-Dockerfile"]);
+        assert_eq!(resolve_platforms(&build_args.platforms), [Platform::host()]);
+
+        let build_args = BuildArgs::parse_from(vec![
+            "rkforge",
+            "--platform",
+            "linux/amd64,linux/arm64",
+            "--platform",
+            "linux/x86_64",
+        ]);
+        let platforms = resolve_platforms(&build_args.platforms)
+            .iter()
+            .map(ToString::to_string)
+            .collect::<Vec<_>>();
+        assert_eq!(platforms, ["linux/amd64", "linux/arm64"]);
+
+        assert!(BuildArgs::try_parse_from(vec!["rkforge", "--platform", "linux"]).is_err());
+    }
+
     #[test]
     fn test_output_dir() {
         let build_args = BuildArgs::parse_from(vec![
@@ -855,7 +962,7 @@ mod tests {
             BuildArgs::parse_from(vec!["rkforge", "-f", "example-Dockerfile", "-t", "image1"]);
 
         assert_eq!(build_args.file, Some(PathBuf::from("example-Dockerfile")));
-        let dockerfile = parse_dockerfile(PathBuf::from("example-Dockerfile")).unwrap();
+        let (dockerfile, _) = parse_dockerfile(PathBuf::from("example-Dockerfile")).unwrap();
         for instruction in dockerfile.instructions.iter() {
             if let Instruction::Run(run_instruction) = instruction {
                 match &run_instruction.expr {
@@ -0,0 +1,311 @@
+use std::{collections::HashMap, fmt, fs, str::FromStr};
+
+use anyhow::{Result, bail};
+use oci_client::manifest::ImageIndexEntry;
+use oci_spec::image::{Arch, Os, PlatformBuilder};
+
+/// Directory where the kernel exposes registered `binfmt_misc` handlers.
+const BINFMT_MISC_DIR: &str = "/proc/sys/fs/binfmt_misc";
+
+/// A target platform in `os/arch[/variant]` form, e.g. `linux/arm64` or `linux/arm/v7`.
+///
+/// Architecture aliases are normalized to their OCI names on parse, so
+/// `linux/x86_64` and `linux/amd64` compare equal.
+#[derive(Clone, Debug, PartialEq, Eq, Hash)]
+pub struct Platform {
+    pub os: String,
+    pub architecture: String,
+    pub variant: Option<String>,
+}
+
+impl Platform {
+    pub fn new(
+        os: impl AsRef<str>,
+        architecture: impl AsRef<str>,
+        variant: Option<impl AsRef<str>>,
+    ) -> Self {
+        let os = os.as_ref().to_ascii_lowercase();
+        let (architecture, variant) = normalize_arch(
+            &architecture.as_ref().to_ascii_lowercase(),
+            variant.map(|v| v.as_ref().to_ascii_lowercase()),
+        );
+        Self {
+            os,
+            architecture,
+            variant,
+        }
+    }
+
+    /// The platform rkforge itself is running on.
+    pub fn host() -> Self {
+        let arch = match std::env::consts::ARCH {
+            "powerpc64" if cfg!(target_endian = "little") => "ppc64le",
+            arch => arch,
+        };
+        Self::new(std::env::consts::OS, arch, None::<&str>)
+    }
+
+    /// Whether binaries built for this platform run natively on the host.
+    pub fn is_native(&self) -> bool {
+        let host = Self::host();
+        if self.os != host.os {
+            return false;
+        }
+        self.architecture == host.architecture
+            || (host.architecture == "amd64" && self.architecture == "386")
+    }
+
+    /// Whether `RUN` steps can be executed for this platform, either natively or
+    /// through a registered qemu `binfmt_misc` handler.
+    pub fn can_execute(&self) -> bool {
+        if self.is_native() {
+            return true;
+        }
+        let Some(qemu_arch) = self.qemu_arch() else {
+            return false;
+        };
+        fs::read_to_string(format!("{BINFMT_MISC_DIR}/qemu-{qemu_arch}"))
+            .is_ok_and(|status| status.lines().next() == Some("enabled"))
+    }
+
+    /// Fail with an actionable error when `RUN` steps cannot execute for this platform.
+    pub fn ensure_executable(&self) -> Result<()> {
+        if self.can_execute() {
+            return Ok(());
+        }
+        match self.qemu_arch() {
+            Some(qemu_arch) => bail!(
+                "cannot RUN for platform {self} on {}: no enabled binfmt_misc handler qemu-{qemu_arch}; \
+                 install qemu-user-static or run the stage with `FROM --platform=$BUILDPLATFORM`",
+                Self::host()
+            ),
+            None => bail!(
+                "cannot RUN for platform {self} on {}: no emulator is known for this architecture",
+                Self::host()
+            ),
+        }
+    }
+
+    /// Name of the qemu user-mode emulator for this architecture.
+    pub fn qemu_arch(&self) -> Option<&'static str> {
+        Some(match self.architecture.as_str() {
+            "amd64" => "x86_64",
+            "arm64" => "aarch64",
+            "arm" => "arm",
+            "386" => "i386",
+            "ppc64le" => "ppc64le",
+            "s390x" => "s390x",
+            "riscv64" => "riscv64",
+            "mips64le" => "mips64el",
+            "loong64" => "loongarch64",
+            _ => return None,
+        })
+    }
+
+    /// Automatic platform build args (`TARGETPLATFORM`, `BUILDARCH`, ...) for a
+    /// build targeting this platform.
+    pub fn build_args(&self) -> HashMap<String, String> {
+        let host = Self::host();
+        let mut args = HashMap::new();
+        for (prefix, platform) in [("TARGET", self), ("BUILD", &host)] {
+            args.insert(format!("{prefix}PLATFORM"), platform.to_string());
+            args.insert(format!("{prefix}OS"), platform.os.clone());
+            args.insert(format!("{prefix}ARCH"), platform.architecture.clone());
+            args.insert(
+                format!("{prefix}VARIANT"),
+                platform.variant.clone().unwrap_or_default(),
+            );
+        }
+        args
+    }
+
+    pub fn oci_arch(&self) -> Arch {
+        Arch::from(self.architecture.as_str())
+    }
+
+    pub fn oci_os(&self) -> Os {
+        Os::from(self.os.as_str())
+    }
+
+    pub fn to_oci(&self) -> Result<oci_spec::image::Platform> {
+        let mut builder = PlatformBuilder::default()
+            .architecture(self.oci_arch())
+            .os(self.oci_os());
+        if let Some(variant) = &self.variant {
+            builder = builder.variant(variant.clone());
+        }
+        Ok(builder.build()?)
+    }
+
+    /// Whether an index entry's platform is this platform.
+    ///
+    /// An entry without a variant matches any variant of the architecture, and a
+    /// platform without a variant matches any entry of its architecture.
+    pub fn matches(&self, os: &str, architecture: &str, variant: Option<&str>) -> bool {
+        let other = Self::new(os, architecture, variant);
+        self.os == other.os
+            && self.architecture == other.architecture
+            && (self.variant.is_none() || other.variant.is_none() || self.variant == other.variant)
+    }
+
+    /// Pick the manifest for this platform from an image index, preferring an
+    /// exact variant match over a compatible one.
+    pub fn select_manifest<'a>(
+        &self,
+        entries: &'a [ImageIndexEntry],
+    ) -> Option<&'a ImageIndexEntry> {
+        let candidates = entries
+            .iter()
+            .filter_map(|entry| entry.platform.as_ref().map(|platform| (entry, platform)))
+            .filter(|(_, platform)| {
+                self.matches(
+                    &platform.os,
+                    &platform.architecture,
+                    platform.variant.as_deref(),
+                )
+            })
+            .collect::<Vec<_>>();
+
+        candidates
+            .iter()
+            .find(|(_, platform)| {
+                Self::new(
+                    &platform.os,
+                    &platform.architecture,
+                    platform.variant.as_deref(),
+                )
+                .variant
+                    == self.variant
+            })
+            .or_else(|| candidates.first())
+            .map(|(entry, _)| *entry)
+    }
+}
+
+/// Map architecture aliases to OCI names; `arm64/v8` is the default and drops
+/// its variant.
+fn normalize_arch(arch: &str, variant: Option<String>) -> (String, Option<String>) {
+    let variant = variant.filter(|v| !v.is_empty());
+    match arch {
+        "x86_64" | "x86-64" | "amd64" => ("amd64".to_string(), variant),
+        "aarch64" | "arm64" => (
+            "arm64".to_string(),
+            variant.filter(|v| v != "v8" && v != "8"),
+        ),
+        "armhf" => ("arm".to_string(), Some("v7".to_string())),
+        "armel" => ("arm".to_string(), Some("v6".to_string())),
+        "i386" | "i686" | "x86" => ("386".to_string(), variant),
+        other => (other.to_string(), variant),
+    }
+}
+
+impl FromStr for Platform {
+    type Err = anyhow::Error;
+
+    fn from_str(raw: &str) -> Result<Self> {
+        let parts = raw.trim().split('/').collect::<Vec<_>>();
+        if parts.iter().any(|part| part.is_empty()) {
+            bail!("expected format OS/ARCH[/VARIANT]");
+        }
+        match parts.as_slice() {
+            [os, arch] => Ok(Self::new(os, arch, None::<&str>)),
+            [os, arch, variant] => Ok(Self::new(os, arch, Some(variant))),
+            _ => bail!("expected format OS/ARCH[/VARIANT]"),
+        }
+    }
+}
+
+impl fmt::Display for Platform {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}/{}", self.os, self.architecture)?;
+        if let Some(variant) = &self.variant {
+            write!(f, "/{variant}")?;
+        }
+        Ok(())
+    }
+}
+
+pub fn parse_platform_arg(value: &str) -> std::result::Result<Platform, String> {
+    value
+        .parse()
+        .map_err(|e| format!("invalid platform `{value}`: {e}"))
+}
+
+#[cfg(test)]
+mod tests {
+    use oci_client::manifest::{ImageIndexEntry, Platform as IndexPlatform};
+
+    use super::Platform;
+
+    fn entry(digest: &str, arch: &str, variant: Option<&str>) -> ImageIndexEntry {
+        ImageIndexEntry {
+            media_type: "application/vnd.oci.image.manifest.v1+json".to_string(),
+            digest: digest.to_string(),
+            size: 100,
+            platform: Some(IndexPlatform {
+                architecture: arch.to_string(),
+                os: "linux".to_string(),
+                os_version: None,
+                os_features: None,
+                variant: variant.map(str::to_string),
+                features: None,
+            }),
+            annotations: None,
+        }
+    }
+
+    #[test]
+    fn test_parse_platform() {
+        let platform: Platform = "linux/amd64".parse().unwrap();
+        assert_eq!(platform.to_string(), "linux/amd64");
+
+        let platform: Platform = "Linux/x86_64".parse().unwrap();
+        assert_eq!(platform, "linux/amd64".parse().unwrap());
+
+        let platform: Platform = "linux/arm64/v8".parse().unwrap();
+        assert_eq!(platform.to_string(), "linux/arm64");
+
+        let platform: Platform = "linux/arm/v7".parse().unwrap();
+        assert_eq!(platform.variant.as_deref(), Some("v7"));
+
+        assert!("linux".parse::<Platform>().is_err());
+        assert!("linux/".parse::<Platform>().is_err());
+        assert!("linux/arm/v7/extra".parse::<Platform>().is_err());
+    }
+
+    #[test]
+    fn test_build_args() {
+        let args = "linux/arm/v7".parse::<Platform>().unwrap().build_args();
+        assert_eq!(args["TARGETPLATFORM"], "linux/arm/v7");
+        assert_eq!(args["TARGETARCH"], "arm");
+        assert_eq!(args["TARGETVARIANT"], "v7");
+        assert_eq!(args["BUILDPLATFORM"], Platform::host().to_string());
+    }
+
+    #[test]
+    fn test_select_manifest() {
+        let entries = vec![
+            entry("sha256:amd64", "amd64", None),
+            entry("sha256:armv6", "arm", Some("v6")),
+            entry("sha256:armv7", "arm", Some("v7")),
+            entry("sha256:arm64", "arm64", Some("v8")),
+        ];
+
+        let select = |raw: &str| {
+            raw.parse::<Platform>()
+                .unwrap()
+                .select_manifest(&entries)
+                .map(|entry| entry.digest.as_str())
+        };
+        assert_eq!(select("linux/x86_64"), Some("sha256:amd64"));
+        assert_eq!(select("linux/arm64"), Some("sha256:arm64"));
+        assert_eq!(select("linux/arm/v7"), Some("sha256:armv7"));
+        assert_eq!(select("linux/s390x"), None);
+    }
+
+    #[test]
+    fn test_host_is_native() {
+        assert!(Platform::host().is_native());
+        assert!(Platform::host().can_execute());
+    }
+}
@@ -0,0 +1,491 @@
+//! `RUN --mount` support.
+//!
+//! `dockerfile_parser` keeps RUN flags as part of the command text, so they are
+//! split off here, parsed into [`RunMountSpec`]s and resolved against the stage
+//! context into [`RunMount`]s. The mounts are set up by `exec-internal` right
+//! before the command runs and torn down by `cleanup`, so nothing they expose
+//! is committed into the resulting layer.
+//!
+//! [Reference](https://docs.docker.com/reference/dockerfile/#run---mount)
+
+use std::fs::{self, File, TryLockError};
+use std::os::unix::fs::PermissionsExt;
+use std::path::{Path, PathBuf};
+
+use anyhow::{Context as _, Result, bail};
+use nix::unistd::{Gid, Uid, chown};
+use sha2::{Digest, Sha256};
+use tempfile::TempDir;
+
+use crate::{
+    config::image::CONFIG,
+    image::{
+        build_runtime::RunMount, config::normalize_path, context::StageContext as Context,
+        parse_size_bytes,
+    },
+    overlayfs::{RKFORGE_SSH_DIR, SECRETS_DIR},
+    pull::sync_pull_or_get_image_for_platform,
+    storage::full_image_ref,
+};
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum MountType {
+    #[default]
+    Bind,
+    Cache,
+    Tmpfs,
+    Secret,
+    Ssh,
+}
+
+/// How concurrent builds share a cache mount.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum CacheSharing {
+    /// Concurrent writers use the same directory.
+    #[default]
+    Shared,
+    /// A concurrent writer gets a fresh, throwaway directory.
+    Private,
+    /// Concurrent writers wait for each other.
+    Locked,
+}
+
+/// A `--mount` flag as written in the Dockerfile.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct RunMountSpec {
+    pub mount_type: MountType,
+    pub target: Option<String>,
+    pub source: Option<String>,
+    pub from: Option<String>,
+    pub id: Option<String>,
+    /// `None` means the per-type default: read-only for bind, writable for cache.
+    pub readonly: Option<bool>,
+    pub sharing: CacheSharing,
+    pub mode: Option<u32>,
+    pub uid: Option<u32>,
+    pub gid: Option<u32>,
+    pub size: Option<u64>,
+    pub required: bool,
+    pub env: Option<String>,
+}
+
+fn unquote(value: &str) -> &str {
+    value
+        .strip_prefix('"')
+        .and_then(|v| v.strip_suffix('"'))
+        .or_else(|| value.strip_prefix('\'').and_then(|v| v.strip_suffix('\'')))
+        .unwrap_or(value)
+}
+
+fn parse_bool(key: &str, value: Option<&str>) -> Result<bool> {
+    match value.map(str::to_ascii_lowercase).as_deref() {
+        None | Some("true") | Some("1") => Ok(true),
+        Some("false") | Some("0") => Ok(false),
+        Some(other) => bail!("invalid --mount option `{key}={other}`: expected true or false"),
+    }
+}
+
+impl RunMountSpec {
+    /// Parse the value of a `--mount` flag, e.g. `type=cache,target=/root/.cache`.
+    pub fn parse(raw: &str) -> Result<Self> {
+        let mut spec = Self::default();
+        for field in raw.split(',').map(str::trim).filter(|f| !f.is_empty()) {
+            let (key, value) = match field.split_once('=') {
+                Some((key, value)) => {
+                    (key.trim().to_ascii_lowercase(), Some(unquote(value.trim())))
+                }
+                None => (field.to_ascii_lowercase(), None),
+            };
+            let value_of = |key: &str| {
+                value
+                    .filter(|v| !v.is_empty())
+                    .map(str::to_string)
+                    .with_context(|| format!("--mount option `{key}` requires a value"))
+            };
+            match key.as_str() {
+                "type" => {
+                    spec.mount_type = match value_of(&key)?.as_str() {
+                        "bind" => MountType::Bind,
+                        "cache" => MountType::Cache,
+                        "tmpfs" => MountType::Tmpfs,
+                        "secret" => MountType::Secret,
+                        "ssh" => MountType::Ssh,
+                        other => bail!("unsupported --mount type `{other}`"),
+                    }
+                }
+                "target" | "dst" | "destination" => spec.target = Some(value_of(&key)?),
+                "source" | "src" => spec.source = Some(value_of(&key)?),
+                "from" => spec.from = Some(value_of(&key)?),
+                "id" => spec.id = Some(value_of(&key)?),
+                "ro" | "readonly" => spec.readonly = Some(parse_bool(&key, value)?),
+                "rw" | "readwrite" => spec.readonly = Some(!parse_bool(&key, value)?),
+                "required" => spec.required = parse_bool(&key, value)?,
+                "env" => spec.env = Some(value_of(&key)?),
+                "sharing" => {
+                    spec.sharing = match value_of(&key)?.as_str() {
+                        "shared" => CacheSharing::Shared,
+                        "private" => CacheSharing::Private,
+                        "locked" => CacheSharing::Locked,
+                        other => bail!("invalid --mount sharing `{other}`"),
+                    }
+                }
+                "mode" => {
+                    let mode = value_of(&key)?;
+                    spec.mode = Some(
+                        u32::from_str_radix(&mode, 8)
+                            .with_context(|| format!("invalid --mount mode `{mode}`"))?,
+                    );
+                }
+                "uid" | "gid" => {
+                    let id = value_of(&key)?;
+                    let id = id
+                        .parse::<u32>()
+                        .with_context(|| format!("invalid --mount {key} `{id}`"))?;
+                    if key == "uid" {
+                        spec.uid = Some(id);
+                    } else {
+                        spec.gid = Some(id);
+                    }
+                }
+                "size" => {
+                    spec.size = Some(
+                        parse_size_bytes(&value_of(&key)?, "--mount size")
+                            .map_err(anyhow::Error::msg)?,
+                    );
+                }
+                other => bail!("unknown --mount option `{other}`"),
+            }
+        }
+
+        if matches!(
+            spec.mount_type,
+            MountType::Bind | MountType::Cache | MountType::Tmpfs
+        ) && spec.target.is_none()
+        {
+            bail!("--mount of this type requires a target");
+        }
+        if spec.mount_type == MountType::Secret && spec.id.is_none() && spec.target.is_none() {
+            bail!("--mount=type=secret requires an id or a target");
+        }
+        Ok(spec)
+    }
+}
+
+/// Split the leading `--flag` words off a shell-form RUN command.
+///
+/// Returns the parsed mounts and the remaining command text.
+pub fn split_run_flags(command: &str) -> Result<(Vec<RunMountSpec>, &str)> {
+    let mut mounts = Vec::new();
+    let mut rest = command.trim_start();
+    while let Some(flag) = rest.strip_prefix("--") {
+        let end = flag.find(char::is_whitespace).unwrap_or(flag.len());
+        let (flag, remaining) = flag.split_at(end);
+        match flag.split_once('=') {
+            Some(("mount", value)) => mounts.push(RunMountSpec::parse(value)?),
+            _ => bail!("Flag --{flag} is not supported in RUN instruction"),
+        }
+        rest = remaining.trim_start();
+    }
+    Ok((mounts, rest))
+}
+
+/// Keeps cache locks and private cache directories alive while a RUN step executes.
+#[derive(Default)]
+pub struct RunMountGuard {
+    _locks: Vec<File>,
+    _private_dirs: Vec<TempDir>,
+}
+
+/// Absolute, normalized container path of a mount target; relative targets follow WORKDIR.
+fn container_path<P: AsRef<Path>>(ctx: &Context<P>, target: &str) -> String {
+    if target.starts_with('/') {
+        normalize_path(target)
+    } else {
+        normalize_path(&format!(
+            "{}/{}",
+            ctx.image_config.get_working_dir(),
+            target
+        ))
+    }
+}
+
+/// Normalize a mount `source` to a path relative to its root, refusing to escape it.
+fn relative_source(source: Option<&str>) -> PathBuf {
+    let source = normalize_path(&format!("/{}", source.unwrap_or_default()));
+    PathBuf::from(source.trim_start_matches('/'))
+}
+
+fn apply_ownership(path: &Path, spec: &RunMountSpec, default_mode: u32) -> Result<()> {
+    fs::set_permissions(
+        path,
+        fs::Permissions::from_mode(spec.mode.unwrap_or(default_mode)),
+    )
+    .with_context(|| format!("Failed to set permissions on {}", path.display()))?;
+    if spec.uid.is_some() || spec.gid.is_some() {
+        chown(
+            path,
+            spec.uid.map(Uid::from_raw),
+            spec.gid.map(Gid::from_raw),
+        )
+        .with_context(|| format!("Failed to set ownership on {}", path.display()))?;
+    }
+    Ok(())
+}
+
+fn resolve_cache(
+    spec: &RunMountSpec,
+    target: String,
+    guard: &mut RunMountGuard,
+) -> Result<RunMount> {
+    if spec.from.is_some() || spec.source.is_some() {
+        bail!("--mount=type=cache does not support `from` or `source`");
+    }
+    let id = spec.id.clone().unwrap_or_else(|| target.clone());
+    let key = hex::encode(Sha256::digest(id.as_bytes()));
+    fs::create_dir_all(&CONFIG.cache_dir).with_context(|| {
+        format!(
+            "Failed to create cache directory {}",
+            CONFIG.cache_dir.display()
+        )
+    })?;
+
+    let mut dir = CONFIG.cache_dir.join(&key);
+    if !dir.exists() {
+        fs::create_dir(&dir).with_context(|| format!("Failed to create cache mount `{id}`"))?;
+        apply_ownership(&dir, spec, 0o755)?;
+    }
+
+    if spec.sharing != CacheSharing::Shared {
+        let lock_path = CONFIG.cache_dir.join(format!("{key}.lock"));
+        let lock = File::create(&lock_path)
+            .with_context(|| format!("Failed to open {}", lock_path.display()))?;
+        match spec.sharing {
+            CacheSharing::Locked => {
+                lock.lock()
+                    .with_context(|| format!("Failed to lock cache mount `{id}`"))?;
+                guard._locks.push(lock);
+            }
+            _ => match lock.try_lock() {
+                Ok(()) => guard._locks.push(lock),
+                Err(TryLockError::WouldBlock) => {
+                    tracing::debug!("Cache mount `{id}` is busy, using a private instance");
+                    let private = TempDir::with_prefix_in(format!("{key}-"), &CONFIG.cache_dir)
+                        .context("Failed to create private cache mount")?;
+                    apply_ownership(private.path(), spec, 0o755)?;
+                    dir = private.path().to_path_buf();
+                    guard._private_dirs.push(private);
+                }
+                Err(TryLockError::Error(e)) => {
+                    return Err(e).with_context(|| format!("Failed to lock cache mount `{id}`"));
+                }
+            },
+        }
+    }
+
+    Ok(RunMount::Cache {
+        target,
+        dir,
+        readonly: spec.readonly.unwrap_or(false),
+    })
+}
+
+fn resolve_bind<P: AsRef<Path>>(
+    spec: &RunMountSpec,
+    target: String,
+    ctx: &Context<P>,
+) -> Result<RunMount> {
+    let source = relative_source(spec.source.as_deref());
+    let (layers, source) = match &spec.from {
+        None => {
+            let build_ctx = ctx.build_context.as_ref().canonicalize()?;
+            let path = build_ctx.join(&source);
+            if !path.exists() {
+                bail!(
+                    "--mount=type=bind source `{}` not found in build context",
+                    source.display()
+                );
+            }
+            (vec![path], PathBuf::new())
+        }
+        Some(from) => {
+            let layers = match ctx.stage_rootfs.get(from) {
+                Some(layers) => layers.clone(),
+                None => {
+                    let (_, layers) = sync_pull_or_get_image_for_platform(
+                        full_image_ref(from, None::<&str>),
+                        &ctx.platform,
+                        ctx.no_cache,
+                        ctx.quiet,
+                    )
+                    .with_context(|| format!("Failed to resolve --mount from=`{from}`"))?;
+                    layers
+                }
+            };
+            (layers, source)
+        }
+    };
+
+    Ok(RunMount::Bind {
+        target,
+        layers,
+        source,
+        readwrite: !spec.readonly.unwrap_or(true),
+    })
+}
+
+fn resolve_secret<P: AsRef<Path>>(
+    spec: &RunMountSpec,
+    ctx: &Context<P>,
+) -> Result<Option<RunMount>> {
+    let target = spec.target.as_deref().map(|t| container_path(ctx, t));
+    let id = match (&spec.id, &target) {
+        (Some(id), _) => id.clone(),
+        (None, Some(target)) => Path::new(target)
+            .file_name()
+            .map(|name| name.to_string_lossy().to_string())
+            .with_context(|| format!("cannot derive secret id from target `{target}`"))?,
+        (None, None) => bail!("--mount=type=secret requires an id or a target"),
+    };
+    let Some(secret) = ctx.secrets.iter().find(|secret| secret.id == id) else {
+        if spec.required {
+            bail!("secret `{id}` is required but was not provided with --secret");
+        }
+        tracing::debug!("Skipping secret mount `{id}`: not provided");
+        return Ok(None);
+    };
+
+    // Secrets exposed only through `env` are not mounted as files.
+    let target = match (target, &spec.env) {
+        (None, None) => Some(format!("{SECRETS_DIR}/{id}")),
+        (target, _) => target,
+    };
+    Ok(Some(RunMount::Secret {
+        id,
+        src: secret.src.clone(),
+        target,
+        env: spec.env.clone(),
+        mode: spec.mode.unwrap_or(0o400),
+        uid: spec.uid.unwrap_or(0),
+        gid: spec.gid.unwrap_or(0),
+    }))
+}
+
+fn resolve_ssh<P: AsRef<Path>>(
+    spec: &RunMountSpec,
+    ctx: &Context<P>,
+    index: usize,
+) -> Result<Option<RunMount>> {
+    if spec.mode.is_some() || spec.uid.is_some() || spec.gid.is_some() {
+        bail!("--mount=type=ssh does not support mode, uid or gid");
+    }
+    let id = spec.id.as_deref().unwrap_or("default");
+    let Some(agent) = ctx.ssh.iter().find(|agent| agent.id == id) else {
+        if spec.required {
+            bail!("ssh agent `{id}` is required but was not provided with --ssh");
+        }
+        tracing::debug!("Skipping ssh mount `{id}`: not provided");
+        return Ok(None);
+    };
+    let target = match &spec.target {
+        Some(target) => container_path(ctx, target),
+        None => format!("{RKFORGE_SSH_DIR}/ssh_agent.{index}"),
+    };
+    Ok(Some(RunMount::Ssh {
+        id: id.to_string(),
+        socket_path: agent.socket_path.clone(),
+        target,
+    }))
+}
+
+/// Resolve parsed `--mount` flags against the stage context.
+///
+/// The returned guard must be kept alive until the RUN step has finished.
+pub fn resolve_run_mounts<P: AsRef<Path>>(
+    specs: &[RunMountSpec],
+    ctx: &Context<P>,
+) -> Result<(Vec<RunMount>, RunMountGuard)> {
+    let mut guard = RunMountGuard::default();
+    let mut mounts = Vec::with_capacity(specs.len());
+    let mut ssh_count = 0;
+    for spec in specs {
+        let target = spec
+            .target
+            .as_deref()
+            .map(|target| container_path(ctx, target));
+        let mount = match spec.mount_type {
+            MountType::Bind => Some(resolve_bind(spec, target.unwrap_or_default(), ctx)?),
+            MountType::Cache => Some(resolve_cache(spec, target.unwrap_or_default(), &mut guard)?),
+            MountType::Tmpfs => Some(RunMount::Tmpfs {
+                target: target.unwrap_or_default(),
+                size: spec.size,
+            }),
+            MountType::Secret => resolve_secret(spec, ctx)?,
+            MountType::Ssh => {
+                let mount = resolve_ssh(spec, ctx, ssh_count)?;
+                ssh_count += usize::from(mount.is_some());
+                mount
+            }
+        };
+        if let Some(target) = mount.as_ref().and_then(RunMount::target)
+            && target == "/"
+        {
+            bail!("--mount target must not be the root directory");
+        }
+        mounts.extend(mount);
+    }
+    Ok((mounts, guard))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{CacheSharing, MountType, RunMountSpec, split_run_flags};
+
+    #[test]
+    fn test_parse_mount_spec_variants() {
+        let spec = RunMountSpec::parse(
+            "type=cache,target=/root/.cache/pip,id=pip,sharing=locked,mode=0700,uid=1000",
+        )
+        .unwrap();
+        assert_eq!(spec.mount_type, MountType::Cache);
+        assert_eq!(spec.target.as_deref(), Some("/root/.cache/pip"));
+        assert_eq!(spec.id.as_deref(), Some("pip"));
+        assert_eq!(spec.sharing, CacheSharing::Locked);
+        assert_eq!(spec.mode, Some(0o700));
+        assert_eq!(spec.uid, Some(1000));
+
+        let spec = RunMountSpec::parse("target=/src,from=builder,source=/out,rw").unwrap();
+        assert_eq!(spec.mount_type, MountType::Bind);
+        assert_eq!(spec.from.as_deref(), Some("builder"));
+        assert_eq!(spec.readonly, Some(false));
+
+        let spec = RunMountSpec::parse("type=secret,id=npmrc,env=NPM_TOKEN,required").unwrap();
+        assert_eq!(spec.mount_type, MountType::Secret);
+        assert_eq!(spec.env.as_deref(), Some("NPM_TOKEN"));
+        assert!(spec.required);
+
+        let spec = RunMountSpec::parse("type=tmpfs,dst=/tmp,size=64m").unwrap();
+        assert_eq!(spec.size, Some(64 * 1024 * 1024));
+
+        assert!(RunMountSpec::parse("type=cache").is_err());
+        assert!(RunMountSpec::parse("type=volume,target=/x").is_err());
+        assert!(RunMountSpec::parse("type=cache,target=/x,bogus=1").is_err());
+        assert!(RunMountSpec::parse("type=secret").is_err());
+    }
+
+    #[test]
+    fn test_split_run_flags() {
+        let (mounts, rest) = split_run_flags(
+            "--mount=type=cache,target=/var/cache/apt --mount=type=ssh apt-get update",
+        )
+        .unwrap();
+        assert_eq!(mounts.len(), 2);
+        assert_eq!(mounts[1].mount_type, MountType::Ssh);
+        assert_eq!(rest, "apt-get update");
+
+        let (mounts, rest) = split_run_flags("echo --mount=type=cache").unwrap();
+        assert!(mounts.is_empty());
+        assert_eq!(rest, "echo --mount=type=cache");
+
+        assert!(split_run_flags("--security=insecure true").is_err());
+    }
+}
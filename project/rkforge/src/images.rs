@@ -1,7 +1,7 @@
 use crate::compressor::tar_gz_compressor::TarGzCompressor;
 use crate::compressor::{LayerCompressionConfig, LayerCompressor};
 use crate::config::meta::Repositories;
-use crate::pull::media::{MediaType, get_media_type};
+use crate::pull::media::get_layer_media_type;
 use crate::storage::{DigestExt, full_image_ref, read_manifest, ultimate_blob_path};
 use anyhow::{Context, Result, anyhow, bail};
 use chrono::{DateTime, Local};
@@ -349,9 +349,9 @@ pub fn save_image(args: SaveArgs) -> Result<()> {
                 .compress_layer(&compression_config)
                 .with_context(|| format!("Failed to compress layer {}", layer.digest))?;
             new_layers.push(oci_client::manifest::OciDescriptor {
-                media_type: "application/vnd.oci.image.layer.v1.tar+gzip".to_string(),
-                digest: format!("sha256:{}", result.gz_sha256sum),
-                size: result.gz_size as i64,
+                media_type: result.media_type.to_string(),
+                digest: format!("sha256:{}", result.blob_sha256sum),
+                size: result.blob_size as i64,
                 urls: layer.urls.clone(),
                 annotations: layer.annotations.clone(),
             });
@@ -460,7 +460,7 @@ fn copy_dir_recursive(src: &Path, dst: &Path) -> Result<()> {
     Ok(())
 }
 
-fn sha256_of_bytes(data: &[u8]) -> String {
+pub(crate) fn sha256_of_bytes(data: &[u8]) -> String {
     use sha2::{Digest, Sha256};
     let mut hasher = Sha256::new();
     hasher.update(data);
@@ -579,7 +579,7 @@ fn read_image_config_value(config_digest: &str) -> Result<Option<Value>> {
     Ok(Some(config_json))
 }
 
-fn read_config_diff_ids(config_json: &Value) -> Result<Vec<String>> {
+pub(crate) fn read_config_diff_ids(config_json: &Value) -> Result<Vec<String>> {
     let diff_ids = config_json
         .get("rootfs")
         .and_then(|v| v.get("diff_ids"))
@@ -596,7 +596,7 @@ fn read_config_diff_ids(config_json: &Value) -> Result<Vec<String>> {
     Ok(result)
 }
 
-fn write_config_diff_ids(config_json: &mut Value, diff_ids: &[String]) -> Result<()> {
+pub(crate) fn write_config_diff_ids(config_json: &mut Value, diff_ids: &[String]) -> Result<()> {
     let rootfs = config_json
         .get_mut("rootfs")
         .and_then(|v| v.as_object_mut())
@@ -680,15 +680,7 @@ fn import_manifest_entry(blobs_dir: &Path, manifest_entry: &Value, image_ref: &s
             continue;
         }
 
-        let media_type = get_media_type(&layer.media_type);
-        match media_type {
-            MediaType::Tar | MediaType::TarGzip => {
-                media_type.unpack(&src, &dst)?;
-            }
-            MediaType::Other => {
-                std::fs::copy(&src, &dst)?;
-            }
-        }
+        get_layer_media_type(layer).unpack(&src, &dst)?;
     }
 
     let manifest_dst = ultimate_blob_path(manifest_digest)?;
@@ -1,16 +1,20 @@
-use std::{fs, path::PathBuf};
+use std::{
+    fs,
+    path::{Path, PathBuf},
+};
 
 use anyhow::Result;
-use oci_spec::image::OciLayoutBuilder;
+use oci_spec::image::{OciLayoutBuilder, Platform};
 
 use crate::utils::hash::calculate_sha256;
 
 use super::{config::OciImageConfig, index::OciImageIndex, manifest::OciImageManifest};
 
-/// Currently only supports single manifest
+/// Builds the OCI layout of a single-platform image.
 ///
-/// To support multiple manifests, consider modifying the struct members
-/// to be a vector of manifests and update the methods accordingly.
+/// Multi-platform images write one manifest per platform with
+/// [`OciBuilder::build_manifest`] and then tie them together with
+/// [`OciBuilder::build_platform_index`].
 #[derive(Default)]
 pub struct OciBuilder {
     pub image_dir: PathBuf,
@@ -41,12 +45,23 @@ impl OciBuilder {
     }
 
     pub fn build(mut self) -> Result<()> {
+        let image_dir = self.image_dir.clone();
+        let oci_image_index = std::mem::take(&mut self.oci_image_index);
+        let manifest = self.build_manifest()?;
+
+        tracing::info!("Generating OCI image index...");
+        write_layout(&image_dir, oci_image_index.manifests(vec![manifest])?)
+    }
+
+    /// Write the image config and manifest blobs without touching `index.json`.
+    ///
+    /// Returns the size and sha256sum of the manifest.
+    pub fn build_manifest(mut self) -> Result<(u64, String)> {
         let layer_dir = self.image_dir.join("blobs/sha256");
 
         tracing::info!("Generating OCI image layout...");
-        let image_config = self.oci_image_config.build()?;
         let image_config_path = layer_dir.join("config.json");
-        image_config.to_file_pretty(&image_config_path)?;
+        std::mem::take(&mut self.oci_image_config).write_file_pretty(&image_config_path)?;
 
         let image_config_sha256sum = calculate_sha256(&image_config_path)?;
         let new_image_config_path = layer_dir.join(&image_config_sha256sum);
@@ -65,20 +80,50 @@ impl OciBuilder {
         fs::rename(&image_manifest_path, &new_image_manifest_path)?;
         let image_manifest_metadata = fs::metadata(&new_image_manifest_path)?;
 
-        tracing::info!("Generating OCI image index...");
-        self.oci_image_index = self.oci_image_index.manifests(vec![(
-            image_manifest_metadata.len(),
-            image_manifest_sha256sum,
-        )])?;
-        let image_index = self.oci_image_index.build()?;
-        let image_index_path = self.image_dir.join("index.json");
-        image_index.to_file_pretty(&image_index_path)?;
-
-        tracing::info!("Generating OCI layout...");
-        let oci_layout = OciLayoutBuilder::default()
-            .image_layout_version("1.0.0".to_string())
+        Ok((image_manifest_metadata.len(), image_manifest_sha256sum))
+    }
+
+    /// Write a nested image index listing the per-platform manifests, and point the
+    /// reference names of `oci_image_index` at it.
+    pub fn build_platform_index(
+        image_dir: &Path,
+        manifests: Vec<(u64, String, Platform)>,
+        oci_image_index: OciImageIndex,
+    ) -> Result<()> {
+        let layer_dir = image_dir.join("blobs/sha256");
+
+        tracing::info!("Generating OCI multi-platform image index...");
+        let platform_index = OciImageIndex::default()
+            .platform_manifests(manifests)?
             .build()?;
-        let oci_layout_path = self.image_dir.join("oci-layout");
-        Ok(oci_layout.to_file_pretty(&oci_layout_path)?)
+        let platform_index_path = layer_dir.join("index.json");
+        platform_index.to_file_pretty(&platform_index_path)?;
+        let platform_index_sha256sum = calculate_sha256(&platform_index_path)?;
+        let new_platform_index_path = layer_dir.join(&platform_index_sha256sum);
+        fs::rename(&platform_index_path, &new_platform_index_path)?;
+        let platform_index_metadata = fs::metadata(&new_platform_index_path)?;
+
+        tracing::info!("Generating OCI image index...");
+        write_layout(
+            image_dir,
+            oci_image_index.image_indexes(vec![(
+                platform_index_metadata.len(),
+                platform_index_sha256sum,
+            )])?,
+        )
     }
 }
+
+/// Write `index.json` and the `oci-layout` marker into `image_dir`.
+fn write_layout(image_dir: &Path, oci_image_index: OciImageIndex) -> Result<()> {
+    let image_index = oci_image_index.build()?;
+    let image_index_path = image_dir.join("index.json");
+    image_index.to_file_pretty(&image_index_path)?;
+
+    tracing::info!("Generating OCI layout...");
+    let oci_layout = OciLayoutBuilder::default()
+        .image_layout_version("1.0.0".to_string())
+        .build()?;
+    let oci_layout_path = image_dir.join("oci-layout");
+    Ok(oci_layout.to_file_pretty(&oci_layout_path)?)
+}
@@ -1,12 +1,20 @@
-use crate::image::config::DEFAULT_ENV;
-use anyhow::Result;
+use crate::image::{
+    config::{DEFAULT_ENV, HealthConfig},
+    platform::Platform,
+};
+use anyhow::{Context, Result};
 use oci_spec::image::{
     Arch, Config, ConfigBuilder, ImageConfiguration, ImageConfigurationBuilder, Os, RootFsBuilder,
 };
+use std::{fs, io::BufWriter, path::Path};
 
 #[derive(Default)]
 pub struct OciImageConfig {
     pub image_config_builder: ImageConfigurationBuilder,
+    /// Docker extension `config.Healthcheck`, not modelled by `oci_spec`.
+    pub healthcheck: Option<HealthConfig>,
+    /// Docker extension `config.OnBuild`, not modelled by `oci_spec`.
+    pub on_build: Option<Vec<String>>,
 }
 
 impl OciImageConfig {
@@ -37,6 +45,18 @@ impl OciImageConfig {
         Ok(self)
     }
 
+    /// Record the platform the image was built for, replacing the host defaults.
+    pub fn platform(mut self, platform: &Platform) -> Self {
+        self.image_config_builder = self
+            .image_config_builder
+            .architecture(platform.oci_arch())
+            .os(platform.oci_os());
+        if let Some(variant) = &platform.variant {
+            self.image_config_builder = self.image_config_builder.variant(variant.clone());
+        }
+        self
+    }
+
     pub fn rootfs(mut self, rootfs: Vec<String>) -> Result<Self> {
         let rootfs = RootFsBuilder::default()
             .typ("layers".to_string())
@@ -53,7 +73,42 @@ impl OciImageConfig {
         Ok(self)
     }
 
+    pub fn healthcheck(mut self, healthcheck: Option<HealthConfig>) -> Self {
+        self.healthcheck = healthcheck;
+        self
+    }
+
+    pub fn on_build(mut self, on_build: Option<Vec<String>>) -> Self {
+        self.on_build = on_build;
+        self
+    }
+
     pub fn build(self) -> Result<ImageConfiguration> {
         Ok(self.image_config_builder.build()?)
     }
+
+    /// Write the image configuration, including the Docker extensions, as pretty JSON.
+    pub fn write_file_pretty(self, path: impl AsRef<Path>) -> Result<()> {
+        let path = path.as_ref();
+        let healthcheck = self.healthcheck.clone();
+        let on_build = self.on_build.clone();
+
+        let mut value = serde_json::to_value(self.build()?)?;
+        if let Some(config) = value.get_mut("config").and_then(|c| c.as_object_mut()) {
+            if let Some(healthcheck) = healthcheck {
+                config.insert(
+                    "Healthcheck".to_string(),
+                    serde_json::to_value(healthcheck)?,
+                );
+            }
+            if let Some(on_build) = on_build {
+                config.insert("OnBuild".to_string(), serde_json::to_value(on_build)?);
+            }
+        }
+
+        let file = fs::File::create(path)
+            .with_context(|| format!("Failed to create {}", path.display()))?;
+        serde_json::to_writer_pretty(BufWriter::new(file), &value)
+            .with_context(|| format!("Failed to write {}", path.display()))
+    }
 }
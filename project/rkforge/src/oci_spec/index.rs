@@ -1,6 +1,7 @@
 use anyhow::{Context, Result};
 use oci_spec::image::{
-    DescriptorBuilder, ImageIndex, ImageIndexBuilder, MediaType, SCHEMA_VERSION, Sha256Digest,
+    Descriptor, DescriptorBuilder, ImageIndex, ImageIndexBuilder, MediaType, Platform,
+    SCHEMA_VERSION, Sha256Digest,
 };
 use std::{collections::HashMap, str::FromStr};
 
@@ -34,7 +35,41 @@ impl OciImageIndex {
         self
     }
 
-    pub fn manifests(mut self, manifests: Vec<(u64, String)>) -> Result<Self> {
+    pub fn manifests(self, manifests: Vec<(u64, String)>) -> Result<Self> {
+        self.named_descriptors(MediaType::ImageManifest, manifests)
+    }
+
+    /// Point the reference names at nested image indexes instead of manifests.
+    pub fn image_indexes(self, indexes: Vec<(u64, String)>) -> Result<Self> {
+        self.named_descriptors(MediaType::ImageIndex, indexes)
+    }
+
+    /// List per-platform manifests, as in the nested index of a multi-platform image.
+    ///
+    /// Reference names are not attached; they belong on the layout's `index.json`.
+    pub fn platform_manifests(mut self, manifests: Vec<(u64, String, Platform)>) -> Result<Self> {
+        let descriptors = manifests
+            .into_iter()
+            .map(|(size, digest_str, platform)| {
+                Ok(DescriptorBuilder::default()
+                    .media_type(MediaType::ImageManifest)
+                    .size(size)
+                    .digest(parse_digest(&digest_str)?)
+                    .platform(platform)
+                    .build()?)
+            })
+            .collect::<Result<Vec<Descriptor>>>()?;
+
+        self.image_index_builder = self.image_index_builder.manifests(descriptors);
+
+        Ok(self)
+    }
+
+    fn named_descriptors(
+        mut self,
+        media_type: MediaType,
+        manifests: Vec<(u64, String)>,
+    ) -> Result<Self> {
         let mut descriptors = Vec::new();
         let reference_names = if self.reference_names.is_empty() {
             vec!["latest".to_string()]
@@ -51,12 +86,9 @@ impl OciImageIndex {
                 );
 
                 let descriptor = DescriptorBuilder::default()
-                    .media_type(MediaType::ImageManifest)
+                    .media_type(media_type.clone())
                     .size(*size)
-                    .digest(
-                        Sha256Digest::from_str(digest_str.as_str())
-                            .with_context(|| format!("Invalid digest format: {digest_str}"))?,
-                    )
+                    .digest(parse_digest(digest_str)?)
                     .annotations(annotations)
                     .build()?;
 
@@ -77,6 +109,11 @@ impl OciImageIndex {
     }
 }
 
+fn parse_digest(digest_str: &str) -> Result<Sha256Digest> {
+    Sha256Digest::from_str(digest_str)
+        .with_context(|| format!("Invalid digest format: {digest_str}"))
+}
+
 impl Default for OciImageIndex {
     fn default() -> Self {
         let image_index_builder = ImageIndexBuilder::default()
@@ -95,6 +132,8 @@ impl Default for OciImageIndex {
 mod tests {
     use std::collections::HashMap;
 
+    use oci_spec::image::{Arch, MediaType, Os, PlatformBuilder};
+
     use super::OciImageIndex;
 
     #[test]
@@ -159,4 +198,36 @@ mod tests {
             Some(&"latest".to_string())
         );
     }
+
+    #[test]
+    fn test_platform_manifests() {
+        let platform = PlatformBuilder::default()
+            .architecture(Arch::ARM64)
+            .os(Os::Linux)
+            .build()
+            .unwrap();
+        let image_index = OciImageIndex::default()
+            .platform_manifests(vec![(123, "c".repeat(64), platform)])
+            .unwrap()
+            .build()
+            .unwrap();
+
+        let descriptor = &image_index.manifests()[0];
+        assert_eq!(descriptor.media_type(), &MediaType::ImageManifest);
+        assert_eq!(
+            descriptor.platform().as_ref().unwrap().architecture(),
+            &Arch::ARM64
+        );
+        assert!(descriptor.annotations().is_none());
+
+        let image_index = OciImageIndex::default()
+            .image_indexes(vec![(456, "d".repeat(64))])
+            .unwrap()
+            .build()
+            .unwrap();
+        assert_eq!(
+            image_index.manifests()[0].media_type(),
+            &MediaType::ImageIndex
+        );
+    }
 }
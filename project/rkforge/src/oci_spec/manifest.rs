@@ -1,3 +1,4 @@
+use crate::compressor::LayerCompressionResult;
 use anyhow::{Context, Result};
 use oci_spec::image::{
     DescriptorBuilder, ImageManifest, ImageManifestBuilder, MediaType, SCHEMA_VERSION, Sha256Digest,
@@ -24,18 +25,22 @@ impl OciImageManifest {
         Ok(self)
     }
 
-    pub fn layers(mut self, layers: Vec<(u64, String)>) -> Result<Self> {
+    pub fn layers(mut self, layers: &[LayerCompressionResult]) -> Result<Self> {
         let mut descriptors = Vec::new();
 
-        for (size, digest_str) in layers.iter() {
+        for layer in layers {
+            let digest_str = &layer.blob_sha256sum;
             let digest = Sha256Digest::from_str(digest_str.as_str())
                 .with_context(|| format!("Invalid digest format: {digest_str}"))?;
 
-            let descriptor = DescriptorBuilder::default()
-                .media_type(MediaType::ImageLayerGzip)
-                .size(*size)
-                .digest(digest)
-                .build()?;
+            let mut descriptor = DescriptorBuilder::default()
+                .media_type(layer.media_type.clone())
+                .size(layer.blob_size)
+                .digest(digest);
+            if !layer.annotations.is_empty() {
+                descriptor = descriptor.annotations(layer.annotations.clone());
+            }
+            let descriptor = descriptor.build()?;
 
             descriptors.push(descriptor);
         }
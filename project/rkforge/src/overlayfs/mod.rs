@@ -3,7 +3,7 @@ pub mod linux;
 
 use crate::config::image::CONFIG;
 use crate::image::build_runtime::{
-    BuildHostEntry, BuildSecret, BuildSshAgent, BuildUlimit, BuildUlimitResource, BuildUlimitValue,
+    BuildHostEntry, BuildUlimit, BuildUlimitResource, BuildUlimitValue, RunMount,
 };
 use anyhow::{Context, Result, bail};
 use base64::{Engine, engine::general_purpose};
@@ -21,8 +21,7 @@ pub static HOSTS_CONFIG: &str = "/etc/hosts";
 pub static SHM_CONFIG: &str = "/dev/shm";
 pub static SECRETS_DIR: &str = "/run/secrets";
 pub static RKFORGE_SSH_DIR: &str = "/run/rkforge/ssh";
-static SECRETS_CREATED_MARKER: &str = "runtime-secrets-dir-created";
-static SSH_CREATED_MARKER: &str = "runtime-rkforge-ssh-dir-created";
+static RUN_MOUNTS_STATE: &str = "run-mounts.json";
 pub static BIND_MOUNTS: [&str; 3] = ["/dev", "/proc", "/sys"];
 
 #[derive(Parser, Debug)]
@@ -623,18 +622,6 @@ fn runtime_temp_file(name: &str) -> PathBuf {
     CONFIG.build_dir.join(format!("{name}.{mount_pid}"))
 }
 
-fn mark_runtime_dir_created(marker_name: &str, existed_before: bool) -> Result<()> {
-    let marker = runtime_temp_file(marker_name);
-    if existed_before {
-        remove_temp_file(&marker);
-        return Ok(());
-    }
-
-    fs::write(&marker, b"1")
-        .with_context(|| format!("Failed to write runtime marker {}", marker.display()))?;
-    Ok(())
-}
-
 fn ensure_no_symlink_components(root: &Path, target: &Path) -> Result<()> {
     let relative = target.strip_prefix(root).with_context(|| {
         format!(
@@ -801,118 +788,302 @@ pub fn prepare_shm<P: AsRef<Path>>(mountpoint: P, shm_size: Option<u64>) -> Resu
     Ok(())
 }
 
-pub fn prepare_secrets<P: AsRef<Path>>(mountpoint: P, secrets: &[BuildSecret]) -> Result<()> {
-    if secrets.is_empty() {
-        return Ok(());
-    }
+/// Bookkeeping of `RUN --mount` setup, read back by `cleanup` to undo it.
+#[derive(Debug, Default, Serialize, Deserialize)]
+struct RunMountState {
+    /// Mounted paths, in mount order.
+    mounted: Vec<PathBuf>,
+    /// Paths created inside the rootfs to serve as mount targets, in creation order.
+    created: Vec<PathBuf>,
+    /// Host-side staging directories.
+    staging: Vec<PathBuf>,
+}
 
-    let mountpoint = mountpoint.as_ref();
-    let target_dir = mountpoint.join(SECRETS_DIR.strip_prefix('/').unwrap());
-    mark_runtime_dir_created(SECRETS_CREATED_MARKER, target_dir.exists())?;
-    fs::create_dir_all(&target_dir)
-        .with_context(|| format!("Failed to create {}", target_dir.display()))?;
+/// Create the mount target for `target` inside the rootfs, recording what was created.
+fn create_mount_target(
+    root: &Path,
+    target: &str,
+    is_dir: bool,
+    state: &mut RunMountState,
+) -> Result<PathBuf> {
+    let path = root.join(target.trim_start_matches('/'));
+    ensure_no_symlink_components(root, &path)?;
+
+    let mut missing: Vec<PathBuf> = path
+        .ancestors()
+        .take_while(|p| *p != root && fs::symlink_metadata(p).is_err())
+        .map(Path::to_path_buf)
+        .collect();
+    missing.reverse();
 
-    nix::mount::mount::<_, _, str, str>(
-        Some("tmpfs"),
-        &target_dir,
-        Some("tmpfs"),
-        nix::mount::MsFlags::empty(),
-        Some("size=1048576,mode=0755"),
-    )
-    .with_context(|| format!("Failed to mount tmpfs on {}", target_dir.display()))?;
+    if is_dir {
+        fs::create_dir_all(&path)
+            .with_context(|| format!("Failed to create {}", path.display()))?;
+    } else {
+        ensure_target_file(root, &path)?;
+    }
+    state.created.extend(missing);
+    Ok(path)
+}
 
-    for secret in secrets {
-        let content = fs::read(&secret.src).with_context(|| {
-            format!(
-                "Failed to read secret `{}` from {}",
-                secret.id,
-                secret.src.display()
-            )
-        })?;
+fn bind_mount_path(src: &Path, dst: &Path, readonly: bool) -> Result<()> {
+    use nix::mount::MsFlags;
 
-        let target_file = target_dir.join(&secret.id);
-        fs::write(&target_file, &content).with_context(|| {
+    nix::mount::mount::<_, _, str, str>(Some(src), dst, None, MsFlags::MS_BIND, None)
+        .with_context(|| {
             format!(
-                "Failed to write secret `{}` to {}",
-                secret.id,
-                target_file.display()
+                "Failed to bind mount {} to {}",
+                src.display(),
+                dst.display()
             )
         })?;
-
-        use std::os::unix::fs::PermissionsExt;
-        fs::set_permissions(&target_file, fs::Permissions::from_mode(0o400))
-            .with_context(|| format!("Failed to set permissions on {}", target_file.display()))?;
+    if readonly {
+        nix::mount::mount::<str, _, str, str>(
+            None,
+            dst,
+            None,
+            MsFlags::MS_BIND | MsFlags::MS_REMOUNT | MsFlags::MS_RDONLY,
+            None,
+        )
+        .with_context(|| format!("Failed to remount {} read-only", dst.display()))?;
     }
-
     Ok(())
 }
 
-pub fn prepare_ssh<P: AsRef<Path>>(mountpoint: P, ssh_agents: &[BuildSshAgent]) -> Result<()> {
-    if ssh_agents.is_empty() {
-        return Ok(());
-    }
-
-    let mountpoint = mountpoint.as_ref();
-    let target_dir = mountpoint.join(RKFORGE_SSH_DIR.strip_prefix('/').unwrap());
-    mark_runtime_dir_created(SSH_CREATED_MARKER, target_dir.exists())?;
-    fs::create_dir_all(&target_dir)
-        .with_context(|| format!("Failed to create {}", target_dir.display()))?;
-
+fn mount_tmpfs(target: &Path, options: &str) -> Result<()> {
     nix::mount::mount::<_, _, str, str>(
         Some("tmpfs"),
-        &target_dir,
+        target,
         Some("tmpfs"),
         nix::mount::MsFlags::empty(),
-        Some("size=1048576,mode=0755"),
+        Some(options),
     )
-    .with_context(|| format!("Failed to mount tmpfs on {}", target_dir.display()))?;
+    .with_context(|| {
+        format!(
+            "Failed to mount tmpfs on {} with options `{options}`",
+            target.display()
+        )
+    })
+}
 
-    for (idx, agent) in ssh_agents.iter().enumerate() {
-        let target_socket = target_dir.join(format!("ssh_agent.{idx}"));
+/// Resolve the host path exposed by a bind mount, merging multiple layers with overlayfs.
+fn prepare_bind_source(
+    index: usize,
+    layers: &[PathBuf],
+    source: &Path,
+    readwrite: bool,
+    state: &mut RunMountState,
+) -> Result<PathBuf> {
+    use std::os::unix::ffi::OsStrExt;
 
-        fs::write(&target_socket, b"").with_context(|| {
-            format!(
-                "Failed to create placeholder for ssh agent `{}` at {}",
-                agent.id,
-                target_socket.display()
-            )
-        })?;
+    if let [layer] = layers
+        && !readwrite
+    {
+        return Ok(layer.join(source));
+    }
+    if layers.is_empty() {
+        bail!("Bind mount has no source layers");
+    }
 
-        nix::mount::mount::<_, _, str, str>(
-            Some(&agent.socket_path),
-            &target_socket,
-            None,
-            nix::mount::MsFlags::MS_BIND,
-            None,
+    let staging = runtime_temp_file(&format!("run-mount-{index}"));
+    if staging.exists() {
+        fs::remove_dir_all(&staging)?;
+    }
+    let rootfs = staging.join("rootfs");
+    fs::create_dir_all(&rootfs)
+        .with_context(|| format!("Failed to create {}", rootfs.display()))?;
+    state.staging.push(staging.clone());
+
+    let lower_dirs_bytes: Vec<&[u8]> = layers
+        .iter()
+        .rev()
+        .map(|p| p.as_os_str().as_bytes())
+        .collect();
+    let mut options = Vec::new();
+    options.extend_from_slice(b"lowerdir=");
+    options.extend_from_slice(&lower_dirs_bytes.join(&b':'));
+    if readwrite {
+        // Writes land in a throwaway upper directory.
+        let upper_dir = staging.join("upper");
+        let work_dir = staging.join("work");
+        fs::create_dir_all(&upper_dir)?;
+        fs::create_dir_all(&work_dir)?;
+        options.extend_from_slice(b",upperdir=");
+        options.extend_from_slice(upper_dir.as_os_str().as_bytes());
+        options.extend_from_slice(b",workdir=");
+        options.extend_from_slice(work_dir.as_os_str().as_bytes());
+    }
+
+    nix::mount::mount(
+        Some("overlay".as_bytes()),
+        &rootfs,
+        Some("overlay".as_bytes()),
+        nix::mount::MsFlags::empty(),
+        Some(&options[..]),
+    )
+    .with_context(|| {
+        format!(
+            "Failed to mount bind source overlay on {}",
+            rootfs.display()
         )
-        .with_context(|| {
-            format!(
-                "Failed to bind mount ssh agent `{}` from {} to {}",
-                agent.id,
-                agent.socket_path.display(),
-                target_socket.display()
+    })?;
+    state.mounted.push(rootfs.clone());
+    Ok(rootfs.join(source))
+}
+
+fn prepare_run_mount(
+    mountpoint: &Path,
+    index: usize,
+    mount: &RunMount,
+    state: &mut RunMountState,
+) -> Result<()> {
+    match mount {
+        RunMount::Bind {
+            target,
+            layers,
+            source,
+            readwrite,
+        } => {
+            let src = prepare_bind_source(index, layers, source, *readwrite, state)?;
+            let meta = fs::metadata(&src)
+                .with_context(|| format!("Bind mount source {} not found", src.display()))?;
+            let dst = create_mount_target(mountpoint, target, meta.is_dir(), state)?;
+            bind_mount_path(&src, &dst, !readwrite)?;
+            state.mounted.push(dst);
+        }
+        RunMount::Cache {
+            target,
+            dir,
+            readonly,
+        } => {
+            let dst = create_mount_target(mountpoint, target, true, state)?;
+            bind_mount_path(dir, &dst, *readonly)?;
+            state.mounted.push(dst);
+        }
+        RunMount::Tmpfs { target, size } => {
+            let dst = create_mount_target(mountpoint, target, true, state)?;
+            let options = size.map(|size| format!("size={size}")).unwrap_or_default();
+            mount_tmpfs(&dst, &options)?;
+            state.mounted.push(dst);
+        }
+        RunMount::Secret {
+            id,
+            src,
+            target: Some(target),
+            mode,
+            uid,
+            gid,
+            ..
+        } => {
+            // Secrets are copied to a private tmpfs so they never touch the layer on disk.
+            let staging = runtime_temp_file(&format!("run-secret-{index}"));
+            fs::create_dir_all(&staging)
+                .with_context(|| format!("Failed to create {}", staging.display()))?;
+            state.staging.push(staging.clone());
+            mount_tmpfs(&staging, "size=1048576,mode=0700")?;
+            state.mounted.push(staging.clone());
+
+            let content = fs::read(src)
+                .with_context(|| format!("Failed to read secret `{id}` from {}", src.display()))?;
+            let staged = staging.join("secret");
+            fs::write(&staged, &content)
+                .with_context(|| format!("Failed to write secret `{id}`"))?;
+            use std::os::unix::fs::PermissionsExt;
+            fs::set_permissions(&staged, fs::Permissions::from_mode(*mode))
+                .with_context(|| format!("Failed to set permissions on secret `{id}`"))?;
+            chown(
+                &staged,
+                Some(nix::unistd::Uid::from_raw(*uid)),
+                Some(nix::unistd::Gid::from_raw(*gid)),
             )
-        })?;
-    }
+            .with_context(|| format!("Failed to set ownership on secret `{id}`"))?;
 
+            let dst = create_mount_target(mountpoint, target, false, state)?;
+            bind_mount_path(&staged, &dst, true)?;
+            state.mounted.push(dst);
+        }
+        // Secret exposed only as an environment variable.
+        RunMount::Secret { target: None, .. } => {}
+        RunMount::Ssh {
+            id,
+            socket_path,
+            target,
+        } => {
+            let dst = create_mount_target(mountpoint, target, false, state)?;
+            bind_mount_path(socket_path, &dst, false)
+                .with_context(|| format!("Failed to mount ssh agent `{id}`"))?;
+            state.mounted.push(dst);
+        }
+    }
     Ok(())
 }
 
-fn umount_and_remove_dir(path: &Path, created_marker_name: &str) -> Result<()> {
-    umount_if_mounted(path)?;
-    let marker = runtime_temp_file(created_marker_name);
-    if marker.exists() {
+/// Set up the `RUN --mount` entries of a single RUN step.
+///
+/// Everything done here is recorded and undone by `cleanup` before the layer is
+/// committed, so neither the mounted content nor the created mount targets end up
+/// in the image.
+pub fn prepare_run_mounts<P: AsRef<Path>>(mountpoint: P, mounts: &[RunMount]) -> Result<()> {
+    if mounts.is_empty() {
+        return Ok(());
+    }
+
+    let mountpoint = mountpoint.as_ref();
+    let mut state = RunMountState::default();
+    let result = mounts
+        .iter()
+        .enumerate()
+        .try_for_each(|(index, mount)| prepare_run_mount(mountpoint, index, mount, &mut state));
+
+    // Record the state even on failure so cleanup can undo a partial setup.
+    let state_file = runtime_temp_file(RUN_MOUNTS_STATE);
+    let state_json =
+        serde_json::to_vec(&state).context("Failed to serialize run mount state to json")?;
+    fs::write(&state_file, state_json)
+        .with_context(|| format!("Failed to write {}", state_file.display()))?;
+    result
+}
+
+fn cleanup_run_mounts() -> Result<()> {
+    let state_file = runtime_temp_file(RUN_MOUNTS_STATE);
+    if !state_file.exists() {
+        return Ok(());
+    }
+    let state_json = fs::read(&state_file)
+        .with_context(|| format!("Failed to read {}", state_file.display()))?;
+    let state: RunMountState = serde_json::from_slice(&state_json)
+        .context("Failed to deserialize run mount state from json")?;
+
+    for path in state.mounted.iter().rev() {
+        umount_if_mounted(path)?;
+    }
+    for path in state.created.iter().rev() {
+        // Directories the command wrote into are kept, they belong to the layer now.
+        let removed = if path.is_dir() {
+            fs::remove_dir(path)
+        } else {
+            fs::remove_file(path)
+        };
+        if let Err(err) = removed {
+            tracing::debug!(
+                error = ?err,
+                path = %path.display(),
+                "Keeping mount target during cleanup"
+            );
+        }
+    }
+    for path in &state.staging {
         if path.exists()
             && let Err(err) = fs::remove_dir_all(path)
         {
             tracing::warn!(
                 error = ?err,
                 path = %path.display(),
-                "Failed to remove runtime directory during cleanup"
+                "Failed to remove run mount staging directory during cleanup"
             );
         }
-        remove_temp_file(&marker);
     }
+    remove_temp_file(&state_file);
     Ok(())
 }
 
@@ -931,14 +1102,7 @@ fn remove_temp_file(path: &Path) {
 fn cleanup_network<P: AsRef<Path>>(mountpoint: P) -> Result<()> {
     let mountpoint = mountpoint.as_ref();
 
-    umount_and_remove_dir(
-        &mountpoint.join(RKFORGE_SSH_DIR.strip_prefix('/').unwrap()),
-        SSH_CREATED_MARKER,
-    )?;
-    umount_and_remove_dir(
-        &mountpoint.join(SECRETS_DIR.strip_prefix('/').unwrap()),
-        SECRETS_CREATED_MARKER,
-    )?;
+    cleanup_run_mounts()?;
     umount_if_mounted(&mountpoint.join(SHM_CONFIG.strip_prefix('/').unwrap()))?;
     umount_if_mounted(&mountpoint.join(HOSTS_CONFIG.strip_prefix('/').unwrap()))?;
     umount_if_mounted(&mountpoint.join(DNS_CONFIG.strip_prefix('/').unwrap()))?;
@@ -1,5 +1,5 @@
 use crate::pull::downloader::LayerDownloadWrapper;
-use crate::pull::media::get_media_type;
+use crate::pull::media::get_layer_media_type;
 use crate::storage::{DigestExt, ultimate_blob_path};
 use anyhow::Context;
 use indicatif::{ProgressBar, ProgressStyle};
@@ -140,7 +140,7 @@ async fn unpack_layer(
     src: impl AsRef<Path>,
     dst: impl AsRef<Path>,
 ) -> anyhow::Result<()> {
-    let media_type = get_media_type(&descriptor.media_type);
+    let media_type = get_layer_media_type(descriptor);
 
     let src = src.as_ref().to_owned();
     let dst = dst.as_ref().to_owned();
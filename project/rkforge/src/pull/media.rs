@@ -1,11 +1,23 @@
+use crate::compressor::seekable_compressor::{ESTARGZ_TOC_DIGEST_ANNOTATION, ESTARGZ_TOC_NAME};
+use oci_client::manifest::OciDescriptor;
+use std::io::Read;
 use std::path::Path;
 
 pub enum MediaType {
     Tar,
     TarGzip,
+    /// Seekable gzip layer; unpacked like `TarGzip`, minus the TOC entry.
+    Estargz,
+    TarZstd,
     Other,
 }
 
+fn unpack_tar(reader: impl Read, dst: &Path) -> anyhow::Result<()> {
+    let mut archive = tar::Archive::new(reader);
+    archive.unpack(dst)?;
+    Ok(())
+}
+
 impl MediaType {
     pub fn unpack(&self, src: impl AsRef<Path>, dst: impl AsRef<Path>) -> anyhow::Result<()> {
         let src = src.as_ref();
@@ -13,15 +25,24 @@ impl MediaType {
 
         match self {
             MediaType::Tar => {
-                let tar_gz = std::fs::File::open(src)?;
-                let mut archive = tar::Archive::new(tar_gz);
-                archive.unpack(dst)?;
+                let tar = std::fs::File::open(src)?;
+                unpack_tar(tar, dst)?;
             }
             MediaType::TarGzip => {
+                // eStargz and other multi-member gzip streams are valid `tar+gzip` layers.
                 let tar_gz = std::fs::File::open(src)?;
-                let decompressor = flate2::read::GzDecoder::new(tar_gz);
-                let mut archive = tar::Archive::new(decompressor);
-                archive.unpack(dst)?;
+                unpack_tar(flate2::read::MultiGzDecoder::new(tar_gz), dst)?;
+            }
+            MediaType::Estargz => {
+                MediaType::TarGzip.unpack(src, dst)?;
+                let toc = dst.join(ESTARGZ_TOC_NAME);
+                if toc.exists() {
+                    std::fs::remove_file(toc)?;
+                }
+            }
+            MediaType::TarZstd => {
+                let tar_zst = std::fs::File::open(src)?;
+                unpack_tar(zstd::Decoder::new(tar_zst)?, dst)?;
             }
             MediaType::Other => {
                 std::fs::copy(src, dst)?;
@@ -35,8 +56,49 @@ pub fn get_media_type(media_type: &str) -> MediaType {
     if media_type.ends_with("tar+gzip") {
         return MediaType::TarGzip;
     }
+    if media_type.ends_with("tar+zstd") {
+        return MediaType::TarZstd;
+    }
     if media_type.ends_with("tar") {
         return MediaType::Tar;
     }
     MediaType::Other
 }
+
+/// Like [`get_media_type`], but also recognises seekable layers by their annotations.
+pub fn get_layer_media_type(descriptor: &OciDescriptor) -> MediaType {
+    let media_type = get_media_type(&descriptor.media_type);
+    let is_estargz = descriptor
+        .annotations
+        .as_ref()
+        .is_some_and(|annotations| annotations.contains_key(ESTARGZ_TOC_DIGEST_ANNOTATION));
+    match media_type {
+        MediaType::TarGzip if is_estargz => MediaType::Estargz,
+        media_type => media_type,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{MediaType, get_media_type};
+
+    #[test]
+    fn test_get_media_type() {
+        assert!(matches!(
+            get_media_type("application/vnd.oci.image.layer.v1.tar+gzip"),
+            MediaType::TarGzip
+        ));
+        assert!(matches!(
+            get_media_type("application/vnd.oci.image.layer.v1.tar+zstd"),
+            MediaType::TarZstd
+        ));
+        assert!(matches!(
+            get_media_type("application/vnd.docker.image.rootfs.diff.tar"),
+            MediaType::Tar
+        ));
+        assert!(matches!(
+            get_media_type("application/vnd.oci.image.config.v1+json"),
+            MediaType::Other
+        ));
+    }
+}
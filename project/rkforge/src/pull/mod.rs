@@ -3,6 +3,7 @@ mod layer;
 pub mod media;
 
 use crate::config::auth::AuthConfig;
+use crate::image::platform::{Platform, parse_platform_arg};
 use crate::pull::layer::pull_layers;
 use crate::registry::{parse_registry_host_arg, resolve_client_ref_auth as resolve_ref_with_auth};
 use crate::storage::write_manifest;
@@ -10,7 +11,7 @@ use anyhow::Context;
 use anyhow::anyhow;
 use clap::Parser;
 use oci_client::Client;
-use oci_client::manifest::OciManifest;
+use oci_client::manifest::{OciImageManifest, OciManifest};
 use oci_client::secrets::RegistryAuth;
 use oci_spec::distribution::Reference;
 use std::path::PathBuf;
@@ -30,6 +31,9 @@ pub struct PullArgs {
     /// Skip TLS certificate verification for HTTPS registry.
     #[arg(long)]
     skip_tls_verify: bool,
+    /// Platform to pull when the image is multi-platform (format: "os/arch[/variant]"). Defaults to the host platform.
+    #[arg(long, value_name = "OS/ARCH[/VARIANT]", value_parser = parse_platform_arg)]
+    platform: Option<Platform>,
 }
 
 pub fn pull(args: PullArgs) -> anyhow::Result<()> {
@@ -39,6 +43,7 @@ pub fn pull(args: PullArgs) -> anyhow::Result<()> {
         false,
         false,
         args.skip_tls_verify,
+        args.platform.unwrap_or_else(Platform::host),
     )?;
     Ok(())
 }
@@ -48,7 +53,14 @@ pub fn sync_pull_or_get_image_with_policy(
     url: Option<impl AsRef<str>>,
     no_cache: bool,
 ) -> anyhow::Result<(PathBuf, Vec<PathBuf>)> {
-    sync_pull_or_get_image_with_policy_and_output_with_tls(image_ref, url, no_cache, false, false)
+    sync_pull_or_get_image_with_policy_and_output_with_tls(
+        image_ref,
+        url,
+        no_cache,
+        false,
+        false,
+        Platform::host(),
+    )
 }
 
 pub fn sync_pull_or_get_image_with_policy_and_output(
@@ -57,7 +69,32 @@ pub fn sync_pull_or_get_image_with_policy_and_output(
     no_cache: bool,
     quiet: bool,
 ) -> anyhow::Result<(PathBuf, Vec<PathBuf>)> {
-    sync_pull_or_get_image_with_policy_and_output_with_tls(image_ref, url, no_cache, quiet, false)
+    sync_pull_or_get_image_with_policy_and_output_with_tls(
+        image_ref,
+        url,
+        no_cache,
+        quiet,
+        false,
+        Platform::host(),
+    )
+}
+
+/// Like [`sync_pull_or_get_image_with_policy_and_output`], but selects the manifest for
+/// `platform` when the image is multi-platform.
+pub fn sync_pull_or_get_image_for_platform(
+    image_ref: impl AsRef<str>,
+    platform: &Platform,
+    no_cache: bool,
+    quiet: bool,
+) -> anyhow::Result<(PathBuf, Vec<PathBuf>)> {
+    sync_pull_or_get_image_with_policy_and_output_with_tls(
+        image_ref,
+        None::<String>,
+        no_cache,
+        quiet,
+        false,
+        platform.clone(),
+    )
 }
 
 fn sync_pull_or_get_image_with_policy_and_output_with_tls(
@@ -66,25 +103,19 @@ fn sync_pull_or_get_image_with_policy_and_output_with_tls(
     no_cache: bool,
     quiet: bool,
     skip_tls_verify: bool,
+    platform: Platform,
 ) -> anyhow::Result<(PathBuf, Vec<PathBuf>)> {
     let image_ref = image_ref.as_ref();
     let url = url.map(|u| u.as_ref().to_string());
     let (client, image_ref, auth_method) =
         resolve_client_ref_auth(image_ref, url, skip_tls_verify)?;
     let do_pull = async move {
-        let (manifest, digest) = client
-            .pull_manifest(&image_ref, &auth_method)
-            .await
-            .map_err(|e| anyhow!("Failed to pull manifest: {e}"))?;
-
-        let layers = match &manifest {
-            OciManifest::Image(manifest) => {
-                pull_layers(&client, &image_ref, manifest, no_cache, quiet).await
-            }
-            OciManifest::ImageIndex(_) => anyhow::bail!("Image indexes are not supported yet"),
-        }?;
-
-        let manifest_path = write_manifest(&image_ref, &manifest, &digest).await?;
+        let (manifest, digest) =
+            pull_platform_manifest(&client, &image_ref, &auth_method, &platform).await?;
+        let layers = pull_layers(&client, &image_ref, &manifest, no_cache, quiet).await?;
+
+        let manifest_path =
+            write_manifest(&image_ref, &OciManifest::Image(manifest), &digest).await?;
         Ok((manifest_path, layers))
     };
     match Handle::try_current() {
@@ -160,20 +191,59 @@ async fn pull_or_get_image_with_policy_and_tls(
     let url = url.map(|u| u.as_ref().to_string());
     let (client, image_ref, auth_method) =
         resolve_client_ref_auth(image_ref, url, skip_tls_verify)?;
+    let (manifest, digest) =
+        pull_platform_manifest(&client, &image_ref, &auth_method, &Platform::host()).await?;
+    let layers = pull_layers(&client, &image_ref, &manifest, no_cache, false).await?;
+
+    let manifest_path = write_manifest(&image_ref, &OciManifest::Image(manifest), &digest).await?;
+    Ok((manifest_path, layers))
+}
+
+/// Fetch the image manifest of `image_ref`, following an image index to the
+/// manifest that matches `platform`.
+async fn pull_platform_manifest(
+    client: &Client,
+    image_ref: &Reference,
+    auth_method: &RegistryAuth,
+    platform: &Platform,
+) -> anyhow::Result<(OciImageManifest, String)> {
     let (manifest, digest) = client
-        .pull_manifest(&image_ref, &auth_method)
+        .pull_manifest(image_ref, auth_method)
         .await
-        .with_context(|| "Failed to pull manifest")?;
+        .map_err(|e| anyhow!("Failed to pull manifest: {e}"))?;
+    let index = match manifest {
+        OciManifest::Image(manifest) => return Ok((manifest, digest)),
+        OciManifest::ImageIndex(index) => index,
+    };
 
-    let layers = match &manifest {
-        OciManifest::Image(manifest) => {
-            pull_layers(&client, &image_ref, manifest, no_cache, false).await
+    let entry = platform.select_manifest(&index.manifests).with_context(|| {
+        let available = index
+            .manifests
+            .iter()
+            .filter_map(|entry| entry.platform.as_ref())
+            .map(|p| Platform::new(&p.os, &p.architecture, p.variant.as_deref()).to_string())
+            .collect::<Vec<_>>()
+            .join(", ");
+        format!("No manifest for platform {platform} in {image_ref}. Available platforms: {available}")
+    })?;
+    let platform_ref = Reference::with_digest(
+        image_ref.registry().to_string(),
+        image_ref.repository().to_string(),
+        entry.digest.clone(),
+    );
+    let (manifest, digest) = client
+        .pull_manifest(&platform_ref, auth_method)
+        .await
+        .map_err(|e| anyhow!("Failed to pull manifest for platform {platform}: {e}"))?;
+    match manifest {
+        OciManifest::Image(manifest) => Ok((manifest, digest)),
+        OciManifest::ImageIndex(_) => {
+            anyhow::bail!(
+                "Manifest {} for platform {platform} is an image index",
+                entry.digest
+            )
         }
-        OciManifest::ImageIndex(_) => anyhow::bail!("Image indexes are not supported yet"),
-    }?;
-
-    let manifest_path = write_manifest(&image_ref, &manifest, &digest).await?;
-    Ok((manifest_path, layers))
+    }
 }
 
 fn resolve_client_ref_auth(
@@ -0,0 +1,108 @@
+use crate::compressor::seekable_compressor::{
+    ESTARGZ_TOC_DIGEST_ANNOTATION, ESTARGZ_UNCOMPRESSED_SIZE_ANNOTATION,
+    ZSTD_CHUNKED_MANIFEST_CHECKSUM_ANNOTATION, ZSTD_CHUNKED_MANIFEST_POSITION_ANNOTATION,
+    read_estargz_toc_offset,
+};
+use crate::compressor::{LayerCompression, LayerCompressionResult, LayerCompressor};
+use crate::images::{read_config_diff_ids, sha256_of_bytes, write_config_diff_ids};
+use crate::pull::media::{MediaType, get_layer_media_type};
+use crate::storage::DigestExt;
+use anyhow::{Context, Result, bail};
+use flate2::read::MultiGzDecoder;
+use oci_client::manifest::{OciDescriptor, OciImageManifest};
+use serde_json::Value;
+use std::fs::File;
+use std::io::{self, BufReader, Read, Seek, SeekFrom};
+use std::path::Path;
+
+/// Annotations that describe the layout of a seekable blob and must not survive a
+/// conversion to another format.
+const FORMAT_ANNOTATIONS: [&str; 4] = [
+    ESTARGZ_TOC_DIGEST_ANNOTATION,
+    ESTARGZ_UNCOMPRESSED_SIZE_ANNOTATION,
+    ZSTD_CHUNKED_MANIFEST_CHECKSUM_ANNOTATION,
+    ZSTD_CHUNKED_MANIFEST_POSITION_ANNOTATION,
+];
+
+/// Recompress every layer of `manifest` that is not in `compression` yet.
+///
+/// New blobs are written next to the existing ones in `blobs_dir`. Seekable formats
+/// change the uncompressed tar (they append a TOC), so the image config is rewritten
+/// as well whenever a diff id changes. The returned manifest is the one to push.
+pub(super) fn convert_layers(
+    blobs_dir: &Path,
+    mut manifest: OciImageManifest,
+    compression: LayerCompression,
+) -> Result<OciImageManifest> {
+    let config_path = blobs_dir.join(manifest.config.digest.split_digest()?);
+    let config_content = std::fs::read(&config_path)
+        .with_context(|| format!("Failed to read from {}", config_path.display()))?;
+    let mut config_json: Value =
+        serde_json::from_slice(&config_content).context("Failed to parse image config blob")?;
+    let original_diff_ids = read_config_diff_ids(&config_json)?;
+    if original_diff_ids.len() != manifest.layers.len() {
+        bail!(
+            "Image config rootfs.diff_ids length ({}) does not match layer count ({})",
+            original_diff_ids.len(),
+            manifest.layers.len()
+        );
+    }
+
+    let compressor = compression.compressor();
+    let mut diff_ids = original_diff_ids.clone();
+    for (layer, diff_id) in manifest.layers.iter_mut().zip(diff_ids.iter_mut()) {
+        if compression.matches(&layer.media_type, layer.annotations.as_ref()) {
+            continue;
+        }
+        tracing::info!("Converting layer {} to {compression:?}", layer.digest);
+        let result = convert_layer(blobs_dir, layer, compressor.as_ref())
+            .with_context(|| format!("Failed to convert layer {}", layer.digest))?;
+
+        let mut annotations = layer.annotations.take().unwrap_or_default();
+        annotations.retain(|key, _| !FORMAT_ANNOTATIONS.contains(&key.as_str()));
+        annotations.extend(result.annotations);
+        layer.annotations = (!annotations.is_empty()).then_some(annotations);
+        layer.media_type = result.media_type.to_string();
+        layer.digest = format!("sha256:{}", result.blob_sha256sum);
+        layer.size = result.blob_size as i64;
+        *diff_id = format!("sha256:{}", result.tar_sha256sum);
+    }
+
+    if diff_ids != original_diff_ids {
+        write_config_diff_ids(&mut config_json, &diff_ids)?;
+        let config_content = serde_json::to_vec_pretty(&config_json)?;
+        let config_hash = sha256_of_bytes(&config_content);
+        std::fs::write(blobs_dir.join(&config_hash), &config_content)
+            .context("Failed to write updated config blob")?;
+        manifest.config.digest = format!("sha256:{config_hash}");
+        manifest.config.size = config_content.len() as i64;
+    }
+    Ok(manifest)
+}
+
+/// Decompress a single layer blob and feed its tar stream to `compressor`.
+fn convert_layer(
+    blobs_dir: &Path,
+    layer: &OciDescriptor,
+    compressor: &(dyn LayerCompressor + Send + Sync),
+) -> Result<LayerCompressionResult> {
+    let blob_path = blobs_dir.join(layer.digest.split_digest()?);
+    let mut blob = File::open(&blob_path)
+        .with_context(|| format!("Failed to open {}", blob_path.display()))?;
+
+    let mut tar: Box<dyn Read> = match get_layer_media_type(layer) {
+        MediaType::Tar => Box::new(BufReader::new(blob)),
+        MediaType::TarGzip => Box::new(MultiGzDecoder::new(BufReader::new(blob))),
+        MediaType::Estargz => {
+            // Only decompress the members before the TOC so that `stargz.index.json`
+            // does not end up as a regular file in the converted layer.
+            let toc_offset = read_estargz_toc_offset(&mut blob)?;
+            blob.seek(SeekFrom::Start(0))?;
+            let content = MultiGzDecoder::new(BufReader::new(blob.take(toc_offset)));
+            Box::new(content.chain(io::repeat(0).take(1024)))
+        }
+        MediaType::TarZstd => Box::new(zstd::Decoder::new(blob)?),
+        MediaType::Other => bail!("Unsupported layer media type {}", layer.media_type),
+    };
+    compressor.compress_tar(&mut tar, blobs_dir)
+}
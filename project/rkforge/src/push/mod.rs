@@ -1,6 +1,10 @@
+mod convert;
 mod pusher;
 
+use crate::compressor::LayerCompression;
 use crate::config::auth::AuthConfig;
+use crate::images::sha256_of_bytes;
+use crate::push::convert::convert_layers;
 use crate::push::pusher::{PushTask, Pusher};
 use crate::registry::{
     parse_registry_host, parse_registry_host_arg, resolve_client_ref_auth as resolve_ref_with_auth,
@@ -10,7 +14,7 @@ use crate::storage::{DigestExt, parse_image_ref};
 use anyhow::{Context, bail};
 use clap::Parser;
 use oci_client::client::ImageLayer;
-use oci_client::manifest::{OciImageIndex, OciManifest};
+use oci_client::manifest::{OciImageIndex, OciImageManifest, OciManifest};
 use oci_client::secrets::RegistryAuth;
 use oci_client::{Client, client};
 use oci_spec::distribution::Reference;
@@ -45,11 +49,20 @@ pub struct PushArgs {
     /// Skip TLS certificate verification for HTTPS registry.
     #[arg(long)]
     skip_tls_verify: bool,
+    /// Recompress layers before pushing (gzip, zstd, estargz, zstd-chunked). Layers already in that format are pushed as-is.
+    #[arg(long, value_enum)]
+    compression: Option<LayerCompression>,
 }
 
 pub fn push(args: PushArgs) -> anyhow::Result<()> {
     let path = args.path.unwrap_or(".".to_string());
-    push_from_layout_with_tls(args.image_ref, path, args.url, args.skip_tls_verify)
+    push_from_layout_with_tls(
+        args.image_ref,
+        path,
+        args.url,
+        args.skip_tls_verify,
+        args.compression,
+    )
 }
 
 pub fn push_from_layout(
@@ -57,7 +70,7 @@ pub fn push_from_layout(
     path: impl AsRef<Path>,
     url: Option<String>,
 ) -> anyhow::Result<()> {
-    push_from_layout_with_tls(image_ref, path, url, false)
+    push_from_layout_with_tls(image_ref, path, url, false, None)
 }
 
 fn push_from_layout_with_tls(
@@ -65,6 +78,7 @@ fn push_from_layout_with_tls(
     path: impl AsRef<Path>,
     url: Option<String>,
     skip_tls_verify: bool,
+    compression: Option<LayerCompression>,
 ) -> anyhow::Result<()> {
     let image_ref = image_ref.into();
     let path = path.as_ref().to_path_buf();
@@ -101,6 +115,7 @@ fn push_from_layout_with_tls(
             &registry_url,
             &requested_repo,
             requested_has_explicit_tag,
+            compression,
         )
         .await
     })?
@@ -114,6 +129,7 @@ pub async fn push_image(
     registry_url: impl AsRef<str>,
     requested_repo: impl AsRef<str>,
     requested_has_explicit_tag: bool,
+    compression: Option<LayerCompression>,
 ) -> anyhow::Result<()> {
     let dir = path.as_ref();
     let registry_url = registry_url.as_ref();
@@ -150,6 +166,7 @@ pub async fn push_image(
     }
 
     let mut tasks = Vec::new();
+    let mut index_pushes = Vec::new();
     let mut matched_requested_tag = false;
     for (digest, ref_names) in digest_to_ref_names {
         if !should_include_digest(requested_tag.as_deref(), &ref_names) {
@@ -159,17 +176,6 @@ pub async fn push_image(
             matched_requested_tag = true;
         }
 
-        let manifest_path = dir.join(&digest);
-        let manifest = serde_json::from_str::<OciManifest>(
-            &tokio::fs::read_to_string(&manifest_path)
-                .await
-                .with_context(|| format!("Failed to read from {}", manifest_path.display()))?,
-        )?;
-        let manifest = match manifest {
-            OciManifest::Image(manifest) => manifest,
-            OciManifest::ImageIndex(_) => anyhow::bail!("Image indexes are not supported yet"),
-        };
-
         let target_refs = if requested_tag.is_some() {
             vec![image_ref.clone()]
         } else {
@@ -181,32 +187,52 @@ pub async fn push_image(
                 .collect::<anyhow::Result<Vec<_>>>()?
         };
 
-        for target_ref in target_refs {
-            let descriptors = &manifest.layers;
-            let mut layers = Vec::new();
-
-            for descriptor in descriptors {
-                let layer_path = dir.join(descriptor.digest.split_digest()?);
-                let layer = from_oci_blob!(ImageLayer, layer_path, descriptor);
-                layers.push(layer);
+        match read_manifest(&dir, &digest).await? {
+            OciManifest::Image(manifest) => {
+                let manifest = convert_manifest(&dir, manifest, compression).await?;
+                for target_ref in target_refs {
+                    let label = format!("{digest}@{}", target_ref.whole());
+                    tasks.push(
+                        image_push_task(client, auth_method, &dir, target_ref, &manifest, label)
+                            .await?,
+                    );
+                }
+            }
+            OciManifest::ImageIndex(mut index) => {
+                // Platform manifests are pushed by digest; the index itself can only be
+                // pushed under the tags once they all exist in the registry.
+                let Some(repo_ref) = target_refs.first() else {
+                    continue;
+                };
+                for entry in &mut index.manifests {
+                    let manifest = match read_manifest(&dir, entry.digest.split_digest()?).await? {
+                        OciManifest::Image(manifest) => manifest,
+                        OciManifest::ImageIndex(_) => {
+                            bail!("Nested image index {} is not supported", entry.digest)
+                        }
+                    };
+                    let manifest = convert_manifest(&dir, manifest, compression).await?;
+                    let (manifest_digest, manifest_size) =
+                        pushed_manifest_digest(&OciManifest::Image(manifest.clone()))?;
+                    let platform_ref = Reference::with_digest(
+                        repo_ref.registry().to_string(),
+                        repo_ref.repository().to_string(),
+                        manifest_digest.clone(),
+                    );
+                    let label = format!("{manifest_digest}@{}", repo_ref.whole());
+                    tasks.push(
+                        image_push_task(client, auth_method, &dir, platform_ref, &manifest, label)
+                            .await?,
+                    );
+                    entry.digest = manifest_digest;
+                    entry.size = manifest_size;
+                }
+                index_pushes.extend(
+                    target_refs
+                        .into_iter()
+                        .map(|target_ref| (target_ref, index.clone())),
+                );
             }
-
-            let config_path = dir.join(manifest.config.digest.split_digest()?);
-            let config = from_oci_blob!(client::Config, config_path, manifest.config);
-
-            let auth_method = auth_method.clone();
-            let client = client.clone();
-            let digest_with_ref = format!("{digest}@{}", target_ref.whole());
-            let manifest = manifest.clone();
-            let task = PushTask::new(
-                digest_with_ref,
-                Box::pin(async move {
-                    client
-                        .push(&target_ref, &layers, config, &auth_method, Some(manifest))
-                        .await
-                }),
-            );
-            tasks.push(task);
         }
     }
 
@@ -218,9 +244,83 @@ pub async fn push_image(
 
     let pusher = Pusher::new(tasks);
     pusher.push_all().await?;
+
+    for (target_ref, index) in index_pushes {
+        client
+            .push_manifest_list(&target_ref, auth_method, index)
+            .await
+            .with_context(|| format!("Failed to push image index {}", target_ref.whole()))?;
+    }
     Ok(())
 }
 
+async fn read_manifest(dir: &Path, digest: &str) -> anyhow::Result<OciManifest> {
+    let manifest_path = dir.join(digest);
+    Ok(serde_json::from_str::<OciManifest>(
+        &tokio::fs::read_to_string(&manifest_path)
+            .await
+            .with_context(|| format!("Failed to read from {}", manifest_path.display()))?,
+    )?)
+}
+
+async fn convert_manifest(
+    dir: &Path,
+    manifest: OciImageManifest,
+    compression: Option<LayerCompression>,
+) -> anyhow::Result<OciImageManifest> {
+    match compression {
+        Some(compression) => {
+            let blobs_dir = dir.to_path_buf();
+            tokio::task::spawn_blocking(move || convert_layers(&blobs_dir, manifest, compression))
+                .await?
+        }
+        None => Ok(manifest),
+    }
+}
+
+async fn image_push_task(
+    client: &Client,
+    auth_method: &RegistryAuth,
+    dir: &Path,
+    target_ref: Reference,
+    manifest: &OciImageManifest,
+    label: String,
+) -> anyhow::Result<PushTask> {
+    let mut layers = Vec::new();
+    for descriptor in &manifest.layers {
+        let layer_path = dir.join(descriptor.digest.split_digest()?);
+        let layer = from_oci_blob!(ImageLayer, layer_path, descriptor);
+        layers.push(layer);
+    }
+
+    let config_path = dir.join(manifest.config.digest.split_digest()?);
+    let config = from_oci_blob!(client::Config, config_path, manifest.config);
+
+    let auth_method = auth_method.clone();
+    let client = client.clone();
+    let manifest = manifest.clone();
+    Ok(PushTask::new(
+        label,
+        Box::pin(async move {
+            client
+                .push(&target_ref, &layers, config, &auth_method, Some(manifest))
+                .await
+        }),
+    ))
+}
+
+/// Digest and size of a manifest as the registry will see it.
+///
+/// oci-client uploads manifests as canonical JSON (compact, keys sorted), which
+/// is what `serde_json` produces for a `Value` without `preserve_order`.
+fn pushed_manifest_digest(manifest: &OciManifest) -> anyhow::Result<(String, i64)> {
+    let body = serde_json::to_vec(&serde_json::to_value(manifest)?)?;
+    Ok((
+        format!("sha256:{}", sha256_of_bytes(&body)),
+        body.len() as i64,
+    ))
+}
+
 fn should_include_digest(requested_tag: Option<&str>, ref_names: &[String]) -> bool {
     match requested_tag {
         Some(tag) => ref_names.iter().any(|ref_name| ref_name == tag),
@@ -1,9 +1,8 @@
 use crate::image::build_runtime::{
-    BuildHostEntry, BuildNetworkMode, BuildSecret, BuildSshAgent, BuildUlimit,
-    normalize_cgroup_parent,
+    BuildHostEntry, BuildNetworkMode, BuildUlimit, RunMount, normalize_cgroup_parent,
 };
 use crate::overlayfs::{
-    bind_mount, do_exec, prepare_hosts, prepare_network, prepare_secrets, prepare_shm, prepare_ssh,
+    bind_mount, do_exec, prepare_hosts, prepare_network, prepare_run_mounts, prepare_shm,
     switch_namespace,
 };
 use anyhow::{Context, Result, bail};
@@ -41,9 +40,7 @@ pub struct ExecInternalArgs {
     #[arg(long)]
     pub cgroup_parent: Option<String>,
     #[arg(long)]
-    pub secrets_base64: Option<String>,
-    #[arg(long)]
-    pub ssh_base64: Option<String>,
+    pub mounts_base64: Option<String>,
 }
 
 fn decode_optional_base64_json<T>(value: Option<&str>, arg_name: &str) -> Result<T>
@@ -76,6 +73,30 @@ fn apply_cgroup_parent(cgroup_parent: Option<&str>) -> Result<()> {
     Ok(())
 }
 
+/// Environment entries for secrets mounted with `env=<NAME>`.
+///
+/// Secrets are read here, inside the build container setup, so their values never
+/// appear on the command line of the parent build process.
+fn secret_envs(mounts: &[RunMount]) -> Result<Vec<String>> {
+    mounts
+        .iter()
+        .filter_map(|mount| match mount {
+            RunMount::Secret {
+                id,
+                src,
+                env: Some(env),
+                ..
+            } => Some((id, src, env)),
+            _ => None,
+        })
+        .map(|(id, src, env)| {
+            let value = fs::read_to_string(src)
+                .with_context(|| format!("Failed to read secret `{id}` from {}", src.display()))?;
+            Ok(format!("{env}={}", value.trim_end_matches(['\r', '\n'])))
+        })
+        .collect()
+}
+
 fn apply_network_mode(network_mode: BuildNetworkMode) -> Result<()> {
     if network_mode == BuildNetworkMode::None {
         nix::sched::unshare(CloneFlags::CLONE_NEWNET)
@@ -96,10 +117,8 @@ pub fn exec_internal(args: ExecInternalArgs) -> Result<()> {
     let ulimits: Vec<BuildUlimit> =
         decode_optional_base64_json(args.ulimits_base64.as_deref(), "ulimits-base64")?;
 
-    let secrets: Vec<BuildSecret> =
-        decode_optional_base64_json(args.secrets_base64.as_deref(), "secrets-base64")?;
-    let ssh_agents: Vec<BuildSshAgent> =
-        decode_optional_base64_json(args.ssh_base64.as_deref(), "ssh-base64")?;
+    let mounts: Vec<RunMount> =
+        decode_optional_base64_json(args.mounts_base64.as_deref(), "mounts-base64")?;
 
     bind_mount(mountpoint)?;
     if matches!(
@@ -110,8 +129,7 @@ pub fn exec_internal(args: ExecInternalArgs) -> Result<()> {
     }
     prepare_hosts(mountpoint, &add_hosts)?;
     prepare_shm(mountpoint, args.shm_size)?;
-    prepare_secrets(mountpoint, &secrets)?;
-    prepare_ssh(mountpoint, &ssh_agents)?;
+    prepare_run_mounts(mountpoint, &mounts)?;
     apply_cgroup_parent(args.cgroup_parent.as_deref())?;
 
     let commands_json = general_purpose::STANDARD
@@ -127,8 +145,9 @@ pub fn exec_internal(args: ExecInternalArgs) -> Result<()> {
     let envp_json = general_purpose::STANDARD
         .decode(&args.envp_base64)
         .context("Failed to decode envp from base64")?;
-    let envp: Vec<String> =
+    let mut envp: Vec<String> =
         serde_json::from_slice(&envp_json).context("Failed to deserialize envp from json")?;
+    envp.extend(secret_envs(&mounts)?);
     let envp = envp
         .iter()
         .map(|s| CString::new(s.as_bytes()).context("Environment variable contains null byte"))
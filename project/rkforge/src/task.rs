@@ -1,6 +1,4 @@
-use crate::image::build_runtime::{
-    BuildHostEntry, BuildNetworkMode, BuildSecret, BuildSshAgent, BuildUlimit,
-};
+use crate::image::build_runtime::{BuildHostEntry, BuildNetworkMode, BuildUlimit, RunMount};
 use crate::overlayfs::MountConfig;
 use anyhow::{Context, Result};
 use base64::{Engine, engine::general_purpose};
@@ -33,16 +31,16 @@ pub struct RunTask {
     pub network_mode: BuildNetworkMode,
     /// Optional cgroup parent for build-time RUN containers.
     pub cgroup_parent: Option<String>,
-    /// Build-time secrets mounted into /run/secrets/<id>.
-    pub secrets: Vec<BuildSecret>,
-    /// SSH agent sockets mounted into /run/rkforge/ssh/ssh_agent.<N>.
-    pub ssh: Vec<BuildSshAgent>,
+    /// `RUN --mount` entries, set up only for this command.
+    pub mounts: Vec<RunMount>,
 }
 
 #[derive(Debug, Serialize, Deserialize, Clone)]
 pub struct CopyTask {
     pub src: Vec<PathBuf>,
     pub dest: PathBuf,
+    /// Local archives unpacked into `dest` instead of being copied (ADD only).
+    pub extract: Vec<PathBuf>,
     /// Suppress stdout while keeping stderr visible.
     pub quiet: bool,
 }
@@ -107,23 +105,16 @@ impl TaskExec for RunTask {
                 .context("Failed to serialize ulimits to json")?;
             Some(general_purpose::STANDARD.encode(ulimits_json))
         };
-        let secrets_base64 = if self.secrets.is_empty() {
-            None
-        } else {
-            let secrets_json = serde_json::to_string(&self.secrets)
-                .context("Failed to serialize secrets to json")?;
-            Some(general_purpose::STANDARD.encode(secrets_json))
-        };
-        let ssh_base64 = if self.ssh.is_empty() {
+        let mounts_base64 = if self.mounts.is_empty() {
             None
         } else {
-            let ssh_json = serde_json::to_string(&self.ssh)
-                .context("Failed to serialize ssh agents to json")?;
-            Some(general_purpose::STANDARD.encode(ssh_json))
+            let mounts_json = serde_json::to_string(&self.mounts)
+                .context("Failed to serialize run mounts to json")?;
+            Some(general_purpose::STANDARD.encode(mounts_json))
         };
 
         trace!(
-            "Run commands: {:?}, envp: {:?}, working_dir: {:?}, user: {:?}, add_hosts: {:?}, shm_size: {:?}, ulimits: {:?}, secrets: {} ssh: {}",
+            "Run commands: {:?}, envp: {:?}, working_dir: {:?}, user: {:?}, add_hosts: {:?}, shm_size: {:?}, ulimits: {:?}, mounts: {}",
             self.commands,
             self.envp,
             self.working_dir,
@@ -131,8 +122,7 @@ impl TaskExec for RunTask {
             self.add_hosts,
             self.shm_size,
             self.ulimits,
-            self.secrets.len(),
-            self.ssh.len()
+            self.mounts.len()
         );
         command
             .arg("--mountpoint")
@@ -167,11 +157,8 @@ impl TaskExec for RunTask {
         if let Some(cgroup_parent) = &self.cgroup_parent {
             command.arg("--cgroup-parent").arg(cgroup_parent);
         }
-        if let Some(secrets_base64) = &secrets_base64 {
-            command.arg("--secrets-base64").arg(secrets_base64);
-        }
-        if let Some(ssh_base64) = &ssh_base64 {
-            command.arg("--ssh-base64").arg(ssh_base64);
+        if let Some(mounts_base64) = &mounts_base64 {
+            command.arg("--mounts-base64").arg(mounts_base64);
         }
 
         if self.quiet {
@@ -193,6 +180,9 @@ impl TaskExec for CopyTask {
         for src in &self.src {
             command.arg("--src").arg(src);
         }
+        for archive in &self.extract {
+            command.arg("--extract").arg(archive);
+        }
         trace!("Running command: {:?}", command);
         if self.quiet {
             command.stdout(Stdio::null());
@@ -31,6 +31,7 @@ use tracing::info;
 pub mod container;
 pub mod deployment;
 pub mod logs;
+pub mod networkpolicy;
 pub mod pod;
 pub mod replicaset;
 pub mod service;
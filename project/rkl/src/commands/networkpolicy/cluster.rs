@@ -0,0 +1,156 @@
+use anyhow::{Result, anyhow};
+use common::{NetworkPolicy, PolicyType, RksMessage};
+use libnetwork::network_policy::validate_network_policy;
+use std::fs::File;
+use std::io::{self, Write};
+use tabwriter::TabWriter;
+
+use crate::commands::pod::TLSConnectionArgs;
+use crate::quic::client::{Cli, QUICClient};
+
+/// Create a new NetworkPolicy
+pub async fn create_network_policy(
+    policy_yaml: &str,
+    addr: &str,
+    tls_cfg: TLSConnectionArgs,
+) -> Result<()> {
+    let cli = QUICClient::<Cli>::connect(addr, &tls_cfg).await?;
+    println!("RKL connected to RKS at {addr}");
+
+    let policy = network_policy_from_path(policy_yaml)?;
+    let policy_name = policy.metadata.name.clone();
+
+    cli.send_msg(&RksMessage::CreateNetworkPolicy(policy))
+        .await?;
+
+    match cli.fetch_msg().await? {
+        RksMessage::Ack => {
+            println!("networkpolicy/{policy_name} created");
+            Ok(())
+        }
+        RksMessage::Error(err) => Err(anyhow!("Failed to create network policy: {}", err)),
+        msg => Err(anyhow!("Unexpected response: {:?}", msg)),
+    }
+}
+
+/// Delete a NetworkPolicy by name
+pub async fn delete_network_policy(
+    policy_name: &str,
+    addr: &str,
+    tls_cfg: TLSConnectionArgs,
+) -> Result<()> {
+    let cli = QUICClient::<Cli>::connect(addr, &tls_cfg).await?;
+    println!("RKL connected to RKS at {addr}");
+
+    cli.send_msg(&RksMessage::DeleteNetworkPolicy(policy_name.to_string()))
+        .await?;
+
+    match cli.fetch_msg().await? {
+        RksMessage::Ack => {
+            println!("networkpolicy/{policy_name} deleted");
+            Ok(())
+        }
+        RksMessage::Error(err) => Err(anyhow!("Failed to delete network policy: {}", err)),
+        msg => Err(anyhow!("Unexpected response: {:?}", msg)),
+    }
+}
+
+/// Get a specific NetworkPolicy
+pub async fn get_network_policy(
+    policy_name: &str,
+    addr: &str,
+    tls_cfg: TLSConnectionArgs,
+) -> Result<()> {
+    let cli = QUICClient::<Cli>::connect(addr, &tls_cfg).await?;
+    println!("RKL connected to RKS at {addr}");
+
+    cli.send_msg(&RksMessage::GetNetworkPolicy(policy_name.to_string()))
+        .await?;
+
+    match cli.fetch_msg().await? {
+        RksMessage::GetNetworkPolicyRes(policy) => {
+            let yaml = serde_yaml::to_string(&*policy)?;
+            println!("{}", yaml);
+            Ok(())
+        }
+        RksMessage::Error(err) => Err(anyhow!("Failed to get network policy: {}", err)),
+        msg => Err(anyhow!("Unexpected response: {:?}", msg)),
+    }
+}
+
+/// List all NetworkPolicies
+pub async fn list_network_policies(addr: &str, tls_cfg: TLSConnectionArgs) -> Result<()> {
+    let cli = QUICClient::<Cli>::connect(addr, &tls_cfg).await?;
+    println!("RKL connected to RKS at {addr}");
+
+    cli.send_msg(&RksMessage::ListNetworkPolicy).await?;
+
+    match cli.fetch_msg().await? {
+        RksMessage::ListNetworkPolicyRes(policies) => list_print(policies),
+        RksMessage::Error(err) => Err(anyhow!("Failed to list network policies: {}", err)),
+        msg => Err(anyhow!("Unexpected response: {:?}", msg)),
+    }
+}
+
+fn network_policy_from_path(policy_yaml: &str) -> Result<Box<NetworkPolicy>> {
+    let policy_file = File::open(policy_yaml)
+        .map_err(|e| anyhow!("Failed to open file '{}': {}", policy_yaml, e))?;
+    let policy: NetworkPolicy =
+        serde_yaml::from_reader(policy_file).map_err(|e| anyhow!("Failed to parse YAML: {}", e))?;
+
+    if policy.metadata.name.is_empty() {
+        return Err(anyhow!("NetworkPolicy metadata.name must not be empty"));
+    }
+    validate_network_policy(&policy)?;
+
+    Ok(Box::new(policy))
+}
+
+fn list_print(policies: Vec<NetworkPolicy>) -> Result<()> {
+    let mut tab_writer = TabWriter::new(io::stdout());
+    writeln!(
+        &mut tab_writer,
+        "NAMESPACE\tNAME\tPOD-SELECTOR\tPOLICY-TYPES"
+    )?;
+
+    for policy in policies {
+        let selector = &policy.spec.pod_selector;
+        let mut terms: Vec<String> = selector
+            .match_labels
+            .iter()
+            .map(|(k, v)| format!("{k}={v}"))
+            .collect();
+        terms.sort();
+        terms.extend(
+            selector
+                .match_expressions
+                .iter()
+                .map(|expr| format!("{} {:?} {:?}", expr.key, expr.operator, expr.values)),
+        );
+        let selector = if terms.is_empty() {
+            "<none>".to_string()
+        } else {
+            terms.join(",")
+        };
+
+        let types = policy
+            .spec
+            .effective_policy_types()
+            .iter()
+            .map(|t| match t {
+                PolicyType::Ingress => "Ingress",
+                PolicyType::Egress => "Egress",
+            })
+            .collect::<Vec<_>>()
+            .join(",");
+
+        writeln!(
+            &mut tab_writer,
+            "{}\t{}\t{}\t{}",
+            policy.metadata.namespace, policy.metadata.name, selector, types
+        )?;
+    }
+
+    tab_writer.flush()?;
+    Ok(())
+}
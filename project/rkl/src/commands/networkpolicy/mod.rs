@@ -0,0 +1,113 @@
+use anyhow::{Result, anyhow};
+use clap::Subcommand;
+use std::env;
+
+use crate::commands::pod::TLSConnectionArgs;
+
+pub mod cluster;
+
+#[derive(Subcommand)]
+pub enum NetworkPolicyCommand {
+    #[command(about = "Create a NetworkPolicy from a YAML file")]
+    Create {
+        #[arg(value_name = "POLICY_YAML")]
+        policy_yaml: String,
+
+        #[arg(
+            long,
+            value_name = "RKS_ADDRESS",
+            env = "RKS_ADDRESS",
+            required = false
+        )]
+        cluster: Option<String>,
+
+        #[clap(flatten)]
+        tls_cfg: TLSConnectionArgs,
+    },
+
+    #[command(about = "Delete a NetworkPolicy by name")]
+    Delete {
+        #[arg(value_name = "POLICY_NAME")]
+        policy_name: String,
+
+        #[arg(
+            long,
+            value_name = "RKS_ADDRESS",
+            env = "RKS_ADDRESS",
+            required = false
+        )]
+        cluster: Option<String>,
+
+        #[clap(flatten)]
+        tls_cfg: TLSConnectionArgs,
+    },
+
+    #[command(about = "Get details of a specific NetworkPolicy")]
+    Get {
+        #[arg(value_name = "POLICY_NAME")]
+        policy_name: String,
+
+        #[arg(
+            long,
+            value_name = "RKS_ADDRESS",
+            env = "RKS_ADDRESS",
+            required = false
+        )]
+        cluster: Option<String>,
+
+        #[clap(flatten)]
+        tls_cfg: TLSConnectionArgs,
+    },
+
+    #[command(about = "List all NetworkPolicies")]
+    List {
+        #[arg(
+            long,
+            value_name = "RKS_ADDRESS",
+            env = "RKS_ADDRESS",
+            required = false
+        )]
+        cluster: Option<String>,
+
+        #[clap(flatten)]
+        tls_cfg: TLSConnectionArgs,
+    },
+}
+
+pub fn networkpolicy_execute(cmd: NetworkPolicyCommand) -> Result<()> {
+    let (cluster, tls_cfg) = match &cmd {
+        NetworkPolicyCommand::Create {
+            cluster, tls_cfg, ..
+        }
+        | NetworkPolicyCommand::Delete {
+            cluster, tls_cfg, ..
+        }
+        | NetworkPolicyCommand::Get {
+            cluster, tls_cfg, ..
+        }
+        | NetworkPolicyCommand::List { cluster, tls_cfg } => (cluster.clone(), tls_cfg.clone()),
+    };
+    let Some(rks_addr) = cluster.or(env::var("RKS_ADDRESS").ok()) else {
+        return Err(anyhow!(
+            "No RKS address provided. Set RKS_ADDRESS or use --cluster"
+        ));
+    };
+
+    let rt = tokio::runtime::Runtime::new()?;
+    match cmd {
+        NetworkPolicyCommand::Create { policy_yaml, .. } => rt.block_on(
+            cluster::create_network_policy(&policy_yaml, &rks_addr, tls_cfg),
+        ),
+        NetworkPolicyCommand::Delete { policy_name, .. } => rt.block_on(
+            cluster::delete_network_policy(&policy_name, &rks_addr, tls_cfg),
+        ),
+        NetworkPolicyCommand::Get { policy_name, .. } => rt.block_on(cluster::get_network_policy(
+            &policy_name,
+            &rks_addr,
+            tls_cfg,
+        )),
+        NetworkPolicyCommand::List { .. } => {
+            rt.block_on(cluster::list_network_policies(&rks_addr, tls_cfg))
+        }
+    }
+}
@@ -26,6 +26,8 @@ pub struct PodRunResult {
     #[allow(unused)]
     pub pod_sandbox_id: String,
     pub pod_ip: String,
+    /// IPs of the pod, one per IP family
+    pub pod_ips: Vec<String>,
     #[allow(unused)]
     pub container_names: Vec<String>,
     pub pod_task: PodTask,
@@ -289,6 +291,7 @@ pub fn sync_run_pod_from_taskrunner(
     Ok(PodRunResult {
         pod_sandbox_id,
         pod_ip: podip,
+        pod_ips: task_runner.pod_ips.clone(),
         container_names,
         pod_task: task_runner.task.clone(),
     })
@@ -320,6 +323,7 @@ pub async fn run_pod_from_taskrunner(
     Ok(PodRunResult {
         pod_sandbox_id,
         pod_ip: podip,
+        pod_ips: task_runner.pod_ips.clone(),
         container_names,
         pod_task: task_runner.task.clone(),
     })
@@ -1,7 +1,8 @@
 use anyhow::{Result, anyhow};
-use common::{LabelSelectorOperator, RksMessage, ServicePort, ServiceTask};
+use common::{IpFamily, LabelSelectorOperator, RksMessage, ServicePort, ServiceTask};
 use std::fs::File;
 use std::io::{self, Write};
+use std::net::{IpAddr, Ipv4Addr};
 use tabwriter::TabWriter;
 
 use crate::commands::pod::TLSConnectionArgs;
@@ -165,17 +166,139 @@ fn validate_service(svc: &ServiceTask) -> Result<()> {
         }
     }
 
+    if let Some(affinity) = svc.spec.session_affinity.as_deref()
+        && affinity != "None"
+        && affinity != "ClientIP"
+    {
+        return Err(anyhow!(
+            "Service spec.sessionAffinity must be None or ClientIP, got {}",
+            affinity
+        ));
+    }
+    if let Some(timeout) = svc
+        .spec
+        .session_affinity_config
+        .as_ref()
+        .and_then(|cfg| cfg.client_ip.as_ref())
+        .and_then(|cfg| cfg.timeout_seconds)
+        && !(1..=86400).contains(&timeout)
+    {
+        return Err(anyhow!(
+            "Service sessionAffinityConfig.clientIP.timeoutSeconds must be between 1 and 86400, got {}",
+            timeout
+        ));
+    }
+
+    if let Some(policy) = svc.spec.external_traffic_policy.as_deref() {
+        if policy != "Cluster" && policy != "Local" {
+            return Err(anyhow!(
+                "Service spec.externalTrafficPolicy must be Cluster or Local, got {}",
+                policy
+            ));
+        }
+        if policy == "Local"
+            && !matches!(svc.spec.service_type.as_str(), "NodePort" | "LoadBalancer")
+        {
+            return Err(anyhow!(
+                "Service spec.externalTrafficPolicy Local requires type NodePort or LoadBalancer"
+            ));
+        }
+    }
+
+    validate_ip_families(svc)?;
+
+    if let Some(lb_ip) = svc.spec.load_balancer_ip.as_deref() {
+        if !svc.spec.is_load_balancer() {
+            return Err(anyhow!(
+                "Service spec.loadBalancerIP requires type LoadBalancer"
+            ));
+        }
+        if lb_ip.parse::<Ipv4Addr>().is_err() {
+            return Err(anyhow!(
+                "Service spec.loadBalancerIP must be an IPv4 address, got {}",
+                lb_ip
+            ));
+        }
+    }
+
+    Ok(())
+}
+
+/// Checks that `clusterIPs` and `ipFamilies` describe at most one ClusterIP
+/// per family, consistent with `clusterIP`
+fn validate_ip_families(svc: &ServiceTask) -> Result<()> {
+    let spec = &svc.spec;
+    if spec.cluster_ips.len() > 2 || spec.ip_families.len() > 2 {
+        return Err(anyhow!(
+            "Service spec.clusterIPs and spec.ipFamilies accept at most two entries"
+        ));
+    }
+    if let (Some(cluster_ip), Some(first)) = (spec.cluster_ip.as_deref(), spec.cluster_ips.first())
+        && cluster_ip != first
+    {
+        return Err(anyhow!(
+            "Service spec.clusterIPs[0] must match spec.clusterIP, got {} and {}",
+            first,
+            cluster_ip
+        ));
+    }
+    if spec.is_headless() {
+        return Ok(());
+    }
+
+    let mut families = Vec::new();
+    for ip in &spec.cluster_ips {
+        let ip: IpAddr = ip
+            .parse()
+            .map_err(|_| anyhow!("Service spec.clusterIPs has an invalid IP {}", ip))?;
+        families.push(IpFamily::of(&ip));
+    }
+    if families.is_empty()
+        && let Some(Ok(ip)) = spec.cluster_ip.as_deref().map(str::parse::<IpAddr>)
+    {
+        families.push(IpFamily::of(&ip));
+    }
+    if families.len() == 2 && families[0] == families[1] {
+        return Err(anyhow!(
+            "Service spec.clusterIPs must hold one IPv4 and one IPv6 address"
+        ));
+    }
+    if spec.ip_families.len() == 2 && spec.ip_families[0] == spec.ip_families[1] {
+        return Err(anyhow!("Service spec.ipFamilies must not repeat a family"));
+    }
+    if !families.is_empty() && !spec.ip_families.is_empty() && families != spec.ip_families {
+        return Err(anyhow!(
+            "Service spec.ipFamilies {:?} do not match the families of spec.clusterIPs {:?}",
+            spec.ip_families,
+            families
+        ));
+    }
     Ok(())
 }
 
 fn list_print(services: Vec<ServiceTask>) -> Result<()> {
     let mut tab_writer = TabWriter::new(io::stdout());
-    writeln!(&mut tab_writer, "NAME\tTYPE\tCLUSTER-IP\tPORT(S)\tAGE")?;
+    writeln!(
+        &mut tab_writer,
+        "NAME\tTYPE\tCLUSTER-IP\tEXTERNAL-IP\tPORT(S)\tAGE"
+    )?;
 
     for svc in services {
         let name = &svc.metadata.name;
         let service_type = &svc.spec.service_type;
-        let cluster_ip = svc.spec.cluster_ip.as_deref().unwrap_or("<none>");
+        let cluster_ip = match svc.spec.cluster_ip_addrs() {
+            ips if ips.is_empty() => svc.spec.cluster_ip.clone().unwrap_or("<none>".to_string()),
+            ips => ips
+                .iter()
+                .map(IpAddr::to_string)
+                .collect::<Vec<_>>()
+                .join(","),
+        };
+        let external_ip = match svc.load_balancer_ip() {
+            Some(ip) => ip,
+            None if svc.spec.is_load_balancer() => "<pending>",
+            None => "<none>",
+        };
         let ports = format_ports(&svc.spec.ports);
 
         let age = svc
@@ -190,8 +313,8 @@ fn list_print(services: Vec<ServiceTask>) -> Result<()> {
 
         writeln!(
             &mut tab_writer,
-            "{}\t{}\t{}\t{}\t{}",
-            name, service_type, cluster_ip, ports, age
+            "{}\t{}\t{}\t{}\t{}\t{}",
+            name, service_type, cluster_ip, external_ip, ports, age
         )?;
     }
 
@@ -13,7 +13,9 @@ use crate::task::TaskRunner;
 use chrono::Utc;
 use common::*;
 use gethostname::gethostname;
+use libnetwork::iface;
 use libnetwork::ip::{IPStack, PublicIPOpts, lookup_ext_iface};
+use libnetwork::overlay;
 
 use crate::commands::pod::TLSConnectionArgs;
 use crate::quic::client::{Daemon as ClientDaemon, QUICClient};
@@ -64,7 +66,7 @@ pub async fn run_forever(tls_cfg: TLSConnectionArgs) -> Result<()> {
         env::var("RKS_ADDRESS").unwrap_or_else(|_| "192.168.73.128:50051".to_string());
     let server_addr: SocketAddr = server_addr.parse()?;
 
-    let ext_iface = lookup_ext_iface(
+    let mut ext_iface = lookup_ext_iface(
         None,
         None,
         None,
@@ -75,12 +77,14 @@ pub async fn run_forever(tls_cfg: TLSConnectionArgs) -> Result<()> {
         },
     )
     .await?;
+    discover_ipv6_addr(&mut ext_iface).await;
 
-    let node: Node = if let Ok(node_yaml) = env::var("NODE_YAML") {
+    let mut node: Node = if let Ok(node_yaml) = env::var("NODE_YAML") {
         load_node_from_yaml(&node_yaml)?
     } else {
         generate_node(&ext_iface).await?
     };
+    annotate_backend(&mut node, &ext_iface).await?;
 
     let ext_iface = Arc::new(ext_iface);
     loop {
@@ -100,6 +104,40 @@ pub async fn run_forever(tls_cfg: TLSConnectionArgs) -> Result<()> {
     }
 }
 
+/// Give `ext_iface` the global IPv6 address of its interface, if it has one.
+/// The other nodes route the IPv6 pod subnet of this node through it in a
+/// dual-stack cluster.
+async fn discover_ipv6_addr(ext_iface: &mut ExternalInterface) {
+    if ext_iface.iface_v6_addr.is_some() {
+        return;
+    }
+    let global = iface::get_interface_ipv6_addrs(ext_iface.iface.index)
+        .await
+        .ok()
+        .and_then(|addrs| addrs.into_iter().find(|addr| !addr.is_unicast_link_local()));
+    if let Some(addr) = global {
+        info!("Using IPv6 address {addr} of {}", ext_iface.iface.name);
+        ext_iface.iface_v6_addr = Some(addr);
+        ext_iface.ext_v6_addr = ext_iface.ext_v6_addr.or(Some(addr));
+    }
+}
+
+/// Record the backend this node is set up for (`BACKEND_TYPE`) and its
+/// backend data in the node annotations. Overlay backends exchange their VTEP
+/// MAC or WireGuard public key with the other nodes this way.
+async fn annotate_backend(node: &mut Node, ext_iface: &ExternalInterface) -> Result<()> {
+    let backend_type = env::var("BACKEND_TYPE").unwrap_or_else(|_| "hostgw".to_string());
+    let annotations = &mut node.metadata.annotations;
+    annotations.insert(
+        lease::BACKEND_TYPE_ANNOTATION.to_string(),
+        backend_type.clone(),
+    );
+    if let Some(data) = overlay::local_backend_data(&backend_type, ext_iface).await? {
+        annotations.insert(lease::BACKEND_DATA_ANNOTATION.to_string(), data.to_string());
+    }
+    Ok(())
+}
+
 fn load_node_from_yaml(yaml_path: &str) -> Result<Node> {
     use std::fs;
     let yaml_content = fs::read_to_string(yaml_path)?;
@@ -131,7 +169,8 @@ pub async fn run_once(
         link_index,
         backend_type,
         node.metadata.name.clone(),
-    );
+    )
+    .with_ext_iface((*ext_iface).clone());
 
     info!("Network receiver created for node: {}", node.metadata.name);
 
@@ -206,9 +245,11 @@ pub async fn run_once(
                                 let _ = client.send_msg(&RksMessage::Ack).await;
                             }
                         }
-                        Ok(RksMessage::UpdateRoutes(_id, routes)) => {
-                            info!("[worker] received routes update: {routes:?}");
-                            let route_msg = NetworkConfigMessage::Route { routes };
+                        Ok(RksMessage::UpdateRoutes(_id, routes, peers)) => {
+                            info!(
+                                "[worker] received routes update: {routes:?}, overlay peers: {peers:?}"
+                            );
+                            let route_msg = NetworkConfigMessage::Route { routes, peers };
                             if let Err(e) = network_receiver.handle_network_config(route_msg).await
                             {
                                 error!("[worker] failed to apply routes: {e}");
@@ -290,11 +331,18 @@ pub async fn run_once(
                                         .unwrap_or(&result.pod_ip)
                                         .to_string();
 
-                                    info!("[worker] SetPodip {} -> {}", pod_name, pod_ip);
-                                    if let Err(e) = client
-                                        .send_msg(&RksMessage::SetPodip((pod_name, pod_ip)))
-                                        .await
-                                    {
+                                    // Dual-stack pods report the IP of each family
+                                    let msg = if result.pod_ips.len() > 1 {
+                                        info!(
+                                            "[worker] SetPodIps {} -> {:?}",
+                                            pod_name, result.pod_ips
+                                        );
+                                        RksMessage::SetPodIps((pod_name, result.pod_ips.clone()))
+                                    } else {
+                                        info!("[worker] SetPodip {} -> {}", pod_name, pod_ip);
+                                        RksMessage::SetPodip((pod_name, pod_ip))
+                                    };
+                                    if let Err(e) = client.send_msg(&msg).await {
                                         error!("[worker] SetPodip send failed: {e}");
                                     }
                                 }
@@ -398,6 +446,23 @@ pub async fn run_once(
                                 }
                             }
                         }
+                        Ok(RksMessage::SetLoadBalancerVips(vips)) => {
+                            info!("[worker] received LoadBalancer VIPs: {vips:?}");
+                            match network_receiver.set_load_balancer_vips(vips).await {
+                                Ok(()) => {
+                                    info!("[worker] LoadBalancer VIPs applied");
+                                    let _ = client.send_msg(&RksMessage::Ack).await;
+                                }
+                                Err(e) => {
+                                    error!("[worker] failed to apply LoadBalancer VIPs: {e}");
+                                    let _ = client
+                                        .send_msg(&RksMessage::Error(format!(
+                                            "apply LoadBalancer VIPs failed: {e}"
+                                        )))
+                                        .await;
+                                }
+                            }
+                        }
                         Ok(RksMessage::GetPodLogs {
                             pod_name,
                             namespace,
@@ -479,6 +544,12 @@ pub async fn generate_node_status(ext_iface: &ExternalInterface) -> Result<NodeS
             address: ip.to_string(),
         });
     }
+    if let Some(ip) = ext_iface.iface_v6_addr {
+        addresses.push(NodeAddress {
+            address_type: "InternalIP".to_string(),
+            address: ip.to_string(),
+        });
+    }
     addresses.push(NodeAddress {
         address_type: "Hostname".to_string(),
         address: hostname,
@@ -529,6 +600,12 @@ pub async fn generate_node(ext_iface: &ExternalInterface) -> Result<Node> {
             address: ip.to_string(),
         });
     }
+    if let Some(ip) = ext_iface.iface_v6_addr {
+        addresses.push(NodeAddress {
+            address_type: "InternalIP".to_string(),
+            address: ip.to_string(),
+        });
+    }
     addresses.push(NodeAddress {
         address_type: "Hostname".to_string(),
         address: hostname.clone(),
@@ -566,6 +643,7 @@ pub async fn generate_node(ext_iface: &ExternalInterface) -> Result<Node> {
         },
         spec: NodeSpec {
             pod_cidr: "0".to_string(),
+            pod_cidrs: vec![],
             taints: vec![],
         },
         status: NodeStatus {
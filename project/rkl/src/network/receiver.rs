@@ -1,11 +1,14 @@
 #![allow(dead_code)]
 use crate::network::{route::RouteReceiver, subnet::SubnetReceiver};
 use anyhow::Result;
-use ipnetwork::Ipv4Network;
+use common::{ExternalInterface, lease::OverlayPeer};
+use ipnetwork::{Ipv4Network, Ipv6Network};
 use libcni::ip::route::Route;
 use libnetwork::{
     config::{NetworkConfig, validate_network_config},
+    overlay::{OverlayConfig, OverlayDevice},
     route::RouteManager,
+    vip::VipAnnouncer,
 };
 use log::{error, info, warn};
 use nftables::{helper, schema};
@@ -30,6 +33,9 @@ pub struct NetworkReceiver {
     quic_endpoint: Option<Endpoint>,
     shutdown_tx: Option<mpsc::Sender<()>>,
     route_shutdown_tx: Option<mpsc::Sender<()>>,
+    ext_iface: Option<ExternalInterface>,
+    overlay_device: Mutex<Option<OverlayDevice>>,
+    vip_announcer: Mutex<Option<VipAnnouncer>>,
 }
 
 impl NetworkReceiver {
@@ -52,9 +58,18 @@ impl NetworkReceiver {
             quic_endpoint: None,
             shutdown_tx: None,
             route_shutdown_tx: None,
+            ext_iface: None,
+            overlay_device: Mutex::new(None),
+            vip_announcer: Mutex::new(None),
         }
     }
 
+    /// Set the external interface that overlay tunnels are built on
+    pub fn with_ext_iface(mut self, ext_iface: ExternalInterface) -> Self {
+        self.ext_iface = Some(ext_iface);
+        self
+    }
+
     /// Set the RKS endpoint for QUIC communication
     pub fn with_rks_endpoint(mut self, endpoint: SocketAddr) -> Self {
         self.rks_endpoint = Some(endpoint);
@@ -88,6 +103,8 @@ impl NetworkReceiver {
             NetworkConfigMessage::SubnetConfig { subnet_env } => {
                 let mut network = None;
                 let mut subnet = None;
+                let mut ipv6_network = None;
+                let mut ipv6_subnet = None;
                 let mut ip_masq = true;
                 let mut mtu = 1500;
 
@@ -96,6 +113,10 @@ impl NetworkReceiver {
                         match key {
                             "RKL_NETWORK" => network = Some(value.parse::<Ipv4Network>()?),
                             "RKL_SUBNET" => subnet = Some(value.parse::<Ipv4Network>()?),
+                            "RKL_IPV6_NETWORK" => {
+                                ipv6_network = Some(value.parse::<Ipv6Network>()?)
+                            }
+                            "RKL_IPV6_SUBNET" => ipv6_subnet = Some(value.parse::<Ipv6Network>()?),
                             "RKL_MTU" => mtu = value.parse().unwrap_or(1500),
                             "RKL_IPMASQ" => ip_masq = value.parse().unwrap_or(true),
                             _ => {}
@@ -103,30 +124,43 @@ impl NetworkReceiver {
                     }
                 }
 
+                let overlay_config = OverlayConfig::from_env(&subnet_env)?;
+                if let Some(overlay_config) = &overlay_config {
+                    mtu = self
+                        .setup_overlay_device(overlay_config, network, subnet)
+                        .await?;
+                }
+
                 let mut network_config = NetworkConfig {
                     enable_ipv4: true,
-                    enable_ipv6: false,
+                    enable_ipv6: ipv6_subnet.is_some(),
                     enable_nftables: false,
                     network,
-                    ipv6_network: None,
+                    ipv6_network,
                     subnet_min: None,
                     subnet_max: None,
                     ipv6_subnet_min: None,
                     ipv6_subnet_max: None,
                     subnet_len: 24,
-                    ipv6_subnet_len: 64,
-                    backend_type: std::env::var("BACKEND_TYPE")
-                        .unwrap_or_else(|_| "hostgw".to_string()),
+                    ipv6_subnet_len: ipv6_subnet.map_or(64, |subnet| subnet.prefix()),
+                    backend_type: match &overlay_config {
+                        Some(overlay_config) => overlay_config.backend_type().to_string(),
+                        None => {
+                            std::env::var("BACKEND_TYPE").unwrap_or_else(|_| "hostgw".to_string())
+                        }
+                    },
                     backend: None,
                 };
 
                 validate_network_config(&mut network_config)?;
 
                 self.subnet_receiver
-                    .handle_subnet_config(&network_config, ip_masq, subnet, None, mtu)
+                    .handle_subnet_config(&network_config, ip_masq, subnet, ipv6_subnet, mtu)
                     .await?;
             }
-            NetworkConfigMessage::Route { routes } => {
+            NetworkConfigMessage::Route { routes, peers } => {
+                // Peers first, so that the routes through them work right away
+                self.apply_overlay_peers(peers).await?;
                 self.route_receiver.handle_route_config(routes).await?;
             }
             NetworkConfigMessage::FullConfig {
@@ -153,6 +187,48 @@ impl NetworkReceiver {
         Ok(())
     }
 
+    /// Create the tunnel device of an overlay backend with the tunnel address
+    /// of the subnet of this node, returning the MTU left for pods
+    async fn setup_overlay_device(
+        &self,
+        overlay_config: &OverlayConfig,
+        network: Option<Ipv4Network>,
+        subnet: Option<Ipv4Network>,
+    ) -> Result<u32> {
+        let ext_iface = self.ext_iface.as_ref().ok_or_else(|| {
+            anyhow::anyhow!(
+                "{} backend requires the external interface",
+                overlay_config.backend_type()
+            )
+        })?;
+        let network = network.ok_or_else(|| anyhow::anyhow!("RKL_NETWORK is missing"))?;
+        let subnet = subnet.ok_or_else(|| anyhow::anyhow!("RKL_SUBNET is missing"))?;
+
+        let device = OverlayDevice::ensure(overlay_config, ext_iface, subnet, network).await?;
+        let mtu = device.mtu();
+        *self.overlay_device.lock().await = Some(device);
+
+        info!(
+            "{} overlay device ready for node {} (mtu {mtu})",
+            overlay_config.backend_type(),
+            self.node_id
+        );
+        Ok(mtu)
+    }
+
+    /// Make the peers of the overlay device exactly `peers`
+    async fn apply_overlay_peers(&self, peers: Vec<OverlayPeer>) -> Result<()> {
+        let mut device = self.overlay_device.lock().await;
+        match device.as_mut() {
+            Some(device) => device.sync_peers(&peers).await,
+            None if peers.is_empty() => Ok(()),
+            None => Err(anyhow::anyhow!(
+                "received {} overlay peers before the overlay device was set up",
+                peers.len()
+            )),
+        }
+    }
+
     /// Start the network receiver service
     /// This will listen for network configurations from rks
     pub async fn start_service(&mut self) -> Result<()> {
@@ -313,6 +389,20 @@ impl NetworkReceiver {
         Ok(())
     }
 
+    /// Announce the LoadBalancer VIPs elected on this node, withdrawing the other ones
+    pub async fn set_load_balancer_vips(&self, vips: Vec<String>) -> Result<()> {
+        let ext_iface = self
+            .ext_iface
+            .as_ref()
+            .ok_or_else(|| anyhow::anyhow!("no external interface to announce VIPs on"))?;
+
+        let mut announcer = self.vip_announcer.lock().await;
+        announcer
+            .get_or_insert_with(|| VipAnnouncer::new(ext_iface))
+            .set_vips(&vips)
+            .await
+    }
+
     /// Stop the network receiver service
     pub async fn stop_service(&mut self) -> Result<()> {
         info!(
@@ -447,8 +537,11 @@ pub enum RksMessage {
 pub enum NetworkConfigMessage {
     /// Subnet configuration only
     SubnetConfig { subnet_env: String },
-    /// Route configuration only
-    Route { routes: Vec<Route> },
+    /// Route configuration only, with the overlay peers the routes go through
+    Route {
+        routes: Vec<Route>,
+        peers: Vec<OverlayPeer>,
+    },
     /// Full network configuration (subnet + routes)
     FullConfig {
         network_config: NetworkConfig,
@@ -84,6 +84,7 @@ impl ImagePuller for RkforgeImagePuller {
 pub struct TaskRunner {
     pub task: PodTask,
     pub pause_pid: Option<i32>, // pid of pause container
+    pub pod_ips: Vec<String>,   // IPs of the pod, one per IP family
     pub sandbox_config: Option<PodSandboxConfig>,
     /// Per-container persistent overlay rootfs mounts (keyed by container name)
     rootfs_mounts: HashMap<String, RootfsMount>,
@@ -101,6 +102,7 @@ impl TaskRunner {
         Ok(TaskRunner {
             task,
             pause_pid: None,
+            pod_ips: Vec::new(),
             sandbox_config: None,
             rootfs_mounts: HashMap::new(),
         })
@@ -289,6 +291,7 @@ impl TaskRunner {
             .unwrap_or("")
             .to_string();
         self.pause_pid = Some(pid_i32);
+        self.pod_ips = pod_ips_from_cni_result(&pod_json);
         // let podip = runner.ip().unwrap().to_string();
 
         info!("podip:{podip}");
@@ -406,6 +409,7 @@ impl TaskRunner {
             .unwrap_or("")
             .to_string();
         self.pause_pid = Some(pid_i32);
+        self.pod_ips = pod_ips_from_cni_result(&pod_json);
         // let podip = runner.ip().unwrap().to_string();
 
         info!("podip:{podip}");
@@ -1059,6 +1063,19 @@ impl TaskRunner {
     }
 }
 
+/// Addresses of the pod in a CNI result, without prefix length: one per IP
+/// family in a dual-stack cluster, the IPv4 one first
+fn pod_ips_from_cni_result(result: &JsonValue) -> Vec<String> {
+    result["ips"]
+        .as_array()
+        .into_iter()
+        .flatten()
+        .filter_map(|ip| ip["address"].as_str())
+        .filter_map(|address| address.split('/').next())
+        .map(str::to_string)
+        .collect()
+}
+
 pub fn get_cni() -> Result<Libcni, anyhow::Error> {
     let plugin_dirs = vec!["/opt/cni/bin".to_string()];
     let plugin_conf_dir = Path::new("/etc/cni/net.d");
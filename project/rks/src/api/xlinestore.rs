@@ -287,15 +287,17 @@ impl XlineStore {
     }
 
     /// Initialize Flannel CNI network configuration.
-    pub async fn init_flannel_config(&self) -> Result<()> {
-        let config_json = r#"{
+    ///
+    /// `backend` is the `Backend` section selecting how nodes reach each
+    /// other, e.g. `{"Type": "hostgw"}`, `{"Type": "vxlan", "VNI": 1}` or
+    /// `{"Type": "wireguard", "ListenPort": 51820}`.
+    pub async fn init_flannel_config(&self, backend: serde_json::Value) -> Result<()> {
+        let config_json = serde_json::json!({
             "Network": "10.244.0.0/16",
             "SubnetLen": 24,
-            "Backend": {
-                "Type": "vxlan",
-                "VNI": 1
-            }
-        }"#;
+            "Backend": backend,
+        })
+        .to_string();
 
         let key = "/coreos.com/network/config";
         let mut client = self.client.write().await;
@@ -466,6 +468,102 @@ impl XlineStore {
         Ok((watcher, stream))
     }
 
+    /// Insert a network policy YAML definition into xline.
+    pub async fn insert_network_policy_yaml(
+        &self,
+        policy_name: &str,
+        policy_yaml: &str,
+    ) -> Result<()> {
+        let key = format!("/registry/networkpolicies/{policy_name}");
+        let mut client = self.client.write().await;
+        client
+            .put(key, policy_yaml, Some(PutOptions::new()))
+            .await?;
+        Ok(())
+    }
+
+    /// Get a network policy YAML definition from xline.
+    pub async fn get_network_policy_yaml(&self, policy_name: &str) -> Result<Option<String>> {
+        let key = format!("/registry/networkpolicies/{policy_name}");
+        let mut client = self.client.write().await;
+        let resp = client.get(key, None).await?;
+        Ok(resp
+            .kvs()
+            .first()
+            .map(|kv| String::from_utf8_lossy(kv.value()).to_string()))
+    }
+
+    /// Get a network policy object from xline.
+    pub async fn get_network_policy(&self, policy_name: &str) -> Result<Option<NetworkPolicy>> {
+        if let Some(yaml) = self.get_network_policy_yaml(policy_name).await? {
+            let policy: NetworkPolicy = serde_yaml::from_str(&yaml)?;
+            Ok(Some(policy))
+        } else {
+            Ok(None)
+        }
+    }
+
+    /// Delete a network policy from xline.
+    pub async fn delete_network_policy(&self, policy_name: &str) -> Result<()> {
+        let key = format!("/registry/networkpolicies/{policy_name}");
+        let mut client = self.client.write().await;
+        client.delete(key, None).await?;
+        Ok(())
+    }
+
+    /// List all network policies (deserialize values).
+    pub async fn list_network_policies(&self) -> Result<Vec<NetworkPolicy>> {
+        let key = "/registry/networkpolicies/".to_string();
+        let mut client = self.client.write().await;
+        let resp = client
+            .get(key.clone(), Some(GetOptions::new().with_prefix()))
+            .await?;
+
+        let policies: Vec<NetworkPolicy> = resp
+            .kvs()
+            .iter()
+            .filter_map(|kv| {
+                let yaml_str = String::from_utf8_lossy(kv.value());
+                serde_yaml::from_str::<NetworkPolicy>(&yaml_str).ok()
+            })
+            .collect();
+
+        Ok(policies)
+    }
+
+    /// Take a snapshot of all network policies and return them with the current revision.
+    pub async fn network_policies_snapshot_with_rev(&self) -> Result<(Vec<(String, String)>, i64)> {
+        let key_prefix = "/registry/networkpolicies/".to_string();
+        let mut client = self.client.write().await;
+        let resp = client
+            .get(key_prefix.clone(), Some(GetOptions::new().with_prefix()))
+            .await?;
+        let rev = resp.header().map(|h| h.revision()).unwrap_or(0);
+        let items: Vec<(String, String)> = resp
+            .kvs()
+            .iter()
+            .map(|kv| {
+                (
+                    String::from_utf8_lossy(kv.key()).replace("/registry/networkpolicies/", ""),
+                    String::from_utf8_lossy(kv.value()).to_string(),
+                )
+            })
+            .collect();
+        Ok((items, rev))
+    }
+
+    /// Create a watch on all network policies with prefix `/registry/networkpolicies/`, starting from a given revision.
+    pub async fn watch_network_policies(&self, start_rev: i64) -> Result<(Watcher, WatchStream)> {
+        let key_prefix = "/registry/networkpolicies/".to_string();
+        let opts = WatchOptions::new()
+            .with_prefix()
+            .with_prev_key()
+            .with_start_revision(start_rev);
+        let mut client = self.client.write().await;
+        let (watcher, stream) = client.watch(key_prefix, Some(opts)).await?;
+        Ok((watcher, stream))
+    }
+
     /// Insert a replicaset YAML definition into xline.
     pub async fn insert_replicaset_yaml(&self, rs_name: &str, rs_yaml: &str) -> Result<()> {
         let key = format!("/registry/replicasets/{rs_name}");
@@ -681,6 +779,7 @@ impl XlineStore {
             ResourceKind::Deployment => self.get_deployment_yaml(name).await,
             ResourceKind::ReplicaSet => self.get_replicaset_yaml(name).await,
             ResourceKind::Endpoint => self.get_endpoint_yaml(name).await,
+            ResourceKind::NetworkPolicy => self.get_network_policy_yaml(name).await,
             ResourceKind::Unknown => Ok(None),
         }
     }
@@ -698,6 +797,7 @@ impl XlineStore {
             ResourceKind::Deployment => self.insert_deployment_yaml(name, yaml).await,
             ResourceKind::ReplicaSet => self.insert_replicaset_yaml(name, yaml).await,
             ResourceKind::Endpoint => self.insert_endpoint_yaml(name, yaml).await,
+            ResourceKind::NetworkPolicy => self.insert_network_policy_yaml(name, yaml).await,
             ResourceKind::Unknown => Ok(()),
         }
     }
@@ -714,6 +814,7 @@ impl XlineStore {
             ResourceKind::Deployment => format!("/registry/deployments/{name}"),
             ResourceKind::ReplicaSet => format!("/registry/replicasets/{name}"),
             ResourceKind::Endpoint => format!("/registry/endpoints/{name}"),
+            ResourceKind::NetworkPolicy => format!("/registry/networkpolicies/{name}"),
             ResourceKind::Unknown => return Ok(()),
         };
         let yaml = self.get_object_yaml(kind, name).await?;
@@ -2,8 +2,9 @@ use crate::api::xlinestore::XlineStore;
 use anyhow::Result;
 use async_trait::async_trait;
 use common::{
-    Endpoint, EndpointAddress, EndpointPort, EndpointSubset, LabelSelector, LabelSelectorOperator,
-    ObjectMeta, ObjectReference, PodTask, ResourceKind, ServiceTask,
+    ConditionStatus, ENDPOINT_WEIGHT_ANNOTATION, Endpoint, EndpointAddress, EndpointPort,
+    EndpointSubset, IpFamily, LabelSelector, LabelSelectorOperator, ObjectMeta, ObjectReference,
+    PodConditionType, PodTask, ResourceKind, ServiceTask,
 };
 use log::{info, warn};
 use std::collections::{HashMap, HashSet};
@@ -15,6 +16,9 @@ use tokio::time::sleep;
 use crate::controllers::manager::{Controller, ResourceWatchResponse, WatchEvent};
 use common::ServicePort;
 
+/// Upper bound of the endpoint weight a pod can request
+pub const MAX_ENDPOINT_WEIGHT: u32 = 100;
+
 /// EndpointController watches Services and Pods and maintains Endpoints objects in the
 /// registry (xline) to reflect Pods that match a Service selector.
 pub struct EndpointController {
@@ -372,7 +376,9 @@ fn build_endpoints_from_service_and_pods(svc: &ServiceTask, pods: &[PodTask]) ->
 
     let selector = svc.spec.selector.as_ref().unwrap();
 
+    let families = svc.spec.effective_ip_families();
     let mut addresses: Vec<EndpointAddress> = Vec::new();
+    let mut not_ready_addresses: Vec<EndpointAddress> = Vec::new();
     for pod in pods.iter() {
         let matched = if selector.match_labels.is_empty() && selector.match_expressions.is_empty() {
             true
@@ -384,7 +390,14 @@ fn build_endpoints_from_service_and_pods(svc: &ServiceTask, pods: &[PodTask]) ->
             continue;
         }
 
-        if let Some(ip) = pod.status.pod_ip.clone() {
+        // One address per IP family served by the Service
+        let ready = is_pod_ready(pod);
+        let weight = pod_endpoint_weight(pod);
+        for ip in pod.status.ips() {
+            if IpFamily::of_str(ip).is_some_and(|family| !families.contains(&family)) {
+                continue;
+            }
+
             let target_ref = Some(ObjectReference {
                 api_version: Some(pod.api_version.clone()),
                 kind: Some(pod.kind.clone()),
@@ -395,18 +408,25 @@ fn build_endpoints_from_service_and_pods(svc: &ServiceTask, pods: &[PodTask]) ->
                 field_path: None,
             });
 
-            addresses.push(EndpointAddress {
-                ip,
+            let address = EndpointAddress {
+                ip: ip.to_string(),
                 node_name: pod.spec.node_name.clone(),
                 target_ref,
-            });
+                weight,
+            };
+            // A weight of 0 drains the pod like a failed readiness check
+            if ready && weight != Some(0) {
+                addresses.push(address);
+            } else {
+                not_ready_addresses.push(address);
+            }
         }
     }
 
     // Build ports
     let mut ports: Vec<EndpointPort> = Vec::new();
     if svc.spec.ports.is_empty() {
-        if svc.spec.cluster_ip.as_deref() == Some("None") {
+        if svc.spec.is_headless() {
             // headless with no ports -> keep ports empty
         }
     } else {
@@ -418,7 +438,7 @@ fn build_endpoints_from_service_and_pods(svc: &ServiceTask, pods: &[PodTask]) ->
 
     let subset = EndpointSubset {
         addresses,
-        not_ready_addresses: vec![],
+        not_ready_addresses,
         ports,
     };
 
@@ -437,6 +457,30 @@ fn build_endpoints_from_service_and_pods(svc: &ServiceTask, pods: &[PodTask]) ->
     Some(endpoints)
 }
 
+/// A pod is ready unless its PodReady condition says otherwise; pods that
+/// report no conditions yet are served.
+fn is_pod_ready(pod: &PodTask) -> bool {
+    !pod.status.conditions.iter().flatten().any(|cond| {
+        cond.condition_type == PodConditionType::PodReady && cond.status == ConditionStatus::False
+    })
+}
+
+/// Weight requested through the pod annotation, clamped to
+/// `MAX_ENDPOINT_WEIGHT`; invalid values are ignored.
+fn pod_endpoint_weight(pod: &PodTask) -> Option<u32> {
+    let value = pod.metadata.annotations.get(ENDPOINT_WEIGHT_ANNOTATION)?;
+    match value.trim().parse::<u32>() {
+        Ok(weight) => Some(weight.min(MAX_ENDPOINT_WEIGHT)),
+        Err(_) => {
+            warn!(
+                "ignoring invalid {} annotation {:?} on pod {}/{}",
+                ENDPOINT_WEIGHT_ANNOTATION, value, pod.metadata.namespace, pod.metadata.name
+            );
+            None
+        }
+    }
+}
+
 /// (Removed pod-based port resolution.) Endpoint ports use service.target_port or service.port.
 fn endpoint_port_from_service_port(service_port: &ServicePort, port_num: i32) -> EndpointPort {
     EndpointPort {
@@ -0,0 +1,295 @@
+use crate::api::xlinestore::XlineStore;
+use crate::controllers::manager::{Controller, ResourceWatchResponse};
+use crate::node::NodeRegistry;
+use anyhow::Result;
+use async_trait::async_trait;
+use common::{
+    self, Endpoint, LoadBalancerIngress, LoadBalancerStatus, ResourceKind, ServiceStatus,
+    ServiceTask,
+};
+use ipnetwork::Ipv4Network;
+use log::{info, warn};
+use std::collections::hash_map::DefaultHasher;
+use std::collections::{BTreeSet, HashMap};
+use std::hash::{Hash, Hasher};
+use std::net::Ipv4Addr;
+use std::sync::Arc;
+use tokio::sync::Mutex;
+use tokio::time::{Duration, sleep};
+
+/// How often the announcing nodes are re-elected, so that the VIPs of a node
+/// that went away move to another one
+const ANNOUNCER_RESYNC_INTERVAL: Duration = Duration::from_secs(5);
+
+/// Allocates the VIPs of LoadBalancer Services from the configured pool and
+/// elects, for each VIP, the node announcing it with gratuitous ARP.
+pub struct LoadBalancerController {
+    xline_store: Arc<XlineStore>,
+    node_registry: Arc<NodeRegistry>,
+    pool: Ipv4Network,
+    /// VIPs last delivered to each node
+    delivered: Arc<Mutex<HashMap<String, Vec<String>>>>,
+}
+
+impl LoadBalancerController {
+    pub fn new(
+        xline_store: Arc<XlineStore>,
+        node_registry: Arc<NodeRegistry>,
+        pool: Ipv4Network,
+    ) -> Self {
+        Self {
+            xline_store,
+            node_registry,
+            pool,
+            delivered: Arc::new(Mutex::new(HashMap::new())),
+        }
+    }
+
+    /// Gives a VIP to the LoadBalancer Services without one and takes it back
+    /// from the Services that are no longer LoadBalancers.
+    async fn allocate_vips(&self) -> Result<()> {
+        let mut services = self.xline_store.list_services().await?;
+        services.sort_by(|a, b| service_key(a).cmp(&service_key(b)));
+
+        // VIPs kept by their Services, which are served first
+        let mut in_use = BTreeSet::new();
+        let mut pending = Vec::new();
+        for svc in &services {
+            let current = svc.load_balancer_ip().and_then(|ip| ip.parse().ok());
+            match current {
+                Some(ip) if svc.spec.is_load_balancer() && self.keeps_vip(svc, ip, &in_use) => {
+                    in_use.insert(ip);
+                }
+                _ if svc.spec.is_load_balancer() => pending.push(svc.clone()),
+                _ if svc.status.is_some() => {
+                    info!("Releasing the VIP of Service {}", service_key(svc));
+                    self.write_status(svc, None).await?;
+                }
+                _ => {}
+            }
+        }
+
+        for svc in pending {
+            let Some(ip) = self.pick_vip(&svc, &in_use) else {
+                warn!(
+                    "No VIP left in pool {} for LoadBalancer Service {}",
+                    self.pool,
+                    service_key(&svc)
+                );
+                if svc.status.is_some() {
+                    self.write_status(&svc, None).await?;
+                }
+                continue;
+            };
+            info!("Allocated VIP {} to Service {}", ip, service_key(&svc));
+            in_use.insert(ip);
+            self.write_status(&svc, Some(ip)).await?;
+        }
+        Ok(())
+    }
+
+    /// Whether `svc` can keep its allocated VIP `ip`
+    fn keeps_vip(&self, svc: &ServiceTask, ip: Ipv4Addr, in_use: &BTreeSet<Ipv4Addr>) -> bool {
+        let requested = svc.spec.load_balancer_ip.as_deref();
+        self.pool.contains(ip)
+            && !in_use.contains(&ip)
+            && requested.is_none_or(|requested| requested == ip.to_string())
+    }
+
+    /// The requested VIP if it is free, else the first free address of the pool
+    fn pick_vip(&self, svc: &ServiceTask, in_use: &BTreeSet<Ipv4Addr>) -> Option<Ipv4Addr> {
+        if let Some(requested) = svc.spec.load_balancer_ip.as_deref() {
+            return match requested.parse::<Ipv4Addr>() {
+                Ok(ip) if self.pool.contains(ip) && !in_use.contains(&ip) => Some(ip),
+                _ => {
+                    warn!(
+                        "loadBalancerIP {} of Service {} is not a free address of pool {}",
+                        requested,
+                        service_key(svc),
+                        self.pool
+                    );
+                    None
+                }
+            };
+        }
+
+        let (network, broadcast) = (self.pool.network(), self.pool.broadcast());
+        self.pool
+            .iter()
+            .filter(|ip| self.pool.prefix() >= 31 || (*ip != network && *ip != broadcast))
+            .find(|ip| !in_use.contains(ip))
+    }
+
+    async fn write_status(&self, svc: &ServiceTask, ip: Option<Ipv4Addr>) -> Result<()> {
+        let mut svc = svc.clone();
+        svc.status = ip.map(|ip| ServiceStatus {
+            load_balancer: LoadBalancerStatus {
+                ingress: vec![LoadBalancerIngress { ip: ip.to_string() }],
+            },
+        });
+        let yaml = serde_yaml::to_string(&svc)?;
+        self.xline_store
+            .insert_service_yaml(&svc.metadata.name, &yaml)
+            .await
+    }
+}
+
+#[async_trait]
+impl Controller for LoadBalancerController {
+    fn name(&self) -> &'static str {
+        "load-balancer-controller"
+    }
+
+    async fn init(&mut self) -> Result<()> {
+        info!(
+            "Initializing LoadBalancerController with pool {}, performing initial sync...",
+            self.pool
+        );
+        self.allocate_vips().await?;
+        sync_announcers(&self.xline_store, &self.node_registry, &self.delivered).await?;
+
+        let xline_store = self.xline_store.clone();
+        let node_registry = self.node_registry.clone();
+        let delivered = self.delivered.clone();
+        tokio::spawn(async move {
+            loop {
+                sleep(ANNOUNCER_RESYNC_INTERVAL).await;
+                if let Err(e) = sync_announcers(&xline_store, &node_registry, &delivered).await {
+                    warn!("LoadBalancerController: failed to sync announcers: {e}");
+                }
+            }
+        });
+        Ok(())
+    }
+
+    fn watch_resources(&self) -> Vec<ResourceKind> {
+        vec![ResourceKind::Service, ResourceKind::Endpoint]
+    }
+
+    async fn handle_watch_response(&mut self, response: &ResourceWatchResponse) -> Result<()> {
+        if response.kind == ResourceKind::Service {
+            self.allocate_vips().await?;
+        }
+        // Endpoints decide the announcers of `externalTrafficPolicy: Local` Services
+        sync_announcers(&self.xline_store, &self.node_registry, &self.delivered).await
+    }
+}
+
+/// Sends every node the VIPs it announces, when they changed
+async fn sync_announcers(
+    xline_store: &XlineStore,
+    node_registry: &NodeRegistry,
+    delivered: &Mutex<HashMap<String, Vec<String>>>,
+) -> Result<()> {
+    let services = xline_store.list_services().await?;
+    let endpoints = xline_store.list_endpoints().await?;
+    let sessions = node_registry.list_sessions().await;
+    let nodes: Vec<String> = sessions.iter().map(|(id, _)| id.clone()).collect();
+    let assignments = assign_announcers(&services, &endpoints, &nodes);
+
+    let mut delivered = delivered.lock().await;
+    delivered.retain(|node_id, _| nodes.contains(node_id));
+
+    for (node_id, session) in sessions {
+        let vips = assignments.get(&node_id).cloned().unwrap_or_default();
+        if delivered.get(&node_id) == Some(&vips) {
+            continue;
+        }
+
+        info!("Node {} now announces VIPs {:?}", node_id, vips);
+        match session
+            .tx
+            .try_send(common::RksMessage::SetLoadBalancerVips(vips.clone()))
+        {
+            Ok(()) => {
+                delivered.insert(node_id, vips);
+            }
+            Err(e) => {
+                // Not recorded as delivered, so the next sync retries
+                warn!("Failed to send VIPs to node {}: {}", node_id, e);
+                delivered.remove(&node_id);
+            }
+        }
+    }
+    Ok(())
+}
+
+/// VIPs announced by the node `node_id`, for the nodes that just registered
+pub async fn build_node_vips(
+    xline_store: &XlineStore,
+    node_registry: &NodeRegistry,
+    node_id: &str,
+) -> Result<Vec<String>> {
+    let services = xline_store.list_services().await?;
+    let endpoints = xline_store.list_endpoints().await?;
+    let mut nodes: Vec<String> = node_registry
+        .list_sessions()
+        .await
+        .into_iter()
+        .map(|(id, _)| id)
+        .collect();
+    if !nodes.iter().any(|id| id == node_id) {
+        nodes.push(node_id.to_string());
+    }
+    let mut assignments = assign_announcers(&services, &endpoints, &nodes);
+    Ok(assignments.remove(node_id).unwrap_or_default())
+}
+
+/// Elects the announcing node of each VIP among `nodes`, by rendezvous hashing
+/// so that nodes joining or leaving only move the VIPs they win or had.
+///
+/// With `externalTrafficPolicy: Local`, only the nodes running a ready endpoint
+/// are candidates, as the other ones drop the traffic.
+fn assign_announcers(
+    services: &[ServiceTask],
+    endpoints: &[Endpoint],
+    nodes: &[String],
+) -> HashMap<String, Vec<String>> {
+    let mut assignments: HashMap<String, Vec<String>> = HashMap::new();
+    for svc in services.iter().filter(|svc| svc.spec.is_load_balancer()) {
+        let Some(vip) = svc.load_balancer_ip() else {
+            continue;
+        };
+
+        let key = service_key(svc);
+        let candidates: Vec<&String> = if svc.spec.is_external_traffic_local() {
+            let endpoint_nodes: BTreeSet<&str> = endpoints
+                .iter()
+                .filter(|ep| {
+                    ep.metadata.namespace == svc.metadata.namespace
+                        && ep.metadata.name == svc.metadata.name
+                })
+                .flat_map(|ep| &ep.subsets)
+                .flat_map(|subset| &subset.addresses)
+                .filter_map(|addr| addr.node_name.as_deref())
+                .collect();
+            nodes
+                .iter()
+                .filter(|node| endpoint_nodes.contains(node.as_str()))
+                .collect()
+        } else {
+            nodes.iter().collect()
+        };
+
+        let announcer = candidates.into_iter().max_by_key(|node| {
+            let mut hasher = DefaultHasher::new();
+            (&key, node).hash(&mut hasher);
+            hasher.finish()
+        });
+        if let Some(node) = announcer {
+            assignments
+                .entry(node.clone())
+                .or_default()
+                .push(vip.to_string());
+        }
+    }
+
+    for vips in assignments.values_mut() {
+        vips.sort();
+    }
+    assignments
+}
+
+fn service_key(svc: &ServiceTask) -> String {
+    format!("{}/{}", svc.metadata.namespace, svc.metadata.name)
+}
@@ -10,6 +10,10 @@ pub use manager::ControllerManager;
 
 pub mod endpoint_controller;
 pub mod garbage_collector;
+pub mod load_balancer_controller;
+pub mod network_policy_controller;
 pub mod nftrules_controller;
 
+pub use load_balancer_controller::LoadBalancerController;
+pub use network_policy_controller::NetworkPolicyController;
 pub use nftrules_controller::NftablesController;
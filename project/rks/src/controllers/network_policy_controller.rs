@@ -0,0 +1,109 @@
+use crate::api::xlinestore::XlineStore;
+use crate::controllers::manager::{Controller, ResourceWatchResponse, WatchEvent};
+use crate::node::NodeRegistry;
+use anyhow::Result;
+use async_trait::async_trait;
+use common::{self, ResourceKind};
+use log::{info, warn};
+use std::collections::HashMap;
+use std::sync::Arc;
+
+/// Watches NetworkPolicies and Pods, compiles the policies into the nftables
+/// rules of each node, and sends every node its own rules.
+pub struct NetworkPolicyController {
+    xline_store: Arc<XlineStore>,
+    node_registry: Arc<NodeRegistry>,
+    /// Rules last delivered to each node, so that pod updates that do not
+    /// change the rules of a node are not pushed to it
+    delivered: HashMap<String, String>,
+}
+
+impl NetworkPolicyController {
+    pub fn new(xline_store: Arc<XlineStore>, node_registry: Arc<NodeRegistry>) -> Self {
+        Self {
+            xline_store,
+            node_registry,
+            delivered: HashMap::new(),
+        }
+    }
+
+    async fn sync_rules(&mut self) -> Result<()> {
+        let policies = self.xline_store.list_network_policies().await?;
+        let pods = self.xline_store.list_pods().await?;
+
+        let sessions = self.node_registry.list_sessions().await;
+        self.delivered
+            .retain(|node_id, _| sessions.iter().any(|(id, _)| id == node_id));
+
+        for (node_id, session) in sessions {
+            let json_rules = generate_network_policy_config(&node_id, &policies, &pods)?;
+            if self.delivered.get(&node_id) == Some(&json_rules) {
+                continue;
+            }
+
+            info!(
+                "Sending network policy rules to node {} (len={})",
+                node_id,
+                json_rules.len()
+            );
+            let msg = common::RksMessage::SetNftablesRules(json_rules.clone());
+            match session.tx.try_send(msg) {
+                Ok(()) => {
+                    self.delivered.insert(node_id, json_rules);
+                }
+                Err(e) => {
+                    // Not recorded as delivered, so the next sync retries
+                    warn!(
+                        "Failed to send network policy rules to node {}: {}",
+                        node_id, e
+                    );
+                    self.delivered.remove(&node_id);
+                }
+            }
+        }
+        Ok(())
+    }
+}
+
+#[async_trait]
+impl Controller for NetworkPolicyController {
+    fn name(&self) -> &'static str {
+        "network-policy-controller"
+    }
+
+    async fn init(&mut self) -> Result<()> {
+        info!("Initializing NetworkPolicyController, performing initial full sync...");
+        self.sync_rules().await
+    }
+
+    fn watch_resources(&self) -> Vec<ResourceKind> {
+        vec![ResourceKind::NetworkPolicy, ResourceKind::Pod]
+    }
+
+    async fn handle_watch_response(&mut self, response: &ResourceWatchResponse) -> Result<()> {
+        if response.kind == ResourceKind::NetworkPolicy {
+            let action = match &response.event {
+                WatchEvent::Add { .. } => "add",
+                WatchEvent::Update { .. } => "update",
+                WatchEvent::Delete { .. } => "delete",
+            };
+            info!(
+                "NetworkPolicyController: processing network policy {} {}, triggering full sync",
+                action, response.key
+            );
+        }
+
+        // Pod events only matter through the IPs, labels and nodes they
+        // change, which the comparison with the delivered rules filters.
+        self.sync_rules().await
+    }
+}
+
+/// Rules of one node, for the nodes that just registered
+pub async fn build_node_rules(xline_store: &XlineStore, node_id: &str) -> Result<String> {
+    let policies = xline_store.list_network_policies().await?;
+    let pods = xline_store.list_pods().await?;
+    generate_network_policy_config(node_id, &policies, &pods)
+}
+
+pub use libnetwork::network_policy::generate_network_policy_config;
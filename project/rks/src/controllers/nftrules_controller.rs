@@ -6,16 +6,18 @@ use async_trait::async_trait;
 use common::{self, ResourceKind};
 use log::{info, warn};
 use serde_yaml;
-use std::collections::HashSet;
+use std::collections::HashMap;
 use std::sync::Arc;
 use tokio::sync::Mutex;
 use tokio::time::{Duration, sleep};
 
-/// Watches Services and Endpoints, generates nftables rules, and broadcasts to workers.
+/// Watches Services and Endpoints, generates nftables rules, and sends every worker
+/// its own rules (they differ for Services with `externalTrafficPolicy: Local`).
 pub struct NftablesController {
     xline_store: Arc<XlineStore>,
     node_registry: Arc<NodeRegistry>,
-    out_of_sync: Arc<Mutex<HashSet<String>>>,
+    /// Rules that could not be delivered yet, by node
+    out_of_sync: Arc<Mutex<HashMap<String, String>>>,
 }
 
 impl NftablesController {
@@ -23,37 +25,20 @@ impl NftablesController {
         Self {
             xline_store,
             node_registry,
-            out_of_sync: Arc::new(Mutex::new(HashSet::new())),
+            out_of_sync: Arc::new(Mutex::new(HashMap::new())),
         }
     }
 
     async fn sync_rules(&self) -> Result<()> {
-        let (services_raw, _srev) = self.xline_store.services_snapshot_with_rev().await?;
-        let (endpoints_raw, _erev) = self.xline_store.endpoints_snapshot_with_rev().await?;
-
-        let mut services = Vec::new();
-        for (key, yaml) in services_raw {
-            match serde_yaml::from_str::<common::ServiceTask>(&yaml) {
-                Ok(svc) => services.push(svc),
-                Err(e) => warn!("Failed to parse Service {}: {}", key, e),
-            }
-        }
-
-        let mut endpoints = Vec::new();
-        for (key, yaml) in endpoints_raw {
-            match serde_yaml::from_str::<common::Endpoint>(&yaml) {
-                Ok(ep) => endpoints.push(ep),
-                Err(e) => warn!("Failed to parse Endpoint {}: {}", key, e),
-            }
-        }
-
-        // Generate JSON rules (Full Sync)
-        let json_rules = generate_nftables_config(&services, &endpoints)?;
-
-        self.broadcast_rules(json_rules).await
+        let (services, endpoints) = load_services_and_endpoints(&self.xline_store).await?;
+        self.broadcast_rules(&services, &endpoints).await
     }
 
-    async fn broadcast_rules(&self, json_rules: String) -> Result<()> {
+    async fn broadcast_rules(
+        &self,
+        services: &[common::ServiceTask],
+        endpoints: &[common::Endpoint],
+    ) -> Result<()> {
         let sessions = self.node_registry.list_sessions().await;
         if sessions.is_empty() {
             info!("Broadcasting nftables rules skipped: no worker nodes connected");
@@ -61,37 +46,36 @@ impl NftablesController {
         }
 
         info!(
-            "Broadcasting full nftables rules to {} nodes (len={})",
-            sessions.len(),
-            json_rules.len()
+            "Broadcasting full nftables rules to {} nodes",
+            sessions.len()
         );
 
-        let msg = common::RksMessage::SetNftablesRules(json_rules);
-
-        let mut failed_nodes = Vec::new();
+        let mut failed = Vec::new();
 
         for (node_id, session) in sessions {
-            if let Err(e) = session.tx.try_send(msg.clone()) {
+            // Generate JSON rules (Full Sync)
+            let json_rules = generate_node_nftables_config(services, endpoints, &node_id)?;
+            let msg = common::RksMessage::SetNftablesRules(json_rules.clone());
+            if let Err(e) = session.tx.try_send(msg) {
                 warn!("Failed to send rules to node {}: {}", node_id, e);
-                failed_nodes.push(node_id);
+                failed.push((node_id, json_rules));
             }
         }
 
-        if !failed_nodes.is_empty() {
-            self.record_out_of_sync(&failed_nodes).await;
-            self.retry_broadcast(msg).await;
+        if !failed.is_empty() {
+            self.record_out_of_sync(failed).await;
+            self.retry_broadcast().await;
         }
         Ok(())
     }
 
-    async fn record_out_of_sync(&self, nodes: &[String]) {
-        let mut set = self.out_of_sync.lock().await;
-        for n in nodes {
-            set.insert(n.clone());
-        }
+    async fn record_out_of_sync(&self, failed: Vec<(String, String)>) {
+        let mut map = self.out_of_sync.lock().await;
+        // Newer rules replace the ones still waiting for a retry
+        map.extend(failed);
     }
 
-    async fn retry_broadcast(&self, msg: common::RksMessage) {
+    async fn retry_broadcast(&self) {
         const MAX_ATTEMPTS: usize = 3;
         const BACKOFF_MS: u64 = 300;
 
@@ -103,20 +87,23 @@ impl NftablesController {
                 // simple linear backoff
                 sleep(Duration::from_millis(BACKOFF_MS * attempt as u64)).await;
 
-                let nodes: Vec<String> = {
-                    let set = tracker.lock().await;
-                    set.iter().cloned().collect()
+                let pending: Vec<(String, String)> = {
+                    let map = tracker.lock().await;
+                    map.iter()
+                        .map(|(node_id, rules)| (node_id.clone(), rules.clone()))
+                        .collect()
                 };
 
-                if nodes.is_empty() {
+                if pending.is_empty() {
                     return;
                 }
 
                 let mut successes = Vec::new();
-                for node_id in nodes {
+                for (node_id, json_rules) in pending {
                     match registry.get(&node_id).await {
                         Some(session) => {
-                            if let Err(e) = session.tx.try_send(msg.clone()) {
+                            let msg = common::RksMessage::SetNftablesRules(json_rules.clone());
+                            if let Err(e) = session.tx.try_send(msg) {
                                 warn!(
                                     "Retry {}/{} failed to send rules to node {}: {}",
                                     attempt, MAX_ATTEMPTS, node_id, e
@@ -126,7 +113,7 @@ impl NftablesController {
                                     "Retry {}/{} succeeded sending rules to node {}",
                                     attempt, MAX_ATTEMPTS, node_id
                                 );
-                                successes.push(node_id);
+                                successes.push((node_id, json_rules));
                             }
                         }
                         None => {
@@ -139,9 +126,12 @@ impl NftablesController {
                 }
 
                 if !successes.is_empty() {
-                    let mut set = tracker.lock().await;
-                    for n in successes {
-                        set.remove(&n);
+                    let mut map = tracker.lock().await;
+                    for (node_id, json_rules) in successes {
+                        // Keep rules queued by a newer sync meanwhile
+                        if map.get(&node_id) == Some(&json_rules) {
+                            map.remove(&node_id);
+                        }
                     }
                 }
 
@@ -152,8 +142,8 @@ impl NftablesController {
             }
 
             let remaining: Vec<String> = {
-                let set = tracker.lock().await;
-                set.iter().cloned().collect()
+                let map = tracker.lock().await;
+                map.keys().cloned().collect()
             };
             if !remaining.is_empty() {
                 warn!(
@@ -164,7 +154,7 @@ impl NftablesController {
         });
     }
 
-    // Only handle Endpoint upserts: Services are not watched to reduce noise.
+    // Endpoint upserts, besides the Service ones handled in `handle_watch_response`.
     async fn process_upsert(&mut self, yaml: &str) -> Result<()> {
         // Parse purely for logging context
         if let Ok(ep) = serde_yaml::from_str::<common::Endpoint>(yaml) {
@@ -210,11 +200,23 @@ impl Controller for NftablesController {
     }
 
     fn watch_resources(&self) -> Vec<ResourceKind> {
-        vec![ResourceKind::Endpoint]
+        vec![ResourceKind::Endpoint, ResourceKind::Service]
     }
 
     async fn handle_watch_response(&mut self, response: &ResourceWatchResponse) -> Result<()> {
-        // We only watch Endpoints in this controller.
+        // Services only matter here for the fields their Endpoints do not carry
+        // (affinity, traffic policy, LoadBalancer VIP), so only updates are synced:
+        // adds and deletes also change the Endpoints.
+        if response.kind == ResourceKind::Service {
+            if let WatchEvent::Update { .. } = &response.event {
+                info!(
+                    "NftablesController: processing service update {}, triggering full sync",
+                    response.key
+                );
+                self.sync_rules().await?;
+            }
+            return Ok(());
+        }
         if response.kind != ResourceKind::Endpoint {
             return Ok(());
         }
@@ -236,7 +238,15 @@ impl Controller for NftablesController {
     }
 }
 
-pub async fn build_rules(xline_store: &XlineStore) -> Result<String> {
+/// Rules of one node, for the nodes that just registered
+pub async fn build_rules(xline_store: &XlineStore, node_id: &str) -> Result<String> {
+    let (services, endpoints) = load_services_and_endpoints(xline_store).await?;
+    generate_node_nftables_config(&services, &endpoints, node_id)
+}
+
+async fn load_services_and_endpoints(
+    xline_store: &XlineStore,
+) -> Result<(Vec<common::ServiceTask>, Vec<common::Endpoint>)> {
     // Use snapshot helpers to avoid many RPCs
     let (services_raw, _srev) = xline_store.services_snapshot_with_rev().await?;
     let (endpoints_raw, _erev) = xline_store.endpoints_snapshot_with_rev().await?;
@@ -257,8 +267,8 @@ pub async fn build_rules(xline_store: &XlineStore) -> Result<String> {
         }
     }
 
-    generate_nftables_config(&services, &endpoints)
+    Ok((services, endpoints))
 }
 
 // Re-export generation functions from libnetwork for tests
-pub use libnetwork::nftables::generate_nftables_config;
+pub use libnetwork::nftables::{generate_nftables_config, generate_node_nftables_config};
@@ -29,7 +29,7 @@ use nftables::stmt::{Match as StmtMatch, NAT, Operator, Statement};
 use nftables::types::NfFamily;
 use std::borrow::Cow;
 use std::env;
-use std::net::{Ipv4Addr, SocketAddr};
+use std::net::{IpAddr, Ipv4Addr, Ipv6Addr, SocketAddr};
 use std::str::FromStr;
 use std::sync::Arc;
 use tokio::net::UdpSocket;
@@ -141,20 +141,13 @@ impl XlineAuthority {
         let mut pod_cache = self.object_cache.pod_cache.write().await;
         info!("DNS server get pods: {pods:?}");
         for pod in pods {
-            let ns = pod.metadata.namespace.clone();
-            let ip_str = pod.status.pod_ip.clone().unwrap_or_default();
-            let ip_only = ip_str.split('/').next().unwrap();
-            let ip = ip_only.parse().ok();
-            let pod_ip_with_dashes = ip_only.replace('.', "-");
-            info!("DNS server insert PodRecord: {pod_ip_with_dashes}, ns: {ns}");
-            pod_cache.insert(
-                (ns.clone(), pod_ip_with_dashes.clone()),
-                PodRecord {
-                    name: pod_ip_with_dashes,
-                    namespace: ns,
-                    pod_ip: ip,
-                },
-            );
+            for record in pod_records(&pod) {
+                info!(
+                    "DNS server insert PodRecord: {}, ns: {}",
+                    record.name, record.namespace
+                );
+                pod_cache.insert((record.namespace.clone(), record.name.clone()), record);
+            }
         }
         info!("DNS server init_from_store pod_cache: {pod_cache:?}");
         drop(pod_cache);
@@ -164,21 +157,8 @@ impl XlineAuthority {
         let mut svc_cache = self.object_cache.service_cache.write().await;
         for svc in services {
             let (ns, name) = (svc.metadata.namespace.clone(), svc.metadata.name.clone());
-            let ip = svc
-                .spec
-                .cluster_ip
-                .as_ref()
-                .and_then(|ipstr| ipstr.parse().ok());
             info!("DNS server insert ServiceRecord : {name}");
-            svc_cache.insert(
-                (ns.clone(), name.clone()),
-                ServiceRecord {
-                    name,
-                    namespace: ns,
-                    cluster_ip: ip,
-                    ports: svc.spec.ports.clone(),
-                },
-            );
+            svc_cache.insert((ns, name), service_record(&svc));
         }
         drop(svc_cache);
 
@@ -221,22 +201,17 @@ impl XlineAuthority {
                                         && let Ok(pod) =
                                             serde_yaml::from_slice::<PodTask>(kv.value())
                                     {
-                                        let ns = pod.metadata.namespace.clone();
-                                        let ip_str = pod.status.pod_ip.clone().unwrap_or_default();
-                                        let ip_only = ip_str.split('/').next().unwrap();
-                                        let ip = ip_only.parse().ok();
-                                        let pod_ip_with_dashes = ip_only.replace('.', "-");
-                                        info!(
-                                            "DNS server insert PodRecord: {pod_ip_with_dashes}, ns: {ns}"
-                                        );
-                                        pod_cache.write().await.insert(
-                                            (ns.clone(), pod_ip_with_dashes.clone()),
-                                            PodRecord {
-                                                name: pod_ip_with_dashes,
-                                                namespace: ns,
-                                                pod_ip: ip,
-                                            },
-                                        );
+                                        let mut pod_cache = pod_cache.write().await;
+                                        for record in pod_records(&pod) {
+                                            info!(
+                                                "DNS server insert PodRecord: {}, ns: {}",
+                                                record.name, record.namespace
+                                            );
+                                            pod_cache.insert(
+                                                (record.namespace.clone(), record.name.clone()),
+                                                record,
+                                            );
+                                        }
                                     }
                                 }
                                 EventType::Delete => {
@@ -245,18 +220,14 @@ impl XlineAuthority {
                                         if let Ok(pod) =
                                             serde_yaml::from_slice::<PodTask>(kv.value())
                                         {
-                                            let ns = pod.metadata.namespace.clone();
-                                            let ip_str =
-                                                pod.status.pod_ip.clone().unwrap_or_default();
-                                            let ip_only = ip_str.split('/').next().unwrap();
-                                            let pod_ip_with_dashes = ip_only.replace('.', "-");
-                                            info!(
-                                                "DNS server delete PodRecord : {pod_ip_with_dashes}"
-                                            );
-                                            pod_cache
-                                                .write()
-                                                .await
-                                                .remove(&(ns, pod_ip_with_dashes));
+                                            let mut pod_cache = pod_cache.write().await;
+                                            for record in pod_records(&pod) {
+                                                info!(
+                                                    "DNS server delete PodRecord : {}",
+                                                    record.name
+                                                );
+                                                pod_cache.remove(&(record.namespace, record.name));
+                                            }
                                         }
                                     }
                                 }
@@ -353,20 +324,10 @@ impl XlineAuthority {
                                             svc.metadata.namespace.clone(),
                                             svc.metadata.name.clone(),
                                         );
-                                        let ip = svc
-                                            .spec
-                                            .cluster_ip
-                                            .as_ref()
-                                            .and_then(|s| s.parse::<Ipv4Addr>().ok());
-                                        svc_cache.write().await.insert(
-                                            (ns.clone(), name.clone()),
-                                            ServiceRecord {
-                                                name,
-                                                namespace: ns,
-                                                cluster_ip: ip,
-                                                ports: svc.spec.ports.clone(),
-                                            },
-                                        );
+                                        svc_cache
+                                            .write()
+                                            .await
+                                            .insert((ns, name), service_record(&svc));
                                     }
                                 }
                                 EventType::Delete => {
@@ -428,7 +389,7 @@ impl XlineAuthority {
             // prefer service-level SRV pointing to service FQDN
             let svc_cache = self.object_cache.service_cache.read().await;
             if let Some(svc) = svc_cache.get(&(ns.clone(), svc_name.clone()))
-                && svc.cluster_ip.is_some()
+                && !svc.cluster_ips.is_empty()
             {
                 // find matching port by name in the service ports
                 let matched_port = svc.ports.iter().find_map(|sp| {
@@ -464,11 +425,11 @@ impl XlineAuthority {
                         if port.name.as_deref() == Some(&port_name) {
                             let port_num = port.port as u16;
                             for addr in &subset.addresses {
-                                if addr.ip.parse::<Ipv4Addr>().is_ok() {
-                                    // target is the pod host name used by pod A records
+                                if addr.ip.parse::<IpAddr>().is_ok() {
+                                    // target is the pod host name used by pod A/AAAA records
                                     let pod_host = format!(
                                         "{}.pod.{}.{}",
-                                        addr.ip.replace('.', "-"),
+                                        pod_host_label(&addr.ip),
                                         ns,
                                         self.origin
                                     );
@@ -502,41 +463,40 @@ impl XlineAuthority {
         if let Some((svc_name, ns)) = parse_service_query(name, &self.origin) {
             let svc_cache = self.object_cache.service_cache.read().await;
             if let Some(svc) = svc_cache.get(&(ns.clone(), svc_name.clone())) {
-                // ClusterIP service -> return cluster IP
-                if rtype == RecordType::A {
-                    if let Some(ip) = svc.cluster_ip {
-                        let mut set = RecordSet::new(name.clone().into(), RecordType::A, 30);
-                        set.insert(
-                            Record::from_rdata(name.clone().into(), 30, RData::A(ip.into())),
-                            0,
-                        );
-                        return Some(set);
-                    }
+                if rtype != RecordType::A && rtype != RecordType::AAAA {
+                    return None;
+                }
+                let mut set = RecordSet::new(name.clone().into(), rtype, 30);
 
+                if !svc.cluster_ips.is_empty() {
+                    // ClusterIP service -> return the cluster IP of the queried family
+                    for ip in &svc.cluster_ips {
+                        if let Some(record) = address_record(name, *ip, rtype) {
+                            set.insert(record, 0);
+                        }
+                    }
+                } else {
                     // headless service: lookup endpoints for backends
                     let ep_cache = self.object_cache.endpoints_cache.read().await;
                     if let Some(ep) = ep_cache.get(&(ns.clone(), svc_name.clone())) {
-                        let mut set = RecordSet::new(name.clone().into(), RecordType::A, 30);
                         for subset in &ep.subsets {
                             for addr in subset.addresses.iter() {
-                                if let Ok(ipv4) = addr.ip.parse::<Ipv4Addr>() {
-                                    set.insert(
-                                        Record::from_rdata(
-                                            name.clone().into(),
-                                            30,
-                                            RData::A(ipv4.into()),
-                                        ),
-                                        0,
-                                    );
+                                if let Some(record) = addr
+                                    .ip
+                                    .parse::<IpAddr>()
+                                    .ok()
+                                    .and_then(|ip| address_record(name, ip, rtype))
+                                {
+                                    set.insert(record, 0);
                                 }
                             }
                         }
-
-                        if !set.is_empty() {
-                            return Some(set);
-                        }
                     }
                 }
+
+                if !set.is_empty() {
+                    return Some(set);
+                }
             }
         }
         None
@@ -548,18 +508,18 @@ impl XlineAuthority {
         rtype: RecordType,
     ) -> Option<RecordSet> {
         if let Some((pod_name, ns)) = parse_pod_query(name, &self.origin) {
+            // Pods are cached under the expanded form of their IPv6 address
+            let pod_name = parse_pod_host_label(&pod_name)
+                .map(|ip| pod_host_label(&ip.to_string()))
+                .unwrap_or(pod_name);
             info!("DNS lookup the pod_name: {pod_name}, ns: {ns}");
             let cache = self.object_cache.pod_cache.read().await;
             if let Some(pod) = cache.get(&(ns.clone(), pod_name.clone()))
-                && rtype == RecordType::A
-                && let Some(ip) = pod.pod_ip
+                && let Some(record) = pod.pod_ip.and_then(|ip| address_record(name, ip, rtype))
             {
                 info!("DNS find the Record: {pod:?}");
-                let mut set = RecordSet::new(name.clone().into(), RecordType::A, 30);
-                set.insert(
-                    Record::from_rdata(name.clone().into(), 30, RData::A(ip.into())),
-                    0,
-                );
+                let mut set = RecordSet::new(name.clone().into(), rtype, 30);
+                set.insert(record, 0);
                 return Some(set);
             }
             info!("DNS not find the Record");
@@ -594,6 +554,65 @@ pub async fn run_dns_server(xline_store: Arc<XlineStore>, port: u16) -> anyhow::
     Ok(())
 }
 
+/// One record per pod IP, named after the IP with dashes
+fn pod_records(pod: &PodTask) -> Vec<PodRecord> {
+    pod.status
+        .ips()
+        .into_iter()
+        .map(|ip| PodRecord {
+            name: pod_host_label(ip),
+            namespace: pod.metadata.namespace.clone(),
+            pod_ip: ip.parse().ok(),
+        })
+        .collect()
+}
+
+/// Pod host name of an IP: "10.1.0.5" -> "10-1-0-5". IPv6 addresses are
+/// expanded, so that "::1" gives a label not starting with a dash:
+/// "fd00::5" -> "fd00-0000-0000-0000-0000-0000-0000-0005"
+fn pod_host_label(ip: &str) -> String {
+    match ip.parse::<IpAddr>() {
+        Ok(IpAddr::V6(ip)) => ip
+            .segments()
+            .iter()
+            .map(|segment| format!("{segment:04x}"))
+            .collect::<Vec<_>>()
+            .join("-"),
+        _ => ip.replace('.', "-"),
+    }
+}
+
+/// IP of a pod host name, the reverse of `pod_host_label`
+fn parse_pod_host_label(label: &str) -> Option<IpAddr> {
+    if let Ok(ip) = label.replace('-', ".").parse::<Ipv4Addr>() {
+        return Some(IpAddr::V4(ip));
+    }
+    label
+        .replace('-', ":")
+        .parse::<Ipv6Addr>()
+        .ok()
+        .map(IpAddr::V6)
+}
+
+fn service_record(svc: &ServiceTask) -> ServiceRecord {
+    ServiceRecord {
+        name: svc.metadata.name.clone(),
+        namespace: svc.metadata.namespace.clone(),
+        cluster_ips: svc.spec.cluster_ip_addrs(),
+        ports: svc.spec.ports.clone(),
+    }
+}
+
+/// A record of an IPv4 address or AAAA record of an IPv6 one, when it answers `rtype`
+fn address_record(name: &LowerName, ip: IpAddr, rtype: RecordType) -> Option<Record> {
+    let rdata = match (ip, rtype) {
+        (IpAddr::V4(ip), RecordType::A) => RData::A(ip.into()),
+        (IpAddr::V6(ip), RecordType::AAAA) => RData::AAAA(ip.into()),
+        _ => return None,
+    };
+    Some(Record::from_rdata(name.clone().into(), 30, rdata))
+}
+
 fn parse_service_query(name: &LowerName, origin: &LowerName) -> Option<(String, String)> {
     let labels: Vec<_> = name
         .iter()
@@ -1046,4 +1065,34 @@ mod tests {
         assert_eq!(svc, "nginx");
         assert_eq!(ns, "default");
     }
+
+    #[test]
+    fn test_pod_host_label() {
+        assert_eq!(pod_host_label("10.1.0.5"), "10-1-0-5");
+        assert_eq!(
+            pod_host_label("fd00:10:1::5"),
+            "fd00-0010-0001-0000-0000-0000-0000-0005"
+        );
+        // Leading and trailing "::" give no leading or trailing dash
+        assert_eq!(
+            pod_host_label("::1"),
+            "0000-0000-0000-0000-0000-0000-0000-0001"
+        );
+        assert_eq!(
+            pod_host_label("fd00::"),
+            "fd00-0000-0000-0000-0000-0000-0000-0000"
+        );
+    }
+
+    #[test]
+    fn test_parse_pod_host_label() {
+        for ip in ["10.1.0.5", "fd00:10:1::5", "::1", "fd00::"] {
+            let ip: IpAddr = ip.parse().unwrap();
+            assert_eq!(
+                parse_pod_host_label(&pod_host_label(&ip.to_string())),
+                Some(ip)
+            );
+        }
+        assert_eq!(parse_pod_host_label("nginx"), None);
+    }
 }
@@ -1,5 +1,5 @@
 #![allow(dead_code)]
-use std::net::Ipv4Addr;
+use std::net::IpAddr;
 use std::{collections::HashMap, sync::Arc};
 use tokio::sync::RwLock;
 
@@ -9,7 +9,8 @@ use common::{EndpointSubset, ServicePort};
 pub struct ServiceRecord {
     pub name: String,
     pub namespace: String,
-    pub cluster_ip: Option<Ipv4Addr>,
+    /// ClusterIPs of the Service, one per IP family
+    pub cluster_ips: Vec<IpAddr>,
     pub ports: Vec<ServicePort>,
 }
 
@@ -17,7 +18,7 @@ pub struct ServiceRecord {
 pub struct PodRecord {
     pub name: String,
     pub namespace: String,
-    pub pod_ip: Option<Ipv4Addr>,
+    pub pod_ip: Option<IpAddr>,
 }
 
 #[derive(Clone, Debug)]
@@ -13,8 +13,8 @@ mod vault;
 use crate::controllers::endpoint_controller::EndpointController;
 use crate::controllers::garbage_collector::GarbageCollector;
 use crate::controllers::{
-    CONTROLLER_MANAGER, ControllerManager, DeploymentController, NftablesController,
-    ReplicaSetController,
+    CONTROLLER_MANAGER, ControllerManager, DeploymentController, LoadBalancerController,
+    NetworkPolicyController, NftablesController, ReplicaSetController,
 };
 use crate::dns::authority::{run_dns_server, setup_dns_nftable};
 use crate::network::init;
@@ -71,6 +71,7 @@ async fn handle_start_command() -> anyhow::Result<()> {
         CONTROLLER_MANAGER.clone(),
         xline_store.clone(),
         node_registry.clone(),
+        cfg,
         4,
     )
     .await?;
@@ -178,13 +179,15 @@ async fn register_controllers(
     mgr: Arc<ControllerManager>,
     xline_store: Arc<XlineStore>,
     node_registry: Arc<NodeRegistry>,
+    cfg: &Config,
     workers: usize,
 ) -> anyhow::Result<()> {
     let gc = GarbageCollector::new(xline_store.clone());
     let rs = ReplicaSetController::new(xline_store.clone());
     let ep = EndpointController::new(xline_store.clone());
     let deploy = DeploymentController::new(xline_store.clone());
-    let nft = NftablesController::new(xline_store.clone(), node_registry);
+    let nft = NftablesController::new(xline_store.clone(), node_registry.clone());
+    let netpol = NetworkPolicyController::new(xline_store.clone(), node_registry.clone());
 
     mgr.clone()
         .register(Arc::new(RwLock::new(gc)), workers)
@@ -201,5 +204,20 @@ async fn register_controllers(
     mgr.clone()
         .register(Arc::new(RwLock::new(nft)), workers)
         .await?;
+    mgr.clone()
+        .register(Arc::new(RwLock::new(netpol)), workers)
+        .await?;
+
+    // LoadBalancer Services only get VIPs when a pool is configured
+    if let Some(lb_cfg) = &cfg.load_balancer_config {
+        let pool = lb_cfg
+            .pool
+            .parse()
+            .with_context(|| format!("invalid LoadBalancer pool {}", lb_cfg.pool))?;
+        let lb = LoadBalancerController::new(xline_store.clone(), node_registry, pool);
+        mgr.clone()
+            .register(Arc::new(RwLock::new(lb)), workers)
+            .await?;
+    }
     Ok(())
 }
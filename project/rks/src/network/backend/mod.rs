@@ -2,12 +2,20 @@
 use anyhow::Result;
 use async_trait::async_trait;
 use common::{ExternalInterface, lease::Lease};
-use libnetwork::config::NetworkConfig;
+use libnetwork::{
+    config::NetworkConfig,
+    overlay::{VXLAN_BACKEND, WIREGUARD_BACKEND},
+};
 use std::sync::Arc;
 use tokio::sync::Mutex;
 
+use crate::network::manager::LocalManager;
+
 pub mod hostgw;
 pub mod interface;
+pub mod overlay;
+pub mod vxlan;
+pub mod wireguard;
 
 #[cfg(test)]
 pub mod tests;
@@ -22,6 +30,20 @@ pub trait Backend: Send + Sync {
     fn backend_type(&self) -> &str;
 }
 
+/// Create the backend selected by the `Backend` section of the network config
+pub fn new_backend(
+    config: &NetworkConfig,
+    ext_iface: ExternalInterface,
+    subnet_manager: LocalManager,
+) -> Result<Box<dyn Backend>> {
+    Ok(match config.backend_type.as_str() {
+        VXLAN_BACKEND => Box::new(vxlan::VxlanBackend::new(ext_iface, subnet_manager)?),
+        WIREGUARD_BACKEND => Box::new(wireguard::WireguardBackend::new(ext_iface, subnet_manager)?),
+        "hostgw" | "host-gw" => Box::new(hostgw::HostgwBackend::new(ext_iface, subnet_manager)?),
+        other => anyhow::bail!("unsupported backend type: {other}"),
+    })
+}
+
 /// Network trait for managing network operations
 #[async_trait]
 pub trait Network: Send + Sync {
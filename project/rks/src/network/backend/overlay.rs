@@ -0,0 +1,250 @@
+use crate::network::{lease::LeaseWatcher, manager::LocalManager};
+use anyhow::{Context, Result, anyhow};
+use async_trait::async_trait;
+use common::{
+    ExternalInterface,
+    lease::{Event, EventType, Lease, LeaseAttrs},
+};
+use libnetwork::{
+    config::NetworkConfig,
+    overlay::{self, OverlayConfig, OverlayDevice},
+    route::{RouteListOps, RouteManager},
+};
+use log::{error, info};
+use netlink_packet_route::AddressFamily;
+use std::sync::Arc;
+use tokio::sync::{Mutex, mpsc};
+
+use super::{Network, SimpleNetwork};
+
+/// Acquire a lease carrying the backend data of this node, then create the
+/// tunnel device of the overlay with the tunnel address of the lease.
+///
+/// Shared by the VXLAN and WireGuard backends, which only differ in the
+/// `OverlayConfig` they pass.
+pub(super) async fn register_overlay_network(
+    ext_iface: &ExternalInterface,
+    subnet_manager: Arc<Mutex<LocalManager>>,
+    config: &NetworkConfig,
+    overlay_config: OverlayConfig,
+) -> Result<Arc<Mutex<dyn Network>>> {
+    let backend_type = overlay_config.backend_type();
+    let network_cidr = config
+        .network
+        .context("overlay backends require an IPv4 Network in the network config")?;
+
+    let lease_attrs = LeaseAttrs {
+        public_ip: ext_iface
+            .ext_addr
+            .or(ext_iface.iface_addr)
+            .ok_or_else(|| anyhow!("Don't have the ext_addr"))?,
+        public_ipv6: ext_iface.ext_v6_addr,
+        backend_type: backend_type.to_string(),
+        backend_data: overlay::local_backend_data(backend_type, ext_iface).await?,
+        ..Default::default()
+    };
+
+    let lease = {
+        let manager = subnet_manager.lock().await;
+        manager.acquire_lease(&lease_attrs).await?
+    };
+
+    let device =
+        OverlayDevice::ensure(&overlay_config, ext_iface, lease.subnet, network_cidr).await?;
+
+    let mut overlay_network = OverlayNetwork {
+        simple_network: SimpleNetwork {
+            ext_iface: ext_iface.clone(),
+            lease: None,
+        },
+        route_manager: RouteManager::new(ext_iface.iface.index, backend_type.to_string()),
+        device,
+        config: overlay_config,
+        lease_watcher: None,
+        subnet_manager,
+        running: false,
+    };
+    overlay_network.set_lease(lease).await?;
+
+    info!("{backend_type} network registered successfully");
+    Ok(Arc::new(Mutex::new(overlay_network)))
+}
+
+/// Network of an overlay backend: keeps the peers of the tunnel device and
+/// the routes through it in line with the leases of the other nodes.
+pub struct OverlayNetwork {
+    pub simple_network: SimpleNetwork,
+    pub route_manager: RouteManager,
+    pub device: OverlayDevice,
+    pub config: OverlayConfig,
+    pub lease_watcher: Option<LeaseWatcher>,
+    pub subnet_manager: Arc<Mutex<LocalManager>>,
+    pub running: bool,
+}
+
+impl OverlayNetwork {
+    /// Handle lease events (add/remove peers and their routes)
+    async fn handle_lease_event(&mut self, event: Event) -> Result<()> {
+        let Some(lease) = event.lease else {
+            return Ok(());
+        };
+        let Some(peer) = overlay::peer_from_lease(&lease, &self.config) else {
+            return Ok(());
+        };
+
+        match event.event_type {
+            EventType::Added => {
+                info!(
+                    "Subnet added: {} -> {}",
+                    lease.subnet, lease.attrs.public_ip
+                );
+
+                // The peer must be known before packets are routed to it
+                self.device.add_peer(peer).await?;
+                if let Some(route) = overlay::route_from_lease(&lease) {
+                    self.route_manager.add_route(&route).await?;
+                }
+            }
+            EventType::Removed => {
+                info!(
+                    "Subnet removed: {} -> {}",
+                    lease.subnet, lease.attrs.public_ip
+                );
+
+                if let Some(route) = overlay::route_from_lease(&lease) {
+                    self.route_manager
+                        .remove_from_route_list(&route, AddressFamily::Inet);
+                    self.route_manager.delete_route(&route).await?;
+                }
+                self.device.remove_peer(&peer).await?;
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Watch for lease changes and update peers and routes accordingly
+    async fn watch_leases(&mut self) -> Result<()> {
+        let (tx, mut rx) = mpsc::channel(100);
+
+        let manager = self.subnet_manager.clone();
+        tokio::spawn(async move {
+            let manager = manager.lock().await;
+            if let Err(e) = manager.watch_leases(tx).await {
+                error!("Failed to watch leases: {e}");
+            }
+        });
+
+        while let Some(results) = rx.recv().await {
+            for result in results {
+                let mut events = Vec::new();
+                if let Some(watcher) = &mut self.lease_watcher {
+                    if !result.snapshot.is_empty() {
+                        info!(
+                            "Received lease snapshot with {} leases",
+                            result.snapshot.len()
+                        );
+                        events.extend(watcher.reset(result.snapshot));
+                    }
+                    events.extend(watcher.update(result.events));
+                }
+
+                for event in events {
+                    if let Err(e) = self.handle_lease_event(event).await {
+                        error!("Failed to handle lease event: {e}");
+                    }
+                }
+            }
+
+            if !self.running {
+                break;
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Leases of the other nodes known so far
+    fn known_leases(&self) -> Vec<Lease> {
+        self.lease_watcher
+            .as_ref()
+            .map(|watcher| watcher.leases.clone())
+            .unwrap_or_default()
+    }
+}
+
+#[async_trait]
+impl Network for OverlayNetwork {
+    async fn get_lease(&self) -> Result<Lease> {
+        self.simple_network.get_lease().await
+    }
+
+    async fn set_lease(&mut self, lease: Lease) -> Result<()> {
+        info!(
+            "Setting lease for {} network: {}",
+            self.config.backend_type(),
+            lease.subnet
+        );
+
+        self.lease_watcher = Some(LeaseWatcher {
+            own_lease: lease.clone(),
+            leases: vec![],
+        });
+
+        self.simple_network.set_lease(lease).await
+    }
+
+    async fn run(&mut self) -> Result<()> {
+        if self.running {
+            return Err(anyhow!("Network is already running"));
+        }
+
+        info!(
+            "Starting {} network on interface {}",
+            self.config.backend_type(),
+            self.simple_network.ext_iface.iface.name
+        );
+        self.running = true;
+
+        self.simple_network.run().await?;
+
+        if let Err(e) = self.watch_leases().await {
+            error!("Lease watching failed: {e}");
+            self.running = false;
+            return Err(e);
+        }
+
+        Ok(())
+    }
+
+    async fn stop(&mut self) -> Result<()> {
+        if !self.running {
+            return Ok(());
+        }
+
+        info!("Stopping {} network", self.config.backend_type());
+        self.running = false;
+
+        for lease in self.known_leases() {
+            if let Some(route) = overlay::route_from_lease(&lease)
+                && let Err(e) = self.route_manager.delete_route(&route).await
+            {
+                error!("Failed to remove route to {}: {e}", lease.subnet);
+            }
+        }
+        self.device.sync_peers(&[]).await?;
+
+        self.simple_network.stop().await?;
+
+        info!("{} network stopped", self.config.backend_type());
+        Ok(())
+    }
+
+    fn mtu(&self) -> Option<u32> {
+        Some(self.device.mtu())
+    }
+
+    fn backend_type(&self) -> &str {
+        self.config.backend_type()
+    }
+}
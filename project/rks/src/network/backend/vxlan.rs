@@ -0,0 +1,60 @@
+use crate::network::manager::LocalManager;
+use anyhow::{Result, anyhow};
+use async_trait::async_trait;
+use common::ExternalInterface;
+use libnetwork::{
+    config::NetworkConfig,
+    overlay::{OverlayConfig, VXLAN_BACKEND},
+};
+use log::info;
+use std::sync::Arc;
+use tokio::sync::Mutex;
+
+use super::{Backend, Network, overlay::register_overlay_network};
+
+/// VXLAN backend implementation
+/// This backend encapsulates container traffic in UDP, so nodes only need
+/// IP connectivity between each other rather than a shared L2 segment
+pub struct VxlanBackend {
+    pub ext_iface: ExternalInterface,
+    pub subnet_manager: Arc<Mutex<LocalManager>>,
+}
+
+impl VxlanBackend {
+    /// Create a new VXLAN backend instance
+    pub fn new(ext_iface: ExternalInterface, subnet_manager: LocalManager) -> Result<Self> {
+        info!(
+            "Initializing vxlan backend on interface {} ({:?})",
+            ext_iface.iface.name, ext_iface.iface_addr
+        );
+
+        Ok(VxlanBackend {
+            ext_iface,
+            subnet_manager: Arc::new(Mutex::new(subnet_manager)),
+        })
+    }
+}
+
+#[async_trait]
+impl Backend for VxlanBackend {
+    async fn register_network(&self, config: &NetworkConfig) -> Result<Arc<Mutex<dyn Network>>> {
+        info!("Registering vxlan network with config");
+
+        let overlay_config = match OverlayConfig::from_network_config(config)? {
+            Some(cfg @ OverlayConfig::Vxlan(_)) => cfg,
+            _ => return Err(anyhow!("network config is not for the vxlan backend")),
+        };
+
+        register_overlay_network(
+            &self.ext_iface,
+            self.subnet_manager.clone(),
+            config,
+            overlay_config,
+        )
+        .await
+    }
+
+    fn backend_type(&self) -> &str {
+        VXLAN_BACKEND
+    }
+}
@@ -0,0 +1,60 @@
+use crate::network::manager::LocalManager;
+use anyhow::{Result, anyhow};
+use async_trait::async_trait;
+use common::ExternalInterface;
+use libnetwork::{
+    config::NetworkConfig,
+    overlay::{OverlayConfig, WIREGUARD_BACKEND},
+};
+use log::info;
+use std::sync::Arc;
+use tokio::sync::Mutex;
+
+use super::{Backend, Network, overlay::register_overlay_network};
+
+/// WireGuard backend implementation
+/// This backend sends container traffic through encrypted WireGuard tunnels,
+/// keyed by the public keys the nodes register with
+pub struct WireguardBackend {
+    pub ext_iface: ExternalInterface,
+    pub subnet_manager: Arc<Mutex<LocalManager>>,
+}
+
+impl WireguardBackend {
+    /// Create a new WireGuard backend instance
+    pub fn new(ext_iface: ExternalInterface, subnet_manager: LocalManager) -> Result<Self> {
+        info!(
+            "Initializing wireguard backend on interface {} ({:?})",
+            ext_iface.iface.name, ext_iface.iface_addr
+        );
+
+        Ok(WireguardBackend {
+            ext_iface,
+            subnet_manager: Arc::new(Mutex::new(subnet_manager)),
+        })
+    }
+}
+
+#[async_trait]
+impl Backend for WireguardBackend {
+    async fn register_network(&self, config: &NetworkConfig) -> Result<Arc<Mutex<dyn Network>>> {
+        info!("Registering wireguard network with config");
+
+        let overlay_config = match OverlayConfig::from_network_config(config)? {
+            Some(cfg @ OverlayConfig::Wireguard(_)) => cfg,
+            _ => return Err(anyhow!("network config is not for the wireguard backend")),
+        };
+
+        register_overlay_network(
+            &self.ext_iface,
+            self.subnet_manager.clone(),
+            config,
+            overlay_config,
+        )
+        .await
+    }
+
+    fn backend_type(&self) -> &str {
+        WIREGUARD_BACKEND
+    }
+}
@@ -12,11 +12,7 @@ use tokio::{
 use tokio_util::sync::CancellationToken;
 
 use crate::{
-    network::{
-        backend::{Backend, hostgw::HostgwBackend},
-        manager::LocalManager,
-        registry::XlineSubnetRegistry,
-    },
+    network::{backend::new_backend, manager::LocalManager, registry::XlineSubnetRegistry},
     protocol::config::XlineConfig,
 };
 use libnetwork::{
@@ -121,11 +117,11 @@ pub async fn init_network(cfg: &mut XlineConfig, cancel_token: CancellationToken
 
     info!("Selected external interface: {}", ext_iface.iface.name);
 
-    let backend = HostgwBackend::new(ext_iface, sm.clone()).map_err(|e| {
-        error!("Error creating HostGW backend: {e}");
+    let backend = new_backend(&config, ext_iface, sm.clone()).map_err(|e| {
+        error!("Error creating {} backend: {e}", config.backend_type);
         cancel_token.cancel();
         cancel_notify.notify_waiters();
-        anyhow::anyhow!("Error creating HostGW backend: {e}")
+        anyhow::anyhow!("Error creating {} backend: {e}", config.backend_type)
     })?;
 
     let network = backend.register_network(&config).await.map_err(|e| {
@@ -525,7 +525,7 @@ mod tests {
             .await
             .expect("failed to connect etcd");
         store
-            .init_flannel_config()
+            .init_flannel_config(serde_json::json!({ "Type": "vxlan", "VNI": 1 }))
             .await
             .expect("failed to init flannel config");
 
@@ -5,6 +5,7 @@ use chrono::Utc;
 use common::quic::RksConnection;
 use common::*;
 use common::{Node, NodeStatus, PodTask, RksMessage};
+use libnetwork::network_policy::validate_network_policy;
 use log::{error, info, warn};
 use std::sync::Arc;
 
@@ -46,6 +47,24 @@ pub async fn dispatch_worker(
                 );
             }
         }
+        RksMessage::SetPodIps((pod_name, pod_ips)) => {
+            if let Some(pod_yaml) = xline_store.get_pod_yaml(&pod_name).await? {
+                let mut pod: PodTask = serde_yaml::from_str(&pod_yaml)?;
+                pod.status.pod_ip = pod_ips.first().cloned();
+                pod.status.pod_ips = pod_ips.clone();
+                let new_yaml = serde_yaml::to_string(&pod)?;
+                xline_store.insert_pod_yaml(&pod_name, &new_yaml).await?;
+                info!(
+                    target: "rks::node::worker_dispatch",
+                    "updated Pod {pod_name} with IPs {pod_ips:?}"
+                );
+            } else {
+                warn!(
+                    target: "rks::node::worker_dispatch",
+                    "Pod {pod_name} not found when setting IPs"
+                );
+            }
+        }
         RksMessage::PodLogsChunk {
             ref namespace,
             ref pod_name,
@@ -375,6 +394,8 @@ pub async fn dispatch_user(
             if svc.metadata.creation_timestamp.is_none() {
                 svc.metadata.creation_timestamp = Some(Utc::now());
             }
+            // The status is written by the LoadBalancer controller only
+            svc.status = None;
             let yaml = serde_yaml::to_string(&*svc)?;
             xline_store.insert_service_yaml(&name, &yaml).await?;
             info!(
@@ -405,7 +426,9 @@ pub async fn dispatch_user(
                     "updated Service {name} (preserved state)"
                 );
             } else {
-                let yaml = serde_yaml::to_string(&*incoming_svc)?;
+                let mut svc = *incoming_svc;
+                svc.status = None;
+                let yaml = serde_yaml::to_string(&svc)?;
                 xline_store.insert_service_yaml(&name, &yaml).await?;
             }
             conn.send_msg(&RksMessage::Ack).await?;
@@ -451,6 +474,71 @@ pub async fn dispatch_user(
             conn.send_msg(&RksMessage::ListServiceRes(services)).await?;
         }
 
+        // NetworkPolicy operations
+        RksMessage::CreateNetworkPolicy(mut policy) => {
+            let name = policy.metadata.name.clone();
+            if xline_store.get_network_policy_yaml(&name).await?.is_some() {
+                let err_msg = format!("network policy \"{}\" already exists", name);
+                conn.send_msg(&RksMessage::Error(err_msg)).await?;
+                return Ok(());
+            }
+            if let Err(e) = validate_network_policy(&policy) {
+                let err_msg = format!("invalid network policy \"{}\": {:#}", name, e);
+                conn.send_msg(&RksMessage::Error(err_msg)).await?;
+                return Ok(());
+            }
+            if policy.metadata.creation_timestamp.is_none() {
+                policy.metadata.creation_timestamp = Some(Utc::now());
+            }
+            let yaml = serde_yaml::to_string(&*policy)?;
+            xline_store.insert_network_policy_yaml(&name, &yaml).await?;
+            info!(
+                target: "rks::node::user_dispatch",
+                "created NetworkPolicy {name}"
+            );
+            conn.send_msg(&RksMessage::Ack).await?;
+        }
+
+        RksMessage::DeleteNetworkPolicy(name) => {
+            xline_store
+                .delete_object(
+                    common::ResourceKind::NetworkPolicy,
+                    &name,
+                    common::DeletePropagationPolicy::Background,
+                )
+                .await?;
+            info!(
+                target: "rks::node::user_dispatch",
+                "marked NetworkPolicy {} for deletion (background policy)",
+                name
+            );
+            conn.send_msg(&RksMessage::Ack).await?;
+        }
+
+        RksMessage::GetNetworkPolicy(name) => {
+            if let Some(policy) = xline_store.get_network_policy(&name).await? {
+                conn.send_msg(&RksMessage::GetNetworkPolicyRes(Box::new(policy)))
+                    .await?;
+            } else {
+                conn.send_msg(&RksMessage::Error(format!(
+                    "NetworkPolicy {} not found",
+                    name
+                )))
+                .await?;
+            }
+        }
+
+        RksMessage::ListNetworkPolicy => {
+            let policies = xline_store.list_network_policies().await?;
+            info!(
+                target: "rks::node::user_dispatch",
+                "list current network policies: {} items",
+                policies.len()
+            );
+            conn.send_msg(&RksMessage::ListNetworkPolicyRes(policies))
+                .await?;
+        }
+
         RksMessage::GetNodeCount => {
             info!(
                 target: "rks::node::user_dispatch",
@@ -469,11 +557,14 @@ pub async fn dispatch_user(
             // Update the pod status in xline store
             if let Some(pod_yaml) = xline_store.get_pod_yaml(&pod_name).await? {
                 let mut pod_task: PodTask = serde_yaml::from_str(&pod_yaml)?;
-                // Preserve existing pod_ip if the incoming status does not carry it.
-                // This avoids wiping pod_ip set by SetPodip.
+                // Preserve existing pod IPs if the incoming status does not carry them.
+                // This avoids wiping the IPs set by SetPodip/SetPodIps.
                 if status.pod_ip.is_none() {
                     status.pod_ip = pod_task.status.pod_ip.clone();
                 }
+                if status.pod_ips.is_empty() {
+                    status.pod_ips = pod_task.status.pod_ips.clone();
+                }
                 pod_task.status = status;
                 let new_yaml = serde_yaml::to_string(&pod_task)?;
                 xline_store.insert_pod_yaml(&pod_name, &new_yaml).await?;
@@ -1,8 +1,9 @@
 use crate::network::manager::LocalManager;
 use crate::{network::lease::LeaseWatchResult, node::NodeRegistry};
 use common::RksMessage;
-use common::lease::Lease;
+use common::lease::{Lease, OverlayPeer};
 use libcni::ip::route::Route;
+use libnetwork::overlay::{self, OverlayConfig};
 use libnetwork::route;
 use log::{debug, error, info, warn};
 use std::sync::Arc;
@@ -31,10 +32,23 @@ impl LeaseSynchronizer {
             "subscribing to lease updates"
         );
         // Channel for receiving lease watch results
-        let mut lease_rx = Self::subscribe(manager);
+        let mut lease_rx = Self::subscribe(manager.clone());
 
         while let Some(results) = lease_rx.recv().await {
-            Self::process_results(registry.clone(), results).await;
+            let overlay = match manager.get_network_config().await {
+                Ok(config) => match OverlayConfig::from_network_config(&config) {
+                    Ok(overlay) => overlay,
+                    Err(e) => {
+                        error!("invalid overlay backend config: {e:?}");
+                        None
+                    }
+                },
+                Err(e) => {
+                    error!("failed to fetch network config: {e:?}");
+                    None
+                }
+            };
+            Self::process_results(registry.clone(), results, overlay.as_ref()).await;
         }
         warn!(
             target: "rks::node::lease",
@@ -57,7 +71,11 @@ impl LeaseSynchronizer {
         lease_rx
     }
 
-    async fn process_results(registry: Arc<NodeRegistry>, results: Vec<LeaseWatchResult>) {
+    async fn process_results(
+        registry: Arc<NodeRegistry>,
+        results: Vec<LeaseWatchResult>,
+        overlay: Option<&OverlayConfig>,
+    ) {
         let leases = results
             .iter()
             .flat_map(|r| r.snapshot.clone())
@@ -77,10 +95,13 @@ impl LeaseSynchronizer {
 
         for node_id in node_ids {
             let routes = calculate_routes_for_node(&node_id, &leases);
+            let peers = overlay
+                .map(|overlay| calculate_peers_for_node(&node_id, &leases, overlay))
+                .unwrap_or_default();
 
-            info!("sending routes to {node_id}: {routes:?}");
+            info!("sending routes to {node_id}: {routes:?}, overlay peers: {peers:?}");
 
-            let msg = RksMessage::UpdateRoutes(node_id.clone(), routes);
+            let msg = RksMessage::UpdateRoutes(node_id.clone(), routes, peers);
             if let Some(worker) = registry.get(&node_id).await {
                 if let Err(e) = worker.tx.try_send(msg) {
                     error!("Failed to enqueue message for {node_id}: {e:?}");
@@ -93,14 +114,35 @@ impl LeaseSynchronizer {
 }
 
 /// Calculate routes for a node from all current leases.
+///
+/// Overlay leases are routed through the tunnel address of their node, which
+/// only carries IPv4; the others through the public IPs of their node.
 fn calculate_routes_for_node(node_id: &str, leases: &[Lease]) -> Vec<Route> {
     leases
         .iter()
         .filter(|lease| lease.attrs.node_id != node_id)
         .flat_map(|lease| {
+            if overlay::is_overlay(&lease.attrs.backend_type) {
+                return overlay::route_from_lease(lease).into_iter().collect();
+            }
             route::get_route_from_lease(lease)
                 .into_iter()
                 .chain(route::get_v6_route_from_lease(lease))
+                .collect::<Vec<_>>()
         })
         .collect()
 }
+
+/// Calculate the overlay peers (FDB/ARP entries or WireGuard peers) a node
+/// needs to reach the subnets of the other nodes.
+fn calculate_peers_for_node(
+    node_id: &str,
+    leases: &[Lease],
+    overlay: &OverlayConfig,
+) -> Vec<OverlayPeer> {
+    leases
+        .iter()
+        .filter(|lease| lease.attrs.node_id != node_id)
+        .filter_map(|lease| overlay::peer_from_lease(lease, overlay))
+        .collect()
+}
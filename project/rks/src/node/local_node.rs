@@ -2,11 +2,12 @@ use crate::network::manager::LocalManager;
 use crate::node::register::build_node_network_config;
 use crate::node::{Shared, WorkerSession};
 use anyhow::{Context, Result};
-use common::RksMessage;
-use common::lease::LeaseAttrs;
+use common::lease::{LeaseAttrs, OverlayPeer};
+use common::{ExternalInterface, RksMessage};
 use ipnetwork::{IpNetwork, Ipv4Network};
 use libcni::ip::route::{self as ip_route, Route};
 use libnetwork::ip::{PublicIPOpts, get_ip_family, lookup_ext_iface};
+use libnetwork::overlay::{self, OverlayConfig, OverlayDevice};
 use libnetwork::route::{RouteListOps, RouteManager};
 use log::{debug, error, info, warn};
 use netlink_packet_route::AddressFamily;
@@ -47,10 +48,15 @@ pub(crate) async fn bootstrap(shared: Arc<Shared>, addr: &str) -> Result<()> {
         .unwrap_or(Ipv4Addr::UNSPECIFIED);
     let public_ipv6 = ext_iface.ext_v6_addr.or(ext_iface.iface_v6_addr);
 
+    let backend_data = overlay::local_backend_data(&network_config.backend_type, &ext_iface)
+        .await
+        .context("failed to prepare backend data for local rks node")?;
+
     let lease_attrs = LeaseAttrs {
         public_ip,
         public_ipv6,
         backend_type: network_config.backend_type.clone(),
+        backend_data,
         node_id: node_id.clone(),
         ..Default::default()
     };
@@ -73,10 +79,12 @@ pub(crate) async fn bootstrap(shared: Arc<Shared>, addr: &str) -> Result<()> {
         network_config.backend_type.clone(),
     )));
 
+    let iface_index = ext_iface.iface.index;
     spawn_local_session_loop(
         node_id.clone(),
         shared.local_manager.clone(),
         route_manager,
+        OverlayState::new(ext_iface),
         msg_rx,
     );
 
@@ -100,16 +108,33 @@ pub(crate) async fn bootstrap(shared: Arc<Shared>, addr: &str) -> Result<()> {
         "local rks node registered: id={}, lease={}, iface_index={}",
         node_id,
         lease.subnet,
-        ext_iface.iface.index
+        iface_index
     );
 
     Ok(())
 }
 
+/// Tunnel device of the local rks node when the cluster uses an overlay
+/// backend, created once the subnet of the node is known.
+struct OverlayState {
+    ext_iface: ExternalInterface,
+    device: Mutex<Option<OverlayDevice>>,
+}
+
+impl OverlayState {
+    fn new(ext_iface: ExternalInterface) -> Arc<Self> {
+        Arc::new(Self {
+            ext_iface,
+            device: Mutex::new(None),
+        })
+    }
+}
+
 fn spawn_local_session_loop(
     node_id: String,
     local_manager: Arc<LocalManager>,
     route_manager: Arc<Mutex<RouteManager>>,
+    overlay_state: Arc<OverlayState>,
     mut msg_rx: mpsc::Receiver<RksMessage>,
 ) {
     tokio::spawn(async move {
@@ -126,8 +151,12 @@ fn spawn_local_session_loop(
                         continue;
                     }
 
-                    if let Err(e) =
-                        apply_subnet_env(local_manager.clone(), &config.subnet_env).await
+                    if let Err(e) = apply_subnet_env(
+                        local_manager.clone(),
+                        overlay_state.clone(),
+                        &config.subnet_env,
+                    )
+                    .await
                     {
                         error!(
                             target: "rks::node::local",
@@ -136,7 +165,7 @@ fn spawn_local_session_loop(
                         );
                     }
                 }
-                RksMessage::UpdateRoutes(target, routes) => {
+                RksMessage::UpdateRoutes(target, routes, peers) => {
                     if target != node_id {
                         debug!(
                             target: "rks::node::local",
@@ -147,6 +176,7 @@ fn spawn_local_session_loop(
                         continue;
                     }
 
+                    apply_peers(overlay_state.clone(), peers).await;
                     apply_routes(route_manager.clone(), routes).await;
                 }
                 RksMessage::SetNftablesRules(_) | RksMessage::UpdateNftablesRules(_) => {
@@ -192,7 +222,11 @@ fn spawn_local_lease_completion(
     });
 }
 
-async fn apply_subnet_env(local_manager: Arc<LocalManager>, subnet_env: &str) -> Result<()> {
+async fn apply_subnet_env(
+    local_manager: Arc<LocalManager>,
+    overlay_state: Arc<OverlayState>,
+    subnet_env: &str,
+) -> Result<()> {
     let mut ipv4_subnet = None;
     let mut ip_masq = true;
     let mut mtu = 1500;
@@ -222,6 +256,18 @@ async fn apply_subnet_env(local_manager: Arc<LocalManager>, subnet_env: &str) ->
         .await
         .context("failed to reload network config when applying local subnet config")?;
 
+    if let Some(overlay_config) = OverlayConfig::from_env(subnet_env)? {
+        let network = config
+            .network
+            .context("overlay backends require an IPv4 Network in the network config")?;
+        let device =
+            OverlayDevice::ensure(&overlay_config, &overlay_state.ext_iface, subnet, network)
+                .await
+                .context("failed to set up overlay device for local rks node")?;
+        mtu = device.mtu();
+        *overlay_state.device.lock().await = Some(device);
+    }
+
     let subnet_file = subnet_file_path();
     local_manager
         .handle_subnet_file(&subnet_file, &config, ip_masq, subnet, None, mtu)
@@ -241,6 +287,27 @@ async fn apply_subnet_env(local_manager: Arc<LocalManager>, subnet_env: &str) ->
     Ok(())
 }
 
+async fn apply_peers(overlay_state: Arc<OverlayState>, peers: Vec<OverlayPeer>) {
+    let mut device = overlay_state.device.lock().await;
+    let Some(device) = device.as_mut() else {
+        if !peers.is_empty() {
+            warn!(
+                target: "rks::node::local",
+                "skip {} overlay peers: overlay device not set up",
+                peers.len()
+            );
+        }
+        return;
+    };
+
+    if let Err(e) = device.sync_peers(&peers).await {
+        error!(
+            target: "rks::node::local",
+            "failed to sync overlay peers: {e:#}"
+        );
+    }
+}
+
 async fn apply_routes(route_manager: Arc<Mutex<RouteManager>>, routes: Vec<Route>) {
     info!(
         target: "rks::node::local",
@@ -5,6 +5,7 @@ use crate::node::server::QUICServer;
 use crate::vault::Vault;
 use common::RksMessage;
 use common::lease::Lease;
+use libnetwork::network_policy::POLICY_TABLE;
 use log::info;
 use log::warn;
 use nftables::{batch::Batch, schema, types};
@@ -104,6 +105,12 @@ impl NodeRegistry {
         {
             warn!("Failed to send nftables cleanup to node {}: {}", node_id, e);
         }
+        if let Err(e) = session
+            .tx
+            .try_send(RksMessage::SetLoadBalancerVips(Vec::new()))
+        {
+            warn!("Failed to send VIP cleanup to node {}: {}", node_id, e);
+        }
 
         session.cancel_notify.notify_one();
     }
@@ -139,6 +146,23 @@ fn build_delete_table_ruleset() -> String {
         name: Cow::Borrowed("rk8s"),
         ..Default::default()
     }));
+    // Lift the network policy isolation too. Adding the table first keeps the
+    // batch from failing if the policy rules never reached the node.
+    let policy_table = schema::Table {
+        family: types::NfFamily::INet,
+        name: Cow::Borrowed(POLICY_TABLE),
+        ..Default::default()
+    };
+    batch.add(schema::NfListObject::Table(policy_table.clone()));
+    batch.delete(schema::NfListObject::Table(policy_table));
+    // The ip6 table only exists on nodes that served IPv6 ClusterIPs
+    let ip6_table = schema::Table {
+        family: types::NfFamily::IP6,
+        name: Cow::Borrowed("rk8s"),
+        ..Default::default()
+    };
+    batch.add(schema::NfListObject::Table(ip6_table.clone()));
+    batch.delete(schema::NfListObject::Table(ip6_table));
 
     serde_json::to_string(&batch.to_nftables()).unwrap_or_else(|e| {
         warn!("Failed to serialize nft delete-table ruleset: {}", e);
@@ -1,13 +1,17 @@
+use crate::controllers::load_balancer_controller::build_node_vips;
+use crate::controllers::network_policy_controller::build_node_rules;
 use crate::controllers::nftrules_controller::build_rules;
 use crate::node::{Shared, WorkerSession};
 use anyhow::Context;
-use common::lease::{Lease, LeaseAttrs};
+use common::lease::{BACKEND_DATA_ANNOTATION, BACKEND_TYPE_ANNOTATION, Lease, LeaseAttrs};
 use common::quic::RksConnection;
 use common::{Node, NodeNetworkConfig, RksMessage, log_error, reply_error_msg_and_bail};
 use ipnetwork::{Ipv4Network, Ipv6Network};
 use libnetwork::config::NetworkConfig;
+use libnetwork::overlay::{self, OverlayConfig};
 use log::info;
-use std::net::{IpAddr, Ipv4Addr};
+use serde_json::Value as JsonValue;
+use std::net::{IpAddr, Ipv4Addr, Ipv6Addr};
 use std::sync::Arc;
 use tokio::sync::mpsc;
 
@@ -30,33 +34,60 @@ impl<'a> NodeRegister<'a> {
             );
         }
 
-        let lease = self.node_set_lease(&id).await?;
+        let config = self.shared.local_manager.get_network_config().await?;
+        info!("fetched network config: {config:?}");
+
+        let backend_data = match node_backend_data(&node, &config.backend_type) {
+            Ok(data) => data,
+            Err(e) => {
+                reply_error_msg_and_bail!(
+                    self.conn,
+                    &RksMessage::Error(format!("invalid node {id}: {e}"))
+                );
+            }
+        };
+
+        let lease = self
+            .node_set_lease(
+                &id,
+                &config.backend_type,
+                backend_data,
+                node_internal_ipv6(&node),
+            )
+            .await?;
 
         let subnet = lease.subnet;
         let ipv6_subnet = lease.ipv6_subnet;
 
         self.register_node_in_registry(node, &id, lease).await?;
 
-        let config = self.shared.local_manager.get_network_config().await?;
-        info!("fetched network config: {config:?}");
         self.node_config_network(&id, &config, subnet, ipv6_subnet)
             .await?;
 
         Ok((true, Some(id)))
     }
 
-    async fn node_set_lease(&self, node_id: impl Into<String>) -> anyhow::Result<Lease> {
+    async fn node_set_lease(
+        &self,
+        node_id: impl Into<String>,
+        backend_type: &str,
+        backend_data: Option<JsonValue>,
+        node_ipv6: Option<Ipv6Addr>,
+    ) -> anyhow::Result<Lease> {
         let node_id = node_id.into();
 
+        // Nodes connecting over IPv4 report their IPv6 address, which routes
+        // their IPv6 pod subnet in a dual-stack cluster
         let (public_ip, public_ipv6) = match self.conn.remote_address().ip() {
-            IpAddr::V4(v4) => (v4, None),
+            IpAddr::V4(v4) => (v4, node_ipv6),
             IpAddr::V6(v6) => (Ipv4Addr::new(0, 0, 0, 0), Some(v6)),
         };
 
         let lease_attrs = LeaseAttrs {
             public_ip,
             public_ipv6,
-            backend_type: "hostgw".to_string(),
+            backend_type: backend_type.to_string(),
+            backend_data,
             node_id,
             ..Default::default()
         };
@@ -82,6 +113,10 @@ impl<'a> NodeRegister<'a> {
         let (msg_tx, mut msg_rx) = mpsc::channel::<RksMessage>(32);
 
         node.spec.pod_cidr = subnet.to_string();
+        node.spec.pod_cidrs = match lease.ipv6_subnet {
+            Some(ipv6_subnet) => vec![subnet.to_string(), ipv6_subnet.to_string()],
+            None => vec![],
+        };
         self.shared.xline_store.insert_node(&node).await?;
 
         info!(
@@ -96,7 +131,7 @@ impl<'a> NodeRegister<'a> {
             .await;
 
         // Send current nftables rules
-        match build_rules(&self.shared.xline_store).await {
+        match build_rules(&self.shared.xline_store, &node_id).await {
             Ok(rules) => {
                 let msg = RksMessage::SetNftablesRules(rules);
                 if let Err(e) = msg_tx.try_send(msg) {
@@ -116,6 +151,46 @@ impl<'a> NodeRegister<'a> {
             }
         }
 
+        // Send the network policy rules of this node
+        match build_node_rules(&self.shared.xline_store, &node_id).await {
+            Ok(rules) => {
+                let msg = RksMessage::SetNftablesRules(rules);
+                if let Err(e) = msg_tx.try_send(msg) {
+                    log::warn!(
+                        "Failed to send initial network policy rules to {}: {}",
+                        node_id,
+                        e
+                    );
+                }
+            }
+            Err(e) => {
+                log::error!(
+                    "Failed to build initial network policy rules for {}: {}",
+                    node_id,
+                    e
+                );
+            }
+        }
+
+        // Send the LoadBalancer VIPs this node announces
+        match build_node_vips(
+            &self.shared.xline_store,
+            &self.shared.node_registry,
+            &node_id,
+        )
+        .await
+        {
+            Ok(vips) if !vips.is_empty() => {
+                if let Err(e) = msg_tx.try_send(RksMessage::SetLoadBalancerVips(vips)) {
+                    log::warn!("Failed to send initial VIPs to {}: {}", node_id, e);
+                }
+            }
+            Ok(_) => {}
+            Err(e) => {
+                log::error!("Failed to build initial VIPs for {}: {}", node_id, e);
+            }
+        }
+
         self.conn.send_msg(&RksMessage::Ack).await?;
 
         let conn = self.conn.clone();
@@ -191,8 +266,52 @@ pub fn build_node_network_config(
 
     contents += &format!("RKL_IPMASQ={ip_masq}\n");
 
+    if let Some(overlay) = OverlayConfig::from_network_config(config)? {
+        contents += &overlay.env_lines();
+    }
+
     Ok(NodeNetworkConfig {
         node_id,
         subnet_env: contents,
     })
 }
+
+/// IPv6 `InternalIP` a node registered with, if it has one
+pub fn node_internal_ipv6(node: &Node) -> Option<Ipv6Addr> {
+    node.status
+        .addresses
+        .iter()
+        .filter(|addr| addr.address_type == "InternalIP")
+        .find_map(|addr| addr.address.parse().ok())
+}
+
+/// Backend data a node registered with, taken from its annotations.
+///
+/// Overlay backends cannot reach a node without it (VTEP MAC, WireGuard
+/// public key), so it is required for them, and the node must have set
+/// itself up for the backend of the cluster.
+pub fn node_backend_data(node: &Node, backend_type: &str) -> anyhow::Result<Option<JsonValue>> {
+    if !overlay::is_overlay(backend_type) {
+        return Ok(None);
+    }
+
+    let annotations = &node.metadata.annotations;
+    let node_backend = annotations
+        .get(BACKEND_TYPE_ANNOTATION)
+        .map(String::as_str)
+        .unwrap_or_default();
+    if node_backend != backend_type {
+        anyhow::bail!(
+            "cluster uses the {backend_type} backend but the node is set up for {:?} (set BACKEND_TYPE={backend_type} on the node)",
+            node_backend
+        );
+    }
+
+    let raw = annotations
+        .get(BACKEND_DATA_ANNOTATION)
+        .with_context(|| format!("missing {BACKEND_DATA_ANNOTATION} annotation"))?;
+    let data: JsonValue = serde_json::from_str(raw)
+        .with_context(|| format!("invalid {BACKEND_DATA_ANNOTATION} annotation"))?;
+    overlay::validate_backend_data(backend_type, &data)?;
+    Ok(Some(data))
+}
@@ -24,6 +24,9 @@ pub struct Config {
     pub tls_config: TLSConfig,
     // DNS config
     pub dns_config: DnsConfig,
+    // LoadBalancer Services config, they get no VIP without it
+    #[serde(default)]
+    pub load_balancer_config: Option<LoadBalancerConfig>,
 }
 
 #[allow(dead_code)]
@@ -50,6 +53,45 @@ pub struct NetworkConfig {
 
     #[serde(rename = "SubnetLen")]
     pub subnet_len: u8,
+
+    /// Also give pods an IPv6 address from `IPv6Network` (dual-stack)
+    #[serde(rename = "EnableIPv6", default)]
+    pub enable_ipv6: bool,
+
+    #[serde(
+        rename = "IPv6Network",
+        default,
+        skip_serializing_if = "Option::is_none"
+    )]
+    pub ipv6_network: Option<String>,
+
+    #[serde(
+        rename = "IPv6SubnetMin",
+        default,
+        skip_serializing_if = "Option::is_none"
+    )]
+    pub ipv6_subnet_min: Option<String>,
+
+    #[serde(
+        rename = "IPv6SubnetMax",
+        default,
+        skip_serializing_if = "Option::is_none"
+    )]
+    pub ipv6_subnet_max: Option<String>,
+
+    /// Prefix length of the IPv6 subnet of each node, /64 by default
+    #[serde(
+        rename = "IPv6SubnetLen",
+        default,
+        skip_serializing_if = "Option::is_none"
+    )]
+    pub ipv6_subnet_len: Option<u8>,
+
+    /// Backend selecting how nodes reach each other: `{"Type": "hostgw"}`
+    /// (default), `{"Type": "vxlan", "VNI": 1, "Port": 8472}` or
+    /// `{"Type": "wireguard", "ListenPort": 51820}`
+    #[serde(rename = "Backend", default, skip_serializing_if = "Option::is_none")]
+    pub backend: Option<serde_json::Value>,
 }
 
 #[derive(Debug, Clone, Deserialize)]
@@ -72,6 +114,13 @@ pub struct DnsConfig {
     pub port: u16,
 }
 
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct LoadBalancerConfig {
+    /// CIDR the VIPs of LoadBalancer Services are allocated from
+    #[serde(rename = "Pool")]
+    pub pool: String,
+}
+
 pub fn load_config(path: &str) -> anyhow::Result<&'static Config> {
     let content =
         fs::read_to_string(path).with_context(|| format!("Failed to read config from {path}"))?;
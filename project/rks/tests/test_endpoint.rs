@@ -3,7 +3,8 @@ use std::sync::Arc;
 
 use anyhow::Result;
 use common::{
-    LabelSelector, ObjectMeta, PodSpec, PodStatus, PodTask, ServicePort, ServiceSpec, ServiceTask,
+    ConditionStatus, ENDPOINT_WEIGHT_ANNOTATION, IpFamily, LabelSelector, ObjectMeta, PodCondition,
+    PodConditionType, PodSpec, PodStatus, PodTask, ServicePort, ServiceSpec, ServiceTask,
 };
 use libvault::storage::xline::XlineOptions;
 use log::LevelFilter;
@@ -130,12 +131,14 @@ fn service_with_selector_and_port(
         selector,
         ports,
         cluster_ip: Some("10.96.0.1".to_string()),
+        ..Default::default()
     };
     ServiceTask {
         api_version: "v1".to_string(),
         kind: "Service".to_string(),
         metadata: meta,
         spec,
+        status: None,
     }
 }
 
@@ -320,6 +323,8 @@ async fn test_external_name_service_is_skipped() -> Result<()> {
             node_port: None,
         }],
         cluster_ip: None,
+
+        ..Default::default()
     };
     let svc = ServiceTask {
         api_version: "v1".to_string(),
@@ -329,6 +334,7 @@ async fn test_external_name_service_is_skipped() -> Result<()> {
             ..Default::default()
         },
         spec,
+        status: None,
     };
     let svc_yaml = serde_yaml::to_string(&svc)?;
     store
@@ -371,6 +377,8 @@ async fn test_nil_selector_service_is_skipped() -> Result<()> {
             node_port: None,
         }],
         cluster_ip: Some("10.96.0.10".to_string()),
+
+        ..Default::default()
     };
     let svc = ServiceTask {
         api_version: "v1".to_string(),
@@ -380,6 +388,7 @@ async fn test_nil_selector_service_is_skipped() -> Result<()> {
             ..Default::default()
         },
         spec,
+        status: None,
     };
     let svc_yaml = serde_yaml::to_string(&svc)?;
     store
@@ -550,3 +559,120 @@ async fn test_update_while_processing_applies_latest() -> Result<()> {
     clean_store(&store).await?;
     Ok(())
 }
+
+#[tokio::test]
+#[serial]
+async fn test_not_ready_and_weighted_pods() -> Result<()> {
+    let store = get_store().await;
+    if store.is_none() {
+        return Ok(());
+    }
+    let store = store.unwrap();
+    clean_store(&store).await?;
+
+    let selector = LabelSelector {
+        match_labels: HashMap::new(),
+        match_expressions: vec![],
+    };
+    let svc = service_with_selector_and_port("svc-ready", Some(selector), 80, None);
+    let svc_yaml = serde_yaml::to_string(&svc)?;
+    store
+        .insert_service_yaml(&svc.metadata.name, &svc_yaml)
+        .await?;
+
+    let _mgr = setup_endpoint_controller(store.clone()).await?;
+
+    // A pod failing its readiness check is only listed as not ready
+    let mut not_ready = pod_with_ip_and_labels("pod-not-ready", "10.0.4.1", HashMap::new());
+    not_ready.status.conditions = Some(vec![PodCondition {
+        condition_type: PodConditionType::PodReady,
+        status: ConditionStatus::False,
+        ..Default::default()
+    }]);
+    store
+        .insert_pod_yaml(
+            &not_ready.metadata.name,
+            &serde_yaml::to_string(&not_ready)?,
+        )
+        .await?;
+
+    // The weight annotation is clamped to the maximum
+    let mut weighted = pod_with_ip_and_labels("pod-weighted", "10.0.4.2", HashMap::new());
+    weighted
+        .metadata
+        .annotations
+        .insert(ENDPOINT_WEIGHT_ANNOTATION.to_string(), "500".to_string());
+    store
+        .insert_pod_yaml(&weighted.metadata.name, &serde_yaml::to_string(&weighted)?)
+        .await?;
+
+    wait_for_endpoints_ips(&store, "svc-ready", &["10.0.4.2"], Duration::from_secs(5)).await?;
+
+    let eps = store.list_endpoints().await?;
+    let ep = eps
+        .into_iter()
+        .find(|e| e.metadata.name == "svc-ready")
+        .expect("endpoints not found");
+    let subset = &ep.subsets[0];
+    assert!(subset.addresses.iter().all(|a| a.ip != "10.0.4.1"));
+    assert_eq!(subset.addresses[0].weight, Some(100));
+    assert!(
+        subset
+            .not_ready_addresses
+            .iter()
+            .any(|a| a.ip == "10.0.4.1")
+    );
+
+    clean_store(&store).await?;
+    Ok(())
+}
+
+#[tokio::test]
+#[serial]
+async fn test_dual_stack_pod_addresses() -> Result<()> {
+    let store = get_store().await;
+    if store.is_none() {
+        return Ok(());
+    }
+    let store = store.unwrap();
+    clean_store(&store).await?;
+
+    let selector = LabelSelector {
+        match_labels: HashMap::new(),
+        match_expressions: vec![],
+    };
+    let mut dual = service_with_selector_and_port("svc-dual", Some(selector.clone()), 80, None);
+    dual.spec.cluster_ips = vec!["10.96.0.1".to_string(), "fd00:96::1".to_string()];
+    dual.spec.ip_families = vec![IpFamily::IPv4, IpFamily::IPv6];
+    let single = service_with_selector_and_port("svc-single", Some(selector), 80, None);
+    for svc in [&dual, &single] {
+        store
+            .insert_service_yaml(&svc.metadata.name, &serde_yaml::to_string(svc)?)
+            .await?;
+    }
+
+    let _mgr = setup_endpoint_controller(store.clone()).await?;
+
+    let mut pod = pod_with_ip_and_labels("pod-dual", "10.0.5.1", HashMap::new());
+    pod.status.pod_ips = vec!["10.0.5.1/24".to_string(), "fd00:5::1/64".to_string()];
+    store
+        .insert_pod_yaml(&pod.metadata.name, &serde_yaml::to_string(&pod)?)
+        .await?;
+
+    // The dual-stack Service lists both pod IPs, the IPv4 one only the IPv4 IP
+    wait_for_endpoints_ips(
+        &store,
+        "svc-dual",
+        &["10.0.5.1", "fd00:5::1"],
+        Duration::from_secs(5),
+    )
+    .await?;
+    wait_for_endpoints_ips(&store, "svc-single", &["10.0.5.1"], Duration::from_secs(5)).await?;
+    assert_eq!(
+        get_endpoints_ips(&store, "svc-single").await?,
+        vec!["10.0.5.1"]
+    );
+
+    clean_store(&store).await?;
+    Ok(())
+}
@@ -65,6 +65,7 @@ fn create_test_node(name: &str, cpu: &str, memory: &str) -> Node {
         },
         spec: NodeSpec {
             pod_cidr: "10.244.0.0/24".to_string(),
+            pod_cidrs: vec![],
             taints: vec![],
         },
         status: NodeStatus {
@@ -14,7 +14,7 @@ use libfuzzer_sys::fuzz_target;
 use slayerfs::{
     CacheConfig, ChunkLayout, ClientOptions, Config, DatabaseConfig, DatabaseMetaStore,
     DatabaseType, EtcdMetaStore, LocalFsBackend, MetaClient, MetaStore, ObjectBlockStore,
-    ObjectClient, SetAttrFlags, SetAttrRequest, VFS, VfsFileAttr, VfsFileType,
+    ObjectClient, RedisMetaStore, SetAttrFlags, SetAttrRequest, VFS, VfsFileAttr, VfsFileType,
 };
 use tokio::runtime::Builder;
 use tokio::task::JoinSet;
@@ -43,6 +43,8 @@ const ALL_NAMES: [&str; 16] = [
     "f0", "f1", "f2", "f3", "f4", "f5", "h0", "h1", "h2", "h3", "s0", "s1", "s2", "s3", "sub0",
     "sub1",
 ];
+const XATTR_NAMES: [&str; 3] = ["user.a", "user.b", "user.c"];
+const MAX_XATTR_VALUE: u64 = 64;
 
 type SlayerVfs = VFS<ObjectBlockStore<LocalFsBackend>, MetaClient<Arc<dyn MetaStore>>>;
 
@@ -75,6 +77,10 @@ enum OpKind {
     Chmod = 23,
     Fchmod = 24,
     Rename = 25,
+    SetXattr = 26,
+    GetXattr = 27,
+    ListXattr = 28,
+    RemoveXattr = 29,
 }
 
 impl From<u8> for OpKind {
@@ -106,6 +112,10 @@ impl From<u8> for OpKind {
             23 => OpKind::Chmod,
             24 => OpKind::Fchmod,
             25 => OpKind::Rename,
+            26 => OpKind::SetXattr,
+            27 => OpKind::GetXattr,
+            28 => OpKind::ListXattr,
+            29 => OpKind::RemoveXattr,
             _ => panic!("unknown op kind"),
         }
     }
@@ -113,7 +123,7 @@ impl From<u8> for OpKind {
 
 impl OpKind {
     fn count() -> u8 {
-        26
+        30
     }
 }
 
@@ -257,6 +267,23 @@ enum Op {
         slot: usize,
         mode: u32,
     },
+    SetXattr {
+        path: String,
+        name: String,
+        value: Vec<u8>,
+        flags: u32,
+    },
+    GetXattr {
+        path: String,
+        name: String,
+    },
+    ListXattr {
+        path: String,
+    },
+    RemoveXattr {
+        path: String,
+        name: String,
+    },
 }
 
 #[derive(Clone, Copy)]
@@ -551,6 +578,35 @@ impl<'a> ByteCursor<'a> {
                 slot: self.next_u8()? as usize % MAX_FDS,
                 mode: self.next_mode()?,
             },
+            OpKind::SetXattr => {
+                let path = self.choose_chmod_path()?;
+                let name = self.choose(&XATTR_NAMES)?.to_string();
+                let len = (self.next_u64()? % MAX_XATTR_VALUE) as usize;
+                let seed = self.next_u64()?;
+                let value = Self::synth_data(seed, len);
+                let flags = match self.next_u8()? % 3 {
+                    0 => 0,
+                    1 => libc::XATTR_CREATE as u32,
+                    _ => libc::XATTR_REPLACE as u32,
+                };
+                Op::SetXattr {
+                    path,
+                    name,
+                    value,
+                    flags,
+                }
+            }
+            OpKind::GetXattr => Op::GetXattr {
+                path: self.choose_chmod_path()?,
+                name: self.choose(&XATTR_NAMES)?.to_string(),
+            },
+            OpKind::ListXattr => Op::ListXattr {
+                path: self.choose_chmod_path()?,
+            },
+            OpKind::RemoveXattr => Op::RemoveXattr {
+                path: self.choose_chmod_path()?,
+                name: self.choose(&XATTR_NAMES)?.to_string(),
+            },
         })
     }
 }
@@ -1211,10 +1267,111 @@ fn model_utimens(
     }
 }
 
+fn c_path_of(path: &Path) -> io::Result<CString> {
+    CString::new(path.as_os_str().as_bytes())
+        .map_err(|_| io::Error::new(io::ErrorKind::InvalidInput, "path contains NUL"))
+}
+
+fn c_name_of(name: &str) -> io::Result<CString> {
+    CString::new(name).map_err(|_| io::Error::new(io::ErrorKind::InvalidInput, "name contains NUL"))
+}
+
+fn model_setxattr(path: &Path, name: &str, value: &[u8], flags: u32) -> io::Result<()> {
+    let c_path = c_path_of(path)?;
+    let c_name = c_name_of(name)?;
+
+    // SAFETY: all pointers are valid for the duration of this call.
+    let rc = unsafe {
+        libc::lsetxattr(
+            c_path.as_ptr(),
+            c_name.as_ptr(),
+            value.as_ptr().cast(),
+            value.len(),
+            flags as libc::c_int,
+        )
+    };
+
+    if rc == 0 {
+        Ok(())
+    } else {
+        Err(io::Error::last_os_error())
+    }
+}
+
+fn model_getxattr(path: &Path, name: &str) -> io::Result<Vec<u8>> {
+    let c_path = c_path_of(path)?;
+    let c_name = c_name_of(name)?;
+    let mut buf = vec![0u8; MAX_XATTR_VALUE as usize];
+
+    // SAFETY: all pointers are valid and `buf` holds `buf.len()` bytes.
+    let rc = unsafe {
+        libc::lgetxattr(
+            c_path.as_ptr(),
+            c_name.as_ptr(),
+            buf.as_mut_ptr().cast(),
+            buf.len(),
+        )
+    };
+
+    if rc < 0 {
+        return Err(io::Error::last_os_error());
+    }
+    buf.truncate(rc as usize);
+    Ok(buf)
+}
+
+fn model_listxattr(path: &Path) -> io::Result<Vec<String>> {
+    let c_path = c_path_of(path)?;
+    let mut buf = vec![0u8; 4096];
+
+    // SAFETY: all pointers are valid and `buf` holds `buf.len()` bytes.
+    let rc = unsafe { libc::llistxattr(c_path.as_ptr(), buf.as_mut_ptr().cast(), buf.len()) };
+
+    if rc < 0 {
+        return Err(io::Error::last_os_error());
+    }
+    buf.truncate(rc as usize);
+    Ok(buf
+        .split(|b| *b == 0)
+        .filter(|name| !name.is_empty())
+        .map(|name| String::from_utf8_lossy(name).into_owned())
+        .collect())
+}
+
+fn model_removexattr(path: &Path, name: &str) -> io::Result<()> {
+    let c_path = c_path_of(path)?;
+    let c_name = c_name_of(name)?;
+
+    // SAFETY: all pointers are valid for the duration of this call.
+    let rc = unsafe { libc::lremovexattr(c_path.as_ptr(), c_name.as_ptr()) };
+
+    if rc == 0 {
+        Ok(())
+    } else {
+        Err(io::Error::last_os_error())
+    }
+}
+
+/// Only `user.*` names are fuzzed; other namespaces (e.g. SELinux labels) on
+/// the model side are ignored.
+fn user_xattrs(mut names: Vec<String>) -> Vec<String> {
+    names.retain(|name| name.starts_with("user."));
+    names.sort();
+    names
+}
+
+/// The model tempdir may live on a filesystem without user xattrs, and Linux
+/// refuses user xattrs on symlinks; such cases are skipped instead of compared.
+fn xattr_unsupported(path: &Path, model: &io::Result<impl Sized>) -> bool {
+    let symlink = fs::symlink_metadata(path).is_ok_and(|m| m.file_type().is_symlink());
+    symlink || matches!(model, Err(e) if e.raw_os_error() == Some(libc::EOPNOTSUPP))
+}
+
 #[derive(Debug, Clone)]
 enum FuzzMetaConfig {
     Sqlite { url: String },
     Etcd { urls: Vec<String> },
+    Redis { url: String },
 }
 
 async fn new_local_vfs(root: &Path, meta: &FuzzMetaConfig) -> io::Result<SlayerVfs> {
@@ -1253,6 +1410,19 @@ async fn new_local_vfs(root: &Path, meta: &FuzzMetaConfig) -> io::Result<SlayerV
                 .map_err(io::Error::other)?;
             Arc::new(store)
         }
+        FuzzMetaConfig::Redis { url } => {
+            let cfg = Config {
+                database: DatabaseConfig {
+                    db_config: DatabaseType::Redis { url: url.clone() },
+                },
+                cache: CacheConfig::default(),
+                client,
+            };
+            let store = RedisMetaStore::from_config(cfg)
+                .await
+                .map_err(io::Error::other)?;
+            Arc::new(store)
+        }
     };
 
     VFS::new(LAYOUT, store, meta_store)
@@ -1289,6 +1459,13 @@ fn fuzz_etcd_urls_from_env() -> Vec<String> {
         .unwrap_or_else(|| vec!["127.0.0.1:2379".to_string()])
 }
 
+fn fuzz_redis_url_from_env() -> String {
+    std::env::var("SLAYERFS_FUZZ_REDIS_URL")
+        .ok()
+        .filter(|v| !v.trim().is_empty())
+        .unwrap_or_else(|| "redis://127.0.0.1:6379/0".to_string())
+}
+
 fn fuzz_meta_config_from_env() -> FuzzMetaConfig {
     let backend = std::env::var("SLAYERFS_FUZZ_META_BACKEND")
         .ok()
@@ -1298,6 +1475,10 @@ fn fuzz_meta_config_from_env() -> FuzzMetaConfig {
         FuzzMetaConfig::Etcd {
             urls: fuzz_etcd_urls_from_env(),
         }
+    } else if backend.eq_ignore_ascii_case("redis") {
+        FuzzMetaConfig::Redis {
+            url: fuzz_redis_url_from_env(),
+        }
     } else {
         FuzzMetaConfig::Sqlite {
             url: fuzz_meta_url_from_env(),
@@ -1316,6 +1497,19 @@ async fn reset_etcd_for_case(urls: &[String]) -> io::Result<()> {
     Ok(())
 }
 
+async fn reset_redis_for_case(url: &str) -> io::Result<()> {
+    let client = redis::Client::open(url).map_err(io::Error::other)?;
+    let mut conn = client
+        .get_multiplexed_async_connection()
+        .await
+        .map_err(io::Error::other)?;
+    redis::cmd("FLUSHDB")
+        .query_async::<_, ()>(&mut conn)
+        .await
+        .map_err(io::Error::other)?;
+    Ok(())
+}
+
 fn fuzz_concurrent_from_env() -> bool {
     std::env::var("SLAYERFS_FUZZ_CONCURRENT")
         .ok()
@@ -1344,9 +1538,12 @@ async fn run_case(data: &[u8]) {
 
     let meta = fuzz_meta_config_from_env();
 
-    if let FuzzMetaConfig::Etcd { urls } = &meta
-        && reset_etcd_for_case(urls).await.is_err()
-    {
+    let reset = match &meta {
+        FuzzMetaConfig::Etcd { urls } => reset_etcd_for_case(urls).await,
+        FuzzMetaConfig::Redis { url } => reset_redis_for_case(url).await,
+        FuzzMetaConfig::Sqlite { .. } => Ok(()),
+    };
+    if reset.is_err() {
         return;
     }
 
@@ -1370,6 +1567,7 @@ fn meta_config_for_clients(meta: &FuzzMetaConfig, clients: usize, root: &Path) -
             }
         }
         FuzzMetaConfig::Etcd { urls } => FuzzMetaConfig::Etcd { urls: urls.clone() },
+        FuzzMetaConfig::Redis { url } => FuzzMetaConfig::Redis { url: url.clone() },
     }
 }
 
@@ -1408,6 +1606,10 @@ fn use_observer_for_op(op: &Op) -> bool {
             | Op::Rename { .. }
             | Op::PurgeDir { .. }
             | Op::Chmod { .. }
+            | Op::SetXattr { .. }
+            | Op::GetXattr { .. }
+            | Op::ListXattr { .. }
+            | Op::RemoveXattr { .. }
     )
 }
 
@@ -1610,6 +1812,31 @@ async fn apply_concurrent_op(vfs: &SlayerVfs, op: Op) {
                 let _ = vfs.set_attr(attr.ino, &req, SetAttrFlags::empty()).await;
             }
         }
+        Op::SetXattr {
+            path,
+            name,
+            value,
+            flags,
+        } => {
+            if let Ok(attr) = vfs.stat(&fs_path(&path)).await {
+                let _ = vfs.set_xattr_ino(attr.ino, &name, &value, flags).await;
+            }
+        }
+        Op::GetXattr { path, name } => {
+            if let Ok(attr) = vfs.stat(&fs_path(&path)).await {
+                let _ = vfs.get_xattr_ino(attr.ino, &name).await;
+            }
+        }
+        Op::ListXattr { path } => {
+            if let Ok(attr) = vfs.stat(&fs_path(&path)).await {
+                let _ = vfs.list_xattr_ino(attr.ino).await;
+            }
+        }
+        Op::RemoveXattr { path, name } => {
+            if let Ok(attr) = vfs.stat(&fs_path(&path)).await {
+                let _ = vfs.remove_xattr_ino(attr.ino, &name).await;
+            }
+        }
         Op::Close { .. }
         | Op::Lseek { .. }
         | Op::ReadFd { .. }
@@ -2318,6 +2545,88 @@ async fn run_clients_case(data: &[u8], clients: usize, meta: &FuzzMetaConfig) {
                     );
                 }
             }
+            Op::SetXattr {
+                path,
+                name,
+                value,
+                flags,
+            } => {
+                let sp = fs_path(&path);
+                let mp = model_path(model_root.path(), &path);
+
+                let slayer_res: io::Result<()> = async {
+                    let attr = vfs.stat(&sp).await.map_err(io::Error::from)?;
+                    vfs.set_xattr_ino(attr.ino, &name, &value, flags)
+                        .await
+                        .map_err(io::Error::other)
+                }
+                .await;
+                let model_res = model_setxattr(&mp, &name, &value, flags);
+
+                if !xattr_unsupported(&mp, &model_res) {
+                    let _ = compare_outcome("setxattr", slayer_res, model_res);
+                }
+            }
+            Op::GetXattr { path, name } => {
+                let sp = fs_path(&path);
+                let mp = model_path(model_root.path(), &path);
+
+                let slayer_res: io::Result<Vec<u8>> = async {
+                    let attr = vfs.stat(&sp).await.map_err(io::Error::from)?;
+                    vfs.get_xattr_ino(attr.ino, &name)
+                        .await
+                        .map_err(io::Error::other)?
+                        .ok_or_else(|| io::Error::from_raw_os_error(libc::ENODATA))
+                }
+                .await;
+                let model_res = model_getxattr(&mp, &name);
+
+                if !xattr_unsupported(&mp, &model_res)
+                    && let Some((got, expect)) = compare_outcome("getxattr", slayer_res, model_res)
+                    && should_strict_compare("getxattr")
+                {
+                    assert_eq!(got, expect, "getxattr: value mismatch for {name} at {sp}");
+                }
+            }
+            Op::ListXattr { path } => {
+                let sp = fs_path(&path);
+                let mp = model_path(model_root.path(), &path);
+
+                let slayer_res: io::Result<Vec<String>> = async {
+                    let attr = vfs.stat(&sp).await.map_err(io::Error::from)?;
+                    vfs.list_xattr_ino(attr.ino).await.map_err(io::Error::other)
+                }
+                .await;
+                let model_res = model_listxattr(&mp);
+
+                if !xattr_unsupported(&mp, &model_res)
+                    && let Some((got, expect)) = compare_outcome("listxattr", slayer_res, model_res)
+                    && should_strict_compare("listxattr")
+                {
+                    assert_eq!(
+                        user_xattrs(got),
+                        user_xattrs(expect),
+                        "listxattr: name mismatch at {sp}"
+                    );
+                }
+            }
+            Op::RemoveXattr { path, name } => {
+                let sp = fs_path(&path);
+                let mp = model_path(model_root.path(), &path);
+
+                let slayer_res: io::Result<()> = async {
+                    let attr = vfs.stat(&sp).await.map_err(io::Error::from)?;
+                    vfs.remove_xattr_ino(attr.ino, &name)
+                        .await
+                        .map_err(io::Error::other)
+                }
+                .await;
+                let model_res = model_removexattr(&mp, &name);
+
+                if !xattr_unsupported(&mp, &model_res) {
+                    let _ = compare_outcome("removexattr", slayer_res, model_res);
+                }
+            }
             Op::Utimens {
                 path,
                 atime_ns,
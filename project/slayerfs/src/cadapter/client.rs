@@ -1,9 +1,20 @@
 //! High-level object client wrapping backend put/get operations.
 
+use std::time::SystemTime;
+
 use anyhow::Result;
 use async_trait::async_trait;
 use bytes::Bytes;
 
+/// An object returned by [`ObjectBackend::list_objects`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ObjectInfo {
+    pub key: String,
+    pub size: u64,
+    /// Last modification time, if the backend reports one.
+    pub last_modified: Option<SystemTime>,
+}
+
 #[async_trait]
 pub trait ObjectBackend: Send + Sync {
     async fn put_object_vectored(&self, key: &str, chunks: Vec<Bytes>) -> Result<()> {
@@ -31,6 +42,9 @@ pub trait ObjectBackend: Send + Sync {
 
     #[allow(dead_code)]
     async fn delete_object(&self, key: &str) -> Result<()>;
+
+    /// List all objects whose key starts with `prefix`, in no particular order.
+    async fn list_objects(&self, prefix: &str) -> Result<Vec<ObjectInfo>>;
 }
 
 #[derive(Clone)]
@@ -74,4 +88,8 @@ impl<B: ObjectBackend> ObjectClient<B> {
     pub async fn delete_object(&self, key: &str) -> Result<()> {
         self.backend.delete_object(key).await
     }
+
+    pub async fn list_objects(&self, prefix: &str) -> Result<Vec<ObjectInfo>> {
+        self.backend.list_objects(prefix).await
+    }
 }
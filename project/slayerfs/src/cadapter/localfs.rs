@@ -7,7 +7,7 @@ use std::os::unix::fs::FileExt;
 #[cfg(windows)]
 use std::os::windows::fs::FileExt;
 
-use crate::cadapter::client::ObjectBackend;
+use crate::cadapter::client::{ObjectBackend, ObjectInfo};
 use anyhow::Result;
 use async_trait::async_trait;
 use bytes::Bytes;
@@ -261,4 +261,48 @@ impl ObjectBackend for LocalFsBackend {
             Err(e) => Err(e.into()),
         }
     }
+
+    async fn list_objects(&self, prefix: &str) -> Result<Vec<ObjectInfo>> {
+        // Only the directory holding the prefix can contain matching keys.
+        let dir = prefix
+            .rsplit_once('/')
+            .map_or("", |(dir, _)| dir)
+            .to_string();
+        let start = self.path_for(&dir);
+        let prefix = prefix.to_string();
+
+        tokio::task::spawn_blocking(move || -> Result<Vec<ObjectInfo>> {
+            let mut objects = Vec::new();
+            let mut stack = vec![(start, dir)];
+            while let Some((path, key)) = stack.pop() {
+                let entries = match std::fs::read_dir(&path) {
+                    Ok(entries) => entries,
+                    Err(e) if e.kind() == std::io::ErrorKind::NotFound => continue,
+                    Err(e) => return Err(e.into()),
+                };
+                for entry in entries {
+                    let entry = entry?;
+                    let name = entry.file_name().to_string_lossy().into_owned();
+                    let child_key = if key.is_empty() {
+                        name
+                    } else {
+                        format!("{key}/{name}")
+                    };
+                    let metadata = entry.metadata()?;
+                    if metadata.is_dir() {
+                        stack.push((entry.path(), child_key));
+                    } else if child_key.starts_with(&prefix) {
+                        objects.push(ObjectInfo {
+                            key: child_key,
+                            size: metadata.len(),
+                            last_modified: metadata.modified().ok(),
+                        });
+                    }
+                }
+            }
+            Ok(objects)
+        })
+        .await
+        .map_err(|e| anyhow::anyhow!("blocking list_objects failed: {e}"))?
+    }
 }
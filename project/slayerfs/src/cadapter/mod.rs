@@ -3,6 +3,7 @@
 //! Submodules:
 //! - `client`: high-level client API used by writer/reader code
 //! - `s3`: S3-compatible adapter implementation
+//! - `storage`: backend selected by a `file://` or `s3://` URL
 //!
 //! Responsibilities summary:
 //! - Provide an async API for put/get/delete/list of block objects.
@@ -12,4 +13,5 @@
 pub mod client;
 pub mod localfs;
 pub mod s3;
+pub mod storage;
 // Module-level TODOs remain: implement concrete adapter logic and tests.
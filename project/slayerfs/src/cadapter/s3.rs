@@ -1,6 +1,6 @@
 //! S3 adapter: simplified aws-sdk-s3 implementation with multipart upload, retries, and validation.
 
-use crate::cadapter::client::ObjectBackend;
+use crate::cadapter::client::{ObjectBackend, ObjectInfo};
 use anyhow::{Context, Result, anyhow};
 use async_trait::async_trait;
 use aws_config::BehaviorVersion;
@@ -594,4 +594,31 @@ impl ObjectBackend for S3Backend {
             }
         }
     }
+
+    async fn list_objects(&self, prefix: &str) -> Result<Vec<ObjectInfo>> {
+        let mut objects = Vec::new();
+        let mut pages = self
+            .client
+            .list_objects_v2()
+            .bucket(&self.config.bucket)
+            .prefix(prefix)
+            .into_paginator()
+            .send();
+
+        while let Some(page) = pages.next().await {
+            for object in page?.contents() {
+                let Some(key) = object.key() else {
+                    continue;
+                };
+                objects.push(ObjectInfo {
+                    key: key.to_string(),
+                    size: object.size().unwrap_or_default().max(0) as u64,
+                    last_modified: object
+                        .last_modified()
+                        .and_then(|time| std::time::SystemTime::try_from(*time).ok()),
+                });
+            }
+        }
+        Ok(objects)
+    }
 }
@@ -0,0 +1,188 @@
+//! Object storage selected by URL.
+//!
+//! Volumes name their object storage with a URL, so the same volume can be
+//! opened by every command:
+//! - `file:///srv/slayerfs` or a plain path: a local directory.
+//! - `s3://bucket?region=eu-west-1&endpoint=http://minio:9000&path_style=true`:
+//!   an S3 bucket; the query parameters are optional. Credentials come from
+//!   the usual AWS environment.
+
+use std::path::PathBuf;
+
+use anyhow::Result;
+use async_trait::async_trait;
+use bytes::Bytes;
+
+use crate::cadapter::client::{ObjectBackend, ObjectInfo};
+use crate::cadapter::localfs::LocalFsBackend;
+use crate::cadapter::s3::{S3Backend, S3Config};
+
+/// Parsed storage URL.
+#[derive(Debug, Clone)]
+pub enum StorageUrl {
+    File(PathBuf),
+    S3(S3Config),
+}
+
+impl StorageUrl {
+    pub fn parse(url: &str) -> Result<Self> {
+        if let Some(path) = url.strip_prefix("file://") {
+            if path.is_empty() {
+                anyhow::bail!("storage URL {url} has no path");
+            }
+            return Ok(StorageUrl::File(PathBuf::from(path)));
+        }
+        if let Some(rest) = url.strip_prefix("s3://") {
+            let (bucket, query) = rest.split_once('?').unwrap_or((rest, ""));
+            let bucket = bucket.trim_end_matches('/');
+            if bucket.is_empty() {
+                anyhow::bail!("storage URL {url} has no bucket");
+            }
+            if bucket.contains('/') {
+                anyhow::bail!("storage URL {url}: key prefixes are not supported");
+            }
+            let mut config = S3Config {
+                bucket: bucket.to_string(),
+                ..Default::default()
+            };
+            for pair in query.split('&').filter(|pair| !pair.is_empty()) {
+                let (name, value) = pair.split_once('=').unwrap_or((pair, ""));
+                match name {
+                    "region" => config.region = Some(value.to_string()),
+                    "endpoint" => config.endpoint = Some(value.to_string()),
+                    "path_style" => {
+                        config.force_path_style = match value {
+                            "" | "true" => true,
+                            "false" => false,
+                            _ => anyhow::bail!("storage URL {url}: invalid path_style {value}"),
+                        }
+                    }
+                    _ => anyhow::bail!("storage URL {url}: unknown parameter {name}"),
+                }
+            }
+            return Ok(StorageUrl::S3(config));
+        }
+        if url.contains("://") {
+            anyhow::bail!("unsupported storage URL {url}, expected file:// or s3://");
+        }
+        Ok(StorageUrl::File(PathBuf::from(url)))
+    }
+}
+
+/// Object backend behind a storage URL.
+#[derive(Clone)]
+pub enum StorageBackend {
+    File(LocalFsBackend),
+    S3(S3Backend),
+}
+
+impl StorageBackend {
+    /// Opens the storage named by `url`, creating a local directory if needed.
+    pub async fn open(url: &str) -> Result<Self> {
+        match StorageUrl::parse(url)? {
+            StorageUrl::File(path) => {
+                tokio::fs::create_dir_all(&path).await?;
+                if !path.is_dir() {
+                    anyhow::bail!("{} is not a directory", path.display());
+                }
+                Ok(StorageBackend::File(LocalFsBackend::new(path)))
+            }
+            StorageUrl::S3(config) => Ok(StorageBackend::S3(S3Backend::with_config(config).await?)),
+        }
+    }
+}
+
+#[async_trait]
+impl ObjectBackend for StorageBackend {
+    async fn put_object_vectored(&self, key: &str, chunks: Vec<Bytes>) -> Result<()> {
+        match self {
+            StorageBackend::File(backend) => backend.put_object_vectored(key, chunks).await,
+            StorageBackend::S3(backend) => backend.put_object_vectored(key, chunks).await,
+        }
+    }
+
+    async fn put_object(&self, key: &str, data: &[u8]) -> Result<()> {
+        match self {
+            StorageBackend::File(backend) => backend.put_object(key, data).await,
+            StorageBackend::S3(backend) => backend.put_object(key, data).await,
+        }
+    }
+
+    async fn get_object(&self, key: &str) -> Result<Option<Vec<u8>>> {
+        match self {
+            StorageBackend::File(backend) => backend.get_object(key).await,
+            StorageBackend::S3(backend) => backend.get_object(key).await,
+        }
+    }
+
+    async fn get_object_range(&self, key: &str, offset: u64, buf: &mut [u8]) -> Result<usize> {
+        match self {
+            StorageBackend::File(backend) => backend.get_object_range(key, offset, buf).await,
+            StorageBackend::S3(backend) => backend.get_object_range(key, offset, buf).await,
+        }
+    }
+
+    async fn get_etag(&self, key: &str) -> Result<String> {
+        match self {
+            StorageBackend::File(backend) => backend.get_etag(key).await,
+            StorageBackend::S3(backend) => backend.get_etag(key).await,
+        }
+    }
+
+    async fn delete_object(&self, key: &str) -> Result<()> {
+        match self {
+            StorageBackend::File(backend) => backend.delete_object(key).await,
+            StorageBackend::S3(backend) => backend.delete_object(key).await,
+        }
+    }
+
+    async fn list_objects(&self, prefix: &str) -> Result<Vec<ObjectInfo>> {
+        match self {
+            StorageBackend::File(backend) => backend.list_objects(prefix).await,
+            StorageBackend::S3(backend) => backend.list_objects(prefix).await,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parse_storage_urls() {
+        let StorageUrl::File(path) = StorageUrl::parse("file:///srv/slayerfs").unwrap() else {
+            panic!("expected a local directory");
+        };
+        assert_eq!(path, PathBuf::from("/srv/slayerfs"));
+        let StorageUrl::File(path) = StorageUrl::parse("./data").unwrap() else {
+            panic!("expected a local directory");
+        };
+        assert_eq!(path, PathBuf::from("./data"));
+
+        let StorageUrl::S3(config) = StorageUrl::parse("s3://bucket").unwrap() else {
+            panic!("expected a bucket");
+        };
+        assert_eq!(config.bucket, "bucket");
+        assert_eq!(config.region, None);
+        assert!(!config.force_path_style);
+
+        let url = "s3://bucket/?region=eu-west-1&endpoint=http://minio:9000&path_style=true";
+        let StorageUrl::S3(config) = StorageUrl::parse(url).unwrap() else {
+            panic!("expected a bucket");
+        };
+        assert_eq!(config.bucket, "bucket");
+        assert_eq!(config.region.as_deref(), Some("eu-west-1"));
+        assert_eq!(config.endpoint.as_deref(), Some("http://minio:9000"));
+        assert!(config.force_path_style);
+
+        for url in [
+            "file://",
+            "s3://",
+            "s3://bucket/prefix",
+            "s3://bucket?acl=private",
+            "gs://bucket",
+        ] {
+            assert!(StorageUrl::parse(url).is_err(), "{url}");
+        }
+    }
+}
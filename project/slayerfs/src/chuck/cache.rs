@@ -129,6 +129,14 @@ pub struct ChunksCacheConfig {
     /// Files are stored using SHA256 hash of the key for unique naming
     pub disk_storage_dir: Option<PathBuf>,
 
+    /// Whether files found in the disk directory are served even if this
+    /// cache did not store them, e.g. blocks preloaded by another process
+    ///
+    /// **Default**: false
+    /// **Note**: Only enable it for a directory used by a single file system,
+    ///         otherwise equal keys of different file systems collide
+    pub persistent: bool,
+
     /// Weight for short window access frequency in promotion decisions
     ///
     /// **Range**: 0.0 - 1.0
@@ -176,6 +184,7 @@ impl Default for ChunksCacheConfig {
             medium_window_size: Duration::from_secs(60),
             max_access_entries: 100,
             disk_storage_dir: None,
+            persistent: false,
             short_window_weight: 0.7,
             medium_window_weight: 0.3,
             enable_adaptive_threshold: true,
@@ -261,6 +270,11 @@ impl DiskStorage {
         }
     }
 
+    pub async fn exists(&self, key: &str) -> bool {
+        let filepath = self.base_dir.join(Self::key_to_filename(key));
+        fs::try_exists(filepath).await.unwrap_or(false)
+    }
+
     #[allow(dead_code)]
     pub async fn remove(&self, key: &str) -> anyhow::Result<()> {
         let filename = Self::key_to_filename(key);
@@ -1292,52 +1306,43 @@ impl ChunksCache {
         debug!("Hot cache MISS for key: {}", key);
         self.policy.record_cache_request(false);
 
-        let diskstorage = self.disk_storage.clone();
-        let policy = self.policy.clone();
-        let hot_cache = self.hot_cache.clone();
-        let hot_bytes = self.hot_bytes.clone();
-        let key_owned = key.to_string();
-
-        // ensure key exists in cold cache
-        self.cold_cache.get(key).await?;
-
-        let load_future = async move {
-            trace!("Loading data from disk for key: {}", key_owned);
-            let value = diskstorage.load(&key_owned).await.ok().unwrap_or_default();
-
-            if value.is_empty() {
-                warn!("No data found on disk for key: {}", key_owned);
-                return value;
-            }
-
-            debug!(
-                "Loaded {} bytes from disk for key: {}",
-                value.len(),
-                key_owned
-            );
-
-            if policy.should_promote(key_owned.clone()).await {
-                debug!("Promoting key to hot cache: {}", key_owned);
-                hot_cache.insert(key_owned.clone(), value.clone()).await;
-                hot_bytes.fetch_add(value.len() as u64, Ordering::Relaxed);
-            } else {
-                trace!("Key not eligible for promotion: {}", key_owned);
-            }
-
-            value
-        };
-
-        let value = self.hot_cache.get_with_by_ref(key, load_future).await;
+        if !self.contains(key).await {
+            return None;
+        }
 
+        // Only data promoted by the policy enters the hot tier, so scans of
+        // cold data do not fill the memory.
+        trace!("Loading data from disk for key: {}", key);
+        let value = self.disk_storage.load(key).await.ok().unwrap_or_default();
         self.update_utilization_metrics();
-
         if value.is_empty() {
-            debug!("No data found for key: {}", key);
-            None
+            warn!("No data found on disk for key: {}", key);
+            return None;
+        }
+        debug!("Loaded {} bytes from disk for key: {}", value.len(), key);
+
+        if self.policy.should_promote(key.clone()).await {
+            debug!("Promoting key to hot cache: {}", key);
+            self.hot_cache.insert(key.clone(), value.clone()).await;
+            self.hot_bytes
+                .fetch_add(value.len() as u64, Ordering::Relaxed);
         } else {
-            debug!("Returning {} bytes for key: {}", value.len(), key);
-            value.into()
+            trace!("Key not eligible for promotion: {}", key);
+        }
+        Some(value)
+    }
+
+    /// Whether `key` can be served from the disk tier. With
+    /// [`ChunksCacheConfig::persistent`], keys found on disk are indexed again.
+    pub async fn contains(&self, key: &str) -> bool {
+        if self.cold_cache.contains_key(key) {
+            return true;
+        }
+        if !self.config.persistent || !self.disk_storage.exists(key).await {
+            return false;
         }
+        self.cold_cache.insert(key.to_owned(), ()).await;
+        true
     }
 
     /// Update cache utilization metrics
@@ -1375,12 +1380,24 @@ impl ChunksCache {
         Ok(())
     }
 
+    /// Stores `data` on disk only; it is promoted to the hot tier once it is
+    /// read often enough.
+    pub async fn insert_on_disk(&self, key: &str, data: &[u8]) -> anyhow::Result<()> {
+        trace!("Storing on disk: {}", key);
+        self.disk_storage.store(key, data).await?;
+        self.cold_cache.insert(key.to_owned(), ()).await;
+        Ok(())
+    }
+
     #[allow(dead_code)]
     pub async fn remove(&self, key: &String) -> anyhow::Result<()> {
         info!("Cache REMOVE request for key: {}", key);
         trace!("Invalidating from hot cache: {}", key);
         self.hot_cache.invalidate(key).await;
-        // self.disk_storage.remove(key).await?;
+        if self.config.persistent && self.disk_storage.exists(key).await {
+            // Otherwise `contains` would index the file again.
+            self.disk_storage.remove(key).await?;
+        }
         trace!("Invalidating from cold cache: {}", key);
         self.cold_cache.invalidate(key).await;
 
@@ -0,0 +1,173 @@
+//! Block objects checked against the slices referenced by the metadata.
+//!
+//! A slice of `length` bytes is stored as the blocks `0..ceil(length /
+//! block_size)` under `chunks/{slice_id}/`. [`missing_blocks`] finds the
+//! blocks referenced slices lack; [`collect_garbage`] deletes the blocks of
+//! slices nothing references any more, e.g. data of removed files or objects
+//! uploaded by a writer that crashed before committing its slice.
+//!
+//! Writers upload blocks before the slice is committed to the metadata, so a
+//! block without a slice may just be in flight. Only blocks older than the
+//! grace period are collected. Blocks of referenced slices are kept even if
+//! they lie past the part still in use.
+
+use std::collections::{HashMap, HashSet};
+use std::time::{Duration, SystemTime};
+
+use crate::cadapter::client::{ObjectBackend, ObjectClient, ObjectInfo};
+use crate::chuck::store::{BLOCK_PREFIX, BlockKey, block_key, parse_block_key};
+
+/// Number of blocks holding the first `length` bytes of a slice.
+pub fn block_count(length: u64, block_size: u32) -> u32 {
+    length.div_ceil(block_size as u64) as u32
+}
+
+/// Returns the blocks missing from the object store for each referenced
+/// slice, given as slice id and bytes in use, sorted by key.
+pub async fn missing_blocks<B: ObjectBackend>(
+    client: &ObjectClient<B>,
+    slices: &HashMap<u64, u64>,
+    block_size: u32,
+) -> anyhow::Result<Vec<BlockKey>> {
+    let stored: HashSet<BlockKey> = client
+        .list_objects(BLOCK_PREFIX)
+        .await?
+        .iter()
+        .filter_map(|object| parse_block_key(&object.key))
+        .collect();
+
+    let mut missing: Vec<BlockKey> = slices
+        .iter()
+        .flat_map(|(&slice_id, &length)| {
+            (0..block_count(length, block_size)).map(move |index| (slice_id, index))
+        })
+        .filter(|key| !stored.contains(key))
+        .collect();
+    missing.sort_unstable();
+    Ok(missing)
+}
+
+/// Options of [`collect_garbage`].
+#[derive(Debug, Clone)]
+pub struct GcOption {
+    /// Blocks modified more recently are kept.
+    pub grace: Duration,
+    /// Only report what would be deleted.
+    pub dry_run: bool,
+}
+
+/// Result of [`collect_garbage`].
+#[derive(Debug, Default)]
+pub struct GcReport {
+    /// Number of block objects listed.
+    pub scanned: usize,
+    /// Unreferenced blocks old enough to be collected, sorted by key.
+    pub leaked: Vec<ObjectInfo>,
+    /// Unreferenced blocks kept because they are younger than the grace
+    /// period or their age is unknown.
+    pub young: usize,
+    /// Number of leaked blocks deleted; 0 on a dry run.
+    pub deleted: usize,
+}
+
+/// Deletes the blocks of slices not in `referenced`; see the module docs.
+pub async fn collect_garbage<B: ObjectBackend>(
+    client: &ObjectClient<B>,
+    referenced: &HashSet<u64>,
+    opt: &GcOption,
+) -> anyhow::Result<GcReport> {
+    let objects = client.list_objects(BLOCK_PREFIX).await?;
+    let edge = SystemTime::now()
+        .checked_sub(opt.grace)
+        .unwrap_or(SystemTime::UNIX_EPOCH);
+
+    let mut report = GcReport {
+        scanned: objects.len(),
+        ..Default::default()
+    };
+    for object in objects {
+        let Some((slice_id, _)) = parse_block_key(&object.key) else {
+            continue;
+        };
+        if referenced.contains(&slice_id) {
+            continue;
+        }
+        match object.last_modified {
+            Some(modified) if modified <= edge => report.leaked.push(object),
+            _ => report.young += 1,
+        }
+    }
+    report.leaked.sort_by(|a, b| a.key.cmp(&b.key));
+
+    if !opt.dry_run {
+        for object in &report.leaked {
+            client.delete_object(&object.key).await?;
+            report.deleted += 1;
+        }
+    }
+    Ok(report)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::cadapter::localfs::LocalFsBackend;
+
+    async fn put_blocks(client: &ObjectClient<LocalFsBackend>, keys: &[BlockKey]) {
+        for &key in keys {
+            client.put_object(&block_key(key), b"block").await.unwrap();
+        }
+    }
+
+    #[tokio::test]
+    async fn test_missing_blocks() {
+        let tmp = tempfile::tempdir().unwrap();
+        let client = ObjectClient::new(LocalFsBackend::new(tmp.path()));
+        put_blocks(&client, &[(1, 0), (1, 1), (2, 0)]).await;
+        client.put_object("unrelated", b"x").await.unwrap();
+
+        // Slice 1 uses 2.5 blocks, slice 2 one block and slice 3 is gone.
+        let slices = HashMap::from([(1, 10), (2, 4), (3, 1)]);
+        let missing = missing_blocks(&client, &slices, 4).await.unwrap();
+        assert_eq!(missing, vec![(1, 2), (3, 0)]);
+    }
+
+    #[tokio::test]
+    async fn test_collect_garbage() {
+        let tmp = tempfile::tempdir().unwrap();
+        let client = ObjectClient::new(LocalFsBackend::new(tmp.path()));
+        put_blocks(&client, &[(1, 0), (2, 0), (2, 1)]).await;
+        let referenced = HashSet::from([1]);
+
+        // Everything was just written and is still within the grace period.
+        let opt = GcOption {
+            grace: Duration::from_secs(3600),
+            dry_run: false,
+        };
+        let report = collect_garbage(&client, &referenced, &opt).await.unwrap();
+        assert_eq!(report.scanned, 3);
+        assert_eq!(report.young, 2);
+        assert!(report.leaked.is_empty());
+
+        let dry_run = GcOption {
+            grace: Duration::ZERO,
+            dry_run: true,
+        };
+        let report = collect_garbage(&client, &referenced, &dry_run)
+            .await
+            .unwrap();
+        let leaked: Vec<_> = report.leaked.iter().map(|o| o.key.as_str()).collect();
+        assert_eq!(leaked, vec!["chunks/2/0", "chunks/2/1"]);
+        assert_eq!(report.deleted, 0);
+        assert!(client.get_object("chunks/2/0").await.unwrap().is_some());
+
+        let opt = GcOption {
+            grace: Duration::ZERO,
+            dry_run: false,
+        };
+        let report = collect_garbage(&client, &referenced, &opt).await.unwrap();
+        assert_eq!(report.deleted, 2);
+        assert!(client.get_object("chunks/2/0").await.unwrap().is_none());
+        assert!(client.get_object("chunks/1/0").await.unwrap().is_some());
+    }
+}
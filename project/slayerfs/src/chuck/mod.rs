@@ -16,11 +16,13 @@
 
 pub mod cache;
 pub mod chunk;
+pub mod gc;
 pub mod reader;
 pub mod singleflight;
 pub mod slice;
 pub mod span;
 pub mod store;
+pub mod transform;
 pub mod util;
 pub mod writer;
 
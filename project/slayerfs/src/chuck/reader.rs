@@ -2,7 +2,7 @@
 
 use super::chunk::ChunkLayout;
 use super::slice::{ChunkOffset, SliceDesc, SliceOffset, block_span_iter_slice};
-use super::store::BlockStore;
+use super::store::{BlockKey, BlockStore};
 use crate::meta::MetaLayer;
 use crate::utils::Intervals;
 use crate::utils::NumCastExt;
@@ -56,6 +56,13 @@ where
         Ok(())
     }
 
+    /// Reads through `slices`, a snapshot of the chunk's slices taken by the
+    /// caller, instead of fetching them.
+    pub(crate) fn use_slices(&mut self, slices: Vec<SliceDesc>) {
+        self.slices = slices;
+        self.prepared = true;
+    }
+
     #[tracing::instrument(
         name = "DataFetcher.read_at",
         level = "trace",
@@ -75,19 +82,7 @@ where
         let mut buf = vec![0; len];
 
         let need_read = tracing::trace_span!("fetch.read_at.build_need_read", offset, len)
-            .in_scope(|| {
-                let mut intervals = Intervals::new(offset, offset + len as u64);
-                let mut need_read = Vec::new();
-
-                for slice in self.slices.iter().copied().rev() {
-                    for (l, r) in intervals.cut(slice.offset, slice.offset + slice.length) {
-                        need_read.push((l, r, slice));
-                    }
-                }
-
-                need_read.sort_by_key(|(l, _, _)| *l);
-                need_read
-            });
+            .in_scope(|| visible_parts(&self.slices, offset, len as u64));
         tracing::Span::current().record("need_reads", need_read.len());
 
         let layout = self.layout;
@@ -152,6 +147,67 @@ where
         }
         Ok(buf)
     }
+
+    /// Asks the block store to prefetch the blocks holding the data of
+    /// `[offset, offset + len)`, all at once; the store bounds the concurrency.
+    #[tracing::instrument(
+        name = "DataFetcher.prefetch",
+        level = "trace",
+        skip(self),
+        fields(chunk_id = self.id, offset = offset.0, len)
+    )]
+    pub(crate) async fn prefetch(&self, offset: ChunkOffset, len: u64) -> Result<()> {
+        ensure!(
+            self.prepared,
+            "DataFetcher::prefetch requires prepare_slices() to run first"
+        );
+        let store = self.backend.store();
+        let mut futures: FuturesUnordered<_> =
+            visible_blocks(&self.slices, self.layout, offset.get(), len)
+                .into_iter()
+                .map(|key| store.prefetch(key))
+                .collect();
+        while let Some(res) = futures.next().await {
+            res?;
+        }
+        Ok(())
+    }
+}
+
+/// Splits `[offset, offset + len)` of a chunk into the parts provided by
+/// each slice, the latest slice winning; holes are left out. Sorted by offset.
+fn visible_parts(slices: &[SliceDesc], offset: u64, len: u64) -> Vec<(u64, u64, SliceDesc)> {
+    let mut intervals = Intervals::new(offset, offset + len);
+    let mut parts = Vec::new();
+
+    for slice in slices.iter().copied().rev() {
+        for (l, r) in intervals.cut(slice.offset, slice.offset + slice.length) {
+            parts.push((l, r, slice));
+        }
+    }
+
+    parts.sort_by_key(|(l, _, _)| *l);
+    parts
+}
+
+/// Blocks holding the data of `[offset, offset + len)` of a chunk made of
+/// `slices`. Blocks whose data was overwritten by later slices are left out.
+pub(crate) fn visible_blocks(
+    slices: &[SliceDesc],
+    layout: ChunkLayout,
+    offset: u64,
+    len: u64,
+) -> Vec<BlockKey> {
+    let mut keys = Vec::new();
+    for (l, r, slice) in visible_parts(slices, offset, len) {
+        let slice_offset = SliceOffset::from(l - slice.offset);
+        for block in block_span_iter_slice(slice_offset, r - l, layout) {
+            keys.push((slice.slice_id, block.index.as_u32()));
+        }
+    }
+    keys.sort_unstable();
+    keys.dedup();
+    keys
 }
 
 #[cfg(test)]
@@ -328,4 +384,25 @@ mod tests {
         assert_eq!(&out[..1024], &data1[..1024]);
         assert_eq!(&out[1024..], &data2[..]);
     }
+
+    #[test]
+    fn test_visible_blocks_skip_overwritten_data() {
+        let layout = ChunkLayout {
+            chunk_size: 16 * 1024,
+            block_size: 4 * 1024,
+        };
+        let slice = |slice_id, offset, length| SliceDesc {
+            slice_id,
+            chunk_id: 9,
+            offset,
+            length,
+        };
+        // Slice 2 hides the second block of slice 1 completely.
+        let slices = [slice(1, 0, 12 * 1024), slice(2, 4 * 1024, 4 * 1024)];
+
+        let keys = visible_blocks(&slices, layout, 0, 16 * 1024);
+        assert_eq!(keys, vec![(1, 0), (1, 2), (2, 0)]);
+        let keys = visible_blocks(&slices, layout, 6 * 1024, 4 * 1024);
+        assert_eq!(keys, vec![(1, 2), (2, 0)]);
+    }
 }
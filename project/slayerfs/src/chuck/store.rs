@@ -1,6 +1,7 @@
 //! Storage backends: asynchronous block-level IO traits and in-memory implementations.
 
 use crate::chuck::singleflight::SingleFlight;
+use crate::chuck::transform::BlockTransform;
 use crate::utils::NumCastExt;
 use crate::utils::zero::make_zero_bytes;
 use crate::{
@@ -17,7 +18,7 @@ use sha2::{Digest, Sha256};
 use std::{collections::HashMap, fs, io::SeekFrom, path::PathBuf, sync::LazyLock};
 use tokio::{
     io::{self, AsyncReadExt, AsyncSeekExt, AsyncWriteExt},
-    sync::RwLock,
+    sync::{RwLock, Semaphore},
 };
 
 /// Abstract block store interface (cadapter/S3/etc. can implement this).
@@ -60,10 +61,31 @@ pub trait BlockStore {
 
     #[allow(dead_code)]
     async fn delete_range(&self, key: BlockKey, len: u64) -> anyhow::Result<()>;
+
+    /// Hint that the block will be read soon, so stores with a local cache
+    /// can load it ahead of time. Does nothing by default.
+    async fn prefetch(&self, _key: BlockKey) -> anyhow::Result<()> {
+        Ok(())
+    }
 }
 
 pub type BlockKey = (u64 /*slice_id*/, u32 /*block_index*/);
 
+/// Common prefix of the object keys of all blocks.
+pub const BLOCK_PREFIX: &str = "chunks/";
+
+/// Object key of a block: `chunks/{slice_id}/{block_index}`.
+pub fn block_key(key: BlockKey) -> String {
+    let (slice_id, block_index) = key;
+    format!("{BLOCK_PREFIX}{slice_id}/{block_index}")
+}
+
+/// Inverse of [`block_key`]; `None` for keys that do not name a block.
+pub fn parse_block_key(key: &str) -> Option<BlockKey> {
+    let (slice_id, block_index) = key.strip_prefix(BLOCK_PREFIX)?.split_once('/')?;
+    Some((slice_id.parse().ok()?, block_index.parse().ok()?))
+}
+
 /// Simple in-memory implementation for local development/testing.
 #[derive(Default)]
 #[allow(dead_code)]
@@ -122,16 +144,20 @@ impl BlockStore for InMemoryBlockStore {
     }
 }
 
-/// BlockStore backed by cadapter::client (key space `chunks/{chunk_id}/{block_index}`).
+/// BlockStore backed by cadapter::client (key space `chunks/{slice_id}/{block_index}`).
 pub struct ObjectBlockStore<B: ObjectBackend> {
     client: ObjectClient<B>,
-    #[allow(dead_code)]
+    /// Decoded blocks loaded by `prefetch`; reads check it before the object store
     block_cache: ChunksCache,
+    /// Bounds the number of blocks prefetched at a time
+    prefetch_permits: Semaphore,
     /// SingleFlight controller for coalescing concurrent reads to the same block
     /// Thread-safe and shared across the store lifetime so concurrent requests can coalesce.
     read_flight: SingleFlight<BlockKey, Bytes>,
     /// Configuration for read strategy
     config: BlockStoreConfig,
+    /// Compression/encryption applied to every block object
+    transform: BlockTransform,
 }
 
 /// Configuration for ObjectBlockStore read strategy
@@ -142,6 +168,9 @@ pub struct BlockStoreConfig {
     /// For ranges smaller than this threshold, use direct range read instead of full block read
     /// Default is 25% of block size (1MB for 4MB blocks)
     pub range_read_threshold: f32,
+    /// Maximum number of blocks prefetched into the block cache at a time
+    /// Default is 16; 0 disables prefetching
+    pub max_prefetch: usize,
 }
 
 impl Default for BlockStoreConfig {
@@ -149,6 +178,7 @@ impl Default for BlockStoreConfig {
         Self {
             block_size: 4 * 1024 * 1024, // 4MB
             range_read_threshold: 0.25,  // 25% = 1MB for 4MB blocks
+            max_prefetch: 16,
         }
     }
 }
@@ -175,7 +205,11 @@ impl<B: ObjectBackend> ObjectBlockStore<B> {
 
         let _ = fs::create_dir_all(cache_dir.clone());
 
-        let block_cache = block_on(ChunksCache::new_with_config(ChunksCacheConfig::default()))
+        let cache_config = ChunksCacheConfig {
+            disk_storage_dir: Some(cache_dir),
+            ..Default::default()
+        };
+        let block_cache = block_on(ChunksCache::new_with_config(cache_config))
             .map_err(|e| anyhow::anyhow!("Failed to create cache: {}", e))
             .unwrap();
         let config = BlockStoreConfig::default();
@@ -183,8 +217,10 @@ impl<B: ObjectBackend> ObjectBlockStore<B> {
         Self {
             client,
             block_cache,
+            prefetch_permits: Semaphore::new(config.max_prefetch),
             read_flight: SingleFlight::new(),
             config,
+            transform: BlockTransform::default(),
         }
     }
     /// Creates a new ObjectBlockStore with custom cache configuration
@@ -212,14 +248,55 @@ impl<B: ObjectBackend> ObjectBlockStore<B> {
         Ok(Self {
             client,
             block_cache,
+            prefetch_permits: Semaphore::new(store_config.max_prefetch),
             read_flight: SingleFlight::new(),
             config: store_config,
+            transform: BlockTransform::default(),
         })
     }
 
+    /// Encodes every block with `transform`. Transformed blocks can only be
+    /// read as a whole, so range reads fetch and decode the full block.
+    pub fn with_transform(mut self, transform: BlockTransform) -> Self {
+        self.transform = transform;
+        self
+    }
+
     fn key_for(key: BlockKey) -> String {
-        let (chunk_id, block_index) = key;
-        format!("chunks/{chunk_id}/{block_index}")
+        block_key(key)
+    }
+
+    /// Reads and decodes the whole block; concurrent reads of a block share
+    /// one request. A missing block reads as empty.
+    async fn fetch_block(&self, key: BlockKey) -> anyhow::Result<Bytes> {
+        let client = &self.client;
+        let transform = &self.transform;
+
+        self.read_flight
+            .execute(key, || async move {
+                let key_str = Self::key_for(key);
+
+                let data = client
+                    .get_object(&key_str)
+                    .await
+                    .map_err(|e| anyhow::anyhow!("object store get failed: {key_str}, {e:?}"))?;
+
+                let data = match data {
+                    Some(stored) => transform.decode(&key_str, stored)?,
+                    None => Vec::new(),
+                };
+                Ok::<_, anyhow::Error>(Bytes::from(data))
+            })
+            .await
+            .map_err(|e| anyhow::anyhow!("SingleFlight read failed: {e}"))
+    }
+
+    async fn put_encoded(&self, key_str: &str, data: &[u8]) -> anyhow::Result<()> {
+        let encoded = self.transform.encode(key_str, data)?;
+        self.client
+            .put_object(key_str, &encoded)
+            .await
+            .map_err(|e| anyhow::anyhow!("object store put failed: {key_str}, {e:?}"))
     }
 }
 
@@ -227,12 +304,15 @@ impl<B: ObjectBackend> ObjectBlockStore<B> {
 impl<B: ObjectBackend + Send + Sync> BlockStore for ObjectBlockStore<B> {
     async fn write_range(&self, key: BlockKey, offset: u64, data: &[u8]) -> anyhow::Result<u64> {
         let key_str = Self::key_for(key);
-        let mut buf = self
+        let mut buf = match self
             .client
             .get_object(&key_str)
             .await
             .map_err(|e| anyhow::anyhow!("object store get failed: {:?}", e))?
-            .unwrap_or_default();
+        {
+            Some(stored) => self.transform.decode(&key_str, stored)?,
+            None => Vec::new(),
+        };
 
         let start = offset.as_usize();
         let end = start + data.len();
@@ -240,10 +320,12 @@ impl<B: ObjectBackend + Send + Sync> BlockStore for ObjectBlockStore<B> {
             buf.resize(end, 0);
         }
         buf[start..end].copy_from_slice(data);
+        let buf = self.transform.encode(&key_str, &buf)?;
         self.client
             .put_object(&key_str, &buf)
             .await
             .map_err(|e| anyhow::anyhow!("object store put failed: {key_str}, {e:?}"))?;
+        self.block_cache.remove(&key_str).await?;
 
         Ok(data.len() as u64)
     }
@@ -260,6 +342,15 @@ impl<B: ObjectBackend + Send + Sync> BlockStore for ObjectBlockStore<B> {
         if total_len == 0 {
             return Ok(0);
         }
+        if !self.transform.is_identity() {
+            let mut buf = vec![0u8; offset.as_usize()];
+            buf.reserve(total_len);
+            for chunk in &chunks {
+                buf.extend_from_slice(chunk);
+            }
+            self.put_encoded(&key_str, &buf).await?;
+            return Ok(total_len as u64);
+        }
 
         let offset_usize = offset.as_usize();
         let mut parts: Vec<Bytes> = Vec::new();
@@ -286,6 +377,12 @@ impl<B: ObjectBackend + Send + Sync> BlockStore for ObjectBlockStore<B> {
         if data.is_empty() {
             return Ok(0);
         }
+        if !self.transform.is_identity() {
+            let mut buf = vec![0u8; offset.as_usize()];
+            buf.extend_from_slice(data);
+            self.put_encoded(&key_str, &buf).await?;
+            return Ok(data.len() as u64);
+        }
 
         let offset_usize = offset.as_usize();
         let mut parts = Vec::new();
@@ -313,16 +410,29 @@ impl<B: ObjectBackend + Send + Sync> BlockStore for ObjectBlockStore<B> {
         let len = buf.len();
         let range_size_threshold = self.config.range_size_threshold();
 
+        // Blocks loaded by `prefetch` are served from the block cache.
+        let key_str = Self::key_for(key);
+        if self.block_cache.contains(&key_str).await
+            && let Some(block_data) = self.block_cache.get(&key_str).await
+        {
+            tracing::Span::current().record("strategy", "cache");
+            let start = offset.as_usize().min(block_data.len());
+            let copy_len = len.min(block_data.len() - start);
+            buf[..copy_len].copy_from_slice(&block_data[start..start + copy_len]);
+            tracing::Span::current().record("read_len", copy_len);
+            return Ok(());
+        }
+
         // Boundary: len == threshold still uses direct range read; threshold is floor-casted usize.
 
         // Smart strategy selection:
         // 1. If the requested range is small (< threshold), use direct range read
         // 2. If the range is large, use SingleFlight to potentially coalesce with other requests
-        if len <= range_size_threshold {
+        // Transformed blocks can only be decoded as a whole, so they always take the second path.
+        if len <= range_size_threshold && self.transform.is_identity() {
             // Strategy 1: Direct range read for small ranges (efficient for random access)
             tracing::Span::current().record("strategy", "direct_range");
 
-            let key_str = Self::key_for(key);
             let read_len = self
                 .client
                 .get_object_range(&key_str, offset, buf)
@@ -338,22 +448,7 @@ impl<B: ObjectBackend + Send + Sync> BlockStore for ObjectBlockStore<B> {
 
         // Use SingleFlight to coalesce concurrent reads to the same block.
         // We read the entire block and then extract the requested range.
-        let client = &self.client;
-
-        let block_data =
-            self.read_flight
-                .execute(key, || async move {
-                    // Read the entire block
-                    let key_str = Self::key_for(key);
-
-                    let data = client.get_object(&key_str).await.map_err(|e| {
-                        anyhow::anyhow!("object store get failed: {key_str}, {e:?}")
-                    })?;
-
-                    Ok::<_, anyhow::Error>(Bytes::from(data.unwrap_or_default()))
-                })
-                .await
-                .map_err(|e| anyhow::anyhow!("SingleFlight read failed: {e}"))?;
+        let block_data = self.fetch_block(key).await?;
 
         // Extract the requested range from the block data
         let offset_usize = offset as usize;
@@ -380,9 +475,32 @@ impl<B: ObjectBackend + Send + Sync> BlockStore for ObjectBlockStore<B> {
                 .delete_object(&key_str)
                 .await
                 .map_err(|e| anyhow::anyhow!("object store delete failed: {key_str}, {e:?}"))?;
+            self.block_cache.remove(&key_str).await?;
         }
         Ok(())
     }
+
+    /// Loads the block into the block cache unless it is cached already.
+    async fn prefetch(&self, key: BlockKey) -> anyhow::Result<()> {
+        if self.config.max_prefetch == 0 {
+            return Ok(());
+        }
+        let key_str = Self::key_for(key);
+        if self.block_cache.contains(&key_str).await {
+            return Ok(());
+        }
+
+        let _permit = self.prefetch_permits.acquire().await?;
+        // Another prefetch may have loaded it while this one waited.
+        if self.block_cache.contains(&key_str).await {
+            return Ok(());
+        }
+        let block_data = self.fetch_block(key).await?;
+        if block_data.is_empty() {
+            return Ok(());
+        }
+        self.block_cache.insert_on_disk(&key_str, &block_data).await
+    }
 }
 
 /// Convenience alias: BlockStore backed by the real S3 backend.
@@ -420,6 +538,50 @@ mod tests {
         assert_eq!(out, data);
     }
 
+    #[tokio::test]
+    async fn test_transformed_blocks_round_trip() {
+        use crate::chuck::transform::{BlockFormat, Compression, Encryption};
+
+        let tmp = tempfile::tempdir().unwrap();
+        let format = BlockFormat::with_iterations(
+            Compression::Zstd,
+            Encryption::ChaCha20Poly1305,
+            Some("secret"),
+            1000,
+        )
+        .unwrap();
+        let client = ObjectClient::new(LocalFsBackend::new(tmp.path()));
+        let store =
+            ObjectBlockStore::new(client).with_transform(format.transform(Some("secret")).unwrap());
+
+        let data = vec![7u8; 256 * 1024];
+        store.write_fresh_range((42, 0), 4096, &data).await.unwrap();
+        store.write_range((42, 0), 8192, &[9u8; 16]).await.unwrap();
+
+        // The object holds neither the plain data nor its size.
+        let raw = ObjectClient::new(LocalFsBackend::new(tmp.path()))
+            .get_object("chunks/42/0")
+            .await
+            .unwrap()
+            .unwrap();
+        assert!(raw.len() < data.len());
+        assert!(!raw.windows(16).any(|w| w == [7u8; 16]));
+
+        // Small reads are served from the decoded block.
+        let mut out = vec![0u8; 32];
+        store.read_range((42, 0), 8184, &mut out).await.unwrap();
+        assert_eq!(&out[..8], &[7u8; 8]);
+        assert_eq!(&out[8..24], &[9u8; 16]);
+        assert_eq!(&out[24..], &[7u8; 8]);
+
+        let mut head = vec![1u8; 4096];
+        store.read_range((42, 0), 0, &mut head).await.unwrap();
+        assert!(head.iter().all(|&b| b == 0));
+
+        let wrong = format.transform(Some("other"));
+        assert!(wrong.is_err());
+    }
+
     #[tokio::test]
     async fn test_cache_effectiveness() -> io::Result<()> {
         let tmp = tempfile::tempdir()?;
@@ -451,7 +613,7 @@ mod tests {
 
     #[tokio::test]
     async fn test_intelligent_read_strategy() -> Result<(), Box<dyn std::error::Error>> {
-        use crate::cadapter::client::{ObjectBackend, ObjectClient};
+        use crate::cadapter::client::{ObjectBackend, ObjectClient, ObjectInfo};
         use async_trait::async_trait;
         use futures::future;
         use std::{
@@ -546,6 +708,21 @@ mod tests {
                 self.data.lock().unwrap().remove(key);
                 Ok(())
             }
+
+            async fn list_objects(&self, prefix: &str) -> anyhow::Result<Vec<ObjectInfo>> {
+                Ok(self
+                    .data
+                    .lock()
+                    .unwrap()
+                    .iter()
+                    .filter(|(key, _)| key.starts_with(prefix))
+                    .map(|(key, data)| ObjectInfo {
+                        key: key.clone(),
+                        size: data.len() as u64,
+                        last_modified: None,
+                    })
+                    .collect())
+            }
         }
 
         // Test small range uses direct read
@@ -554,10 +731,16 @@ mod tests {
         let config = BlockStoreConfig {
             block_size: 4 * 1024 * 1024,
             range_read_threshold: 0.25, // 1MB threshold
+            ..Default::default()
+        };
+        let cache_dir = tempfile::tempdir()?;
+        let cache_config = ChunksCacheConfig {
+            disk_storage_dir: Some(cache_dir.path().to_path_buf()),
+            ..Default::default()
         };
         let store = Arc::new(ObjectBlockStore::new_with_configs(
             client,
-            ChunksCacheConfig::default(),
+            cache_config,
             config,
         )?);
 
@@ -614,6 +797,20 @@ mod tests {
             "Coalesced path should not fall back to range reads",
         );
 
+        // Prefetched blocks are served from the block cache
+        backend.reset_stats();
+        store.prefetch((42, 3)).await?;
+        store.prefetch((42, 3)).await?;
+        let stats = backend.get_stats();
+        assert_eq!(stats.get_object_calls, 1, "Prefetch should read once");
+
+        backend.reset_stats();
+        store.read_range((42, 3), 0, &mut small_buf).await?;
+        store.read_range((42, 3), 0, &mut large_buf).await?;
+        let stats = backend.get_stats();
+        assert_eq!(stats.get_object_calls, 0);
+        assert_eq!(stats.get_object_range_calls, 0);
+
         Ok(())
     }
 }
@@ -0,0 +1,421 @@
+//! Block transforms: compression and encryption of block objects.
+//!
+//! Every block is compressed first and then sealed, so the object store only
+//! ever sees the transformed bytes. Which transform a volume uses is fixed
+//! when it is first mounted and recorded in the metadata as a
+//! [`BlockFormat`], so every client encodes and decodes blocks the same way.
+//!
+//! Encrypted volumes have a random data key. It is stored in the format
+//! wrapped by a key derived from a passphrase (PBKDF2-HMAC-SHA256), the way
+//! age wraps file keys for passphrase recipients; the passphrase itself is
+//! never stored. Each block is sealed with a fresh nonce and its object key as
+//! associated data, so blocks cannot be swapped between keys unnoticed.
+
+use std::borrow::Cow;
+use std::fmt;
+use std::num::NonZeroU32;
+
+use anyhow::Context;
+use base64::Engine;
+use base64::engine::general_purpose::STANDARD as BASE64;
+use ring::aead::{
+    AES_256_GCM, Aad, Algorithm, CHACHA20_POLY1305, LessSafeKey, NONCE_LEN, Nonce, UnboundKey,
+};
+use ring::pbkdf2;
+use ring::rand::{SecureRandom, SystemRandom};
+use serde::{Deserialize, Serialize};
+use zeroize::Zeroizing;
+
+use crate::meta::MetaStore;
+
+/// Name of the volume setting holding the [`BlockFormat`].
+pub const BLOCK_FORMAT_SETTING: &str = "block_format";
+
+const DEFAULT_PBKDF2_ITERATIONS: u32 = 600_000;
+const SALT_LEN: usize = 16;
+const KEY_LEN: usize = 32;
+/// Associated data binding a wrapped key to its purpose.
+const KEY_AAD: &[u8] = b"slayerfs volume key";
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize, clap::ValueEnum)]
+#[serde(rename_all = "lowercase")]
+pub enum Compression {
+    #[default]
+    None,
+    Lz4,
+    Zstd,
+}
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize, clap::ValueEnum)]
+#[serde(rename_all = "kebab-case")]
+pub enum Encryption {
+    #[default]
+    None,
+    #[value(name = "aes256-gcm")]
+    #[serde(rename = "aes256-gcm")]
+    Aes256Gcm,
+    #[value(name = "chacha20-poly1305")]
+    #[serde(rename = "chacha20-poly1305")]
+    ChaCha20Poly1305,
+}
+
+impl Encryption {
+    fn algorithm(self) -> Option<&'static Algorithm> {
+        match self {
+            Encryption::None => None,
+            Encryption::Aes256Gcm => Some(&AES_256_GCM),
+            Encryption::ChaCha20Poly1305 => Some(&CHACHA20_POLY1305),
+        }
+    }
+}
+
+impl fmt::Display for Compression {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            Compression::None => "none",
+            Compression::Lz4 => "lz4",
+            Compression::Zstd => "zstd",
+        })
+    }
+}
+
+impl fmt::Display for Encryption {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            Encryption::None => "none",
+            Encryption::Aes256Gcm => "aes256-gcm",
+            Encryption::ChaCha20Poly1305 => "chacha20-poly1305",
+        })
+    }
+}
+
+/// Data key of an encrypted volume, sealed with a passphrase-derived key.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct WrappedKey {
+    /// Base64 PBKDF2 salt.
+    pub salt: String,
+    pub iterations: u32,
+    /// Base64 nonce followed by the sealed data key.
+    pub sealed: String,
+}
+
+/// Block format of a volume, stored under [`BLOCK_FORMAT_SETTING`].
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct BlockFormat {
+    #[serde(default)]
+    pub compression: Compression,
+    #[serde(default)]
+    pub encryption: Encryption,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub key: Option<WrappedKey>,
+}
+
+fn random_bytes(len: usize) -> anyhow::Result<Vec<u8>> {
+    let mut buf = vec![0u8; len];
+    SystemRandom::new()
+        .fill(&mut buf)
+        .map_err(|_| anyhow::anyhow!("failed to generate random bytes"))?;
+    Ok(buf)
+}
+
+fn aead_key(algorithm: &'static Algorithm, key: &[u8]) -> anyhow::Result<LessSafeKey> {
+    let key = UnboundKey::new(algorithm, key).map_err(|_| anyhow::anyhow!("invalid key"))?;
+    Ok(LessSafeKey::new(key))
+}
+
+fn derive_key(
+    passphrase: &str,
+    salt: &[u8],
+    iterations: u32,
+) -> anyhow::Result<Zeroizing<[u8; KEY_LEN]>> {
+    let iterations =
+        NonZeroU32::new(iterations).context("key derivation needs at least one iteration")?;
+    let mut key = Zeroizing::new([0u8; KEY_LEN]);
+    pbkdf2::derive(
+        pbkdf2::PBKDF2_HMAC_SHA256,
+        iterations,
+        salt,
+        passphrase.as_bytes(),
+        key.as_mut(),
+    );
+    Ok(key)
+}
+
+/// Prepends a fresh nonce to `data` sealed with `key`.
+fn seal(key: &LessSafeKey, aad: &[u8], data: &[u8]) -> anyhow::Result<Vec<u8>> {
+    let nonce = random_bytes(NONCE_LEN)?;
+    let mut out = Vec::with_capacity(NONCE_LEN + data.len() + key.algorithm().tag_len());
+    out.extend_from_slice(&nonce);
+    let mut sealed = data.to_vec();
+    key.seal_in_place_append_tag(
+        Nonce::try_assume_unique_for_key(&nonce).expect("nonce has the right length"),
+        Aad::from(aad),
+        &mut sealed,
+    )
+    .map_err(|_| anyhow::anyhow!("failed to encrypt"))?;
+    out.extend_from_slice(&sealed);
+    Ok(out)
+}
+
+/// Opens data produced by [`seal`], failing if it was tampered with.
+fn open(key: &LessSafeKey, aad: &[u8], data: &[u8]) -> Option<Vec<u8>> {
+    if data.len() < NONCE_LEN + key.algorithm().tag_len() {
+        return None;
+    }
+    let (nonce, sealed) = data.split_at(NONCE_LEN);
+    let nonce = Nonce::try_assume_unique_for_key(nonce).ok()?;
+    let mut buf = sealed.to_vec();
+    let len = key
+        .open_in_place(nonce, Aad::from(aad), &mut buf)
+        .ok()?
+        .len();
+    buf.truncate(len);
+    Some(buf)
+}
+
+impl BlockFormat {
+    /// Creates a format, generating a data key wrapped with `passphrase` if
+    /// the volume is encrypted.
+    pub fn new(
+        compression: Compression,
+        encryption: Encryption,
+        passphrase: Option<&str>,
+    ) -> anyhow::Result<Self> {
+        Self::with_iterations(
+            compression,
+            encryption,
+            passphrase,
+            DEFAULT_PBKDF2_ITERATIONS,
+        )
+    }
+
+    /// Like [`BlockFormat::new`], with a custom key derivation cost.
+    pub(crate) fn with_iterations(
+        compression: Compression,
+        encryption: Encryption,
+        passphrase: Option<&str>,
+        iterations: u32,
+    ) -> anyhow::Result<Self> {
+        let key = match encryption.algorithm() {
+            None => None,
+            Some(algorithm) => {
+                let passphrase = passphrase
+                    .filter(|p| !p.is_empty())
+                    .context("encryption needs a passphrase")?;
+                let salt = random_bytes(SALT_LEN)?;
+                let wrapping = derive_key(passphrase, &salt, iterations)?;
+                let data_key = Zeroizing::new(random_bytes(KEY_LEN)?);
+                let sealed = seal(&aead_key(algorithm, wrapping.as_ref())?, KEY_AAD, &data_key)?;
+                Some(WrappedKey {
+                    salt: BASE64.encode(salt),
+                    iterations,
+                    sealed: BASE64.encode(sealed),
+                })
+            }
+        };
+        Ok(Self {
+            compression,
+            encryption,
+            key,
+        })
+    }
+
+    /// Builds the transform of this format, unwrapping the data key with
+    /// `passphrase` for encrypted volumes.
+    pub fn transform(&self, passphrase: Option<&str>) -> anyhow::Result<BlockTransform> {
+        let cipher = match self.encryption.algorithm() {
+            None => None,
+            Some(algorithm) => {
+                let wrapped = self
+                    .key
+                    .as_ref()
+                    .context("block format of encrypted volume has no key")?;
+                let passphrase =
+                    passphrase.context("volume is encrypted, a passphrase is needed")?;
+                let salt = BASE64
+                    .decode(&wrapped.salt)
+                    .context("invalid salt in block format")?;
+                let sealed = BASE64
+                    .decode(&wrapped.sealed)
+                    .context("invalid key in block format")?;
+                let wrapping = derive_key(passphrase, &salt, wrapped.iterations)?;
+                let data_key = open(&aead_key(algorithm, wrapping.as_ref())?, KEY_AAD, &sealed)
+                    .map(Zeroizing::new)
+                    .context("wrong passphrase")?;
+                Some(aead_key(algorithm, &data_key)?)
+            }
+        };
+        Ok(BlockTransform {
+            compression: self.compression,
+            cipher,
+        })
+    }
+}
+
+/// Encodes blocks before they are stored and decodes them after reading.
+/// The default transform stores blocks unchanged.
+#[derive(Default)]
+pub struct BlockTransform {
+    compression: Compression,
+    cipher: Option<LessSafeKey>,
+}
+
+impl BlockTransform {
+    /// Whether blocks are stored as written, which allows reading parts of
+    /// an object directly.
+    pub fn is_identity(&self) -> bool {
+        self.compression == Compression::None && self.cipher.is_none()
+    }
+
+    /// Encodes the block stored under the object key `key`.
+    pub fn encode<'a>(&self, key: &str, data: &'a [u8]) -> anyhow::Result<Cow<'a, [u8]>> {
+        let compressed = match self.compression {
+            Compression::None => Cow::Borrowed(data),
+            Compression::Lz4 => Cow::Owned(lz4_flex::compress_prepend_size(data)),
+            Compression::Zstd => Cow::Owned(
+                zstd::encode_all(data, zstd::DEFAULT_COMPRESSION_LEVEL)
+                    .context("zstd compression failed")?,
+            ),
+        };
+        match &self.cipher {
+            None => Ok(compressed),
+            Some(cipher) => seal(cipher, key.as_bytes(), &compressed).map(Cow::Owned),
+        }
+    }
+
+    /// Decodes the block stored under the object key `key`.
+    pub fn decode(&self, key: &str, data: Vec<u8>) -> anyhow::Result<Vec<u8>> {
+        let compressed = match &self.cipher {
+            None => data,
+            Some(cipher) => open(cipher, key.as_bytes(), &data)
+                .with_context(|| format!("failed to decrypt block {key}"))?,
+        };
+        match self.compression {
+            Compression::None => Ok(compressed),
+            Compression::Lz4 => lz4_flex::decompress_size_prepended(&compressed)
+                .with_context(|| format!("failed to decompress block {key}")),
+            Compression::Zstd => zstd::decode_all(compressed.as_slice())
+                .with_context(|| format!("failed to decompress block {key}")),
+        }
+    }
+}
+
+/// Returns the transform of the volume behind `store`.
+///
+/// The stored block format wins; `compression` and `encryption` must match
+/// it when given. A volume without a format gets one built from them, but
+/// only a volume without entries can start out compressed or encrypted:
+/// blocks written before would no longer be readable.
+pub async fn load_transform<S>(
+    store: &S,
+    compression: Option<Compression>,
+    encryption: Option<Encryption>,
+    passphrase: Option<&str>,
+) -> anyhow::Result<BlockTransform>
+where
+    S: MetaStore + ?Sized,
+{
+    let format = match store.get_setting(BLOCK_FORMAT_SETTING).await? {
+        Some(stored) => parse_format(&stored)?,
+        None => {
+            let format = BlockFormat::new(
+                compression.unwrap_or_default(),
+                encryption.unwrap_or_default(),
+                passphrase,
+            )?;
+            if format != BlockFormat::default()
+                && !store.readdir(store.root_ino()).await?.is_empty()
+            {
+                anyhow::bail!(
+                    "volume already holds data, it cannot be compressed or encrypted afterwards"
+                );
+            }
+            let json = serde_json::to_string(&format)?;
+            if store
+                .set_setting(BLOCK_FORMAT_SETTING, &json, false)
+                .await?
+            {
+                format
+            } else {
+                // Another client recorded a format first.
+                let stored = store
+                    .get_setting(BLOCK_FORMAT_SETTING)
+                    .await?
+                    .context("block format vanished")?;
+                parse_format(&stored)?
+            }
+        }
+    };
+
+    if let Some(compression) = compression
+        && compression != format.compression
+    {
+        anyhow::bail!(
+            "volume uses {} compression, not {compression}",
+            format.compression
+        );
+    }
+    if let Some(encryption) = encryption
+        && encryption != format.encryption
+    {
+        anyhow::bail!(
+            "volume uses {} encryption, not {encryption}",
+            format.encryption
+        );
+    }
+    format.transform(passphrase)
+}
+
+fn parse_format(json: &str) -> anyhow::Result<BlockFormat> {
+    serde_json::from_str(json).context("invalid block format")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn format(compression: Compression, encryption: Encryption) -> BlockFormat {
+        BlockFormat::with_iterations(compression, encryption, Some("secret"), 1000).unwrap()
+    }
+
+    #[test]
+    fn blocks_round_trip() {
+        let data: Vec<u8> = (0..64 * 1024).map(|i| (i % 251) as u8).collect();
+        for compression in [Compression::None, Compression::Lz4, Compression::Zstd] {
+            for encryption in [
+                Encryption::None,
+                Encryption::Aes256Gcm,
+                Encryption::ChaCha20Poly1305,
+            ] {
+                let transform = format(compression, encryption)
+                    .transform(Some("secret"))
+                    .unwrap();
+                let encoded = transform.encode("chunks/1/0", &data).unwrap();
+                if compression != Compression::None {
+                    assert!(encoded.len() < data.len());
+                }
+                if encryption != Encryption::None {
+                    assert!(transform.decode("chunks/1/1", encoded.to_vec()).is_err());
+                }
+                assert_eq!(
+                    transform
+                        .decode("chunks/1/0", encoded.into_owned())
+                        .unwrap(),
+                    data
+                );
+            }
+        }
+    }
+
+    #[test]
+    fn wrong_passphrase_is_rejected() {
+        let format = format(Compression::None, Encryption::Aes256Gcm);
+        assert!(format.transform(Some("other")).is_err());
+        assert!(format.transform(None).is_err());
+        assert!(format.transform(Some("secret")).is_ok());
+
+        let json = serde_json::to_string(&format).unwrap();
+        assert!(!json.contains("secret"));
+        assert_eq!(parse_format(&json).unwrap(), format);
+    }
+}
@@ -12,14 +12,17 @@
 use crate::chuck::chunk::ChunkLayout;
 use crate::chuck::store::BlockStore;
 use crate::meta::MetaStore;
+use crate::meta::acl::{AclRule, AclType};
 use crate::meta::client::MetaClient;
 use crate::meta::config::MetaClientConfig;
 use crate::meta::layer::MetaLayer;
 use crate::meta::permission::Permission;
 use crate::meta::store::{
-    DirEntry, FileAttr, FileType, MetaError, SetAttrFlags, SetAttrRequest, StatFsSnapshot,
+    CloneOption, DirEntry, FileAttr, FileType, MetaError, SetAttrFlags, SetAttrRequest,
+    StatFsSnapshot, chmod_request,
 };
 use crate::vfs::fs::VFS;
+use crate::vfs::io::warmup::WarmupReport;
 use libc::{getegid, geteuid, getgroups};
 use std::io;
 use std::sync::Arc;
@@ -462,7 +465,8 @@ where
         .await
     }
 
-    async fn with_configs(
+    /// Create a new FileSystem with MetaClient and FileSystem configuration.
+    pub async fn with_configs(
         layout: ChunkLayout,
         store: S,
         meta: M,
@@ -625,12 +629,12 @@ where
         }
     }
 
-    fn permission_for(attr: &FileAttr) -> Permission {
+    fn permission_for(attr: &FileAttr, acl: Option<AclRule>) -> Permission {
         Permission {
             mode: attr.mode,
             uid: attr.uid,
             gid: attr.gid,
-            acl: None,
+            acl,
         }
     }
 
@@ -642,12 +646,17 @@ where
         }
     }
 
-    fn check_access(&self, attr: &FileAttr, mask: AccessMask, path: &str) -> io::Result<()> {
+    async fn check_access(&self, attr: &FileAttr, mask: AccessMask, path: &str) -> io::Result<()> {
         if !self.config.enforce_permissions {
             return Ok(());
         }
         let caller = &self.config.caller;
-        let perm = Self::permission_for(attr);
+        let acl = self
+            .meta_layer()
+            .get_acl(attr.ino, AclType::Access)
+            .await
+            .map_err(|e| meta_error_to_io(path, e))?;
+        let perm = Self::permission_for(attr, acl);
         if mask.contains(AccessMask::READ) && !perm.can_read(caller.uid, &caller.groups) {
             return Err(io::Error::new(
                 io::ErrorKind::PermissionDenied,
@@ -691,10 +700,22 @@ where
             gid: Some(gid),
             ..Default::default()
         };
-        self.meta_layer()
+        let attr = self
+            .meta_layer()
             .set_attr(ino, &req, SetAttrFlags::empty())
             .await
             .map_err(|e| meta_error_to_io(path, e))?;
+        let mode = self
+            .meta_layer()
+            .inherit_acl(parent_attr.ino, ino, attr.mode)
+            .await
+            .map_err(|e| meta_error_to_io(path, e))?;
+        if mode != attr.mode {
+            self.meta_layer()
+                .set_attr(ino, &chmod_request(mode), SetAttrFlags::empty())
+                .await
+                .map_err(|e| meta_error_to_io(path, e))?;
+        }
         Ok(())
     }
 
@@ -749,7 +770,7 @@ where
                 access |= AccessMask::WRITE;
             }
             if !access.is_empty() {
-                self.check_access(fi.attr(), access, &path)?;
+                self.check_access(fi.attr(), access, &path).await?;
             }
 
             // Handle truncate
@@ -805,7 +826,7 @@ where
                 access |= AccessMask::WRITE;
             }
             if !access.is_empty() {
-                self.check_access(fi.attr(), access, &path)?;
+                self.check_access(fi.attr(), access, &path).await?;
             }
 
             let fh = self
@@ -888,7 +909,8 @@ where
             if parent_attr.kind != FileType::Dir {
                 return Err(io::Error::new(io::ErrorKind::NotADirectory, dir));
             }
-            self.check_access(&parent_attr, AccessMask::WRITE | AccessMask::EXEC, &dir)?;
+            self.check_access(&parent_attr, AccessMask::WRITE | AccessMask::EXEC, &dir)
+                .await?;
 
             if let Some(ino) = self
                 .meta_layer()
@@ -1088,7 +1110,8 @@ where
                 &old_parent_attr,
                 AccessMask::WRITE | AccessMask::EXEC,
                 &old_dir,
-            )?;
+            )
+            .await?;
 
             let src_ino = self
                 .meta_layer()
@@ -1130,7 +1153,8 @@ where
                     &new_parent_attr,
                     AccessMask::WRITE | AccessMask::EXEC,
                     &new_dir,
-                )?;
+                )
+                .await?;
 
                 if dest_kind == FileType::Dir {
                     if src_attr.kind != FileType::Dir {
@@ -1189,7 +1213,8 @@ where
                 &new_parent_attr,
                 AccessMask::WRITE | AccessMask::EXEC,
                 &new_dir,
-            )?;
+            )
+            .await?;
             self.meta_layer()
                 .rename(old_parent_ino, &old_name, new_dir_ino, new_name)
                 .await
@@ -1253,7 +1278,8 @@ where
                 &parent_attr,
                 AccessMask::WRITE | AccessMask::EXEC,
                 &parent_path,
-            )?;
+            )
+            .await?;
 
             if self
                 .meta_layer()
@@ -1276,6 +1302,129 @@ where
         result
     }
 
+    /// Clone a file, symlink or directory tree without copying its data.
+    ///
+    /// With `preserve` the clone keeps the owner, mode and timestamps of the
+    /// source, which requires owning it; otherwise it belongs to the caller.
+    pub async fn clone_entry(&self, src: &str, dst: &str, preserve: bool) -> io::Result<FileAttr> {
+        let src = Self::normalize_path(src);
+        let dst = Self::normalize_path(dst);
+        let log_ctx = self.log_context();
+        let op_path = format!("{src} -> {dst}");
+        let result = async {
+            if dst == "/" {
+                return Err(io::Error::new(io::ErrorKind::InvalidInput, dst));
+            }
+            let (src_ino, _) = self
+                .meta_layer()
+                .lookup_path(&src)
+                .await
+                .map_err(|e| meta_error_to_io(&src, e))?
+                .ok_or_else(|| io::Error::new(io::ErrorKind::NotFound, src.clone()))?;
+            let src_attr = self
+                .meta_layer()
+                .stat(src_ino)
+                .await
+                .map_err(|e| meta_error_to_io(&src, e))?
+                .ok_or_else(|| io::Error::new(io::ErrorKind::NotFound, src.clone()))?;
+            self.check_access(&src_attr, AccessMask::READ, &src).await?;
+            if preserve {
+                self.check_owner(&src_attr, &src)?;
+            }
+
+            let (parent_path, _) = Self::split_dir_file(&dst);
+            let (parent_ino, parent_kind) = self
+                .meta_layer()
+                .lookup_path(&parent_path)
+                .await
+                .map_err(|e| meta_error_to_io(&parent_path, e))?
+                .ok_or_else(|| io::Error::new(io::ErrorKind::NotFound, parent_path.clone()))?;
+            if parent_kind != FileType::Dir {
+                return Err(io::Error::new(io::ErrorKind::NotADirectory, parent_path));
+            }
+            let parent_attr = self
+                .meta_layer()
+                .stat(parent_ino)
+                .await
+                .map_err(|e| meta_error_to_io(&parent_path, e))?
+                .ok_or_else(|| io::Error::new(io::ErrorKind::NotFound, parent_path.clone()))?;
+            self.check_access(
+                &parent_attr,
+                AccessMask::WRITE | AccessMask::EXEC,
+                &parent_path,
+            )
+            .await?;
+
+            let caller = &self.config.caller;
+            let opt = CloneOption {
+                preserve_attr: preserve,
+                uid: caller.uid,
+                gid: Self::desired_gid(&parent_attr, caller.gid),
+                ..Default::default()
+            };
+            self.vfs
+                .clone_ino(src_ino, &dst, opt)
+                .await
+                .map_err(io::Error::from)
+        }
+        .await;
+        self.log_result(log_ctx.as_ref(), "clone", &op_path, &result);
+        result
+    }
+
+    /// Get an extended attribute, `None` if it is not set. Requires read
+    /// access to `path`.
+    pub async fn get_xattr(&self, path: &str, name: &str) -> io::Result<Option<Vec<u8>>> {
+        let path = Self::normalize_path(path);
+        let log_ctx = self.log_context();
+        let result = async {
+            let fi = self.resolve(&path, true).await?;
+            self.check_access(fi.attr(), AccessMask::READ, &path)
+                .await?;
+            self.vfs
+                .get_xattr_ino(fi.inode(), name)
+                .await
+                .map_err(|e| meta_error_to_io(&path, e))
+        }
+        .await;
+        self.log_result(log_ctx.as_ref(), "get_xattr", &path, &result);
+        result
+    }
+
+    /// Set an extended attribute, replacing its value if it is set. Requires
+    /// write access to `path`.
+    pub async fn set_xattr(&self, path: &str, name: &str, value: &[u8]) -> io::Result<()> {
+        let path = Self::normalize_path(path);
+        let log_ctx = self.log_context();
+        let result = async {
+            let fi = self.resolve(&path, true).await?;
+            self.check_access(fi.attr(), AccessMask::WRITE, &path)
+                .await?;
+            self.vfs
+                .set_xattr_ino(fi.inode(), name, value, 0)
+                .await
+                .map_err(|e| meta_error_to_io(&path, e))
+        }
+        .await;
+        self.log_result(log_ctx.as_ref(), "set_xattr", &path, &result);
+        result
+    }
+
+    /// Load the data of the file or directory tree at `path` into the block
+    /// cache ahead of a job reading it. Requires read access to `path`.
+    pub async fn warmup(&self, path: &str) -> io::Result<WarmupReport> {
+        let path = Self::normalize_path(path);
+        let log_ctx = self.log_context();
+        let result = async {
+            let attr = self.vfs.stat(&path).await.map_err(io::Error::from)?;
+            self.check_access(&attr, AccessMask::READ, &path).await?;
+            self.vfs.warmup(&path).await.map_err(io::Error::from)
+        }
+        .await;
+        self.log_result(log_ctx.as_ref(), "warmup", &path, &result);
+        result
+    }
+
     /// Create a symbolic link.
     pub async fn symlink(&self, link_path: &str, target: &str) -> io::Result<()> {
         let link = Self::normalize_path(link_path);
@@ -1370,7 +1519,8 @@ where
             if fi.file_type() != FileType::File {
                 return Err(io::Error::new(io::ErrorKind::InvalidInput, path.clone()));
             }
-            self.check_access(fi.attr(), AccessMask::WRITE, &path)?;
+            self.check_access(fi.attr(), AccessMask::WRITE, &path)
+                .await?;
             self.vfs
                 .truncate_inode(fi.inode(), size)
                 .await
@@ -1403,7 +1553,7 @@ where
                 self.check_owner(attr, &path)?;
             }
             if req.size.is_some() {
-                self.check_access(attr, AccessMask::WRITE, &path)?;
+                self.check_access(attr, AccessMask::WRITE, &path).await?;
             }
             if (req.atime.is_some()
                 || req.mtime.is_some()
@@ -1412,7 +1562,7 @@ where
                 || flags.contains(SetAttrFlags::SET_MTIME_NOW))
                 && self.check_owner(attr, &path).is_err()
             {
-                self.check_access(attr, AccessMask::WRITE, &path)?;
+                self.check_access(attr, AccessMask::WRITE, &path).await?;
             }
             self.vfs
                 .set_attr(fi.inode(), req, flags)
@@ -1438,7 +1588,8 @@ where
             if attr.kind != FileType::Dir {
                 return Err(io::Error::new(io::ErrorKind::NotADirectory, path.clone()));
             }
-            self.check_access(&attr, AccessMask::READ | AccessMask::EXEC, &path)?;
+            self.check_access(&attr, AccessMask::READ | AccessMask::EXEC, &path)
+                .await?;
             self.meta_layer()
                 .readdir(ino)
                 .await
@@ -1455,7 +1606,8 @@ where
         let log_ctx = self.log_context();
         let result = async {
             let fi = self.resolve(&path, true).await?;
-            self.check_access(fi.attr(), AccessMask::READ, &path)?;
+            self.check_access(fi.attr(), AccessMask::READ, &path)
+                .await?;
             read_inode(&self.vfs, fi.inode(), fi.attr().clone(), offset, len, &path).await
         }
         .await;
@@ -1469,7 +1621,8 @@ where
         let log_ctx = self.log_context();
         let result = async {
             let fi = self.resolve(&path, true).await?;
-            self.check_access(fi.attr(), AccessMask::WRITE, &path)?;
+            self.check_access(fi.attr(), AccessMask::WRITE, &path)
+                .await?;
             write_inode(
                 &self.vfs,
                 fi.inode(),
@@ -1571,7 +1724,8 @@ where
                     if attr.kind != FileType::Dir {
                         return Err(io::Error::new(io::ErrorKind::NotADirectory, cur_path));
                     }
-                    self.check_access(&attr, AccessMask::EXEC, &cur_path)?;
+                    self.check_access(&attr, AccessMask::EXEC, &cur_path)
+                        .await?;
                     cur_ino = ino;
                     cur_attr = attr;
                 }
@@ -1580,7 +1734,8 @@ where
                         &cur_attr,
                         AccessMask::WRITE | AccessMask::EXEC,
                         &parent_path,
-                    )?;
+                    )
+                    .await?;
                     let ino = self
                         .meta_layer()
                         .mkdir(cur_ino, part.to_string())
@@ -1637,7 +1792,8 @@ where
         if parent_attr.kind != FileType::Dir {
             return Err(io::Error::new(io::ErrorKind::NotADirectory, dir));
         }
-        self.check_access(&parent_attr, AccessMask::WRITE | AccessMask::EXEC, &dir)?;
+        self.check_access(&parent_attr, AccessMask::WRITE | AccessMask::EXEC, &dir)
+            .await?;
 
         if let Some(existing) = self
             .meta_layer()
@@ -16,8 +16,10 @@ pub(crate) mod adapter;
 pub mod mount;
 use crate::chuck::store::BlockStore;
 use crate::meta::MetaLayer;
+use crate::meta::acl::{AclRule, AclType};
 use crate::meta::file_lock::{FileLockQuery, FileLockRange, FileLockType};
-use crate::meta::store::{MetaError, SetAttrFlags, SetAttrRequest};
+use crate::meta::permission::{AclFlags, Permission};
+use crate::meta::store::{CloneOption, MetaError, SetAttrFlags, SetAttrRequest};
 use crate::posix::NAME_MAX;
 use crate::vfs::error::VfsError;
 use crate::vfs::fs::{FileAttr as VfsFileAttr, FileType as VfsFileType, VFS};
@@ -37,6 +39,13 @@ use futures_util::stream::{self, BoxStream};
 use rfuse3::raw::Filesystem;
 use rfuse3::{FileType as FuseFileType, SetAttr, Timestamp};
 use tracing::{debug, error};
+
+/// Setting this xattr on a node clones it to the path given as value, e.g.
+/// `setfattr -n user.slayerfs.clone -v /snapshots/a src`. The path is
+/// relative to the root of the mount.
+const CLONE_XATTR: &str = "user.slayerfs.clone";
+/// Like [`CLONE_XATTR`], but the clone keeps the owner, mode and timestamps.
+const CLONE_PRESERVE_XATTR: &str = "user.slayerfs.clone-preserve";
 #[cfg(all(test, target_os = "linux"))]
 mod mount_tests {
     use super::*;
@@ -153,15 +162,31 @@ where
 {
     async fn apply_new_entry_attrs(
         &self,
+        parent: u64,
         ino: i64,
         uid: u32,
         gid: u32,
         mode: Option<u32>,
     ) -> Option<VfsFileAttr> {
+        let mode = match mode.map(sanitize_special_mode_bits) {
+            // The default ACL of the parent may take permissions away.
+            Some(mode) => match self
+                .meta_layer()
+                .inherit_acl(parent as i64, ino, mode)
+                .await
+            {
+                Ok(mode) => Some(mode),
+                Err(err) => {
+                    error!(ino, "failed to inherit default ACL: {err}");
+                    Some(mode)
+                }
+            },
+            None => None,
+        };
         let req = SetAttrRequest {
             uid: Some(uid),
             gid: Some(gid),
-            mode: mode.map(sanitize_special_mode_bits),
+            mode,
             ..Default::default()
         };
         if attr_request_is_empty(&req) {
@@ -172,6 +197,46 @@ where
             Err(_err) => self.stat_ino(ino).await,
         }
     }
+
+    /// New entries are created by root and handed to the caller afterwards,
+    /// so the caller's user and group quotas are checked up front.
+    async fn check_new_entry_quota(
+        &self,
+        parent: u64,
+        uid: u32,
+        gid: u32,
+        space: u64,
+    ) -> FuseResult<()> {
+        self.meta_layer()
+            .check_quota(parent as i64, uid, gid, space, 1)
+            .await
+            .map_err(Errno::from)
+    }
+
+    /// Whether the caller of `req` has all access in `flag` to `attr`,
+    /// going by its access ACL when it has one.
+    async fn check_request_access(
+        &self,
+        attr: &VfsFileAttr,
+        req: &Request,
+        flag: AclFlags,
+    ) -> FuseResult<()> {
+        let acl = self
+            .get_acl_ino(attr.ino, AclType::Access)
+            .await
+            .map_err(Errno::from)?;
+        let perm = Permission {
+            mode: attr.mode,
+            uid: attr.uid,
+            gid: attr.gid,
+            acl,
+        };
+        if perm.check_access(req.uid, &[req.gid], flag) {
+            Ok(())
+        } else {
+            Err(libc::EACCES.into())
+        }
+    }
 }
 #[allow(refining_impl_trait_reachable)]
 impl<S, M> Filesystem for VFS<S, M>
@@ -644,6 +709,9 @@ where
         }
         p.push_str(&name);
 
+        self.check_new_entry_quota(parent, req.uid, req.gid, 0)
+            .await?;
+
         // Extract file type from mode
         let file_type = mode & libc::S_IFMT;
 
@@ -666,7 +734,7 @@ where
 
         // Apply mode after stripping special bits unsupported by SlayerFS.
         let Some(vattr) = self
-            .apply_new_entry_attrs(ino, req.uid, req.gid, Some(mode))
+            .apply_new_entry_attrs(parent, ino, req.uid, req.gid, Some(mode))
             .await
         else {
             return Err(libc::ENOENT.into());
@@ -717,11 +785,13 @@ where
             p.push('/');
         }
         p.push_str(&name);
+        self.check_new_entry_quota(parent, req.uid, req.gid, 0)
+            .await?;
         let _ino = self.mkdir_p(&p).await.map_err(Errno::from)?;
         // Strip setuid/setgid/sticky, then apply the caller's umask.
         let masked_mode = apply_creation_umask(mode, umask);
         let Some(vattr) = self
-            .apply_new_entry_attrs(_ino, req.uid, req.gid, Some(masked_mode))
+            .apply_new_entry_attrs(parent, _ino, req.uid, req.gid, Some(masked_mode))
             .await
         else {
             return Err(libc::ENOENT.into());
@@ -766,9 +836,11 @@ where
             p.push('/');
         }
         p.push_str(&name);
+        self.check_new_entry_quota(parent, req.uid, req.gid, 0)
+            .await?;
         let ino = self.create_file(&p).await.map_err(Errno::from)?;
         let Some(vattr) = self
-            .apply_new_entry_attrs(ino, req.uid, req.gid, Some(mode))
+            .apply_new_entry_attrs(parent, ino, req.uid, req.gid, Some(mode))
             .await
         else {
             return Err(libc::ENOENT.into());
@@ -896,6 +968,8 @@ where
         parent_path.push_str(&name);
 
         let target = link.to_string_lossy();
+        self.check_new_entry_quota(parent, req.uid, req.gid, target.len() as u64)
+            .await?;
 
         let (ino, vattr) = self
             .create_symlink(&parent_path, target.as_ref())
@@ -903,7 +977,7 @@ where
             .map_err(Errno::from)?;
 
         let attr = self
-            .apply_new_entry_attrs(ino, req.uid, req.gid, None)
+            .apply_new_entry_attrs(parent, ino, req.uid, req.gid, None)
             .await
             .unwrap_or(vattr);
 
@@ -1100,7 +1174,7 @@ where
 
     async fn setxattr(
         &self,
-        _req: Request,
+        req: Request,
         inode: u64,
         name: &OsStr,
         value: &[u8],
@@ -1110,16 +1184,62 @@ where
         if position != 0 {
             return Err(libc::EINVAL.into());
         }
-        if self.stat_ino(inode as i64).await.is_none() {
+        let Some(attr) = self.stat_ino(inode as i64).await else {
             return Err(libc::ENOENT.into());
-        }
+        };
         let name = name.to_string_lossy();
+        if name == CLONE_XATTR || name == CLONE_PRESERVE_XATTR {
+            let preserve = name == CLONE_PRESERVE_XATTR;
+            self.check_request_access(&attr, &req, AclFlags::READ).await?;
+            if preserve && req.uid != 0 && req.uid != attr.uid {
+                return Err(libc::EPERM.into());
+            }
+            let dst = std::str::from_utf8(value).map_err(|_| Errno::from(libc::EINVAL))?;
+            let (parent_path, _) = Self::split_dir_file(&Self::norm_path(dst));
+            let (parent_ino, _) = self
+                .meta_layer()
+                .lookup_path(&parent_path)
+                .await
+                .map_err(Errno::from)?
+                .ok_or_else(|| Errno::from(libc::ENOENT))?;
+            let Some(parent_attr) = self.stat_ino(parent_ino).await else {
+                return Err(libc::ENOENT.into());
+            };
+            if !matches!(parent_attr.kind, VfsFileType::Dir) {
+                return Err(libc::ENOTDIR.into());
+            }
+            self.check_request_access(&parent_attr, &req, AclFlags::WRITE | AclFlags::EXEC)
+                .await?;
+            let opt = CloneOption {
+                preserve_attr: preserve,
+                uid: req.uid,
+                gid: req.gid,
+                ..Default::default()
+            };
+            return self
+                .clone_ino(inode as i64, dst, opt)
+                .await
+                .map(|_| ())
+                .map_err(Errno::from);
+        }
+        if let Some(acl_type) = AclType::from_xattr_name(&name) {
+            if req.uid != 0 && req.uid != attr.uid {
+                return Err(libc::EPERM.into());
+            }
+            let rule = AclRule::from_xattr(value).map_err(|_| Errno::from(libc::EINVAL))?;
+            return self
+                .set_acl_ino(inode as i64, acl_type, Some(&rule))
+                .await
+                .map_err(acl_errno);
+        }
         self.set_xattr_ino(inode as i64, &name, value, flags)
             .await
             .map_err(|e| match e {
                 MetaError::AlreadyExists { .. } => Errno::from(libc::EEXIST),
                 MetaError::NotSupported(_) | MetaError::NotImplemented => Errno::from(libc::ENOSYS),
                 MetaError::NotFound(_) => Errno::from(libc::ENODATA),
+                MetaError::InvalidXattrName(_) => Errno::from(libc::ERANGE),
+                MetaError::XattrTooLarge => Errno::from(libc::E2BIG),
                 _ => Errno::from(libc::EIO),
             })
     }
@@ -1135,9 +1255,14 @@ where
             return Err(libc::ENOENT.into());
         }
         let name = name.to_string_lossy();
-        let value = self
-            .get_xattr_ino(inode as i64, &name)
-            .await
+        let value = match AclType::from_xattr_name(&name) {
+            Some(acl_type) => self
+                .get_acl_ino(inode as i64, acl_type)
+                .await
+                .map(|rule| rule.map(|rule| rule.to_xattr())),
+            None => self.get_xattr_ino(inode as i64, &name).await,
+        };
+        let value = value
             .map_err(|e| match e {
                 MetaError::NotSupported(_) | MetaError::NotImplemented => Errno::from(libc::ENOSYS),
                 _ => Errno::from(libc::EIO),
@@ -1178,11 +1303,20 @@ where
         Ok(ReplyXAttr::Data(Bytes::from(data)))
     }
 
-    async fn removexattr(&self, _req: Request, inode: u64, name: &OsStr) -> FuseResult<()> {
-        if self.stat_ino(inode as i64).await.is_none() {
+    async fn removexattr(&self, req: Request, inode: u64, name: &OsStr) -> FuseResult<()> {
+        let Some(attr) = self.stat_ino(inode as i64).await else {
             return Err(libc::ENOENT.into());
-        }
+        };
         let name = name.to_string_lossy();
+        if let Some(acl_type) = AclType::from_xattr_name(&name) {
+            if req.uid != 0 && req.uid != attr.uid {
+                return Err(libc::EPERM.into());
+            }
+            return self
+                .set_acl_ino(inode as i64, acl_type, None)
+                .await
+                .map_err(acl_errno);
+        }
         self.remove_xattr_ino(inode as i64, &name)
             .await
             .map_err(|e| match e {
@@ -1380,6 +1514,17 @@ where
 }
 
 // =============== helpers ===============
+fn acl_errno(err: MetaError) -> Errno {
+    match err {
+        MetaError::InvalidAcl(_) => Errno::from(libc::EINVAL),
+        // Only directories have a default ACL.
+        MetaError::NotDirectory(_) => Errno::from(libc::EACCES),
+        MetaError::NotSupported(_) | MetaError::NotImplemented => Errno::from(libc::EOPNOTSUPP),
+        MetaError::NotFound(_) => Errno::from(libc::ENOENT),
+        _ => Errno::from(libc::EIO),
+    }
+}
+
 impl From<MetaError> for Errno {
     fn from(val: MetaError) -> Self {
         let code = match val {
@@ -1389,7 +1534,10 @@ impl From<MetaError> for Errno {
             MetaError::DirectoryNotEmpty(_) => libc::ENOTEMPTY,
             MetaError::AlreadyExists { .. } => libc::EEXIST,
             MetaError::NotSupported(_) | MetaError::NotImplemented => libc::ENOSYS,
-            MetaError::InvalidPath(_) => libc::EINVAL,
+            MetaError::InvalidPath(_) | MetaError::InvalidAcl(_) => libc::EINVAL,
+            MetaError::QuotaExceeded => libc::EDQUOT,
+            MetaError::InvalidXattrName(_) => libc::ERANGE,
+            MetaError::XattrTooLarge => libc::E2BIG,
             _ => libc::EIO,
         };
         Errno::from(code)
@@ -1423,7 +1571,7 @@ impl From<VfsError> for Errno {
             VfsError::WriteZero => libc::EIO,
             VfsError::StorageFull => libc::ENOSPC,
             VfsError::NotSeekable => libc::ESPIPE,
-            VfsError::QuotaExceeded => libc::EDQUOT,
+            VfsError::QuotaExceeded | VfsError::Meta(MetaError::QuotaExceeded) => libc::EDQUOT,
             VfsError::FileTooLarge => libc::EFBIG,
             VfsError::ResourceBusy => libc::EBUSY,
             VfsError::ExecutableFileBusy => libc::ETXTBSY,
@@ -1558,3 +1706,80 @@ mod mode_sanitization_tests {
         assert_eq!(apply_creation_umask(0o4755, 0o022), 0o755);
     }
 }
+
+#[cfg(test)]
+mod clone_xattr_tests {
+    use super::*;
+    use crate::chuck::chunk::ChunkLayout;
+    use crate::chuck::store::InMemoryBlockStore;
+    use crate::meta::factory::create_meta_store_from_url;
+
+    const USER: Request = Request {
+        unique: 0,
+        uid: 1000,
+        gid: 1000,
+        pid: 0,
+    };
+
+    async fn new_test_vfs() -> VFS<InMemoryBlockStore, impl MetaLayer> {
+        let meta_handle = create_meta_store_from_url("sqlite::memory:").await.unwrap();
+        VFS::new(
+            ChunkLayout::default(),
+            InMemoryBlockStore::new(),
+            meta_handle.store(),
+        )
+        .await
+        .unwrap()
+    }
+
+    #[tokio::test]
+    async fn clone_xattr_checks_access_of_the_caller() {
+        let fs = new_test_vfs().await;
+        // Both owned by root, 0644 and 0755
+        let src = fs.create_file("/src").await.unwrap();
+        let dst = fs.mkdir_p("/dst").await.unwrap();
+        let clone = |name: &'static str, path: &'static str| {
+            Filesystem::setxattr(
+                &fs,
+                USER,
+                src as u64,
+                OsStr::new(name),
+                path.as_bytes(),
+                0,
+                0,
+            )
+        };
+
+        // The destination directory is not writable by the user
+        assert_eq!(
+            clone(CLONE_XATTR, "/dst/a").await,
+            Err(Errno::from(libc::EACCES))
+        );
+
+        let chown = SetAttrRequest {
+            uid: Some(USER.uid),
+            gid: Some(USER.gid),
+            ..Default::default()
+        };
+        fs.set_attr(dst, &chown, SetAttrFlags::empty())
+            .await
+            .unwrap();
+        clone(CLONE_XATTR, "/dst/a").await.unwrap();
+        assert_eq!(fs.stat("/dst/a").await.unwrap().uid, USER.uid);
+
+        // Keeping the owner of the source requires owning it
+        assert_eq!(
+            clone(CLONE_PRESERVE_XATTR, "/dst/b").await,
+            Err(Errno::from(libc::EPERM))
+        );
+
+        // The source is no longer readable by the user
+        fs.chmod(src, 0o600).await.unwrap();
+        assert_eq!(
+            clone(CLONE_XATTR, "/dst/c").await,
+            Err(Errno::from(libc::EACCES))
+        );
+        assert!(fs.stat("/dst/b").await.is_err());
+        assert!(fs.stat("/dst/c").await.is_err());
+    }
+}
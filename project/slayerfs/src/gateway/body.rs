@@ -0,0 +1,240 @@
+//! Upload bodies: streaming them into a file of the volume.
+//!
+//! Bodies are written in large pieces through [`VfsClient::write_at`], which
+//! commits every piece before returning, so a file is complete once
+//! [`write_body`] returns and can be renamed into place.
+
+use axum::body::Body;
+use axum::http::HeaderMap;
+use futures::StreamExt;
+use sha2::{Digest, Sha256};
+
+use crate::chuck::store::BlockStore;
+use crate::gateway::error::S3Error;
+use crate::meta::MetaStore;
+use crate::vfs::sdk::VfsClient;
+
+/// Bytes gathered before they are written to the file.
+const WRITE_BUFFER: usize = 8 * 1024 * 1024;
+/// Longest chunk header or trailer line accepted in an aws-chunked body.
+const MAX_LINE: usize = 4096;
+/// Largest XML request document, enough for a batch delete of 1000 keys.
+const MAX_XML_BODY: usize = 2 * 1024 * 1024;
+
+/// Writes the (decoded) request body to the existing empty file at `path`,
+/// refusing bodies larger than `limit`, and returns its MD5. The payload
+/// hash the request was signed with is checked when the client sent one.
+pub(crate) async fn write_body<S, M>(
+    fs: &VfsClient<S, M>,
+    path: &str,
+    headers: &HeaderMap,
+    body: Body,
+    limit: u64,
+) -> Result<[u8; 16], S3Error>
+where
+    S: BlockStore + Send + Sync + 'static,
+    M: MetaStore + 'static,
+{
+    let header = |name: &str| headers.get(name).and_then(|value| value.to_str().ok());
+    let content_sha256 = header("x-amz-content-sha256");
+    let chunked = content_sha256.is_some_and(|sha| sha.starts_with("STREAMING-"))
+        || header("content-encoding").is_some_and(|enc| {
+            enc.split(',')
+                .any(|enc| enc.trim().eq_ignore_ascii_case("aws-chunked"))
+        });
+    let expected_sha256 = content_sha256
+        .filter(|sha| sha.len() == 64)
+        .and_then(|sha| hex::decode(sha).ok());
+
+    let mut decoder = chunked.then(ChunkedDecoder::default);
+    let mut sha256 = Sha256::new();
+    let mut md5 = md5::Context::new();
+    let mut buf = Vec::with_capacity(WRITE_BUFFER);
+    let mut offset = 0u64;
+    let mut stream = body.into_data_stream();
+    loop {
+        let piece = stream.next().await;
+        if let Some(piece) = &piece {
+            let piece = piece.as_ref().map_err(|_| S3Error::IncompleteBody)?;
+            if expected_sha256.is_some() {
+                sha256.update(piece);
+            }
+            match &mut decoder {
+                Some(decoder) => decoder.feed(piece, &mut buf)?,
+                None => buf.extend_from_slice(piece),
+            }
+            if offset + buf.len() as u64 > limit {
+                return Err(S3Error::EntityTooLarge);
+            }
+            if buf.len() < WRITE_BUFFER {
+                continue;
+            }
+        }
+        if !buf.is_empty() {
+            md5.consume(&buf);
+            fs.write_at(path, offset, &buf)
+                .await
+                .map_err(|err| S3Error::from_io(err, path))?;
+            offset += buf.len() as u64;
+            buf.clear();
+        }
+        if piece.is_none() {
+            break;
+        }
+    }
+
+    if decoder.is_some_and(|decoder| !decoder.is_done()) {
+        return Err(S3Error::IncompleteBody);
+    }
+    if let Some(expected) = header("x-amz-decoded-content-length")
+        && expected.parse::<u64>().ok() != Some(offset)
+    {
+        return Err(S3Error::IncompleteBody);
+    }
+    if let Some(expected) = expected_sha256
+        && sha256.finalize().as_slice() != expected.as_slice()
+    {
+        return Err(S3Error::ContentSha256Mismatch);
+    }
+    Ok(md5.compute().0)
+}
+
+/// Reads the XML document of a request, checking the payload hash the
+/// request was signed with when the client sent one.
+pub(crate) async fn read_xml(headers: &HeaderMap, body: Body) -> Result<String, S3Error> {
+    let data = axum::body::to_bytes(body, MAX_XML_BODY)
+        .await
+        .map_err(|_| S3Error::MalformedXml)?;
+    if let Some(expected) = headers
+        .get("x-amz-content-sha256")
+        .and_then(|value| value.to_str().ok())
+        .filter(|sha| sha.len() == 64)
+        && !hex::encode(Sha256::digest(&data)).eq_ignore_ascii_case(expected)
+    {
+        return Err(S3Error::ContentSha256Mismatch);
+    }
+    String::from_utf8(data.to_vec()).map_err(|_| S3Error::MalformedXml)
+}
+
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+enum ChunkState {
+    /// Expecting `<hex size>[;chunk-signature=<sig>]\r\n`.
+    #[default]
+    Header,
+    /// Inside the data of a chunk, with this many bytes left.
+    Data(u64),
+    /// Expecting the `\r\n` after the data of a chunk.
+    DataEnd,
+    /// After the final zero-sized chunk: optional trailers, then `\r\n`.
+    Trailer,
+    Done,
+}
+
+/// Decodes the `aws-chunked` content encoding of streaming uploads. Chunk
+/// signatures are not checked; the seed signature of the request is.
+#[derive(Debug, Default)]
+pub(crate) struct ChunkedDecoder {
+    state: ChunkState,
+    line: Vec<u8>,
+}
+
+impl ChunkedDecoder {
+    /// Decodes `input`, appending the payload to `out`.
+    pub(crate) fn feed(&mut self, mut input: &[u8], out: &mut Vec<u8>) -> Result<(), S3Error> {
+        while !input.is_empty() {
+            match self.state {
+                ChunkState::Data(left) => {
+                    let n = left.min(input.len() as u64) as usize;
+                    out.extend_from_slice(&input[..n]);
+                    input = &input[n..];
+                    self.state = match left - n as u64 {
+                        0 => ChunkState::DataEnd,
+                        left => ChunkState::Data(left),
+                    };
+                }
+                ChunkState::Done => return Err(S3Error::IncompleteBody),
+                _ => {
+                    let Some(end) = input.iter().position(|&b| b == b'\n') else {
+                        self.line.extend_from_slice(input);
+                        return self.check_line_len();
+                    };
+                    self.line.extend_from_slice(&input[..=end]);
+                    input = &input[end + 1..];
+                    self.check_line_len()?;
+                    let line = std::mem::take(&mut self.line);
+                    self.end_line(&line)?;
+                }
+            }
+        }
+        Ok(())
+    }
+
+    pub(crate) fn is_done(&self) -> bool {
+        self.state == ChunkState::Done
+    }
+
+    fn check_line_len(&self) -> Result<(), S3Error> {
+        if self.line.len() > MAX_LINE {
+            return Err(S3Error::IncompleteBody);
+        }
+        Ok(())
+    }
+
+    fn end_line(&mut self, line: &[u8]) -> Result<(), S3Error> {
+        let line = line.strip_suffix(b"\r\n").ok_or(S3Error::IncompleteBody)?;
+        self.state = match self.state {
+            ChunkState::Header => {
+                let size = std::str::from_utf8(line)
+                    .ok()
+                    .and_then(|line| line.split(';').next())
+                    .and_then(|size| u64::from_str_radix(size.trim(), 16).ok())
+                    .ok_or(S3Error::IncompleteBody)?;
+                match size {
+                    0 => ChunkState::Trailer,
+                    size => ChunkState::Data(size),
+                }
+            }
+            ChunkState::DataEnd if line.is_empty() => ChunkState::Header,
+            ChunkState::Trailer if line.is_empty() => ChunkState::Done,
+            // Trailing checksums such as `x-amz-checksum-crc32:...`.
+            ChunkState::Trailer => ChunkState::Trailer,
+            _ => return Err(S3Error::IncompleteBody),
+        };
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn decodes_aws_chunked_in_any_split() {
+        let body = b"5;chunk-signature=aa\r\nhello\r\n7;chunk-signature=bb\r\n, world\r\n\
+                     0;chunk-signature=cc\r\nx-amz-checksum-crc32:AAAAAA==\r\n\r\n";
+        for split in 1..body.len() {
+            let mut decoder = ChunkedDecoder::default();
+            let mut out = Vec::new();
+            for piece in body.chunks(split) {
+                decoder.feed(piece, &mut out).unwrap();
+            }
+            assert!(decoder.is_done(), "split {split}");
+            assert_eq!(out, b"hello, world");
+        }
+    }
+
+    #[test]
+    fn rejects_malformed_chunks() {
+        for body in [
+            &b"zz\r\nhello\r\n"[..],
+            b"5\r\nhello!\r\n",
+            b"0\r\n\r\nmore",
+        ] {
+            let mut decoder = ChunkedDecoder::default();
+            assert!(decoder.feed(body, &mut Vec::new()).is_err());
+        }
+        let mut decoder = ChunkedDecoder::default();
+        decoder.feed(b"5\r\nhel", &mut Vec::new()).unwrap();
+        assert!(!decoder.is_done());
+    }
+}
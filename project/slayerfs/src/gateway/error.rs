@@ -0,0 +1,171 @@
+//! S3 error responses.
+
+use std::io;
+
+use axum::http::header::CONTENT_TYPE;
+use axum::http::{Method, StatusCode};
+use axum::response::{IntoResponse, Response};
+use thiserror::Error;
+
+use crate::gateway::xml::XmlWriter;
+
+#[derive(Error, Debug)]
+pub enum S3Error {
+    #[error("Access Denied")]
+    AccessDenied,
+
+    #[error("The specified bucket is not valid: {0}")]
+    InvalidBucketName(String),
+
+    #[error("The specified bucket does not exist: {0}")]
+    NoSuchBucket(String),
+
+    #[error("The bucket you tried to delete is not empty: {0}")]
+    BucketNotEmpty(String),
+
+    #[error(
+        "Your previous request to create the named bucket succeeded and you already own it: {0}"
+    )]
+    BucketAlreadyOwnedByYou(String),
+
+    #[error("The specified key does not exist: {0}")]
+    NoSuchKey(String),
+
+    #[error("The specified multipart upload does not exist: {0}")]
+    NoSuchUpload(String),
+
+    #[error("One or more of the specified parts could not be found: {0}")]
+    InvalidPart(String),
+
+    #[error("The list of parts was not in ascending order")]
+    InvalidPartOrder,
+
+    #[error("Your proposed upload is smaller than the minimum allowed object size")]
+    EntityTooSmall,
+
+    #[error("Your proposed upload exceeds the maximum allowed object size")]
+    EntityTooLarge,
+
+    #[error("You did not provide the number of bytes specified by the Content-Length HTTP header")]
+    IncompleteBody,
+
+    #[error("The provided 'x-amz-content-sha256' header does not match what was computed")]
+    ContentSha256Mismatch,
+
+    #[error("The requested range is not satisfiable")]
+    InvalidRange,
+
+    #[error("{0}")]
+    InvalidArgument(String),
+
+    #[error("{0}")]
+    InvalidRequest(String),
+
+    #[error(
+        "The XML you provided was not well-formed or did not validate against our published schema"
+    )]
+    MalformedXml,
+
+    #[error("The specified method is not allowed against this resource")]
+    MethodNotAllowed,
+
+    #[error("{0} is not implemented by the gateway")]
+    NotImplemented(String),
+
+    #[error("The AWS access key ID you provided does not exist in our records")]
+    InvalidAccessKeyId,
+
+    #[error("The request signature we calculated does not match the signature you provided")]
+    SignatureDoesNotMatch,
+
+    #[error("{0}")]
+    AuthorizationHeaderMalformed(String),
+
+    #[error("The difference between the request time and the server's time is too large")]
+    RequestTimeTooSkewed,
+
+    #[error("Quota exceeded")]
+    QuotaExceeded,
+
+    #[error("{0}")]
+    Internal(String),
+}
+
+impl S3Error {
+    fn status_and_code(&self) -> (StatusCode, &'static str) {
+        match self {
+            Self::AccessDenied => (StatusCode::FORBIDDEN, "AccessDenied"),
+            Self::InvalidBucketName(_) => (StatusCode::BAD_REQUEST, "InvalidBucketName"),
+            Self::NoSuchBucket(_) => (StatusCode::NOT_FOUND, "NoSuchBucket"),
+            Self::BucketNotEmpty(_) => (StatusCode::CONFLICT, "BucketNotEmpty"),
+            Self::BucketAlreadyOwnedByYou(_) => (StatusCode::CONFLICT, "BucketAlreadyOwnedByYou"),
+            Self::NoSuchKey(_) => (StatusCode::NOT_FOUND, "NoSuchKey"),
+            Self::NoSuchUpload(_) => (StatusCode::NOT_FOUND, "NoSuchUpload"),
+            Self::InvalidPart(_) => (StatusCode::BAD_REQUEST, "InvalidPart"),
+            Self::InvalidPartOrder => (StatusCode::BAD_REQUEST, "InvalidPartOrder"),
+            Self::EntityTooSmall => (StatusCode::BAD_REQUEST, "EntityTooSmall"),
+            Self::EntityTooLarge => (StatusCode::BAD_REQUEST, "EntityTooLarge"),
+            Self::IncompleteBody => (StatusCode::BAD_REQUEST, "IncompleteBody"),
+            Self::ContentSha256Mismatch => (StatusCode::BAD_REQUEST, "XAmzContentSHA256Mismatch"),
+            Self::InvalidRange => (StatusCode::RANGE_NOT_SATISFIABLE, "InvalidRange"),
+            Self::InvalidArgument(_) => (StatusCode::BAD_REQUEST, "InvalidArgument"),
+            Self::InvalidRequest(_) => (StatusCode::BAD_REQUEST, "InvalidRequest"),
+            Self::MalformedXml => (StatusCode::BAD_REQUEST, "MalformedXML"),
+            Self::MethodNotAllowed => (StatusCode::METHOD_NOT_ALLOWED, "MethodNotAllowed"),
+            Self::NotImplemented(_) => (StatusCode::NOT_IMPLEMENTED, "NotImplemented"),
+            Self::InvalidAccessKeyId => (StatusCode::FORBIDDEN, "InvalidAccessKeyId"),
+            Self::SignatureDoesNotMatch => (StatusCode::FORBIDDEN, "SignatureDoesNotMatch"),
+            Self::AuthorizationHeaderMalformed(_) => {
+                (StatusCode::BAD_REQUEST, "AuthorizationHeaderMalformed")
+            }
+            Self::RequestTimeTooSkewed => (StatusCode::FORBIDDEN, "RequestTimeTooSkewed"),
+            Self::QuotaExceeded => (StatusCode::FORBIDDEN, "QuotaExceeded"),
+            Self::Internal(_) => (StatusCode::INTERNAL_SERVER_ERROR, "InternalError"),
+        }
+    }
+
+    /// The S3 error code, e.g. `NoSuchKey`.
+    pub fn code(&self) -> &'static str {
+        self.status_and_code().1
+    }
+
+    /// Maps a filesystem error on the object `key`.
+    pub fn from_io(err: io::Error, key: &str) -> Self {
+        match err.kind() {
+            io::ErrorKind::NotFound => Self::NoSuchKey(key.to_string()),
+            io::ErrorKind::PermissionDenied => Self::AccessDenied,
+            io::ErrorKind::QuotaExceeded | io::ErrorKind::StorageFull => Self::QuotaExceeded,
+            io::ErrorKind::NotADirectory
+            | io::ErrorKind::IsADirectory
+            | io::ErrorKind::AlreadyExists
+            | io::ErrorKind::DirectoryNotEmpty => Self::InvalidRequest(format!(
+                "{key} conflicts with an existing file or directory"
+            )),
+            io::ErrorKind::InvalidFilename => {
+                Self::InvalidArgument(format!("{key} is not a valid object key"))
+            }
+            _ => Self::Internal(err.to_string()),
+        }
+    }
+
+    /// The response to a request with `method`; HEAD responses carry no body.
+    pub fn into_response_for(self, method: &Method) -> Response {
+        if *method == Method::HEAD {
+            return self.status_and_code().0.into_response();
+        }
+        self.into_response()
+    }
+}
+
+impl IntoResponse for S3Error {
+    fn into_response(self) -> Response {
+        let (status, code) = self.status_and_code();
+        if status.is_server_error() {
+            tracing::warn!(error = %self, "gateway request failed");
+        }
+        let mut xml = XmlWriter::new("Error");
+        xml.elem("Code", code).elem("Message", self.to_string());
+        let body = xml.finish("Error");
+        (status, [(CONTENT_TYPE, "application/xml")], body).into_response()
+    }
+}
@@ -0,0 +1,329 @@
+//! ListBuckets and ListObjects (V1 and V2).
+//!
+//! Listings walk the directory tree of the bucket in key order. A directory
+//! sorts as its name followed by `/`, which is where its keys sort, so the
+//! walk can stop as soon as a page is full and skip every subtree that lies
+//! before the marker or outside the prefix.
+
+use std::io;
+
+use axum::response::Response;
+
+use crate::chuck::store::BlockStore;
+use crate::gateway::auth::uri_encode;
+use crate::gateway::xml::XmlWriter;
+use crate::gateway::{EMPTY_ETAG, Gateway, Query, S3Error, is_bucket_name, iso8601, xml_response};
+use crate::meta::MetaStore;
+use crate::meta::store::{DirEntry, FileType};
+
+/// Most keys returned per page.
+const MAX_KEYS: usize = 1000;
+const OWNER_ID: &str = "slayerfs";
+
+enum Item {
+    Object {
+        key: String,
+        size: u64,
+        mtime: i64,
+        etag: String,
+    },
+    Prefix(String),
+}
+
+impl Item {
+    fn key(&self) -> &str {
+        match self {
+            Item::Object { key, .. } | Item::Prefix(key) => key,
+        }
+    }
+}
+
+/// A page of a listing being collected.
+struct Listing {
+    prefix: String,
+    /// Whether keys are rolled up into common prefixes at `/`.
+    delimited: bool,
+    /// Only keys after this one are listed.
+    marker: String,
+    max_keys: usize,
+    /// Up to `max_keys + 1` items, the last one telling the page is
+    /// truncated.
+    items: Vec<Item>,
+}
+
+impl Listing {
+    fn is_full(&self) -> bool {
+        self.items.len() > self.max_keys
+    }
+}
+
+impl<S, M> Gateway<S, M>
+where
+    S: BlockStore + Send + Sync + 'static,
+    M: MetaStore + 'static,
+{
+    pub(super) async fn list_buckets(&self) -> Result<Response, S3Error> {
+        let mut entries = self
+            .fs
+            .readdir("/")
+            .await
+            .map_err(|err| S3Error::from_io(err, "/"))?;
+        entries.retain(|entry| entry.kind == FileType::Dir && is_bucket_name(&entry.name));
+        entries.sort_by(|a, b| a.name.cmp(&b.name));
+
+        let mut doc = XmlWriter::new("ListAllMyBucketsResult");
+        doc.open("Owner")
+            .elem("ID", OWNER_ID)
+            .elem("DisplayName", OWNER_ID)
+            .close("Owner")
+            .open("Buckets");
+        for entry in entries {
+            let Ok(attr) = self.fs.stat(&format!("/{}", entry.name)).await else {
+                continue;
+            };
+            doc.open("Bucket")
+                .elem("Name", &entry.name)
+                .elem("CreationDate", iso8601(attr.ctime))
+                .close("Bucket");
+        }
+        doc.close("Buckets");
+        Ok(xml_response(doc.finish("ListAllMyBucketsResult")))
+    }
+
+    pub(super) async fn list_objects(
+        &self,
+        bucket: &str,
+        query: &Query,
+    ) -> Result<Response, S3Error> {
+        self.check_bucket(bucket).await?;
+        let param = |name: &str| query.get(name).map(String::as_str);
+        let v2 = match param("list-type") {
+            None | Some("1") => false,
+            Some("2") => true,
+            Some(other) => {
+                return Err(S3Error::InvalidArgument(format!(
+                    "invalid list-type {other}"
+                )));
+            }
+        };
+        let url_encoded = match param("encoding-type") {
+            None => false,
+            Some("url") => true,
+            Some(other) => {
+                return Err(S3Error::InvalidArgument(format!(
+                    "invalid encoding-type {other}"
+                )));
+            }
+        };
+        let delimiter = param("delimiter").filter(|delimiter| !delimiter.is_empty());
+        if delimiter.is_some_and(|delimiter| delimiter != "/") {
+            return Err(S3Error::NotImplemented(
+                "delimiters other than /".to_string(),
+            ));
+        }
+        let max_keys = match param("max-keys") {
+            None => MAX_KEYS,
+            Some(max_keys) => max_keys
+                .parse::<usize>()
+                .map_err(|_| S3Error::InvalidArgument(format!("invalid max-keys {max_keys}")))?
+                .min(MAX_KEYS),
+        };
+        let token = param("continuation-token");
+        let marker = if v2 {
+            match token {
+                Some(token) => hex::decode(token)
+                    .ok()
+                    .and_then(|key| String::from_utf8(key).ok())
+                    .ok_or_else(|| {
+                        S3Error::InvalidArgument("invalid continuation-token".to_string())
+                    })?,
+                None => param("start-after").unwrap_or_default().to_string(),
+            }
+        } else {
+            param("marker").unwrap_or_default().to_string()
+        };
+
+        let mut listing = Listing {
+            prefix: param("prefix").unwrap_or_default().to_string(),
+            delimited: delimiter.is_some(),
+            marker,
+            max_keys,
+            items: Vec::new(),
+        };
+        if max_keys > 0 {
+            self.walk(&format!("/{bucket}"), &mut listing).await?;
+        }
+        let truncated = listing.is_full();
+        listing.items.truncate(max_keys);
+        let next = truncated
+            .then(|| listing.items.last().map(|item| item.key().to_string()))
+            .flatten();
+
+        let encode = |text: &str| {
+            if url_encoded {
+                uri_encode(text, false)
+            } else {
+                text.to_string()
+            }
+        };
+        let mut doc = XmlWriter::new("ListBucketResult");
+        doc.elem("Name", bucket)
+            .elem("Prefix", encode(listing.prefix.as_str()))
+            .elem("MaxKeys", max_keys.to_string())
+            .elem("IsTruncated", truncated.to_string());
+        if let Some(delimiter) = delimiter {
+            doc.elem("Delimiter", encode(delimiter));
+        }
+        if url_encoded {
+            doc.elem("EncodingType", "url");
+        }
+        if v2 {
+            doc.elem("KeyCount", listing.items.len().to_string());
+            if let Some(token) = token {
+                doc.elem("ContinuationToken", token);
+            }
+            if let Some(start_after) = param("start-after") {
+                doc.elem("StartAfter", encode(start_after));
+            }
+            if let Some(next) = &next {
+                doc.elem("NextContinuationToken", hex::encode(next));
+            }
+        } else {
+            doc.elem("Marker", encode(listing.marker.as_str()));
+            if let Some(next) = &next {
+                doc.elem("NextMarker", encode(next.as_str()));
+            }
+        }
+        let owner = !v2 || param("fetch-owner") == Some("true");
+        for item in &listing.items {
+            if let Item::Object {
+                key,
+                size,
+                mtime,
+                etag,
+            } = item
+            {
+                doc.open("Contents")
+                    .elem("Key", encode(key.as_str()))
+                    .elem("LastModified", iso8601(*mtime))
+                    .elem("ETag", etag)
+                    .elem("Size", size.to_string())
+                    .elem("StorageClass", "STANDARD");
+                if owner {
+                    doc.open("Owner")
+                        .elem("ID", OWNER_ID)
+                        .elem("DisplayName", OWNER_ID)
+                        .close("Owner");
+                }
+                doc.close("Contents");
+            }
+        }
+        for item in &listing.items {
+            if let Item::Prefix(prefix) = item {
+                doc.open("CommonPrefixes")
+                    .elem("Prefix", encode(prefix.as_str()))
+                    .close("CommonPrefixes");
+            }
+        }
+        Ok(xml_response(doc.finish("ListBucketResult")))
+    }
+
+    /// Adds the keys of the bucket directory `root` to `listing` in key
+    /// order until the page is full.
+    async fn walk(&self, root: &str, listing: &mut Listing) -> Result<(), S3Error> {
+        let mut stack = Vec::new();
+        self.enter(&mut stack, root.to_string(), "", listing)
+            .await?;
+        while !listing.is_full() {
+            let Some((dir, entries)) = stack.last_mut() else {
+                break;
+            };
+            let Some((key, entry)) = entries.pop() else {
+                stack.pop();
+                continue;
+            };
+            let path = format!("{dir}/{}", entry.name);
+            if entry.kind == FileType::Dir {
+                if !key.starts_with(&listing.prefix) && !listing.prefix.starts_with(&key) {
+                    continue;
+                }
+                // Every key below sorts before the marker.
+                if key < listing.marker && !listing.marker.starts_with(&key) {
+                    continue;
+                }
+                if listing.delimited && key.len() > listing.prefix.len() {
+                    if key > listing.marker {
+                        listing.items.push(Item::Prefix(key));
+                    }
+                } else {
+                    self.enter(&mut stack, path, &key, listing).await?;
+                }
+                continue;
+            }
+            if !key.starts_with(&listing.prefix) || key <= listing.marker {
+                continue;
+            }
+            let attr = match self.fs.lstat(&path).await {
+                Ok(attr) if attr.kind == FileType::File => attr,
+                Ok(_) => continue,
+                Err(err) if err.kind() == io::ErrorKind::NotFound => continue,
+                Err(err) => return Err(S3Error::from_io(err, &key)),
+            };
+            let etag = self.etag(&path, &attr).await;
+            listing.items.push(Item::Object {
+                key,
+                size: attr.size,
+                mtime: attr.mtime,
+                etag,
+            });
+        }
+        Ok(())
+    }
+
+    /// Reads the directory `dir`, whose own key is `dir_key`, and pushes its
+    /// entries with their keys onto `stack`, the first key on top. An empty
+    /// directory is listed as an object instead.
+    async fn enter(
+        &self,
+        stack: &mut Vec<(String, Vec<(String, DirEntry)>)>,
+        dir: String,
+        dir_key: &str,
+        listing: &mut Listing,
+    ) -> Result<(), S3Error> {
+        let entries = match self.fs.readdir(&dir).await {
+            Ok(entries) => entries,
+            // Removed since its parent was read.
+            Err(err) if err.kind() == io::ErrorKind::NotFound => return Ok(()),
+            Err(err) => return Err(S3Error::from_io(err, dir_key)),
+        };
+        let mut entries: Vec<(String, DirEntry)> = entries
+            .into_iter()
+            .filter(|entry| entry.kind != FileType::Symlink)
+            .map(|entry| {
+                let key = match entry.kind {
+                    FileType::Dir => format!("{dir_key}{}/", entry.name),
+                    _ => format!("{dir_key}{}", entry.name),
+                };
+                (key, entry)
+            })
+            .collect();
+        if entries.is_empty() {
+            if !dir_key.is_empty()
+                && dir_key.starts_with(&listing.prefix)
+                && dir_key > listing.marker.as_str()
+                && let Ok(attr) = self.fs.lstat(&dir).await
+            {
+                listing.items.push(Item::Object {
+                    key: dir_key.to_string(),
+                    size: 0,
+                    mtime: attr.mtime,
+                    etag: format!("\"{EMPTY_ETAG}\""),
+                });
+            }
+            return Ok(());
+        }
+        entries.sort_by(|(a, _), (b, _)| b.cmp(a));
+        stack.push((dir, entries));
+        Ok(())
+    }
+}
@@ -0,0 +1,620 @@
+//! S3-compatible gateway over a volume.
+//!
+//! `slayerfs gateway` serves a subset of the S3 REST API through the path SDK
+//! ([`VfsClient`]), so objects are plain files that FUSE clients see as well:
+//! - Buckets are the top-level directories whose names are valid bucket
+//!   names; `.trash` and the gateway's own `/.s3-gateway` never are.
+//! - The object `a/b/c` of bucket `x` is the file `/x/a/b/c`. Parent
+//!   directories are created by uploads and removed again when the last
+//!   object below them is deleted.
+//! - A key ending in `/` is the directory itself, the "folder" objects some
+//!   clients create. Empty directories are listed as such objects.
+//!
+//! Uploads are written to a staging file under `/.s3-gateway` and renamed
+//! over the key once complete, so readers, FUSE clients included, see either
+//! the old or the new object and never a partial one. Reads go through the
+//! same metadata engine and block cache as every other client; the gateway
+//! keeps no state of its own, and several gateways may serve one volume.
+//!
+//! Only path-style requests (`http://host:port/bucket/key`) are understood.
+
+mod auth;
+mod body;
+mod error;
+mod list;
+mod multipart;
+mod object;
+mod xml;
+
+use std::collections::HashMap;
+use std::future::Future;
+use std::io;
+use std::sync::Arc;
+use std::time::Duration;
+
+use axum::Router;
+use axum::extract::{Request, State};
+use axum::http::header::{CONTENT_TYPE, LOCATION};
+use axum::http::{Method, StatusCode};
+use axum::response::{IntoResponse, Response};
+use chrono::{DateTime, Utc};
+use tokio::net::TcpListener;
+use uuid::Uuid;
+
+pub use auth::Credentials;
+pub use error::S3Error;
+
+use crate::chuck::store::BlockStore;
+use crate::gateway::xml::XmlWriter;
+use crate::meta::MetaStore;
+use crate::meta::store::{FileAttr, FileType};
+use crate::vfs::sdk::VfsClient;
+
+/// Directories of the volume holding uploads in progress.
+const TMP_DIR: &str = "/.s3-gateway/tmp";
+const UPLOADS_DIR: &str = "/.s3-gateway/uploads";
+/// Staging files this old were left behind by a gateway that stopped
+/// mid-upload and are removed when a gateway starts.
+const STALE_STAGING: Duration = Duration::from_secs(24 * 3600);
+/// Largest object a single PUT, or part of a multipart upload, may carry.
+const MAX_OBJECT_SIZE: u64 = 5 << 30;
+/// Extended attribute holding `<etag> <size> <mtime>` of files the gateway
+/// wrote. The ETag is only trusted while size and mtime still match.
+const ETAG_XATTR: &str = "user.slayerfs.s3-etag";
+/// ETag of empty objects, such as directories.
+const EMPTY_ETAG: &str = "d41d8cd98f00b204e9800998ecf8427e";
+
+/// Query parameters understood on buckets and objects. Anything else is a
+/// subresource (`?acl`, `?tagging`, ...) the gateway does not implement.
+const BUCKET_PARAMS: &[&str] = &[
+    "prefix",
+    "delimiter",
+    "marker",
+    "max-keys",
+    "list-type",
+    "continuation-token",
+    "start-after",
+    "fetch-owner",
+    "encoding-type",
+    "location",
+    "delete",
+    "x-id",
+];
+const OBJECT_PARAMS: &[&str] = &[
+    "uploads",
+    "uploadId",
+    "partNumber",
+    "max-parts",
+    "part-number-marker",
+    "x-id",
+];
+
+type Query = HashMap<String, String>;
+
+/// S3 frontend of one volume.
+pub struct Gateway<S, M>
+where
+    S: BlockStore + Send + Sync + 'static,
+    M: MetaStore + 'static,
+{
+    fs: VfsClient<S, M>,
+    credentials: Option<Credentials>,
+}
+
+impl<S, M> Gateway<S, M>
+where
+    S: BlockStore + Send + Sync + 'static,
+    M: MetaStore + 'static,
+{
+    /// Serves the volume behind `fs`. Without `credentials` requests are not
+    /// authenticated.
+    pub fn new(fs: VfsClient<S, M>, credentials: Option<Credentials>) -> Self {
+        Self { fs, credentials }
+    }
+
+    /// Serves requests accepted on `listener` until `shutdown` completes.
+    pub async fn serve(
+        self,
+        listener: TcpListener,
+        shutdown: impl Future<Output = ()> + Send + 'static,
+    ) -> anyhow::Result<()> {
+        self.prepare().await?;
+        axum::serve(listener, Arc::new(self).router())
+            .with_graceful_shutdown(shutdown)
+            .await?;
+        Ok(())
+    }
+
+    fn router(self: Arc<Self>) -> Router {
+        Router::new().fallback(dispatch::<S, M>).with_state(self)
+    }
+
+    /// Creates the staging directories and removes stale staging files.
+    async fn prepare(&self) -> io::Result<()> {
+        self.fs.mkdir_p(TMP_DIR).await?;
+        self.fs.mkdir_p(UPLOADS_DIR).await?;
+        let cutoff = Utc::now() - STALE_STAGING;
+        for entry in self.fs.readdir(TMP_DIR).await? {
+            let path = format!("{TMP_DIR}/{}", entry.name);
+            let Ok(attr) = self.fs.lstat(&path).await else {
+                continue;
+            };
+            if DateTime::from_timestamp_nanos(attr.mtime) < cutoff {
+                self.discard(&path).await;
+            }
+        }
+        Ok(())
+    }
+
+    async fn handle(self: &Arc<Self>, request: Request) -> Result<Response, S3Error> {
+        let (parts, body) = request.into_parts();
+        if let Some(credentials) = &self.credentials {
+            auth::verify(
+                credentials,
+                &parts.method,
+                &parts.uri,
+                &parts.headers,
+                Utc::now(),
+            )?;
+        }
+        let query: Query = auth::query_pairs(parts.uri.query())
+            .ok_or_else(|| S3Error::InvalidArgument("malformed query string".to_string()))?
+            .into_iter()
+            .collect();
+        let path = auth::percent_decode(parts.uri.path())
+            .ok_or_else(|| S3Error::InvalidArgument("malformed request path".to_string()))?;
+        let path = path.strip_prefix('/').unwrap_or(&path);
+        let (bucket, key) = path.split_once('/').unwrap_or((path, ""));
+        let headers = &parts.headers;
+
+        if bucket.is_empty() {
+            return match parts.method {
+                Method::GET => self.list_buckets().await,
+                _ => Err(S3Error::MethodNotAllowed),
+            };
+        }
+        check_bucket_name(bucket)?;
+        if key.is_empty() {
+            check_params(&query, BUCKET_PARAMS)?;
+            return match parts.method {
+                Method::GET if query.contains_key("location") => Ok(location()),
+                Method::GET => self.list_objects(bucket, &query).await,
+                Method::HEAD => self.check_bucket(bucket).await.map(|()| ().into_response()),
+                Method::PUT => self.create_bucket(bucket).await,
+                Method::DELETE => self.delete_bucket(bucket).await,
+                Method::POST if query.contains_key("delete") => {
+                    self.delete_objects(bucket, headers, body).await
+                }
+                _ => Err(S3Error::MethodNotAllowed),
+            };
+        }
+
+        check_params(&query, OBJECT_PARAMS)?;
+        self.check_bucket(bucket).await?;
+        let upload_id = query.get("uploadId").map(String::as_str);
+        match (parts.method.clone(), upload_id) {
+            (Method::GET, Some(id)) => self.list_parts(bucket, key, id, &query).await,
+            (Method::GET, None) if query.contains_key("partNumber") => Err(
+                S3Error::NotImplemented("reading single parts of objects".to_string()),
+            ),
+            (Method::GET, None) => self.get_object(bucket, key, headers, false).await,
+            (Method::HEAD, None) => self.get_object(bucket, key, headers, true).await,
+            (Method::PUT, Some(id)) => {
+                self.upload_part(bucket, key, id, &query, headers, body)
+                    .await
+            }
+            (Method::PUT, None) if headers.contains_key("x-amz-copy-source") => {
+                self.copy_object(bucket, key, headers).await
+            }
+            (Method::PUT, None) => self.put_object(bucket, key, headers, body).await,
+            (Method::POST, None) if query.contains_key("uploads") => {
+                self.create_multipart_upload(bucket, key).await
+            }
+            (Method::POST, Some(id)) => {
+                self.complete_multipart_upload(bucket, key, id, headers, body)
+                    .await
+            }
+            (Method::DELETE, Some(id)) => self.abort_multipart_upload(bucket, key, id).await,
+            (Method::DELETE, None) => self.delete_object(bucket, key).await,
+            _ => Err(S3Error::MethodNotAllowed),
+        }
+    }
+
+    async fn check_bucket(&self, bucket: &str) -> Result<(), S3Error> {
+        match self.fs.stat(&format!("/{bucket}")).await {
+            Ok(attr) if attr.kind == FileType::Dir => Ok(()),
+            Ok(_) => Err(S3Error::NoSuchBucket(bucket.to_string())),
+            Err(err) if err.kind() == io::ErrorKind::NotFound => {
+                Err(S3Error::NoSuchBucket(bucket.to_string()))
+            }
+            Err(err) => Err(S3Error::from_io(err, bucket)),
+        }
+    }
+
+    async fn create_bucket(&self, bucket: &str) -> Result<Response, S3Error> {
+        match self.fs.mkdir(&format!("/{bucket}")).await {
+            Ok(()) => Ok([(LOCATION, format!("/{bucket}"))].into_response()),
+            Err(err) if err.kind() == io::ErrorKind::AlreadyExists => {
+                Err(S3Error::BucketAlreadyOwnedByYou(bucket.to_string()))
+            }
+            Err(err) => Err(S3Error::from_io(err, bucket)),
+        }
+    }
+
+    async fn delete_bucket(&self, bucket: &str) -> Result<Response, S3Error> {
+        match self.fs.rmdir(&format!("/{bucket}")).await {
+            Ok(()) => Ok(StatusCode::NO_CONTENT.into_response()),
+            Err(err) => Err(match err.kind() {
+                io::ErrorKind::NotFound | io::ErrorKind::NotADirectory => {
+                    S3Error::NoSuchBucket(bucket.to_string())
+                }
+                io::ErrorKind::DirectoryNotEmpty => S3Error::BucketNotEmpty(bucket.to_string()),
+                _ => S3Error::from_io(err, bucket),
+            }),
+        }
+    }
+
+    /// The quoted ETag of the file at `path`: the MD5-based one recorded when
+    /// the gateway wrote it, or one derived from the inode, size and mtime
+    /// for files written by other clients or changed since.
+    async fn etag(&self, path: &str, attr: &FileAttr) -> String {
+        if let Ok(Some(recorded)) = self.fs.get_xattr(path, ETAG_XATTR).await
+            && let Ok(recorded) = String::from_utf8(recorded)
+            && let Some(etag) = recorded.strip_suffix(&format!(" {} {}", attr.size, attr.mtime))
+        {
+            return format!("\"{etag}\"");
+        }
+        format!("\"{:x}-{:x}-{:x}\"", attr.ino, attr.size, attr.mtime)
+    }
+
+    /// Records `etag` for the file at `path` as it is now.
+    async fn record_etag(&self, path: &str, etag: &str) -> Result<FileAttr, S3Error> {
+        let attr = self
+            .fs
+            .stat(path)
+            .await
+            .map_err(|err| S3Error::from_io(err, path))?;
+        let value = format!("{etag} {} {}", attr.size, attr.mtime);
+        self.fs
+            .set_xattr(path, ETAG_XATTR, value.as_bytes())
+            .await
+            .map_err(|err| S3Error::from_io(err, path))?;
+        Ok(attr)
+    }
+
+    /// Creates an empty staging file and returns its path.
+    async fn create_staging(&self) -> Result<String, S3Error> {
+        let path = staging_path();
+        self.fs
+            .create_file(&path, true)
+            .await
+            .map_err(|err| S3Error::from_io(err, &path))?;
+        Ok(path)
+    }
+
+    /// Removes a staging file that will not be published.
+    async fn discard(&self, path: &str) {
+        if let Err(err) = self.fs.unlink(path).await
+            && err.kind() != io::ErrorKind::NotFound
+        {
+            tracing::warn!(path, error = %err, "failed to remove gateway staging file");
+        }
+    }
+
+    /// Moves the staging file `staged` to `path`, the file of `key`,
+    /// creating its parent directories.
+    async fn publish(
+        &self,
+        staged: &str,
+        bucket: &str,
+        path: &str,
+        key: &str,
+    ) -> Result<(), S3Error> {
+        let parent = path.rsplit_once('/').map_or("/", |(parent, _)| parent);
+        let mut attempts = 0;
+        loop {
+            attempts += 1;
+            if parent.len() > bucket.len() + 1 {
+                self.fs
+                    .mkdir_p(parent)
+                    .await
+                    .map_err(|err| S3Error::from_io(err, key))?;
+            }
+            match self.fs.rename(staged, path).await {
+                Ok(()) => return Ok(()),
+                // A concurrent delete pruned the new parent directory.
+                Err(err) if err.kind() == io::ErrorKind::NotFound && attempts < 3 => {}
+                Err(err) => return Err(S3Error::from_io(err, key)),
+            }
+        }
+    }
+
+    /// Removes the directories above `path` that became empty, up to but not
+    /// including the bucket.
+    async fn prune_parents(&self, bucket: &str, path: &str) {
+        let mut dir = path;
+        while let Some((parent, _)) = dir.rsplit_once('/')
+            && parent.len() > bucket.len() + 1
+        {
+            if self.fs.rmdir(parent).await.is_err() {
+                break;
+            }
+            dir = parent;
+        }
+    }
+}
+
+async fn dispatch<S, M>(State(gateway): State<Arc<Gateway<S, M>>>, request: Request) -> Response
+where
+    S: BlockStore + Send + Sync + 'static,
+    M: MetaStore + 'static,
+{
+    let method = request.method().clone();
+    match gateway.handle(request).await {
+        Ok(response) => response,
+        Err(err) => err.into_response_for(&method),
+    }
+}
+
+fn check_params(query: &Query, known: &[&str]) -> Result<(), S3Error> {
+    match query
+        .keys()
+        .find(|name| !known.contains(&name.as_str()) && !name.starts_with("response-"))
+    {
+        Some(name) => Err(S3Error::NotImplemented(format!("the ?{name} subresource"))),
+        None => Ok(()),
+    }
+}
+
+/// Whether `name` follows the S3 bucket naming rules.
+fn is_bucket_name(name: &str) -> bool {
+    (3..=63).contains(&name.len())
+        && name
+            .bytes()
+            .all(|b| b.is_ascii_lowercase() || b.is_ascii_digit() || b == b'.' || b == b'-')
+        && name.starts_with(|c: char| c.is_ascii_alphanumeric())
+        && name.ends_with(|c: char| c.is_ascii_alphanumeric())
+        && !name.contains("..")
+}
+
+fn check_bucket_name(name: &str) -> Result<(), S3Error> {
+    if !is_bucket_name(name) {
+        return Err(S3Error::InvalidBucketName(name.to_string()));
+    }
+    Ok(())
+}
+
+/// Path of the file or directory holding `key`.
+fn object_path(bucket: &str, key: &str) -> Result<String, S3Error> {
+    if key.len() > 1024 {
+        return Err(S3Error::InvalidArgument(
+            "object keys are limited to 1024 bytes".to_string(),
+        ));
+    }
+    let path = key.strip_suffix('/').unwrap_or(key);
+    if path.split('/').any(|name| {
+        name.is_empty() || name == "." || name == ".." || name.len() > 255 || name.contains('\0')
+    }) {
+        return Err(S3Error::InvalidArgument(format!(
+            "{key} is not a valid object key for the gateway"
+        )));
+    }
+    Ok(format!("/{bucket}/{path}"))
+}
+
+/// A fresh path for a staging file.
+fn staging_path() -> String {
+    format!("{TMP_DIR}/{}", Uuid::now_v7().simple())
+}
+
+fn location() -> Response {
+    xml_response(XmlWriter::new("LocationConstraint").finish("LocationConstraint"))
+}
+
+fn xml_response(doc: String) -> Response {
+    ([(CONTENT_TYPE, "application/xml")], doc).into_response()
+}
+
+/// Timestamp of a listing, e.g. `2009-10-12T17:50:30.000Z`.
+fn iso8601(nanos: i64) -> String {
+    DateTime::from_timestamp_nanos(nanos)
+        .format("%Y-%m-%dT%H:%M:%S%.3fZ")
+        .to_string()
+}
+
+/// Timestamp of a `Last-Modified` header.
+fn http_date(nanos: i64) -> String {
+    DateTime::from_timestamp_nanos(nanos)
+        .format("%a, %d %b %Y %H:%M:%S GMT")
+        .to_string()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::chuck::chunk::ChunkLayout;
+    use crate::fs::{CallerIdentity, FileSystemConfig};
+    use crate::vfs::sdk::LocalClient;
+    use axum::body::Body;
+    use axum::http::header::{CONTENT_RANGE, ETAG, RANGE};
+    use tempfile::tempdir;
+    use tower::ServiceExt;
+
+    async fn gateway(root: &std::path::Path) -> Router {
+        let config = FileSystemConfig::default().with_caller(CallerIdentity::root());
+        let client = LocalClient::new_local_with_config(root, ChunkLayout::default(), config)
+            .await
+            .unwrap();
+        let gateway = Gateway::new(client, None);
+        gateway.prepare().await.unwrap();
+        Arc::new(gateway).router()
+    }
+
+    async fn send(
+        router: &Router,
+        method: Method,
+        uri: &str,
+        headers: &[(&str, &str)],
+        body: impl Into<Body>,
+    ) -> (StatusCode, axum::http::HeaderMap, Vec<u8>) {
+        let mut request = Request::builder().method(method).uri(uri);
+        for (name, value) in headers {
+            request = request.header(*name, *value);
+        }
+        let response = router
+            .clone()
+            .oneshot(request.body(body.into()).unwrap())
+            .await
+            .unwrap();
+        let (parts, body) = response.into_parts();
+        let body = axum::body::to_bytes(body, usize::MAX).await.unwrap();
+        (parts.status, parts.headers, body.to_vec())
+    }
+
+    fn text(body: &[u8]) -> String {
+        String::from_utf8(body.to_vec()).unwrap()
+    }
+
+    #[tokio::test]
+    async fn objects_are_files() {
+        let tmp = tempdir().unwrap();
+        let router = gateway(tmp.path()).await;
+
+        let (status, _, _) = send(&router, Method::PUT, "/bucket", &[], "").await;
+        assert_eq!(status, StatusCode::OK);
+        let (status, _, body) = send(&router, Method::GET, "/", &[], "").await;
+        assert_eq!(status, StatusCode::OK);
+        let body = text(&body);
+        assert!(body.contains("<Name>bucket</Name>"), "{body}");
+        assert!(!body.contains(".s3-gateway"), "{body}");
+
+        let (status, headers, _) = send(
+            &router,
+            Method::PUT,
+            "/bucket/dir/a%20b.txt",
+            &[],
+            "hello world",
+        )
+        .await;
+        assert_eq!(status, StatusCode::OK);
+        assert_eq!(headers[ETAG], "\"5eb63bbbe01eeed093cb22bb8f5acdc3\"");
+
+        let (status, headers, body) =
+            send(&router, Method::GET, "/bucket/dir/a%20b.txt", &[], "").await;
+        assert_eq!(status, StatusCode::OK);
+        assert_eq!(headers[ETAG], "\"5eb63bbbe01eeed093cb22bb8f5acdc3\"");
+        assert_eq!(body, b"hello world");
+
+        let (status, headers, body) = send(
+            &router,
+            Method::GET,
+            "/bucket/dir/a%20b.txt",
+            &[(RANGE.as_str(), "bytes=6-")],
+            "",
+        )
+        .await;
+        assert_eq!(status, StatusCode::PARTIAL_CONTENT);
+        assert_eq!(headers[CONTENT_RANGE], "bytes 6-10/11");
+        assert_eq!(body, b"world");
+
+        let (status, _, body) = send(
+            &router,
+            Method::GET,
+            "/bucket?list-type=2&delimiter=/",
+            &[],
+            "",
+        )
+        .await;
+        assert_eq!(status, StatusCode::OK);
+        let body = text(&body);
+        assert!(body.contains("<Prefix>dir/</Prefix>"), "{body}");
+        assert!(!body.contains("<Contents>"), "{body}");
+        let (_, _, body) = send(&router, Method::GET, "/bucket?list-type=2", &[], "").await;
+        assert!(text(&body).contains("<Key>dir/a b.txt</Key>"));
+
+        // Deleting the last object removes its directory, and deleting
+        // twice succeeds.
+        for _ in 0..2 {
+            let (status, _, _) =
+                send(&router, Method::DELETE, "/bucket/dir/a%20b.txt", &[], "").await;
+            assert_eq!(status, StatusCode::NO_CONTENT);
+        }
+        let (status, _, body) = send(&router, Method::GET, "/bucket/dir/a%20b.txt", &[], "").await;
+        assert_eq!(status, StatusCode::NOT_FOUND);
+        assert!(text(&body).contains("<Code>NoSuchKey</Code>"));
+        let (_, _, body) = send(&router, Method::GET, "/bucket", &[], "").await;
+        assert!(!text(&body).contains("<Key>"));
+        let (status, _, _) = send(&router, Method::DELETE, "/bucket", &[], "").await;
+        assert_eq!(status, StatusCode::NO_CONTENT);
+        let (status, _, _) = send(&router, Method::HEAD, "/bucket", &[], "").await;
+        assert_eq!(status, StatusCode::NOT_FOUND);
+    }
+
+    #[tokio::test]
+    async fn multipart_upload() {
+        let tmp = tempdir().unwrap();
+        let router = gateway(tmp.path()).await;
+        send(&router, Method::PUT, "/bucket", &[], "").await;
+
+        let (status, _, body) = send(&router, Method::POST, "/bucket/big?uploads", &[], "").await;
+        assert_eq!(status, StatusCode::OK);
+        let id = xml::text(&text(&body), "UploadId").unwrap();
+
+        let first = vec![b'a'; 5 << 20];
+        let mut etags = Vec::new();
+        for (number, data) in [(1, first.clone()), (2, b"tail".to_vec())] {
+            let uri = format!("/bucket/big?partNumber={number}&uploadId={id}");
+            let (status, headers, _) = send(&router, Method::PUT, &uri, &[], data).await;
+            assert_eq!(status, StatusCode::OK);
+            etags.push(headers[ETAG].to_str().unwrap().to_string());
+        }
+        let (_, _, body) = send(
+            &router,
+            Method::GET,
+            &format!("/bucket/big?uploadId={id}"),
+            &[],
+            "",
+        )
+        .await;
+        assert_eq!(xml::elements(&text(&body), "Part").len(), 2);
+
+        // Parts must be listed in ascending order.
+        let complete = |order: &[usize]| {
+            let mut doc = String::from("<CompleteMultipartUpload>");
+            for &i in order {
+                doc.push_str(&format!(
+                    "<Part><PartNumber>{}</PartNumber><ETag>{}</ETag></Part>",
+                    i + 1,
+                    etags[i]
+                ));
+            }
+            doc + "</CompleteMultipartUpload>"
+        };
+        let uri = format!("/bucket/big?uploadId={id}");
+        let (status, _, body) = send(&router, Method::POST, &uri, &[], complete(&[1, 0])).await;
+        assert_eq!(status, StatusCode::BAD_REQUEST);
+        assert!(text(&body).contains("InvalidPartOrder"));
+        let (status, _, body) = send(&router, Method::POST, &uri, &[], complete(&[0, 1])).await;
+        assert_eq!(status, StatusCode::OK, "{}", text(&body));
+        assert!(xml::text(&text(&body), "ETag").unwrap().ends_with("-2\""));
+
+        let (status, headers, _) = send(&router, Method::HEAD, "/bucket/big", &[], "").await;
+        assert_eq!(status, StatusCode::OK);
+        assert_eq!(headers["content-length"], ((5 << 20) + 4).to_string());
+        let (status, _, _) = send(&router, Method::GET, &uri, &[], "").await;
+        assert_eq!(status, StatusCode::NOT_FOUND);
+    }
+
+    #[test]
+    fn keys_map_to_paths() {
+        assert_eq!(object_path("b", "a/c.txt").unwrap(), "/b/a/c.txt");
+        assert_eq!(object_path("b", "dir/").unwrap(), "/b/dir");
+        for key in ["/a", "a//b", "a/../b", "./a", "a\0"] {
+            assert!(object_path("b", key).is_err(), "{key}");
+        }
+        assert!(is_bucket_name("my.bucket-1"));
+        for name in [".trash", ".s3-gateway", "ab", "Bucket", "a..b", "bucket-"] {
+            assert!(!is_bucket_name(name), "{name}");
+        }
+    }
+}
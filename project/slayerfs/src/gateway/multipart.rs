@@ -0,0 +1,311 @@
+//! Multipart uploads.
+//!
+//! An upload is a directory `<UPLOADS_DIR>/<upload id>` whose
+//! [`UPLOAD_XATTR`] names the bucket and key it is for, holding one file per
+//! uploaded part. Completing the upload concatenates the parts into a
+//! staging file and renames it over the key like a PUT does.
+
+use std::io;
+
+use axum::body::Body;
+use axum::http::header::ETAG;
+use axum::http::{HeaderMap, StatusCode};
+use axum::response::{IntoResponse, Response};
+use uuid::Uuid;
+
+use crate::chuck::store::BlockStore;
+use crate::gateway::body::read_xml;
+use crate::gateway::xml::{self, XmlWriter};
+use crate::gateway::{Gateway, Query, S3Error, UPLOADS_DIR, iso8601, object_path, xml_response};
+use crate::meta::MetaStore;
+
+/// Extended attribute of an upload directory holding `<bucket>/<key>`.
+const UPLOAD_XATTR: &str = "user.slayerfs.s3-upload";
+const MAX_PART_NUMBER: u32 = 10_000;
+/// Smallest size of every part but the last.
+const MIN_PART_SIZE: u64 = 5 << 20;
+/// Most parts returned by one ListParts request.
+const MAX_PARTS: usize = 1000;
+/// Bytes copied at a time when completing an upload.
+const COPY_PIECE: usize = 8 * 1024 * 1024;
+
+/// Directory of the upload `upload_id`.
+fn upload_dir(upload_id: &str) -> Result<String, S3Error> {
+    if upload_id.len() != 32 || !upload_id.bytes().all(|b| b.is_ascii_hexdigit()) {
+        return Err(S3Error::NoSuchUpload(upload_id.to_string()));
+    }
+    Ok(format!("{UPLOADS_DIR}/{upload_id}"))
+}
+
+fn part_path(dir: &str, number: u32) -> String {
+    format!("{dir}/{number:05}")
+}
+
+fn parse_part_number(number: &str) -> Result<u32, S3Error> {
+    number
+        .parse::<u32>()
+        .ok()
+        .filter(|number| (1..=MAX_PART_NUMBER).contains(number))
+        .ok_or_else(|| {
+            S3Error::InvalidArgument(format!(
+                "part number must be an integer between 1 and {MAX_PART_NUMBER}"
+            ))
+        })
+}
+
+impl<S, M> Gateway<S, M>
+where
+    S: BlockStore + Send + Sync + 'static,
+    M: MetaStore + 'static,
+{
+    /// Returns the directory of the upload `upload_id` of `bucket/key`.
+    async fn check_upload(
+        &self,
+        bucket: &str,
+        key: &str,
+        upload_id: &str,
+    ) -> Result<String, S3Error> {
+        let dir = upload_dir(upload_id)?;
+        match self.fs.get_xattr(&dir, UPLOAD_XATTR).await {
+            Ok(Some(target)) if target == format!("{bucket}/{key}").as_bytes() => Ok(dir),
+            Ok(_) => Err(S3Error::NoSuchUpload(upload_id.to_string())),
+            Err(err) if err.kind() == io::ErrorKind::NotFound => {
+                Err(S3Error::NoSuchUpload(upload_id.to_string()))
+            }
+            Err(err) => Err(S3Error::from_io(err, key)),
+        }
+    }
+
+    pub(super) async fn create_multipart_upload(
+        &self,
+        bucket: &str,
+        key: &str,
+    ) -> Result<Response, S3Error> {
+        object_path(bucket, key)?;
+        if key.ends_with('/') {
+            return Err(S3Error::InvalidRequest(format!(
+                "{key} is a directory and cannot hold data"
+            )));
+        }
+        let upload_id = Uuid::now_v7().simple().to_string();
+        let dir = format!("{UPLOADS_DIR}/{upload_id}");
+        self.fs
+            .mkdir(&dir)
+            .await
+            .map_err(|err| S3Error::from_io(err, key))?;
+        self.fs
+            .set_xattr(&dir, UPLOAD_XATTR, format!("{bucket}/{key}").as_bytes())
+            .await
+            .map_err(|err| S3Error::from_io(err, key))?;
+
+        let mut doc = XmlWriter::new("InitiateMultipartUploadResult");
+        doc.elem("Bucket", bucket)
+            .elem("Key", key)
+            .elem("UploadId", &upload_id);
+        Ok(xml_response(doc.finish("InitiateMultipartUploadResult")))
+    }
+
+    pub(super) async fn upload_part(
+        &self,
+        bucket: &str,
+        key: &str,
+        upload_id: &str,
+        query: &Query,
+        headers: &HeaderMap,
+        body: Body,
+    ) -> Result<Response, S3Error> {
+        if headers.contains_key("x-amz-copy-source") {
+            return Err(S3Error::NotImplemented("UploadPartCopy".to_string()));
+        }
+        let number = parse_part_number(query.get("partNumber").map_or("", String::as_str))?;
+        let dir = self.check_upload(bucket, key, upload_id).await?;
+
+        let (staged, etag) = self.stage_body(headers, body).await?;
+        if let Err(err) = self.fs.rename(&staged, &part_path(&dir, number)).await {
+            self.discard(&staged).await;
+            return Err(match err.kind() {
+                // Aborted or completed meanwhile.
+                io::ErrorKind::NotFound => S3Error::NoSuchUpload(upload_id.to_string()),
+                _ => S3Error::from_io(err, key),
+            });
+        }
+        Ok([(ETAG, format!("\"{etag}\""))].into_response())
+    }
+
+    /// The parts uploaded so far, by ascending part number.
+    async fn parts(&self, dir: &str, upload_id: &str) -> Result<Vec<u32>, S3Error> {
+        let entries = self.fs.readdir(dir).await.map_err(|err| match err.kind() {
+            io::ErrorKind::NotFound => S3Error::NoSuchUpload(upload_id.to_string()),
+            _ => S3Error::from_io(err, dir),
+        })?;
+        let mut numbers: Vec<u32> = entries
+            .iter()
+            .filter(|entry| entry.name.len() == 5)
+            .filter_map(|entry| entry.name.parse().ok())
+            .collect();
+        numbers.sort_unstable();
+        Ok(numbers)
+    }
+
+    pub(super) async fn list_parts(
+        &self,
+        bucket: &str,
+        key: &str,
+        upload_id: &str,
+        query: &Query,
+    ) -> Result<Response, S3Error> {
+        let dir = self.check_upload(bucket, key, upload_id).await?;
+        let marker = match query.get("part-number-marker") {
+            Some(marker) => marker
+                .parse::<u32>()
+                .map_err(|_| S3Error::InvalidArgument("invalid part-number-marker".to_string()))?,
+            None => 0,
+        };
+        let max_parts = match query.get("max-parts") {
+            Some(max_parts) => max_parts
+                .parse::<usize>()
+                .map_err(|_| S3Error::InvalidArgument("invalid max-parts".to_string()))?
+                .min(MAX_PARTS),
+            None => MAX_PARTS,
+        };
+        let mut numbers = self.parts(&dir, upload_id).await?;
+        numbers.retain(|&number| number > marker);
+        let truncated = numbers.len() > max_parts;
+        numbers.truncate(max_parts);
+
+        let mut doc = XmlWriter::new("ListPartsResult");
+        doc.elem("Bucket", bucket)
+            .elem("Key", key)
+            .elem("UploadId", upload_id)
+            .elem("PartNumberMarker", marker.to_string())
+            .elem("MaxParts", max_parts.to_string())
+            .elem("IsTruncated", truncated.to_string());
+        if let Some(last) = numbers.last() {
+            doc.elem("NextPartNumberMarker", last.to_string());
+        }
+        for number in numbers {
+            let path = part_path(&dir, number);
+            let Ok(attr) = self.fs.lstat(&path).await else {
+                continue;
+            };
+            let etag = self.etag(&path, &attr).await;
+            doc.open("Part")
+                .elem("PartNumber", number.to_string())
+                .elem("LastModified", iso8601(attr.mtime))
+                .elem("ETag", etag)
+                .elem("Size", attr.size.to_string())
+                .close("Part");
+        }
+        Ok(xml_response(doc.finish("ListPartsResult")))
+    }
+
+    pub(super) async fn abort_multipart_upload(
+        &self,
+        bucket: &str,
+        key: &str,
+        upload_id: &str,
+    ) -> Result<Response, S3Error> {
+        let dir = self.check_upload(bucket, key, upload_id).await?;
+        match self.fs.remove_dir_all(&dir).await {
+            Ok(()) => Ok(StatusCode::NO_CONTENT.into_response()),
+            Err(err) if err.kind() == io::ErrorKind::NotFound => {
+                Err(S3Error::NoSuchUpload(upload_id.to_string()))
+            }
+            Err(err) => Err(S3Error::from_io(err, key)),
+        }
+    }
+
+    pub(super) async fn complete_multipart_upload(
+        &self,
+        bucket: &str,
+        key: &str,
+        upload_id: &str,
+        headers: &HeaderMap,
+        body: Body,
+    ) -> Result<Response, S3Error> {
+        let path = object_path(bucket, key)?;
+        let dir = self.check_upload(bucket, key, upload_id).await?;
+        let doc = read_xml(headers, body).await?;
+        let complete = xml::elements(&doc, "CompleteMultipartUpload")
+            .into_iter()
+            .next()
+            .ok_or(S3Error::MalformedXml)?;
+        let requested = xml::elements(complete, "Part")
+            .into_iter()
+            .map(|part| {
+                let number = xml::text(part, "PartNumber").ok_or(S3Error::MalformedXml)?;
+                let etag = xml::text(part, "ETag").ok_or(S3Error::MalformedXml)?;
+                Ok((parse_part_number(&number)?, etag))
+            })
+            .collect::<Result<Vec<_>, S3Error>>()?;
+        if requested.is_empty() {
+            return Err(S3Error::MalformedXml);
+        }
+        if requested.windows(2).any(|pair| pair[0].0 >= pair[1].0) {
+            return Err(S3Error::InvalidPartOrder);
+        }
+
+        // The ETag of a multipart object is the MD5 of the MD5s of its
+        // parts, followed by the number of parts.
+        let mut md5s = Vec::with_capacity(requested.len() * 16);
+        let mut parts = Vec::with_capacity(requested.len());
+        for (i, (number, etag)) in requested.iter().enumerate() {
+            let part = part_path(&dir, *number);
+            let invalid = || S3Error::InvalidPart(number.to_string());
+            let attr = self.fs.lstat(&part).await.map_err(|_| invalid())?;
+            let actual = self.etag(&part, &attr).await;
+            let actual = actual.trim_matches('"');
+            if actual != etag.trim_matches('"') {
+                return Err(invalid());
+            }
+            if i + 1 < requested.len() && attr.size < MIN_PART_SIZE {
+                return Err(S3Error::EntityTooSmall);
+            }
+            md5s.extend(hex::decode(actual).map_err(|_| invalid())?);
+            parts.push((*number, part, attr.size));
+        }
+        let etag = format!("{}-{}", hex::encode(md5::compute(&md5s).0), parts.len());
+
+        let staged = self.create_staging().await?;
+        let result = async {
+            let mut offset = 0;
+            for (number, part, size) in &parts {
+                let mut copied = 0;
+                while copied < *size {
+                    let len = (size - copied).min(COPY_PIECE as u64) as usize;
+                    let data = self
+                        .fs
+                        .read_at(part, copied, len)
+                        .await
+                        .map_err(|err| S3Error::from_io(err, key))?;
+                    if data.len() != len {
+                        return Err(S3Error::InvalidPart(number.to_string()));
+                    }
+                    self.fs
+                        .write_at(&staged, offset, &data)
+                        .await
+                        .map_err(|err| S3Error::from_io(err, key))?;
+                    copied += len as u64;
+                    offset += len as u64;
+                }
+            }
+            self.record_etag(&staged, &etag).await?;
+            self.publish(&staged, bucket, &path, key).await
+        }
+        .await;
+        if let Err(err) = result {
+            self.discard(&staged).await;
+            return Err(err);
+        }
+        if let Err(err) = self.fs.remove_dir_all(&dir).await {
+            tracing::warn!(upload_id, error = %err, "failed to remove completed upload");
+        }
+
+        let mut doc = XmlWriter::new("CompleteMultipartUploadResult");
+        doc.elem("Location", format!("/{bucket}/{key}"))
+            .elem("Bucket", bucket)
+            .elem("Key", key)
+            .elem("ETag", format!("\"{etag}\""));
+        Ok(xml_response(doc.finish("CompleteMultipartUploadResult")))
+    }
+}
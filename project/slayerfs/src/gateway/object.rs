@@ -0,0 +1,388 @@
+//! Single-object operations: Get/Head/Put/Copy/DeleteObject and
+//! DeleteObjects.
+
+use std::io;
+use std::ops::Range;
+use std::sync::Arc;
+
+use axum::body::Body;
+use axum::http::header::{
+    ACCEPT_RANGES, CONTENT_LENGTH, CONTENT_RANGE, CONTENT_TYPE, ETAG, LAST_MODIFIED, RANGE,
+};
+use axum::http::{HeaderMap, StatusCode};
+use axum::response::{IntoResponse, Response};
+use bytes::Bytes;
+
+use crate::chuck::store::BlockStore;
+use crate::gateway::auth::percent_decode;
+use crate::gateway::body::{read_xml, write_body};
+use crate::gateway::xml::{self, XmlWriter};
+use crate::gateway::{
+    EMPTY_ETAG, Gateway, MAX_OBJECT_SIZE, S3Error, check_bucket_name, http_date, iso8601,
+    object_path, staging_path, xml_response,
+};
+use crate::meta::MetaStore;
+use crate::meta::store::{FileAttr, FileType};
+
+/// Bytes read from the volume per piece of a GET response.
+const READ_PIECE: usize = 8 * 1024 * 1024;
+/// Most keys one DeleteObjects request may name.
+const MAX_DELETE_KEYS: usize = 1000;
+
+impl<S, M> Gateway<S, M>
+where
+    S: BlockStore + Send + Sync + 'static,
+    M: MetaStore + 'static,
+{
+    pub(super) async fn get_object(
+        self: &Arc<Self>,
+        bucket: &str,
+        key: &str,
+        headers: &HeaderMap,
+        head_only: bool,
+    ) -> Result<Response, S3Error> {
+        let path = object_path(bucket, key)?;
+        let attr = self
+            .fs
+            .lstat(&path)
+            .await
+            .map_err(|err| S3Error::from_io(err, key))?;
+        let etag = match attr.kind {
+            FileType::File if !key.ends_with('/') => self.etag(&path, &attr).await,
+            FileType::Dir if key.ends_with('/') => {
+                return Ok((
+                    [
+                        (CONTENT_TYPE, "application/x-directory".to_string()),
+                        (ETAG, format!("\"{EMPTY_ETAG}\"")),
+                        (LAST_MODIFIED, http_date(attr.mtime)),
+                    ],
+                    Body::empty(),
+                )
+                    .into_response());
+            }
+            _ => return Err(S3Error::NoSuchKey(key.to_string())),
+        };
+
+        let range = parse_range(
+            headers.get(RANGE).and_then(|value| value.to_str().ok()),
+            attr.size,
+        )?;
+        let (status, span) = match &range {
+            Some(range) => (StatusCode::PARTIAL_CONTENT, range.clone()),
+            None => (StatusCode::OK, 0..attr.size),
+        };
+        let mut response = Response::builder()
+            .status(status)
+            .header(CONTENT_TYPE, "application/octet-stream")
+            .header(CONTENT_LENGTH, span.end - span.start)
+            .header(ETAG, etag)
+            .header(LAST_MODIFIED, http_date(attr.mtime))
+            .header(ACCEPT_RANGES, "bytes");
+        if range.is_some() {
+            response = response.header(
+                CONTENT_RANGE,
+                format!("bytes {}-{}/{}", span.start, span.end - 1, attr.size),
+            );
+        }
+        let body = if head_only {
+            Body::empty()
+        } else {
+            self.read_stream(path, attr, span)
+        };
+        response
+            .body(body)
+            .map_err(|err| S3Error::Internal(err.to_string()))
+    }
+
+    /// Streams `span` of the file at `path`, which had `attr` when the
+    /// request started. The response is cut short if the file is replaced or
+    /// modified meanwhile, rather than mixing two versions of the object.
+    fn read_stream(self: &Arc<Self>, path: String, attr: FileAttr, span: Range<u64>) -> Body {
+        let gateway = Arc::clone(self);
+        let (ino, size, mtime) = (attr.ino, attr.size, attr.mtime);
+        let end = span.end;
+        let stream = futures::stream::try_unfold(span.start, move |offset| {
+            let gateway = Arc::clone(&gateway);
+            let path = path.clone();
+            async move {
+                if offset >= end {
+                    return Ok(None);
+                }
+                let len = (end - offset).min(READ_PIECE as u64) as usize;
+                let data = gateway.fs.read_at(&path, offset, len).await?;
+                let now = gateway.fs.lstat(&path).await?;
+                if data.len() != len || now.ino != ino || now.size != size || now.mtime != mtime {
+                    tracing::warn!(path = %path, "object changed while it was read, aborting response");
+                    return Err(io::Error::other(format!(
+                        "{path} changed while it was read"
+                    )));
+                }
+                Ok(Some((Bytes::from(data), offset + len as u64)))
+            }
+        });
+        Body::from_stream(stream)
+    }
+
+    pub(super) async fn put_object(
+        &self,
+        bucket: &str,
+        key: &str,
+        headers: &HeaderMap,
+        body: Body,
+    ) -> Result<Response, S3Error> {
+        let path = object_path(bucket, key)?;
+        if key.ends_with('/') {
+            let length = headers
+                .get("x-amz-decoded-content-length")
+                .or_else(|| headers.get(CONTENT_LENGTH));
+            if length.is_some_and(|length| length != "0") {
+                return Err(S3Error::InvalidRequest(format!(
+                    "{key} is a directory and cannot hold data"
+                )));
+            }
+            self.fs
+                .mkdir_p(&path)
+                .await
+                .map_err(|err| S3Error::from_io(err, key))?;
+            return Ok([(ETAG, format!("\"{EMPTY_ETAG}\""))].into_response());
+        }
+
+        let (staged, etag) = self.stage_body(headers, body).await?;
+        if let Err(err) = self.publish(&staged, bucket, &path, key).await {
+            self.discard(&staged).await;
+            return Err(err);
+        }
+        Ok([(ETAG, format!("\"{etag}\""))].into_response())
+    }
+
+    /// Writes the request body to a new staging file and records its MD5 as
+    /// ETag. Returns the staging file, which the caller publishes or
+    /// discards, and the ETag.
+    pub(super) async fn stage_body(
+        &self,
+        headers: &HeaderMap,
+        body: Body,
+    ) -> Result<(String, String), S3Error> {
+        let staged = self.create_staging().await?;
+        let result = async {
+            let md5 = write_body(&self.fs, &staged, headers, body, MAX_OBJECT_SIZE).await?;
+            let etag = hex::encode(md5);
+            self.record_etag(&staged, &etag).await?;
+            Ok(etag)
+        }
+        .await;
+        match result {
+            Ok(etag) => Ok((staged, etag)),
+            Err(err) => {
+                self.discard(&staged).await;
+                Err(err)
+            }
+        }
+    }
+
+    /// Copies an object by cloning its file, which shares the data instead
+    /// of copying it.
+    pub(super) async fn copy_object(
+        &self,
+        bucket: &str,
+        key: &str,
+        headers: &HeaderMap,
+    ) -> Result<Response, S3Error> {
+        let invalid_source = || S3Error::InvalidArgument("invalid x-amz-copy-source".to_string());
+        let source = headers
+            .get("x-amz-copy-source")
+            .and_then(|value| value.to_str().ok())
+            .and_then(percent_decode)
+            .ok_or_else(invalid_source)?;
+        let (source, version) = source
+            .split_once("?versionId=")
+            .unwrap_or((source.as_str(), "null"));
+        if version != "null" {
+            return Err(S3Error::NotImplemented("object versions".to_string()));
+        }
+        let (src_bucket, src_key) = source
+            .strip_prefix('/')
+            .unwrap_or(source)
+            .split_once('/')
+            .ok_or_else(invalid_source)?;
+        check_bucket_name(src_bucket)?;
+        self.check_bucket(src_bucket).await?;
+        if src_key.ends_with('/') || key.ends_with('/') {
+            return Err(S3Error::InvalidRequest(
+                "directories cannot be copied".to_string(),
+            ));
+        }
+        let src_path = object_path(src_bucket, src_key)?;
+        let path = object_path(bucket, key)?;
+        let attr = self
+            .fs
+            .lstat(&src_path)
+            .await
+            .map_err(|err| S3Error::from_io(err, src_key))?;
+        if attr.kind != FileType::File {
+            return Err(S3Error::NoSuchKey(src_key.to_string()));
+        }
+        let etag = self.etag(&src_path, &attr).await;
+
+        let staged = staging_path();
+        self.fs
+            .clone(&src_path, &staged, false)
+            .await
+            .map_err(|err| S3Error::from_io(err, src_key))?;
+        let result = async {
+            // The source may have changed after its ETag was taken.
+            let cloned = self
+                .fs
+                .lstat(&staged)
+                .await
+                .map_err(|err| S3Error::from_io(err, key))?;
+            if cloned.size != attr.size {
+                return Err(S3Error::InvalidRequest(format!(
+                    "{src_key} changed while it was copied"
+                )));
+            }
+            let copied = self.record_etag(&staged, etag.trim_matches('"')).await?;
+            self.publish(&staged, bucket, &path, key).await?;
+            Ok(copied)
+        }
+        .await;
+        let copied = match result {
+            Ok(copied) => copied,
+            Err(err) => {
+                self.discard(&staged).await;
+                return Err(err);
+            }
+        };
+
+        let mut doc = XmlWriter::new("CopyObjectResult");
+        doc.elem("LastModified", iso8601(copied.mtime))
+            .elem("ETag", &etag);
+        Ok(xml_response(doc.finish("CopyObjectResult")))
+    }
+
+    pub(super) async fn delete_object(&self, bucket: &str, key: &str) -> Result<Response, S3Error> {
+        self.remove_object(bucket, key).await?;
+        Ok(StatusCode::NO_CONTENT.into_response())
+    }
+
+    /// Removes the object `key`, which succeeds if it does not exist. The
+    /// directory of a key ending in `/` stays while it holds other objects.
+    async fn remove_object(&self, bucket: &str, key: &str) -> Result<(), S3Error> {
+        let path = object_path(bucket, key)?;
+        let result = if key.ends_with('/') {
+            self.fs.rmdir(&path).await
+        } else {
+            self.fs.unlink(&path).await
+        };
+        match result {
+            Ok(()) => self.prune_parents(bucket, &path).await,
+            Err(err)
+                if matches!(
+                    err.kind(),
+                    io::ErrorKind::NotFound
+                        | io::ErrorKind::NotADirectory
+                        | io::ErrorKind::IsADirectory
+                        | io::ErrorKind::DirectoryNotEmpty
+                ) => {}
+            Err(err) => return Err(S3Error::from_io(err, key)),
+        }
+        Ok(())
+    }
+
+    pub(super) async fn delete_objects(
+        &self,
+        bucket: &str,
+        headers: &HeaderMap,
+        body: Body,
+    ) -> Result<Response, S3Error> {
+        self.check_bucket(bucket).await?;
+        let doc = read_xml(headers, body).await?;
+        let delete = xml::elements(&doc, "Delete")
+            .into_iter()
+            .next()
+            .ok_or(S3Error::MalformedXml)?;
+        let quiet = xml::text(delete, "Quiet").is_some_and(|quiet| quiet == "true");
+        let keys = xml::elements(delete, "Object")
+            .into_iter()
+            .map(|object| xml::text(object, "Key").ok_or(S3Error::MalformedXml))
+            .collect::<Result<Vec<_>, _>>()?;
+        if keys.is_empty() || keys.len() > MAX_DELETE_KEYS {
+            return Err(S3Error::MalformedXml);
+        }
+
+        let mut result = XmlWriter::new("DeleteResult");
+        for key in keys {
+            match self.remove_object(bucket, &key).await {
+                Ok(()) if quiet => {}
+                Ok(()) => {
+                    result.open("Deleted").elem("Key", &key).close("Deleted");
+                }
+                Err(err) => {
+                    result
+                        .open("Error")
+                        .elem("Key", &key)
+                        .elem("Code", err.code())
+                        .elem("Message", err.to_string())
+                        .close("Error");
+                }
+            }
+        }
+        Ok(xml_response(result.finish("DeleteResult")))
+    }
+}
+
+/// Parses a `Range` header for an object of `size` bytes. Ranges the gateway
+/// does not understand, including multiple ranges, select the whole object
+/// as HTTP allows; ranges outside the object are an error.
+fn parse_range(header: Option<&str>, size: u64) -> Result<Option<Range<u64>>, S3Error> {
+    let Some((first, last)) = header
+        .and_then(|header| header.trim().strip_prefix("bytes="))
+        .filter(|spec| !spec.contains(','))
+        .and_then(|spec| spec.split_once('-'))
+    else {
+        return Ok(None);
+    };
+    let (first, last) = (first.trim(), last.trim());
+    let range = if first.is_empty() {
+        match last.parse::<u64>() {
+            Ok(0) => return Err(S3Error::InvalidRange),
+            Ok(suffix) => size.saturating_sub(suffix)..size,
+            Err(_) => return Ok(None),
+        }
+    } else {
+        let Ok(start) = first.parse::<u64>() else {
+            return Ok(None);
+        };
+        let end = match last.parse::<u64>() {
+            _ if last.is_empty() => size,
+            Ok(last) if last >= start => last.saturating_add(1).min(size),
+            _ => return Ok(None),
+        };
+        start..end
+    };
+    if range.start >= size {
+        return Err(S3Error::InvalidRange);
+    }
+    Ok(Some(range))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parses_ranges() {
+        let range = |header| parse_range(Some(header), 100);
+        assert_eq!(range("bytes=0-9").unwrap(), Some(0..10));
+        assert_eq!(range("bytes=90-").unwrap(), Some(90..100));
+        assert_eq!(range("bytes=90-200").unwrap(), Some(90..100));
+        assert_eq!(range("bytes=-10").unwrap(), Some(90..100));
+        assert_eq!(range("bytes=-200").unwrap(), Some(0..100));
+        assert_eq!(range("bytes=0-1,5-6").unwrap(), None);
+        assert_eq!(range("bytes=5-1").unwrap(), None);
+        assert_eq!(range("items=0-1").unwrap(), None);
+        assert!(matches!(range("bytes=100-"), Err(S3Error::InvalidRange)));
+        assert!(matches!(range("bytes=-0"), Err(S3Error::InvalidRange)));
+        assert_eq!(parse_range(None, 100).unwrap(), None);
+    }
+}
@@ -0,0 +1,182 @@
+//! The bits of XML the S3 API needs: writing responses and reading the
+//! small request documents of multipart uploads and batch deletes.
+
+use std::fmt::Write as _;
+
+pub(crate) const S3_NAMESPACE: &str = "http://s3.amazonaws.com/doc/2006-03-01/";
+
+/// Builds an XML document element by element.
+pub(crate) struct XmlWriter {
+    out: String,
+}
+
+impl XmlWriter {
+    /// Starts a document whose root element `root` is in the S3 namespace.
+    pub(crate) fn new(root: &str) -> Self {
+        let mut out = String::from(r#"<?xml version="1.0" encoding="UTF-8"?>"#);
+        let _ = write!(out, r#"<{root} xmlns="{S3_NAMESPACE}">"#);
+        Self { out }
+    }
+
+    pub(crate) fn open(&mut self, tag: &str) -> &mut Self {
+        let _ = write!(self.out, "<{tag}>");
+        self
+    }
+
+    pub(crate) fn close(&mut self, tag: &str) -> &mut Self {
+        let _ = write!(self.out, "</{tag}>");
+        self
+    }
+
+    /// Writes `<tag>text</tag>`, escaping `text`.
+    pub(crate) fn elem(&mut self, tag: &str, text: impl AsRef<str>) -> &mut Self {
+        let _ = write!(self.out, "<{tag}>{}</{tag}>", escape(text.as_ref()));
+        self
+    }
+
+    /// Closes the root element and returns the document.
+    pub(crate) fn finish(mut self, root: &str) -> String {
+        let _ = write!(self.out, "</{root}>");
+        self.out
+    }
+}
+
+pub(crate) fn escape(text: &str) -> String {
+    let mut out = String::with_capacity(text.len());
+    for c in text.chars() {
+        match c {
+            '&' => out.push_str("&amp;"),
+            '<' => out.push_str("&lt;"),
+            '>' => out.push_str("&gt;"),
+            '"' => out.push_str("&quot;"),
+            '\'' => out.push_str("&apos;"),
+            // Not allowed in XML 1.0 text at all.
+            c if (c as u32) < 0x20 && !matches!(c, '\t' | '\n' | '\r') => {
+                let _ = write!(out, "&#x{:x};", c as u32);
+            }
+            c => out.push(c),
+        }
+    }
+    out
+}
+
+/// Resolves the predefined and numeric character references in `text`.
+pub(crate) fn unescape(text: &str) -> Option<String> {
+    let mut out = String::with_capacity(text.len());
+    let mut rest = text;
+    while let Some(start) = rest.find('&') {
+        out.push_str(&rest[..start]);
+        let end = rest[start..].find(';')? + start;
+        let entity = &rest[start + 1..end];
+        let c = match entity {
+            "amp" => '&',
+            "lt" => '<',
+            "gt" => '>',
+            "quot" => '"',
+            "apos" => '\'',
+            _ => {
+                let code = if let Some(hex) = entity.strip_prefix("#x") {
+                    u32::from_str_radix(hex, 16).ok()?
+                } else {
+                    entity.strip_prefix('#')?.parse().ok()?
+                };
+                char::from_u32(code)?
+            }
+        };
+        out.push(c);
+        rest = &rest[end + 1..];
+    }
+    out.push_str(rest);
+    Some(out)
+}
+
+/// Returns the raw content of every `<tag>` element in `xml`, in document
+/// order. Elements of the same name must not nest, which holds for the
+/// documents the gateway reads.
+pub(crate) fn elements<'a>(xml: &'a str, tag: &str) -> Vec<&'a str> {
+    let open = format!("<{tag}");
+    let close = format!("</{tag}>");
+    let mut found = Vec::new();
+    let mut rest = xml;
+    while let Some(start) = rest.find(&open) {
+        let after = &rest[start + open.len()..];
+        // `<PartNumber>` also starts with `<Part`.
+        let Some(gt) = after.find('>') else { break };
+        let head = &after[..gt];
+        if !(head.is_empty() || head == "/" || head.starts_with(char::is_whitespace)) {
+            rest = after;
+            continue;
+        }
+        if head.ends_with('/') {
+            found.push("");
+            rest = &after[gt + 1..];
+            continue;
+        }
+        let body = &after[gt + 1..];
+        let Some(end) = body.find(&close) else { break };
+        found.push(&body[..end]);
+        rest = &body[end + close.len()..];
+    }
+    found
+}
+
+/// Returns the unescaped text of the first `<tag>` element in `xml`.
+pub(crate) fn text(xml: &str, tag: &str) -> Option<String> {
+    elements(xml, tag)
+        .first()
+        .and_then(|raw| unescape(raw.trim()))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn escape_round_trip() {
+        let text = "a&b <c> \"d\" 'e'";
+        assert_eq!(unescape(&escape(text)).unwrap(), text);
+        assert_eq!(unescape("&#65;&#x42;").unwrap(), "AB");
+        assert_eq!(unescape("&bogus;"), None);
+    }
+
+    #[test]
+    fn reads_complete_multipart_upload() {
+        let xml = r#"<?xml version="1.0" encoding="UTF-8"?>
+            <CompleteMultipartUpload xmlns="http://s3.amazonaws.com/doc/2006-03-01/">
+              <Part><PartNumber>1</PartNumber><ETag>"a&amp;b"</ETag></Part>
+              <Part>
+                <ETag>"c"</ETag>
+                <PartNumber>2</PartNumber>
+              </Part>
+            </CompleteMultipartUpload>"#;
+        let parts: Vec<(String, String)> = elements(xml, "Part")
+            .into_iter()
+            .map(|part| {
+                (
+                    text(part, "PartNumber").unwrap(),
+                    text(part, "ETag").unwrap(),
+                )
+            })
+            .collect();
+        assert_eq!(
+            parts,
+            [
+                ("1".to_string(), "\"a&b\"".to_string()),
+                ("2".to_string(), "\"c\"".to_string())
+            ]
+        );
+        assert_eq!(elements("<Quiet/>", "Quiet"), [""]);
+    }
+
+    #[test]
+    fn writes_documents() {
+        let mut xml = XmlWriter::new("Result");
+        xml.open("Item").elem("Key", "a<b").close("Item");
+        assert_eq!(
+            xml.finish("Result"),
+            format!(
+                r#"<?xml version="1.0" encoding="UTF-8"?><Result xmlns="{S3_NAMESPACE}"><Item><Key>a&lt;b</Key></Item></Result>"#
+            )
+        );
+    }
+}
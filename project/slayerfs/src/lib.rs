@@ -7,6 +7,7 @@ pub mod chuck;
 pub mod daemon;
 pub(crate) mod fs;
 pub mod fuse;
+pub mod gateway;
 pub(crate) mod meta;
 pub(crate) mod posix;
 pub mod sdk_fs;
@@ -25,6 +26,7 @@ pub use crate::vfs::sdk::{LocalClient, VfsClient};
 pub use crate::cadapter::client::{ObjectBackend, ObjectClient};
 pub use crate::cadapter::localfs::LocalFsBackend;
 pub use crate::cadapter::s3::{S3Backend, S3Config};
+pub use crate::cadapter::storage::StorageBackend;
 pub use crate::chuck::chunk::ChunkLayout;
 pub use crate::chuck::store::{BlockKey, BlockStore, InMemoryBlockStore, ObjectBlockStore};
 pub use crate::meta::client::MetaClient;
@@ -40,3 +42,4 @@ pub use crate::meta::{
     MetaHandle, MetaStore, create_meta_store_from_url, create_redis_meta_store_from_url,
 };
 pub use crate::vfs::fs::{RenameFlags, VFS};
+pub use crate::vfs::io::warmup::WarmupReport;
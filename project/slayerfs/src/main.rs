@@ -4,6 +4,7 @@ mod daemon;
 #[allow(dead_code)]
 mod fs;
 mod fuse;
+mod gateway;
 mod meta;
 mod posix;
 mod utils;
@@ -17,30 +18,48 @@ use tikv_jemallocator::Jemalloc;
 #[global_allocator]
 static GLOBAL: Jemalloc = Jemalloc;
 
-#[cfg(feature = "profiling")]
 use std::fs::File;
-#[cfg(feature = "profiling")]
-use std::io::BufWriter;
+use std::io::{BufWriter, Write};
 use std::path::PathBuf;
 use std::sync::Arc;
 #[cfg(feature = "profiling")]
 use std::sync::{LazyLock, Mutex as StdMutex};
+use std::time::Duration;
 
+use anyhow::Context;
 use clap::{Args, Parser, Subcommand, ValueEnum};
+use sha2::{Digest, Sha256};
 use tracing_subscriber::fmt::format::FmtSpan;
 use tracing_subscriber::layer::SubscriberExt;
 use tracing_subscriber::util::SubscriberInitExt;
 
 use crate::cadapter::client::ObjectClient;
-use crate::cadapter::localfs::LocalFsBackend;
+use crate::cadapter::storage::{StorageBackend, StorageUrl};
+use crate::chuck::cache::ChunksCacheConfig;
 use crate::chuck::chunk::{ChunkLayout, DEFAULT_BLOCK_SIZE, DEFAULT_CHUNK_SIZE};
-use crate::chuck::store::ObjectBlockStore;
+use crate::chuck::gc::{self, GcOption};
+use crate::chuck::store::{ObjectBlockStore, block_key};
+use crate::chuck::transform::{Compression, Encryption, load_transform};
+use crate::fs::{CallerIdentity, FileSystem, FileSystemConfig};
 use crate::fuse::mount::mount_vfs_unprivileged;
-use crate::meta::MetaStore;
-use crate::meta::config::{CacheConfig, ClientOptions, Config, DatabaseConfig, DatabaseType};
+use crate::gateway::{Credentials, Gateway};
+use crate::meta::client::MetaClientOptions;
+use crate::meta::config::{
+    CacheConfig, ClientOptions, Config, DatabaseConfig, DatabaseType, MetaClientConfig,
+};
 use crate::meta::factory::MetaStoreFactory;
-use crate::meta::stores::{DatabaseMetaStore, EtcdMetaStore};
+use crate::meta::fsck::{self, FsckOption};
+use crate::meta::quota::{QuotaKey, owner_usage, subtree_usage};
+use crate::meta::store::{
+    CloneOption, DumpOption, DumpRecord, FileType, LoadOption, MetaError, Quota, QuotaType, Visitor,
+};
+use crate::meta::stores::{DatabaseMetaStore, EtcdMetaStore, RedisMetaStore};
+use crate::meta::trash::list_entries;
+use crate::meta::volume::{VolumeFormat, format_volume, load_volume, resolve_layout};
+use crate::meta::{MetaLayer, MetaStore};
 use crate::vfs::fs::VFS;
+use crate::vfs::io::warmup::warmup;
+use crate::vfs::sdk::VfsClient;
 
 #[derive(Parser)]
 #[command(name = "slayerfs", version, about = "SlayerFS FUSE CLI")]
@@ -51,31 +70,83 @@ struct Cli {
 
 #[derive(Subcommand)]
 enum Command {
+    /// Record the name, layout and object storage of a new volume.
+    Format(FormatArgs),
     /// Mount SlayerFS via FUSE.
     Mount(MountArgs),
+    /// Manage directory, user and group quotas.
+    Quota(QuotaArgs),
+    /// Dump the metadata of a volume as JSON lines.
+    Dump(DumpArgs),
+    /// Load a metadata dump into an empty volume.
+    Load(LoadArgs),
+    /// Clone a file or directory tree without copying its data.
+    Clone(CloneArgs),
+    /// List or restore removed files held in the trash.
+    Trash(TrashArgs),
+    /// Check the metadata and block objects for consistency.
+    Fsck(FsckArgs),
+    /// Delete block objects no file references any more.
+    Gc(GcArgs),
+    /// Load files into the block cache before a job reads them.
+    Warmup(WarmupArgs),
+    /// Serve the volume through an S3-compatible API.
+    Gateway(GatewayArgs),
 }
 
+/// Metadata backend selection shared by all commands.
 #[derive(Args)]
-struct MountArgs {
-    /// Directory to mount the filesystem.
-    #[arg(value_name = "MOUNT_POINT")]
-    mount_point: PathBuf,
+struct MetaArgs {
+    /// Metadata backend (sqlx, redis or etcd); inferred from --meta-url if not given.
+    #[arg(long, global = true, value_enum)]
+    meta_backend: Option<MetaBackendKind>,
+
+    /// Metadata backend URL (sqlx or redis, e.g. sqlite::memory:, postgres://... or redis://...).
+    #[arg(
+        long,
+        global = true,
+        value_name = "URL",
+        default_value = "sqlite::memory:"
+    )]
+    meta_url: String,
 
-    /// Local directory used as object storage backend.
+    /// Etcd endpoint URLs (comma-separated).
+    #[arg(long, global = true, value_name = "URLS", value_delimiter = ',')]
+    meta_etcd_urls: Vec<String>,
+}
+
+/// Object storage and layout of a volume; formatted volumes record both.
+#[derive(Args)]
+struct VolumeArgs {
+    /// Object storage URL (file:///path or s3://bucket); defaults to the one recorded by `format`.
+    #[arg(long, value_name = "URL")]
+    storage: Option<String>,
+
+    /// Local directory used as object storage backend of volumes that were not formatted.
     #[arg(long, value_name = "DIR", default_value = "./data")]
     data_dir: PathBuf,
 
-    /// Metadata backend (sqlx or etcd).
-    #[arg(long, value_enum, default_value_t = MetaBackendKind::Sqlx)]
-    meta_backend: MetaBackendKind,
+    /// Chunk size in bytes; must match the one of a formatted volume.
+    #[arg(long)]
+    chunk_size: Option<u64>,
 
-    /// Metadata backend URL (sqlx only, e.g. sqlite::memory: or postgres://...).
-    #[arg(long, value_name = "URL", default_value = "sqlite::memory:")]
-    meta_url: String,
+    /// Block size in bytes; must match the one of a formatted volume.
+    #[arg(long)]
+    block_size: Option<u32>,
+}
 
-    /// Etcd endpoint URLs (comma-separated).
-    #[arg(long, value_name = "URLS", value_delimiter = ',')]
-    meta_etcd_urls: Vec<String>,
+#[derive(Args)]
+struct FormatArgs {
+    /// Name of the volume.
+    #[arg(value_name = "NAME")]
+    name: String,
+
+    #[command(flatten)]
+    meta: MetaArgs,
+
+    /// Object storage URL, e.g. file:///srv/slayerfs or s3://bucket?region=eu-west-1.
+    #[arg(long, value_name = "URL")]
+    storage: String,
 
     /// Chunk size in bytes.
     #[arg(long, default_value_t = DEFAULT_CHUNK_SIZE)]
@@ -84,11 +155,281 @@ struct MountArgs {
     /// Block size in bytes.
     #[arg(long, default_value_t = DEFAULT_BLOCK_SIZE)]
     block_size: u32,
+
+    /// Compression of block objects.
+    #[arg(long, value_enum, default_value_t = Compression::None)]
+    compression: Compression,
+
+    /// Encryption of block objects.
+    #[arg(long, value_enum, default_value_t = Encryption::None)]
+    encryption: Encryption,
+
+    /// Passphrase protecting the key of an encrypted volume.
+    #[arg(long, env = "SLAYERFS_PASSPHRASE", hide_env_values = true)]
+    passphrase: Option<String>,
+}
+
+#[derive(Args)]
+struct MountArgs {
+    /// Directory to mount the filesystem.
+    #[arg(value_name = "MOUNT_POINT")]
+    mount_point: PathBuf,
+
+    #[command(flatten)]
+    volume: VolumeArgs,
+
+    #[command(flatten)]
+    meta: MetaArgs,
+
+    /// Days removed files are kept in the trash; 0 deletes them right away.
+    #[arg(long, value_name = "DAYS", default_value_t = 0)]
+    trash_days: u64,
+
+    /// Compression of block objects; fixed when the volume is first mounted.
+    #[arg(long, value_enum)]
+    compression: Option<Compression>,
+
+    /// Encryption of block objects; fixed when the volume is first mounted.
+    #[arg(long, value_enum)]
+    encryption: Option<Encryption>,
+
+    /// Passphrase protecting the key of an encrypted volume.
+    #[arg(long, env = "SLAYERFS_PASSPHRASE", hide_env_values = true)]
+    passphrase: Option<String>,
+
+    /// Directory of the block cache; defaults to one per volume below the user cache directory.
+    #[arg(long, value_name = "DIR")]
+    cache_dir: Option<PathBuf>,
+}
+
+#[derive(Args)]
+struct QuotaArgs {
+    #[command(flatten)]
+    meta: MetaArgs,
+
+    #[command(subcommand)]
+    cmd: QuotaCommand,
+}
+
+#[derive(Subcommand)]
+enum QuotaCommand {
+    /// Set the limits of a quota, creating it if needed.
+    Set {
+        #[command(flatten)]
+        target: QuotaTarget,
+
+        /// Space limit in bytes; accepts K, M, G and T suffixes, 0 means unlimited.
+        #[arg(long, value_name = "SIZE", value_parser = parse_size)]
+        space: Option<u64>,
+
+        /// Inode limit, 0 means unlimited.
+        #[arg(long, value_name = "COUNT")]
+        inodes: Option<u64>,
+    },
+    /// Show the limits and usage of a quota.
+    Get {
+        #[command(flatten)]
+        target: QuotaTarget,
+    },
+    /// List all quotas.
+    List,
+    /// Remove a quota.
+    Delete {
+        #[command(flatten)]
+        target: QuotaTarget,
+    },
+}
+
+#[derive(Args)]
+struct DumpArgs {
+    #[command(flatten)]
+    meta: MetaArgs,
+
+    /// File to write the dump to; defaults to stdout.
+    #[arg(long, short, value_name = "FILE")]
+    output: Option<PathBuf>,
+
+    /// Chunk size in bytes the volume was created with; recorded by `format`.
+    #[arg(long)]
+    chunk_size: Option<u64>,
+}
+
+#[derive(Args)]
+struct LoadArgs {
+    #[command(flatten)]
+    meta: MetaArgs,
+
+    /// Dump file produced by `slayerfs dump`.
+    #[arg(value_name = "FILE")]
+    input: PathBuf,
+
+    /// Merge into existing directories instead of failing on existing entries.
+    #[arg(long)]
+    allow_conflicts: bool,
+}
+
+#[derive(Args)]
+struct CloneArgs {
+    #[command(flatten)]
+    meta: MetaArgs,
+
+    /// Path of the file or directory to clone, e.g. /projects/a.
+    #[arg(value_name = "SRC")]
+    src: String,
+
+    /// Path of the clone; must not exist yet.
+    #[arg(value_name = "DST")]
+    dst: String,
+
+    /// Keep the owner, mode and timestamps of the source.
+    #[arg(long)]
+    preserve: bool,
+
+    /// Chunk size in bytes the volume was created with; recorded by `format`.
+    #[arg(long)]
+    chunk_size: Option<u64>,
+}
+
+#[derive(Args)]
+struct TrashArgs {
+    #[command(flatten)]
+    meta: MetaArgs,
+
+    #[command(subcommand)]
+    cmd: TrashCommand,
+}
+
+#[derive(Subcommand)]
+enum TrashCommand {
+    /// List the entries in the trash with their original paths.
+    List,
+    /// Move entries back to where they were removed from.
+    Restore {
+        /// A whole batch (e.g. 2024-05-01-13) or one entry of it
+        /// (e.g. 2024-05-01-13/1-42-report.txt), as printed by `trash list`.
+        #[arg(value_name = "ENTRY", required = true)]
+        entries: Vec<String>,
+    },
+}
+
+#[derive(Args)]
+struct FsckArgs {
+    #[command(flatten)]
+    meta: MetaArgs,
+
+    #[command(flatten)]
+    volume: VolumeArgs,
+
+    /// Repair slice reference counts, quota usage and leftover clones.
+    #[arg(long)]
+    repair: bool,
+}
+
+#[derive(Args)]
+struct GcArgs {
+    #[command(flatten)]
+    meta: MetaArgs,
+
+    #[command(flatten)]
+    volume: VolumeArgs,
+
+    /// Seconds an unreferenced object is kept, covering in-flight writes.
+    #[arg(long, value_name = "SECS", default_value_t = 3600)]
+    grace: u64,
+
+    /// Only list the objects that would be deleted.
+    #[arg(long)]
+    dry_run: bool,
+}
+
+#[derive(Args)]
+struct WarmupArgs {
+    /// Files or directory trees inside the filesystem, e.g. /datasets/train.
+    #[arg(value_name = "PATH", required = true)]
+    paths: Vec<String>,
+
+    #[command(flatten)]
+    meta: MetaArgs,
+
+    #[command(flatten)]
+    volume: VolumeArgs,
+
+    /// Passphrase protecting the key of an encrypted volume.
+    #[arg(long, env = "SLAYERFS_PASSPHRASE", hide_env_values = true)]
+    passphrase: Option<String>,
+
+    /// Directory of the block cache; must match the one of the mount.
+    #[arg(long, value_name = "DIR")]
+    cache_dir: Option<PathBuf>,
+}
+
+#[derive(Args)]
+struct GatewayArgs {
+    #[command(flatten)]
+    meta: MetaArgs,
+
+    #[command(flatten)]
+    volume: VolumeArgs,
+
+    /// Address to serve the S3 API on.
+    #[arg(long, value_name = "ADDR", default_value = "127.0.0.1:9000")]
+    listen: String,
+
+    /// Access key clients sign requests with; requests are not authenticated without one.
+    #[arg(long, env = "SLAYERFS_ACCESS_KEY", requires = "secret_key")]
+    access_key: Option<String>,
+
+    /// Secret key clients sign requests with.
+    #[arg(
+        long,
+        env = "SLAYERFS_SECRET_KEY",
+        hide_env_values = true,
+        requires = "access_key"
+    )]
+    secret_key: Option<String>,
+
+    /// User id objects are read and written as; defaults to the current user.
+    #[arg(long)]
+    uid: Option<u32>,
+
+    /// Group id objects are read and written as; defaults to the current group.
+    #[arg(long)]
+    gid: Option<u32>,
+
+    /// Days removed objects are kept in the trash; 0 deletes them right away.
+    #[arg(long, value_name = "DAYS", default_value_t = 0)]
+    trash_days: u64,
+
+    /// Passphrase protecting the key of an encrypted volume.
+    #[arg(long, env = "SLAYERFS_PASSPHRASE", hide_env_values = true)]
+    passphrase: Option<String>,
+
+    /// Directory of the block cache; defaults to the one of mounts of the volume.
+    #[arg(long, value_name = "DIR")]
+    cache_dir: Option<PathBuf>,
+}
+
+/// What a quota applies to: exactly one of a directory, a user or a group.
+#[derive(Args)]
+#[group(required = true, multiple = false)]
+struct QuotaTarget {
+    /// Directory inside the filesystem, e.g. /projects/a.
+    #[arg(long, value_name = "PATH")]
+    path: Option<String>,
+
+    /// User id.
+    #[arg(long)]
+    uid: Option<u32>,
+
+    /// Group id.
+    #[arg(long)]
+    gid: Option<u32>,
 }
 
 #[derive(ValueEnum, Clone, Copy)]
 enum MetaBackendKind {
     Sqlx,
+    Redis,
     Etcd,
 }
 
@@ -98,7 +439,17 @@ async fn main() -> anyhow::Result<()> {
 
     let cli = Cli::parse();
     let result = match cli.cmd {
+        Command::Format(args) => format_cmd(args).await,
         Command::Mount(args) => mount_cmd(args).await,
+        Command::Quota(args) => quota_cmd(args).await,
+        Command::Dump(args) => dump_cmd(args).await,
+        Command::Load(args) => load_cmd(args).await,
+        Command::Clone(args) => clone_cmd(args).await,
+        Command::Trash(args) => trash_cmd(args).await,
+        Command::Fsck(args) => fsck_cmd(args).await,
+        Command::Gc(args) => gc_cmd(args).await,
+        Command::Warmup(args) => warmup_cmd(args).await,
+        Command::Gateway(args) => gateway_cmd(args).await,
     };
     shutdown_flame();
     shutdown_chrome();
@@ -166,6 +517,42 @@ fn init_tracing() {
         .init();
 }
 
+async fn format_cmd(args: FormatArgs) -> anyhow::Result<()> {
+    let layout = ChunkLayout {
+        chunk_size: args.chunk_size,
+        block_size: args.block_size,
+    };
+    // Fails on storage that cannot be opened before anything is recorded.
+    StorageBackend::open(&args.storage).await?;
+    let storage = match StorageUrl::parse(&args.storage)? {
+        // Mounts may run from any directory.
+        StorageUrl::File(path) => format!("file://{}", std::fs::canonicalize(path)?.display()),
+        StorageUrl::S3(_) => args.storage.clone(),
+    };
+    let format = VolumeFormat::new(&args.name, layout, &storage)?;
+
+    let store = create_meta_store(&args.meta).await?;
+    load_transform(
+        store.as_ref(),
+        Some(args.compression),
+        Some(args.encryption),
+        args.passphrase.as_deref(),
+    )
+    .await?;
+    let volume = format_volume(store.as_ref(), &format).await?;
+    println!(
+        "volume {} ({}): chunk size {}, block size {}, storage {}, compression {}, encryption {}",
+        volume.name,
+        volume.uuid,
+        volume.chunk_size,
+        volume.block_size,
+        volume.storage,
+        args.compression,
+        args.encryption
+    );
+    Ok(())
+}
+
 async fn mount_cmd(args: MountArgs) -> anyhow::Result<()> {
     if !args.mount_point.exists() {
         std::fs::create_dir_all(&args.mount_point)?;
@@ -174,27 +561,29 @@ async fn mount_cmd(args: MountArgs) -> anyhow::Result<()> {
         anyhow::bail!("mount point must be a directory");
     }
 
-    if !args.data_dir.exists() {
-        std::fs::create_dir_all(&args.data_dir)?;
-    }
-    if !args.data_dir.is_dir() {
-        anyhow::bail!("data dir must be a directory");
-    }
-
-    if args.chunk_size < args.block_size as u64 {
-        anyhow::bail!("chunk_size must be >= block_size");
-    }
-
-    let layout = ChunkLayout {
-        chunk_size: args.chunk_size,
-        block_size: args.block_size,
+    let meta_store = create_meta_store(&args.meta).await?;
+    let volume = args.volume.open(meta_store.as_ref()).await?;
+    let layout = volume.layout;
+    let transform = load_transform(
+        meta_store.as_ref(),
+        args.compression,
+        args.encryption,
+        args.passphrase.as_deref(),
+    )
+    .await?;
+    let cache_config = block_cache_config(&volume.cache_id, args.cache_dir)?;
+    let store = ObjectBlockStore::new_with_config(ObjectClient::new(volume.backend), cache_config)?
+        .with_transform(transform);
+    let meta_config = MetaClientConfig {
+        options: MetaClientOptions {
+            trash_retention: (args.trash_days > 0)
+                .then(|| Duration::from_secs(args.trash_days * 24 * 3600)),
+            ..Default::default()
+        },
+        ..Default::default()
     };
 
-    let client = ObjectClient::new(LocalFsBackend::new(&args.data_dir));
-    let store = ObjectBlockStore::new(client);
-    let meta_store = create_meta_store(&args).await?;
-
-    let fs = VFS::new(layout, store, meta_store)
+    let fs = VFS::with_meta_client_config(layout, store, meta_store, meta_config)
         .await
         .map_err(anyhow::Error::from)?;
     let handle = mount_vfs_unprivileged(fs, &args.mount_point).await?;
@@ -206,6 +595,462 @@ async fn mount_cmd(args: MountArgs) -> anyhow::Result<()> {
     Ok(())
 }
 
+async fn quota_cmd(args: QuotaArgs) -> anyhow::Result<()> {
+    let store = create_meta_store(&args.meta).await?;
+    match args.cmd {
+        QuotaCommand::Set {
+            target,
+            space,
+            inodes,
+        } => {
+            if space.is_none() && inodes.is_none() {
+                anyhow::bail!("at least one of --space and --inodes must be given");
+            }
+            let key = resolve_quota_target(store.as_ref(), &target).await?;
+            let limit = |value: u64| (value > 0).then_some(value as i64);
+
+            let quota = match store.get_quota(key.0.into(), key.1).await? {
+                Some(mut quota) => {
+                    if let Some(space) = space {
+                        quota.limit_space = limit(space);
+                    }
+                    if let Some(inodes) = inodes {
+                        quota.limit_inodes = limit(inodes);
+                    }
+                    quota
+                }
+                None => {
+                    let usage = match key.0 {
+                        QuotaType::Dir => subtree_usage(store.as_ref(), key.1 as i64).await?,
+                        QuotaType::User | QuotaType::Group => {
+                            owner_usage(store.as_ref(), key).await?
+                        }
+                    };
+                    Quota {
+                        limit_space: space.and_then(limit),
+                        limit_inodes: inodes.and_then(limit),
+                        used_space: usage.space,
+                        used_inodes: usage.inodes,
+                    }
+                }
+            };
+            store.set_quota(key.0.into(), key.1, quota.clone()).await?;
+            print_quota(key, &quota);
+        }
+        QuotaCommand::Get { target } => {
+            let key = resolve_quota_target(store.as_ref(), &target).await?;
+            match store.get_quota(key.0.into(), key.1).await? {
+                Some(quota) => print_quota(key, &quota),
+                None => anyhow::bail!("no {} quota set for {}", key.0, key.1),
+            }
+        }
+        QuotaCommand::List => {
+            let (dirs, users, groups) = store.load_quotas().await?;
+            let mut quotas: Vec<(QuotaKey, Quota)> = [
+                (QuotaType::Dir, dirs),
+                (QuotaType::User, users),
+                (QuotaType::Group, groups),
+            ]
+            .into_iter()
+            .flat_map(|(qtype, quotas)| {
+                quotas
+                    .into_iter()
+                    .map(move |(id, quota)| ((qtype, id), quota))
+            })
+            .collect();
+            quotas.sort_by_key(|(key, _)| *key);
+            for (key, quota) in &quotas {
+                print_quota(*key, quota);
+            }
+        }
+        QuotaCommand::Delete { target } => {
+            let key = resolve_quota_target(store.as_ref(), &target).await?;
+            store.delete_quota(key.0.into(), key.1).await?;
+        }
+    }
+    Ok(())
+}
+
+/// Writes each dump record as one line of JSON.
+struct JsonLines<W: Write + Send>(W);
+
+impl<W: Write + Send> Visitor<DumpRecord> for JsonLines<W> {
+    fn visit(&mut self, item: DumpRecord) -> Result<(), MetaError> {
+        serde_json::to_writer(&mut self.0, &item)
+            .map_err(|e| MetaError::Serialization(e.to_string()))?;
+        self.0.write_all(b"\n")?;
+        Ok(())
+    }
+}
+
+async fn dump_cmd(args: DumpArgs) -> anyhow::Result<()> {
+    let store = create_meta_store(&args.meta).await?;
+    let volume = load_volume(store.as_ref()).await?;
+    let opt = DumpOption {
+        chunk_size: resolve_layout(volume.as_ref(), args.chunk_size, None)?.chunk_size,
+        ..Default::default()
+    };
+    let out: Box<dyn Write + Send> = match &args.output {
+        Some(path) => Box::new(File::create(path)?),
+        None => Box::new(std::io::stdout()),
+    };
+    let mut writer = JsonLines(BufWriter::new(out));
+    store.dump(opt, &mut writer).await?;
+    writer.0.flush()?;
+    Ok(())
+}
+
+async fn load_cmd(args: LoadArgs) -> anyhow::Result<()> {
+    let store = create_meta_store(&args.meta).await?;
+    let data = std::fs::read(&args.input)?;
+    let opt = LoadOption {
+        allow_conflicts: args.allow_conflicts,
+    };
+    store.load(opt, &data).await?;
+    Ok(())
+}
+
+async fn clone_cmd(args: CloneArgs) -> anyhow::Result<()> {
+    let (store, layer) = create_meta(&args.meta).await?;
+    let volume = load_volume(store.as_ref()).await?;
+    let chunk_size = resolve_layout(volume.as_ref(), args.chunk_size, None)?.chunk_size;
+    let Some((src, _)) = layer.lookup_path(&args.src).await? else {
+        anyhow::bail!("{} does not exist", args.src);
+    };
+    let dst = args.dst.trim_end_matches('/');
+    let (parent_path, name) = match dst.rsplit_once('/') {
+        Some(("", name)) => ("/", name),
+        Some((parent, name)) => (parent, name),
+        None => anyhow::bail!("{} is not an absolute path", args.dst),
+    };
+    let Some((parent, FileType::Dir)) = layer.lookup_path(parent_path).await? else {
+        anyhow::bail!("{parent_path} is not a directory");
+    };
+    let opt = CloneOption {
+        preserve_attr: args.preserve,
+        // SAFETY: geteuid/getegid are thread-safe libc calls and do not dereference pointers.
+        uid: unsafe { libc::geteuid() },
+        gid: unsafe { libc::getegid() },
+        umask: 0o022,
+        chunk_size,
+    };
+    let result = layer.clone_entry(src, parent, name, &opt).await;
+    // Writes back the quota usage charged for the clone.
+    layer.shutdown_session().await?;
+    let attr = result?;
+    println!("cloned {} to {} (inode {})", args.src, args.dst, attr.ino);
+    Ok(())
+}
+
+async fn trash_cmd(args: TrashArgs) -> anyhow::Result<()> {
+    let (store, layer) = create_meta(&args.meta).await?;
+    let entries = list_entries(store.as_ref()).await?;
+    match args.cmd {
+        TrashCommand::List => {
+            for entry in &entries {
+                let dir = match store.get_paths(entry.parent).await?.into_iter().next() {
+                    Some(dir) => dir,
+                    None => "<removed>".to_string(),
+                };
+                let orig = format!("{}/{}", dir.trim_end_matches('/'), entry.orig_name);
+                println!("{}/{}\t{orig}", entry.batch, entry.name);
+            }
+        }
+        TrashCommand::Restore { entries: wanted } => {
+            let (mut matched, mut failed) = (0, 0);
+            for entry in &entries {
+                let id = format!("{}/{}", entry.batch, entry.name);
+                if !wanted.iter().any(|w| *w == entry.batch || *w == id) {
+                    continue;
+                }
+                matched += 1;
+                match layer.restore_trash(&entry.batch, &entry.name).await {
+                    Ok(_) => println!("restored {id}"),
+                    Err(err) => {
+                        eprintln!("failed to restore {id}: {err}");
+                        failed += 1;
+                    }
+                }
+            }
+            // Writes back the quota usage charged for restored files.
+            layer.shutdown_session().await?;
+            if matched == 0 {
+                anyhow::bail!("no matching entries in the trash");
+            }
+            if failed > 0 {
+                anyhow::bail!("{failed} entries could not be restored");
+            }
+        }
+    }
+    Ok(())
+}
+
+async fn fsck_cmd(args: FsckArgs) -> anyhow::Result<()> {
+    let store = create_meta_store(&args.meta).await?;
+    let volume = args.volume.open(store.as_ref()).await?;
+    let opt = FsckOption {
+        chunk_size: volume.layout.chunk_size,
+        repair: args.repair,
+    };
+    let report = fsck::check(store.as_ref(), opt).await?;
+    let mut unrepaired = 0;
+    for finding in &report.findings {
+        if finding.repaired {
+            println!("{} (repaired)", finding.issue);
+        } else {
+            println!("{}", finding.issue);
+            unrepaired += 1;
+        }
+    }
+
+    let client = ObjectClient::new(volume.backend);
+    let lengths = report
+        .slices
+        .iter()
+        .map(|(&id, slice)| (id, slice.length))
+        .collect();
+    let missing = gc::missing_blocks(&client, &lengths, volume.layout.block_size).await?;
+    for &(slice_id, index) in &missing {
+        let slice = &report.slices[&slice_id];
+        let path = slice.path.as_deref().unwrap_or("<deleted>");
+        println!(
+            "missing block {} of {path} (inode {})",
+            block_key((slice_id, index)),
+            slice.ino
+        );
+    }
+
+    println!(
+        "checked {} inodes and {} slices",
+        report.inodes,
+        report.slices.len()
+    );
+    if unrepaired > 0 || !missing.is_empty() {
+        anyhow::bail!(
+            "{unrepaired} unrepaired issues and {} missing blocks",
+            missing.len()
+        );
+    }
+    Ok(())
+}
+
+async fn gc_cmd(args: GcArgs) -> anyhow::Result<()> {
+    let store = create_meta_store(&args.meta).await?;
+    let volume = args.volume.open(store.as_ref()).await?;
+    let referenced = fsck::referenced_slices(store.as_ref(), volume.layout.chunk_size)
+        .await?
+        .into_keys()
+        .collect();
+    let client = ObjectClient::new(volume.backend);
+    let opt = GcOption {
+        grace: Duration::from_secs(args.grace),
+        dry_run: args.dry_run,
+    };
+    let report = gc::collect_garbage(&client, &referenced, &opt).await?;
+    let bytes: u64 = report.leaked.iter().map(|object| object.size).sum();
+    for object in &report.leaked {
+        println!("{}\t{}", object.key, object.size);
+    }
+    if args.dry_run {
+        println!(
+            "would delete {} of {} objects ({bytes} bytes)",
+            report.leaked.len(),
+            report.scanned
+        );
+    } else {
+        println!(
+            "deleted {} of {} objects ({bytes} bytes)",
+            report.deleted, report.scanned
+        );
+    }
+    if report.young > 0 {
+        println!(
+            "kept {} unreferenced objects younger than the grace period",
+            report.young
+        );
+    }
+    Ok(())
+}
+
+async fn warmup_cmd(args: WarmupArgs) -> anyhow::Result<()> {
+    let (store, layer) = create_meta(&args.meta).await?;
+    let volume = args.volume.open(store.as_ref()).await?;
+    let layout = volume.layout;
+    let transform = load_transform(store.as_ref(), None, None, args.passphrase.as_deref()).await?;
+    let cache_config = block_cache_config(&volume.cache_id, args.cache_dir)?;
+    let blocks =
+        ObjectBlockStore::new_with_config(ObjectClient::new(volume.backend), cache_config)?
+            .with_transform(transform);
+
+    for path in &args.paths {
+        let Some((ino, _)) = layer.lookup_path(path).await? else {
+            anyhow::bail!("{path} does not exist");
+        };
+        let report = warmup(&blocks, layer.as_ref(), layout, ino).await?;
+        println!(
+            "warmed up {path}: {} files, {} bytes in {} blocks",
+            report.files, report.bytes, report.blocks
+        );
+    }
+    Ok(())
+}
+
+async fn gateway_cmd(args: GatewayArgs) -> anyhow::Result<()> {
+    let meta_store = create_meta_store(&args.meta).await?;
+    let volume = args.volume.open(meta_store.as_ref()).await?;
+    let transform =
+        load_transform(meta_store.as_ref(), None, None, args.passphrase.as_deref()).await?;
+    let cache_config = block_cache_config(&volume.cache_id, args.cache_dir)?;
+    let store = ObjectBlockStore::new_with_config(ObjectClient::new(volume.backend), cache_config)?
+        .with_transform(transform);
+    let meta_config = MetaClientConfig {
+        options: MetaClientOptions {
+            trash_retention: (args.trash_days > 0)
+                .then(|| Duration::from_secs(args.trash_days * 24 * 3600)),
+            ..Default::default()
+        },
+        ..Default::default()
+    };
+    let mut caller = CallerIdentity::current();
+    if let Some(uid) = args.uid {
+        caller.uid = uid;
+    }
+    if let Some(gid) = args.gid {
+        caller = CallerIdentity::new(caller.uid, gid, vec![gid]);
+    }
+    let config = FileSystemConfig::default().with_caller(caller);
+    let fs =
+        FileSystem::with_configs(volume.layout, store, meta_store, meta_config, config).await?;
+    let credentials = args
+        .access_key
+        .zip(args.secret_key)
+        .map(|(access_key, secret_key)| Credentials {
+            access_key,
+            secret_key,
+        });
+    if credentials.is_none() {
+        tracing::warn!("no access key given, S3 requests are not authenticated");
+    }
+
+    let listener = tokio::net::TcpListener::bind(&args.listen)
+        .await
+        .with_context(|| format!("failed to listen on {}", args.listen))?;
+    println!("serving S3 API on http://{}", listener.local_addr()?);
+    Gateway::new(VfsClient::from_filesystem(fs), credentials)
+        .serve(listener, async {
+            let _ = tokio::signal::ctrl_c().await;
+        })
+        .await
+}
+
+/// Volume opened with the layout and storage recorded by `format`, or the
+/// ones given on the command line for volumes that were not formatted.
+struct Volume {
+    layout: ChunkLayout,
+    backend: StorageBackend,
+    /// Identifies the blocks of the volume in the block cache.
+    cache_id: Vec<u8>,
+}
+
+impl VolumeArgs {
+    async fn open(&self, store: &dyn MetaStore) -> anyhow::Result<Volume> {
+        let format = load_volume(store).await?;
+        let layout = resolve_layout(format.as_ref(), self.chunk_size, self.block_size)?;
+        let storage = match (&self.storage, &format) {
+            (Some(url), _) => url.clone(),
+            (None, Some(format)) => format.storage.clone(),
+            (None, None) => self.data_dir.to_string_lossy().into_owned(),
+        };
+        let backend = StorageBackend::open(&storage).await?;
+        let cache_id = match (&format, StorageUrl::parse(&storage)?) {
+            (Some(format), _) => format.uuid.clone().into_bytes(),
+            (None, StorageUrl::File(path)) => std::fs::canonicalize(path)?
+                .into_os_string()
+                .into_encoded_bytes(),
+            (None, StorageUrl::S3(_)) => storage.into_bytes(),
+        };
+        Ok(Volume {
+            layout,
+            backend,
+            cache_id,
+        })
+    }
+}
+
+/// Cache of the blocks of the volume identified by `cache_id`. Mount and
+/// warmup use the same directory, so blocks loaded by a warmup serve later
+/// mounts.
+fn block_cache_config(
+    cache_id: &[u8],
+    cache_dir: Option<PathBuf>,
+) -> anyhow::Result<ChunksCacheConfig> {
+    let dir = match cache_dir {
+        Some(dir) => dir,
+        None => {
+            let digest = Sha256::digest(cache_id);
+            dirs::cache_dir()
+                .context("no user cache directory, set --cache-dir")?
+                .join("slayerfs")
+                .join(hex::encode(&digest[..8]))
+        }
+    };
+    Ok(ChunksCacheConfig {
+        disk_storage_dir: Some(dir),
+        persistent: true,
+        ..Default::default()
+    })
+}
+
+async fn resolve_quota_target(
+    store: &dyn MetaStore,
+    target: &QuotaTarget,
+) -> anyhow::Result<QuotaKey> {
+    if let Some(uid) = target.uid {
+        return Ok((QuotaType::User, uid as u64));
+    }
+    if let Some(gid) = target.gid {
+        return Ok((QuotaType::Group, gid as u64));
+    }
+    let path = target.path.as_deref().unwrap_or("/");
+    match store.lookup_path(path).await? {
+        Some((ino, FileType::Dir)) => Ok((QuotaType::Dir, ino as u64)),
+        Some(_) => anyhow::bail!("{path} is not a directory"),
+        None => anyhow::bail!("{path} does not exist"),
+    }
+}
+
+fn print_quota(key: QuotaKey, quota: &Quota) {
+    let limit =
+        |value: Option<i64>| value.map_or_else(|| "unlimited".to_string(), |v| v.to_string());
+    println!(
+        "{} {}: space {}/{} inodes {}/{}",
+        key.0,
+        key.1,
+        quota.used_space,
+        limit(quota.limit_space),
+        quota.used_inodes,
+        limit(quota.limit_inodes)
+    );
+}
+
+/// Parses a byte count with an optional binary K/M/G/T suffix.
+fn parse_size(value: &str) -> Result<u64, String> {
+    let value = value.trim();
+    let (digits, shift) = match value.chars().last().map(|c| c.to_ascii_uppercase()) {
+        Some('K') => (&value[..value.len() - 1], 10),
+        Some('M') => (&value[..value.len() - 1], 20),
+        Some('G') => (&value[..value.len() - 1], 30),
+        Some('T') => (&value[..value.len() - 1], 40),
+        _ => (value, 0),
+    };
+    let number: u64 = digits
+        .parse()
+        .map_err(|_| format!("invalid size: {value}"))?;
+    number
+        .checked_mul(1 << shift)
+        .ok_or_else(|| format!("size too large: {value}"))
+}
+
 #[cfg(feature = "profiling")]
 static FLAME_GUARD: LazyLock<StdMutex<Option<tracing_flame::FlushGuard<BufWriter<File>>>>> =
     LazyLock::new(|| StdMutex::new(None));
@@ -250,8 +1095,14 @@ fn shutdown_chrome() {
 #[cfg(not(feature = "profiling"))]
 fn shutdown_chrome() {}
 
-async fn create_meta_store(args: &MountArgs) -> anyhow::Result<Arc<dyn MetaStore>> {
-    match args.meta_backend {
+async fn create_meta_store(args: &MetaArgs) -> anyhow::Result<Arc<dyn MetaStore>> {
+    Ok(create_meta(args).await?.0)
+}
+
+/// Opens the metadata backend, returning both the raw store and the cached
+/// layer on top of it.
+async fn create_meta(args: &MetaArgs) -> anyhow::Result<(Arc<dyn MetaStore>, Arc<dyn MetaLayer>)> {
+    match args.backend_kind() {
         MetaBackendKind::Sqlx => {
             let client = ClientOptions::default();
 
@@ -263,7 +1114,26 @@ async fn create_meta_store(args: &MountArgs) -> anyhow::Result<Arc<dyn MetaStore
                 client,
             };
             let handle = MetaStoreFactory::<DatabaseMetaStore>::create_from_config(config).await?;
-            Ok(handle.store() as Arc<dyn MetaStore>)
+            Ok((
+                handle.store() as Arc<dyn MetaStore>,
+                handle.layer() as Arc<dyn MetaLayer>,
+            ))
+        }
+        MetaBackendKind::Redis => {
+            let config = Config {
+                database: DatabaseConfig {
+                    db_config: DatabaseType::Redis {
+                        url: args.meta_url.clone(),
+                    },
+                },
+                cache: CacheConfig::default(),
+                client: ClientOptions::default(),
+            };
+            let handle = MetaStoreFactory::<RedisMetaStore>::create_from_config(config).await?;
+            Ok((
+                handle.store() as Arc<dyn MetaStore>,
+                handle.layer() as Arc<dyn MetaLayer>,
+            ))
         }
         MetaBackendKind::Etcd => {
             if args.meta_etcd_urls.is_empty() {
@@ -281,7 +1151,26 @@ async fn create_meta_store(args: &MountArgs) -> anyhow::Result<Arc<dyn MetaStore
                 client,
             };
             let handle = MetaStoreFactory::<EtcdMetaStore>::create_from_config(config).await?;
-            Ok(handle.store() as Arc<dyn MetaStore>)
+            Ok((
+                handle.store() as Arc<dyn MetaStore>,
+                handle.layer() as Arc<dyn MetaLayer>,
+            ))
+        }
+    }
+}
+
+impl MetaArgs {
+    fn backend_kind(&self) -> MetaBackendKind {
+        if let Some(kind) = self.meta_backend {
+            return kind;
+        }
+        let lower = self.meta_url.to_ascii_lowercase();
+        if lower.starts_with("redis://") || lower.starts_with("rediss://") {
+            MetaBackendKind::Redis
+        } else if !self.meta_etcd_urls.is_empty() {
+            MetaBackendKind::Etcd
+        } else {
+            MetaBackendKind::Sqlx
         }
     }
 }
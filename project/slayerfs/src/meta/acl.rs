@@ -0,0 +1,441 @@
+//! POSIX access and default ACLs.
+//!
+//! ACLs are kept per inode in the extended attributes
+//! `system.posix_acl_access` and `system.posix_acl_default`, encoded the way
+//! the Linux kernel passes them through `getxattr`/`setxattr`. The FUSE
+//! adapter can therefore hand them over unchanged, and dumps and clones carry
+//! them along with the other extended attributes. Like the dump and clones,
+//! this only uses the generic [`MetaStore`] operations.
+//!
+//! The permission bits of the mode always match the access ACL: the group
+//! bits mirror the mask entry, or the owning group entry if there is no
+//! mask. An access ACL without named entries says nothing the mode does not,
+//! so it is not stored at all.
+
+use crate::meta::store::{FileType, MetaError, MetaStore, SetAttrFlags, chmod_request};
+
+/// Extended attribute holding the access ACL.
+pub const ACL_ACCESS_XATTR: &str = "system.posix_acl_access";
+/// Extended attribute holding the default ACL of a directory.
+pub const ACL_DEFAULT_XATTR: &str = "system.posix_acl_default";
+
+const ACL_XATTR_VERSION: u32 = 2;
+const ACL_UNDEFINED_ID: u32 = u32::MAX;
+const ACL_USER_OBJ: u16 = 0x01;
+const ACL_USER: u16 = 0x02;
+const ACL_GROUP_OBJ: u16 = 0x04;
+const ACL_GROUP: u16 = 0x08;
+const ACL_MASK: u16 = 0x10;
+const ACL_OTHER: u16 = 0x20;
+const ENTRY_LEN: usize = 8;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum AclType {
+    /// Checked when the inode is accessed.
+    Access,
+    /// Inherited by entries created in a directory.
+    Default,
+}
+
+impl AclType {
+    pub fn xattr_name(self) -> &'static str {
+        match self {
+            AclType::Access => ACL_ACCESS_XATTR,
+            AclType::Default => ACL_DEFAULT_XATTR,
+        }
+    }
+
+    pub fn from_xattr_name(name: &str) -> Option<Self> {
+        match name {
+            ACL_ACCESS_XATTR => Some(AclType::Access),
+            ACL_DEFAULT_XATTR => Some(AclType::Default),
+            _ => None,
+        }
+    }
+}
+
+/// Permissions granted to a named user or group.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct NamedEntry {
+    pub id: u32,
+    /// `rwx` bits.
+    pub perm: u16,
+}
+
+/// A POSIX ACL. Permissions are `rwx` bits (`0o7` at most).
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct AclRule {
+    pub owner: u16,
+    /// Owning group.
+    pub group: u16,
+    /// Upper bound for named entries and the owning group; required as soon
+    /// as there are named entries.
+    pub mask: Option<u16>,
+    pub other: u16,
+    /// Sorted by id.
+    pub named_users: Vec<NamedEntry>,
+    /// Sorted by id.
+    pub named_groups: Vec<NamedEntry>,
+}
+
+fn invalid(msg: &str) -> MetaError {
+    MetaError::InvalidAcl(msg.to_string())
+}
+
+impl AclRule {
+    /// The minimal ACL equivalent to the permission bits of `mode`.
+    pub fn from_mode(mode: u32) -> Self {
+        Self {
+            owner: ((mode >> 6) & 0o7) as u16,
+            group: ((mode >> 3) & 0o7) as u16,
+            mask: None,
+            other: (mode & 0o7) as u16,
+            named_users: Vec::new(),
+            named_groups: Vec::new(),
+        }
+    }
+
+    /// Whether the ACL has no named entries and so is fully described by
+    /// the permission bits of the mode.
+    pub fn is_minimal(&self) -> bool {
+        self.named_users.is_empty() && self.named_groups.is_empty()
+    }
+
+    /// Permission bits of the mode matching this ACL.
+    pub fn mode_bits(&self) -> u32 {
+        (u32::from(self.owner) << 6)
+            | (u32::from(self.mask.unwrap_or(self.group)) << 3)
+            | u32::from(self.other)
+    }
+
+    /// Applies a change of the permission bits to the ACL, the way `chmod`
+    /// does: the group bits go to the mask if there is one.
+    pub fn chmod(&mut self, mode: u32) {
+        self.owner = ((mode >> 6) & 0o7) as u16;
+        let group = ((mode >> 3) & 0o7) as u16;
+        match &mut self.mask {
+            Some(mask) => *mask = group,
+            None => self.group = group,
+        }
+        self.other = (mode & 0o7) as u16;
+    }
+
+    /// Whether `uid` with the groups `gids` gets all of `want` on an inode
+    /// owned by `owner_uid`/`owner_gid`. Named entries and the owning group
+    /// are limited by the mask; a matching group entry that does not grant
+    /// `want` denies even if the other entry would.
+    pub fn allows(
+        &self,
+        owner_uid: u32,
+        owner_gid: u32,
+        uid: u32,
+        gids: &[u32],
+        want: u16,
+    ) -> bool {
+        let grants = |perm: u16| perm & want == want;
+        let mask = self.mask.unwrap_or(0o7);
+        if uid == owner_uid {
+            return grants(self.owner);
+        }
+        if let Some(entry) = self.named_users.iter().find(|entry| entry.id == uid) {
+            return grants(entry.perm & mask);
+        }
+        let mut group_matched = false;
+        if gids.contains(&owner_gid) {
+            group_matched = true;
+            if grants(self.group & mask) {
+                return true;
+            }
+        }
+        for entry in &self.named_groups {
+            if gids.contains(&entry.id) {
+                group_matched = true;
+                if grants(entry.perm & mask) {
+                    return true;
+                }
+            }
+        }
+        !group_matched && grants(self.other)
+    }
+
+    /// Parses the kernel's `system.posix_acl_*` attribute format.
+    pub fn from_xattr(value: &[u8]) -> Result<Self, MetaError> {
+        let version = value
+            .get(..4)
+            .map(|v| u32::from_le_bytes(v.try_into().unwrap()))
+            .ok_or_else(|| invalid("missing header"))?;
+        if version != ACL_XATTR_VERSION {
+            return Err(invalid("unsupported version"));
+        }
+        let entries = &value[4..];
+        if entries.len() % ENTRY_LEN != 0 {
+            return Err(invalid("truncated entry"));
+        }
+
+        let (mut owner, mut group, mut other) = (None, None, None);
+        let mut rule = AclRule::default();
+        for entry in entries.chunks_exact(ENTRY_LEN) {
+            let tag = u16::from_le_bytes([entry[0], entry[1]]);
+            let perm = u16::from_le_bytes([entry[2], entry[3]]);
+            let id = u32::from_le_bytes([entry[4], entry[5], entry[6], entry[7]]);
+            if perm & !0o7 != 0 {
+                return Err(invalid("invalid permissions"));
+            }
+            let slot = match tag {
+                ACL_USER_OBJ => &mut owner,
+                ACL_GROUP_OBJ => &mut group,
+                ACL_OTHER => &mut other,
+                ACL_MASK => &mut rule.mask,
+                ACL_USER => {
+                    rule.named_users.push(NamedEntry { id, perm });
+                    continue;
+                }
+                ACL_GROUP => {
+                    rule.named_groups.push(NamedEntry { id, perm });
+                    continue;
+                }
+                _ => return Err(invalid("unknown tag")),
+            };
+            if slot.replace(perm).is_some() {
+                return Err(invalid("duplicate entry"));
+            }
+        }
+
+        for named in [&mut rule.named_users, &mut rule.named_groups] {
+            named.sort_by_key(|entry| entry.id);
+            if named.windows(2).any(|pair| pair[0].id == pair[1].id) {
+                return Err(invalid("duplicate entry"));
+            }
+        }
+        if !rule.is_minimal() && rule.mask.is_none() {
+            return Err(invalid("named entries need a mask"));
+        }
+        match (owner, group, other) {
+            (Some(owner), Some(group), Some(other)) => {
+                rule.owner = owner;
+                rule.group = group;
+                rule.other = other;
+                Ok(rule)
+            }
+            _ => Err(invalid("missing entry")),
+        }
+    }
+
+    /// Encodes the ACL in the kernel's `system.posix_acl_*` attribute format.
+    pub fn to_xattr(&self) -> Vec<u8> {
+        let mut entries = vec![(ACL_USER_OBJ, self.owner, ACL_UNDEFINED_ID)];
+        entries.extend(self.named_users.iter().map(|e| (ACL_USER, e.perm, e.id)));
+        entries.push((ACL_GROUP_OBJ, self.group, ACL_UNDEFINED_ID));
+        entries.extend(self.named_groups.iter().map(|e| (ACL_GROUP, e.perm, e.id)));
+        if let Some(mask) = self.mask {
+            entries.push((ACL_MASK, mask, ACL_UNDEFINED_ID));
+        }
+        entries.push((ACL_OTHER, self.other, ACL_UNDEFINED_ID));
+
+        let mut out = Vec::with_capacity(4 + entries.len() * ENTRY_LEN);
+        out.extend_from_slice(&ACL_XATTR_VERSION.to_le_bytes());
+        for (tag, perm, id) in entries {
+            out.extend_from_slice(&tag.to_le_bytes());
+            out.extend_from_slice(&perm.to_le_bytes());
+            out.extend_from_slice(&id.to_le_bytes());
+        }
+        out
+    }
+
+    /// The access ACL of an entry created with `mode` in a directory with
+    /// this default ACL, and the mode it ends up with. Like the umask, the
+    /// ACL can only take permissions away from `mode`.
+    pub fn inherit(&self, mode: u32) -> (AclRule, u32) {
+        let mut access = self.clone();
+        access.owner &= ((mode >> 6) & 0o7) as u16;
+        let group = ((mode >> 3) & 0o7) as u16;
+        match &mut access.mask {
+            Some(mask) => *mask &= group,
+            None => access.group &= group,
+        }
+        access.other &= (mode & 0o7) as u16;
+        let mode = (mode & !0o777) | access.mode_bits();
+        (access, mode)
+    }
+}
+
+pub async fn get_acl<S>(
+    store: &S,
+    inode: i64,
+    acl_type: AclType,
+) -> Result<Option<AclRule>, MetaError>
+where
+    S: MetaStore + ?Sized,
+{
+    match store.get_xattr(inode, acl_type.xattr_name()).await {
+        Ok(Some(value)) => AclRule::from_xattr(&value).map(Some),
+        Ok(None) => Ok(None),
+        // Without extended attributes there cannot be any ACL.
+        Err(MetaError::NotImplemented) => Ok(None),
+        Err(err) => Err(err),
+    }
+}
+
+async fn remove_acl<S>(store: &S, inode: i64, acl_type: AclType) -> Result<(), MetaError>
+where
+    S: MetaStore + ?Sized,
+{
+    match store.remove_xattr(inode, acl_type.xattr_name()).await {
+        Ok(()) | Err(MetaError::NotFound(_)) => Ok(()),
+        Err(err) => Err(err),
+    }
+}
+
+pub async fn set_acl<S>(
+    store: &S,
+    inode: i64,
+    acl_type: AclType,
+    rule: Option<&AclRule>,
+) -> Result<(), MetaError>
+where
+    S: MetaStore + ?Sized,
+{
+    let attr = store.stat(inode).await?.ok_or(MetaError::NotFound(inode))?;
+    match (acl_type, rule) {
+        (AclType::Default, Some(_)) if attr.kind != FileType::Dir => {
+            Err(MetaError::NotDirectory(inode))
+        }
+        (_, None) => remove_acl(store, inode, acl_type).await,
+        (AclType::Default, Some(rule)) => {
+            store
+                .set_xattr(inode, ACL_DEFAULT_XATTR, &rule.to_xattr(), 0)
+                .await
+        }
+        (AclType::Access, Some(rule)) => {
+            if rule.is_minimal() {
+                remove_acl(store, inode, acl_type).await?;
+            } else {
+                store
+                    .set_xattr(inode, ACL_ACCESS_XATTR, &rule.to_xattr(), 0)
+                    .await?;
+            }
+            if attr.mode & 0o777 != rule.mode_bits() {
+                store
+                    .set_attr(
+                        inode,
+                        &chmod_request(rule.mode_bits()),
+                        SetAttrFlags::empty(),
+                    )
+                    .await?;
+            }
+            Ok(())
+        }
+    }
+}
+
+/// Brings the access ACL of `inode` in line with the new permission bits of
+/// `mode` after a `chmod`.
+pub async fn sync_mode<S>(store: &S, inode: i64, mode: u32) -> Result<(), MetaError>
+where
+    S: MetaStore + ?Sized,
+{
+    let Some(mut rule) = get_acl(store, inode, AclType::Access).await? else {
+        return Ok(());
+    };
+    rule.chmod(mode);
+    store
+        .set_xattr(inode, ACL_ACCESS_XATTR, &rule.to_xattr(), 0)
+        .await
+}
+
+/// Gives the new entry `inode` in `parent` the ACLs inherited from the
+/// parent's default ACL and returns its creation `mode` restricted by them.
+/// Without a default ACL `mode` comes back unchanged.
+pub async fn inherit_acl<S>(store: &S, parent: i64, inode: i64, mode: u32) -> Result<u32, MetaError>
+where
+    S: MetaStore + ?Sized,
+{
+    let Some(default) = get_acl(store, parent, AclType::Default).await? else {
+        return Ok(mode);
+    };
+    let attr = store.stat(inode).await?.ok_or(MetaError::NotFound(inode))?;
+    if attr.kind == FileType::Dir {
+        store
+            .set_xattr(inode, ACL_DEFAULT_XATTR, &default.to_xattr(), 0)
+            .await?;
+    }
+    if attr.kind == FileType::Symlink {
+        return Ok(mode);
+    }
+    let (access, mode) = default.inherit(mode);
+    if !access.is_minimal() {
+        store
+            .set_xattr(inode, ACL_ACCESS_XATTR, &access.to_xattr(), 0)
+            .await?;
+    }
+    Ok(mode)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn extended() -> AclRule {
+        AclRule {
+            owner: 0o7,
+            group: 0o5,
+            mask: Some(0o7),
+            other: 0o0,
+            named_users: vec![NamedEntry {
+                id: 1001,
+                perm: 0o6,
+            }],
+            named_groups: vec![NamedEntry {
+                id: 2001,
+                perm: 0o4,
+            }],
+        }
+    }
+
+    #[test]
+    fn xattr_round_trip() {
+        let rule = extended();
+        assert_eq!(AclRule::from_xattr(&rule.to_xattr()).unwrap(), rule);
+
+        let minimal = AclRule::from_mode(0o640);
+        assert!(minimal.is_minimal());
+        assert_eq!(AclRule::from_xattr(&minimal.to_xattr()).unwrap(), minimal);
+        assert_eq!(minimal.mode_bits(), 0o640);
+
+        assert!(AclRule::from_xattr(&[2, 0, 0, 0, 1]).is_err());
+        let mut no_mask = extended();
+        no_mask.mask = None;
+        assert!(AclRule::from_xattr(&no_mask.to_xattr()).is_err());
+    }
+
+    #[test]
+    fn access_checks_follow_posix() {
+        let rule = extended();
+        let (owner, group) = (1000, 100);
+        assert!(rule.allows(owner, group, owner, &[], 0o7));
+        // Named users are limited by the mask.
+        assert!(rule.allows(owner, group, 1001, &[], 0o6));
+        let mut narrowed = rule.clone();
+        narrowed.mask = Some(0o4);
+        assert!(!narrowed.allows(owner, group, 1001, &[], 0o2));
+        // A matching group that lacks the permission does not fall back to other.
+        assert!(rule.allows(owner, group, 1002, &[2001], 0o4));
+        assert!(!rule.allows(owner, group, 1002, &[2001], 0o2));
+        assert!(!rule.allows(owner, group, 1002, &[], 0o4));
+    }
+
+    #[test]
+    fn chmod_and_inherit_keep_mode_in_sync() {
+        let mut rule = extended();
+        rule.chmod(0o750);
+        assert_eq!(rule.mask, Some(0o5));
+        assert_eq!(rule.group, 0o5);
+        assert_eq!(rule.mode_bits(), 0o750);
+
+        let (access, mode) = extended().inherit(0o100644);
+        assert_eq!(mode, 0o100640);
+        assert_eq!(access.owner, 0o6);
+        assert_eq!(access.mask, Some(0o4));
+        assert_eq!(access.named_users, extended().named_users);
+    }
+}
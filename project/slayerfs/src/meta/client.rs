@@ -4,14 +4,19 @@ mod path_trie;
 pub mod session;
 
 use crate::chuck::SliceDesc;
+use crate::meta::acl::{self, AclRule, AclType};
 use crate::meta::config::{CacheCapacity, CacheTtl};
 use crate::meta::file_lock::{FileLockInfo, FileLockQuery, FileLockRange, FileLockType};
 use crate::meta::layer::MetaLayer;
+use crate::meta::quota::{
+    QuotaCache, QuotaKey, QuotaMove, charged_space, subtree_usage, tree_usage,
+};
 use crate::meta::store::{
-    AclRule, DirEntry, FileAttr, MetaError, MetaStore, OpenFlags, SetAttrFlags, SetAttrRequest,
-    StatFsSnapshot,
+    CloneOption, DelayedSlice, DirEntry, FileAttr, LockName, MetaError, MetaStore, OpenFlags,
+    QuotaType, SetAttrFlags, SetAttrRequest, StatFsSnapshot,
 };
 use crate::meta::stores::{CacheInvalidationEvent, EtcdMetaStore, EtcdWatchWorker, WatchConfig};
+use crate::meta::trash;
 use crate::posix::NAME_MAX;
 use crate::vfs::fs::FileType;
 use crate::vfs::handles::DirHandle;
@@ -22,7 +27,7 @@ use if_addrs::get_if_addrs;
 use moka::future::Cache;
 use std::sync::Arc;
 use std::sync::atomic::{AtomicBool, AtomicI64, Ordering};
-use std::time::Duration;
+use std::time::{Duration, SystemTime};
 use std::{collections::HashSet, process};
 use tokio::sync::{Mutex, mpsc};
 use tracing::{Instrument, debug, info, trace, warn};
@@ -37,6 +42,12 @@ use session::{SessionInfo, SessionManager};
 
 const ROOT_INODE: i64 = 1;
 
+/// How often locally accumulated quota usage is flushed and quotas reloaded.
+const QUOTA_SYNC_INTERVAL: Duration = Duration::from_secs(3);
+
+/// How often expired trash batches are removed.
+const TRASH_SWEEP_INTERVAL: Duration = Duration::from_secs(600);
+
 /// Configuration options for `MetaClient` that correspond to the core metadata
 /// behaviours implemented by the Go `baseMeta`. Only a minimal subset of
 /// fields is supported for now; additional knobs can be added as the Rust
@@ -59,6 +70,9 @@ pub struct MetaClientOptions {
     pub max_symlinks: usize,
     /// Batch attribute prefetch configuration
     pub batch_prefetch: BatchPrefetchConfig,
+    /// How long removed files are kept in the trash before they are deleted;
+    /// `None` deletes them right away.
+    pub trash_retention: Option<Duration>,
 }
 
 /// Configuration for batch attribute prefetching during opendir
@@ -131,6 +145,7 @@ impl Default for MetaClientOptions {
             case_insensitive: false,
             max_symlinks: 40,
             batch_prefetch: BatchPrefetchConfig::default(),
+            trash_retention: None,
         }
     }
 }
@@ -160,6 +175,12 @@ pub struct MetaClient<T: MetaStore> {
     /// it's absolute path.
     inode_to_paths: Arc<DashMap<i64, Vec<String>>>,
 
+    /// ACLs by inode and type; `None` records that there is no ACL.
+    acl_cache: Cache<(i64, AclType), Option<AclRule>>,
+
+    /// Directory, user and group quotas plus usage not flushed to the store yet.
+    quotas: Arc<QuotaCache>,
+
     /// Manages background session heartbeats when enabled by callers.
     #[allow(dead_code)]
     session_manager: Arc<SessionManager<T>>,
@@ -249,6 +270,11 @@ impl<T: MetaStore + 'static> MetaClient<T> {
                 .build(),
             path_trie: Arc::new(PathTrie::new()),
             inode_to_paths: Arc::new(DashMap::new()),
+            acl_cache: Cache::builder()
+                .max_capacity(capacity.inode as u64)
+                .time_to_live(ttl.inode_ttl)
+                .build(),
+            quotas: Arc::new(QuotaCache::new()),
             session_manager: Arc::new(SessionManager::new(store.clone())),
             watch_worker: watch_worker.as_ref().map(|(w, _)| w.clone()),
         });
@@ -268,6 +294,10 @@ impl<T: MetaStore + 'static> MetaClient<T> {
                     warn!("MetaClient: failed to auto-start session: {err}");
                 }
             });
+            Self::spawn_quota_sync(&client);
+            if client.options.trash_retention.is_some() {
+                Self::spawn_trash_sweeper(&client);
+            }
         }
 
         client
@@ -359,6 +389,9 @@ impl<T: MetaStore + 'static> MetaClient<T> {
     #[allow(dead_code)]
     pub async fn shutdown_session(&self) {
         self.mark_umounting();
+        if let Err(err) = self.flush_quota_usage().await {
+            warn!("MetaClient: failed to flush quota usage: {err}");
+        }
         self.session_manager.shutdown().await;
     }
 
@@ -432,6 +465,7 @@ impl<T: MetaStore + 'static> MetaClient<T> {
             match event {
                 CacheInvalidationEvent::InvalidateInode(ino) => {
                     self.inode_cache.invalidate_inode(ino).await;
+                    self.invalidate_acls(ino).await;
 
                     if let Some(paths_entry) = self.inode_to_paths.get(&ino) {
                         for path in paths_entry.value() {
@@ -502,6 +536,11 @@ impl<T: MetaStore + 'static> MetaClient<T> {
         Ok(())
     }
 
+    async fn invalidate_acls(&self, ino: i64) {
+        self.acl_cache.invalidate(&(ino, AclType::Access)).await;
+        self.acl_cache.invalidate(&(ino, AclType::Default)).await;
+    }
+
     /// Intelligently invalidates path cache entries for a parent directory.
     ///
     /// # Strategy (Trie-based approach)
@@ -971,6 +1010,338 @@ impl<T: MetaStore + 'static> MetaClient<T> {
 
         (done_flag, task)
     }
+
+    /// Flushes the quota usage accumulated locally and reloads all quotas from
+    /// the store.
+    pub async fn sync_quotas(&self) -> Result<(), MetaError> {
+        self.flush_quota_usage().await?;
+        let (dirs, users, groups) = self.store.load_quotas().await?;
+        self.quotas.replace(dirs, users, groups);
+        Ok(())
+    }
+
+    async fn flush_quota_usage(&self) -> Result<(), MetaError> {
+        let deltas = self.quotas.take_pending();
+        if deltas.is_empty() {
+            return Ok(());
+        }
+        if let Err(err) = self.store.flush_quotas(&deltas).await {
+            // None of the deltas were applied: retry all of them later.
+            self.quotas.restore_pending(deltas);
+            return Err(err);
+        }
+        Ok(())
+    }
+
+    fn spawn_quota_sync(client: &Arc<Self>) {
+        let client = Arc::downgrade(client);
+        tokio::spawn(async move {
+            let mut ticker = tokio::time::interval(QUOTA_SYNC_INTERVAL);
+            ticker.tick().await;
+            loop {
+                ticker.tick().await;
+                let Some(client) = client.upgrade() else {
+                    break;
+                };
+                match client.sync_quotas().await {
+                    Ok(()) | Err(MetaError::NotImplemented) => {}
+                    Err(err) => warn!("MetaClient: failed to sync quotas: {err}"),
+                }
+            }
+        });
+    }
+
+    fn spawn_trash_sweeper(client: &Arc<Self>) {
+        let client = Arc::downgrade(client);
+        tokio::spawn(async move {
+            let mut ticker = tokio::time::interval(TRASH_SWEEP_INTERVAL);
+            loop {
+                ticker.tick().await;
+                let Some(client) = client.upgrade() else {
+                    break;
+                };
+                if let Err(err) = client.sweep_trash().await {
+                    warn!("MetaClient: failed to clean up the trash: {err}");
+                }
+            }
+        });
+    }
+
+    /// Deletes the trash entries older than the retention period; returns the
+    /// number of entries removed.
+    pub async fn sweep_trash(&self) -> Result<i32, MetaError> {
+        let Some(retention) = self.options.trash_retention else {
+            return Ok(0);
+        };
+        if !self.store.get_global_lock(LockName::CleanupTrashLock).await {
+            return Ok(0);
+        }
+        let edge = Utc::now().timestamp_nanos_opt().unwrap_or(i64::MAX)
+            - retention.as_nanos().min(i64::MAX as u128) as i64;
+        let removed = self.store.cleanup_delayed_slices(edge).await?;
+        if removed > 0
+            && let Some(trash) = trash::trash_dir(self.store.as_ref()).await?
+        {
+            self.inode_cache.invalidate_inode(trash).await;
+            self.invalidate_parent_path(trash).await;
+        }
+        Ok(removed)
+    }
+
+    /// Moves the last link of the non-directory `attr`, `parent/name`, to the
+    /// trash if it is enabled; returns whether it did.
+    async fn move_to_trash(
+        &self,
+        parent: i64,
+        name: &str,
+        attr: &FileAttr,
+    ) -> Result<bool, MetaError> {
+        if self.options.trash_retention.is_none()
+            || attr.kind == FileType::Dir
+            || attr.nlink > 1
+            || trash::is_trash_dir(self.store.as_ref(), parent).await?
+        {
+            return Ok(false);
+        }
+
+        let (_, batch) = trash::batch_dir(self.store.as_ref(), SystemTime::now()).await?;
+        let entry = trash::entry_name(parent, attr.ino, name);
+        self.store
+            .rename(parent, name, batch, entry.clone())
+            .await?;
+        // Trashed files no longer count against any quota.
+        self.charge_entry(parent, attr, -1, true).await;
+
+        self.inode_cache.remove_child(parent, name).await;
+        self.inode_cache.invalidate_inode(batch).await;
+        self.inode_cache.invalidate_inode(attr.ino).await;
+        self.invalidate_parent_path(parent).await;
+        Ok(true)
+    }
+
+    /// Directory quotas covering entries of `dir`: `dir` itself and all of
+    /// its ancestors.
+    async fn dir_quota_keys(&self, dir: i64) -> Result<Vec<QuotaKey>, MetaError> {
+        let mut keys = Vec::new();
+        if !self.quotas.has_type(QuotaType::Dir) {
+            return Ok(keys);
+        }
+
+        let mut ino = dir;
+        loop {
+            let key = (QuotaType::Dir, ino as u64);
+            if self.quotas.contains(&key) {
+                keys.push(key);
+            }
+            if ino == ROOT_INODE {
+                break;
+            }
+            match self.store.get_dir_parent(ino).await? {
+                Some(parent) if parent != ino => ino = parent,
+                _ => break,
+            }
+        }
+        Ok(keys)
+    }
+
+    /// Directory quotas an existing inode's size is charged to. Files with
+    /// several hard links are only tracked by their owner quotas.
+    async fn inode_dir_quota_keys(&self, attr: &FileAttr) -> Result<Vec<QuotaKey>, MetaError> {
+        if !self.quotas.has_type(QuotaType::Dir) {
+            return Ok(Vec::new());
+        }
+        let parent = if attr.kind == FileType::Dir {
+            self.store.get_dir_parent(attr.ino).await?
+        } else if attr.nlink <= 1 {
+            self.store
+                .get_dentries(attr.ino)
+                .await?
+                .first()
+                .map(|(parent, _)| *parent)
+        } else {
+            None
+        };
+        match parent {
+            Some(parent) => self.dir_quota_keys(parent).await,
+            None => Ok(Vec::new()),
+        }
+    }
+
+    /// Fails with [`MetaError::QuotaExceeded`] if `parent` has no room for
+    /// another entry of `space` bytes.
+    async fn check_entry_quota(&self, parent: i64, space: i64) -> Result<(), MetaError> {
+        if self.quotas.is_empty() {
+            return Ok(());
+        }
+        let keys = self.dir_quota_keys(parent).await?;
+        self.quotas.check(&keys, space, 1)
+    }
+
+    /// Charges (`sign` = 1) or releases (`sign` = -1) an entry for `attr` in
+    /// `parent`. Owner quotas count inodes rather than links, so they are only
+    /// touched when `owners` is set.
+    async fn charge_entry(&self, parent: i64, attr: &FileAttr, sign: i64, owners: bool) {
+        if self.quotas.is_empty() {
+            return;
+        }
+        let mut keys = match self.dir_quota_keys(parent).await {
+            Ok(keys) => keys,
+            Err(err) => {
+                warn!("MetaClient: failed to resolve quotas of directory {parent}: {err}");
+                Vec::new()
+            }
+        };
+        if owners {
+            keys.push((QuotaType::User, attr.uid as u64));
+            keys.push((QuotaType::Group, attr.gid as u64));
+        }
+        self.quotas.charge(&keys, sign * charged_space(attr), sign);
+    }
+
+    /// Usage changes caused by turning the attributes `old` into `new`, as
+    /// (quotas, space, inodes) triples.
+    async fn attr_quota_deltas(
+        &self,
+        old: &FileAttr,
+        new: &FileAttr,
+    ) -> Result<Vec<(Vec<QuotaKey>, i64, i64)>, MetaError> {
+        let mut deltas = Vec::new();
+        if self.quotas.is_empty() {
+            return Ok(deltas);
+        }
+
+        let (old_space, new_space) = (charged_space(old), charged_space(new));
+        if new_space != old_space {
+            let keys = self.inode_dir_quota_keys(old).await?;
+            deltas.push((keys, new_space - old_space, 0));
+        }
+        for (qtype, old_id, new_id) in [
+            (QuotaType::User, old.uid, new.uid),
+            (QuotaType::Group, old.gid, new.gid),
+        ] {
+            let (old_key, new_key) = ((qtype, old_id as u64), (qtype, new_id as u64));
+            if old_key == new_key {
+                deltas.push((vec![new_key], new_space - old_space, 0));
+            } else {
+                deltas.push((vec![old_key], -old_space, -1));
+                deltas.push((vec![new_key], new_space, 1));
+            }
+        }
+        Ok(deltas)
+    }
+
+    async fn check_attr_quota(&self, old: &FileAttr, new: &FileAttr) -> Result<(), MetaError> {
+        for (keys, space, inodes) in self.attr_quota_deltas(old, new).await? {
+            self.quotas.check(&keys, space, inodes)?;
+        }
+        Ok(())
+    }
+
+    async fn charge_attr_quota(&self, old: &FileAttr, new: &FileAttr) {
+        match self.attr_quota_deltas(old, new).await {
+            Ok(deltas) => {
+                for (keys, space, inodes) in deltas {
+                    self.quotas.charge(&keys, space, inodes);
+                }
+            }
+            Err(err) => warn!(
+                "MetaClient: failed to update quotas of inode {}: {err}",
+                old.ino
+            ),
+        }
+    }
+
+    /// Attributes to account a size change against, if quotas are in use.
+    async fn quota_attr(&self, ino: i64) -> Result<Option<FileAttr>, MetaError> {
+        if self.quotas.is_empty() {
+            return Ok(None);
+        }
+        self.cached_stat(ino).await
+    }
+
+    /// Checks that a clone of `src` fits into the quotas of `parent` and its
+    /// owners and returns the usage to charge once it is done.
+    async fn plan_clone_quota(
+        &self,
+        src: i64,
+        parent: i64,
+        opt: &CloneOption,
+    ) -> Result<Vec<(Vec<QuotaKey>, i64, i64)>, MetaError> {
+        let mut charges = Vec::new();
+        let Some(attr) = self.quota_attr(src).await? else {
+            return Ok(charges);
+        };
+        let usage = tree_usage(self.store.as_ref(), &attr).await?;
+
+        charges.push((
+            self.dir_quota_keys(parent).await?,
+            usage.total.space,
+            usage.total.inodes,
+        ));
+        if opt.preserve_attr {
+            for (uid, stat) in usage.users {
+                charges.push((vec![(QuotaType::User, uid as u64)], stat.space, stat.inodes));
+            }
+            for (gid, stat) in usage.groups {
+                charges.push((
+                    vec![(QuotaType::Group, gid as u64)],
+                    stat.space,
+                    stat.inodes,
+                ));
+            }
+        } else {
+            let owners = vec![
+                (QuotaType::User, opt.uid as u64),
+                (QuotaType::Group, opt.gid as u64),
+            ];
+            charges.push((owners, usage.total.space, usage.total.inodes));
+        }
+
+        for (keys, space, inodes) in &charges {
+            self.quotas.check(keys, *space, *inodes)?;
+        }
+        Ok(charges)
+    }
+
+    /// Usage that leaves the directory quotas of `old_parent` and enters those
+    /// of `new_parent` when `attr` is moved between them.
+    async fn plan_quota_move(
+        &self,
+        attr: &FileAttr,
+        old_parent: i64,
+        new_parent: i64,
+    ) -> Result<Option<QuotaMove>, MetaError> {
+        if old_parent == new_parent || !self.quotas.has_type(QuotaType::Dir) {
+            return Ok(None);
+        }
+        let old_keys = self.dir_quota_keys(old_parent).await?;
+        let new_keys = self.dir_quota_keys(new_parent).await?;
+        let from: Vec<QuotaKey> = old_keys
+            .iter()
+            .filter(|key| !new_keys.contains(key))
+            .copied()
+            .collect();
+        let to: Vec<QuotaKey> = new_keys
+            .into_iter()
+            .filter(|key| !old_keys.contains(key))
+            .collect();
+        if from.is_empty() && to.is_empty() {
+            return Ok(None);
+        }
+
+        let (space, inodes) = if attr.kind == FileType::Dir {
+            let usage = subtree_usage(self.store.as_ref(), attr.ino).await?;
+            (usage.space, usage.inodes + 1)
+        } else {
+            (charged_space(attr), 1)
+        };
+        Ok(Some(QuotaMove {
+            from,
+            to,
+            space,
+            inodes,
+        }))
+    }
 }
 
 #[async_trait]
@@ -990,7 +1361,12 @@ impl<T: MetaStore + 'static> MetaLayer for MetaClient<T> {
 
     #[tracing::instrument(level = "trace", skip(self))]
     async fn initialize(&self) -> Result<(), MetaError> {
-        self.store.initialize().await
+        self.store.initialize().await?;
+        match self.sync_quotas().await {
+            Ok(()) | Err(MetaError::NotImplemented) => {}
+            Err(err) => warn!("MetaClient: failed to load quotas: {err}"),
+        }
+        Ok(())
     }
 
     #[tracing::instrument(level = "trace", skip(self))]
@@ -1128,6 +1504,7 @@ impl<T: MetaStore + 'static> MetaLayer for MetaClient<T> {
 
         info!("MetaClient: mkdir operation for ({}, '{}')", parent, name);
 
+        self.check_entry_quota(parent, 0).await?;
         let ino = self.store.mkdir(parent, name.clone()).await?;
 
         debug!("MetaClient: mkdir created inode {}, updating cache", ino);
@@ -1139,6 +1516,7 @@ impl<T: MetaStore + 'static> MetaLayer for MetaClient<T> {
 
         // Cache the new directory node
         if let Ok(Some(attr)) = self.store.stat(ino).await {
+            self.charge_entry(parent, &attr, 1, true).await;
             self.inode_cache.insert_node(ino, attr, Some(parent)).await;
         }
         self.inode_cache.add_child(parent, name, ino).await;
@@ -1154,10 +1532,33 @@ impl<T: MetaStore + 'static> MetaLayer for MetaClient<T> {
         let parent = self.check_root(parent);
         info!("MetaClient: rmdir operation for ({}, '{}')", parent, name);
 
+        let removed = if self.quotas.is_empty() {
+            None
+        } else {
+            match self.cached_lookup(parent, name).await? {
+                Some(ino) => self.cached_stat(ino).await?,
+                None => None,
+            }
+        };
+
         self.store.rmdir(parent, name).await?;
 
         debug!("MetaClient: rmdir completed, updating cache");
 
+        if let Some(attr) = removed {
+            self.charge_entry(parent, &attr, -1, true).await;
+            let key = (QuotaType::Dir, attr.ino as u64);
+            if self.quotas.contains(&key) {
+                self.quotas.remove(&key);
+                if let Err(err) = self.store.delete_quota(key.0.into(), key.1).await {
+                    warn!(
+                        "MetaClient: failed to delete quota of removed directory {}: {err}",
+                        attr.ino
+                    );
+                }
+            }
+        }
+
         self.inode_cache.remove_child(parent, name).await;
         self.invalidate_parent_path(parent).await;
 
@@ -1173,6 +1574,7 @@ impl<T: MetaStore + 'static> MetaLayer for MetaClient<T> {
             parent, name
         );
 
+        self.check_entry_quota(parent, 0).await?;
         let ino = self.store.create_file(parent, name.clone()).await?;
 
         info!(
@@ -1186,6 +1588,7 @@ impl<T: MetaStore + 'static> MetaLayer for MetaClient<T> {
             .await?;
 
         if let Ok(Some(attr)) = self.store.stat(ino).await {
+            self.charge_entry(parent, &attr, 1, true).await;
             let cache_parent = (attr.nlink <= 1).then_some(parent);
             self.inode_cache.insert_node(ino, attr, cache_parent).await;
         }
@@ -1214,7 +1617,11 @@ impl<T: MetaStore + 'static> MetaLayer for MetaClient<T> {
             inode, parent, name
         );
 
+        if let Some(attr) = self.quota_attr(inode).await? {
+            self.check_entry_quota(parent, charged_space(&attr)).await?;
+        }
         let attr = self.store.link(inode, parent, name).await?;
+        self.charge_entry(parent, &attr, 1, false).await;
 
         self.inode_cache
             .ensure_node_in_cache(parent, &self.store, None)
@@ -1259,7 +1666,9 @@ impl<T: MetaStore + 'static> MetaLayer for MetaClient<T> {
             parent, name, target
         );
 
+        self.check_entry_quota(parent, target.len() as i64).await?;
         let (ino, attr) = self.store.symlink(parent, name, target).await?;
+        self.charge_entry(parent, &attr, 1, true).await;
 
         debug!("MetaClient: symlink created inode {}, updating cache", ino);
 
@@ -1280,6 +1689,101 @@ impl<T: MetaStore + 'static> MetaLayer for MetaClient<T> {
         Ok((ino, attr))
     }
 
+    #[tracing::instrument(level = "trace", skip(self), fields(batch, entry))]
+    async fn restore_trash(&self, batch: &str, entry: &str) -> Result<FileAttr, MetaError> {
+        self.ensure_writable()?;
+
+        let (parent, _, name) = trash::parse_entry_name(entry)
+            .ok_or_else(|| MetaError::InvalidPath(format!("{batch}/{entry}")))?;
+        let trash = trash::trash_dir(self.store.as_ref())
+            .await?
+            .ok_or_else(|| MetaError::InvalidPath(format!("{batch}/{entry}")))?;
+        let batch_ino = self
+            .store
+            .lookup(trash, batch)
+            .await?
+            .ok_or(MetaError::NotFound(trash))?;
+        let ino = self
+            .store
+            .lookup(batch_ino, entry)
+            .await?
+            .ok_or(MetaError::NotFound(batch_ino))?;
+        let attr = self
+            .store
+            .stat(ino)
+            .await?
+            .ok_or(MetaError::NotFound(ino))?;
+
+        // The original directory may have been removed in the meantime.
+        match self.store.stat(parent).await? {
+            Some(dir) if dir.kind == FileType::Dir => {}
+            Some(_) => return Err(MetaError::NotDirectory(parent)),
+            None => return Err(MetaError::ParentNotFound(parent)),
+        }
+        if self.store.lookup(parent, &name).await?.is_some() {
+            return Err(MetaError::AlreadyExists { parent, name });
+        }
+        self.check_quota(parent, attr.uid, attr.gid, charged_space(&attr) as u64, 1)
+            .await?;
+
+        self.store
+            .rename(batch_ino, entry, parent, name.clone())
+            .await?;
+        self.charge_entry(parent, &attr, 1, true).await;
+
+        self.inode_cache.invalidate_inode(batch_ino).await;
+        self.inode_cache.invalidate_inode(ino).await;
+        self.inode_cache.add_child(parent, name, ino).await;
+        self.invalidate_parent_path(parent).await;
+
+        Ok(attr)
+    }
+
+    #[tracing::instrument(level = "trace", skip(self, opt), fields(src, parent, name))]
+    async fn clone_entry(
+        &self,
+        src: i64,
+        parent: i64,
+        name: &str,
+        opt: &CloneOption,
+    ) -> Result<FileAttr, MetaError> {
+        self.ensure_writable()?;
+        let src = self.check_root(src);
+        let parent = self.check_root(parent);
+
+        if name.is_empty() || name.len() > NAME_MAX {
+            return Err(MetaError::InvalidFilename);
+        }
+        if name.contains('/') || name.contains('\0') {
+            return Err(MetaError::InvalidFilename);
+        }
+
+        info!(
+            "MetaClient: clone operation for inode {} into ({}, '{}')",
+            src, parent, name
+        );
+
+        let charges = self.plan_clone_quota(src, parent, opt).await?;
+        let attr = self.store.clone_entry(src, parent, name, opt).await?;
+        for (keys, space, inodes) in &charges {
+            self.quotas.charge(keys, *space, *inodes);
+        }
+
+        self.inode_cache
+            .ensure_node_in_cache(parent, &self.store, None)
+            .await?;
+        self.inode_cache
+            .insert_node(attr.ino, attr.clone(), Some(parent))
+            .await;
+        self.inode_cache
+            .add_child(parent, name.to_string(), attr.ino)
+            .await;
+
+        self.invalidate_parent_path(parent).await;
+
+        Ok(attr)
+    }
+
     #[tracing::instrument(level = "trace", skip(self), fields(parent, name))]
     async fn unlink(&self, parent: i64, name: &str) -> Result<(), MetaError> {
         self.ensure_writable()?;
@@ -1295,8 +1799,26 @@ impl<T: MetaStore + 'static> MetaLayer for MetaClient<T> {
         let parent = self.check_root(parent);
         info!("MetaClient: unlink operation for ({}, '{}')", parent, name);
 
+        let removed = if self.quotas.is_empty() && self.options.trash_retention.is_none() {
+            None
+        } else {
+            match self.cached_lookup(parent, name).await? {
+                Some(ino) => self.store.stat(ino).await?,
+                None => None,
+            }
+        };
+        if let Some(attr) = &removed
+            && self.move_to_trash(parent, name, attr).await?
+        {
+            return Ok(());
+        }
+
         self.store.unlink(parent, name).await?;
 
+        if let Some(attr) = removed {
+            self.charge_entry(parent, &attr, -1, attr.nlink <= 1).await;
+        }
+
         debug!("MetaClient: unlink completed, updating cache");
 
         self.inode_cache.remove_child(parent, name).await;
@@ -1358,11 +1880,47 @@ impl<T: MetaStore + 'static> MetaLayer for MetaClient<T> {
             return Err(MetaError::InvalidFilename);
         }
 
+        let (quota_move, mut replaced) = match &src_attr {
+            Some(attr) if !self.quotas.is_empty() => {
+                let quota_move = self.plan_quota_move(attr, old_parent, new_parent).await?;
+                if let Some(quota_move) = &quota_move {
+                    self.quotas.check_move(quota_move)?;
+                }
+                let replaced = match self.cached_lookup(new_parent, &new_name).await? {
+                    Some(dest_ino) => self.store.stat(dest_ino).await?,
+                    None => None,
+                };
+                (quota_move, replaced)
+            }
+            _ => (None, None),
+        };
+
+        // A file about to be replaced goes to the trash first, so the rename
+        // below no longer replaces anything.
+        if self.options.trash_retention.is_some()
+            && let Some(src) = &src_attr
+            && src.kind != FileType::Dir
+            && let Some(dest_ino) = self.cached_lookup(new_parent, &new_name).await?
+            && dest_ino != src.ino
+            && let Some(dest) = self.store.stat(dest_ino).await?
+            && self.move_to_trash(new_parent, &new_name, &dest).await?
+        {
+            replaced = None;
+        }
+
         // Execute the store-level rename with atomic cache updates
         self.store
             .rename(old_parent, old_name, new_parent, new_name.clone())
             .await?;
 
+        if let Some(quota_move) = &quota_move {
+            self.quotas.apply_move(quota_move);
+        }
+        if let Some(attr) = replaced {
+            let last_link = attr.kind == FileType::Dir || attr.nlink <= 1;
+            self.charge_entry(new_parent, &attr, -1, last_link).await;
+        }
+
         debug!("MetaClient: rename completed, updating cache");
 
         // Update cache atomically with enhanced consistency management
@@ -1474,11 +2032,30 @@ impl<T: MetaStore + 'static> MetaLayer for MetaClient<T> {
             .await?
             .ok_or_else(|| MetaError::NotFound(new_parent))?;
 
+        let mut quota_moves = Vec::new();
+        if !self.quotas.is_empty() {
+            for (ino, from, to) in [
+                (old_ino, old_parent, new_parent),
+                (new_ino, new_parent, old_parent),
+            ] {
+                if let Some(attr) = self.cached_stat(ino).await?
+                    && let Some(quota_move) = self.plan_quota_move(&attr, from, to).await?
+                {
+                    self.quotas.check_move(&quota_move)?;
+                    quota_moves.push(quota_move);
+                }
+            }
+        }
+
         // Execute the store-level exchange
         self.store
             .rename_exchange(old_parent, old_name, new_parent, new_name)
             .await?;
 
+        for quota_move in &quota_moves {
+            self.quotas.apply_move(quota_move);
+        }
+
         debug!("MetaClient: rename_exchange completed, updating cache");
 
         // Update cache to reflect the exchange
@@ -1649,7 +2226,28 @@ impl<T: MetaStore + 'static> MetaLayer for MetaClient<T> {
     async fn set_file_size(&self, ino: i64, size: u64) -> Result<(), MetaError> {
         self.ensure_writable()?;
         let inode = self.check_root(ino);
+        let old = self.quota_attr(inode).await?;
+        if let Some(old) = &old {
+            self.check_attr_quota(
+                old,
+                &FileAttr {
+                    size,
+                    ..old.clone()
+                },
+            )
+            .await?;
+        }
         self.store.set_file_size(inode, size).await?;
+        if let Some(old) = &old {
+            self.charge_attr_quota(
+                old,
+                &FileAttr {
+                    size,
+                    ..old.clone()
+                },
+            )
+            .await;
+        }
 
         // Update cached attribute
         if let Some(node) = self.inode_cache.get_node(inode).await {
@@ -1664,7 +2262,20 @@ impl<T: MetaStore + 'static> MetaLayer for MetaClient<T> {
     async fn extend_file_size(&self, ino: i64, size: u64) -> Result<(), MetaError> {
         self.ensure_writable()?;
         let inode = self.check_root(ino);
+        let old = self.quota_attr(inode).await?;
         self.store.extend_file_size(inode, size).await?;
+        if let Some(old) = &old
+            && size > old.size
+        {
+            self.charge_attr_quota(
+                old,
+                &FileAttr {
+                    size,
+                    ..old.clone()
+                },
+            )
+            .await;
+        }
 
         if let Some(node) = self.inode_cache.get_node(inode).await {
             let mut attr = node.attr.write().await;
@@ -1680,7 +2291,28 @@ impl<T: MetaStore + 'static> MetaLayer for MetaClient<T> {
     async fn truncate(&self, ino: i64, size: u64, chunk_size: u64) -> Result<(), MetaError> {
         self.ensure_writable()?;
         let inode = self.check_root(ino);
+        let old = self.quota_attr(inode).await?;
+        if let Some(old) = &old {
+            self.check_attr_quota(
+                old,
+                &FileAttr {
+                    size,
+                    ..old.clone()
+                },
+            )
+            .await?;
+        }
         self.store.truncate(inode, size, chunk_size).await?;
+        if let Some(old) = &old {
+            self.charge_attr_quota(
+                old,
+                &FileAttr {
+                    size,
+                    ..old.clone()
+                },
+            )
+            .await;
+        }
         self.inode_cache.invalidate_inode(inode).await;
         Ok(())
     }
@@ -1745,7 +2377,24 @@ impl<T: MetaStore + 'static> MetaLayer for MetaClient<T> {
     ) -> Result<FileAttr, MetaError> {
         self.ensure_writable()?;
         let inode = self.check_root(ino);
+        let old = self.quota_attr(inode).await?;
+        if let Some(old) = &old {
+            let new = FileAttr {
+                size: req.size.unwrap_or(old.size),
+                uid: req.uid.unwrap_or(old.uid),
+                gid: req.gid.unwrap_or(old.gid),
+                ..old.clone()
+            };
+            self.check_attr_quota(old, &new).await?;
+        }
         let attr = self.store.set_attr(inode, req, flags).await?;
+        if let Some(old) = &old {
+            self.charge_attr_quota(old, &attr).await;
+        }
+        if req.mode.is_some() && attr.kind != FileType::Symlink {
+            acl::sync_mode(self.store.as_ref(), inode, attr.mode).await?;
+            self.acl_cache.invalidate(&(inode, AclType::Access)).await;
+        }
         self.inode_cache
             .insert_node(inode, attr.clone(), None)
             .await;
@@ -1785,7 +2434,17 @@ impl<T: MetaStore + 'static> MetaLayer for MetaClient<T> {
     ) -> Result<(), MetaError> {
         self.ensure_writable()?;
         let inode = self.check_root(ino);
+        let old = self.quota_attr(inode).await?;
         self.store.write(inode, chunk_id, slice, new_size).await?;
+        if let Some(old) = &old
+            && new_size > old.size
+        {
+            let new = FileAttr {
+                size: new_size,
+                ..old.clone()
+            };
+            self.charge_attr_quota(old, &new).await;
+        }
 
         let (inode_from_chunk, chunk_index) = extract_ino_and_chunk_index(chunk_id);
         self.inode_cache
@@ -1802,6 +2461,41 @@ impl<T: MetaStore + 'static> MetaLayer for MetaClient<T> {
         Ok(())
     }
 
+    #[tracing::instrument(level = "trace", skip(self), fields(parent, uid, gid, space, inodes))]
+    async fn check_quota(
+        &self,
+        parent: i64,
+        uid: u32,
+        gid: u32,
+        space: u64,
+        inodes: u64,
+    ) -> Result<(), MetaError> {
+        if self.quotas.is_empty() {
+            return Ok(());
+        }
+        let parent = self.check_root(parent);
+        let mut keys = self.dir_quota_keys(parent).await?;
+        keys.push((QuotaType::User, uid as u64));
+        keys.push((QuotaType::Group, gid as u64));
+        self.quotas.check(&keys, space as i64, inodes as i64)
+    }
+
+    #[tracing::instrument(level = "trace", skip(self), fields(ino, new_size))]
+    async fn check_write_quota(&self, ino: i64, new_size: u64) -> Result<(), MetaError> {
+        let inode = self.check_root(ino);
+        let Some(old) = self.quota_attr(inode).await? else {
+            return Ok(());
+        };
+        if new_size <= old.size {
+            return Ok(());
+        }
+        let new = FileAttr {
+            size: new_size,
+            ..old.clone()
+        };
+        self.check_attr_quota(&old, &new).await
+    }
+
     #[tracing::instrument(level = "trace", skip(self))]
     async fn get_deleted_files(&self) -> Result<Vec<i64>, MetaError> {
         self.store.get_deleted_files().await
@@ -1863,6 +2557,39 @@ impl<T: MetaStore + 'static> MetaLayer for MetaClient<T> {
         Ok(())
     }
 
+    #[tracing::instrument(
+        level = "trace",
+        skip(self, old, slice),
+        fields(chunk_id, replaced = old.len(), slice_id = slice.slice_id)
+    )]
+    async fn compact_chunk(
+        &self,
+        chunk_id: u64,
+        old: &[SliceDesc],
+        slice: SliceDesc,
+    ) -> Result<bool, MetaError> {
+        self.ensure_writable()?;
+        if !self.store.compact_chunk(chunk_id, old, slice).await? {
+            return Ok(false);
+        }
+        // Slices appended meanwhile are kept, so reload the whole list.
+        let (inode, chunk_index) = extract_ino_and_chunk_index(chunk_id);
+        let slices = self.store.get_slices(chunk_id).await?;
+        self.inode_cache
+            .replace_slices(inode, chunk_index, &slices)
+            .await;
+        Ok(true)
+    }
+
+    async fn list_delayed_slices(&self, edge_ts: i64) -> Result<Vec<DelayedSlice>, MetaError> {
+        self.store.list_delayed_slices(edge_ts).await
+    }
+
+    async fn delete_slice(&self, slice_id: u64) -> Result<(), MetaError> {
+        self.ensure_writable()?;
+        self.store.delete_slice(slice_id).await
+    }
+
     async fn next_id(&self, key: &str) -> Result<i64, MetaError> {
         self.ensure_writable()?;
         self.store.next_id(key).await
@@ -1915,7 +2642,11 @@ impl<T: MetaStore + 'static> MetaLayer for MetaClient<T> {
         flags: u32,
     ) -> Result<(), MetaError> {
         self.ensure_writable()?;
-        self.store.set_xattr(inode, name, value, flags).await
+        self.store.set_xattr(inode, name, value, flags).await?;
+        if AclType::from_xattr_name(name).is_some() {
+            self.invalidate_acls(inode).await;
+        }
+        Ok(())
     }
 
     async fn get_xattr(&self, inode: i64, name: &str) -> Result<Option<Vec<u8>>, MetaError> {
@@ -1928,21 +2659,47 @@ impl<T: MetaStore + 'static> MetaLayer for MetaClient<T> {
 
     async fn remove_xattr(&self, inode: i64, name: &str) -> Result<(), MetaError> {
         self.ensure_writable()?;
-        self.store.remove_xattr(inode, name).await
+        self.store.remove_xattr(inode, name).await?;
+        if AclType::from_xattr_name(name).is_some() {
+            self.invalidate_acls(inode).await;
+        }
+        Ok(())
     }
 
-    async fn set_acl(&self, inode: i64, rule: AclRule) -> Result<(), MetaError> {
+    async fn set_acl(
+        &self,
+        inode: i64,
+        acl_type: AclType,
+        rule: Option<&AclRule>,
+    ) -> Result<(), MetaError> {
         self.ensure_writable()?;
-        self.store.set_acl(inode, rule).await
+        let inode = self.check_root(inode);
+        let result = self.store.set_acl(inode, acl_type, rule).await;
+        // Setting the access ACL may have changed the mode as well.
+        self.acl_cache.invalidate(&(inode, acl_type)).await;
+        self.inode_cache.invalidate_inode(inode).await;
+        result
     }
 
-    async fn get_acl(
-        &self,
-        inode: i64,
-        acl_type: u8,
-        acl_id: u32,
-    ) -> Result<Option<AclRule>, MetaError> {
-        self.store.get_acl(inode, acl_type, acl_id).await
+    async fn get_acl(&self, inode: i64, acl_type: AclType) -> Result<Option<AclRule>, MetaError> {
+        let inode = self.check_root(inode);
+        if let Some(rule) = self.acl_cache.get(&(inode, acl_type)).await {
+            return Ok(rule);
+        }
+        let rule = self.store.get_acl(inode, acl_type).await?;
+        self.acl_cache.insert((inode, acl_type), rule.clone()).await;
+        Ok(rule)
+    }
+
+    async fn inherit_acl(&self, parent: i64, ino: i64, mode: u32) -> Result<u32, MetaError> {
+        self.ensure_writable()?;
+        let parent = self.check_root(parent);
+        if self.get_acl(parent, AclType::Default).await?.is_none() {
+            return Ok(mode);
+        }
+        let mode = acl::inherit_acl(self.store.as_ref(), parent, ino, mode).await?;
+        self.invalidate_acls(ino).await;
+        Ok(mode)
     }
 }
 
@@ -1950,6 +2707,7 @@ impl<T: MetaStore + 'static> MetaLayer for MetaClient<T> {
 mod tests {
     use super::*;
     use crate::meta::config::{CacheConfig, ClientOptions, Config, DatabaseConfig, DatabaseType};
+    use crate::meta::permission::Permission;
     use crate::meta::stores::database_store::DatabaseMetaStore;
     use crate::vfs::chunk_id_for;
     use std::time::Duration;
@@ -2500,4 +3258,164 @@ mod tests {
         let final_attr = client.cached_stat(file_ino).await.unwrap().unwrap();
         assert_eq!(original_attr.ino, final_attr.ino);
     }
+
+    #[tokio::test]
+    async fn test_trash_unlink_restore_and_sweep() {
+        let config = Config {
+            database: DatabaseConfig {
+                db_config: DatabaseType::Sqlite {
+                    url: "sqlite::memory:".to_string(),
+                },
+            },
+            cache: CacheConfig::default(),
+            client: ClientOptions::default(),
+        };
+        let store = Arc::new(DatabaseMetaStore::from_config(config).await.unwrap());
+        let options = MetaClientOptions {
+            no_background_jobs: true,
+            trash_retention: Some(Duration::from_secs(3600)),
+            ..Default::default()
+        };
+        let client = MetaClient::with_options(
+            store,
+            CacheCapacity::default(),
+            CacheTtl::for_sqlite(),
+            options,
+        );
+
+        let dir = client.mkdir(1, "dir".to_string()).await.unwrap();
+        let file = client.create_file(dir, "a.txt".to_string()).await.unwrap();
+        let other = client.create_file(dir, "b.txt".to_string()).await.unwrap();
+
+        // Unlinking moves the file to the trash instead of deleting it.
+        client.unlink(dir, "a.txt").await.unwrap();
+        assert_eq!(client.lookup(dir, "a.txt").await.unwrap(), None);
+        assert!(client.get_deleted_files().await.unwrap().is_empty());
+        let entries = trash::list_entries(client.store.as_ref()).await.unwrap();
+        assert_eq!(entries.len(), 1);
+        assert_eq!(entries[0].ino, file);
+        assert_eq!(
+            (entries[0].parent, entries[0].orig_name.as_str()),
+            (dir, "a.txt")
+        );
+
+        // Only root may look up entries: the trash and batch directories
+        // belong to root and deny execute permission to other users.
+        let trash_ino = trash::trash_dir(client.store.as_ref())
+            .await
+            .unwrap()
+            .unwrap();
+        let batch_ino = client
+            .lookup(trash_ino, &entries[0].batch)
+            .await
+            .unwrap()
+            .unwrap();
+        for ino in [trash_ino, batch_ino] {
+            let attr = client.stat(ino).await.unwrap().unwrap();
+            assert_eq!(attr.uid, 0);
+            let perm = Permission::new(attr.mode, attr.uid, attr.gid);
+            assert!(!perm.can_execute(1000, &[1000]));
+            assert!(!perm.can_read(1000, &[0]));
+        }
+
+        // A file replaced by a rename goes to the trash as well.
+        client.create_file(dir, "a.txt".to_string()).await.unwrap();
+        client
+            .rename(dir, "b.txt", dir, "a.txt".to_string())
+            .await
+            .unwrap();
+        assert_eq!(client.lookup(dir, "a.txt").await.unwrap(), Some(other));
+        let entries = trash::list_entries(client.store.as_ref()).await.unwrap();
+        assert_eq!(entries.len(), 2);
+
+        // Restoring fails while the name is taken, then succeeds.
+        let first = entries.iter().find(|e| e.ino == file).unwrap();
+        let err = client
+            .restore_trash(&first.batch, &first.name)
+            .await
+            .unwrap_err();
+        assert!(matches!(err, MetaError::AlreadyExists { .. }));
+        client.unlink(dir, "a.txt").await.unwrap();
+        client
+            .restore_trash(&first.batch, &first.name)
+            .await
+            .unwrap();
+        assert_eq!(client.lookup(dir, "a.txt").await.unwrap(), Some(file));
+
+        // Entries newer than the retention period survive a sweep; removing
+        // them from the trash deletes them for good.
+        assert_eq!(client.sweep_trash().await.unwrap(), 0);
+        assert_eq!(
+            client.store.cleanup_delayed_slices(i64::MAX).await.unwrap(),
+            2
+        );
+        assert!(
+            trash::list_entries(client.store.as_ref())
+                .await
+                .unwrap()
+                .is_empty()
+        );
+        assert_eq!(client.get_deleted_files().await.unwrap().len(), 2);
+    }
+
+    #[tokio::test]
+    async fn test_acl_inheritance_and_chmod() {
+        use crate::meta::acl::NamedEntry;
+
+        let client = create_test_client().await;
+        let dir = client.mkdir(1, "dir".to_string()).await.unwrap();
+        let file = client.create_file(1, "plain".to_string()).await.unwrap();
+
+        let mut default = AclRule::from_mode(0o750);
+        default.mask = Some(0o7);
+        default.named_users.push(NamedEntry {
+            id: 1001,
+            perm: 0o7,
+        });
+        client
+            .set_acl(dir, AclType::Default, Some(&default))
+            .await
+            .unwrap();
+        let err = client
+            .set_acl(file, AclType::Default, Some(&default))
+            .await
+            .unwrap_err();
+        assert!(matches!(err, MetaError::NotDirectory(_)));
+
+        // New entries get the default ACL, limited by their creation mode.
+        let child = client.create_file(dir, "a".to_string()).await.unwrap();
+        let mode = client.inherit_acl(dir, child, 0o100644).await.unwrap();
+        assert_eq!(mode, 0o100640);
+        let access = client
+            .get_acl(child, AclType::Access)
+            .await
+            .unwrap()
+            .unwrap();
+        assert_eq!(access.mask, Some(0o4));
+        assert_eq!(access.named_users, default.named_users);
+        let sub = client.mkdir(dir, "sub".to_string()).await.unwrap();
+        client.inherit_acl(dir, sub, 0o040755).await.unwrap();
+        assert_eq!(
+            client.get_acl(sub, AclType::Default).await.unwrap(),
+            Some(default.clone())
+        );
+
+        // chmod moves the group bits to the mask.
+        client.chmod(child, 0o660).await.unwrap();
+        let access = client
+            .get_acl(child, AclType::Access)
+            .await
+            .unwrap()
+            .unwrap();
+        assert_eq!((access.owner, access.mask), (0o6, Some(0o6)));
+
+        // Setting a minimal access ACL drops it and only changes the mode.
+        client
+            .set_acl(child, AclType::Access, Some(&AclRule::from_mode(0o600)))
+            .await
+            .unwrap();
+        assert_eq!(client.get_acl(child, AclType::Access).await.unwrap(), None);
+        let attr = client.stat(child).await.unwrap().unwrap();
+        assert_eq!(attr.mode & 0o777, 0o600);
+    }
 }
@@ -0,0 +1,388 @@
+//! Metadata-only clones of files and directory trees.
+//!
+//! A clone gets new inodes but shares the slices of its source, so no object
+//! data is copied; the reference count of every shared slice is raised
+//! instead. Like the dump, cloning only uses the generic [`MetaStore`]
+//! operations and works on every backend.
+//!
+//! The clone is built below [`DETACHED_DIR`], a root-only directory in the
+//! root, and moved to its final name with a single rename once complete, so a
+//! half-built tree is never visible there. Trees left behind by an interrupted clone are found
+//! with [`MetaStore::find_detached_nodes`] and removed with
+//! [`MetaStore::cleanup_detached_node`].
+
+use std::collections::{HashMap, VecDeque};
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use crate::chuck::SliceDesc;
+use crate::meta::acl;
+use crate::meta::store::{
+    CloneOption, FileAttr, FileType, MetaError, MetaStore, SetAttrFlags, SetAttrRequest,
+    chmod_request,
+};
+use crate::vfs::chunk_id_for;
+
+/// Name of the root directory that holds clones under construction.
+pub const DETACHED_DIR: &str = ".detached";
+
+fn chunk_id(ino: i64, index: u64) -> Result<u64, MetaError> {
+    chunk_id_for(ino, index).map_err(|e| MetaError::Internal(e.to_string()))
+}
+
+fn nanos(time: SystemTime) -> i64 {
+    time.duration_since(UNIX_EPOCH)
+        .map(|d| d.as_nanos() as i64)
+        .unwrap_or_default()
+}
+
+/// Returns the directory holding detached nodes, creating it if `create` is
+/// set.
+async fn detached_dir<S>(store: &S, create: bool) -> Result<Option<i64>, MetaError>
+where
+    S: MetaStore + ?Sized,
+{
+    let root = store.root_ino();
+    if let Some(ino) = store.lookup(root, DETACHED_DIR).await? {
+        return Ok(Some(ino));
+    }
+    if !create {
+        return Ok(None);
+    }
+    match store.mkdir(root, DETACHED_DIR.to_string()).await {
+        Ok(ino) => {
+            store
+                .set_attr(ino, &chmod_request(0o700), SetAttrFlags::empty())
+                .await?;
+            Ok(Some(ino))
+        }
+        Err(MetaError::AlreadyExists { .. }) => store.lookup(root, DETACHED_DIR).await,
+        Err(err) => Err(err),
+    }
+}
+
+/// Name of the detached node `ino`, if it is one.
+async fn detached_name<S>(store: &S, staging: i64, ino: i64) -> Result<Option<String>, MetaError>
+where
+    S: MetaStore + ?Sized,
+{
+    Ok(store
+        .readdir(staging)
+        .await?
+        .into_iter()
+        .find(|entry| entry.ino == ino)
+        .map(|entry| entry.name))
+}
+
+/// Whether `dir` is `ancestor` or lies below it.
+async fn is_within<S>(store: &S, dir: i64, ancestor: i64) -> Result<bool, MetaError>
+where
+    S: MetaStore + ?Sized,
+{
+    let root = store.root_ino();
+    let mut current = dir;
+    loop {
+        if current == ancestor {
+            return Ok(true);
+        }
+        if current == root {
+            return Ok(false);
+        }
+        match store.get_dir_parent(current).await? {
+            Some(parent) if parent != current => current = parent,
+            _ => return Ok(false),
+        }
+    }
+}
+
+fn clone_attr(src: &FileAttr, opt: &CloneOption) -> SetAttrRequest {
+    if opt.preserve_attr {
+        SetAttrRequest {
+            mode: Some(src.mode),
+            uid: Some(src.uid),
+            gid: Some(src.gid),
+            atime: Some(src.atime),
+            mtime: Some(src.mtime),
+            ..Default::default()
+        }
+    } else {
+        SetAttrRequest {
+            mode: Some(src.mode & !u32::from(opt.umask)),
+            uid: Some(opt.uid),
+            gid: Some(opt.gid),
+            ..Default::default()
+        }
+    }
+}
+
+/// Applies the attributes of `src` to its copy `ino`. A umask may take
+/// permission bits away, which the copied access ACL has to follow.
+async fn set_clone_attr<S>(
+    store: &S,
+    ino: i64,
+    src: &FileAttr,
+    opt: &CloneOption,
+) -> Result<(), MetaError>
+where
+    S: MetaStore + ?Sized,
+{
+    let attr = store
+        .set_attr(ino, &clone_attr(src, opt), SetAttrFlags::empty())
+        .await?;
+    if !opt.preserve_attr && src.kind != FileType::Symlink {
+        acl::sync_mode(store, ino, attr.mode).await?;
+    }
+    Ok(())
+}
+
+/// Copies the slice lists of `src` to `dst`. References are taken before the
+/// slices are attached, so an interrupted clone can leak data but never
+/// lose it.
+async fn clone_chunks<S>(
+    store: &S,
+    src: &FileAttr,
+    dst: i64,
+    chunk_size: u64,
+) -> Result<(), MetaError>
+where
+    S: MetaStore + ?Sized,
+{
+    for index in 0..src.size.div_ceil(chunk_size) {
+        let slices = store.get_slices(chunk_id(src.ino, index)?).await?;
+        if slices.is_empty() {
+            continue;
+        }
+        let ids: Vec<u64> = slices.iter().map(|slice| slice.slice_id).collect();
+        store.update_slice_refs(&ids, 1).await?;
+
+        let chunk_id = chunk_id(dst, index)?;
+        for slice in slices {
+            store
+                .append_slice(chunk_id, SliceDesc { chunk_id, ..slice })
+                .await?;
+        }
+    }
+    if src.size > 0 {
+        store.set_file_size(dst, src.size).await?;
+    }
+    Ok(())
+}
+
+async fn clone_xattrs<S>(store: &S, src: i64, dst: i64) -> Result<(), MetaError>
+where
+    S: MetaStore + ?Sized,
+{
+    let names = match store.list_xattr(src).await {
+        Ok(names) => names,
+        Err(MetaError::NotImplemented) => return Ok(()),
+        Err(err) => return Err(err),
+    };
+    for name in names {
+        if let Some(value) = store.get_xattr(src, &name).await? {
+            store.set_xattr(dst, &name, &value, 0).await?;
+        }
+    }
+    Ok(())
+}
+
+/// Creates a copy of the single node `src` as `parent/name`.
+async fn clone_node<S>(
+    store: &S,
+    src: &FileAttr,
+    parent: i64,
+    name: &str,
+    opt: &CloneOption,
+) -> Result<i64, MetaError>
+where
+    S: MetaStore + ?Sized,
+{
+    let ino = match src.kind {
+        FileType::Dir => store.mkdir(parent, name.to_string()).await?,
+        FileType::File => {
+            let ino = store.create_file(parent, name.to_string()).await?;
+            clone_chunks(store, src, ino, opt.chunk_size).await?;
+            ino
+        }
+        FileType::Symlink => {
+            let target = store.read_symlink(src.ino).await?;
+            store.symlink(parent, name, &target).await?.0
+        }
+    };
+    clone_xattrs(store, src.ino, ino).await?;
+    Ok(ino)
+}
+
+/// Clones the tree rooted at `top` to `parent/name` and returns the inode of
+/// the copy. Hard links inside the tree stay hard links in the copy.
+async fn clone_tree<S>(
+    store: &S,
+    top: &FileAttr,
+    parent: i64,
+    name: &str,
+    opt: &CloneOption,
+) -> Result<i64, MetaError>
+where
+    S: MetaStore + ?Sized,
+{
+    let top_ino = clone_node(store, top, parent, name, opt).await?;
+    let mut queue = VecDeque::from([(top.clone(), top_ino)]);
+    let mut links: HashMap<i64, i64> = HashMap::new();
+    // Directory times change while children are added, so they are set last.
+    let mut dirs = Vec::new();
+
+    while let Some((src, ino)) = queue.pop_front() {
+        if src.kind != FileType::Dir {
+            set_clone_attr(store, ino, &src, opt).await?;
+            continue;
+        }
+        for child in store.readdir(src.ino).await? {
+            if let Some(&linked) = links.get(&child.ino) {
+                store.link(linked, ino, &child.name).await?;
+                continue;
+            }
+            let Some(attr) = store.stat(child.ino).await? else {
+                continue;
+            };
+            let child_ino = clone_node(store, &attr, ino, &child.name, opt).await?;
+            if attr.kind != FileType::Dir && attr.nlink > 1 {
+                links.insert(attr.ino, child_ino);
+            }
+            queue.push_back((attr, child_ino));
+        }
+        dirs.push((ino, src));
+    }
+
+    for (ino, src) in dirs.iter().rev() {
+        set_clone_attr(store, *ino, src, opt).await?;
+    }
+    Ok(top_ino)
+}
+
+pub async fn clone_entry<S>(
+    store: &S,
+    src: i64,
+    parent: i64,
+    name: &str,
+    opt: &CloneOption,
+) -> Result<FileAttr, MetaError>
+where
+    S: MetaStore + ?Sized,
+{
+    if opt.chunk_size == 0 {
+        return Err(MetaError::Config("chunk size must be positive".to_string()));
+    }
+    let src_attr = store.stat(src).await?.ok_or(MetaError::NotFound(src))?;
+    let parent_attr = store
+        .stat(parent)
+        .await?
+        .ok_or(MetaError::ParentNotFound(parent))?;
+    if parent_attr.kind != FileType::Dir {
+        return Err(MetaError::NotDirectory(parent));
+    }
+    if store.lookup(parent, name).await?.is_some() {
+        return Err(MetaError::AlreadyExists {
+            parent,
+            name: name.to_string(),
+        });
+    }
+    if src_attr.kind == FileType::Dir && is_within(store, parent, src).await? {
+        return Err(MetaError::InvalidPath(format!(
+            "cannot clone directory {src} into itself"
+        )));
+    }
+
+    let staging = detached_dir(store, true)
+        .await?
+        .ok_or(MetaError::NotFound(store.root_ino()))?;
+    let staged_name = format!("{src}-{}", nanos(SystemTime::now()));
+    let cloned = match clone_tree(store, &src_attr, staging, &staged_name, opt).await {
+        Ok(ino) => ino,
+        Err(err) => {
+            if let Ok(Some(ino)) = store.lookup(staging, &staged_name).await {
+                let _ = store.cleanup_detached_node(ino).await;
+            }
+            return Err(err);
+        }
+    };
+
+    if let Err(err) = store.attach_dir_node(parent, cloned, name).await {
+        let _ = store.cleanup_detached_node(cloned).await;
+        return Err(err);
+    }
+    store.stat(cloned).await?.ok_or(MetaError::NotFound(cloned))
+}
+
+pub async fn attach_node<S>(store: &S, parent: i64, dst: i64, name: &str) -> Result<(), MetaError>
+where
+    S: MetaStore + ?Sized,
+{
+    let staging = detached_dir(store, false)
+        .await?
+        .ok_or(MetaError::NotFound(dst))?;
+    let staged_name = detached_name(store, staging, dst)
+        .await?
+        .ok_or(MetaError::NotFound(dst))?;
+    // Rename replaces existing entries, attaching must not.
+    if store.lookup(parent, name).await?.is_some() {
+        return Err(MetaError::AlreadyExists {
+            parent,
+            name: name.to_string(),
+        });
+    }
+    store
+        .rename(staging, &staged_name, parent, name.to_string())
+        .await
+}
+
+pub async fn find_detached_nodes<S>(store: &S, since: SystemTime) -> Result<Vec<i64>, MetaError>
+where
+    S: MetaStore + ?Sized,
+{
+    let Some(staging) = detached_dir(store, false).await? else {
+        return Ok(Vec::new());
+    };
+    let since = nanos(since);
+    let mut nodes = Vec::new();
+    for entry in store.readdir(staging).await? {
+        if let Some(attr) = store.stat(entry.ino).await?
+            && attr.ctime < since
+        {
+            nodes.push(entry.ino);
+        }
+    }
+    Ok(nodes)
+}
+
+pub async fn cleanup_detached_node<S>(store: &S, inode: i64) -> Result<(), MetaError>
+where
+    S: MetaStore + ?Sized,
+{
+    let staging = detached_dir(store, false)
+        .await?
+        .ok_or(MetaError::NotFound(inode))?;
+    let name = detached_name(store, staging, inode)
+        .await?
+        .ok_or(MetaError::NotFound(inode))?;
+    let kind = store
+        .stat(inode)
+        .await?
+        .ok_or(MetaError::NotFound(inode))?
+        .kind;
+
+    // Post-order walk: a directory is removed once its children are gone.
+    // Removed files keep their slices until the garbage collector drops them
+    // together with the references they hold.
+    let mut stack = vec![(staging, name, inode, kind, false)];
+    while let Some((parent, name, ino, kind, expanded)) = stack.pop() {
+        if kind != FileType::Dir {
+            store.unlink(parent, &name).await?;
+        } else if expanded {
+            store.rmdir(parent, &name).await?;
+        } else {
+            stack.push((parent, name, ino, kind, true));
+            for child in store.readdir(ino).await? {
+                stack.push((ino, child.name, child.ino, child.kind, false));
+            }
+        }
+    }
+    Ok(())
+}
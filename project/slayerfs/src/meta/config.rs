@@ -169,6 +169,9 @@ pub struct ClientOptions {
     /// Maximum symlink follow depth.
     #[serde(default = "default_max_symlinks")]
     pub max_symlinks: usize,
+    /// How long removed files are kept in the trash; unset disables the trash.
+    #[serde(default, with = "duration_option_serde")]
+    pub trash_retention: Option<Duration>,
 }
 
 fn default_max_symlinks() -> usize {
@@ -183,6 +186,7 @@ impl Default for ClientOptions {
             case_insensitive: false,
             session_heartbeat: None,
             max_symlinks: default_max_symlinks(),
+            trash_retention: None,
         }
     }
 }
@@ -0,0 +1,362 @@
+//! Backend-neutral metadata dump and load.
+//!
+//! A dump is a stream of [`DumpRecord`]s, written as one JSON object per line
+//! by the CLI. It is produced and consumed purely through the generic
+//! [`MetaStore`] operations, so a volume dumped from SQLite can be loaded into
+//! Postgres, Redis or etcd. Inode numbers are reassigned on load; slice ids are
+//! kept because they name the blocks in the object store.
+
+use std::collections::{HashMap, HashSet, VecDeque};
+
+use base64::Engine as _;
+use base64::engine::general_purpose::STANDARD as B64;
+
+use crate::chuck::SliceDesc;
+use crate::chuck::transform::BLOCK_FORMAT_SETTING;
+use crate::meta::store::{
+    DumpEntry, DumpOption, DumpRecord, FileAttr, FileType, LoadOption, MetaError, MetaStore,
+    SetAttrFlags, SetAttrRequest, Visitor,
+};
+use crate::meta::volume::VOLUME_SETTING;
+use crate::meta::{INODE_ID_KEY, SLICE_ID_KEY};
+use crate::vfs::chunk_id_for;
+
+/// Version of the dump format written into the header record.
+pub const DUMP_VERSION: u32 = 1;
+
+/// Volume settings carried by a dump.
+const DUMPED_SETTINGS: &[&str] = &[BLOCK_FORMAT_SETTING, VOLUME_SETTING];
+
+fn chunk_id(ino: i64, index: u64) -> Result<u64, MetaError> {
+    chunk_id_for(ino, index).map_err(|e| MetaError::Internal(e.to_string()))
+}
+
+/// Returns the counter value, or `None` if the backend does not track it.
+async fn counter<S>(store: &S, name: &str) -> Result<Option<i64>, MetaError>
+where
+    S: MetaStore + ?Sized,
+{
+    match store.get_counter(name).await {
+        Ok(value) => Ok(Some(value)),
+        Err(MetaError::NotImplemented | MetaError::NotSupported(_)) => Ok(None),
+        Err(err) => Err(err),
+    }
+}
+
+/// Returns the setting, or `None` if the backend does not store settings.
+async fn setting<S>(store: &S, name: &str) -> Result<Option<String>, MetaError>
+where
+    S: MetaStore + ?Sized,
+{
+    match store.get_setting(name).await {
+        Ok(value) => Ok(value),
+        Err(MetaError::NotImplemented | MetaError::NotSupported(_)) => Ok(None),
+        Err(err) => Err(err),
+    }
+}
+
+async fn dump_xattrs<S>(store: &S, ino: i64) -> Result<HashMap<String, String>, MetaError>
+where
+    S: MetaStore + ?Sized,
+{
+    let names = match store.list_xattr(ino).await {
+        Ok(names) => names,
+        Err(MetaError::NotImplemented) => return Ok(HashMap::new()),
+        Err(err) => return Err(err),
+    };
+    let mut xattrs = HashMap::with_capacity(names.len());
+    for name in names {
+        if let Some(value) = store.get_xattr(ino, &name).await? {
+            xattrs.insert(name, B64.encode(value));
+        }
+    }
+    Ok(xattrs)
+}
+
+pub async fn dump_namespace<S>(
+    store: &S,
+    opt: DumpOption,
+    visitor: &mut dyn Visitor<DumpRecord>,
+) -> Result<(), MetaError>
+where
+    S: MetaStore + ?Sized,
+{
+    if opt.include_deleted {
+        return Err(MetaError::NotSupported(
+            "dumping deleted inodes".to_string(),
+        ));
+    }
+    if opt.chunk_size == 0 {
+        return Err(MetaError::Config("chunk size must be positive".to_string()));
+    }
+
+    visitor.visit(DumpRecord::Header {
+        version: DUMP_VERSION,
+        chunk_size: opt.chunk_size,
+    })?;
+    for &name in DUMPED_SETTINGS {
+        if let Some(value) = setting(store, name).await? {
+            visitor.visit(DumpRecord::Setting {
+                name: name.to_string(),
+                value,
+            })?;
+        }
+    }
+
+    let root = store.root_ino();
+    let root_attr = store.stat(root).await?.ok_or(MetaError::NotFound(root))?;
+    let mut queue = VecDeque::from([(0, String::new(), "/".to_string(), root_attr)]);
+    let mut seen = HashSet::new();
+    let (mut max_inode, mut max_slice) = (root, 0u64);
+    let mut dumped = 0usize;
+
+    while let Some((parent, name, path, attr)) = queue.pop_front() {
+        if opt.limit.is_some_and(|limit| dumped >= limit) {
+            break;
+        }
+        let ino = attr.ino;
+        max_inode = max_inode.max(ino);
+
+        let mut entry = DumpEntry {
+            inode: ino,
+            parent,
+            name,
+            path,
+            attr,
+            symlink_target: None,
+            xattrs: HashMap::new(),
+            chunks: Vec::new(),
+        };
+
+        // Hard links only repeat the name; the content goes with the first one.
+        if seen.insert(ino) {
+            entry.xattrs = dump_xattrs(store, ino).await?;
+            match entry.attr.kind {
+                FileType::Symlink => {
+                    entry.symlink_target = Some(store.read_symlink(ino).await?);
+                }
+                FileType::File => {
+                    let chunks = entry.attr.size.div_ceil(opt.chunk_size);
+                    for index in 0..chunks {
+                        let slices = store.get_slices(chunk_id(ino, index)?).await?;
+                        if slices.is_empty() {
+                            continue;
+                        }
+                        max_slice = slices
+                            .iter()
+                            .map(|slice| slice.slice_id)
+                            .fold(max_slice, u64::max);
+                        entry.chunks.push((index, slices));
+                    }
+                }
+                FileType::Dir => {
+                    let mut children = store.readdir(ino).await?;
+                    children.sort_by(|a, b| a.name.cmp(&b.name));
+                    for child in children {
+                        let Some(child_attr) = store.stat(child.ino).await? else {
+                            continue;
+                        };
+                        let child_path = if entry.path == "/" {
+                            format!("/{}", child.name)
+                        } else {
+                            format!("{}/{}", entry.path, child.name)
+                        };
+                        queue.push_back((ino, child.name, child_path, child_attr));
+                    }
+                }
+            }
+        }
+
+        visitor.visit(DumpRecord::Entry(entry))?;
+        dumped += 1;
+    }
+
+    let next_inode = counter(store, INODE_ID_KEY)
+        .await?
+        .unwrap_or_default()
+        .max(max_inode + 1);
+    let next_slice = counter(store, SLICE_ID_KEY)
+        .await?
+        .unwrap_or_default()
+        .max(max_slice as i64 + 1);
+    visitor.visit(DumpRecord::Counters {
+        next_inode,
+        next_slice,
+    })
+}
+
+/// Creates the inode of `entry` below `parent`, or with `allow_conflicts`
+/// reuses an entry of the same name. Returns the new inode and whether it was
+/// created.
+async fn create_entry<S>(
+    store: &S,
+    parent: i64,
+    entry: &DumpEntry,
+    allow_conflicts: bool,
+) -> Result<(i64, bool), MetaError>
+where
+    S: MetaStore + ?Sized,
+{
+    let created = match entry.attr.kind {
+        FileType::Dir => store.mkdir(parent, entry.name.clone()).await,
+        FileType::File => store.create_file(parent, entry.name.clone()).await,
+        FileType::Symlink => {
+            let target = entry.symlink_target.as_deref().ok_or_else(|| {
+                MetaError::Serialization(format!("symlink {} has no target", entry.path))
+            })?;
+            store
+                .symlink(parent, &entry.name, target)
+                .await
+                .map(|(ino, _)| ino)
+        }
+    };
+    match created {
+        Ok(ino) => Ok((ino, true)),
+        Err(MetaError::AlreadyExists { .. }) if allow_conflicts => {
+            let ino = store
+                .lookup(parent, &entry.name)
+                .await?
+                .ok_or(MetaError::NotFound(parent))?;
+            Ok((ino, false))
+        }
+        Err(err) => Err(err),
+    }
+}
+
+async fn restore_content<S>(store: &S, ino: i64, entry: &DumpEntry) -> Result<(), MetaError>
+where
+    S: MetaStore + ?Sized,
+{
+    for (index, slices) in &entry.chunks {
+        let chunk_id = chunk_id(ino, *index)?;
+        for slice in slices {
+            store
+                .append_slice(chunk_id, SliceDesc { chunk_id, ..*slice })
+                .await?;
+        }
+    }
+    if entry.attr.kind == FileType::File && entry.attr.size > 0 {
+        store.set_file_size(ino, entry.attr.size).await?;
+    }
+
+    for (name, value) in &entry.xattrs {
+        let value = B64
+            .decode(value)
+            .map_err(|e| MetaError::Serialization(format!("xattr {name}: {e}")))?;
+        store.set_xattr(ino, name, &value, 0).await?;
+    }
+    Ok(())
+}
+
+async fn restore_attr<S>(store: &S, ino: i64, attr: &FileAttr) -> Result<(), MetaError>
+where
+    S: MetaStore + ?Sized,
+{
+    let req = SetAttrRequest {
+        mode: Some(attr.mode),
+        uid: Some(attr.uid),
+        gid: Some(attr.gid),
+        atime: Some(attr.atime),
+        mtime: Some(attr.mtime),
+        ..Default::default()
+    };
+    store.set_attr(ino, &req, SetAttrFlags::empty()).await?;
+    Ok(())
+}
+
+pub async fn load_namespace<S>(store: &S, opt: LoadOption, data: &[u8]) -> Result<(), MetaError>
+where
+    S: MetaStore + ?Sized,
+{
+    let mut inodes: HashMap<i64, i64> = HashMap::new();
+    // Directory times change while children are added, so they are set last.
+    let mut dirs = Vec::new();
+    let mut header_seen = false;
+    // Slice ids are kept while inodes are renumbered, so the slice counter
+    // must move past every loaded slice even if the dump was cut short.
+    let mut next_slice = 1i64;
+
+    for (line_no, line) in data.split(|b| *b == b'\n').enumerate() {
+        if line.iter().all(u8::is_ascii_whitespace) {
+            continue;
+        }
+        let record: DumpRecord = serde_json::from_slice(line)
+            .map_err(|e| MetaError::Serialization(format!("line {}: {e}", line_no + 1)))?;
+
+        match record {
+            DumpRecord::Header { version, .. } => {
+                if version != DUMP_VERSION {
+                    return Err(MetaError::NotSupported(format!(
+                        "dump format version {version}"
+                    )));
+                }
+                header_seen = true;
+            }
+            _ if !header_seen => {
+                return Err(MetaError::Serialization(
+                    "dump does not start with a header".to_string(),
+                ));
+            }
+            DumpRecord::Setting { name, value } => {
+                // Blocks are only readable with the settings they were written with.
+                if !store.set_setting(&name, &value, false).await?
+                    && store.get_setting(&name).await?.as_ref() != Some(&value)
+                {
+                    return Err(MetaError::Config(format!(
+                        "volume already has a different {name} setting"
+                    )));
+                }
+            }
+            DumpRecord::Entry(entry) => {
+                if entry.parent == 0 {
+                    let root = store.root_ino();
+                    inodes.insert(entry.inode, root);
+                    restore_content(store, root, &entry).await?;
+                    dirs.push((root, entry.attr));
+                    continue;
+                }
+
+                let parent = *inodes
+                    .get(&entry.parent)
+                    .ok_or(MetaError::ParentNotFound(entry.parent))?;
+                if let Some(&ino) = inodes.get(&entry.inode) {
+                    match store.link(ino, parent, &entry.name).await {
+                        Ok(_) => {}
+                        Err(MetaError::AlreadyExists { .. }) if opt.allow_conflicts => {}
+                        Err(err) => return Err(err),
+                    }
+                    continue;
+                }
+
+                let (ino, created) =
+                    create_entry(store, parent, &entry, opt.allow_conflicts).await?;
+                inodes.insert(entry.inode, ino);
+                if entry.attr.kind == FileType::Dir {
+                    restore_content(store, ino, &entry).await?;
+                    dirs.push((ino, entry.attr));
+                } else if created {
+                    restore_content(store, ino, &entry).await?;
+                    restore_attr(store, ino, &entry.attr).await?;
+                }
+                for (_, slices) in &entry.chunks {
+                    for slice in slices {
+                        next_slice = next_slice.max(slice.slice_id as i64 + 1);
+                    }
+                }
+            }
+            DumpRecord::Counters {
+                next_slice: dumped, ..
+            } => {
+                next_slice = next_slice.max(dumped);
+            }
+        }
+    }
+
+    store
+        .set_counter_if_small(SLICE_ID_KEY, next_slice, 0)
+        .await?;
+    for (ino, attr) in dirs.iter().rev() {
+        restore_attr(store, *ino, attr).await?;
+    }
+    Ok(())
+}
@@ -0,0 +1,18 @@
+use sea_orm::entity::prelude::*;
+
+/// Slices replaced by compaction whose blocks are waiting to be deleted.
+#[derive(Clone, Debug, PartialEq, DeriveEntityModel, Eq)]
+#[sea_orm(table_name = "delayed_slice_meta")]
+pub struct Model {
+    #[sea_orm(primary_key, auto_increment = false)]
+    pub slice_id: i64,
+    pub length: i64,
+    /// Nanoseconds since the epoch when the slice was replaced.
+    #[sea_orm(indexed)]
+    pub since: i64,
+}
+
+#[derive(Copy, Clone, Debug, EnumIter, DeriveRelation)]
+pub enum Relation {}
+
+impl ActiveModelBehavior for ActiveModel {}
@@ -0,0 +1,20 @@
+use sea_orm::entity::prelude::*;
+
+/// Directory/user/group quota limits and the usage charged against them.
+#[derive(Clone, Debug, PartialEq, DeriveEntityModel, Eq)]
+#[sea_orm(table_name = "quota_meta")]
+pub struct Model {
+    #[sea_orm(primary_key, auto_increment = false)]
+    pub quota_type: i32,
+    #[sea_orm(primary_key, auto_increment = false)]
+    pub quota_key: i64,
+    pub limit_space: Option<i64>,
+    pub limit_inodes: Option<i64>,
+    pub used_space: i64,
+    pub used_inodes: i64,
+}
+
+#[derive(Copy, Clone, Debug, EnumIter, DeriveRelation)]
+pub enum Relation {}
+
+impl ActiveModelBehavior for ActiveModel {}
@@ -0,0 +1,16 @@
+use sea_orm::entity::prelude::*;
+
+/// Extra references to a slice shared by cloned chunks. A slice without a row
+/// is referenced only by the chunk it was written to.
+#[derive(Clone, Debug, PartialEq, DeriveEntityModel, Eq)]
+#[sea_orm(table_name = "slice_ref_meta")]
+pub struct Model {
+    #[sea_orm(primary_key, auto_increment = false)]
+    pub slice_id: i64,
+    pub extra_refs: i64,
+}
+
+#[derive(Copy, Clone, Debug, EnumIter, DeriveRelation)]
+pub enum Relation {}
+
+impl ActiveModelBehavior for ActiveModel {}
@@ -0,0 +1,633 @@
+//! Backend-neutral consistency check of the metadata.
+//!
+//! [`check`] walks the namespace from the root and verifies that every entry
+//! names an existing inode of the right type, that directories point back to
+//! their parent, that link counts match the entries naming a file and that
+//! slices fit their chunks and files. It also compares slice reference counts
+//! and quota usage with what the walk found, and looks for clones left behind
+//! by an interrupted `clone`. The last three are repaired on request; the
+//! others need a human to decide what the right state is.
+//!
+//! The walk also collects every referenced slice, which is what the block
+//! checks and the garbage collector in [`crate::chuck::gc`] compare the
+//! object store against. Like the dump, this only uses the generic
+//! [`MetaStore`] operations and works on every backend. Results are only
+//! exact on a volume nobody is writing to.
+
+use std::collections::{HashMap, HashSet, VecDeque};
+use std::fmt;
+use std::time::{Duration, SystemTime};
+
+use crate::chuck::SliceDesc;
+use crate::meta::clone::{cleanup_detached_node, find_detached_nodes};
+use crate::meta::quota::{QuotaKey, owner_usage, subtree_usage};
+use crate::meta::store::{DirStat, FileAttr, FileType, MetaError, MetaStore, Quota, QuotaType};
+use crate::vfs::chunk_id_for;
+
+/// Clones younger than this may still be under construction and are not
+/// reported as left behind.
+const DETACHED_GRACE: Duration = Duration::from_secs(3600);
+
+fn chunk_id(ino: i64, index: u64) -> Result<u64, MetaError> {
+    chunk_id_for(ino, index).map_err(|e| MetaError::Internal(e.to_string()))
+}
+
+/// Options of [`check`].
+#[derive(Debug, Clone)]
+pub struct FsckOption {
+    /// Chunk size in bytes the volume was created with.
+    pub chunk_size: u64,
+    /// Repair the issues that can be repaired.
+    pub repair: bool,
+}
+
+/// An inconsistency found by [`check`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum FsckIssue {
+    /// A directory entry whose inode does not exist.
+    DanglingEntry { path: String, ino: i64 },
+    /// A directory entry whose type differs from that of its inode.
+    KindMismatch {
+        path: String,
+        ino: i64,
+        entry: FileType,
+        inode: FileType,
+    },
+    /// A directory whose recorded parent is not the directory listing it.
+    WrongParent {
+        path: String,
+        ino: i64,
+        recorded: Option<i64>,
+    },
+    /// A file whose link count differs from the number of entries naming it.
+    LinkCount {
+        path: String,
+        ino: i64,
+        nlink: u32,
+        entries: u32,
+    },
+    /// A slice that does not fit in its chunk or reaches past the end of its
+    /// file.
+    BadSlice {
+        path: String,
+        ino: i64,
+        chunk_index: u64,
+        slice: SliceDesc,
+    },
+    /// A slice whose reference count differs from the number of chunks
+    /// holding it.
+    SliceRefs {
+        slice_id: u64,
+        recorded: i64,
+        actual: i64,
+    },
+    /// A clone left behind by an interrupted `clone`.
+    DetachedNode { ino: i64 },
+    /// A quota whose recorded usage differs from the usage it covers.
+    QuotaUsage {
+        key: QuotaKey,
+        recorded: DirStat,
+        actual: DirStat,
+    },
+}
+
+impl FsckIssue {
+    /// Whether [`check`] can repair the issue.
+    pub fn is_repairable(&self) -> bool {
+        matches!(
+            self,
+            FsckIssue::SliceRefs { .. }
+                | FsckIssue::DetachedNode { .. }
+                | FsckIssue::QuotaUsage { .. }
+        )
+    }
+}
+
+impl fmt::Display for FsckIssue {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            FsckIssue::DanglingEntry { path, ino } => {
+                write!(f, "{path}: entry names missing inode {ino}")
+            }
+            FsckIssue::KindMismatch {
+                path,
+                ino,
+                entry,
+                inode,
+            } => write!(
+                f,
+                "{path}: entry is a {entry:?} but inode {ino} is a {inode:?}"
+            ),
+            FsckIssue::WrongParent {
+                path,
+                ino,
+                recorded,
+            } => match recorded {
+                Some(parent) => write!(f, "{path}: directory {ino} records parent {parent}"),
+                None => write!(f, "{path}: directory {ino} records no parent"),
+            },
+            FsckIssue::LinkCount {
+                path,
+                ino,
+                nlink,
+                entries,
+            } => write!(
+                f,
+                "{path}: inode {ino} has {nlink} links but {entries} entries"
+            ),
+            FsckIssue::BadSlice {
+                path,
+                ino,
+                chunk_index,
+                slice,
+            } => write!(
+                f,
+                "{path}: slice {} at {}+{} of chunk {chunk_index} of inode {ino} does not fit",
+                slice.slice_id, slice.offset, slice.length
+            ),
+            FsckIssue::SliceRefs {
+                slice_id,
+                recorded,
+                actual,
+            } => write!(
+                f,
+                "slice {slice_id} records {recorded} references but has {actual}"
+            ),
+            FsckIssue::DetachedNode { ino } => {
+                write!(f, "inode {ino} is left over from an interrupted clone")
+            }
+            FsckIssue::QuotaUsage {
+                key,
+                recorded,
+                actual,
+            } => write!(
+                f,
+                "{} quota {}: records {} bytes/{} inodes but uses {}/{}",
+                key.0, key.1, recorded.space, recorded.inodes, actual.space, actual.inodes
+            ),
+        }
+    }
+}
+
+/// An issue and whether it was repaired.
+#[derive(Debug, Clone)]
+pub struct Finding {
+    pub issue: FsckIssue,
+    pub repaired: bool,
+}
+
+/// The longest use of a slice found in the metadata.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SliceUse {
+    /// Bytes of the slice in use, counted from its start.
+    pub length: u64,
+    /// An inode holding the slice.
+    pub ino: i64,
+    /// A path of that inode; `None` for deleted files awaiting collection.
+    pub path: Option<String>,
+}
+
+/// Result of [`check`].
+#[derive(Debug, Default)]
+pub struct FsckReport {
+    /// Number of inodes checked.
+    pub inodes: usize,
+    pub findings: Vec<Finding>,
+    /// Every slice referenced by a file, including deleted files whose data
+    /// has not been collected yet.
+    pub slices: HashMap<u64, SliceUse>,
+}
+
+/// What a walk of the namespace found.
+#[derive(Default)]
+struct Scan {
+    inodes: usize,
+    issues: Vec<FsckIssue>,
+    slices: HashMap<u64, SliceUse>,
+    refs: HashMap<u64, i64>,
+}
+
+impl Scan {
+    async fn add_file<S>(
+        &mut self,
+        store: &S,
+        attr: &FileAttr,
+        path: Option<&str>,
+        chunk_size: u64,
+    ) -> Result<(), MetaError>
+    where
+        S: MetaStore + ?Sized,
+    {
+        let ino = attr.ino;
+        for index in 0..attr.size.div_ceil(chunk_size) {
+            for slice in store.get_slices(chunk_id(ino, index)?).await? {
+                *self.refs.entry(slice.slice_id).or_default() += 1;
+
+                let end = index * chunk_size + slice.offset + slice.length;
+                if slice.length == 0 || slice.offset + slice.length > chunk_size || end > attr.size
+                {
+                    self.issues.push(FsckIssue::BadSlice {
+                        path: path.unwrap_or("<deleted>").to_string(),
+                        ino,
+                        chunk_index: index,
+                        slice,
+                    });
+                }
+
+                let used = self.slices.entry(slice.slice_id).or_insert(SliceUse {
+                    length: 0,
+                    ino,
+                    path: path.map(str::to_string),
+                });
+                if slice.length > used.length {
+                    *used = SliceUse {
+                        length: slice.length,
+                        ino,
+                        path: path.map(str::to_string),
+                    };
+                }
+            }
+        }
+        Ok(())
+    }
+}
+
+fn child_path(dir: &str, name: &str) -> String {
+    if dir == "/" {
+        format!("/{name}")
+    } else {
+        format!("{dir}/{name}")
+    }
+}
+
+/// Walks the namespace from the root, then the deleted files awaiting
+/// collection.
+async fn scan<S>(store: &S, chunk_size: u64) -> Result<Scan, MetaError>
+where
+    S: MetaStore + ?Sized,
+{
+    let mut scan = Scan::default();
+    let root = store.root_ino();
+    store.stat(root).await?.ok_or(MetaError::NotFound(root))?;
+    scan.inodes += 1;
+
+    // Link counts of non-directories, checked once all entries are known.
+    let mut links: HashMap<i64, (FileAttr, String, u32)> = HashMap::new();
+    let mut seen = HashSet::from([root]);
+    let mut queue = VecDeque::from([(root, "/".to_string())]);
+
+    while let Some((dir, dir_path)) = queue.pop_front() {
+        let mut children = store.readdir(dir).await?;
+        children.sort_by(|a, b| a.name.cmp(&b.name));
+        for child in children {
+            let path = child_path(&dir_path, &child.name);
+            let Some(attr) = store.stat(child.ino).await? else {
+                scan.issues.push(FsckIssue::DanglingEntry {
+                    path,
+                    ino: child.ino,
+                });
+                continue;
+            };
+            if attr.kind != child.kind {
+                scan.issues.push(FsckIssue::KindMismatch {
+                    path: path.clone(),
+                    ino: child.ino,
+                    entry: child.kind,
+                    inode: attr.kind,
+                });
+            }
+
+            if attr.kind != FileType::Dir {
+                if let Some((_, _, entries)) = links.get_mut(&attr.ino) {
+                    *entries += 1;
+                    continue;
+                }
+                scan.inodes += 1;
+                if attr.kind == FileType::File {
+                    scan.add_file(store, &attr, Some(&path), chunk_size).await?;
+                }
+                links.insert(attr.ino, (attr, path, 1));
+                continue;
+            }
+
+            let recorded = store.get_dir_parent(attr.ino).await?;
+            if recorded != Some(dir) {
+                scan.issues.push(FsckIssue::WrongParent {
+                    path: path.clone(),
+                    ino: attr.ino,
+                    recorded,
+                });
+            }
+            // A directory listed twice would be walked forever.
+            if seen.insert(attr.ino) {
+                scan.inodes += 1;
+                queue.push_back((attr.ino, path));
+            }
+        }
+    }
+
+    let mut links: Vec<_> = links.into_values().collect();
+    links.sort_by_key(|(attr, _, _)| attr.ino);
+    for (attr, path, entries) in links {
+        if attr.nlink != entries {
+            scan.issues.push(FsckIssue::LinkCount {
+                path,
+                ino: attr.ino,
+                nlink: attr.nlink,
+                entries,
+            });
+        }
+    }
+
+    let deleted = match store.get_deleted_files().await {
+        Ok(deleted) => deleted,
+        Err(MetaError::NotImplemented) => Vec::new(),
+        Err(err) => return Err(err),
+    };
+    for ino in deleted {
+        if let Some(attr) = store.stat(ino).await? {
+            scan.add_file(store, &attr, None, chunk_size).await?;
+        }
+    }
+    Ok(scan)
+}
+
+/// Returns every slice referenced by the metadata with its longest use.
+pub async fn referenced_slices<S>(
+    store: &S,
+    chunk_size: u64,
+) -> Result<HashMap<u64, SliceUse>, MetaError>
+where
+    S: MetaStore + ?Sized,
+{
+    if chunk_size == 0 {
+        return Err(MetaError::Config("chunk size must be positive".to_string()));
+    }
+    Ok(scan(store, chunk_size).await?.slices)
+}
+
+async fn check_slice_refs<S>(
+    store: &S,
+    refs: &HashMap<u64, i64>,
+) -> Result<Vec<FsckIssue>, MetaError>
+where
+    S: MetaStore + ?Sized,
+{
+    let mut ids: Vec<_> = refs.keys().copied().collect();
+    ids.sort_unstable();
+    let mut issues = Vec::new();
+    for slice_id in ids {
+        let recorded = match store.get_slice_refs(slice_id).await {
+            Ok(recorded) => recorded,
+            Err(MetaError::NotImplemented) => return Ok(Vec::new()),
+            Err(err) => return Err(err),
+        };
+        let actual = refs[&slice_id];
+        if recorded != actual {
+            issues.push(FsckIssue::SliceRefs {
+                slice_id,
+                recorded,
+                actual,
+            });
+        }
+    }
+    Ok(issues)
+}
+
+async fn check_quotas<S>(store: &S) -> Result<Vec<FsckIssue>, MetaError>
+where
+    S: MetaStore + ?Sized,
+{
+    let (dirs, users, groups) = match store.load_quotas().await {
+        Ok(quotas) => quotas,
+        Err(MetaError::NotImplemented) => return Ok(Vec::new()),
+        Err(err) => return Err(err),
+    };
+    let mut quotas: Vec<(QuotaKey, Quota)> = [
+        (QuotaType::Dir, dirs),
+        (QuotaType::User, users),
+        (QuotaType::Group, groups),
+    ]
+    .into_iter()
+    .flat_map(|(qtype, quotas)| {
+        quotas
+            .into_iter()
+            .map(move |(id, quota)| ((qtype, id), quota))
+    })
+    .collect();
+    quotas.sort_by_key(|(key, _)| *key);
+
+    let mut issues = Vec::new();
+    for (key, quota) in quotas {
+        let actual = match key.0 {
+            QuotaType::Dir => subtree_usage(store, key.1 as i64).await?,
+            QuotaType::User | QuotaType::Group => owner_usage(store, key).await?,
+        };
+        let recorded = DirStat {
+            space: quota.used_space,
+            inodes: quota.used_inodes,
+        };
+        if recorded != actual {
+            issues.push(FsckIssue::QuotaUsage {
+                key,
+                recorded,
+                actual,
+            });
+        }
+    }
+    Ok(issues)
+}
+
+async fn repair<S>(store: &S, issue: &FsckIssue) -> Result<(), MetaError>
+where
+    S: MetaStore + ?Sized,
+{
+    match issue {
+        FsckIssue::SliceRefs {
+            slice_id,
+            recorded,
+            actual,
+        } => {
+            store
+                .update_slice_refs(&[*slice_id], actual - recorded)
+                .await
+        }
+        FsckIssue::DetachedNode { ino } => cleanup_detached_node(store, *ino).await,
+        FsckIssue::QuotaUsage { key, actual, .. } => {
+            let Some(mut quota) = store.get_quota(key.0.into(), key.1).await? else {
+                return Ok(());
+            };
+            quota.used_space = actual.space;
+            quota.used_inodes = actual.inodes;
+            store
+                .set_quota(key.0.into(), key.1, quota)
+                .await
+                .map(|_| ())
+        }
+        _ => Err(MetaError::NotSupported(format!("repairing: {issue}"))),
+    }
+}
+
+/// Records `issues`, repairing them first if requested and possible.
+async fn settle<S>(
+    store: &S,
+    repair_issues: bool,
+    issues: Vec<FsckIssue>,
+    findings: &mut Vec<Finding>,
+) -> Result<(), MetaError>
+where
+    S: MetaStore + ?Sized,
+{
+    for issue in issues {
+        let repaired = repair_issues && issue.is_repairable();
+        if repaired {
+            repair(store, &issue).await?;
+        }
+        findings.push(Finding { issue, repaired });
+    }
+    Ok(())
+}
+
+/// Checks the consistency of the metadata; see the module docs.
+pub async fn check<S>(store: &S, opt: FsckOption) -> Result<FsckReport, MetaError>
+where
+    S: MetaStore + ?Sized,
+{
+    if opt.chunk_size == 0 {
+        return Err(MetaError::Config("chunk size must be positive".to_string()));
+    }
+    let mut findings = Vec::new();
+
+    // Leftover clones go first, so that once removed the walk no longer sees
+    // them.
+    let since = SystemTime::now() - DETACHED_GRACE;
+    let detached = find_detached_nodes(store, since)
+        .await?
+        .into_iter()
+        .map(|ino| FsckIssue::DetachedNode { ino })
+        .collect();
+    settle(store, opt.repair, detached, &mut findings).await?;
+
+    let scan = scan(store, opt.chunk_size).await?;
+    settle(store, opt.repair, scan.issues, &mut findings).await?;
+    let issues = check_slice_refs(store, &scan.refs).await?;
+    settle(store, opt.repair, issues, &mut findings).await?;
+    let issues = check_quotas(store).await?;
+    settle(store, opt.repair, issues, &mut findings).await?;
+
+    Ok(FsckReport {
+        inodes: scan.inodes,
+        findings,
+        slices: scan.slices,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::meta::factory::create_meta_store_from_url;
+
+    const CHUNK_SIZE: u64 = 1024;
+
+    fn slice(slice_id: u64, chunk_id: u64, length: u64) -> SliceDesc {
+        SliceDesc {
+            slice_id,
+            chunk_id,
+            offset: 0,
+            length,
+        }
+    }
+
+    #[tokio::test]
+    async fn test_check_finds_and_repairs_slice_refs() {
+        let store = create_meta_store_from_url("sqlite::memory:")
+            .await
+            .unwrap()
+            .store();
+        let root = store.root_ino();
+        let dir = store.mkdir(root, "d".to_string()).await.unwrap();
+        let file = store.create_file(dir, "f".to_string()).await.unwrap();
+        let chunk = chunk_id(file, 1).unwrap();
+        store
+            .write(file, chunk, slice(7, chunk, 100), CHUNK_SIZE + 100)
+            .await
+            .unwrap();
+
+        let opt = FsckOption {
+            chunk_size: CHUNK_SIZE,
+            repair: false,
+        };
+        let report = check(store.as_ref(), opt.clone()).await.unwrap();
+        assert!(report.findings.is_empty(), "{:?}", report.findings);
+        assert_eq!(report.inodes, 3);
+        assert_eq!(
+            report.slices[&7],
+            SliceUse {
+                length: 100,
+                ino: file,
+                path: Some("/d/f".to_string()),
+            }
+        );
+
+        // An interrupted clone took a reference it never used.
+        store.update_slice_refs(&[7], 1).await.unwrap();
+        let report = check(store.as_ref(), opt.clone()).await.unwrap();
+        assert_eq!(report.findings.len(), 1);
+        assert_eq!(
+            report.findings[0].issue,
+            FsckIssue::SliceRefs {
+                slice_id: 7,
+                recorded: 2,
+                actual: 1,
+            }
+        );
+        assert!(!report.findings[0].repaired);
+
+        let repair = FsckOption {
+            repair: true,
+            ..opt.clone()
+        };
+        let report = check(store.as_ref(), repair).await.unwrap();
+        assert!(report.findings[0].repaired);
+        assert_eq!(store.get_slice_refs(7).await.unwrap(), 1);
+        assert!(
+            check(store.as_ref(), opt)
+                .await
+                .unwrap()
+                .findings
+                .is_empty()
+        );
+    }
+
+    #[tokio::test]
+    async fn test_check_reports_slices_past_eof() {
+        let store = create_meta_store_from_url("sqlite::memory:")
+            .await
+            .unwrap()
+            .store();
+        let root = store.root_ino();
+        let file = store.create_file(root, "f".to_string()).await.unwrap();
+        let chunk = chunk_id(file, 0).unwrap();
+        store
+            .write(file, chunk, slice(3, chunk, 200), 200)
+            .await
+            .unwrap();
+        store.set_file_size(file, 100).await.unwrap();
+
+        let report = check(
+            store.as_ref(),
+            FsckOption {
+                chunk_size: CHUNK_SIZE,
+                repair: true,
+            },
+        )
+        .await
+        .unwrap();
+        assert_eq!(report.findings.len(), 1);
+        assert!(matches!(
+            &report.findings[0].issue,
+            FsckIssue::BadSlice { path, chunk_index: 0, .. } if path == "/f"
+        ));
+        assert!(!report.findings[0].repaired);
+    }
+}
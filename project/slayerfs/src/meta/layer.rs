@@ -1,11 +1,12 @@
 use async_trait::async_trait;
 
 use crate::chuck::SliceDesc;
+use crate::meta::acl::{AclRule, AclType};
 use crate::meta::client::session::SessionInfo;
 use crate::meta::file_lock::{FileLockInfo, FileLockQuery, FileLockRange, FileLockType};
 use crate::meta::store::{
-    AclRule, DirEntry, FileAttr, FileType, MetaError, OpenFlags, SetAttrFlags, SetAttrRequest,
-    StatFsSnapshot, chmod_request, chown_request,
+    CloneOption, DelayedSlice, DirEntry, FileAttr, FileType, MetaError, OpenFlags, SetAttrFlags,
+    SetAttrRequest, StatFsSnapshot, chmod_request, chown_request,
 };
 use crate::vfs::handles::DirHandle;
 
@@ -78,6 +79,20 @@ pub trait MetaLayer: Send + Sync {
 
     async fn unlink(&self, parent: i64, name: &str) -> Result<(), MetaError>;
 
+    /// Clones `src` to `parent/name` sharing its data; see
+    /// [`MetaStore::clone_entry`](crate::meta::MetaStore::clone_entry).
+    async fn clone_entry(
+        &self,
+        src: i64,
+        parent: i64,
+        name: &str,
+        opt: &CloneOption,
+    ) -> Result<FileAttr, MetaError>;
+
+    /// Moves the trash entry `batch/entry` back to the directory and name it
+    /// was removed from; see [`crate::meta::trash`].
+    async fn restore_trash(&self, batch: &str, entry: &str) -> Result<FileAttr, MetaError>;
+
     async fn rename(
         &self,
         old_parent: i64,
@@ -234,6 +249,30 @@ pub trait MetaLayer: Send + Sync {
         new_size: u64,
     ) -> Result<(), MetaError>;
 
+    // ---------- Quota enforcement ----------
+
+    /// Fails with `MetaError::QuotaExceeded` if creating entries of `space`
+    /// bytes and `inodes` inodes in `parent`, owned by `uid`/`gid`, would
+    /// exceed a directory, user or group quota.
+    async fn check_quota(
+        &self,
+        parent: i64,
+        uid: u32,
+        gid: u32,
+        space: u64,
+        inodes: u64,
+    ) -> Result<(), MetaError> {
+        let _ = (parent, uid, gid, space, inodes);
+        Ok(())
+    }
+
+    /// Fails with `MetaError::QuotaExceeded` if growing `ino` to `new_size`
+    /// bytes would exceed one of its quotas.
+    async fn check_write_quota(&self, ino: i64, new_size: u64) -> Result<(), MetaError> {
+        let _ = (ino, new_size);
+        Ok(())
+    }
+
     // ---------- Metadata + ID utilities ----------
     async fn get_deleted_files(&self) -> Result<Vec<i64>, MetaError>;
 
@@ -243,6 +282,21 @@ pub trait MetaLayer: Send + Sync {
 
     async fn append_slice(&self, chunk_id: u64, slice: SliceDesc) -> Result<(), MetaError>;
 
+    /// Swaps the leading slices `old` of a chunk for their compacted `slice`;
+    /// see [`MetaStore::compact_chunk`](crate::meta::MetaStore::compact_chunk).
+    async fn compact_chunk(
+        &self,
+        chunk_id: u64,
+        old: &[SliceDesc],
+        slice: SliceDesc,
+    ) -> Result<bool, MetaError>;
+
+    /// Slices replaced by compaction before `edge_ts` whose blocks can go.
+    async fn list_delayed_slices(&self, edge_ts: i64) -> Result<Vec<DelayedSlice>, MetaError>;
+
+    /// Forgets a delayed slice once its blocks are deleted.
+    async fn delete_slice(&self, slice_id: u64) -> Result<(), MetaError>;
+
     async fn next_id(&self, key: &str) -> Result<i64, MetaError>;
 
     // ---------- Session lifecycle ----------
@@ -275,11 +329,15 @@ pub trait MetaLayer: Send + Sync {
     async fn get_xattr(&self, inode: i64, name: &str) -> Result<Option<Vec<u8>>, MetaError>;
     async fn list_xattr(&self, inode: i64) -> Result<Vec<String>, MetaError>;
     async fn remove_xattr(&self, inode: i64, name: &str) -> Result<(), MetaError>;
-    async fn set_acl(&self, inode: i64, rule: AclRule) -> Result<(), MetaError>;
-    async fn get_acl(
+    async fn set_acl(
         &self,
         inode: i64,
-        acl_type: u8,
-        acl_id: u32,
-    ) -> Result<Option<AclRule>, MetaError>;
+        acl_type: AclType,
+        rule: Option<&AclRule>,
+    ) -> Result<(), MetaError>;
+    async fn get_acl(&self, inode: i64, acl_type: AclType) -> Result<Option<AclRule>, MetaError>;
+    /// Applies the default ACL of `parent` to its new entry `ino` and returns
+    /// the creation `mode` restricted by it; call before setting the mode of
+    /// the new entry.
+    async fn inherit_acl(&self, parent: i64, ino: i64, mode: u32) -> Result<u32, MetaError>;
 }
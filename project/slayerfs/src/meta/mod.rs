@@ -12,18 +12,25 @@
 //! - Ensure critical write-path updates (blocks + slice_blocks + slices + inode.size)
 //!   are committed atomically.
 //!
+pub mod acl;
 pub(crate) mod backoff;
 pub mod client;
+pub mod clone;
 pub mod config;
+pub mod dump;
 pub(crate) mod entities;
 pub mod factory;
 pub mod file_lock;
+pub mod fsck;
 pub mod layer;
 pub(crate) mod migrations;
 pub mod permission;
+pub mod quota;
 pub(crate) mod serialization;
 pub mod store;
 pub mod stores;
+pub mod trash;
+pub mod volume;
 
 // Primary exports
 #[allow(dead_code)]
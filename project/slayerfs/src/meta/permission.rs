@@ -5,6 +5,8 @@ use sea_orm::{
 };
 use serde::{Deserialize, Serialize};
 
+use crate::meta::acl::AclRule;
+
 bitflags! {
     #[derive(
         Copy,
@@ -22,51 +24,20 @@ bitflags! {
     }
 }
 
-#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
-#[cfg_attr(
-    feature = "rkyv-serialization",
-    derive(rkyv::Archive, rkyv::Serialize, rkyv::Deserialize)
-)]
-pub enum AclSubject {
-    User(u32),
-    Group(u32),
-    Other,
-}
-
-#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
-pub struct AclEntry {
-    pub subject: AclSubject,
-    pub flags: AclFlags,
-}
-
-#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
-pub struct Acl {
-    pub entries: Vec<AclEntry>,
-}
-
-impl Acl {
-    pub fn check_permission(&self, uid: u32, gids: &[u32], flag: AclFlags) -> bool {
-        for entry in &self.entries {
-            match &entry.subject {
-                AclSubject::User(u) if *u == uid => {
-                    if entry.flags.contains(flag) {
-                        return true;
-                    }
-                }
-                AclSubject::Group(g) if gids.contains(g) => {
-                    if entry.flags.contains(flag) {
-                        return true;
-                    }
-                }
-                AclSubject::Other => {
-                    if entry.flags.contains(flag) {
-                        return true;
-                    }
-                }
-                _ => {}
-            }
+impl AclFlags {
+    /// The flags as `rwx` bits, laid out like the "other" bits of a mode.
+    pub fn rwx_bits(self) -> u32 {
+        let mut bits = 0;
+        if self.contains(AclFlags::READ) {
+            bits |= 0o4;
         }
-        false
+        if self.contains(AclFlags::WRITE) {
+            bits |= 0o2;
+        }
+        if self.contains(AclFlags::EXEC) {
+            bits |= 0o1;
+        }
+        bits
     }
 }
 
@@ -75,7 +46,10 @@ pub struct Permission {
     pub mode: u32,
     pub uid: u32,
     pub gid: u32,
-    pub acl: Option<Acl>,
+    /// Access ACL of the inode. ACLs are stored as extended attributes, so
+    /// this is filled in by callers that check access and never persisted.
+    #[serde(skip)]
+    pub acl: Option<AclRule>,
 }
 
 impl Permission {
@@ -115,40 +89,35 @@ impl Permission {
             acl: None,
         }
     }
-    pub fn check_access(
-        &self,
-        uid: u32,
-        gids: &[u32],
-        flag: AclFlags,
-        ugo_mask: u32,
-        group_mask: u32,
-        other_mask: u32,
-    ) -> bool {
+    /// Whether `uid` with the groups `gids` has all access in `flag`. With an
+    /// access ACL the POSIX ACL algorithm decides, otherwise the mode bits of
+    /// the class `uid` falls in. Root is always allowed.
+    pub fn check_access(&self, uid: u32, gids: &[u32], flag: AclFlags) -> bool {
         if uid == 0 {
             return true;
         }
-        if let Some(acl) = &self.acl
-            && acl.check_permission(uid, gids, flag)
-        {
-            return true;
+        let want = flag.rwx_bits();
+        if let Some(acl) = &self.acl {
+            return acl.allows(self.uid, self.gid, uid, gids, want as u16);
         }
         let perm_bits = self.permission_bits();
-        if uid == self.uid {
-            return perm_bits & ugo_mask != 0;
-        }
-        if gids.contains(&self.gid) {
-            return perm_bits & group_mask != 0;
-        }
-        perm_bits & other_mask != 0
+        let class_bits = if uid == self.uid {
+            perm_bits >> 6
+        } else if gids.contains(&self.gid) {
+            perm_bits >> 3
+        } else {
+            perm_bits
+        };
+        class_bits & want == want
     }
     pub fn can_read(&self, uid: u32, gids: &[u32]) -> bool {
-        self.check_access(uid, gids, AclFlags::READ, 0o400, 0o040, 0o004)
+        self.check_access(uid, gids, AclFlags::READ)
     }
     pub fn can_write(&self, uid: u32, gids: &[u32]) -> bool {
-        self.check_access(uid, gids, AclFlags::WRITE, 0o200, 0o020, 0o002)
+        self.check_access(uid, gids, AclFlags::WRITE)
     }
     pub fn can_execute(&self, uid: u32, gids: &[u32]) -> bool {
-        self.check_access(uid, gids, AclFlags::EXEC, 0o100, 0o010, 0o001)
+        self.check_access(uid, gids, AclFlags::EXEC)
     }
     pub fn chmod(&mut self, new_mode: u32) {
         let file_type = self.file_type_bits();
@@ -280,6 +249,31 @@ mod tests {
         assert_eq!(perm.gid, 2000);
     }
 
+    #[test]
+    fn test_acl_overrides_mode_bits() {
+        use crate::meta::acl::NamedEntry;
+
+        let mut perm = Permission::new(0o100640, 1000, 100);
+        assert!(!perm.can_write(1001, &[]));
+
+        let mut acl = AclRule::from_mode(0o640);
+        acl.mask = Some(0o6);
+        acl.named_users.push(NamedEntry {
+            id: 1001,
+            perm: 0o6,
+        });
+        perm.acl = Some(acl);
+        assert!(perm.can_read(1001, &[]));
+        assert!(perm.can_write(1001, &[]));
+        assert!(!perm.can_read(1002, &[]));
+        assert!(perm.can_write(0, &[]));
+
+        // The ACL lives in extended attributes, not in the stored permission.
+        let json = serde_json::to_string(&perm).unwrap();
+        let stored: Permission = serde_json::from_str(&json).unwrap();
+        assert_eq!(stored.acl, None);
+    }
+
     #[test]
     fn test_chown_preserves_mode() {
         let mut perm = Permission::default_file(0, 0);
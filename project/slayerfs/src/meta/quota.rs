@@ -0,0 +1,387 @@
+//! Directory, user and group quotas.
+//!
+//! Quotas live in the metadata store (see the quota APIs of [`MetaStore`]);
+//! each client keeps a [`QuotaCache`] with the limits and usage it last loaded
+//! plus the usage it changed locally since. Mutations are checked and charged
+//! against the cache and the accumulated deltas are flushed periodically with
+//! [`MetaStore::flush_quotas`], so the store is not hit on every write. Like
+//! JuiceFS, limits are therefore enforced per client and may be overrun by a
+//! small margin when several clients write below the same quota concurrently.
+
+use std::collections::{HashMap, HashSet, VecDeque};
+
+use dashmap::DashMap;
+
+use crate::meta::store::{DirStat, FileAttr, MetaError, MetaStore, Quota, QuotaDelta, QuotaType};
+use crate::meta::trash::TRASH_DIR;
+use crate::vfs::fs::FileType;
+
+/// Identifies a single quota: its scope and the directory inode, uid or gid.
+pub type QuotaKey = (QuotaType, u64);
+
+/// Usage that moves from one set of directory quotas to another on rename.
+#[derive(Debug, Clone)]
+pub struct QuotaMove {
+    pub from: Vec<QuotaKey>,
+    pub to: Vec<QuotaKey>,
+    pub space: i64,
+    pub inodes: i64,
+}
+
+/// Client-side view of all quotas plus usage changes not flushed yet.
+#[derive(Default)]
+pub struct QuotaCache {
+    quotas: DashMap<QuotaKey, Quota>,
+    pending: DashMap<QuotaKey, (i64, i64)>,
+}
+
+impl QuotaCache {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.quotas.is_empty()
+    }
+
+    /// Whether at least one quota of `qtype` is set.
+    pub fn has_type(&self, qtype: QuotaType) -> bool {
+        self.quotas.iter().any(|entry| entry.key().0 == qtype)
+    }
+
+    pub fn contains(&self, key: &QuotaKey) -> bool {
+        self.quotas.contains_key(key)
+    }
+
+    pub fn get(&self, key: &QuotaKey) -> Option<Quota> {
+        self.quotas.get(key).map(|quota| quota.clone())
+    }
+
+    pub fn remove(&self, key: &QuotaKey) {
+        self.quotas.remove(key);
+        self.pending.remove(key);
+    }
+
+    /// Replaces the cached quotas with freshly loaded ones. Deltas that are
+    /// still pending are applied on top so local usage is not lost.
+    pub fn replace(
+        &self,
+        dirs: HashMap<u64, Quota>,
+        users: HashMap<u64, Quota>,
+        groups: HashMap<u64, Quota>,
+    ) {
+        let loaded = [
+            (QuotaType::Dir, dirs),
+            (QuotaType::User, users),
+            (QuotaType::Group, groups),
+        ];
+        let mut keys = HashSet::new();
+        for (qtype, quotas) in loaded {
+            for (id, mut quota) in quotas {
+                let key = (qtype, id);
+                if let Some(delta) = self.pending.get(&key) {
+                    quota.used_space += delta.0;
+                    quota.used_inodes += delta.1;
+                }
+                self.quotas.insert(key, quota);
+                keys.insert(key);
+            }
+        }
+        self.quotas.retain(|key, _| keys.contains(key));
+        self.pending.retain(|key, _| keys.contains(key));
+    }
+
+    /// Fails with [`MetaError::QuotaExceeded`] if adding `space` bytes and
+    /// `inodes` inodes would exceed any of the quotas in `keys`.
+    pub fn check(&self, keys: &[QuotaKey], space: i64, inodes: i64) -> Result<(), MetaError> {
+        let exceeded = keys.iter().any(|key| {
+            self.quotas
+                .get(key)
+                .is_some_and(|quota| quota.exceeded(space, inodes))
+        });
+        if exceeded {
+            Err(MetaError::QuotaExceeded)
+        } else {
+            Ok(())
+        }
+    }
+
+    /// Adds usage to every quota in `keys` that is set; keys without a quota
+    /// are ignored.
+    pub fn charge(&self, keys: &[QuotaKey], space: i64, inodes: i64) {
+        if space == 0 && inodes == 0 {
+            return;
+        }
+        for key in keys {
+            let Some(mut quota) = self.quotas.get_mut(key) else {
+                continue;
+            };
+            quota.used_space += space;
+            quota.used_inodes += inodes;
+            let mut delta = self.pending.entry(*key).or_default();
+            delta.0 += space;
+            delta.1 += inodes;
+        }
+    }
+
+    pub fn check_move(&self, mv: &QuotaMove) -> Result<(), MetaError> {
+        self.check(&mv.to, mv.space, mv.inodes)
+    }
+
+    pub fn apply_move(&self, mv: &QuotaMove) {
+        self.charge(&mv.from, -mv.space, -mv.inodes);
+        self.charge(&mv.to, mv.space, mv.inodes);
+    }
+
+    /// Drains the accumulated usage deltas for flushing to the store.
+    pub fn take_pending(&self) -> Vec<QuotaDelta> {
+        let keys: Vec<QuotaKey> = self.pending.iter().map(|entry| *entry.key()).collect();
+        keys.into_iter()
+            .filter_map(|key| self.pending.remove(&key))
+            .filter(|(_, (space, inodes))| *space != 0 || *inodes != 0)
+            .map(|((qtype, key), (space_delta, inode_delta))| QuotaDelta {
+                qtype: qtype.into(),
+                key,
+                space_delta,
+                inode_delta,
+            })
+            .collect()
+    }
+
+    /// Puts deltas back after a failed flush so they are retried later.
+    pub fn restore_pending(&self, deltas: Vec<QuotaDelta>) {
+        for delta in deltas {
+            let Ok(qtype) = QuotaType::try_from(delta.qtype) else {
+                continue;
+            };
+            let mut pending = self.pending.entry((qtype, delta.key)).or_default();
+            pending.0 += delta.space_delta;
+            pending.1 += delta.inode_delta;
+        }
+    }
+}
+
+/// Space an inode is charged for: the logical size of files and symlinks.
+pub fn charged_space(attr: &FileAttr) -> i64 {
+    match attr.kind {
+        FileType::File | FileType::Symlink => attr.size as i64,
+        _ => 0,
+    }
+}
+
+/// Files in the trash are not charged to any quota.
+fn is_trash(root: i64, parent: i64, name: &str) -> bool {
+    parent == root && name == TRASH_DIR
+}
+
+/// Computes the usage below `dir`, excluding `dir` itself. Hard links are
+/// counted once.
+pub async fn subtree_usage<S>(store: &S, dir: i64) -> Result<DirStat, MetaError>
+where
+    S: MetaStore + ?Sized,
+{
+    let root = store.root_ino();
+    let mut stat = DirStat::default();
+    let mut seen = HashSet::new();
+    let mut queue = VecDeque::from([dir]);
+    while let Some(current) = queue.pop_front() {
+        for entry in store.readdir(current).await? {
+            if is_trash(root, current, &entry.name) || !seen.insert(entry.ino) {
+                continue;
+            }
+            let Some(attr) = store.stat(entry.ino).await? else {
+                continue;
+            };
+            stat.space += charged_space(&attr);
+            stat.inodes += 1;
+            if attr.kind == FileType::Dir {
+                queue.push_back(entry.ino);
+            }
+        }
+    }
+    Ok(stat)
+}
+
+/// Computes the usage of everything owned by the user or group quota `key`,
+/// scanning the whole tree from the root.
+pub async fn owner_usage<S>(store: &S, key: QuotaKey) -> Result<DirStat, MetaError>
+where
+    S: MetaStore + ?Sized,
+{
+    let owned = |attr: &FileAttr| match key.0 {
+        QuotaType::User => attr.uid as u64 == key.1,
+        QuotaType::Group => attr.gid as u64 == key.1,
+        QuotaType::Dir => false,
+    };
+
+    let root = store.root_ino();
+    let mut stat = DirStat::default();
+    if let Some(attr) = store.stat(root).await?
+        && owned(&attr)
+    {
+        stat.inodes += 1;
+    }
+
+    let mut seen = HashSet::from([root]);
+    let mut queue = VecDeque::from([root]);
+    while let Some(current) = queue.pop_front() {
+        for entry in store.readdir(current).await? {
+            if is_trash(root, current, &entry.name) || !seen.insert(entry.ino) {
+                continue;
+            }
+            let Some(attr) = store.stat(entry.ino).await? else {
+                continue;
+            };
+            if owned(&attr) {
+                stat.space += charged_space(&attr);
+                stat.inodes += 1;
+            }
+            if attr.kind == FileType::Dir {
+                queue.push_back(entry.ino);
+            }
+        }
+    }
+    Ok(stat)
+}
+
+/// Usage of a whole tree, in total and per owning user and group.
+#[derive(Debug, Default)]
+pub struct TreeUsage {
+    pub total: DirStat,
+    pub users: HashMap<u32, DirStat>,
+    pub groups: HashMap<u32, DirStat>,
+}
+
+impl TreeUsage {
+    fn add(&mut self, attr: &FileAttr) {
+        let space = charged_space(attr);
+        for stat in [
+            &mut self.total,
+            self.users.entry(attr.uid).or_default(),
+            self.groups.entry(attr.gid).or_default(),
+        ] {
+            stat.space += space;
+            stat.inodes += 1;
+        }
+    }
+}
+
+/// Computes the usage of `top` and everything below it. Hard links are
+/// counted once.
+pub async fn tree_usage<S>(store: &S, top: &FileAttr) -> Result<TreeUsage, MetaError>
+where
+    S: MetaStore + ?Sized,
+{
+    let mut usage = TreeUsage::default();
+    usage.add(top);
+    if top.kind != FileType::Dir {
+        return Ok(usage);
+    }
+
+    let mut seen = HashSet::from([top.ino]);
+    let mut queue = VecDeque::from([top.ino]);
+    while let Some(current) = queue.pop_front() {
+        for entry in store.readdir(current).await? {
+            if !seen.insert(entry.ino) {
+                continue;
+            }
+            let Some(attr) = store.stat(entry.ino).await? else {
+                continue;
+            };
+            usage.add(&attr);
+            if attr.kind == FileType::Dir {
+                queue.push_back(entry.ino);
+            }
+        }
+    }
+    Ok(usage)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn limited(space: Option<i64>, inodes: Option<i64>) -> Quota {
+        Quota {
+            limit_space: space,
+            limit_inodes: inodes,
+            ..Default::default()
+        }
+    }
+
+    fn cache_with(key: QuotaKey, quota: Quota) -> QuotaCache {
+        let cache = QuotaCache::new();
+        let mut maps: [HashMap<u64, Quota>; 3] = Default::default();
+        maps[u32::from(key.0) as usize].insert(key.1, quota);
+        let [dirs, users, groups] = maps;
+        cache.replace(dirs, users, groups);
+        cache
+    }
+
+    #[test]
+    fn test_check_and_charge() {
+        let key = (QuotaType::Dir, 2);
+        let cache = cache_with(key, limited(Some(100), Some(2)));
+
+        cache.check(&[key], 100, 1).unwrap();
+        cache.charge(&[key], 60, 1);
+        assert!(matches!(
+            cache.check(&[key], 50, 0),
+            Err(MetaError::QuotaExceeded)
+        ));
+        cache.check(&[key], 0, 1).unwrap();
+        cache.charge(&[key], 0, 1);
+        assert!(matches!(
+            cache.check(&[key], 0, 1),
+            Err(MetaError::QuotaExceeded)
+        ));
+        // Releasing usage is always allowed.
+        cache.check(&[key], -10, -1).unwrap();
+
+        let used = cache.get(&key).unwrap();
+        assert_eq!((used.used_space, used.used_inodes), (60, 2));
+    }
+
+    #[test]
+    fn test_unknown_keys_are_ignored() {
+        let cache = cache_with((QuotaType::User, 1000), limited(None, Some(1)));
+        let other = (QuotaType::User, 1001);
+
+        cache.charge(&[other], 10, 10);
+        cache.check(&[other], 10, 10).unwrap();
+        assert!(cache.take_pending().is_empty());
+    }
+
+    #[test]
+    fn test_pending_survives_reload() {
+        let key = (QuotaType::Group, 100);
+        let cache = cache_with(key, limited(Some(1000), None));
+        cache.charge(&[key], 300, 3);
+
+        // A reload from the store that does not include the local deltas yet.
+        let mut groups = HashMap::new();
+        groups.insert(100, limited(Some(1000), None));
+        cache.replace(HashMap::new(), HashMap::new(), groups);
+        assert_eq!(cache.get(&key).unwrap().used_space, 300);
+
+        let deltas = cache.take_pending();
+        assert_eq!(deltas.len(), 1);
+        assert_eq!(deltas[0].qtype, u32::from(QuotaType::Group));
+        assert_eq!((deltas[0].space_delta, deltas[0].inode_delta), (300, 3));
+        assert!(cache.take_pending().is_empty());
+
+        cache.restore_pending(deltas);
+        assert_eq!(cache.take_pending().len(), 1);
+    }
+
+    #[test]
+    fn test_reload_drops_deleted_quotas() {
+        let key = (QuotaType::Dir, 5);
+        let cache = cache_with(key, limited(Some(10), None));
+        cache.charge(&[key], 5, 1);
+
+        cache.replace(HashMap::new(), HashMap::new(), HashMap::new());
+        assert!(cache.is_empty());
+        assert!(cache.take_pending().is_empty());
+    }
+}
@@ -2,10 +2,12 @@
 //!
 //! Defines unified interface for filesystem metadata operations
 use crate::chuck::SliceDesc;
+use crate::meta::acl::{AclRule, AclType};
 use crate::meta::client::session::{Session, SessionInfo};
 use crate::meta::entities::content_meta::EntryType;
 use crate::meta::file_lock::{FileLockInfo, FileLockQuery, FileLockRange, FileLockType};
 use async_trait::async_trait;
+use serde::{Deserialize, Serialize};
 use std::collections::HashMap;
 use std::fmt;
 use std::time::SystemTime;
@@ -13,7 +15,7 @@ use tokio_util::sync::CancellationToken;
 use uuid::Uuid;
 
 /// File type enumeration
-#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
 pub enum FileType {
     File,
     Dir,
@@ -31,7 +33,7 @@ impl From<EntryType> for FileType {
 }
 
 /// File attributes
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, Serialize, Deserialize)]
 #[allow(dead_code)]
 pub struct FileAttr {
     pub ino: i64,
@@ -163,15 +165,62 @@ pub struct DirEntryPlus {
 }
 
 /// Directory statistics used for quota/accounting updates
-#[derive(Debug, Clone, Default)]
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
 #[allow(dead_code)]
 pub struct DirStat {
     pub space: i64,
     pub inodes: i64,
 }
 
-/// Quota information for a key (user/group/project)
-#[derive(Debug, Clone, Default)]
+/// Scope of a quota; passed to the quota APIs as `qtype`.
+///
+/// Directory quotas are keyed by the directory inode and cover everything
+/// below it, user and group quotas are keyed by uid/gid.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub enum QuotaType {
+    Dir = 0,
+    User = 1,
+    Group = 2,
+}
+
+impl QuotaType {
+    pub const ALL: [QuotaType; 3] = [QuotaType::Dir, QuotaType::User, QuotaType::Group];
+}
+
+impl From<QuotaType> for u32 {
+    fn from(qtype: QuotaType) -> Self {
+        qtype as u32
+    }
+}
+
+impl TryFrom<u32> for QuotaType {
+    type Error = MetaError;
+
+    fn try_from(value: u32) -> Result<Self, Self::Error> {
+        match value {
+            0 => Ok(QuotaType::Dir),
+            1 => Ok(QuotaType::User),
+            2 => Ok(QuotaType::Group),
+            other => Err(MetaError::NotSupported(format!("quota type {other}"))),
+        }
+    }
+}
+
+impl fmt::Display for QuotaType {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            QuotaType::Dir => write!(f, "dir"),
+            QuotaType::User => write!(f, "user"),
+            QuotaType::Group => write!(f, "group"),
+        }
+    }
+}
+
+/// Quota information for a key (directory/user/group)
+///
+/// Space is the logical size of regular files and symlinks in bytes; every
+/// file, directory and symlink counts as one inode.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
 #[allow(dead_code)]
 pub struct Quota {
     pub limit_space: Option<i64>,
@@ -180,15 +229,40 @@ pub struct Quota {
     pub used_inodes: i64,
 }
 
+impl Quota {
+    /// Whether adding `space` bytes and `inodes` inodes would go over a limit.
+    /// Releasing usage never exceeds a quota.
+    pub fn exceeded(&self, space: i64, inodes: i64) -> bool {
+        let over = |limit: Option<i64>, used: i64, delta: i64| {
+            delta > 0 && limit.is_some_and(|limit| used.saturating_add(delta) > limit)
+        };
+        over(self.limit_space, self.used_space, space)
+            || over(self.limit_inodes, self.used_inodes, inodes)
+    }
+}
+
 /// Incremental quota delta awaiting flush
 #[derive(Debug, Clone, Default)]
 #[allow(dead_code)]
 pub struct QuotaDelta {
+    pub qtype: u32,
     pub key: u64,
     pub space_delta: i64,
     pub inode_delta: i64,
 }
 
+/// A slice replaced by compaction that no chunk references any more. Its
+/// blocks are deleted only after a delay, so readers that fetched the old
+/// slice list can finish.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub struct DelayedSlice {
+    pub slice_id: u64,
+    /// Bytes of the slice; its blocks are `0..ceil(length / block_size)`.
+    pub length: u64,
+    /// When the slice was replaced, in nanoseconds since the epoch.
+    pub since: i64,
+}
+
 /// Metadata engine runtime statistics snapshot
 #[derive(Debug, Clone, Default)]
 #[allow(dead_code)]
@@ -197,48 +271,100 @@ pub struct VolumeStat {
     pub inode_count: i64,
 }
 
-/// ACL rule placeholder (to be fleshed out once ACL storage lands)
-#[derive(Debug, Clone, Default)]
-#[allow(dead_code)]
-pub struct AclRule {
-    pub acl_type: u8,
-    pub qualifier: u32,
-    pub permissions: u32,
-}
-
 /// Options used by metadata dump API
 #[derive(Debug, Clone, Default)]
 #[allow(dead_code)]
 pub struct DumpOption {
+    /// Inodes that were unlinked but not yet garbage collected are not part
+    /// of the namespace; asking for them is rejected with `NotSupported`.
     pub include_deleted: bool,
+    /// Stop after this many entries.
     pub limit: Option<usize>,
+    /// Chunk size of the volume, needed to enumerate the chunks of a file.
+    pub chunk_size: u64,
 }
 
 /// Result row produced by metadata dump streaming API
-#[derive(Debug, Clone)]
+///
+/// A dump is a [`DumpRecord::Header`], the volume settings, the entries of
+/// the namespace in breadth-first order (so a directory always precedes its
+/// children) and a closing [`DumpRecord::Counters`].
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
 #[allow(dead_code)]
-pub struct DumpRecord {
+pub enum DumpRecord {
+    Header {
+        version: u32,
+        chunk_size: u64,
+    },
+    /// A volume setting, such as the block format needed to read the data.
+    Setting {
+        name: String,
+        value: String,
+    },
+    Entry(DumpEntry),
+    /// Next ids to allocate, so loaded slices never collide with new ones.
+    Counters {
+        next_inode: i64,
+        next_slice: i64,
+    },
+}
+
+/// A directory entry and, on the first entry of an inode, its content.
+/// Further entries of the same inode are hard links and only carry the name.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[allow(dead_code)]
+pub struct DumpEntry {
     pub inode: i64,
-    pub path: Option<String>,
+    /// Parent inode; 0 for the root.
+    pub parent: i64,
+    pub name: String,
+    pub path: String,
     pub attr: FileAttr,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub symlink_target: Option<String>,
+    /// Extended attributes with base64 encoded values.
+    #[serde(default, skip_serializing_if = "HashMap::is_empty")]
+    pub xattrs: HashMap<String, String>,
+    /// Slices of every chunk, keyed by chunk index.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub chunks: Vec<(u64, Vec<SliceDesc>)>,
 }
 
 /// Options used during metadata bulk load operations
 #[derive(Debug, Clone, Default)]
 #[allow(dead_code)]
 pub struct LoadOption {
+    /// Merge into existing directories instead of failing on the first
+    /// entry that already exists; existing files are left untouched.
     pub allow_conflicts: bool,
 }
 
+/// Options of [`MetaStore::clone_entry`]
+#[derive(Debug, Clone, Default)]
+#[allow(dead_code)]
+pub struct CloneOption {
+    /// Keep owner, mode and timestamps of the source. Otherwise the clone is
+    /// owned by `uid`/`gid` and its modes are masked with `umask`.
+    pub preserve_attr: bool,
+    pub uid: u32,
+    pub gid: u32,
+    pub umask: u16,
+    /// Chunk size of the volume, needed to enumerate the chunks of a file.
+    pub chunk_size: u64,
+}
+
 #[derive(Debug)]
 pub enum LockName {
     CleanupSessionsLock,
+    CleanupTrashLock,
 }
 
 impl fmt::Display for LockName {
     fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
         match self {
             LockName::CleanupSessionsLock => write!(f, "CleanupSessionsLock"),
+            LockName::CleanupTrashLock => write!(f, "CleanupTrashLock"),
         }
     }
 }
@@ -299,6 +425,18 @@ pub enum MetaError {
     #[error("Not implemented")]
     NotImplemented,
 
+    #[error("Quota exceeded")]
+    QuotaExceeded,
+
+    #[error("Invalid ACL: {0}")]
+    InvalidAcl(String),
+
+    #[error("Invalid extended attribute name: {0}")]
+    InvalidXattrName(String),
+
+    #[error("Extended attribute value too large")]
+    XattrTooLarge,
+
     #[error("Internal error: {0}")]
     Internal(String),
 
@@ -472,6 +610,8 @@ pub trait MetaStore: Send + Sync {
     /// Returns all file inodes marked for deletion (for garbage collection)
     async fn get_deleted_files(&self) -> Result<Vec<i64>, MetaError>;
 
+    /// Removes a file marked for deletion. Its chunks drop their references
+    /// to their slices as in [`Self::compact_chunk`].
     async fn remove_file_metadata(&self, ino: i64) -> Result<(), MetaError>;
 
     async fn get_slices(&self, chunk_id: u64) -> Result<Vec<SliceDesc>, MetaError>;
@@ -492,6 +632,7 @@ pub trait MetaStore: Send + Sync {
 
     // ---------- Counter & statistics helpers ----------
 
+    /// Returns the next id [`MetaStore::next_id`] would hand out for `name`.
     async fn get_counter(&self, name: &str) -> Result<i64, MetaError> {
         let _ = name;
         Err(MetaError::NotImplemented)
@@ -502,6 +643,8 @@ pub trait MetaStore: Send + Sync {
         Err(MetaError::NotImplemented)
     }
 
+    /// Raises counter `name` to `value` unless it is already above
+    /// `value - diff`; returns whether the counter changed.
     async fn set_counter_if_small(
         &self,
         name: &str,
@@ -521,6 +664,27 @@ pub trait MetaStore: Send + Sync {
         Err(MetaError::NotImplemented)
     }
 
+    // ---------- Volume settings ----------
+
+    /// Returns the volume setting `name`, a document every client of the
+    /// volume has to agree on (e.g. the block format).
+    async fn get_setting(&self, name: &str) -> Result<Option<String>, MetaError> {
+        let _ = name;
+        Err(MetaError::NotImplemented)
+    }
+
+    /// Stores the volume setting `name`. Unless `overwrite` is set an existing
+    /// value is kept; returns whether `value` was written.
+    async fn set_setting(
+        &self,
+        name: &str,
+        value: &str,
+        overwrite: bool,
+    ) -> Result<bool, MetaError> {
+        let _ = (name, value, overwrite);
+        Err(MetaError::NotImplemented)
+    }
+
     // ---------- Session lifecycle ----------
 
     async fn start_session(
@@ -634,46 +798,72 @@ pub trait MetaStore: Send + Sync {
         Err(MetaError::NotImplemented)
     }
 
+    /// Releases the slices held back by the trash: removes the trash batches
+    /// that ended before `edge_ts` (nanoseconds since the epoch), so their
+    /// files become regular deleted files for the garbage collector. Entries
+    /// of newer batches keep their slices. Returns the number of entries
+    /// removed.
     async fn cleanup_delayed_slices(&self, edge_ts: i64) -> Result<i32, MetaError> {
+        crate::meta::trash::cleanup_expired(self, edge_ts).await
+    }
+
+    /// Returns the delayed slices replaced before `edge_ts` (nanoseconds since
+    /// the epoch).
+    async fn list_delayed_slices(&self, edge_ts: i64) -> Result<Vec<DelayedSlice>, MetaError> {
         let _ = edge_ts;
         Err(MetaError::NotImplemented)
     }
 
-    async fn delete_slice(&self, slice_id: u64, size: u32) -> Result<(), MetaError> {
-        let _ = (slice_id, size);
+    /// Forgets the delayed slice `slice_id` once its blocks are deleted.
+    async fn delete_slice(&self, slice_id: u64) -> Result<(), MetaError> {
+        let _ = slice_id;
+        Err(MetaError::NotImplemented)
+    }
+
+    /// Adds `delta` to the reference count of every slice in `slices`.
+    /// Slices are referenced once by the chunk they were written to; clones
+    /// add a reference per chunk that shares them.
+    async fn update_slice_refs(&self, slices: &[u64], delta: i64) -> Result<(), MetaError> {
+        let _ = (slices, delta);
+        Err(MetaError::NotImplemented)
+    }
+
+    /// Returns the number of chunks referencing `slice_id`.
+    async fn get_slice_refs(&self, slice_id: u64) -> Result<i64, MetaError> {
+        let _ = slice_id;
         Err(MetaError::NotImplemented)
     }
 
     // ---------- Directory maintenance ----------
 
+    /// Clones `src` (a file, symlink or directory tree) to `parent/name`
+    /// without copying data: the clone shares the slices of the source and
+    /// raises their reference counts. The clone is built as a detached node
+    /// and only attached under its name once complete.
     async fn clone_entry(
         &self,
         src: i64,
         parent: i64,
         name: &str,
-        ino: i64,
-        attr: &mut FileAttr,
-        cmode: u8,
-        cumask: u16,
-        top: bool,
-    ) -> Result<(), MetaError> {
-        let _ = (src, parent, name, ino, attr, cmode, cumask, top);
-        Err(MetaError::NotImplemented)
+        opt: &CloneOption,
+    ) -> Result<FileAttr, MetaError> {
+        crate::meta::clone::clone_entry(self, src, parent, name, opt).await
     }
 
+    /// Moves the detached node `dst` to `parent/name`.
     async fn attach_dir_node(&self, parent: i64, dst: i64, name: &str) -> Result<(), MetaError> {
-        let _ = (parent, dst, name);
-        Err(MetaError::NotImplemented)
+        crate::meta::clone::attach_node(self, parent, dst, name).await
     }
 
+    /// Detached nodes created before `since`, left behind by interrupted
+    /// clones.
     async fn find_detached_nodes(&self, since: SystemTime) -> Result<Vec<i64>, MetaError> {
-        let _ = since;
-        Err(MetaError::NotImplemented)
+        crate::meta::clone::find_detached_nodes(self, since).await
     }
 
+    /// Removes the detached node `inode` and everything below it.
     async fn cleanup_detached_node(&self, inode: i64) -> Result<(), MetaError> {
-        let _ = inode;
-        Err(MetaError::NotImplemented)
+        crate::meta::clone::cleanup_detached_node(self, inode).await
     }
 
     /// Returns directory statistics map keyed by parent inode.
@@ -703,11 +893,13 @@ pub trait MetaStore: Send + Sync {
 
     // ---------- Quota management ----------
 
+    /// Returns the quota of `key` for the [`QuotaType`] `qtype`, if one is set.
     async fn get_quota(&self, qtype: u32, key: u64) -> Result<Option<Quota>, MetaError> {
         let _ = (qtype, key);
         Err(MetaError::NotImplemented)
     }
 
+    /// Stores limits and usage of a quota; returns true if it did not exist before.
     async fn set_quota(&self, qtype: u32, key: u64, quota: Quota) -> Result<bool, MetaError> {
         let _ = (qtype, key, quota);
         Err(MetaError::NotImplemented)
@@ -718,6 +910,7 @@ pub trait MetaStore: Send + Sync {
         Err(MetaError::NotImplemented)
     }
 
+    /// Loads all quotas as (directory, user, group) maps.
     async fn load_quotas(
         &self,
     ) -> Result<
@@ -731,6 +924,8 @@ pub trait MetaStore: Send + Sync {
         Err(MetaError::NotImplemented)
     }
 
+    /// Atomically adds usage deltas to existing quotas; deltas for quotas that
+    /// no longer exist are dropped. On error none of the deltas were applied.
     async fn flush_quotas(&self, deltas: &[QuotaDelta]) -> Result<(), MetaError> {
         let _ = deltas;
         Err(MetaError::NotImplemented)
@@ -747,18 +942,21 @@ pub trait MetaStore: Send + Sync {
         Err(MetaError::NotImplemented)
     }
 
+    /// Streams the whole namespace to `visitor`. The dump only uses the
+    /// generic operations of this trait, so it can be loaded into any backend.
     async fn dump(
         &self,
         opt: DumpOption,
         visitor: &mut dyn Visitor<DumpRecord>,
     ) -> Result<(), MetaError> {
-        let _ = (opt, visitor);
-        Err(MetaError::NotImplemented)
+        crate::meta::dump::dump_namespace(self, opt, visitor).await
     }
 
+    /// Recreates a namespace from a dump of JSON lines below the root.
+    /// Inodes get new numbers; slices keep their ids so file data stored in
+    /// the object store stays reachable.
     async fn load(&self, opt: LoadOption, data: &[u8]) -> Result<(), MetaError> {
-        let _ = (opt, data);
-        Err(MetaError::NotImplemented)
+        crate::meta::dump::load_namespace(self, opt, data).await
     }
 
     // ---------- Extended attribute & ACL ----------
@@ -789,23 +987,20 @@ pub trait MetaStore: Send + Sync {
         Err(MetaError::NotImplemented)
     }
 
-    async fn cache_acls(&self) -> Result<(), MetaError> {
-        Err(MetaError::NotImplemented)
-    }
-
-    async fn set_acl(&self, inode: i64, rule: AclRule) -> Result<(), MetaError> {
-        let _ = (inode, rule);
-        Err(MetaError::NotImplemented)
-    }
-
-    async fn get_acl(
+    /// Sets or, with `None`, removes an ACL. Setting the access ACL also
+    /// updates the permission bits of the mode to match it.
+    async fn set_acl(
         &self,
         inode: i64,
-        acl_type: u8,
-        acl_id: u32,
-    ) -> Result<Option<AclRule>, MetaError> {
-        let _ = (inode, acl_type, acl_id);
-        Err(MetaError::NotImplemented)
+        acl_type: AclType,
+        rule: Option<&AclRule>,
+    ) -> Result<(), MetaError> {
+        crate::meta::acl::set_acl(self, inode, acl_type, rule).await
+    }
+
+    /// Returns the ACL of `inode`, if it has one beyond its mode bits.
+    async fn get_acl(&self, inode: i64, acl_type: AclType) -> Result<Option<AclRule>, MetaError> {
+        crate::meta::acl::get_acl(self, inode, acl_type).await
     }
 
     // ---------- File data path helpers ----------
@@ -865,21 +1060,19 @@ pub trait MetaStore: Send + Sync {
         Err(MetaError::NotImplemented)
     }
 
+    /// Replaces `old`, the leading slices of `chunk_id` that were compacted,
+    /// with the compacted `slice` in one atomic step; slices appended since
+    /// stay after it. Every replaced descriptor drops a reference to its
+    /// slice, and slices left without references become delayed slices.
+    /// Returns false and changes nothing if the chunk no longer starts with
+    /// `old`, e.g. because it was truncated meanwhile.
     async fn compact_chunk(
         &self,
-        inode: i64,
-        index: u32,
-        origin: &[u8],
-        slices: &[SliceDesc],
-        skipped: i32,
-        pos: u32,
-        id: u64,
-        size: u32,
-        delayed: &[u8],
-    ) -> Result<(), MetaError> {
-        let _ = (
-            inode, index, origin, slices, skipped, pos, id, size, delayed,
-        );
+        chunk_id: u64,
+        old: &[SliceDesc],
+        slice: SliceDesc,
+    ) -> Result<bool, MetaError> {
+        let _ = (chunk_id, old, slice);
         Err(MetaError::NotImplemented)
     }
     // ---------- File lock ----------
@@ -2,22 +2,26 @@
 //!
 //! Supports SQLite and PostgreSQL backends via SeaORM
 
-use super::{TrimAction, apply_truncate_plan, trim_action};
+use super::{TrimAction, apply_truncate_plan, check_xattr, released_slices, trim_action};
 use crate::chuck::SliceDesc;
 use crate::meta::client::session::{Session, SessionInfo};
 use crate::meta::config::{Config, DatabaseType};
 use crate::meta::entities::counter_meta;
+use crate::meta::entities::delayed_slice_meta;
 use crate::meta::entities::link_parent_meta;
+use crate::meta::entities::quota_meta;
 use crate::meta::entities::session_meta::{self, Entity as SessionMeta};
+use crate::meta::entities::setting_meta;
 use crate::meta::entities::slice_meta::{self, Entity as SliceMeta};
+use crate::meta::entities::slice_ref_meta;
 use crate::meta::entities::xattr_meta;
 use crate::meta::entities::*;
 use crate::meta::file_lock::{
     FileLockInfo, FileLockQuery, FileLockRange, FileLockType, PlockRecord,
 };
 use crate::meta::store::{
-    DirEntry, FileAttr, LockName, MetaError, MetaStore, OpenFlags, SetAttrFlags, SetAttrRequest,
-    StatFsSnapshot,
+    DelayedSlice, DirEntry, FileAttr, LockName, MetaError, MetaStore, OpenFlags, Quota, QuotaDelta,
+    QuotaType, SetAttrFlags, SetAttrRequest, StatFsSnapshot,
 };
 use crate::meta::{INODE_ID_KEY, Permission, SLICE_ID_KEY};
 use crate::utils::NumCastExt;
@@ -323,6 +327,22 @@ impl DatabaseMetaStore {
                 .create_table_from_entity(XattrMeta)
                 .if_not_exists()
                 .to_owned(),
+            schema
+                .create_table_from_entity(QuotaMeta)
+                .if_not_exists()
+                .to_owned(),
+            schema
+                .create_table_from_entity(SliceRefMeta)
+                .if_not_exists()
+                .to_owned(),
+            schema
+                .create_table_from_entity(SettingMeta)
+                .if_not_exists()
+                .to_owned(),
+            schema
+                .create_table_from_entity(DelayedSliceMeta)
+                .if_not_exists()
+                .to_owned(),
         ];
 
         for (i, stmt) in stmts.iter().enumerate() {
@@ -651,6 +671,7 @@ impl DatabaseMetaStore {
                     .await
                     .map_err(MetaError::Database)?;
 
+                let mut dropped = Vec::new();
                 for row in rows {
                     debug_assert!(row.offset >= 0);
                     debug_assert!(row.length >= 0);
@@ -660,6 +681,7 @@ impl DatabaseMetaStore {
                     match trim_action(offset, length, cutoff_offset) {
                         TrimAction::Keep => {}
                         TrimAction::Drop => {
+                            dropped.push(SliceDesc::from(row.clone()));
                             let active: slice_meta::ActiveModel = row.into();
                             active.delete(conn).await.map_err(MetaError::Database)?;
                         }
@@ -670,25 +692,97 @@ impl DatabaseMetaStore {
                         }
                     }
                 }
-                Ok(())
+                Self::release_slices(conn, &dropped).await
             },
             |start, end| async move {
                 let start_chunk_id = i64::try_from(chunk_id_for(ino, start)?)
                     .map_err(|_| MetaError::Internal("chunk_id overflow".to_string()))?;
                 let end_chunk_id = i64::try_from(chunk_id_for(ino, end)?)
                     .map_err(|_| MetaError::Internal("chunk_id overflow".to_string()))?;
-                SliceMeta::delete_many()
-                    .filter(slice_meta::Column::ChunkId.gte(start_chunk_id))
-                    .filter(slice_meta::Column::ChunkId.lt(end_chunk_id))
-                    .exec(conn)
-                    .await
-                    .map_err(MetaError::Database)?;
-                Ok(())
+                Self::drop_chunk_slices(conn, start_chunk_id, end_chunk_id).await
             },
         )
         .await
     }
 
+    /// Deletes the slices of the chunks in `start..end` and releases their
+    /// references.
+    async fn drop_chunk_slices<C>(conn: &C, start: i64, end: i64) -> Result<(), MetaError>
+    where
+        C: ConnectionTrait,
+    {
+        let rows = SliceMeta::find()
+            .filter(slice_meta::Column::ChunkId.gte(start))
+            .filter(slice_meta::Column::ChunkId.lt(end))
+            .all(conn)
+            .await
+            .map_err(MetaError::Database)?;
+        if rows.is_empty() {
+            return Ok(());
+        }
+        SliceMeta::delete_many()
+            .filter(slice_meta::Column::ChunkId.gte(start))
+            .filter(slice_meta::Column::ChunkId.lt(end))
+            .exec(conn)
+            .await
+            .map_err(MetaError::Database)?;
+        let dropped: Vec<SliceDesc> = rows.into_iter().map(Into::into).collect();
+        Self::release_slices(conn, &dropped).await
+    }
+
+    /// Drops the reference every descriptor in `dropped` holds to its slice;
+    /// slices left without references become delayed slices.
+    async fn release_slices<C>(conn: &C, dropped: &[SliceDesc]) -> Result<(), MetaError>
+    where
+        C: ConnectionTrait,
+    {
+        let since = Self::now_nanos();
+        for (slice_id, refs, length) in released_slices(dropped) {
+            let extra = SliceRefMeta::find_by_id(slice_id as i64)
+                .one(conn)
+                .await
+                .map_err(MetaError::Database)?
+                .map_or(0, |row| row.extra_refs);
+            // Still shared with clones, which hold the extra references.
+            if extra >= refs {
+                let row = slice_ref_meta::ActiveModel {
+                    slice_id: Set(slice_id as i64),
+                    extra_refs: Set(extra - refs),
+                };
+                row.update(conn).await.map_err(MetaError::Database)?;
+                continue;
+            }
+            SliceRefMeta::delete_by_id(slice_id as i64)
+                .exec(conn)
+                .await
+                .map_err(MetaError::Database)?;
+            let delayed = delayed_slice_meta::ActiveModel {
+                slice_id: Set(slice_id as i64),
+                length: Set(length.as_i64()),
+                since: Set(since),
+            };
+            delayed.insert(conn).await.map_err(MetaError::Database)?;
+        }
+        Ok(())
+    }
+
+    /// Primary key of the quota row for `(qtype, key)`.
+    fn quota_id(qtype: u32, key: u64) -> Result<(i32, i64), MetaError> {
+        let qtype = QuotaType::try_from(qtype)?;
+        let key = i64::try_from(key)
+            .map_err(|_| MetaError::Internal(format!("quota key {key} out of range")))?;
+        Ok((u32::from(qtype) as i32, key))
+    }
+
+    fn quota_from_model(model: quota_meta::Model) -> Quota {
+        Quota {
+            limit_space: model.limit_space,
+            limit_inodes: model.limit_inodes,
+            used_space: model.used_space,
+            used_inodes: model.used_inodes,
+        }
+    }
+
     /// Convert FileMeta to FileAttr
     fn file_meta_to_attr(file_meta: &FileMetaModel) -> FileAttr {
         let permission = file_meta.permission();
@@ -2478,6 +2572,12 @@ impl MetaStore for DatabaseMetaStore {
             .await
             .map_err(MetaError::Database)?;
 
+        let start = i64::try_from(chunk_id_for(ino, 0)?)
+            .map_err(|_| MetaError::Internal("chunk_id overflow".to_string()))?;
+        let end = i64::try_from(chunk_id_for(ino + 1, 0)?)
+            .map_err(|_| MetaError::Internal("chunk_id overflow".to_string()))?;
+        Self::drop_chunk_slices(&txn, start, end).await?;
+
         txn.commit().await.map_err(MetaError::Database)?;
 
         Ok(())
@@ -2520,6 +2620,117 @@ impl MetaStore for DatabaseMetaStore {
         Ok(())
     }
 
+    async fn update_slice_refs(&self, slices: &[u64], delta: i64) -> Result<(), MetaError> {
+        for &slice_id in slices {
+            loop {
+                let updated = SliceRefMeta::update_many()
+                    .col_expr(
+                        slice_ref_meta::Column::ExtraRefs,
+                        sea_query::Expr::col(slice_ref_meta::Column::ExtraRefs).add(delta),
+                    )
+                    .filter(slice_ref_meta::Column::SliceId.eq(slice_id as i64))
+                    .exec(&self.db)
+                    .await
+                    .map_err(MetaError::Database)?;
+                if updated.rows_affected > 0 {
+                    break;
+                }
+
+                let row = slice_ref_meta::ActiveModel {
+                    slice_id: Set(slice_id as i64),
+                    extra_refs: Set(delta),
+                };
+                match row.insert(&self.db).await {
+                    Ok(_) => break,
+                    Err(err) if Self::is_unique_violation(&err) => continue,
+                    Err(err) => return Err(MetaError::Database(err)),
+                }
+            }
+        }
+        Ok(())
+    }
+
+    async fn get_slice_refs(&self, slice_id: u64) -> Result<i64, MetaError> {
+        let row = SliceRefMeta::find_by_id(slice_id as i64)
+            .one(&self.db)
+            .await
+            .map_err(MetaError::Database)?;
+        Ok(1 + row.map_or(0, |row| row.extra_refs))
+    }
+
+    async fn compact_chunk(
+        &self,
+        chunk_id: u64,
+        old: &[SliceDesc],
+        slice: SliceDesc,
+    ) -> Result<bool, MetaError> {
+        if old.is_empty() {
+            return Ok(false);
+        }
+        let txn = self.db.begin().await.map_err(MetaError::Database)?;
+        let rows = SliceMeta::find()
+            .filter(slice_meta::Column::ChunkId.eq(chunk_id as i64))
+            .order_by_asc(slice_meta::Column::Id)
+            .all(&txn)
+            .await
+            .map_err(MetaError::Database)?;
+        let unchanged = rows.len() >= old.len()
+            && rows
+                .iter()
+                .zip(old)
+                .all(|(row, desc)| SliceDesc::from(row.clone()) == *desc);
+        if !unchanged {
+            let _ = txn.rollback().await;
+            return Ok(false);
+        }
+
+        // The compacted slice takes the row of the first replaced slice, so
+        // slices appended meanwhile still sort after it.
+        let mut first: slice_meta::ActiveModel = rows[0].clone().into();
+        first.slice_id = Set(slice.slice_id as i64);
+        first.offset = Set(slice.offset.as_i64());
+        first.length = Set(slice.length.as_i64());
+        first.update(&txn).await.map_err(MetaError::Database)?;
+        let replaced: Vec<i64> = rows[1..old.len()].iter().map(|row| row.id).collect();
+        if !replaced.is_empty() {
+            SliceMeta::delete_many()
+                .filter(slice_meta::Column::Id.is_in(replaced))
+                .exec(&txn)
+                .await
+                .map_err(MetaError::Database)?;
+        }
+
+        Self::release_slices(&txn, old).await?;
+
+        txn.commit().await.map_err(MetaError::Database)?;
+        Ok(true)
+    }
+
+    async fn list_delayed_slices(&self, edge_ts: i64) -> Result<Vec<DelayedSlice>, MetaError> {
+        let rows = DelayedSliceMeta::find()
+            .filter(delayed_slice_meta::Column::Since.lt(edge_ts))
+            .order_by_asc(delayed_slice_meta::Column::Since)
+            .all(&self.db)
+            .await
+            .map_err(MetaError::Database)?;
+        Ok(rows
+            .into_iter()
+            .map(|row| DelayedSlice {
+                slice_id: row.slice_id as u64,
+                length: row.length as u64,
+                since: row.since,
+            })
+            .collect())
+    }
+
+    async fn delete_slice(&self, slice_id: u64) -> Result<(), MetaError> {
+        DelayedSliceMeta::delete_by_id(slice_id as i64)
+            .exec(&self.db)
+            .await
+            .map_err(MetaError::Database)?;
+        Ok(())
+    }
+
     #[tracing::instrument(
         level = "trace",
         skip(self, slice),
@@ -2605,6 +2816,58 @@ impl MetaStore for DatabaseMetaStore {
         }
     }
 
+    async fn get_counter(&self, name: &str) -> Result<i64, MetaError> {
+        let row = CounterMeta::find_by_id(name.to_string())
+            .one(&self.db)
+            .await
+            .map_err(MetaError::Database)?;
+        Ok(row.map_or(1, |row| row.value))
+    }
+
+    async fn set_counter_if_small(
+        &self,
+        name: &str,
+        value: i64,
+        diff: i64,
+    ) -> Result<bool, MetaError> {
+        if self.get_counter(name).await? > value - diff {
+            return Ok(false);
+        }
+        Self::set_counter_floor(&self.db, name, value).await?;
+        Ok(true)
+    }
+
+    async fn get_setting(&self, name: &str) -> Result<Option<String>, MetaError> {
+        let row = SettingMeta::find_by_id(name.to_string())
+            .one(&self.db)
+            .await
+            .map_err(MetaError::Database)?;
+        Ok(row.map(|row| row.value))
+    }
+
+    async fn set_setting(
+        &self,
+        name: &str,
+        value: &str,
+        overwrite: bool,
+    ) -> Result<bool, MetaError> {
+        let active = setting_meta::ActiveModel {
+            name: Set(name.to_string()),
+            value: Set(value.to_string()),
+        };
+        match active.clone().insert(&self.db).await {
+            Ok(_) => Ok(true),
+            Err(e) if Self::is_unique_violation(&e) => {
+                if !overwrite {
+                    return Ok(false);
+                }
+                active.update(&self.db).await.map_err(MetaError::Database)?;
+                Ok(true)
+            }
+            Err(e) => Err(MetaError::Database(e)),
+        }
+    }
+
     // ---------- Session lifecycle implementation ----------
 
     #[tracing::instrument(level = "trace", skip(self), fields(pid = session_info.process_id))]
@@ -2750,6 +3013,7 @@ impl MetaStore for DatabaseMetaStore {
         value: &[u8],
         flags: u32,
     ) -> Result<(), MetaError> {
+        check_xattr(name, value)?;
         if self.stat(inode).await?.is_none() {
             return Err(MetaError::NotFound(inode));
         }
@@ -2828,6 +3092,109 @@ impl MetaStore for DatabaseMetaStore {
         }
         Ok(())
     }
+
+    async fn get_quota(&self, qtype: u32, key: u64) -> Result<Option<Quota>, MetaError> {
+        let id = Self::quota_id(qtype, key)?;
+        let model = QuotaMeta::find_by_id(id)
+            .one(&self.db)
+            .await
+            .map_err(MetaError::Database)?;
+        Ok(model.map(Self::quota_from_model))
+    }
+
+    async fn set_quota(&self, qtype: u32, key: u64, quota: Quota) -> Result<bool, MetaError> {
+        let (quota_type, quota_key) = Self::quota_id(qtype, key)?;
+        let txn = self.db.begin().await.map_err(MetaError::Database)?;
+        let existing = QuotaMeta::find_by_id((quota_type, quota_key))
+            .one(&txn)
+            .await
+            .map_err(MetaError::Database)?;
+        let created = existing.is_none();
+
+        let active = quota_meta::ActiveModel {
+            quota_type: Set(quota_type),
+            quota_key: Set(quota_key),
+            limit_space: Set(quota.limit_space),
+            limit_inodes: Set(quota.limit_inodes),
+            used_space: Set(quota.used_space),
+            used_inodes: Set(quota.used_inodes),
+        };
+        if created {
+            active.insert(&txn).await.map_err(MetaError::Database)?;
+        } else {
+            active.update(&txn).await.map_err(MetaError::Database)?;
+        }
+
+        txn.commit().await.map_err(MetaError::Database)?;
+        Ok(created)
+    }
+
+    async fn delete_quota(&self, qtype: u32, key: u64) -> Result<(), MetaError> {
+        let id = Self::quota_id(qtype, key)?;
+        QuotaMeta::delete_by_id(id)
+            .exec(&self.db)
+            .await
+            .map_err(MetaError::Database)?;
+        Ok(())
+    }
+
+    async fn load_quotas(
+        &self,
+    ) -> Result<
+        (
+            HashMap<u64, Quota>,
+            HashMap<u64, Quota>,
+            HashMap<u64, Quota>,
+        ),
+        MetaError,
+    > {
+        let rows = QuotaMeta::find()
+            .all(&self.db)
+            .await
+            .map_err(MetaError::Database)?;
+
+        let (mut dirs, mut users, mut groups) = (HashMap::new(), HashMap::new(), HashMap::new());
+        for row in rows {
+            let Ok(qtype) = QuotaType::try_from(row.quota_type as u32) else {
+                continue;
+            };
+            let key = row.quota_key as u64;
+            let quota = Self::quota_from_model(row);
+            match qtype {
+                QuotaType::Dir => dirs.insert(key, quota),
+                QuotaType::User => users.insert(key, quota),
+                QuotaType::Group => groups.insert(key, quota),
+            };
+        }
+        Ok((dirs, users, groups))
+    }
+
+    async fn flush_quotas(&self, deltas: &[QuotaDelta]) -> Result<(), MetaError> {
+        if deltas.is_empty() {
+            return Ok(());
+        }
+
+        let txn = self.db.begin().await.map_err(MetaError::Database)?;
+        for delta in deltas {
+            let (quota_type, quota_key) = Self::quota_id(delta.qtype, delta.key)?;
+            QuotaMeta::update_many()
+                .col_expr(
+                    quota_meta::Column::UsedSpace,
+                    sea_query::Expr::col(quota_meta::Column::UsedSpace).add(delta.space_delta),
+                )
+                .col_expr(
+                    quota_meta::Column::UsedInodes,
+                    sea_query::Expr::col(quota_meta::Column::UsedInodes).add(delta.inode_delta),
+                )
+                .filter(quota_meta::Column::QuotaType.eq(quota_type))
+                .filter(quota_meta::Column::QuotaKey.eq(quota_key))
+                .exec(&txn)
+                .await
+                .map_err(MetaError::Database)?;
+        }
+        txn.commit().await.map_err(MetaError::Database)?;
+        Ok(())
+    }
 }
 
 #[cfg(test)]
@@ -2835,6 +3202,7 @@ mod tests {
     use super::*;
     use crate::meta::config::{CacheConfig, ClientOptions, DatabaseConfig};
     use crate::meta::file_lock::{FileLockQuery, FileLockRange, FileLockType};
+    use crate::meta::store::{CloneOption, DumpOption, DumpRecord, LoadOption, Visitor};
     use tokio::time;
 
     fn test_config() -> Config {
@@ -4021,4 +4389,277 @@ mod tests {
         assert_eq!(stat.uid, 500);
         assert_eq!(stat.gid, 500);
     }
+
+    struct Collect(Vec<DumpRecord>);
+
+    impl Visitor<DumpRecord> for Collect {
+        fn visit(&mut self, item: DumpRecord) -> Result<(), MetaError> {
+            self.0.push(item);
+            Ok(())
+        }
+    }
+
+    #[tokio::test]
+    async fn test_dump_load_roundtrip() {
+        use crate::chuck::chunk::DEFAULT_CHUNK_SIZE;
+        use crate::chuck::transform::BLOCK_FORMAT_SETTING;
+
+        let temp_dir = tempfile::tempdir().expect("tempdir");
+        let src = DatabaseMetaStore::from_config(file_db_config(&temp_dir.path().join("src.db")))
+            .await
+            .unwrap();
+        let root = src.root_ino();
+        let dir = src.mkdir(root, "dir".to_string()).await.unwrap();
+        let file = src.create_file(dir, "file".to_string()).await.unwrap();
+        let chunk_id = chunk_id_for(file, 0).unwrap();
+        let slice_id = src.next_id(SLICE_ID_KEY).await.unwrap() as u64;
+        src.append_slice(
+            chunk_id,
+            SliceDesc {
+                slice_id,
+                chunk_id,
+                offset: 0,
+                length: 4096,
+            },
+        )
+        .await
+        .unwrap();
+        src.set_file_size(file, 4096).await.unwrap();
+        src.link(file, root, "hardlink").await.unwrap();
+        src.symlink(root, "sym", "dir/file").await.unwrap();
+        src.set_xattr(file, "user.tag", b"v1", 0).await.unwrap();
+        src.chown(dir, Some(1000), Some(1000)).await.unwrap();
+        let format = r#"{"compression":"lz4","encryption":"none"}"#;
+        src.set_setting(BLOCK_FORMAT_SETTING, format, false)
+            .await
+            .unwrap();
+
+        let mut records = Collect(Vec::new());
+        let opt = DumpOption {
+            chunk_size: DEFAULT_CHUNK_SIZE,
+            ..Default::default()
+        };
+        src.dump(opt, &mut records).await.unwrap();
+        let mut data = Vec::new();
+        for record in &records.0 {
+            serde_json::to_writer(&mut data, record).unwrap();
+            data.push(b'\n');
+        }
+
+        let dst = DatabaseMetaStore::from_config(file_db_config(&temp_dir.path().join("dst.db")))
+            .await
+            .unwrap();
+        dst.load(LoadOption::default(), &data).await.unwrap();
+
+        let (new_file, _) = dst.lookup_path("/dir/file").await.unwrap().unwrap();
+        let attr = dst.stat(new_file).await.unwrap().unwrap();
+        assert_eq!(attr.size, 4096);
+        assert_eq!(attr.nlink, 2);
+        let (linked, _) = dst.lookup_path("/hardlink").await.unwrap().unwrap();
+        assert_eq!(linked, new_file);
+        let slices = dst
+            .get_slices(chunk_id_for(new_file, 0).unwrap())
+            .await
+            .unwrap();
+        assert_eq!(slices.len(), 1);
+        assert_eq!(slices[0].slice_id, slice_id);
+        assert_eq!(
+            dst.get_xattr(new_file, "user.tag").await.unwrap(),
+            Some(b"v1".to_vec())
+        );
+
+        let (sym, _) = dst.lookup_path("/sym").await.unwrap().unwrap();
+        assert_eq!(dst.read_symlink(sym).await.unwrap(), "dir/file");
+        let (new_dir, _) = dst.lookup_path("/dir").await.unwrap().unwrap();
+        assert_eq!(dst.stat(new_dir).await.unwrap().unwrap().uid, 1000);
+        assert_eq!(
+            dst.get_setting(BLOCK_FORMAT_SETTING)
+                .await
+                .unwrap()
+                .as_deref(),
+            Some(format)
+        );
+
+        // Slices created after the load must not reuse the loaded ids.
+        assert!(dst.next_id(SLICE_ID_KEY).await.unwrap() as u64 > slice_id);
+    }
+
+    #[tokio::test]
+    async fn test_block_format_setting() {
+        use crate::chuck::transform::{BLOCK_FORMAT_SETTING, Compression, load_transform};
+
+        let store = new_test_store().await;
+        assert_eq!(store.get_setting("missing").await.unwrap(), None);
+        assert!(store.set_setting("name", "a", false).await.unwrap());
+        assert!(!store.set_setting("name", "b", false).await.unwrap());
+        assert_eq!(
+            store.get_setting("name").await.unwrap().as_deref(),
+            Some("a")
+        );
+        assert!(store.set_setting("name", "b", true).await.unwrap());
+        assert_eq!(
+            store.get_setting("name").await.unwrap().as_deref(),
+            Some("b")
+        );
+
+        // The first mount records the format, later ones must agree with it.
+        let transform = load_transform(&store, Some(Compression::Lz4), None, None)
+            .await
+            .unwrap();
+        assert!(!transform.is_identity());
+        assert!(
+            store
+                .get_setting(BLOCK_FORMAT_SETTING)
+                .await
+                .unwrap()
+                .is_some()
+        );
+        assert!(load_transform(&store, None, None, None).await.is_ok());
+        assert!(
+            load_transform(&store, Some(Compression::Zstd), None, None)
+                .await
+                .is_err()
+        );
+    }
+
+    #[tokio::test]
+    async fn test_clone_shares_slices() {
+        use crate::chuck::chunk::DEFAULT_CHUNK_SIZE;
+        use crate::meta::clone::DETACHED_DIR;
+
+        let store = new_test_store().await;
+        let root = store.root_ino();
+        let dir = store.mkdir(root, "dir".to_string()).await.unwrap();
+        let file = store.create_file(dir, "file".to_string()).await.unwrap();
+        let chunk_id = chunk_id_for(file, 0).unwrap();
+        let slice_id = store.next_id(SLICE_ID_KEY).await.unwrap() as u64;
+        store
+            .append_slice(
+                chunk_id,
+                SliceDesc {
+                    slice_id,
+                    chunk_id,
+                    offset: 0,
+                    length: 4096,
+                },
+            )
+            .await
+            .unwrap();
+        store.set_file_size(file, 4096).await.unwrap();
+        store.link(file, dir, "hardlink").await.unwrap();
+        assert_eq!(store.get_slice_refs(slice_id).await.unwrap(), 1);
+
+        let opt = CloneOption {
+            preserve_attr: true,
+            chunk_size: DEFAULT_CHUNK_SIZE,
+            ..Default::default()
+        };
+        let cloned = store.clone_entry(dir, root, "snap", &opt).await.unwrap();
+        assert_eq!(cloned.kind, FileType::Dir);
+
+        let (new_file, _) = store.lookup_path("/snap/file").await.unwrap().unwrap();
+        assert_ne!(new_file, file);
+        let (linked, _) = store.lookup_path("/snap/hardlink").await.unwrap().unwrap();
+        assert_eq!(linked, new_file);
+        let attr = store.stat(new_file).await.unwrap().unwrap();
+        assert_eq!(attr.size, 4096);
+        assert_eq!(attr.nlink, 2);
+        let slices = store
+            .get_slices(chunk_id_for(new_file, 0).unwrap())
+            .await
+            .unwrap();
+        assert_eq!(slices.len(), 1);
+        assert_eq!(slices[0].slice_id, slice_id);
+        assert_eq!(store.get_slice_refs(slice_id).await.unwrap(), 2);
+
+        let err = store
+            .clone_entry(dir, root, "snap", &opt)
+            .await
+            .unwrap_err();
+        assert!(matches!(err, MetaError::AlreadyExists { .. }));
+        let (snap, _) = store.lookup_path("/snap").await.unwrap().unwrap();
+        let err = store
+            .clone_entry(root, snap, "loop", &opt)
+            .await
+            .unwrap_err();
+        assert!(matches!(err, MetaError::InvalidPath(_)));
+
+        // Nothing is left behind in the staging directory.
+        let (staging, _) = store
+            .lookup_path(&format!("/{DETACHED_DIR}"))
+            .await
+            .unwrap()
+            .unwrap();
+        assert!(store.readdir(staging).await.unwrap().is_empty());
+        assert!(
+            store
+                .find_detached_nodes(std::time::SystemTime::now())
+                .await
+                .unwrap()
+                .is_empty()
+        );
+    }
+
+    #[tokio::test]
+    async fn test_xattr_operations() {
+        let store = new_test_store().await;
+        let root = store.root_ino();
+        let file = store.create_file(root, "x.txt".to_string()).await.unwrap();
+        let dir = store.mkdir(root, "xdir".to_string()).await.unwrap();
+        let create = libc::XATTR_CREATE as u32;
+        let replace = libc::XATTR_REPLACE as u32;
+
+        assert!(matches!(
+            store.set_xattr(file, "user.a", b"1", replace).await,
+            Err(MetaError::NotFound(_))
+        ));
+        store.set_xattr(file, "user.a", b"1", create).await.unwrap();
+        assert!(matches!(
+            store.set_xattr(file, "user.a", b"2", create).await,
+            Err(MetaError::AlreadyExists { .. })
+        ));
+        store
+            .set_xattr(file, "user.a", b"2", replace)
+            .await
+            .unwrap();
+        store.set_xattr(file, "user.b", &[0, 255], 0).await.unwrap();
+        assert_eq!(
+            store.get_xattr(file, "user.a").await.unwrap(),
+            Some(b"2".to_vec())
+        );
+        assert_eq!(
+            store.get_xattr(file, "user.b").await.unwrap(),
+            Some(vec![0, 255])
+        );
+        assert_eq!(store.get_xattr(file, "user.c").await.unwrap(), None);
+        let mut names = store.list_xattr(file).await.unwrap();
+        names.sort();
+        assert_eq!(names, vec!["user.a", "user.b"]);
+
+        assert!(matches!(
+            store.set_xattr(file, &"n".repeat(256), b"", 0).await,
+            Err(MetaError::InvalidXattrName(_))
+        ));
+        assert!(matches!(
+            store.set_xattr(file, "user.big", &vec![0; 65537], 0).await,
+            Err(MetaError::XattrTooLarge)
+        ));
+        assert!(matches!(
+            store.set_xattr(999999, "user.a", b"1", 0).await,
+            Err(MetaError::NotFound(_))
+        ));
+
+        store.remove_xattr(file, "user.a").await.unwrap();
+        assert!(matches!(
+            store.remove_xattr(file, "user.a").await,
+            Err(MetaError::NotFound(_))
+        ));
+        assert_eq!(store.list_xattr(file).await.unwrap(), vec!["user.b"]);
+
+        // Attributes go away with their inode.
+        store.set_xattr(dir, "user.a", b"1", 0).await.unwrap();
+        store.rmdir(root, "xdir").await.unwrap();
+        let again = store.mkdir(root, "xdir".to_string()).await.unwrap();
+        assert!(store.list_xattr(again).await.unwrap().is_empty());
+    }
 }
@@ -2,7 +2,7 @@
 //!
 //! Uses Etcd/etcd as the backend for metadata storage
 
-use super::{apply_truncate_plan, trim_slices_in_place};
+use super::{apply_truncate_plan, check_xattr, released_slices, trim_slices_in_place};
 use crate::chuck::SliceDesc;
 use crate::chuck::slice::key_for_slice;
 use crate::meta::backoff::backoff;
@@ -15,12 +15,13 @@ use crate::meta::file_lock::{
     FileLockInfo, FileLockQuery, FileLockRange, FileLockType, PlockRecord,
 };
 use crate::meta::store::{
-    DirEntry, FileAttr, LockName, MetaError, MetaStore, SetAttrFlags, SetAttrRequest,
+    DelayedSlice, DirEntry, FileAttr, LockName, MetaError, MetaStore, Quota, QuotaDelta, QuotaType,
+    SetAttrFlags, SetAttrRequest,
 };
 use crate::meta::stores::pool::IdPool;
 use crate::meta::{INODE_ID_KEY, Permission};
-use crate::vfs::chunk_id_for;
 use crate::vfs::fs::FileType;
+use crate::vfs::{chunk_id_for, extract_ino_and_chunk_index};
 use async_trait::async_trait;
 use chrono::{Duration, Utc};
 use etcd_client::{
@@ -31,7 +32,8 @@ use serde::de::DeserializeOwned;
 use serde_json;
 use std::collections::HashMap;
 use std::path::Path;
-use std::sync::OnceLock;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, OnceLock};
 use tokio::select;
 use tokio_util::sync::CancellationToken;
 use tracing::{Instrument, error, info, warn};
@@ -350,6 +352,63 @@ impl EtcdMetaStore {
         format!("l:{}", inode)
     }
 
+    /// Etcd helper method: generate quota key, e.g. `q:dir:42` or `q:user:1000`
+    fn etcd_quota_key(qtype: QuotaType, key: u64) -> String {
+        format!("q:{qtype}:{key}")
+    }
+
+    /// Etcd helper method: generate key holding the references of a slice
+    /// beyond the first, e.g. `sr:42`
+    fn etcd_slice_ref_key(slice_id: u64) -> String {
+        format!("sr:{slice_id}")
+    }
+
+    /// Etcd helper method: generate key holding a slice replaced by
+    /// compaction, e.g. `ds:42`
+    fn etcd_delayed_slice_key(slice_id: u64) -> String {
+        format!("ds:{slice_id}")
+    }
+
+    /// Etcd helper method: generate key holding a volume setting, e.g.
+    /// `setting:block_format`
+    fn etcd_setting_key(name: &str) -> String {
+        format!("setting:{name}")
+    }
+
+    /// Etcd helper method: generate prefix of the extended attribute keys
+    /// of an inode, e.g. `x:42:`
+    fn etcd_xattr_prefix(inode: i64) -> String {
+        format!("x:{inode}:")
+    }
+
+    /// Etcd helper method: generate key holding an extended attribute, e.g.
+    /// `x:42:user.tag`
+    fn etcd_xattr_key(inode: i64, name: &str) -> String {
+        format!("x:{inode}:{name}")
+    }
+
+    /// Removes all extended attributes of an inode that no longer exists.
+    async fn delete_xattrs(&self, inode: i64) -> Result<(), MetaError> {
+        let prefix = Self::etcd_xattr_prefix(inode);
+        let mut client = self.client.clone();
+        client
+            .delete(
+                prefix.as_str(),
+                Some(etcd_client::DeleteOptions::new().with_prefix()),
+            )
+            .await
+            .map_err(|e| MetaError::Internal(format!("Failed to delete {prefix}*: {e}")))?;
+        Ok(())
+    }
+
+    fn parse_quota_key(quota_key: &str) -> Option<(QuotaType, u64)> {
+        let (qtype, key) = quota_key.strip_prefix("q:")?.split_once(':')?;
+        let qtype = QuotaType::ALL
+            .into_iter()
+            .find(|candidate| candidate.to_string() == qtype)?;
+        Some((qtype, key.parse().ok()?))
+    }
+
     /// Create or open an etcd metadata store
     pub async fn new(backend_path: &Path) -> Result<Self, MetaError> {
         let _config =
@@ -458,6 +517,15 @@ impl EtcdMetaStore {
         }
     }
 
+    async fn etcd_get_raw(&self, key: &str) -> Result<Option<Vec<u8>>, MetaError> {
+        let mut client = self.client.clone();
+        let resp = client
+            .get(key.to_string(), None)
+            .await
+            .map_err(|e| MetaError::Internal(format!("Failed to get key {}: {}", key, e)))?;
+        Ok(resp.kvs().first().map(|kv| kv.value().to_vec()))
+    }
+
     async fn etcd_get_json_serde_only<T: DeserializeOwned>(
         &self,
         key: &str,
@@ -563,29 +631,12 @@ impl EtcdMetaStore {
             old_size,
             chunk_size,
             |cutoff_chunk, cutoff_offset| async move {
-                let chunk_id = chunk_id_for(ino, cutoff_chunk)?;
-                let key = key_for_slice(chunk_id);
-                let mut slices: Vec<SliceDesc> =
-                    self.etcd_get_json(&key).await?.unwrap_or_default();
-                trim_slices_in_place(&mut slices, cutoff_offset);
-                if slices.is_empty() {
-                    let mut client = self.client.clone();
-                    client.delete(key.as_str(), None).await.map_err(|e| {
-                        MetaError::Internal(format!("Failed to delete key {key}: {e}"))
-                    })?;
-                } else {
-                    self.etcd_put_json(&key, &slices, None).await?;
-                }
-                Ok(())
+                self.drop_chunk_slices(chunk_id_for(ino, cutoff_chunk)?, cutoff_offset)
+                    .await
             },
             |start, end| async move {
                 for idx in start..end {
-                    let chunk_id = chunk_id_for(ino, idx)?;
-                    let key = key_for_slice(chunk_id);
-                    let mut client = self.client.clone();
-                    client.delete(key.as_str(), None).await.map_err(|e| {
-                        MetaError::Internal(format!("Failed to delete key {key}: {e}"))
-                    })?;
+                    self.drop_chunk_slices(chunk_id_for(ino, idx)?, 0).await?;
                 }
                 Ok(())
             },
@@ -593,6 +644,108 @@ impl EtcdMetaStore {
         .await
     }
 
+    /// Cuts the slices of `chunk_id` at `cutoff_offset` (all of them for 0)
+    /// and releases the references of the dropped slices in one transaction.
+    async fn drop_chunk_slices(&self, chunk_id: u64, cutoff_offset: u64) -> Result<(), MetaError> {
+        let slice_key = key_for_slice(chunk_id);
+        loop {
+            let old = self.get_slices(chunk_id).await?;
+            if old.is_empty() {
+                return Ok(());
+            }
+            let mut kept = old.clone();
+            trim_slices_in_place(&mut kept, cutoff_offset);
+            let dropped: Vec<SliceDesc> = old
+                .iter()
+                .filter(|desc| desc.offset >= cutoff_offset)
+                .copied()
+                .collect();
+            let released = released_slices(&dropped);
+            let mut deps = vec![slice_key.clone()];
+            deps.extend(Self::release_deps(&released));
+            let since = Utc::now().timestamp_nanos_opt().unwrap_or(i64::MAX);
+            let changed = Arc::new(AtomicBool::new(false));
+            let changed_in_stage = changed.clone();
+            let key = slice_key.clone();
+
+            let mut builder = TxnBuilder::new();
+            builder.add_stage(deps, move |ctx| {
+                let slices: Vec<SliceDesc> = match ctx.value(&key) {
+                    Some(raw) => crate::meta::serialization::deserialize_meta(raw)?,
+                    None => Vec::new(),
+                };
+                // Slices appended meanwhile: start over with the new list.
+                if slices != old {
+                    changed_in_stage.store(true, Ordering::Relaxed);
+                    return Ok(Vec::new());
+                }
+                let mut plans = vec![if kept.is_empty() {
+                    UpdatePlan::new_delete(ctx, key.clone())?
+                } else {
+                    let payload = crate::meta::serialization::serialize_meta(&kept)?;
+                    UpdatePlan::new_write(ctx, key.clone(), payload)?
+                }];
+                plans.extend(Self::release_plans(ctx, &released, since)?);
+                Ok(plans)
+            });
+
+            builder.execute(&self.client, 10).await?;
+            if !changed.load(Ordering::Relaxed) {
+                return Ok(());
+            }
+        }
+    }
+
+    /// Keys read by [`Self::release_plans`] for `released`.
+    fn release_deps(released: &[(u64, i64, u64)]) -> Vec<String> {
+        let mut deps = Vec::with_capacity(released.len() * 2);
+        for &(slice_id, _, _) in released {
+            deps.push(Self::etcd_slice_ref_key(slice_id));
+            deps.push(Self::etcd_delayed_slice_key(slice_id));
+        }
+        deps
+    }
+
+    /// Plans dropping the references of `released` (see [`released_slices`]);
+    /// slices left without references become delayed slices.
+    fn release_plans(
+        ctx: &TxnContext,
+        released: &[(u64, i64, u64)],
+        since: i64,
+    ) -> Result<Vec<UpdatePlan>, MetaError> {
+        let mut plans = Vec::new();
+        for &(slice_id, refs, length) in released {
+            let ref_key = Self::etcd_slice_ref_key(slice_id);
+            let stored = ctx.value(&ref_key);
+            let extra: i64 = match stored {
+                Some(raw) => crate::meta::serialization::deserialize_meta(raw)?,
+                None => 0,
+            };
+            // Still shared with clones, which hold the extra references.
+            if extra >= refs {
+                let payload = crate::meta::serialization::serialize_meta(&(extra - refs))?;
+                plans.push(UpdatePlan::new_write(ctx, ref_key, payload)?);
+                continue;
+            }
+            if stored.is_some() {
+                plans.push(UpdatePlan::new_delete(ctx, ref_key)?);
+            }
+            let delayed = DelayedSlice {
+                slice_id,
+                length,
+                since,
+            };
+            let payload = serde_json::to_vec(&delayed)
+                .map_err(|e| MetaError::Serialization(e.to_string()))?;
+            plans.push(UpdatePlan::new_write(
+                ctx,
+                Self::etcd_delayed_slice_key(slice_id),
+                payload,
+            )?);
+        }
+        Ok(plans)
+    }
+
     #[cfg(feature = "rkyv-serialization")]
     async fn etcd_get_json_lenient<T>(&self, key: &str) -> Result<Option<T>, MetaError>
     where
@@ -1868,6 +2021,13 @@ impl MetaStore for EtcdMetaStore {
             .await
         {
             Ok(_) => {
+                if let Err(e) = self.delete_xattrs(child_ino).await {
+                    warn!(
+                        "Rmdir succeeded but failed to delete extended attributes: inode={}, error={}",
+                        child_ino, e
+                    );
+                }
+
                 // Step 2: Directory deleted successfully, now update parent children map
                 // If this fails, forward key is deleted but parent still references it
                 // This is acceptable: lookup will fail (forward key gone), no dangling data
@@ -3015,13 +3175,39 @@ impl MetaStore for EtcdMetaStore {
             ));
         }
 
+        // Release the slices of the file; chunk keys of other inodes can
+        // share the prefix, e.g. `slices/12` and `slices/123000000000`.
+        let prefix = format!("slices/{ino}");
+        let resp = client
+            .get(
+                prefix.as_str(),
+                Some(
+                    etcd_client::GetOptions::new()
+                        .with_prefix()
+                        .with_keys_only(),
+                ),
+            )
+            .await
+            .map_err(|e| MetaError::Internal(format!("Failed to list {prefix}*: {e}")))?;
+        for kv in resp.kvs() {
+            let Some(chunk_id) = String::from_utf8_lossy(kv.key())
+                .strip_prefix("slices/")
+                .and_then(|id| id.parse::<u64>().ok())
+            else {
+                continue;
+            };
+            if extract_ino_and_chunk_index(chunk_id).0 == ino {
+                self.drop_chunk_slices(chunk_id, 0).await?;
+            }
+        }
+
         // Delete the reverse index entry (file metadata)
         client
             .delete(reverse_key, None)
             .await
             .map_err(|e| MetaError::Internal(format!("Failed to remove file metadata: {}", e)))?;
 
-        Ok(())
+        self.delete_xattrs(ino).await
     }
 
     #[tracing::instrument(
@@ -3063,6 +3249,102 @@ impl MetaStore for EtcdMetaStore {
         .map(|_| ())
     }
 
+    async fn update_slice_refs(&self, slices: &[u64], delta: i64) -> Result<(), MetaError> {
+        for &slice_id in slices {
+            self.atomic_update(
+                &Self::etcd_slice_ref_key(slice_id),
+                |extra: i64| Ok((extra + delta, ())),
+                || Ok((delta, ())),
+                10,
+                &None,
+            )
+            .await?;
+        }
+        Ok(())
+    }
+
+    async fn get_slice_refs(&self, slice_id: u64) -> Result<i64, MetaError> {
+        let extra = self
+            .etcd_get_json::<i64>(&Self::etcd_slice_ref_key(slice_id))
+            .await?;
+        Ok(1 + extra.unwrap_or(0))
+    }
+
+    async fn compact_chunk(
+        &self,
+        chunk_id: u64,
+        old: &[SliceDesc],
+        slice: SliceDesc,
+    ) -> Result<bool, MetaError> {
+        if old.is_empty() {
+            return Ok(false);
+        }
+        let slice_key = key_for_slice(chunk_id);
+        let released = released_slices(old);
+        let mut deps = vec![slice_key.clone()];
+        deps.extend(Self::release_deps(&released));
+        let old = old.to_vec();
+        let since = Utc::now().timestamp_nanos_opt().unwrap_or(i64::MAX);
+        let replaced = Arc::new(AtomicBool::new(false));
+        let replaced_in_stage = replaced.clone();
+
+        let mut builder = TxnBuilder::new();
+        builder.add_stage(deps, move |ctx| {
+            let mut slices: Vec<SliceDesc> = match ctx.value(&slice_key) {
+                Some(raw) => crate::meta::serialization::deserialize_meta(raw)?,
+                None => Vec::new(),
+            };
+            let unchanged = slices.starts_with(&old);
+            replaced_in_stage.store(unchanged, Ordering::Relaxed);
+            if !unchanged {
+                return Ok(Vec::new());
+            }
+
+            slices.drain(..old.len());
+            slices.insert(0, slice);
+            let payload = crate::meta::serialization::serialize_meta(&slices)?;
+            let mut plans = vec![UpdatePlan::new_write(ctx, slice_key.clone(), payload)?];
+            plans.extend(Self::release_plans(ctx, &released, since)?);
+            Ok(plans)
+        });
+
+        builder.execute(&self.client, 10).await?;
+        Ok(replaced.load(Ordering::Relaxed))
+    }
+
+    async fn list_delayed_slices(&self, edge_ts: i64) -> Result<Vec<DelayedSlice>, MetaError> {
+        let mut client = self.client.clone();
+        let resp = client
+            .get("ds:", Some(etcd_client::GetOptions::new().with_prefix()))
+            .await
+            .map_err(|e| MetaError::Internal(format!("Failed to scan delayed slices: {e}")))?;
+
+        let mut slices = Vec::new();
+        for kv in resp.kvs() {
+            let delayed: DelayedSlice = serde_json::from_slice(kv.value()).map_err(|e| {
+                MetaError::Internal(format!(
+                    "Failed to parse {}: {e}",
+                    String::from_utf8_lossy(kv.key())
+                ))
+            })?;
+            if delayed.since < edge_ts {
+                slices.push(delayed);
+            }
+        }
+        slices.sort_by_key(|delayed| delayed.since);
+        Ok(slices)
+    }
+
+    async fn delete_slice(&self, slice_id: u64) -> Result<(), MetaError> {
+        let key = Self::etcd_delayed_slice_key(slice_id);
+        let mut client = self.client.clone();
+        client
+            .delete(key.clone(), None)
+            .await
+            .map_err(|e| MetaError::Internal(format!("Failed to delete key {key}: {e}")))?;
+        Ok(())
+    }
+
     async fn write(
         &self,
         ino: i64,
@@ -3138,6 +3420,177 @@ impl MetaStore for EtcdMetaStore {
         self.generate_id(key).await
     }
 
+    /// Ids are handed out from local batches, so this is the start of the
+    /// next batch and may be ahead of what clients have actually used.
+    async fn get_counter(&self, name: &str) -> Result<i64, MetaError> {
+        Ok(self
+            .etcd_get_json::<i64>(name)
+            .await?
+            .unwrap_or(FIRST_ALLOCATED_ID))
+    }
+
+    async fn set_counter_if_small(
+        &self,
+        name: &str,
+        value: i64,
+        diff: i64,
+    ) -> Result<bool, MetaError> {
+        let raise = |current: i64| {
+            if current > value - diff {
+                Ok((current, false))
+            } else {
+                Ok((value, true))
+            }
+        };
+        self.atomic_update(name, raise, || raise(FIRST_ALLOCATED_ID), 10, &None)
+            .await
+    }
+
+    async fn get_setting(&self, name: &str) -> Result<Option<String>, MetaError> {
+        let key = Self::etcd_setting_key(name);
+        let mut client = self.client.clone();
+        let resp = client
+            .get(key.clone(), None)
+            .await
+            .map_err(|e| MetaError::Internal(format!("Failed to get key {key}: {e}")))?;
+        resp.kvs()
+            .first()
+            .map(|kv| {
+                kv.value_str()
+                    .map(str::to_string)
+                    .map_err(|e| MetaError::Internal(format!("Failed to parse {key}: {e}")))
+            })
+            .transpose()
+    }
+
+    async fn set_setting(
+        &self,
+        name: &str,
+        value: &str,
+        overwrite: bool,
+    ) -> Result<bool, MetaError> {
+        let key = Self::etcd_setting_key(name);
+        let mut client = self.client.clone();
+        if overwrite {
+            client
+                .put(key.clone(), value, None)
+                .await
+                .map_err(|e| MetaError::Internal(format!("Failed to put key {key}: {e}")))?;
+            return Ok(true);
+        }
+        // version == 0 means the setting is not present yet
+        let txn = Txn::new()
+            .when([Compare::version(key.clone(), CompareOp::Equal, 0)])
+            .and_then([TxnOp::put(key.clone(), value, None)]);
+        let resp = client
+            .txn(txn)
+            .await
+            .map_err(|e| MetaError::Internal(format!("Failed to put key {key}: {e}")))?;
+        Ok(resp.succeeded())
+    }
+
+    async fn set_xattr(
+        &self,
+        inode: i64,
+        name: &str,
+        value: &[u8],
+        flags: u32,
+    ) -> Result<(), MetaError> {
+        check_xattr(name, value)?;
+        let reverse_key = Self::etcd_reverse_key(inode);
+        let key = Self::etcd_xattr_key(inode, name);
+        let create_only = flags & (libc::XATTR_CREATE as u32) != 0;
+        let replace_only = flags & (libc::XATTR_REPLACE as u32) != 0;
+
+        // version == 0 means the key is currently not present
+        let mut compares = vec![Compare::version(reverse_key.clone(), CompareOp::Greater, 0)];
+        if create_only {
+            compares.push(Compare::version(key.clone(), CompareOp::Equal, 0));
+        } else if replace_only {
+            compares.push(Compare::version(key.clone(), CompareOp::Greater, 0));
+        }
+        let txn = Txn::new()
+            .when(compares)
+            .and_then([TxnOp::put(key.clone(), value, None)]);
+        let mut client = self.client.clone();
+        let resp = client
+            .txn(txn)
+            .await
+            .map_err(|e| MetaError::Internal(format!("Failed to put key {key}: {e}")))?;
+        if resp.succeeded() {
+            return Ok(());
+        }
+
+        let exists = self.etcd_get_raw(&key).await?.is_some();
+        if create_only && exists {
+            return Err(MetaError::AlreadyExists {
+                parent: inode,
+                name: name.to_string(),
+            });
+        }
+        Err(MetaError::NotFound(inode))
+    }
+
+    async fn get_xattr(&self, inode: i64, name: &str) -> Result<Option<Vec<u8>>, MetaError> {
+        let value = self
+            .etcd_get_raw(&Self::etcd_xattr_key(inode, name))
+            .await?;
+        if value.is_none()
+            && self
+                .etcd_get_raw(&Self::etcd_reverse_key(inode))
+                .await?
+                .is_none()
+        {
+            return Err(MetaError::NotFound(inode));
+        }
+        Ok(value)
+    }
+
+    async fn list_xattr(&self, inode: i64) -> Result<Vec<String>, MetaError> {
+        if self
+            .etcd_get_raw(&Self::etcd_reverse_key(inode))
+            .await?
+            .is_none()
+        {
+            return Err(MetaError::NotFound(inode));
+        }
+        let prefix = Self::etcd_xattr_prefix(inode);
+        let mut client = self.client.clone();
+        let resp = client
+            .get(
+                prefix.as_str(),
+                Some(
+                    etcd_client::GetOptions::new()
+                        .with_prefix()
+                        .with_keys_only(),
+                ),
+            )
+            .await
+            .map_err(|e| MetaError::Internal(format!("Failed to list {prefix}*: {e}")))?;
+        Ok(resp
+            .kvs()
+            .iter()
+            .filter_map(|kv| {
+                String::from_utf8_lossy(kv.key())
+                    .strip_prefix(&prefix)
+                    .map(str::to_string)
+            })
+            .collect())
+    }
+
+    async fn remove_xattr(&self, inode: i64, name: &str) -> Result<(), MetaError> {
+        let key = Self::etcd_xattr_key(inode, name);
+        let mut client = self.client.clone();
+        let resp = client
+            .delete(key.as_str(), None)
+            .await
+            .map_err(|e| MetaError::Internal(format!("Failed to delete key {key}: {e}")))?;
+        if resp.deleted() == 0 {
+            return Err(MetaError::NotFound(inode));
+        }
+        Ok(())
+    }
+
     // ---------- Session lifecycle implementation ----------
 
     #[tracing::instrument(level = "trace", skip(self), fields(pid = session_info.process_id))]
@@ -3487,6 +3940,97 @@ impl MetaStore for EtcdMetaStore {
             }
         }
     }
+
+    async fn get_quota(&self, qtype: u32, key: u64) -> Result<Option<Quota>, MetaError> {
+        let quota_key = Self::etcd_quota_key(QuotaType::try_from(qtype)?, key);
+        self.etcd_get_json_serde_only(&quota_key).await
+    }
+
+    async fn set_quota(&self, qtype: u32, key: u64, quota: Quota) -> Result<bool, MetaError> {
+        let quota_key = Self::etcd_quota_key(QuotaType::try_from(qtype)?, key);
+        self.atomic_update(
+            &quota_key,
+            |_: Quota| Ok((quota.clone(), false)),
+            || Ok((quota.clone(), true)),
+            10,
+            &None,
+        )
+        .await
+    }
+
+    async fn delete_quota(&self, qtype: u32, key: u64) -> Result<(), MetaError> {
+        let quota_key = Self::etcd_quota_key(QuotaType::try_from(qtype)?, key);
+        let mut client = self.client.clone();
+        client
+            .delete(quota_key.as_str(), None)
+            .await
+            .map_err(|e| MetaError::Internal(format!("Failed to delete key {quota_key}: {e}")))?;
+        Ok(())
+    }
+
+    async fn load_quotas(
+        &self,
+    ) -> Result<
+        (
+            HashMap<u64, Quota>,
+            HashMap<u64, Quota>,
+            HashMap<u64, Quota>,
+        ),
+        MetaError,
+    > {
+        let mut client = self.client.clone();
+        let resp = client
+            .get("q:", Some(etcd_client::GetOptions::new().with_prefix()))
+            .await
+            .map_err(|e| MetaError::Internal(format!("Failed to scan quotas: {e}")))?;
+
+        let (mut dirs, mut users, mut groups) = (HashMap::new(), HashMap::new(), HashMap::new());
+        for kv in resp.kvs() {
+            let key_str = String::from_utf8_lossy(kv.key());
+            let Some((qtype, key)) = Self::parse_quota_key(&key_str) else {
+                warn!("Ignoring malformed quota key {key_str}");
+                continue;
+            };
+            let quota: Quota = serde_json::from_slice(kv.value())
+                .map_err(|e| MetaError::Internal(format!("Failed to parse {key_str}: {e}")))?;
+            match qtype {
+                QuotaType::Dir => dirs.insert(key, quota),
+                QuotaType::User => users.insert(key, quota),
+                QuotaType::Group => groups.insert(key, quota),
+            };
+        }
+        Ok((dirs, users, groups))
+    }
+
+    async fn flush_quotas(&self, deltas: &[QuotaDelta]) -> Result<(), MetaError> {
+        let mut updates: HashMap<String, (i64, i64)> = HashMap::with_capacity(deltas.len());
+        for delta in deltas {
+            let quota_key = Self::etcd_quota_key(QuotaType::try_from(delta.qtype)?, delta.key);
+            let update = updates.entry(quota_key).or_default();
+            update.0 += delta.space_delta;
+            update.1 += delta.inode_delta;
+        }
+
+        // One transaction for all deltas, so a failed flush applied none of them
+        let deps = updates.keys().cloned().collect();
+        let mut builder = TxnBuilder::new();
+        builder.add_stage(deps, move |ctx| {
+            let mut plans = Vec::with_capacity(updates.len());
+            for (quota_key, (space_delta, inode_delta)) in &updates {
+                // The quota was deleted meanwhile; drop its usage.
+                let Some(raw) = ctx.value(quota_key) else {
+                    continue;
+                };
+                let mut quota: Quota = crate::meta::serialization::deserialize_meta(raw)?;
+                quota.used_space += space_delta;
+                quota.used_inodes += inode_delta;
+                let payload = crate::meta::serialization::serialize_meta(&quota)?;
+                plans.push(UpdatePlan::new_write(ctx, quota_key.clone(), payload)?);
+            }
+            Ok(plans)
+        });
+        builder.execute(&self.client, 10).await
+    }
 }
 
 #[cfg(test)]
@@ -4310,4 +4854,107 @@ mod tests {
         let result = store.chmod(999999, 0o644).await;
         assert!(result.is_err(), "chmod on nonexistent inode should fail");
     }
+
+    #[serial]
+    #[tokio::test]
+    #[ignore]
+    async fn test_xattr_operations() {
+        let store = new_test_store().await;
+        let root = store.root_ino();
+        let file = store.create_file(root, "x.txt".to_string()).await.unwrap();
+        let dir = store.mkdir(root, "xdir".to_string()).await.unwrap();
+        let create = libc::XATTR_CREATE as u32;
+        let replace = libc::XATTR_REPLACE as u32;
+
+        assert!(matches!(
+            store.set_xattr(file, "user.a", b"1", replace).await,
+            Err(MetaError::NotFound(_))
+        ));
+        store.set_xattr(file, "user.a", b"1", create).await.unwrap();
+        assert!(matches!(
+            store.set_xattr(file, "user.a", b"2", create).await,
+            Err(MetaError::AlreadyExists { .. })
+        ));
+        store
+            .set_xattr(file, "user.a", b"2", replace)
+            .await
+            .unwrap();
+        store.set_xattr(file, "user.b", &[0, 255], 0).await.unwrap();
+        assert_eq!(
+            store.get_xattr(file, "user.a").await.unwrap(),
+            Some(b"2".to_vec())
+        );
+        assert_eq!(
+            store.get_xattr(file, "user.b").await.unwrap(),
+            Some(vec![0, 255])
+        );
+        assert_eq!(store.get_xattr(file, "user.c").await.unwrap(), None);
+        let mut names = store.list_xattr(file).await.unwrap();
+        names.sort();
+        assert_eq!(names, vec!["user.a", "user.b"]);
+
+        assert!(matches!(
+            store.set_xattr(file, &"n".repeat(256), b"", 0).await,
+            Err(MetaError::InvalidXattrName(_))
+        ));
+        assert!(matches!(
+            store.set_xattr(file, "user.big", &vec![0; 65537], 0).await,
+            Err(MetaError::XattrTooLarge)
+        ));
+        assert!(matches!(
+            store.set_xattr(999999, "user.a", b"1", 0).await,
+            Err(MetaError::NotFound(_))
+        ));
+
+        store.remove_xattr(file, "user.a").await.unwrap();
+        assert!(matches!(
+            store.remove_xattr(file, "user.a").await,
+            Err(MetaError::NotFound(_))
+        ));
+        assert_eq!(store.list_xattr(file).await.unwrap(), vec!["user.b"]);
+
+        // Attributes go away with their inode.
+        store.set_xattr(dir, "user.a", b"1", 0).await.unwrap();
+        store.rmdir(root, "xdir").await.unwrap();
+        let again = store.mkdir(root, "xdir".to_string()).await.unwrap();
+        assert!(store.list_xattr(again).await.unwrap().is_empty());
+    }
+
+    #[serial]
+    #[tokio::test]
+    #[ignore]
+    async fn test_flush_quotas_is_all_or_nothing() {
+        use crate::meta::store::{Quota, QuotaDelta, QuotaType};
+
+        let store = new_test_store().await;
+        let qtype = u32::from(QuotaType::Dir);
+        for key in [1, 2] {
+            store.set_quota(qtype, key, Quota::default()).await.unwrap();
+        }
+        let deltas = [1, 2].map(|key| QuotaDelta {
+            qtype,
+            key,
+            space_delta: 100,
+            inode_delta: 1,
+        });
+
+        // The second quota cannot be updated: the first one is left alone
+        let key = EtcdMetaStore::etcd_quota_key(QuotaType::Dir, 2);
+        let mut client = store.get_client();
+        client.put(key.as_str(), "garbage", None).await.unwrap();
+        assert!(store.flush_quotas(&deltas).await.is_err());
+        assert_eq!(
+            store.get_quota(qtype, 1).await.unwrap().unwrap().used_space,
+            0
+        );
+
+        // Retrying once it can applies every delta once
+        store.delete_quota(qtype, 2).await.unwrap();
+        store.set_quota(qtype, 2, Quota::default()).await.unwrap();
+        store.flush_quotas(&deltas).await.unwrap();
+        for key in [1, 2] {
+            let quota = store.get_quota(qtype, key).await.unwrap().unwrap();
+            assert_eq!((quota.used_space, quota.used_inodes), (100, 1));
+        }
+    }
 }
@@ -17,6 +17,36 @@ pub use etcd_store::EtcdMetaStore;
 pub(crate) use etcd_watch::{CacheInvalidationEvent, EtcdWatchWorker, WatchConfig};
 pub use redis_store::RedisMetaStore;
 
+use crate::meta::store::MetaError;
+use crate::posix::{XATTR_NAME_MAX, XATTR_SIZE_MAX};
+
+/// Rejects extended attributes the kernel would not accept either.
+fn check_xattr(name: &str, value: &[u8]) -> Result<(), MetaError> {
+    if name.is_empty() || name.len() > XATTR_NAME_MAX {
+        return Err(MetaError::InvalidXattrName(name.to_string()));
+    }
+    if value.len() > XATTR_SIZE_MAX {
+        return Err(MetaError::XattrTooLarge);
+    }
+    Ok(())
+}
+
+/// References dropped by compacting `old`: for every slice, the number of
+/// descriptors replaced and the longest of them.
+fn released_slices(old: &[crate::chuck::SliceDesc]) -> Vec<(u64, i64, u64)> {
+    let mut released: Vec<(u64, i64, u64)> = Vec::new();
+    for desc in old {
+        match released.iter_mut().find(|(id, _, _)| *id == desc.slice_id) {
+            Some((_, refs, length)) => {
+                *refs += 1;
+                *length = (*length).max(desc.length);
+            }
+            None => released.push((desc.slice_id, 1, desc.length)),
+        }
+    }
+    released
+}
+
 struct TruncatePlan {
     cutoff_chunk: u64,
     cutoff_offset: u64,
@@ -3,10 +3,10 @@
 //! This store focuses on the core interfaces needed by the VFS layer so that
 //! the filesystem can persist metadata in Redis. It purposely keeps the key
 //! layout simple (one key per inode plus a hash per directory) and uses JSON
-//! serialization for file attributes. Advanced features (sessions, quota, etc.)
-//! can be layered on later by extending the schema.
+//! serialization for file attributes. Quotas live in one hash of limits per
+//! quota type plus two hashes of usage counters so usage can be `HINCRBY`ed.
 
-use super::{apply_truncate_plan, trim_slices_in_place};
+use super::{apply_truncate_plan, check_xattr, released_slices, trim_slices_in_place};
 use crate::chuck::SliceDesc;
 use crate::meta::client::session::{Session, SessionInfo};
 use crate::meta::config::{Config, DatabaseType};
@@ -14,7 +14,8 @@ use crate::meta::file_lock::{
     FileLockInfo, FileLockQuery, FileLockRange, FileLockType, PlockRecord,
 };
 use crate::meta::store::{
-    DirEntry, FileAttr, FileType, LockName, MetaError, MetaStore, SetAttrFlags, SetAttrRequest,
+    DelayedSlice, DirEntry, FileAttr, FileType, LockName, MetaError, MetaStore, Quota, QuotaDelta,
+    QuotaType, SetAttrFlags, SetAttrRequest,
 };
 use crate::meta::{INODE_ID_KEY, SLICE_ID_KEY};
 use async_trait::async_trait;
@@ -23,6 +24,7 @@ use redis::AsyncCommands;
 use redis::aio::ConnectionManager;
 use serde::{Deserialize, Serialize};
 use std::any::Any;
+use std::collections::HashMap;
 use std::path::Path;
 use std::str::FromStr;
 use std::time::Duration;
@@ -45,6 +47,15 @@ const PLOCK_PREFIX: &str = "plock";
 const LOCKS_KEY: &str = "locks";
 const LOCKED_KEY: &str = "locked";
 const LINK_PARENT_KEY_PREFIX: &str = "lp:";
+const QUOTA_KEY_SUFFIX: &str = "Quota";
+// Hash of slice id -> references beyond the first, for slices shared by clones
+const SLICE_REFS_KEY: &str = "sliceRef";
+// Hash of slice id -> DelayedSlice JSON, for slices replaced by compaction
+const DELAYED_SLICES_KEY: &str = "delayedSlices";
+// Hash of volume setting name -> JSON document
+const SETTINGS_KEY: &str = "setting";
+// Per-inode hash of extended attribute name -> value
+const XATTR_KEY_PREFIX: &str = "x";
 
 const CHUNK_ID_BASE: u64 = 1_000_000_000u64;
 
@@ -74,6 +85,40 @@ const EXTEND_FILE_SIZE_LUA: &str = r#"
     return cjson.encode({ok=true, updated=true})
 "#;
 
+// Lua script for adding usage deltas in one step, each only while the limits
+// of its quota still exist; KEYS and ARGV hold three entries per delta
+const FLUSH_QUOTA_LUA: &str = r#"
+    -- Redis keeps the writes made before an error: check every counter first
+    for i = 1, #KEYS, 3 do
+        for j = 1, 2 do
+            local used = redis.call('HGET', KEYS[i + j], ARGV[i])
+            if used and not string.match(used, '^-?%d+$') then
+                return redis.error_reply('invalid usage in ' .. KEYS[i + j] .. ' for ' .. ARGV[i])
+            end
+        end
+    end
+    local applied = 0
+    for i = 1, #KEYS, 3 do
+        local field = ARGV[i]
+        if redis.call('HEXISTS', KEYS[i], field) == 1 then
+            redis.call('HINCRBY', KEYS[i + 1], field, ARGV[i + 1])
+            redis.call('HINCRBY', KEYS[i + 2], field, ARGV[i + 2])
+            applied = applied + 1
+        end
+    end
+    return applied
+"#;
+
+// Lua script for raising a counter; INCR counters hold the last id handed out
+const SET_COUNTER_IF_SMALL_LUA: &str = r#"
+    local cur = tonumber(redis.call('GET', KEYS[1]) or '0') + 1
+    if cur > tonumber(ARGV[1]) - tonumber(ARGV[2]) then
+        return 0
+    end
+    redis.call('SET', KEYS[1], tonumber(ARGV[1]) - 1)
+    return 1
+"#;
+
 // Lua script for atomically incrementing nlink and updating link_parents
 const LINK_LUA: &str = r#"
     local node_key = KEYS[1]
@@ -188,6 +233,7 @@ const RMDIR_LUA: &str = r#"
     local child_node_key = KEYS[2]
     local parent_node_key = KEYS[3]
     local child_dir_key = KEYS[4]
+    local child_xattr_key = KEYS[5]
     local name = ARGV[1]
     local child_ino = tonumber(ARGV[2])
     local parent_ino = tonumber(ARGV[3])
@@ -238,10 +284,93 @@ const RMDIR_LUA: &str = r#"
     redis.call('HDEL', parent_dir_key, name)
     redis.call('DEL', child_node_key)
     redis.call('DEL', child_dir_key)
+    redis.call('DEL', child_xattr_key)
 
     return cjson.encode({ok=true})
 "#;
 
+// Lua script for setting an extended attribute of an existing inode;
+// ARGV[3] is 1 for XATTR_CREATE and 2 for XATTR_REPLACE
+const SET_XATTR_LUA: &str = r#"
+    if redis.call('EXISTS', KEYS[1]) == 0 then
+        return 'node_not_found'
+    end
+    local exists = redis.call('HEXISTS', KEYS[2], ARGV[1]) == 1
+    if ARGV[3] == '1' and exists then
+        return 'exists'
+    end
+    if ARGV[3] == '2' and not exists then
+        return 'missing'
+    end
+    redis.call('HSET', KEYS[2], ARGV[1], ARGV[2])
+    return 'ok'
+"#;
+
+// Lua script for replacing the leading slices of a chunk (KEYS[1]) with a
+// compacted slice. ARGV: the compacted slice, the number n of replaced slices,
+// the n replaced slices, then a slice id, dropped references and DelayedSlice
+// JSON for every replaced slice.
+const COMPACT_CHUNK_LUA: &str = r#"
+    local n = tonumber(ARGV[2])
+    local current = redis.call('LRANGE', KEYS[1], 0, n - 1)
+    if #current ~= n then
+        return 0
+    end
+    for i = 1, n do
+        if current[i] ~= ARGV[2 + i] then
+            return 0
+        end
+    end
+    redis.call('LTRIM', KEYS[1], n, -1)
+    redis.call('LPUSH', KEYS[1], ARGV[1])
+    for i = 3 + n, #ARGV, 3 do
+        local id = ARGV[i]
+        local refs = tonumber(ARGV[i + 1])
+        local extra = tonumber(redis.call('HGET', KEYS[2], id) or '0')
+        if extra >= refs then
+            redis.call('HINCRBY', KEYS[2], id, -refs)
+        else
+            redis.call('HDEL', KEYS[2], id)
+            redis.call('HSET', KEYS[3], id, ARGV[i + 2])
+        end
+    end
+    return 1
+"#;
+
+// Lua script for replacing the slices of a chunk (KEYS[1]) with the ones a
+// truncate or unlink keeps. ARGV: the number n of current slices, the n
+// current slices, the number m of kept slices, the m kept slices, then a slice
+// id, dropped references and DelayedSlice JSON for every dropped slice.
+const DROP_CHUNK_SLICES_LUA: &str = r#"
+    local n = tonumber(ARGV[1])
+    local current = redis.call('LRANGE', KEYS[1], 0, -1)
+    if #current ~= n then
+        return 0
+    end
+    for i = 1, n do
+        if current[i] ~= ARGV[1 + i] then
+            return 0
+        end
+    end
+    local m = tonumber(ARGV[2 + n])
+    redis.call('DEL', KEYS[1])
+    for i = 3 + n, 2 + n + m do
+        redis.call('RPUSH', KEYS[1], ARGV[i])
+    end
+    for i = 3 + n + m, #ARGV, 3 do
+        local id = ARGV[i]
+        local refs = tonumber(ARGV[i + 1])
+        local extra = tonumber(redis.call('HGET', KEYS[2], id) or '0')
+        if extra >= refs then
+            redis.call('HINCRBY', KEYS[2], id, -refs)
+        else
+            redis.call('HDEL', KEYS[2], id)
+            redis.call('HSET', KEYS[3], id, ARGV[i + 2])
+        end
+    end
+    return 1
+"#;
+
 // Lua script for atomically creating directory entry with inode allocation
 const CREATE_ENTRY_LUA: &str = r#"
     local cjson = cjson
@@ -687,6 +816,10 @@ impl RedisMetaStore {
         format!("{DIR_KEY_PREFIX}{ino}")
     }
 
+    fn xattr_key(&self, ino: i64) -> String {
+        format!("{XATTR_KEY_PREFIX}{ino}")
+    }
+
     fn chunk_key(&self, chunk_id: u64) -> String {
         let inode = chunk_id / CHUNK_ID_BASE;
         let chunk_index = chunk_id % CHUNK_ID_BASE;
@@ -731,6 +864,16 @@ impl RedisMetaStore {
         format!("{LINK_PARENT_KEY_PREFIX}{ino}")
     }
 
+    /// Hashes holding the limits, used space and used inodes of a quota type,
+    /// e.g. `dirQuota`, `dirQuotaUsedSpace` and `dirQuotaUsedInodes`.
+    fn quota_keys(qtype: QuotaType) -> (String, String, String) {
+        (
+            format!("{qtype}{QUOTA_KEY_SUFFIX}"),
+            format!("{qtype}{QUOTA_KEY_SUFFIX}UsedSpace"),
+            format!("{qtype}{QUOTA_KEY_SUFFIX}UsedInodes"),
+        )
+    }
+
     async fn init_root_directory(&self) -> Result<(), MetaError> {
         let mut conn = self.conn.clone();
         let root_key = self.node_key(ROOT_INODE);
@@ -820,7 +963,9 @@ impl RedisMetaStore {
 
     async fn delete_node(&self, ino: i64) -> Result<(), MetaError> {
         let mut conn = self.conn.clone();
-        conn.del(self.node_key(ino)).await.map_err(redis_err)
+        conn.del(&[self.node_key(ino), self.xattr_key(ino)])
+            .await
+            .map_err(redis_err)
     }
 
     async fn load_link_parents(&self, ino: i64) -> Result<Vec<(i64, String)>, MetaError> {
@@ -1100,16 +1245,67 @@ impl RedisMetaStore {
         }
     }
 
-    async fn rewrite_slices(&self, chunk_id: u64, slices: &[SliceDesc]) -> Result<(), MetaError> {
-        let mut conn = self.conn.clone();
-        let key = self.chunk_key(chunk_id);
-        let mut pipe = redis::pipe();
-        pipe.cmd("DEL").arg(&key);
-        for slice in slices {
-            let data = crate::meta::serialization::serialize_meta(slice)?;
-            pipe.cmd("RPUSH").arg(&key).arg(data);
+    /// Cuts the slices of `chunk_id` at `cutoff_offset` (all of them for 0)
+    /// and releases the references of the dropped slices atomically.
+    async fn drop_chunk_slices(&self, chunk_id: u64, cutoff_offset: u64) -> Result<(), MetaError> {
+        let script = redis::Script::new(DROP_CHUNK_SLICES_LUA);
+        loop {
+            let old = self.get_slices(chunk_id).await?;
+            if old.is_empty() {
+                return Ok(());
+            }
+            let mut kept = old.clone();
+            trim_slices_in_place(&mut kept, cutoff_offset);
+            let dropped: Vec<SliceDesc> = old
+                .iter()
+                .filter(|desc| desc.offset >= cutoff_offset)
+                .copied()
+                .collect();
+
+            let mut invocation = script.key(self.chunk_key(chunk_id));
+            invocation
+                .key(SLICE_REFS_KEY)
+                .key(DELAYED_SLICES_KEY)
+                .arg(old.len());
+            for desc in &old {
+                invocation.arg(crate::meta::serialization::serialize_meta(desc)?);
+            }
+            invocation.arg(kept.len());
+            for desc in &kept {
+                invocation.arg(crate::meta::serialization::serialize_meta(desc)?);
+            }
+            Self::release_args(&mut invocation, &dropped)?;
+            let mut conn = self.conn.clone();
+            let done: i64 = invocation
+                .invoke_async(&mut conn)
+                .await
+                .map_err(redis_err)?;
+            // Otherwise slices were appended meanwhile: start over.
+            if done == 1 {
+                return Ok(());
+            }
+        }
+    }
+
+    /// Appends the slice id, dropped references and [`DelayedSlice`] of every
+    /// slice released by `dropped` (see [`released_slices`]) to the
+    /// arguments of a slice releasing script.
+    fn release_args(
+        invocation: &mut redis::ScriptInvocation<'_>,
+        dropped: &[SliceDesc],
+    ) -> Result<(), MetaError> {
+        let since = Utc::now().timestamp_nanos_opt().unwrap_or(i64::MAX);
+        for (slice_id, refs, length) in released_slices(dropped) {
+            let delayed = DelayedSlice {
+                slice_id,
+                length,
+                since,
+            };
+            invocation.arg(slice_id).arg(refs).arg(
+                serde_json::to_vec(&delayed)
+                    .map_err(|e| MetaError::Serialization(e.to_string()))?,
+            );
         }
-        pipe.query_async::<()>(&mut conn).await.map_err(redis_err)?;
         Ok(())
     }
 
@@ -1173,22 +1369,12 @@ impl RedisMetaStore {
             old_size,
             chunk_size,
             |cutoff_chunk, cutoff_offset| async move {
-                let chunk_id = self.chunk_id(ino, cutoff_chunk);
-                let mut slices = self.get_slices(chunk_id).await?;
-                trim_slices_in_place(&mut slices, cutoff_offset);
-                self.rewrite_slices(chunk_id, &slices).await?;
-                Ok(())
+                self.drop_chunk_slices(self.chunk_id(ino, cutoff_chunk), cutoff_offset)
+                    .await
             },
             |start, end| async move {
                 for idx in start..end {
-                    let chunk_id = self.chunk_id(ino, idx);
-                    let key = self.chunk_key(chunk_id);
-                    let mut conn = self.conn.clone();
-                    redis::cmd("DEL")
-                        .arg(&key)
-                        .query_async::<()>(&mut conn)
-                        .await
-                        .map_err(redis_err)?;
+                    self.drop_chunk_slices(self.chunk_id(ino, idx), 0).await?;
                 }
                 Ok(())
             },
@@ -1357,6 +1543,7 @@ impl MetaStore for RedisMetaStore {
         let child_node_key = self.node_key(child);
         let parent_node_key = self.node_key(parent);
         let child_dir_key = self.dir_key(child);
+        let child_xattr_key = self.xattr_key(child);
         let now = current_time();
 
         // Step 3: Invoke Lua script atomically
@@ -1366,6 +1553,7 @@ impl MetaStore for RedisMetaStore {
             .key(&child_node_key)
             .key(&parent_node_key)
             .key(&child_dir_key)
+            .key(&child_xattr_key)
             .arg(name)
             .arg(child)
             .arg(parent)
@@ -1888,6 +2076,22 @@ impl MetaStore for RedisMetaStore {
     #[tracing::instrument(level = "trace", skip(self), fields(ino))]
     async fn remove_file_metadata(&self, ino: i64) -> Result<(), MetaError> {
         let mut conn = self.conn.clone();
+        let prefix = format!("{CHUNK_KEY_PREFIX}{ino}_");
+        let mut keys: Vec<String> = Vec::new();
+        let mut iter = conn
+            .scan_match::<_, String>(format!("{prefix}*"))
+            .await
+            .map_err(redis_err)?;
+        while let Some(key) = iter.next_item().await {
+            keys.push(key);
+        }
+        drop(iter);
+        for key in keys {
+            if let Some(index) = key.strip_prefix(&prefix).and_then(|i| i.parse().ok()) {
+                self.drop_chunk_slices(self.chunk_id(ino, index), 0).await?;
+            }
+        }
+
         let _: () = conn
             .hdel(self.deleted_set_key(), ino.to_string())
             .await
@@ -1936,6 +2140,81 @@ impl MetaStore for RedisMetaStore {
         Ok(())
     }
 
+    async fn update_slice_refs(&self, slices: &[u64], delta: i64) -> Result<(), MetaError> {
+        if slices.is_empty() {
+            return Ok(());
+        }
+        let mut conn = self.conn.clone();
+        let mut pipe = redis::pipe();
+        for slice_id in slices {
+            pipe.hincr(SLICE_REFS_KEY, slice_id.to_string(), delta)
+                .ignore();
+        }
+        let _: () = pipe.query_async(&mut conn).await.map_err(redis_err)?;
+        Ok(())
+    }
+
+    async fn get_slice_refs(&self, slice_id: u64) -> Result<i64, MetaError> {
+        let mut conn = self.conn.clone();
+        let extra: Option<i64> = conn
+            .hget(SLICE_REFS_KEY, slice_id.to_string())
+            .await
+            .map_err(redis_err)?;
+        Ok(1 + extra.unwrap_or(0))
+    }
+
+    async fn compact_chunk(
+        &self,
+        chunk_id: u64,
+        old: &[SliceDesc],
+        slice: SliceDesc,
+    ) -> Result<bool, MetaError> {
+        if old.is_empty() {
+            return Ok(false);
+        }
+        let script = redis::Script::new(COMPACT_CHUNK_LUA);
+        let mut invocation = script.key(self.chunk_key(chunk_id));
+        invocation
+            .key(SLICE_REFS_KEY)
+            .key(DELAYED_SLICES_KEY)
+            .arg(crate::meta::serialization::serialize_meta(&slice)?)
+            .arg(old.len());
+        for desc in old {
+            invocation.arg(crate::meta::serialization::serialize_meta(desc)?);
+        }
+        Self::release_args(&mut invocation, old)?;
+        let mut conn = self.conn.clone();
+        let replaced: i64 = invocation
+            .invoke_async(&mut conn)
+            .await
+            .map_err(redis_err)?;
+        Ok(replaced == 1)
+    }
+
+    async fn list_delayed_slices(&self, edge_ts: i64) -> Result<Vec<DelayedSlice>, MetaError> {
+        let mut conn = self.conn.clone();
+        let raw: Vec<Vec<u8>> = conn.hvals(DELAYED_SLICES_KEY).await.map_err(redis_err)?;
+        let mut slices = Vec::new();
+        for entry in raw {
+            let delayed: DelayedSlice = serde_json::from_slice(&entry)
+                .map_err(|e| MetaError::Serialization(e.to_string()))?;
+            if delayed.since < edge_ts {
+                slices.push(delayed);
+            }
+        }
+        slices.sort_by_key(|delayed| delayed.since);
+        Ok(slices)
+    }
+
+    async fn delete_slice(&self, slice_id: u64) -> Result<(), MetaError> {
+        let mut conn = self.conn.clone();
+        let _: i64 = conn
+            .hdel(DELAYED_SLICES_KEY, slice_id.to_string())
+            .await
+            .map_err(redis_err)?;
+        Ok(())
+    }
+
     #[tracing::instrument(
         level = "trace",
         skip(self, slice),
@@ -1957,6 +2236,124 @@ impl MetaStore for RedisMetaStore {
         self.alloc_id(key).await
     }
 
+    async fn get_counter(&self, name: &str) -> Result<i64, MetaError> {
+        let mut conn = self.conn.clone();
+        let last: Option<i64> = conn
+            .get(Self::counter_key(name)?)
+            .await
+            .map_err(redis_err)?;
+        Ok(last.unwrap_or(0) + 1)
+    }
+
+    async fn set_counter_if_small(
+        &self,
+        name: &str,
+        value: i64,
+        diff: i64,
+    ) -> Result<bool, MetaError> {
+        let script = redis::Script::new(SET_COUNTER_IF_SMALL_LUA);
+        let mut conn = self.conn.clone();
+        let changed: i64 = script
+            .key(Self::counter_key(name)?)
+            .arg(value)
+            .arg(diff)
+            .invoke_async(&mut conn)
+            .await
+            .map_err(redis_err)?;
+        Ok(changed == 1)
+    }
+
+    async fn get_setting(&self, name: &str) -> Result<Option<String>, MetaError> {
+        let mut conn = self.conn.clone();
+        conn.hget(SETTINGS_KEY, name).await.map_err(redis_err)
+    }
+
+    async fn set_setting(
+        &self,
+        name: &str,
+        value: &str,
+        overwrite: bool,
+    ) -> Result<bool, MetaError> {
+        let mut conn = self.conn.clone();
+        if overwrite {
+            let _: i64 = conn
+                .hset(SETTINGS_KEY, name, value)
+                .await
+                .map_err(redis_err)?;
+            return Ok(true);
+        }
+        conn.hset_nx(SETTINGS_KEY, name, value)
+            .await
+            .map_err(redis_err)
+    }
+
+    async fn set_xattr(
+        &self,
+        inode: i64,
+        name: &str,
+        value: &[u8],
+        flags: u32,
+    ) -> Result<(), MetaError> {
+        check_xattr(name, value)?;
+        let mode = if flags & (libc::XATTR_CREATE as u32) != 0 {
+            1
+        } else if flags & (libc::XATTR_REPLACE as u32) != 0 {
+            2
+        } else {
+            0
+        };
+        let result: String = redis::Script::new(SET_XATTR_LUA)
+            .key(self.node_key(inode))
+            .key(self.xattr_key(inode))
+            .arg(name)
+            .arg(value)
+            .arg(mode)
+            .invoke_async(&mut self.conn.clone())
+            .await
+            .map_err(redis_err)?;
+        match result.as_str() {
+            "ok" => Ok(()),
+            "exists" => Err(MetaError::AlreadyExists {
+                parent: inode,
+                name: name.to_string(),
+            }),
+            "missing" | "node_not_found" => Err(MetaError::NotFound(inode)),
+            other => Err(MetaError::Internal(format!("Lua error: {other}"))),
+        }
+    }
+
+    async fn get_xattr(&self, inode: i64, name: &str) -> Result<Option<Vec<u8>>, MetaError> {
+        let mut conn = self.conn.clone();
+        let value: Option<Vec<u8>> = conn
+            .hget(self.xattr_key(inode), name)
+            .await
+            .map_err(redis_err)?;
+        if value.is_none() && self.get_node(inode).await?.is_none() {
+            return Err(MetaError::NotFound(inode));
+        }
+        Ok(value)
+    }
+
+    async fn list_xattr(&self, inode: i64) -> Result<Vec<String>, MetaError> {
+        if self.get_node(inode).await?.is_none() {
+            return Err(MetaError::NotFound(inode));
+        }
+        let mut conn = self.conn.clone();
+        conn.hkeys(self.xattr_key(inode)).await.map_err(redis_err)
+    }
+
+    async fn remove_xattr(&self, inode: i64, name: &str) -> Result<(), MetaError> {
+        let mut conn = self.conn.clone();
+        let removed: i64 = conn
+            .hdel(self.xattr_key(inode), name)
+            .await
+            .map_err(redis_err)?;
+        if removed == 0 {
+            return Err(MetaError::NotFound(inode));
+        }
+        Ok(())
+    }
+
     #[tracing::instrument(level = "trace", skip(self), fields(pid = session_info.process_id))]
     async fn start_session(
         &self,
@@ -2167,6 +2564,164 @@ impl MetaStore for RedisMetaStore {
             }
         }
     }
+
+    async fn get_quota(&self, qtype: u32, key: u64) -> Result<Option<Quota>, MetaError> {
+        let (limits_key, space_key, inodes_key) = Self::quota_keys(QuotaType::try_from(qtype)?);
+        let field = key.to_string();
+        let (limits, used_space, used_inodes): (Option<String>, Option<i64>, Option<i64>) =
+            redis::pipe()
+                .hget(&limits_key, &field)
+                .hget(&space_key, &field)
+                .hget(&inodes_key, &field)
+                .query_async(&mut self.conn.clone())
+                .await
+                .map_err(redis_err)?;
+
+        let Some(limits) = limits else {
+            return Ok(None);
+        };
+        let limits: StoredQuotaLimits =
+            serde_json::from_str(&limits).map_err(|e| MetaError::Serialization(e.to_string()))?;
+        Ok(Some(limits.into_quota(
+            used_space.unwrap_or(0),
+            used_inodes.unwrap_or(0),
+        )))
+    }
+
+    async fn set_quota(&self, qtype: u32, key: u64, quota: Quota) -> Result<bool, MetaError> {
+        let (limits_key, space_key, inodes_key) = Self::quota_keys(QuotaType::try_from(qtype)?);
+        let field = key.to_string();
+        let limits = serde_json::to_string(&StoredQuotaLimits::from(&quota))
+            .map_err(|e| MetaError::Serialization(e.to_string()))?;
+
+        let (created,): (bool,) = redis::pipe()
+            .atomic()
+            .hset(&limits_key, &field, limits)
+            .hset(&space_key, &field, quota.used_space)
+            .ignore()
+            .hset(&inodes_key, &field, quota.used_inodes)
+            .ignore()
+            .query_async(&mut self.conn.clone())
+            .await
+            .map_err(redis_err)?;
+        Ok(created)
+    }
+
+    async fn delete_quota(&self, qtype: u32, key: u64) -> Result<(), MetaError> {
+        let (limits_key, space_key, inodes_key) = Self::quota_keys(QuotaType::try_from(qtype)?);
+        let field = key.to_string();
+        redis::pipe()
+            .atomic()
+            .hdel(&limits_key, &field)
+            .hdel(&space_key, &field)
+            .hdel(&inodes_key, &field)
+            .exec_async(&mut self.conn.clone())
+            .await
+            .map_err(redis_err)
+    }
+
+    async fn load_quotas(
+        &self,
+    ) -> Result<
+        (
+            HashMap<u64, Quota>,
+            HashMap<u64, Quota>,
+            HashMap<u64, Quota>,
+        ),
+        MetaError,
+    > {
+        let mut conn = self.conn.clone();
+        let mut loaded = Vec::with_capacity(QuotaType::ALL.len());
+        for qtype in QuotaType::ALL {
+            let (limits_key, space_key, inodes_key) = Self::quota_keys(qtype);
+            let (limits, used_space, used_inodes): (
+                HashMap<String, String>,
+                HashMap<String, i64>,
+                HashMap<String, i64>,
+            ) = redis::pipe()
+                .hgetall(&limits_key)
+                .hgetall(&space_key)
+                .hgetall(&inodes_key)
+                .query_async(&mut conn)
+                .await
+                .map_err(redis_err)?;
+
+            let mut quotas = HashMap::with_capacity(limits.len());
+            for (field, raw) in limits {
+                let (Ok(key), Ok(limits)) = (
+                    field.parse::<u64>(),
+                    serde_json::from_str::<StoredQuotaLimits>(&raw),
+                ) else {
+                    tracing::warn!("invalid {qtype} quota entry {field}: {raw}");
+                    continue;
+                };
+                let quota = limits.into_quota(
+                    used_space.get(&field).copied().unwrap_or(0),
+                    used_inodes.get(&field).copied().unwrap_or(0),
+                );
+                quotas.insert(key, quota);
+            }
+            loaded.push(quotas);
+        }
+
+        let groups = loaded.pop().unwrap_or_default();
+        let users = loaded.pop().unwrap_or_default();
+        let dirs = loaded.pop().unwrap_or_default();
+        Ok((dirs, users, groups))
+    }
+
+    async fn flush_quotas(&self, deltas: &[QuotaDelta]) -> Result<(), MetaError> {
+        if deltas.is_empty() {
+            return Ok(());
+        }
+        // One script for all deltas, so a failed flush applied none of them
+        let script = redis::Script::new(FLUSH_QUOTA_LUA);
+        let mut invocation = script.prepare_invoke();
+        for delta in deltas {
+            let (limits_key, space_key, inodes_key) =
+                Self::quota_keys(QuotaType::try_from(delta.qtype)?);
+            invocation
+                .key(limits_key)
+                .key(space_key)
+                .key(inodes_key)
+                .arg(delta.key.to_string())
+                .arg(delta.space_delta)
+                .arg(delta.inode_delta);
+        }
+        let _: i64 = invocation
+            .invoke_async(&mut self.conn.clone())
+            .await
+            .map_err(redis_err)?;
+        Ok(())
+    }
+}
+
+/// Quota limits as stored in the `<type>Quota` hashes; usage lives in
+/// separate counter hashes.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+struct StoredQuotaLimits {
+    limit_space: Option<i64>,
+    limit_inodes: Option<i64>,
+}
+
+impl StoredQuotaLimits {
+    fn into_quota(self, used_space: i64, used_inodes: i64) -> Quota {
+        Quota {
+            limit_space: self.limit_space,
+            limit_inodes: self.limit_inodes,
+            used_space,
+            used_inodes,
+        }
+    }
+}
+
+impl From<&Quota> for StoredQuotaLimits {
+    fn from(quota: &Quota) -> Self {
+        Self {
+            limit_space: quota.limit_space,
+            limit_inodes: quota.limit_inodes,
+        }
+    }
 }
 
 #[derive(Clone, Debug, Serialize, Deserialize)]
@@ -3828,4 +4383,111 @@ mod tests {
         let result = store.chmod(999999, 0o644).await;
         assert!(result.is_err(), "chmod on nonexistent inode should fail");
     }
+
+    #[serial]
+    #[tokio::test]
+    #[ignore]
+    async fn test_xattr_operations() {
+        let store = new_test_store().await;
+        let root = store.root_ino();
+        let file = store.create_file(root, "x.txt".to_string()).await.unwrap();
+        let dir = store.mkdir(root, "xdir".to_string()).await.unwrap();
+        let create = libc::XATTR_CREATE as u32;
+        let replace = libc::XATTR_REPLACE as u32;
+
+        assert!(matches!(
+            store.set_xattr(file, "user.a", b"1", replace).await,
+            Err(MetaError::NotFound(_))
+        ));
+        store.set_xattr(file, "user.a", b"1", create).await.unwrap();
+        assert!(matches!(
+            store.set_xattr(file, "user.a", b"2", create).await,
+            Err(MetaError::AlreadyExists { .. })
+        ));
+        store
+            .set_xattr(file, "user.a", b"2", replace)
+            .await
+            .unwrap();
+        store.set_xattr(file, "user.b", &[0, 255], 0).await.unwrap();
+        assert_eq!(
+            store.get_xattr(file, "user.a").await.unwrap(),
+            Some(b"2".to_vec())
+        );
+        assert_eq!(
+            store.get_xattr(file, "user.b").await.unwrap(),
+            Some(vec![0, 255])
+        );
+        assert_eq!(store.get_xattr(file, "user.c").await.unwrap(), None);
+        let mut names = store.list_xattr(file).await.unwrap();
+        names.sort();
+        assert_eq!(names, vec!["user.a", "user.b"]);
+
+        assert!(matches!(
+            store.set_xattr(file, &"n".repeat(256), b"", 0).await,
+            Err(MetaError::InvalidXattrName(_))
+        ));
+        assert!(matches!(
+            store.set_xattr(file, "user.big", &vec![0; 65537], 0).await,
+            Err(MetaError::XattrTooLarge)
+        ));
+        assert!(matches!(
+            store.set_xattr(999999, "user.a", b"1", 0).await,
+            Err(MetaError::NotFound(_))
+        ));
+
+        store.remove_xattr(file, "user.a").await.unwrap();
+        assert!(matches!(
+            store.remove_xattr(file, "user.a").await,
+            Err(MetaError::NotFound(_))
+        ));
+        assert_eq!(store.list_xattr(file).await.unwrap(), vec!["user.b"]);
+
+        // Attributes go away with their inode.
+        store.set_xattr(dir, "user.a", b"1", 0).await.unwrap();
+        store.rmdir(root, "xdir").await.unwrap();
+        let again = store.mkdir(root, "xdir".to_string()).await.unwrap();
+        assert!(store.list_xattr(again).await.unwrap().is_empty());
+    }
+
+    #[serial]
+    #[tokio::test]
+    #[ignore]
+    async fn test_flush_quotas_is_all_or_nothing() {
+        use crate::meta::store::{Quota, QuotaDelta, QuotaType};
+        use redis::AsyncCommands;
+
+        let store = new_test_store().await;
+        let qtype = u32::from(QuotaType::Dir);
+        for key in [1, 2] {
+            store.set_quota(qtype, key, Quota::default()).await.unwrap();
+        }
+        let deltas = [1, 2].map(|key| QuotaDelta {
+            qtype,
+            key,
+            space_delta: 100,
+            inode_delta: 1,
+        });
+
+        // The second quota cannot be updated: the first one is left alone
+        let (_, space_key, _) = RedisMetaStore::quota_keys(QuotaType::Dir);
+        let _: () = store
+            .conn
+            .clone()
+            .hset(&space_key, "2", "garbage")
+            .await
+            .unwrap();
+        assert!(store.flush_quotas(&deltas).await.is_err());
+        assert_eq!(
+            store.get_quota(qtype, 1).await.unwrap().unwrap().used_space,
+            0
+        );
+
+        // Retrying once it can applies every delta once
+        let _: () = store.conn.clone().hset(&space_key, "2", 0).await.unwrap();
+        store.flush_quotas(&deltas).await.unwrap();
+        for key in [1, 2] {
+            let quota = store.get_quota(qtype, key).await.unwrap().unwrap();
+            assert_eq!((quota.used_space, quota.used_inodes), (100, 1));
+        }
+    }
 }
@@ -0,0 +1,252 @@
+//! Trash for removed files.
+//!
+//! With a retention period configured, the metadata client moves files whose
+//! last link is removed (by `unlink` or by a rename replacing them) to
+//! `/.trash/<batch>/<parent>-<inode>-<name>` instead of deleting them. The
+//! batch is the UTC hour of the removal, so a whole batch expires at once.
+//!
+//! Trashed files keep their slices and are not reported by
+//! [`MetaStore::get_deleted_files`]. Only once their batch is older than the
+//! retention period does [`MetaStore::cleanup_delayed_slices`] remove them,
+//! handing their data over to the garbage collector. Until then an entry can
+//! be moved back to its original directory.
+//!
+//! Like the dump and clones, this only uses the generic [`MetaStore`]
+//! operations and works on every backend.
+
+use std::time::{Duration, SystemTime};
+
+use chrono::{DateTime, NaiveDateTime, Utc};
+
+use crate::meta::store::{MetaError, MetaStore, SetAttrFlags, chmod_request};
+use crate::posix::NAME_MAX;
+use crate::vfs::fs::FileType;
+
+/// Name of the root directory holding removed files.
+pub const TRASH_DIR: &str = ".trash";
+
+/// Format of batch directory names: the UTC hour the entries were removed in.
+const BATCH_FORMAT: &str = "%Y-%m-%d-%H";
+
+/// Time span covered by one batch.
+const BATCH_SPAN: Duration = Duration::from_secs(3600);
+
+/// Trash and batch directories are only accessible to root: removed files
+/// keep their own modes, which may not be meant for everyone's eyes once
+/// their directory is gone. Only the metadata client adds or removes entries.
+const TRASH_MODE: u32 = 0o700;
+
+/// A removed file waiting in the trash.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct TrashEntry {
+    /// Name of the batch directory holding the entry.
+    pub batch: String,
+    /// Name of the entry inside its batch.
+    pub name: String,
+    pub ino: i64,
+    /// Directory the file was removed from.
+    pub parent: i64,
+    /// Name the file had; may be truncated for very long names.
+    pub orig_name: String,
+}
+
+/// Name of the trash entry for `parent/name`, truncated to `NAME_MAX`.
+pub fn entry_name(parent: i64, ino: i64, name: &str) -> String {
+    let mut entry = format!("{parent}-{ino}-{name}");
+    if entry.len() > NAME_MAX {
+        let mut end = NAME_MAX;
+        while !entry.is_char_boundary(end) {
+            end -= 1;
+        }
+        entry.truncate(end);
+    }
+    entry
+}
+
+/// Splits a trash entry name into the original parent, inode and name.
+pub fn parse_entry_name(entry: &str) -> Option<(i64, i64, String)> {
+    let (parent, rest) = entry.split_once('-')?;
+    let (ino, name) = rest.split_once('-')?;
+    if name.is_empty() {
+        return None;
+    }
+    Some((parent.parse().ok()?, ino.parse().ok()?, name.to_string()))
+}
+
+fn batch_name(time: SystemTime) -> String {
+    DateTime::<Utc>::from(time).format(BATCH_FORMAT).to_string()
+}
+
+/// Start of the hour covered by the batch `name`.
+fn batch_start(name: &str) -> Option<SystemTime> {
+    let start = NaiveDateTime::parse_from_str(&format!("{name}-00"), "%Y-%m-%d-%H-%M").ok()?;
+    Some(start.and_utc().into())
+}
+
+async fn ensure_dir<S>(store: &S, parent: i64, name: &str) -> Result<i64, MetaError>
+where
+    S: MetaStore + ?Sized,
+{
+    if let Some(ino) = store.lookup(parent, name).await? {
+        return Ok(ino);
+    }
+    match store.mkdir(parent, name.to_string()).await {
+        Ok(ino) => {
+            store
+                .set_attr(ino, &chmod_request(TRASH_MODE), SetAttrFlags::empty())
+                .await?;
+            Ok(ino)
+        }
+        Err(MetaError::AlreadyExists { .. }) => store
+            .lookup(parent, name)
+            .await?
+            .ok_or(MetaError::NotFound(parent)),
+        Err(err) => Err(err),
+    }
+}
+
+/// Returns the trash directory, if it exists.
+pub async fn trash_dir<S>(store: &S) -> Result<Option<i64>, MetaError>
+where
+    S: MetaStore + ?Sized,
+{
+    store.lookup(store.root_ino(), TRASH_DIR).await
+}
+
+/// Returns the batch directory for files removed at `time`, creating it and
+/// the trash directory as needed.
+pub async fn batch_dir<S>(store: &S, time: SystemTime) -> Result<(String, i64), MetaError>
+where
+    S: MetaStore + ?Sized,
+{
+    let trash = ensure_dir(store, store.root_ino(), TRASH_DIR).await?;
+    let batch = batch_name(time);
+    let ino = ensure_dir(store, trash, &batch).await?;
+    Ok((batch, ino))
+}
+
+/// Whether `dir` is the trash directory or one of its batches. Removing
+/// entries from there deletes them for good.
+pub async fn is_trash_dir<S>(store: &S, dir: i64) -> Result<bool, MetaError>
+where
+    S: MetaStore + ?Sized,
+{
+    let Some(trash) = trash_dir(store).await? else {
+        return Ok(false);
+    };
+    if dir == trash {
+        return Ok(true);
+    }
+    match store.get_dir_parent(dir).await {
+        Ok(parent) => Ok(parent == Some(trash)),
+        Err(MetaError::NotDirectory(_)) => Ok(false),
+        Err(err) => Err(err),
+    }
+}
+
+/// Lists the entries of all batches, oldest batch first.
+pub async fn list_entries<S>(store: &S) -> Result<Vec<TrashEntry>, MetaError>
+where
+    S: MetaStore + ?Sized,
+{
+    let Some(trash) = trash_dir(store).await? else {
+        return Ok(Vec::new());
+    };
+    let mut batches: Vec<_> = store
+        .readdir(trash)
+        .await?
+        .into_iter()
+        .filter(|entry| entry.kind == FileType::Dir)
+        .collect();
+    batches.sort_by(|a, b| a.name.cmp(&b.name));
+
+    let mut entries = Vec::new();
+    for batch in batches {
+        for entry in store.readdir(batch.ino).await? {
+            let Some((parent, _, orig_name)) = parse_entry_name(&entry.name) else {
+                continue;
+            };
+            entries.push(TrashEntry {
+                batch: batch.name.clone(),
+                name: entry.name,
+                ino: entry.ino,
+                parent,
+                orig_name,
+            });
+        }
+    }
+    Ok(entries)
+}
+
+/// Removes every batch that ended before `edge_ts` (nanoseconds since the
+/// epoch) together with its entries, returning the number of entries removed.
+/// Entries of newer batches are left alone.
+pub async fn cleanup_expired<S>(store: &S, edge_ts: i64) -> Result<i32, MetaError>
+where
+    S: MetaStore + ?Sized,
+{
+    let Some(trash) = trash_dir(store).await? else {
+        return Ok(0);
+    };
+    let edge = SystemTime::UNIX_EPOCH + Duration::from_nanos(edge_ts.max(0) as u64);
+
+    let mut removed = 0;
+    for batch in store.readdir(trash).await? {
+        let Some(start) = batch_start(&batch.name) else {
+            continue;
+        };
+        if batch.kind != FileType::Dir || start + BATCH_SPAN > edge {
+            continue;
+        }
+        for entry in store.readdir(batch.ino).await? {
+            if entry.kind == FileType::Dir {
+                continue;
+            }
+            // Another client may be sweeping the same batch.
+            match store.unlink(batch.ino, &entry.name).await {
+                Ok(()) => removed += 1,
+                Err(MetaError::NotFound(_)) => {}
+                Err(err) => return Err(err),
+            }
+        }
+        match store.rmdir(trash, &batch.name).await {
+            Ok(()) | Err(MetaError::NotFound(_)) | Err(MetaError::DirectoryNotEmpty(_)) => {}
+            Err(err) => return Err(err),
+        }
+    }
+    Ok(removed)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn entry_names_round_trip() {
+        let name = entry_name(1, 42, "report-final.txt");
+        assert_eq!(name, "1-42-report-final.txt");
+        assert_eq!(
+            parse_entry_name(&name),
+            Some((1, 42, "report-final.txt".to_string()))
+        );
+        assert_eq!(parse_entry_name("1-42-"), None);
+        assert_eq!(parse_entry_name("x-42-a"), None);
+
+        let long = "é".repeat(NAME_MAX);
+        let name = entry_name(1, 42, &long);
+        assert!(name.len() <= NAME_MAX);
+        assert!(parse_entry_name(&name).is_some());
+    }
+
+    #[test]
+    fn batch_names_cover_an_hour() {
+        let time = SystemTime::UNIX_EPOCH + Duration::from_secs(3600 * 24 + 1800);
+        let name = batch_name(time);
+        assert_eq!(name, "1970-01-02-00");
+        assert_eq!(
+            batch_start(&name),
+            Some(SystemTime::UNIX_EPOCH + Duration::from_secs(3600 * 24))
+        );
+        assert_eq!(batch_start("not-a-batch"), None);
+    }
+}
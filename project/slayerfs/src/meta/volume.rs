@@ -0,0 +1,212 @@
+//! Volume identity recorded by `slayerfs format`.
+//!
+//! Besides the block format, every client of a volume has to agree on how
+//! files are cut into chunks and blocks and on where the blocks are stored.
+//! `format` records these once as a [`VolumeFormat`] under
+//! [`VOLUME_SETTING`]; clients read it instead of taking them from the
+//! command line, and refuse to open the volume with a different layout.
+//!
+//! Volumes created before `format` existed have no such setting. They keep
+//! working with the layout given on the command line and can be formatted
+//! afterwards, as long as the recorded layout is the one they were written
+//! with.
+
+use anyhow::Context;
+use serde::{Deserialize, Serialize};
+use uuid::Uuid;
+
+use crate::chuck::ChunkLayout;
+use crate::meta::store::{MetaError, MetaStore};
+
+/// Name of the volume setting holding the [`VolumeFormat`].
+pub const VOLUME_SETTING: &str = "volume";
+
+/// Settings fixed when a volume is formatted.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct VolumeFormat {
+    pub name: String,
+    pub uuid: String,
+    pub chunk_size: u64,
+    pub block_size: u32,
+    /// URL of the object storage holding the blocks, e.g. `s3://bucket`.
+    pub storage: String,
+}
+
+impl VolumeFormat {
+    /// Creates the format of a new volume with a fresh UUID.
+    pub fn new(name: &str, layout: ChunkLayout, storage: &str) -> anyhow::Result<Self> {
+        if name.is_empty() || name.contains(['/', '\0']) {
+            anyhow::bail!("invalid volume name {name:?}");
+        }
+        check_layout(layout)?;
+        Ok(Self {
+            name: name.to_string(),
+            uuid: Uuid::now_v7().to_string(),
+            chunk_size: layout.chunk_size,
+            block_size: layout.block_size,
+            storage: storage.to_string(),
+        })
+    }
+
+    pub fn layout(&self) -> ChunkLayout {
+        ChunkLayout {
+            chunk_size: self.chunk_size,
+            block_size: self.block_size,
+        }
+    }
+
+    /// Whether `other` describes the same volume apart from its UUID.
+    fn same_settings(&self, other: &VolumeFormat) -> bool {
+        self.name == other.name && self.layout() == other.layout() && self.storage == other.storage
+    }
+}
+
+fn check_layout(layout: ChunkLayout) -> anyhow::Result<()> {
+    if layout.block_size == 0 || layout.chunk_size < layout.block_size as u64 {
+        anyhow::bail!(
+            "invalid layout: chunk size {} must be at least block size {}",
+            layout.chunk_size,
+            layout.block_size
+        );
+    }
+    Ok(())
+}
+
+fn parse_volume(json: &str) -> anyhow::Result<VolumeFormat> {
+    serde_json::from_str(json).context("invalid volume format")
+}
+
+/// Returns the format of the volume behind `store`, or `None` if it was
+/// never formatted or the backend does not store settings.
+pub async fn load_volume<S>(store: &S) -> anyhow::Result<Option<VolumeFormat>>
+where
+    S: MetaStore + ?Sized,
+{
+    match store.get_setting(VOLUME_SETTING).await {
+        Ok(Some(json)) => parse_volume(&json).map(Some),
+        Ok(None) | Err(MetaError::NotImplemented | MetaError::NotSupported(_)) => Ok(None),
+        Err(err) => Err(err.into()),
+    }
+}
+
+/// Records `format` for the volume behind `store` and returns the format the
+/// volume ends up with. Formatting a volume again with the same name, layout
+/// and storage keeps its UUID; anything else is refused.
+pub async fn format_volume<S>(store: &S, format: &VolumeFormat) -> anyhow::Result<VolumeFormat>
+where
+    S: MetaStore + ?Sized,
+{
+    let json = serde_json::to_string(format)?;
+    if store.set_setting(VOLUME_SETTING, &json, false).await? {
+        return Ok(format.clone());
+    }
+    let stored = load_volume(store)
+        .await?
+        .context("volume format vanished")?;
+    if !stored.same_settings(format) {
+        anyhow::bail!(
+            "volume is already formatted as {} ({}), with chunk size {}, block size {} and storage {}",
+            stored.name,
+            stored.uuid,
+            stored.chunk_size,
+            stored.block_size,
+            stored.storage
+        );
+    }
+    Ok(stored)
+}
+
+/// Returns the layout to open the volume with. A formatted volume uses its
+/// recorded layout and `chunk_size` and `block_size` must match it when
+/// given; other volumes use them or the defaults.
+pub fn resolve_layout(
+    volume: Option<&VolumeFormat>,
+    chunk_size: Option<u64>,
+    block_size: Option<u32>,
+) -> anyhow::Result<ChunkLayout> {
+    let Some(volume) = volume else {
+        let layout = ChunkLayout::default();
+        let layout = ChunkLayout {
+            chunk_size: chunk_size.unwrap_or(layout.chunk_size),
+            block_size: block_size.unwrap_or(layout.block_size),
+        };
+        check_layout(layout)?;
+        return Ok(layout);
+    };
+    if let Some(chunk_size) = chunk_size
+        && chunk_size != volume.chunk_size
+    {
+        anyhow::bail!(
+            "volume {} uses chunk size {}, not {chunk_size}",
+            volume.name,
+            volume.chunk_size
+        );
+    }
+    if let Some(block_size) = block_size
+        && block_size != volume.block_size
+    {
+        anyhow::bail!(
+            "volume {} uses block size {}, not {block_size}",
+            volume.name,
+            volume.block_size
+        );
+    }
+    Ok(volume.layout())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::meta::factory::create_meta_store_from_url;
+
+    fn layout() -> ChunkLayout {
+        ChunkLayout {
+            chunk_size: 16 * 1024,
+            block_size: 4 * 1024,
+        }
+    }
+
+    #[tokio::test]
+    async fn format_is_recorded_once() {
+        let store = create_meta_store_from_url("sqlite::memory:")
+            .await
+            .unwrap()
+            .store();
+        assert_eq!(load_volume(store.as_ref()).await.unwrap(), None);
+
+        let format = VolumeFormat::new("vol", layout(), "s3://bucket").unwrap();
+        assert_eq!(
+            format_volume(store.as_ref(), &format).await.unwrap(),
+            format
+        );
+        assert_eq!(
+            load_volume(store.as_ref()).await.unwrap(),
+            Some(format.clone())
+        );
+
+        // Formatting again keeps the UUID, other settings are refused.
+        let again = VolumeFormat::new("vol", layout(), "s3://bucket").unwrap();
+        assert_eq!(format_volume(store.as_ref(), &again).await.unwrap(), format);
+        let other = VolumeFormat::new("vol", ChunkLayout::default(), "s3://bucket").unwrap();
+        assert!(format_volume(store.as_ref(), &other).await.is_err());
+    }
+
+    #[test]
+    fn layout_must_match_volume() {
+        let format = VolumeFormat::new("vol", layout(), "file:///data").unwrap();
+        assert_eq!(resolve_layout(Some(&format), None, None).unwrap(), layout());
+        assert_eq!(
+            resolve_layout(Some(&format), Some(16 * 1024), Some(4 * 1024)).unwrap(),
+            layout()
+        );
+        assert!(resolve_layout(Some(&format), Some(64 * 1024), None).is_err());
+        assert!(resolve_layout(Some(&format), None, Some(1024)).is_err());
+
+        assert_eq!(
+            resolve_layout(None, None, None).unwrap(),
+            ChunkLayout::default()
+        );
+        assert!(resolve_layout(None, Some(1024), Some(4096)).is_err());
+        assert!(VolumeFormat::new("", layout(), "file:///data").is_err());
+    }
+}
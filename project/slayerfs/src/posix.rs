@@ -4,3 +4,9 @@
 
 /// POSIX NAME_MAX: maximum length for a single filename component (bytes)
 pub const NAME_MAX: usize = 255;
+
+/// Linux XATTR_NAME_MAX: maximum length of an extended attribute name (bytes)
+pub const XATTR_NAME_MAX: usize = 255;
+
+/// Linux XATTR_SIZE_MAX: maximum size of an extended attribute value (bytes)
+pub const XATTR_SIZE_MAX: usize = 65536;
@@ -7,6 +7,8 @@ pub const DEFAULT_MAX_AHEAD: u64 = 32 * 1024 * 1024; // 32MB
 pub const DEFAULT_BUFFER_SIZE: u64 = 1024 * 1024 * 300; // 300MB
 pub const DEFAULT_WRITE_BUFFER_SIZE: u64 = 1024 * 1024 * 300; // 300MB
 pub const DEFAULT_FLUSH_ALL_INTERVAL: Duration = Duration::from_secs(5);
+pub const DEFAULT_COMPACT_THRESHOLD: usize = 100;
+pub const DEFAULT_SLICE_DELETE_DELAY: Duration = Duration::from_secs(3600);
 
 #[derive(Clone)]
 pub struct ReadConfig {
@@ -22,6 +24,11 @@ pub struct ReadConfig {
     /// can waste memory on random access patterns.
     /// Default: 32MB. Adjust based on typical sequential read sizes.
     pub max_ahead: u64,
+
+    /// Whether sequential reads prefetch the blocks following the readahead
+    /// window into the block cache of the block store.
+    /// Default: true.
+    pub prefetch: bool,
 }
 
 impl Default for ReadConfig {
@@ -30,6 +37,7 @@ impl Default for ReadConfig {
             layout: ChunkLayout::default(),
             buffer_size: DEFAULT_BUFFER_SIZE,
             max_ahead: DEFAULT_MAX_AHEAD,
+            prefetch: true,
         }
     }
 }
@@ -53,6 +61,10 @@ impl ReadConfig {
     pub fn max_ahead(self, max_ahead: u64) -> Self {
         Self { max_ahead, ..self }
     }
+
+    pub fn prefetch(self, prefetch: bool) -> Self {
+        Self { prefetch, ..self }
+    }
 }
 
 #[derive(Clone)]
@@ -64,6 +76,13 @@ pub struct WriteConfig {
     /// Default: 300MB. Set to 0 to disable throttling.
     pub buffer_size: u64,
     pub flush_all_interval: Duration,
+    /// Number of slices in a chunk from which a write compacts it into one.
+    /// Default: 100. Set to 0 to disable compaction.
+    pub compact_threshold: usize,
+    /// How long the blocks of slices replaced by compaction are kept, so
+    /// readers that still use the old slices can finish.
+    /// Default: 1 hour.
+    pub slice_delete_delay: Duration,
 }
 
 impl Default for WriteConfig {
@@ -73,6 +92,8 @@ impl Default for WriteConfig {
             page_size: DEFAULT_PAGE_SIZE,
             buffer_size: DEFAULT_WRITE_BUFFER_SIZE,
             flush_all_interval: DEFAULT_FLUSH_ALL_INTERVAL,
+            compact_threshold: DEFAULT_COMPACT_THRESHOLD,
+            slice_delete_delay: DEFAULT_SLICE_DELETE_DELAY,
         }
     }
 }
@@ -103,6 +124,20 @@ impl WriteConfig {
             ..self
         }
     }
+
+    pub fn compact_threshold(self, compact_threshold: usize) -> Self {
+        Self {
+            compact_threshold,
+            ..self
+        }
+    }
+
+    pub fn slice_delete_delay(self, slice_delete_delay: Duration) -> Self {
+        Self {
+            slice_delete_delay,
+            ..self
+        }
+    }
 }
 
 #[derive(Clone, Default)]
@@ -187,7 +187,7 @@ impl VfsError {
             MetaError::NotDirectory(_) => VfsError::NotADirectory { path },
             MetaError::DirectoryNotEmpty(_) => VfsError::DirectoryNotEmpty { path },
             MetaError::InvalidFilename => VfsError::InvalidFilename,
-            MetaError::InvalidPath(_) => VfsError::InvalidInput,
+            MetaError::InvalidPath(_) | MetaError::InvalidAcl(_) => VfsError::InvalidInput,
             MetaError::TooManySymlinks => VfsError::InvalidInput,
             MetaError::NotSupported(_) | MetaError::NotImplemented => VfsError::Unsupported,
             MetaError::Io(err) => VfsError::from(err),
@@ -195,6 +195,9 @@ impl VfsError {
             MetaError::LockNotFound { .. } => VfsError::NotFound { path },
             MetaError::DeadlockDetected { .. } => VfsError::Deadlock,
             MetaError::InvalidHandle(_) => VfsError::StaleNetworkFileHandle,
+            MetaError::QuotaExceeded => VfsError::QuotaExceeded,
+            MetaError::InvalidXattrName(_) => VfsError::InvalidInput,
+            MetaError::XattrTooLarge => VfsError::ArgumentListTooLong,
             MetaError::Anyhow(err) => VfsError::from(err),
             other => VfsError::Meta(other),
         }
@@ -291,7 +294,9 @@ impl From<VfsError> for std::io::Error {
             VfsError::WriteZero => ErrorKind::WriteZero,
             VfsError::StorageFull => ErrorKind::StorageFull,
             VfsError::NotSeekable => ErrorKind::NotSeekable,
-            VfsError::QuotaExceeded => ErrorKind::QuotaExceeded,
+            VfsError::QuotaExceeded | VfsError::Meta(MetaError::QuotaExceeded) => {
+                ErrorKind::QuotaExceeded
+            }
             VfsError::FileTooLarge => ErrorKind::FileTooLarge,
             VfsError::ResourceBusy => ErrorKind::ResourceBusy,
             VfsError::ExecutableFileBusy => ErrorKind::ExecutableFileBusy,
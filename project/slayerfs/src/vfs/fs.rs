@@ -3,11 +3,12 @@
 use crate::chuck::chunk::ChunkLayout;
 use crate::chuck::store::BlockStore;
 use crate::meta::MetaLayer;
+use crate::meta::acl::{AclRule, AclType};
 use crate::meta::client::MetaClient;
 use crate::meta::config::MetaClientConfig;
 use crate::meta::file_lock::{FileLockInfo, FileLockQuery, FileLockRange, FileLockType};
 use crate::meta::store::{
-    AclRule, MetaError, MetaStore, SetAttrFlags, SetAttrRequest, StatFsSnapshot,
+    CloneOption, MetaError, MetaStore, SetAttrFlags, SetAttrRequest, StatFsSnapshot,
 };
 use dashmap::{DashMap, Entry};
 use std::collections::HashMap;
@@ -35,7 +36,8 @@ use crate::vfs::backend::Backend;
 use crate::vfs::config::VFSConfig;
 use crate::vfs::error::{PathHint, VfsError};
 use crate::vfs::handles::{DirHandle, FileHandle, HandleFlags};
-use crate::vfs::io::{DataReader, DataWriter};
+use crate::vfs::io::warmup::{WarmupReport, warmup};
+use crate::vfs::io::{Compactor, DataReader, DataWriter};
 
 struct HandleRegistry<B, M>
 where
@@ -198,10 +200,13 @@ where
 {
     fn new(config: Arc<VFSConfig>, backend: Arc<Backend<S, M>>) -> Self {
         let reader = Arc::new(DataReader::new(config.read.clone(), backend.clone()));
+        let compactor = Arc::new(Compactor::new(config.write.clone(), backend.clone()));
+        compactor.start_background();
         let writer = Arc::new(DataWriter::new(
             config.write.clone(),
             backend,
             reader.clone(),
+            compactor,
         ));
         writer.start_flush_background();
         Self {
@@ -488,7 +493,7 @@ where
 
     /// Normalize a path by stripping redundant separators and ensuring it starts with `/`.
     /// Does not resolve `.` or `..`.
-    fn norm_path(p: &str) -> String {
+    pub(crate) fn norm_path(p: &str) -> String {
         if p.is_empty() {
             return "/".into();
         }
@@ -499,7 +504,7 @@ where
     }
 
     /// Split a normalized path into parent directory and basename.
-    fn split_dir_file(path: &str) -> (String, String) {
+    pub(crate) fn split_dir_file(path: &str) -> (String, String) {
         let n = path.rfind('/').unwrap_or(0);
         if n == 0 {
             ("/".into(), path[1..].into())
@@ -855,6 +860,92 @@ where
         Ok(attr)
     }
 
+    /// Clone the file, symlink or directory tree at `src_path` to `dst_path`
+    /// without copying data.
+    #[tracing::instrument(level = "trace", skip(self, opt), fields(src_path, dst_path))]
+    pub async fn clone_path(
+        &self,
+        src_path: &str,
+        dst_path: &str,
+        opt: CloneOption,
+    ) -> Result<FileAttr, VfsError> {
+        let src_path = Self::norm_path(src_path);
+        let (src_ino, _) = self
+            .core
+            .meta_layer
+            .lookup_path(&src_path)
+            .await
+            .map_err(VfsError::from)?
+            .ok_or_else(|| VfsError::NotFound {
+                path: PathHint::some(src_path.clone()),
+            })?;
+        self.clone_ino(src_ino, dst_path, opt).await
+    }
+
+    /// Clone inode `src` to `dst_path`. The clone shares the slices of the
+    /// source; pending writes are flushed first so it sees all data written
+    /// so far.
+    #[tracing::instrument(level = "trace", skip(self, opt), fields(src, dst_path))]
+    pub async fn clone_ino(
+        &self,
+        src: i64,
+        dst_path: &str,
+        mut opt: CloneOption,
+    ) -> Result<FileAttr, VfsError> {
+        let dst_path = Self::norm_path(dst_path);
+        if dst_path == "/" {
+            return Err(VfsError::InvalidFilename);
+        }
+        let (dir, name) = Self::split_dir_file(&dst_path);
+        if name.is_empty() {
+            return Err(VfsError::InvalidFilename);
+        }
+        let parent_ino = self
+            .resolve_parent_inode(&dir, "Destination directory")
+            .await?;
+
+        self.state.writer.flush_all().await;
+        opt.chunk_size = self.core.layout.chunk_size;
+        let attr = self
+            .core
+            .meta_layer
+            .clone_entry(src, parent_ino, &name, &opt)
+            .await
+            .map_err(VfsError::from)?;
+
+        self.state.modified.touch(parent_ino).await;
+        self.state.modified.touch(attr.ino).await;
+
+        Ok(attr)
+    }
+
+    /// Load the data of the file or directory tree at `path` into the block
+    /// cache, so a job reading it later does not wait for the object store.
+    #[tracing::instrument(level = "trace", skip(self), fields(path))]
+    pub async fn warmup(&self, path: &str) -> Result<WarmupReport, VfsError> {
+        let path = Self::norm_path(path);
+        let (ino, _) = self
+            .core
+            .meta_layer
+            .lookup_path(&path)
+            .await
+            .map_err(VfsError::from)?
+            .ok_or_else(|| VfsError::NotFound {
+                path: PathHint::some(path.clone()),
+            })?;
+
+        // Pending writes are not in the object store yet.
+        self.state.writer.flush_all().await;
+        let report = warmup(
+            self.core.backend.store(),
+            self.core.meta_layer.as_ref(),
+            self.core.layout,
+            ino,
+        )
+        .await?;
+        Ok(report)
+    }
+
     /// Create a symbolic link at `link_path` pointing to `target`.
     #[tracing::instrument(level = "trace", skip(self), fields(link_path, target))]
     pub async fn create_symlink(
@@ -1978,6 +2069,12 @@ where
             });
         }
 
+        self.core
+            .meta_layer
+            .check_write_quota(handle.ino, offset + data.len() as u64)
+            .await
+            .map_err(VfsError::from)?;
+
         tracing::trace!(fh, ino = handle.ino, offset, len = data.len(), "vfs.write");
         let written = handle.write(offset, data).await?;
         self.state.modified.touch(handle.ino).await;
@@ -2008,6 +2105,11 @@ where
         if attr.kind != FileType::File {
             return Err(VfsError::InvalidInput);
         }
+        self.core
+            .meta_layer
+            .check_write_quota(ino, offset + data.len() as u64)
+            .await
+            .map_err(VfsError::from)?;
 
         let inode = self.ensure_inode_registered(ino).await?;
         let writer = self.state.writer.ensure_file(inode);
@@ -2324,19 +2426,23 @@ where
         self.core.meta_layer.remove_xattr(inode, name).await
     }
 
-    /// Set ACL rule for a given inode.
-    pub async fn set_acl_ino(&self, inode: i64, rule: AclRule) -> Result<(), MetaError> {
-        self.core.meta_layer.set_acl(inode, rule).await
+    /// Set or, with `None`, remove an ACL of a given inode.
+    pub async fn set_acl_ino(
+        &self,
+        inode: i64,
+        acl_type: AclType,
+        rule: Option<&AclRule>,
+    ) -> Result<(), MetaError> {
+        self.core.meta_layer.set_acl(inode, acl_type, rule).await
     }
 
-    /// Get ACL rule for a given inode.
+    /// Get an ACL of a given inode.
     pub async fn get_acl_ino(
         &self,
         inode: i64,
-        acl_type: u8,
-        acl_id: u32,
+        acl_type: AclType,
     ) -> Result<Option<AclRule>, MetaError> {
-        self.core.meta_layer.get_acl(inode, acl_type, acl_id).await
+        self.core.meta_layer.get_acl(inode, acl_type).await
     }
 
     /// Get file lock information by path.
@@ -0,0 +1,382 @@
+// Compaction pipeline (high-level):
+// - Every write commits a new slice to its chunk, so random and append writes leave chunks with
+//   many overlapping slices that each read has to merge. After a commit, FileWriter calls
+//   Compactor::check; once the chunk holds compact_threshold slices, a background task compacts it.
+// - compact reads the visible data of the chunk through a snapshot of its slices, writes it as
+//   one new slice and swaps it in with MetaLayer::compact_chunk. Slices committed meanwhile stay
+//   on top of the new one; if the snapshot is stale (e.g. after a truncate) the new slice is
+//   dropped and the chunk is left alone.
+// - Slices no chunk references any more become delayed slices. sweep_delayed deletes their
+//   blocks once slice_delete_delay has passed, so readers holding the old slice list can finish.
+
+use crate::chuck::gc::block_count;
+use crate::chuck::reader::DataFetcher;
+use crate::chuck::writer::DataUploader;
+use crate::chuck::{BlockStore, SliceDesc};
+use crate::meta::store::MetaError;
+use crate::meta::{MetaLayer, SLICE_ID_KEY};
+use crate::utils::NumCastExt;
+use crate::vfs::backend::Backend;
+use crate::vfs::config::WriteConfig;
+use bytes::Bytes;
+use dashmap::DashSet;
+use std::sync::Arc;
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+use tokio::sync::Semaphore;
+use tokio::time::interval;
+use tracing::{Instrument, warn};
+
+const SWEEP_INTERVAL: Duration = Duration::from_secs(60);
+const MAX_COMPACTIONS: usize = 4;
+
+pub(crate) struct Compactor<B, M> {
+    config: Arc<WriteConfig>,
+    backend: Arc<Backend<B, M>>,
+    /// Chunks being compacted.
+    running: DashSet<u64>,
+    permits: Semaphore,
+}
+
+impl<B, M> Compactor<B, M>
+where
+    B: BlockStore + Send + Sync + 'static,
+    M: MetaLayer + Send + Sync + 'static,
+{
+    pub(crate) fn new(config: Arc<WriteConfig>, backend: Arc<Backend<B, M>>) -> Self {
+        Self {
+            config,
+            backend,
+            running: DashSet::new(),
+            permits: Semaphore::new(MAX_COMPACTIONS),
+        }
+    }
+
+    /// Starts the loop deleting the blocks of delayed slices.
+    /// Use `Weak` to stop it when the `Compactor` was dropped.
+    pub(crate) fn start_background(self: &Arc<Self>) {
+        let weak = Arc::downgrade(self);
+
+        tokio::spawn(async move {
+            let mut ticker = interval(SWEEP_INTERVAL);
+            loop {
+                ticker.tick().await;
+                let Some(compactor) = weak.upgrade() else {
+                    return;
+                };
+                if let Err(err) = compactor.sweep_delayed().await {
+                    warn!(error = ?err, "failed to delete delayed slices");
+                }
+            }
+        });
+    }
+
+    /// Compacts `chunk_id` in the background once it holds too many slices.
+    pub(crate) fn check(self: &Arc<Self>, chunk_id: u64) {
+        let threshold = self.config.compact_threshold;
+        if threshold == 0 || self.running.contains(&chunk_id) {
+            return;
+        }
+
+        let compactor = self.clone();
+        tokio::spawn(
+            async move {
+                match compactor.backend.meta().get_slices(chunk_id).await {
+                    Ok(slices) if slices.len() >= threshold => {}
+                    _ => return,
+                }
+                if !compactor.running.insert(chunk_id) {
+                    return;
+                }
+                if let Ok(_permit) = compactor.permits.acquire().await
+                    && let Err(err) = compactor.compact(chunk_id).await
+                {
+                    warn!(chunk_id, error = ?err, "chunk compaction failed");
+                }
+                compactor.running.remove(&chunk_id);
+            }
+            .instrument(tracing::trace_span!("Compactor.check", chunk_id)),
+        );
+    }
+
+    /// Rewrites the slices of `chunk_id` as one slice; returns false if the
+    /// chunk changed meanwhile and was left as it is.
+    #[tracing::instrument(name = "Compactor.compact", level = "trace", skip(self))]
+    pub(crate) async fn compact(&self, chunk_id: u64) -> anyhow::Result<bool> {
+        let layout = self.config.layout;
+        let backend = self.backend.as_ref();
+        let slices = backend.meta().get_slices(chunk_id).await?;
+        if slices.len() < 2 {
+            return Ok(false);
+        }
+        let start = slices.iter().map(|s| s.offset).min().unwrap_or(0);
+        let end = slices
+            .iter()
+            .map(|s| s.offset + s.length)
+            .max()
+            .unwrap_or(0);
+        if end <= start {
+            return Ok(false);
+        }
+
+        // Holes between the slices are written as zeros, which is what reads
+        // return for them anyway.
+        let mut fetcher = DataFetcher::new(layout, chunk_id, backend);
+        fetcher.use_slices(slices.clone());
+        let data = fetcher
+            .read_at(start.into(), (end - start).as_usize())
+            .await?;
+
+        let slice_id = backend.meta().next_id(SLICE_ID_KEY).await? as u64;
+        DataUploader::new(layout, backend)
+            .write_at_vectored(slice_id, 0u64.into(), &[Bytes::from(data)])
+            .await?;
+        let slice = SliceDesc {
+            slice_id,
+            chunk_id,
+            offset: start,
+            length: end - start,
+        };
+        if backend
+            .meta()
+            .compact_chunk(chunk_id, &slices, slice)
+            .await?
+        {
+            return Ok(true);
+        }
+
+        // Nothing references the new slice.
+        let blocks = block_count(slice.length, layout.block_size);
+        backend
+            .store()
+            .delete_range((slice_id, 0), blocks as u64)
+            .await?;
+        Ok(false)
+    }
+
+    /// Deletes the blocks of the slices replaced more than
+    /// `slice_delete_delay` ago; returns the number of slices deleted.
+    #[tracing::instrument(name = "Compactor.sweep_delayed", level = "trace", skip(self))]
+    pub(crate) async fn sweep_delayed(&self) -> anyhow::Result<usize> {
+        let now = SystemTime::now().duration_since(UNIX_EPOCH)?;
+        let edge = now
+            .saturating_sub(self.config.slice_delete_delay)
+            .as_nanos() as i64;
+        let meta = self.backend.meta();
+        let slices = match meta.list_delayed_slices(edge).await {
+            Ok(slices) => slices,
+            Err(MetaError::NotImplemented) => return Ok(0),
+            Err(err) => return Err(err.into()),
+        };
+
+        for slice in &slices {
+            let blocks = block_count(slice.length, self.config.layout.block_size);
+            self.backend
+                .store()
+                .delete_range((slice.slice_id, 0), blocks as u64)
+                .await?;
+            meta.delete_slice(slice.slice_id).await?;
+        }
+        Ok(slices.len())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::chuck::ChunkLayout;
+    use crate::chuck::store::InMemoryBlockStore;
+    use crate::meta::factory::create_meta_store_from_url;
+    use crate::meta::store::CloneOption;
+    use crate::vfs::chunk_id_for;
+
+    fn small_layout() -> ChunkLayout {
+        ChunkLayout {
+            chunk_size: 16 * 1024,
+            block_size: 4 * 1024,
+        }
+    }
+
+    async fn write_slice<B, M>(backend: &Backend<B, M>, chunk_id: u64, offset: u64, data: &[u8])
+    where
+        B: BlockStore + Send + Sync + 'static,
+        M: MetaLayer + Send + Sync + 'static,
+    {
+        let slice_id = backend.meta().next_id(SLICE_ID_KEY).await.unwrap() as u64;
+        DataUploader::new(small_layout(), backend)
+            .write_at_vectored(slice_id, 0u64.into(), &[Bytes::copy_from_slice(data)])
+            .await
+            .unwrap();
+        let slice = SliceDesc {
+            slice_id,
+            chunk_id,
+            offset,
+            length: data.len() as u64,
+        };
+        let (ino, _) = crate::vfs::extract_ino_and_chunk_index(chunk_id);
+        backend
+            .meta()
+            .write(ino, chunk_id, slice, offset + data.len() as u64)
+            .await
+            .unwrap();
+    }
+
+    async fn read_chunk<B, M>(backend: &Backend<B, M>, chunk_id: u64, len: usize) -> Vec<u8>
+    where
+        B: BlockStore + Send + Sync + 'static,
+        M: MetaLayer + Send + Sync + 'static,
+    {
+        let mut fetcher = DataFetcher::new(small_layout(), chunk_id, backend);
+        fetcher.prepare_slices().await.unwrap();
+        fetcher.read_at(0u64.into(), len).await.unwrap()
+    }
+
+    #[tokio::test]
+    async fn test_compact_merges_slices_and_delays_deletion() {
+        let store = Arc::new(InMemoryBlockStore::new());
+        let meta = create_meta_store_from_url("sqlite::memory:")
+            .await
+            .unwrap()
+            .layer();
+        let backend = Arc::new(Backend::new(store.clone(), meta.clone()));
+        let ino = meta.create_file(1, "log".to_string()).await.unwrap();
+        let cid = chunk_id_for(ino, 0).unwrap();
+
+        // Appends with an overwrite in the middle and a hole at the end.
+        write_slice(&backend, cid, 0, &[1u8; 5000]).await;
+        write_slice(&backend, cid, 5000, &[2u8; 3000]).await;
+        write_slice(&backend, cid, 2000, &[3u8; 4000]).await;
+        write_slice(&backend, cid, 9000, &[4u8; 1000]).await;
+        let old = meta.get_slices(cid).await.unwrap();
+        let before = read_chunk(&backend, cid, 10_000).await;
+
+        let config = Arc::new(WriteConfig::new(small_layout()).slice_delete_delay(Duration::ZERO));
+        let compactor = Compactor::new(config, backend.clone());
+        assert!(compactor.compact(cid).await.unwrap());
+
+        let slices = meta.get_slices(cid).await.unwrap();
+        assert_eq!(slices.len(), 1);
+        assert_eq!((slices[0].offset, slices[0].length), (0, 10_000));
+        assert_eq!(read_chunk(&backend, cid, 10_000).await, before);
+
+        // The old slices stay readable until the delay has passed.
+        let mut stale = DataFetcher::new(small_layout(), cid, backend.as_ref());
+        stale.use_slices(old.clone());
+        assert_eq!(stale.read_at(0u64.into(), 10_000).await.unwrap(), before);
+
+        assert_eq!(compactor.sweep_delayed().await.unwrap(), old.len());
+        let mut buf = [0u8; 1];
+        store
+            .read_range((old[0].slice_id, 0), 0, &mut buf)
+            .await
+            .unwrap();
+        assert_eq!(buf, [0]);
+        assert_eq!(compactor.sweep_delayed().await.unwrap(), 0);
+        assert_eq!(read_chunk(&backend, cid, 10_000).await, before);
+    }
+
+    #[tokio::test]
+    async fn test_compact_keeps_slices_written_meanwhile() {
+        let store = Arc::new(InMemoryBlockStore::new());
+        let meta = create_meta_store_from_url("sqlite::memory:")
+            .await
+            .unwrap()
+            .layer();
+        let backend = Arc::new(Backend::new(store, meta.clone()));
+        let ino = meta.create_file(1, "log".to_string()).await.unwrap();
+        let cid = chunk_id_for(ino, 0).unwrap();
+
+        write_slice(&backend, cid, 0, &[1u8; 100]).await;
+        write_slice(&backend, cid, 100, &[2u8; 100]).await;
+        let old = meta.get_slices(cid).await.unwrap();
+        write_slice(&backend, cid, 50, &[3u8; 100]).await;
+        let newest = meta.get_slices(cid).await.unwrap()[2];
+
+        let merged = SliceDesc {
+            slice_id: meta.next_id(SLICE_ID_KEY).await.unwrap() as u64,
+            chunk_id: cid,
+            offset: 0,
+            length: 200,
+        };
+        assert!(meta.compact_chunk(cid, &old, merged).await.unwrap());
+        assert_eq!(meta.get_slices(cid).await.unwrap(), vec![merged, newest]);
+
+        // The snapshot is stale now.
+        assert!(!meta.compact_chunk(cid, &old, merged).await.unwrap());
+    }
+
+    /// Reads the first byte of the first block of `slice_id`.
+    async fn first_byte(store: &InMemoryBlockStore, slice_id: u64) -> u8 {
+        let mut buf = [0u8; 1];
+        store.read_range((slice_id, 0), 0, &mut buf).await.unwrap();
+        buf[0]
+    }
+
+    #[tokio::test]
+    async fn test_cloned_slices_are_deleted_with_the_last_copy() {
+        let store = Arc::new(InMemoryBlockStore::new());
+        let meta = create_meta_store_from_url("sqlite::memory:")
+            .await
+            .unwrap()
+            .layer();
+        let backend = Arc::new(Backend::new(store.clone(), meta.clone()));
+        let ino = meta.create_file(1, "file".to_string()).await.unwrap();
+        let cid = chunk_id_for(ino, 0).unwrap();
+        write_slice(&backend, cid, 0, &[1u8; 5000]).await;
+        let slice_id = meta.get_slices(cid).await.unwrap()[0].slice_id;
+        let opt = CloneOption {
+            preserve_attr: true,
+            chunk_size: small_layout().chunk_size,
+            ..Default::default()
+        };
+        meta.clone_entry(ino, 1, "copy", &opt).await.unwrap();
+
+        let config = Arc::new(WriteConfig::new(small_layout()).slice_delete_delay(Duration::ZERO));
+        let compactor = Compactor::new(config, backend.clone());
+        for name in ["file", "copy"] {
+            meta.unlink(1, name).await.unwrap();
+            for deleted in meta.get_deleted_files().await.unwrap() {
+                meta.remove_file_metadata(deleted).await.unwrap();
+            }
+            if name == "file" {
+                // The copy still reads the blocks.
+                assert_eq!(compactor.sweep_delayed().await.unwrap(), 0);
+                assert_eq!(first_byte(&store, slice_id).await, 1);
+            }
+        }
+        assert_eq!(compactor.sweep_delayed().await.unwrap(), 1);
+        assert_eq!(first_byte(&store, slice_id).await, 0);
+    }
+
+    #[tokio::test]
+    async fn test_truncate_releases_slices() {
+        let store = Arc::new(InMemoryBlockStore::new());
+        let meta = create_meta_store_from_url("sqlite::memory:")
+            .await
+            .unwrap()
+            .layer();
+        let backend = Arc::new(Backend::new(store.clone(), meta.clone()));
+        let chunk_size = small_layout().chunk_size;
+        let ino = meta.create_file(1, "file".to_string()).await.unwrap();
+        let cid = chunk_id_for(ino, 0).unwrap();
+        write_slice(&backend, cid, 0, &[1u8; 100]).await;
+        write_slice(&backend, cid, 100, &[2u8; 100]).await;
+        let slices = meta.get_slices(cid).await.unwrap();
+        let opt = CloneOption {
+            preserve_attr: true,
+            chunk_size,
+            ..Default::default()
+        };
+        let copy = meta.clone_entry(ino, 1, "copy", &opt).await.unwrap().ino;
+
+        let config = Arc::new(WriteConfig::new(small_layout()).slice_delete_delay(Duration::ZERO));
+        let compactor = Compactor::new(config, backend.clone());
+        meta.truncate(copy, 0, chunk_size).await.unwrap();
+        assert_eq!(compactor.sweep_delayed().await.unwrap(), 0);
+
+        // Cutting into the first slice keeps it; the second one goes.
+        meta.truncate(ino, 50, chunk_size).await.unwrap();
+        assert_eq!(compactor.sweep_delayed().await.unwrap(), 1);
+        assert_eq!(first_byte(&store, slices[0].slice_id).await, 1);
+        assert_eq!(first_byte(&store, slices[1].slice_id).await, 0);
+    }
+}
@@ -1,8 +1,11 @@
 use crate::chuck::{ChunkLayout, ChunkSpan, ChunkTag};
 
+pub(crate) mod compact;
 pub(crate) mod reader;
+pub(crate) mod warmup;
 pub(crate) mod writer;
 
+pub(crate) use compact::Compactor;
 pub(crate) use reader::DataReader;
 pub(crate) use reader::FileReader;
 pub(crate) use writer::DataWriter;
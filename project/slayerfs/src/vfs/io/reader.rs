@@ -127,11 +127,12 @@ where
 
 /// A Session tracks the read pattern of a specific handle to guide slice eviction.
 ///
-/// There are 4 fields:
+/// There are 5 fields:
 /// 1. `ahead`: possible readahead length.
 /// 2. `last_off`: the offset of the last read operation.
 /// 3. `total`: total read length of the session.
 /// 4. `atime`: the last time.
+/// 5. `prefetched`: the end of the range already prefetched into the block cache.
 ///
 /// According to the Principle of Locality, when a range is read, its adjacent ranges are
 /// likely to be read soon. The session records the last read offset and predicts a readahead range.
@@ -154,12 +155,18 @@ where
 /// two separate read streams simultaneously without their predictive windows interfering with each other.
 ///
 /// If these two sessions are both available, it selects the oldest (atime).
+///
+/// Beyond the readahead kept in memory, a sequential session asks the block store to prefetch
+/// the blocks of `[last_off + ahead, last_off + 2 * ahead)` into the block cache, so the next
+/// windows are fetched from the object store in parallel. `prefetched` keeps each block from
+/// being requested twice.
 #[derive(Clone, Copy)]
 struct Session {
     ahead: u64,
     last_off: u64,
     total: u64,
     atime: Instant,
+    prefetched: u64,
 }
 
 impl Default for Session {
@@ -169,6 +176,7 @@ impl Default for Session {
             last_off: 0,
             total: 0,
             atime: Instant::now(),
+            prefetched: 0,
         }
     }
 }
@@ -179,6 +187,7 @@ impl Session {
         self.total = 0;
         self.ahead = 0;
         self.atime = Instant::now();
+        self.prefetched = 0;
     }
 
     fn update(&mut self, off: u64, len: u64) {
@@ -227,6 +236,24 @@ impl Session {
 
         self.ahead = ahead;
     }
+
+    /// Returns the range to prefetch after this read, if any, and marks it as prefetched.
+    fn next_prefetch(&mut self) -> Option<(u64, u64)> {
+        if self.ahead == 0 {
+            return None;
+        }
+
+        let start = self
+            .last_off
+            .saturating_add(self.ahead)
+            .max(self.prefetched);
+        let end = self.last_off.saturating_add(self.ahead.saturating_mul(2));
+        if start >= end {
+            return None;
+        }
+        self.prefetched = end;
+        Some((start, end))
+    }
 }
 
 #[derive(Copy, Clone)]
@@ -516,7 +543,8 @@ where
         oldest_atime
     }
 
-    fn check_session(&self, offset: u64, len: usize) -> u64 {
+    /// Returns the readahead length and the range to prefetch into the block cache.
+    fn check_session(&self, offset: u64, len: usize) -> (u64, Option<(u64, u64)>) {
         let mut session = self.sessions.lock();
 
         let selected = self
@@ -533,7 +561,12 @@ where
             offset,
             len as u64,
         );
-        session[selected].ahead
+        let prefetch = if self.config.prefetch {
+            session[selected].next_prefetch()
+        } else {
+            None
+        };
+        (session[selected].ahead, prefetch)
     }
 
     fn buffer_usage(&self) -> u64 {
@@ -664,6 +697,41 @@ where
         }
     }
 
+    /// Asks the block store to prefetch the blocks of `[start, end)` in the background.
+    fn prefetch_blocks(&self, start: u64, end: u64) {
+        let end = end.min(self.inode.file_size());
+        if start >= end {
+            return;
+        }
+
+        let layout = self.config.layout;
+        let ino = self.inode.ino();
+        let backend = self.backend.clone();
+        tokio::spawn(
+            async move {
+                let result = async {
+                    for span in split_chunk_spans(layout, start, (end - start).as_usize()) {
+                        let chunk_id = chunk_id_for(ino, span.index)?;
+                        let mut fetcher = DataFetcher::new(layout, chunk_id, &backend);
+                        fetcher.prepare_slices().await?;
+                        fetcher.prefetch(span.offset.into(), span.len).await?;
+                    }
+                    Ok::<_, anyhow::Error>(())
+                }
+                .await;
+                // Prefetching is best effort; the read itself reports errors.
+                if let Err(err) = result {
+                    tracing::debug!(error = ?err, "block prefetch failed");
+                }
+            }
+            .instrument(tracing::trace_span!(
+                "FileReader.prefetch_blocks",
+                start,
+                end
+            )),
+        );
+    }
+
     #[tracing::instrument(name = "FileReader.read_at", level = "trace", skip(self, buf), fields(offset, len = buf.len()))]
     pub(crate) async fn read_at(&self, offset: u64, buf: &mut [u8]) -> anyhow::Result<usize> {
         if buf.is_empty() {
@@ -709,12 +777,16 @@ where
             );
         }
 
-        let ahead = tracing::trace_span!("read_at.check_session", offset, len = actual_len)
-            .in_scope(|| self.check_session(offset, actual_len));
+        let (ahead, prefetch) =
+            tracing::trace_span!("read_at.check_session", offset, len = actual_len)
+                .in_scope(|| self.check_session(offset, actual_len));
 
         tracing::trace_span!("FileReader.read_at.prepare_ahead_slices", offset, ahead)
             .in_scope(|| self.prepare_ahead_slices(offset, ahead, &mut pin_guard))
             .await;
+        if let Some((start, end)) = prefetch {
+            self.prefetch_blocks(start, end);
+        }
 
         let mut tail = buf;
         let result = async {
@@ -1006,6 +1078,7 @@ mod tests {
     use crate::meta::store::MetaStore;
     use crate::vfs::Inode;
     use crate::vfs::config::{ReadConfig, WriteConfig};
+    use crate::vfs::io::compact::Compactor;
     use crate::vfs::io::writer::FileWriter;
     use bytes::Bytes;
     use std::sync::Arc;
@@ -1189,6 +1262,10 @@ mod tests {
             Arc::new(WriteConfig::new(layout).page_size(4 * 1024)),
             backend.clone(),
             reader,
+            Arc::new(Compactor::new(
+                Arc::new(WriteConfig::new(layout)),
+                backend.clone(),
+            )),
             Arc::new(AtomicU64::new(0)),
         ));
 
@@ -1215,4 +1292,22 @@ mod tests {
 
         write_task.await.unwrap();
     }
+
+    #[test]
+    fn test_session_prefetch_window_advances() {
+        let mut session = Session::default();
+        assert_eq!(session.next_prefetch(), None);
+
+        session.ahead = 4096;
+        session.last_off = 8192;
+        assert_eq!(session.next_prefetch(), Some((12288, 16384)));
+
+        // Only the part not prefetched yet is returned.
+        session.update(8192, 2048);
+        assert_eq!(session.next_prefetch(), Some((16384, 18432)));
+        assert_eq!(session.next_prefetch(), None);
+
+        session.reset(0, 0);
+        assert_eq!(session.prefetched, 0);
+    }
 }
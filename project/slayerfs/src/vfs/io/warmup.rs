@@ -0,0 +1,181 @@
+//! Warmup: loads the data of files into the block cache before a job reads them.
+
+use crate::chuck::reader::visible_blocks;
+use crate::chuck::{BlockStore, ChunkLayout};
+use crate::meta::MetaLayer;
+use crate::meta::store::FileType;
+use crate::meta::trash::TRASH_DIR;
+use crate::vfs::chunk_id_for;
+use futures_util::StreamExt;
+use futures_util::stream::FuturesUnordered;
+use std::collections::HashSet;
+
+/// Result of [`warmup`].
+#[derive(Debug, Default, Clone, PartialEq, Eq)]
+pub struct WarmupReport {
+    /// Regular files whose data was loaded.
+    pub files: usize,
+    /// Bytes of file data covered.
+    pub bytes: u64,
+    /// Blocks handed to the block store.
+    pub blocks: usize,
+}
+
+/// Loads the data of `ino`, or of every file below it if it is a directory,
+/// into the block cache of `store`. Files with several links are loaded
+/// once, symlinks are not followed and the trash is skipped.
+pub async fn warmup<B, M>(
+    store: &B,
+    meta: &M,
+    layout: ChunkLayout,
+    ino: i64,
+) -> anyhow::Result<WarmupReport>
+where
+    B: BlockStore + Sync,
+    M: MetaLayer + ?Sized,
+{
+    let mut report = WarmupReport::default();
+    let mut visited = HashSet::new();
+    let mut pending = vec![ino];
+
+    while let Some(ino) = pending.pop() {
+        if !visited.insert(ino) {
+            continue;
+        }
+        // Removed meanwhile.
+        let Some(attr) = meta.stat(ino).await? else {
+            continue;
+        };
+        match attr.kind {
+            FileType::Dir => {
+                let at_root = ino == meta.root_ino();
+                let entries = meta.readdir(ino).await?;
+                pending.extend(
+                    entries
+                        .into_iter()
+                        .filter(|entry| !(at_root && entry.name == TRASH_DIR))
+                        .map(|entry| entry.ino),
+                );
+            }
+            FileType::File => {
+                report.files += 1;
+                report.bytes += attr.size;
+                report.blocks += warmup_file(store, meta, layout, ino, attr.size).await?;
+            }
+            FileType::Symlink => {}
+        }
+    }
+    Ok(report)
+}
+
+/// Prefetches the blocks of the first `size` bytes of `ino`, one chunk at a
+/// time; returns the number of blocks.
+async fn warmup_file<B, M>(
+    store: &B,
+    meta: &M,
+    layout: ChunkLayout,
+    ino: i64,
+    size: u64,
+) -> anyhow::Result<usize>
+where
+    B: BlockStore + Sync,
+    M: MetaLayer + ?Sized,
+{
+    let mut blocks = 0;
+    for index in 0..size.div_ceil(layout.chunk_size) {
+        let chunk_id = chunk_id_for(ino, index)?;
+        let slices = meta.get_slices(chunk_id).await?;
+        let len = (size - index * layout.chunk_size).min(layout.chunk_size);
+
+        let mut futures: FuturesUnordered<_> = visible_blocks(&slices, layout, 0, len)
+            .into_iter()
+            .map(|key| store.prefetch(key))
+            .collect();
+        blocks += futures.len();
+        while let Some(res) = futures.next().await {
+            res?;
+        }
+    }
+    Ok(blocks)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::cadapter::client::ObjectClient;
+    use crate::cadapter::localfs::LocalFsBackend;
+    use crate::chuck::cache::ChunksCacheConfig;
+    use crate::chuck::reader::DataFetcher;
+    use crate::chuck::store::{BlockStoreConfig, ObjectBlockStore};
+    use crate::meta::factory::create_meta_store_from_url;
+    use crate::vfs::backend::Backend;
+    use crate::vfs::fs::VFS;
+    use std::sync::Arc;
+
+    #[tokio::test]
+    async fn test_warmup_serves_blocks_from_cache() {
+        let layout = ChunkLayout {
+            chunk_size: 16 * 1024,
+            block_size: 4 * 1024,
+        };
+        let data_dir = tempfile::tempdir().unwrap();
+        let cache_dir = tempfile::tempdir().unwrap();
+        let open_store = || {
+            let cache_config = ChunksCacheConfig {
+                disk_storage_dir: Some(cache_dir.path().to_path_buf()),
+                persistent: true,
+                ..Default::default()
+            };
+            let store_config = BlockStoreConfig {
+                block_size: layout.block_size as usize,
+                ..Default::default()
+            };
+            ObjectBlockStore::new_with_configs(
+                ObjectClient::new(LocalFsBackend::new(data_dir.path())),
+                cache_config,
+                store_config,
+            )
+            .unwrap()
+        };
+
+        let meta = create_meta_store_from_url("sqlite::memory:")
+            .await
+            .unwrap()
+            .layer();
+        let fs = VFS::with_meta_layer(layout, Arc::new(open_store()), meta.clone()).unwrap();
+        fs.mkdir_p("/job/in").await.unwrap();
+        let data: Vec<u8> = (0..40 * 1024).map(|i| (i % 251) as u8).collect();
+        for path in ["/job/in/a", "/job/b"] {
+            fs.create_file(path).await.unwrap();
+            let attr = fs.stat(path).await.unwrap();
+            let fh = fs.open(attr.ino, attr, false, true).await.unwrap();
+            fs.write(fh, 0, &data).await.unwrap();
+            fs.close(fh).await.unwrap();
+        }
+        fs.link("/job/b", "/job/in/c").await.unwrap();
+        fs.create_file("/other").await.unwrap();
+
+        // Another process warms the shared cache directory.
+        let (job, _) = meta.lookup_path("/job").await.unwrap().unwrap();
+        let report = warmup(&open_store(), meta.as_ref(), layout, job)
+            .await
+            .unwrap();
+        assert_eq!(report.files, 2);
+        assert_eq!(report.bytes, 2 * data.len() as u64);
+        // 10 blocks per file at least, more if a write was split into several slices.
+        assert!(report.blocks >= 2 * 10);
+
+        // With the objects gone, reads are served from the cache only.
+        std::fs::remove_dir_all(data_dir.path().join("chunks")).unwrap();
+        let backend = Backend::new(Arc::new(open_store()), meta.clone());
+        let (b, _) = meta.lookup_path("/job/b").await.unwrap().unwrap();
+        for index in 0..3u64 {
+            let mut fetcher = DataFetcher::new(layout, chunk_id_for(b, index).unwrap(), &backend);
+            fetcher.prepare_slices().await.unwrap();
+            let start = (index * layout.chunk_size) as usize;
+            let end = (start + layout.chunk_size as usize).min(data.len());
+            let out = fetcher.read_at(0u64.into(), end - start).await.unwrap();
+            assert_eq!(out, data[start..end]);
+        }
+    }
+}
@@ -8,7 +8,10 @@
 //   to the metadata layer and marks them Committed. Only Committed slices are visible to readers.
 // - FileWriter::flush() freezes all slices and waits until commit threads drain the chunks.
 //   While flushing, new writes are blocked via flush_waiting/write_waiting gates.
+// - After each commit the Compactor checks whether the chunk has collected enough slices to be
+//   compacted.
 
+use super::compact::Compactor;
 use super::reader::DataReader;
 use crate::chuck::writer::DataUploader;
 use crate::chuck::{BlockStore, SliceDesc};
@@ -547,6 +550,7 @@ struct Shared<B, M> {
     flush_notify: Notify,
     backend: Arc<Backend<B, M>>,
     reader: Arc<DataReader<B, M>>,
+    compactor: Arc<Compactor<B, M>>,
 }
 
 impl<B, M> Shared<B, M>
@@ -559,6 +563,7 @@ where
         config: Arc<WriteConfig>,
         backend: Arc<Backend<B, M>>,
         reader: Arc<DataReader<B, M>>,
+        compactor: Arc<Compactor<B, M>>,
         buffer_usage: Arc<AtomicU64>,
     ) -> Self {
         Self {
@@ -574,6 +579,7 @@ where
             flush_notify: Notify::new(),
             backend,
             reader,
+            compactor,
         }
     }
 }
@@ -664,9 +670,17 @@ where
         config: Arc<WriteConfig>,
         backend: Arc<Backend<B, M>>,
         reader: Arc<DataReader<B, M>>,
+        compactor: Arc<Compactor<B, M>>,
         buffer_usage: Arc<AtomicU64>,
     ) -> Self {
-        let shared = Arc::new(Shared::new(inode, config, backend, reader, buffer_usage));
+        let shared = Arc::new(Shared::new(
+            inode,
+            config,
+            backend,
+            reader,
+            compactor,
+            buffer_usage,
+        ));
         let flush_shared = Arc::downgrade(&shared);
         tokio::spawn(async move { Self::auto_flush(flush_shared).await });
         Self { shared }
@@ -1061,6 +1075,7 @@ where
                                 len = desc.length
                             ))
                             .await;
+                        shared.compactor.check(desc.chunk_id);
                         should_pop = true;
                     }
                 } else {
@@ -1187,6 +1202,7 @@ pub(crate) struct DataWriter<B, M> {
     config: Arc<WriteConfig>,
     backend: Arc<Backend<B, M>>,
     reader: Arc<DataReader<B, M>>,
+    compactor: Arc<Compactor<B, M>>,
     files: DashMap<u64, Arc<FileWriter<B, M>>>,
     buffer_usage: Arc<AtomicU64>,
 }
@@ -1200,11 +1216,13 @@ where
         config: Arc<WriteConfig>,
         backend: Arc<Backend<B, M>>,
         reader: Arc<DataReader<B, M>>,
+        compactor: Arc<Compactor<B, M>>,
     ) -> Self {
         Self {
             config,
             backend,
             reader,
+            compactor,
             files: DashMap::new(),
             buffer_usage: Arc::new(AtomicU64::new(0)),
         }
@@ -1219,6 +1237,7 @@ where
                     self.config.clone(),
                     self.backend.clone(),
                     self.reader.clone(),
+                    self.compactor.clone(),
                     self.buffer_usage.clone(),
                 ))
             })
@@ -1250,6 +1269,11 @@ where
         }
     }
 
+    /// Flushes the pending writes of every open file.
+    pub(crate) async fn flush_all(&self) {
+        self.flush_once().await;
+    }
+
     pub(crate) async fn clear(&self, ino: u64) {
         let writer = self.files.get(&ino).map(|entry| entry.value().clone());
         if let Some(writer) = writer {
@@ -1434,6 +1458,7 @@ mod tests {
             test_config(layout),
             backend.clone(),
             reader,
+            Arc::new(Compactor::new(test_config(layout), backend.clone())),
             Arc::new(AtomicU64::new(0)),
         );
 
@@ -1480,6 +1505,7 @@ mod tests {
             test_config(layout),
             backend.clone(),
             reader,
+            Arc::new(Compactor::new(test_config(layout), backend.clone())),
             Arc::new(AtomicU64::new(0)),
         );
 
@@ -1526,6 +1552,7 @@ mod tests {
             test_config(layout),
             backend.clone(),
             reader.clone(),
+            Arc::new(Compactor::new(test_config(layout), backend.clone())),
             Arc::new(AtomicU64::new(0)),
         );
 
@@ -1569,6 +1596,7 @@ mod tests {
             test_config(layout),
             backend.clone(),
             reader,
+            Arc::new(Compactor::new(test_config(layout), backend.clone())),
             Arc::new(AtomicU64::new(0)),
         ));
 
@@ -1625,7 +1653,13 @@ mod tests {
                 .page_size(4 * 1024)
                 .flush_all_interval(Duration::from_millis(50)),
         );
-        let writer_pool = Arc::new(DataWriter::new(write_cfg, backend.clone(), reader));
+        let compactor = Arc::new(Compactor::new(write_cfg.clone(), backend.clone()));
+        let writer_pool = Arc::new(DataWriter::new(
+            write_cfg,
+            backend.clone(),
+            reader,
+            compactor,
+        ));
         writer_pool.start_flush_background();
 
         let ino = meta
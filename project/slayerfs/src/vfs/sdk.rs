@@ -17,6 +17,7 @@ use crate::meta::store::{
     DirEntry, FileAttr, FileType, SetAttrFlags, SetAttrRequest, StatFsSnapshot,
 };
 use crate::meta::stores::DatabaseMetaStore;
+use crate::vfs::io::warmup::WarmupReport;
 use std::future::Future;
 use std::io;
 use std::path::Path;
@@ -209,6 +210,28 @@ impl<S: BlockStore + Send + Sync + 'static, M: MetaStore + 'static> VfsClient<S,
         self.fs.lstat(link_path).await.map(|fi| fi.attr().clone())
     }
 
+    /// Clone a file or directory tree without copying data; the clone shares
+    /// the source's slices. See [`FileSystem::clone_entry`].
+    pub async fn clone(&self, src: &str, dst: &str, preserve: bool) -> io::Result<FileAttr> {
+        self.fs.clone_entry(src, dst, preserve).await
+    }
+
+    /// Load the data of a file or directory tree into the block cache before
+    /// a job reads it. See [`FileSystem::warmup`].
+    pub async fn warmup(&self, path: &str) -> io::Result<WarmupReport> {
+        self.fs.warmup(path).await
+    }
+
+    /// Get an extended attribute, `None` if it is not set.
+    pub async fn get_xattr(&self, path: &str, name: &str) -> io::Result<Option<Vec<u8>>> {
+        self.fs.get_xattr(path, name).await
+    }
+
+    /// Set an extended attribute.
+    pub async fn set_xattr(&self, path: &str, name: &str, value: &[u8]) -> io::Result<()> {
+        self.fs.set_xattr(path, name, value).await
+    }
+
     /// Read the target of a symbolic link.
     pub async fn readlink(&self, path: &str) -> io::Result<String> {
         self.fs.readlink(path).await
@@ -420,4 +443,47 @@ mod tests {
         }
         cli.rmdir("/links").await.unwrap();
     }
+
+    #[tokio::test]
+    async fn test_sdk_local_clone() {
+        let layout = ChunkLayout::default();
+        let tmp = tempdir().unwrap();
+        let config = FileSystemConfig::default().with_caller(CallerIdentity::root());
+        let cli = LocalClient::new_local_with_config(tmp.path(), layout, config)
+            .await
+            .expect("init LocalClient");
+
+        cli.mkdir_p("/src/sub").await.unwrap();
+        cli.create_file("/src/sub/data.txt", false).await.unwrap();
+        cli.write_at("/src/sub/data.txt", 0, b"original")
+            .await
+            .unwrap();
+
+        cli.clone("/src", "/snap", false).await.unwrap();
+        cli.clone("/src/sub/data.txt", "/copy.txt", false)
+            .await
+            .unwrap();
+        assert_eq!(
+            cli.read_at("/snap/sub/data.txt", 0, 8).await.unwrap(),
+            b"original"
+        );
+        assert_eq!(cli.read_at("/copy.txt", 0, 8).await.unwrap(), b"original");
+
+        // Writes to a clone must not show through in its source.
+        cli.write_at("/snap/sub/data.txt", 0, b"modified")
+            .await
+            .unwrap();
+        assert_eq!(
+            cli.read_at("/src/sub/data.txt", 0, 8).await.unwrap(),
+            b"original"
+        );
+
+        let err = cli.clone("/src", "/snap", false).await.unwrap_err();
+        assert_eq!(err.kind(), io::ErrorKind::AlreadyExists);
+        let err = cli
+            .clone("/src", "/src/sub/inner", false)
+            .await
+            .unwrap_err();
+        assert_eq!(err.kind(), io::ErrorKind::InvalidInput);
+    }
 }